@@ -0,0 +1,245 @@
+use crate::heavy_hitters::HeavyHitters;
+use crate::scenarios::Operation;
+use crate::workload::Workload;
+use wasm_bindgen::prelude::*;
+
+/// One of the most accessed keys, as returned by `hot_keys`.
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct HotKey {
+    pub key: String,
+    /// Estimated reads plus writes; never below the true count
+    pub accesses: u64,
+    /// Estimated reads and writes, each 0 if the key fell out of that
+    /// sketch
+    pub reads: u64,
+    pub writes: u64,
+    /// Fraction of all recorded accesses that went to this key
+    pub share: f64,
+    /// Most `accesses` can exceed the true count by
+    pub error: u64,
+}
+
+/// Read/write mix and skew of the accesses an [`AccessProfile`] recorded.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct AccessReport {
+    pub reads: u64,
+    pub writes: u64,
+    /// reads / (reads + writes), or 0 before any access
+    pub read_fraction: f64,
+    /// Hot keys the report covers
+    pub k: u32,
+    /// Fraction of all accesses that certainly went to the `k` hottest
+    /// keys: their counts less each count's possible overestimate
+    pub top_k_share: f64,
+    /// The same lower bound for reads alone, roughly what a cache of `k`
+    /// entries could serve
+    pub top_k_read_share: f64,
+}
+
+#[wasm_bindgen]
+impl AccessReport {
+    /// Reads per write; infinite for a read-only stream.
+    pub fn read_write_ratio(&self) -> f64 {
+        if self.writes == 0 {
+            if self.reads == 0 {
+                0.0
+            } else {
+                f64::INFINITY
+            }
+        } else {
+            self.reads as f64 / self.writes as f64
+        }
+    }
+}
+
+/// Per-key read and write counts of a workload, in fixed memory, to show
+/// how skewed it is.
+///
+/// Accesses go into three [`HeavyHitters`] sketches (all accesses, reads,
+/// writes) of `capacity` counters each, so memory stays bounded however
+/// many distinct keys pass through and every count is an upper bound.
+/// When a few keys take most of the reads (`top_k_read_share` near 1) a
+/// small cache in front of the structure, or a structure that moves hot
+/// keys closer to the root, pays off; a flat profile gains little from
+/// either.
+///
+/// Enable it on a store with `DynamicStore.track_access`, or profile a
+/// recorded workload:
+///
+/// ```javascript
+/// const profile = AccessProfile.from_workload(recorder.workload(), 256);
+/// for (const hot of profile.hot_keys(10)) console.log(hot.key, hot.reads, hot.writes);
+/// const report = profile.report(10);
+/// console.log(report.read_write_ratio(), report.top_k_read_share);
+/// ```
+#[wasm_bindgen]
+pub struct AccessProfile {
+    all: HeavyHitters,
+    reads: HeavyHitters,
+    writes: HeavyHitters,
+}
+
+impl AccessProfile {
+    pub fn from_operations(operations: &[Operation], capacity: u32) -> AccessProfile {
+        let mut profile = AccessProfile::new(capacity);
+        for op in operations {
+            match op {
+                Operation::Get(key) => profile.record_read(key),
+                Operation::Insert(key, _) | Operation::Delete(key) => profile.record_write(key),
+            }
+        }
+        profile
+    }
+
+    fn share(&self, count: u64, total: u64) -> f64 {
+        if total == 0 {
+            0.0
+        } else {
+            (count as f64 / total as f64).min(1.0)
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl AccessProfile {
+    /// An empty profile tracking at most `capacity` keys per sketch (at
+    /// least 1). Counts are exact while fewer distinct keys are seen.
+    #[wasm_bindgen(constructor)]
+    pub fn new(capacity: u32) -> AccessProfile {
+        AccessProfile {
+            all: HeavyHitters::new(capacity),
+            reads: HeavyHitters::new(capacity),
+            writes: HeavyHitters::new(capacity),
+        }
+    }
+
+    /// Profile every operation of `workload`: gets are reads, inserts and
+    /// deletes writes.
+    pub fn from_workload(workload: &Workload, capacity: u32) -> AccessProfile {
+        Self::from_operations(workload.operations(), capacity)
+    }
+
+    pub fn record_read(&mut self, key: &str) {
+        self.all.offer(key);
+        self.reads.offer(key);
+    }
+
+    pub fn record_write(&mut self, key: &str) {
+        self.all.offer(key);
+        self.writes.offer(key);
+    }
+
+    /// The `k` most accessed keys, hottest first.
+    pub fn hot_keys(&self, k: u32) -> Vec<HotKey> {
+        let total = self.all.stream_length();
+        self.all
+            .top(k)
+            .into_iter()
+            .map(|hitter| HotKey {
+                reads: self.reads.estimate(&hitter.key),
+                writes: self.writes.estimate(&hitter.key),
+                share: self.share(hitter.count, total),
+                accesses: hitter.count,
+                error: hitter.error,
+                key: hitter.key,
+            })
+            .collect()
+    }
+
+    /// Read/write mix, and how much of the traffic the `k` hottest keys
+    /// take.
+    pub fn report(&self, k: u32) -> AccessReport {
+        let reads = self.reads.stream_length();
+        let writes = self.writes.stream_length();
+        let top_sum =
+            |sketch: &HeavyHitters| sketch.top(k).iter().map(|h| h.count - h.error).sum::<u64>();
+        AccessReport {
+            reads,
+            writes,
+            read_fraction: self.share(reads, reads + writes),
+            k,
+            top_k_share: self.share(top_sum(&self.all), reads + writes),
+            top_k_read_share: self.share(top_sum(&self.reads), reads),
+        }
+    }
+
+    /// Accesses recorded so far.
+    pub fn accesses(&self) -> u64 {
+        self.all.stream_length()
+    }
+
+    pub fn clear(&mut self) {
+        *self = AccessProfile::new(self.all.capacity());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kv_store::DynamicStore;
+    use crate::rng::DefaultRng;
+    use crate::scenarios::ZipfSampler;
+    use rand::Rng;
+
+    #[test]
+    fn test_skewed_reads_are_reported() {
+        let mut rng = DefaultRng::seed_from(5);
+        let zipf = ZipfSampler::new(10_000, 1.2);
+        let mut operations = Vec::new();
+        for i in 0..20_000 {
+            let key = format!("k{}", zipf.sample(&mut rng));
+            if rng.gen_bool(0.9) {
+                operations.push(Operation::Get(key));
+            } else {
+                operations.push(Operation::Insert(key, i));
+            }
+        }
+        let profile = AccessProfile::from_operations(&operations, 200);
+        let hot = profile.hot_keys(5);
+        assert_eq!(hot[0].key, "k0");
+        assert!(hot.windows(2).all(|w| w[0].accesses >= w[1].accesses));
+        assert!(hot[0].reads > 5 * hot[0].writes, "{:?}", hot[0]);
+
+        let report = profile.report(10);
+        assert_eq!(report.reads + report.writes, 20_000);
+        assert!((report.read_fraction - 0.9).abs() < 0.02);
+        assert!(report.read_write_ratio() > 8.0);
+        assert!(report.top_k_read_share > 0.5, "{:?}", report);
+
+        let mut uniform = AccessProfile::new(200);
+        for _ in 0..20_000 {
+            uniform.record_read(&format!("k{}", rng.gen_range(0..10_000)));
+        }
+        assert!(uniform.report(10).top_k_read_share < 0.05);
+        assert_eq!(uniform.report(10).read_write_ratio(), f64::INFINITY);
+    }
+
+    #[test]
+    fn test_store_tracks_access() {
+        let mut store = DynamicStore::try_new("rbtree", 0).unwrap();
+        store.insert("a".to_string(), 1);
+        assert!(store.access_profile().is_none());
+        store.track_access(16);
+        store.insert("a".to_string(), 2);
+        for _ in 0..3 {
+            store.get("a");
+        }
+        store.get("b");
+        store.delete("b");
+        let hot = store.access_profile().unwrap().hot_keys(2);
+        assert_eq!(
+            (hot[0].key.as_str(), hot[0].reads, hot[0].writes),
+            ("a", 3, 1)
+        );
+        assert_eq!(
+            (hot[1].key.as_str(), hot[1].reads, hot[1].writes),
+            ("b", 1, 1)
+        );
+        assert_eq!(hot[0].share, 4.0 / 6.0);
+        store.stop_tracking_access();
+        store.get("a");
+        assert!(store.access_profile().is_none());
+    }
+}
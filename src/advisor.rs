@@ -0,0 +1,317 @@
+use crate::access::AccessProfile;
+use crate::kv_store::{new_store, ORDERED_KINDS, STORE_KINDS};
+use crate::scenarios::Operation;
+use crate::workload::Workload;
+use wasm_bindgen::prelude::*;
+
+/// Replays per candidate; the fastest is kept, to damp timer noise.
+const ROUNDS: usize = 3;
+
+/// Candidates this close to the fastest run time count as tied with it, and
+/// are ranked by memory instead.
+const TIE_THRESHOLD: f64 = 0.05;
+
+/// Keys the hot-key share is measured over.
+const HOT_KEYS: u32 = 10;
+
+/// How one structure did on the replayed workload.
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Candidate {
+    pub structure: String,
+    /// Fastest replay of the workload, in milliseconds
+    pub run_ms: f64,
+    pub ops_per_ms: f64,
+    /// Per-operation p99 latency of that replay, in milliseconds
+    pub p99_ms: f64,
+    /// Reserved bytes once the workload has run
+    pub memory_bytes: u32,
+    /// run_ms relative to the fastest candidate's (1.0 for the fastest)
+    pub slowdown: f64,
+    /// Within 5% of the fastest run time, so ranked by memory among them
+    pub tied_with_best: bool,
+}
+
+/// Structures ranked for a recorded workload, with the numbers and the
+/// workload traits the ranking rests on.
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Debug)]
+pub struct Recommendation {
+    pub operations: u32,
+    pub distinct_keys: u32,
+    /// Fractions of the operations that were gets, inserts and deletes
+    pub read_fraction: f64,
+    pub insert_fraction: f64,
+    pub delete_fraction: f64,
+    /// Lower bound on the fraction of accesses that went to the 10 hottest
+    /// keys
+    pub hot_key_share: f64,
+    candidates: Vec<Candidate>,
+    notes: Vec<String>,
+}
+
+impl Recommendation {
+    pub fn candidates(&self) -> &[Candidate] {
+        &self.candidates
+    }
+
+    pub fn notes(&self) -> &[String] {
+        &self.notes
+    }
+}
+
+#[wasm_bindgen]
+impl Recommendation {
+    /// The top-ranked structure.
+    pub fn best(&self) -> String {
+        self.candidates[0].structure.clone()
+    }
+
+    /// Every candidate, best first.
+    pub fn ranking(&self) -> Vec<Candidate> {
+        self.candidates.clone()
+    }
+
+    /// Advice drawn from the workload's shape rather than the timings.
+    pub fn advice(&self) -> Vec<String> {
+        self.notes.clone()
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut out = format!(
+            "## Recommendation: {}\n\n{} operations on {} keys: {:.0}% gets, {:.0}% inserts, {:.0}% deletes; the {} hottest keys take {:.0}% of accesses.\n\n",
+            self.best(),
+            self.operations,
+            self.distinct_keys,
+            self.read_fraction * 100.0,
+            self.insert_fraction * 100.0,
+            self.delete_fraction * 100.0,
+            HOT_KEYS,
+            self.hot_key_share * 100.0
+        );
+        out.push_str(
+            "| Rank | Structure | Run (ms) | Ops/ms | p99 (ms) | Memory (bytes) | Slowdown |\n",
+        );
+        out.push_str("|---:|---|---:|---:|---:|---:|---:|\n");
+        for (rank, c) in self.candidates.iter().enumerate() {
+            out.push_str(&format!(
+                "| {} | {} | {:.2} | {:.1} | {:.4} | {} | {:.2}x{} |\n",
+                rank + 1,
+                c.structure,
+                c.run_ms,
+                c.ops_per_ms,
+                c.p99_ms,
+                c.memory_bytes,
+                c.slowdown,
+                if c.tied_with_best { " (tie)" } else { "" }
+            ));
+        }
+        if !self.notes.is_empty() {
+            out.push('\n');
+            for note in &self.notes {
+                out.push_str(&format!("- {}\n", note));
+            }
+        }
+        out
+    }
+}
+
+/// Replay `workload` against each of `structures` and rank them: by run
+/// time, except that candidates within 5% of the fastest are ranked by
+/// memory. Fails on an empty workload or an unknown structure.
+pub fn try_recommend(workload: &Workload, structures: &[&str]) -> Result<Recommendation, String> {
+    if workload.is_empty() {
+        return Err("the workload has no operations to replay".to_string());
+    }
+    if structures.is_empty() {
+        return Err("no candidate structures given".to_string());
+    }
+    let mut candidates = Vec::with_capacity(structures.len());
+    for &structure in structures {
+        let mut best: Option<Candidate> = None;
+        for _ in 0..ROUNDS {
+            let mut store = new_store(structure, workload.distinct_keys())
+                .ok_or_else(|| format!("unknown structure '{}'", structure))?;
+            let result = workload.replay_on(store.as_mut());
+            if best.as_ref().is_none_or(|b| result.run_ms < b.run_ms) {
+                best = Some(Candidate {
+                    structure: structure.to_string(),
+                    run_ms: result.run_ms,
+                    ops_per_ms: result.ops_per_ms(),
+                    p99_ms: result.latency_percentile(0.99).unwrap_or(0.0),
+                    memory_bytes: store.memory_report().reserved_bytes as u32,
+                    slowdown: 1.0,
+                    tied_with_best: false,
+                });
+            }
+        }
+        candidates.extend(best);
+    }
+    rank(&mut candidates);
+
+    let (mut reads, mut inserts, mut deletes) = (0, 0, 0);
+    for op in workload.operations() {
+        match op {
+            Operation::Get(_) => reads += 1,
+            Operation::Insert(..) => inserts += 1,
+            Operation::Delete(_) => deletes += 1,
+        }
+    }
+    let total = workload.len() as f64;
+    let profile = AccessProfile::from_workload(workload, 256);
+    let mut recommendation = Recommendation {
+        operations: workload.len() as u32,
+        distinct_keys: workload.distinct_keys() as u32,
+        read_fraction: reads as f64 / total,
+        insert_fraction: inserts as f64 / total,
+        delete_fraction: deletes as f64 / total,
+        hot_key_share: profile.report(HOT_KEYS).top_k_share,
+        candidates,
+        notes: Vec::new(),
+    };
+    recommendation.notes = advise(&recommendation);
+    Ok(recommendation)
+}
+
+fn rank(candidates: &mut [Candidate]) {
+    let fastest = candidates
+        .iter()
+        .map(|c| c.run_ms)
+        .fold(f64::INFINITY, f64::min);
+    for c in candidates.iter_mut() {
+        c.slowdown = if fastest > 0.0 {
+            c.run_ms / fastest
+        } else {
+            1.0
+        };
+        c.tied_with_best = c.run_ms <= fastest * (1.0 + TIE_THRESHOLD);
+    }
+    candidates.sort_by(|a, b| {
+        b.tied_with_best
+            .cmp(&a.tied_with_best)
+            .then_with(|| {
+                if a.tied_with_best {
+                    a.memory_bytes.cmp(&b.memory_bytes)
+                } else {
+                    a.run_ms.total_cmp(&b.run_ms)
+                }
+            })
+            .then_with(|| a.structure.cmp(&b.structure))
+    });
+}
+
+/// Notes on what the workload's shape suggests, whatever won the timings.
+fn advise(r: &Recommendation) -> Vec<String> {
+    let mut notes = Vec::new();
+    if r.read_fraction >= 0.8 && r.hot_key_share >= 0.5 {
+        notes.push(format!(
+            "Reads dominate and the {} hottest keys take {:.0}% of accesses: a small cache in front (PolicyCache) would serve most of them.",
+            HOT_KEYS,
+            r.hot_key_share * 100.0
+        ));
+    }
+    if r.delete_fraction >= 0.2 {
+        notes.push(
+            "Deletes are frequent: open addressing accumulates tombstones that slow probes until shrink_to_fit, so chaining or a tree degrades more gracefully."
+                .to_string(),
+        );
+    }
+    if r.insert_fraction >= 0.8 {
+        notes.push(
+            "The workload is mostly inserts: sizing the structure for the final key count up front (e.g. HashMapBuilder.bucket_count) avoids rehashing."
+                .to_string(),
+        );
+    }
+    if !ORDERED_KINDS.contains(&r.candidates[0].structure.as_str()) {
+        if let Some(c) = r
+            .candidates
+            .iter()
+            .find(|c| ORDERED_KINDS.contains(&c.structure.as_str()))
+        {
+            notes.push(format!(
+                "If you also need keys in order or prefix queries, {} is the fastest ordered structure at {:.2}x the best run time.",
+                c.structure, c.slowdown
+            ));
+        }
+    }
+    notes
+}
+
+/// Replay a recorded workload against every structure in
+/// [`STORE_KINDS`](crate::kv_store::STORE_KINDS) and rank them, with the
+/// supporting numbers and advice from the workload's op mix and skew.
+/// Throws on an empty workload.
+///
+/// # Example
+/// ```javascript
+/// const advice = recommend_structure(recorder.workload());
+/// console.log(advice.best());
+/// console.log(advice.to_markdown());
+/// ```
+#[wasm_bindgen]
+pub fn recommend_structure(workload: &Workload) -> Result<Recommendation, JsValue> {
+    try_recommend(workload, &STORE_KINDS).map_err(|e| JsValue::from_str(&e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scenarios::Scenario;
+
+    fn candidate(structure: &str, run_ms: f64, memory_bytes: u32) -> Candidate {
+        Candidate {
+            structure: structure.to_string(),
+            run_ms,
+            ops_per_ms: 0.0,
+            p99_ms: 0.0,
+            memory_bytes,
+            slowdown: 1.0,
+            tied_with_best: false,
+        }
+    }
+
+    #[test]
+    fn test_ties_are_ranked_by_memory() {
+        let mut candidates = vec![
+            candidate("bst", 30.0, 100),
+            candidate("hashmap", 10.0, 5_000),
+            candidate("rbtree", 10.3, 2_000),
+            candidate("trie", 12.0, 1_000),
+        ];
+        rank(&mut candidates);
+        let order: Vec<&str> = candidates.iter().map(|c| c.structure.as_str()).collect();
+        assert_eq!(order, ["rbtree", "hashmap", "trie", "bst"]);
+        assert!(candidates[1].tied_with_best && !candidates[2].tied_with_best);
+        assert_eq!(candidates[3].slowdown, 3.0);
+    }
+
+    #[test]
+    fn test_recommends_from_recorded_workload() {
+        let scenario = Scenario::by_name("session-cache-churn").unwrap();
+        let workload = Workload::from_operations(scenario.generate_operations());
+        let recommendation = try_recommend(&workload, &STORE_KINDS).unwrap();
+        assert_eq!(recommendation.candidates().len(), STORE_KINDS.len());
+        assert_eq!(
+            recommendation.best(),
+            recommendation.candidates()[0].structure
+        );
+        let fractions = recommendation.read_fraction
+            + recommendation.insert_fraction
+            + recommendation.delete_fraction;
+        assert!((fractions - 1.0).abs() < 1e-9);
+        assert!(recommendation
+            .candidates()
+            .iter()
+            .all(|c| c.slowdown >= 1.0 && c.memory_bytes > 0));
+        assert!(recommendation.to_markdown().contains("| 1 | "));
+
+        assert!(try_recommend(&Workload::default(), &STORE_KINDS).is_err());
+        assert!(try_recommend(&workload, &["btree"]).is_err());
+
+        let reads = vec![Operation::Get("hot".to_string()); 100];
+        let recommendation =
+            try_recommend(&Workload::from_operations(reads), &["hashmap"]).unwrap();
+        assert_eq!(recommendation.hot_key_share, 1.0);
+        assert!(recommendation.notes()[0].contains("cache"));
+    }
+}
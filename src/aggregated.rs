@@ -0,0 +1,198 @@
+use crate::kv_store::{new_store, KvStore};
+use std::collections::BTreeMap;
+use wasm_bindgen::prelude::*;
+
+/// Any structure with running aggregates of its values, kept up to date on
+/// every insert and delete so that reading them is O(1) however large the
+/// store grows.
+///
+/// Count and sum are adjusted by the old and new value of each write; min
+/// and max come from a multiset of values, re-read after each write. To
+/// know the old value a write first looks the key up, which the wrapped
+/// structure counts among its searches.
+///
+/// # Example
+/// ```javascript
+/// const prices = new AggregatedMap("rbtree");
+/// prices.insert("apple", 120);
+/// prices.insert("pear", 80);
+/// prices.delete("apple");
+/// prices.mean(); // 80
+/// ```
+#[wasm_bindgen]
+pub struct AggregatedMap {
+    store: Box<dyn KvStore>,
+    sum: u64,
+    /// How many keys hold each value
+    values: BTreeMap<u32, u32>,
+    min: Option<u32>,
+    max: Option<u32>,
+}
+
+impl AggregatedMap {
+    /// Wrap `store`, aggregating the entries it already holds.
+    pub fn wrap(store: Box<dyn KvStore>) -> AggregatedMap {
+        let mut map = AggregatedMap {
+            store,
+            sum: 0,
+            values: BTreeMap::new(),
+            min: None,
+            max: None,
+        };
+        for (_, value) in map.store.kv_entries() {
+            map.add(value);
+        }
+        map.refresh_bounds();
+        map
+    }
+
+    pub fn try_new(kind: &str, capacity_hint: usize) -> Result<AggregatedMap, String> {
+        let store = new_store(kind, capacity_hint)
+            .ok_or_else(|| format!("unknown structure '{}'", kind))?;
+        Ok(Self::wrap(store))
+    }
+
+    pub fn inner(&self) -> &dyn KvStore {
+        self.store.as_ref()
+    }
+
+    fn add(&mut self, value: u32) {
+        self.sum += value as u64;
+        *self.values.entry(value).or_insert(0) += 1;
+    }
+
+    fn remove(&mut self, value: u32) {
+        self.sum -= value as u64;
+        if let Some(count) = self.values.get_mut(&value) {
+            *count -= 1;
+            if *count == 0 {
+                self.values.remove(&value);
+            }
+        }
+    }
+
+    fn refresh_bounds(&mut self) {
+        self.min = self.values.keys().next().copied();
+        self.max = self.values.keys().next_back().copied();
+    }
+}
+
+#[wasm_bindgen]
+impl AggregatedMap {
+    /// An empty structure of `kind` (any name `new_store` accepts) with
+    /// aggregates.
+    #[wasm_bindgen(constructor)]
+    pub fn new(kind: &str) -> Result<AggregatedMap, JsValue> {
+        Self::try_new(kind, 0).map_err(|e| JsValue::from_str(&e))
+    }
+
+    pub fn insert(&mut self, key: String, value: u32) {
+        if let Some(old) = self.store.kv_get(&key) {
+            self.remove(old);
+        }
+        self.store.kv_insert(key, value);
+        self.add(value);
+        self.refresh_bounds();
+    }
+
+    pub fn get(&mut self, key: &str) -> Option<u32> {
+        self.store.kv_get(key)
+    }
+
+    pub fn delete(&mut self, key: &str) -> bool {
+        let Some(old) = self.store.kv_get(key) else {
+            return false;
+        };
+        self.store.kv_delete(key);
+        self.remove(old);
+        self.refresh_bounds();
+        true
+    }
+
+    /// Structure kind being wrapped.
+    pub fn kind(&self) -> String {
+        self.store.kind().to_string()
+    }
+
+    /// Number of entries.
+    pub fn count(&self) -> usize {
+        self.store.kv_len()
+    }
+
+    pub fn len(&self) -> usize {
+        self.store.kv_len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.store.kv_len() == 0
+    }
+
+    /// Sum of all values; exact as a JS number up to 2^53.
+    pub fn sum(&self) -> f64 {
+        self.sum as f64
+    }
+
+    pub fn min(&self) -> Option<u32> {
+        self.min
+    }
+
+    pub fn max(&self) -> Option<u32> {
+        self.max
+    }
+
+    /// Mean value, or `None` when empty.
+    pub fn mean(&self) -> Option<f64> {
+        let count = self.count();
+        (count > 0).then(|| self.sum as f64 / count as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::DefaultRng;
+    use rand::Rng;
+
+    #[test]
+    fn test_aggregates_match_recomputation() {
+        for kind in ["hashmap", "bst", "skiplist"] {
+            let mut map = AggregatedMap::try_new(kind, 0).unwrap();
+            let mut rng = DefaultRng::seed_from(7);
+            for step in 0..2_000 {
+                let key = format!("k{}", rng.gen_range(0..200));
+                if rng.gen_bool(0.3) {
+                    map.delete(&key);
+                } else {
+                    map.insert(key, rng.gen_range(0..1_000));
+                }
+                if step % 100 == 0 {
+                    let values: Vec<u32> = map
+                        .inner()
+                        .kv_entries()
+                        .into_iter()
+                        .map(|(_, v)| v)
+                        .collect();
+                    let sum: u64 = values.iter().map(|&v| v as u64).sum();
+                    assert_eq!(map.count(), values.len(), "{}", kind);
+                    assert_eq!(map.sum(), sum as f64, "{}", kind);
+                    assert_eq!(map.min(), values.iter().min().copied(), "{}", kind);
+                    assert_eq!(map.max(), values.iter().max().copied(), "{}", kind);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_wrap_existing_and_empty() {
+        let mut store = new_store("hashmap", 0).unwrap();
+        store.kv_insert("a".to_string(), 10);
+        store.kv_insert("b".to_string(), 30);
+        let mut map = AggregatedMap::wrap(store);
+        assert_eq!(
+            (map.min(), map.max(), map.mean()),
+            (Some(10), Some(30), Some(20.0))
+        );
+        assert!(map.delete("a") && map.delete("b") && !map.delete("b"));
+        assert_eq!((map.min(), map.mean()), (None, None));
+    }
+}
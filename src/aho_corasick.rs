@@ -0,0 +1,292 @@
+use crate::memory::MemoryReport;
+use js_sys::Function;
+use std::collections::{BTreeMap, VecDeque};
+use wasm_bindgen::prelude::*;
+
+struct State {
+    transitions: BTreeMap<char, usize>,
+    /// Longest proper suffix of this state's string that is also a state
+    fail: usize,
+    /// Patterns ending here, including those reached through `fail`
+    outputs: Vec<u32>,
+}
+
+impl State {
+    fn new() -> State {
+        State {
+            transitions: BTreeMap::new(),
+            fail: 0,
+            outputs: Vec::new(),
+        }
+    }
+}
+
+/// Aho–Corasick automaton: finds every occurrence of many patterns in one
+/// pass over the text.
+///
+/// The patterns form a trie whose nodes gain failure links pointing at the
+/// longest suffix that is also a trie node. Scanning follows goto edges and
+/// falls back along failure links on a mismatch, so the work is linear in
+/// the text plus the number of matches, however many patterns there are.
+/// Overlapping matches are all reported.
+///
+/// Positions count chars (Unicode scalar values), not bytes.
+///
+/// # Example
+/// ```javascript
+/// const ac = new AhoCorasick(["he", "she", "his", "hers"]);
+/// ac.find_all("ushers"); // she@1..4, he@2..4, hers@2..6
+/// ac.find_each(bigText, (pattern, start, end) => highlight(start, end));
+/// ```
+#[wasm_bindgen]
+pub struct AhoCorasick {
+    states: Vec<State>,
+    patterns: Vec<String>,
+    metrics: AhoCorasickMetrics,
+}
+
+/// Size of the automaton and the work done by searches
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AhoCorasickMetrics {
+    pub pattern_count: u32,
+    pub state_count: u32,
+    /// Goto edges in the underlying trie
+    pub goto_transitions: u32,
+    pub total_searches: u32,
+    pub chars_scanned: u32,
+    /// Goto edges followed while scanning
+    pub goto_steps: u32,
+    /// Failure links followed while scanning
+    pub failure_steps: u32,
+    pub matches_reported: u32,
+}
+
+/// One occurrence of a pattern, as `[start, end)` char offsets.
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct PatternMatch {
+    /// Index of the pattern in the list the automaton was built from
+    pub pattern_index: u32,
+    pub pattern: String,
+    pub start: u32,
+    pub end: u32,
+}
+
+impl AhoCorasick {
+    pub fn from_patterns<S: AsRef<str>>(patterns: &[S]) -> AhoCorasick {
+        let mut states = vec![State::new()];
+        let mut lengths = Vec::with_capacity(patterns.len());
+        for (index, pattern) in patterns.iter().enumerate() {
+            let pattern = pattern.as_ref();
+            lengths.push(pattern.chars().count());
+            // The empty pattern would match everywhere; skip it
+            if pattern.is_empty() {
+                continue;
+            }
+            let mut state = 0;
+            for ch in pattern.chars() {
+                state = match states[state].transitions.get(&ch) {
+                    Some(&next) => next,
+                    None => {
+                        states.push(State::new());
+                        let next = states.len() - 1;
+                        states[state].transitions.insert(ch, next);
+                        next
+                    }
+                };
+            }
+            states[state].outputs.push(index as u32);
+        }
+
+        // Breadth first, so every failure target is finished before use
+        let mut queue: VecDeque<usize> = states[0].transitions.values().copied().collect();
+        while let Some(state) = queue.pop_front() {
+            let edges: Vec<(char, usize)> = states[state]
+                .transitions
+                .iter()
+                .map(|(&ch, &next)| (ch, next))
+                .collect();
+            for (ch, next) in edges {
+                let mut fail = states[state].fail;
+                let target = loop {
+                    if let Some(&t) = states[fail].transitions.get(&ch) {
+                        break t;
+                    }
+                    if fail == 0 {
+                        break 0;
+                    }
+                    fail = states[fail].fail;
+                };
+                states[next].fail = target;
+                let inherited = states[target].outputs.clone();
+                states[next].outputs.extend(inherited);
+                queue.push_back(next);
+            }
+        }
+
+        let goto_transitions = states.iter().map(|s| s.transitions.len() as u32).sum();
+        AhoCorasick {
+            metrics: AhoCorasickMetrics {
+                pattern_count: patterns.len() as u32,
+                state_count: states.len() as u32,
+                goto_transitions,
+                ..AhoCorasickMetrics::default()
+            },
+            states,
+            patterns: patterns.iter().map(|p| p.as_ref().to_string()).collect(),
+        }
+    }
+
+    /// Scan `text`, handing each match to `on_match` as it is found.
+    pub fn find_with(&mut self, text: &str, mut on_match: impl FnMut(PatternMatch)) {
+        self.metrics.total_searches += 1;
+        let mut state = 0;
+        for (position, ch) in text.chars().enumerate() {
+            self.metrics.chars_scanned += 1;
+            state = loop {
+                if let Some(&next) = self.states[state].transitions.get(&ch) {
+                    self.metrics.goto_steps += 1;
+                    break next;
+                }
+                if state == 0 {
+                    break 0;
+                }
+                self.metrics.failure_steps += 1;
+                state = self.states[state].fail;
+            };
+            for &index in &self.states[state].outputs {
+                self.metrics.matches_reported += 1;
+                let pattern = &self.patterns[index as usize];
+                let end = position as u32 + 1;
+                on_match(PatternMatch {
+                    pattern_index: index,
+                    pattern: pattern.clone(),
+                    start: end - pattern.chars().count() as u32,
+                    end,
+                });
+            }
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl AhoCorasick {
+    #[wasm_bindgen(constructor)]
+    pub fn new(patterns: Vec<String>) -> AhoCorasick {
+        Self::from_patterns(&patterns)
+    }
+
+    /// Every match, ordered by end position (longest pattern first on ties).
+    pub fn find_all(&mut self, text: &str) -> Vec<PatternMatch> {
+        let mut matches = Vec::new();
+        self.find_with(text, |m| matches.push(m));
+        matches
+    }
+
+    /// Call `callback(pattern_index, start, end)` for each match as the scan
+    /// reaches it, without collecting them. Exceptions thrown by the
+    /// callback are swallowed and the scan continues.
+    pub fn find_each(&mut self, text: &str, callback: &Function) {
+        self.find_with(text, |m| {
+            let _ = callback.call3(
+                &JsValue::UNDEFINED,
+                &m.pattern_index.into(),
+                &m.start.into(),
+                &m.end.into(),
+            );
+        });
+    }
+
+    /// Whether any pattern occurs in `text`.
+    pub fn is_match(&mut self, text: &str) -> bool {
+        let mut found = false;
+        self.find_with(text, |_| found = true);
+        found
+    }
+
+    pub fn pattern_count(&self) -> usize {
+        self.patterns.len()
+    }
+
+    pub fn get_metrics(&self) -> AhoCorasickMetrics {
+        self.metrics
+    }
+
+    /// Estimate heap usage: the state array, each state's transition map and
+    /// output list, and the pattern strings.
+    pub fn memory_report(&self) -> MemoryReport {
+        let mut report = MemoryReport::default();
+        report.add_vec(&self.states, self.states.len());
+        // BTreeMap nodes aren't exposed; count entries plus a pointer each
+        let edge = std::mem::size_of::<(char, usize)>() + std::mem::size_of::<usize>();
+        for state in &self.states {
+            report.add_exact(state.transitions.len() * edge);
+            report.add_vec(&state.outputs, state.outputs.len());
+        }
+        report.add_vec(&self.patterns, self.patterns.len());
+        for pattern in &self.patterns {
+            report.add_string(pattern, pattern.capacity());
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_finds_overlapping_matches() {
+        let mut ac = AhoCorasick::from_patterns(&["he", "she", "his", "hers"]);
+        let found: Vec<(String, u32, u32)> = ac
+            .find_all("ushers")
+            .into_iter()
+            .map(|m| (m.pattern, m.start, m.end))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("she".to_string(), 1, 4),
+                ("he".to_string(), 2, 4),
+                ("hers".to_string(), 2, 6)
+            ]
+        );
+        assert!(!ac.is_match("hxs"));
+
+        let metrics = ac.get_metrics();
+        assert_eq!(metrics.state_count, 10);
+        assert_eq!(metrics.goto_transitions, 9);
+        assert_eq!(metrics.matches_reported, 3);
+        assert!(metrics.failure_steps > 0);
+    }
+
+    #[test]
+    fn test_matches_naive_search() {
+        let patterns = ["a", "ab", "bab", "bc", "bca", "c", "caa", "", "ünï"];
+        let text = "abccab bcaab ünïcode abcaa";
+        let mut ac = AhoCorasick::from_patterns(&patterns);
+        let mut found: Vec<(u32, u32)> = ac
+            .find_all(text)
+            .into_iter()
+            .map(|m| (m.pattern_index, m.start))
+            .collect();
+        found.sort();
+
+        let chars: Vec<char> = text.chars().collect();
+        let mut expected = Vec::new();
+        for (index, pattern) in patterns.iter().enumerate() {
+            let p: Vec<char> = pattern.chars().collect();
+            if p.is_empty() {
+                continue;
+            }
+            for start in 0..=chars.len().saturating_sub(p.len()) {
+                if chars[start..start + p.len()] == p[..] {
+                    expected.push((index as u32, start as u32));
+                }
+            }
+        }
+        expected.sort();
+        assert_eq!(found, expected);
+    }
+}
@@ -0,0 +1,168 @@
+//! Plain-text drawings shared by the structures' `to_ascii` methods.
+
+/// Rows in an occupancy chart; larger tables are grouped into ranges.
+const MAX_ROWS: usize = 32;
+/// Characters in the longest bar.
+const BAR_WIDTH: usize = 40;
+
+enum Step<'a, N> {
+    Visit(&'a N, String, Side),
+    Line(String),
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Side {
+    Root,
+    Right,
+    Left,
+}
+
+/// Draw a binary tree rotated a quarter turn: the root at the left margin,
+/// right subtrees above their parent and left subtrees below, so reading
+/// top to bottom visits keys in descending order.
+///
+/// ```text
+///     /-- plum
+/// pear
+///     |   /-- fig
+///     \-- apple
+/// ```
+///
+/// Walks with an explicit stack, so degenerate trees don't overflow.
+pub(crate) fn binary_tree<'a, N>(
+    root: Option<&'a N>,
+    children: impl Fn(&'a N) -> (Option<&'a N>, Option<&'a N>),
+    label: impl Fn(&N) -> String,
+) -> String {
+    let Some(root) = root else {
+        return "(empty)".to_string();
+    };
+    let mut out = String::new();
+    let mut stack = vec![Step::Visit(root, String::new(), Side::Root)];
+    while let Some(step) = stack.pop() {
+        let (node, prefix, side) = match step {
+            Step::Line(line) => {
+                out.push_str(&line);
+                out.push('\n');
+                continue;
+            }
+            Step::Visit(node, prefix, side) => (node, prefix, side),
+        };
+        let (left, right) = children(node);
+        let connector = match side {
+            Side::Root => "",
+            Side::Right => "/-- ",
+            Side::Left => "\\-- ",
+        };
+        // A vertical bar continues toward the parent, which sits below a
+        // right child and above a left child
+        let indent = |toward_parent: bool| {
+            let bar = if toward_parent { "|   " } else { "    " };
+            if side == Side::Root {
+                "    ".to_string()
+            } else {
+                format!("{}{}", prefix, bar)
+            }
+        };
+        if let Some(left) = left {
+            stack.push(Step::Visit(left, indent(side == Side::Right), Side::Left));
+        }
+        stack.push(Step::Line(format!(
+            "{}{}{}",
+            prefix,
+            connector,
+            label(node)
+        )));
+        if let Some(right) = right {
+            stack.push(Step::Visit(right, indent(side == Side::Left), Side::Right));
+        }
+    }
+    out.pop();
+    out
+}
+
+/// Bar chart of entries per bucket (or live entries per slot), under a
+/// `summary` line. Tables with more than 32 buckets are grouped into equal
+/// ranges, each row showing the range's total.
+///
+/// ```text
+/// 5 entries in 4 buckets
+///   0 |##########                                 2
+///   1 |                                           0
+/// ```
+pub(crate) fn occupancy_bars(summary: &str, sizes: &[usize]) -> String {
+    let group = sizes.len().div_ceil(MAX_ROWS).max(1);
+    let rows: Vec<(String, usize)> = sizes
+        .chunks(group)
+        .enumerate()
+        .map(|(i, chunk)| {
+            let first = i * group;
+            let label = if group == 1 {
+                first.to_string()
+            } else {
+                format!("{}-{}", first, first + chunk.len() - 1)
+            };
+            (label, chunk.iter().sum())
+        })
+        .collect();
+    let label_width = rows.iter().map(|(label, _)| label.len()).max().unwrap_or(0);
+    let largest = rows.iter().map(|&(_, n)| n).max().unwrap_or(0).max(1);
+    let mut out = summary.to_string();
+    for (label, n) in rows {
+        let bar = (n * BAR_WIDTH).div_ceil(largest);
+        out.push_str(&format!(
+            "\n{:>width$} |{:<bar_width$} {}",
+            label,
+            "#".repeat(bar),
+            n,
+            width = label_width,
+            bar_width = BAR_WIDTH
+        ));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Node(&'static str, Option<Box<Node>>, Option<Box<Node>>);
+
+    fn children(node: &Node) -> (Option<&Node>, Option<&Node>) {
+        (node.1.as_deref(), node.2.as_deref())
+    }
+
+    #[test]
+    fn test_binary_tree() {
+        let leaf = |key| Some(Box::new(Node(key, None, None)));
+        let tree = Node(
+            "pear",
+            Some(Box::new(Node("apple", None, leaf("fig")))),
+            Some(Box::new(Node("plum", None, leaf("quince")))),
+        );
+        let drawn = binary_tree(Some(&tree), children, |n| n.0.to_string());
+        let expected = [
+            "        /-- quince",
+            "    /-- plum",
+            "pear",
+            "    |   /-- fig",
+            "    \\-- apple",
+        ];
+        assert_eq!(drawn, expected.join("\n"));
+        assert_eq!(binary_tree(None, children, |n| n.0.to_string()), "(empty)");
+    }
+
+    #[test]
+    fn test_occupancy_bars() {
+        let drawn = occupancy_bars("3 entries", &[2, 0, 1]);
+        let lines: Vec<&str> = drawn.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[1].starts_with("0 |") && lines[1].ends_with(" 2"));
+        assert_eq!(lines[1].matches('#').count(), BAR_WIDTH);
+        assert_eq!(lines[3].matches('#').count(), BAR_WIDTH / 2);
+
+        let drawn = occupancy_bars("256 entries", &[1; 256]);
+        assert_eq!(drawn.lines().count(), MAX_ROWS + 1);
+        assert!(drawn.contains("248-255 |"));
+    }
+}
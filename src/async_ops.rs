@@ -0,0 +1,451 @@
+use crate::cancel::CancellationToken;
+use crate::clock::now_ms;
+use crate::kv_store::DynamicStore;
+use crate::progress::ProgressHook;
+use js_sys::{Array, AsyncIterator, Function, Promise, Reflect};
+use std::future::Future;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
+
+/// Inserts between yields when the caller passes 0.
+pub const DEFAULT_YIELD_EVERY: u32 = 1_000;
+
+/// What an async bulk operation did, plus the structure it filled.
+///
+/// The structure is moved into the summary because async exports must own
+/// their arguments; call `take_store()` to get it back.
+#[wasm_bindgen]
+pub struct BulkSummary {
+    kind: String,
+    inserted: u32,
+    yields: u32,
+    elapsed_ms: f64,
+    cancelled: bool,
+    store: Option<DynamicStore>,
+}
+
+#[wasm_bindgen]
+impl BulkSummary {
+    /// Kind of the structure that was filled.
+    pub fn kind(&self) -> String {
+        self.kind.clone()
+    }
+
+    /// Entries inserted by the operation.
+    pub fn inserted(&self) -> u32 {
+        self.inserted
+    }
+
+    /// How many times the operation handed control back to the event loop.
+    pub fn yields(&self) -> u32 {
+        self.yields
+    }
+
+    /// Wall-clock time from start to resolution, including time spent yielded.
+    pub fn elapsed_ms(&self) -> f64 {
+        self.elapsed_ms
+    }
+
+    /// Whether the operation stopped early because its token was cancelled.
+    /// The store then holds only the first `inserted()` entries.
+    pub fn cancelled(&self) -> bool {
+        self.cancelled
+    }
+
+    /// Move the filled structure out. Returns `undefined` on later calls.
+    pub fn take_store(&mut self) -> Option<DynamicStore> {
+        self.store.take()
+    }
+}
+
+/// Feed `entries` to `insert`, awaiting `yield_now()` after every
+/// `yield_every` inserts. Returns `(inserted, yields, cancelled)`, or the
+/// first error `yield_now()` fails with.
+///
+/// `progress` is updated at every yield and on completion; `cancel` is
+/// checked before starting and after every yield, since that's when a user
+/// gets the chance to click cancel. `insert` borrows the structure only
+/// for its own call, so JS can use it freely while this is yielded. Kept
+/// independent of JS so the batching can be tested natively.
+pub(crate) async fn insert_with_yields<I, Y, F, E>(
+    mut insert: impl FnMut(String, u32),
+    entries: I,
+    yield_every: u32,
+    progress: &mut ProgressHook,
+    cancel: &CancellationToken,
+    mut yield_now: Y,
+) -> Result<(u32, u32, bool), E>
+where
+    I: IntoIterator<Item = (String, u32)>,
+    I::IntoIter: ExactSizeIterator,
+    Y: FnMut() -> F,
+    F: Future<Output = Result<(), E>>,
+{
+    let entries = entries.into_iter();
+    let total = Some(entries.len() as u32);
+    let yield_every = effective_yield_every(yield_every);
+    let start = now_ms();
+    let mut inserted = 0;
+    let mut yields = 0;
+    for (key, value) in entries {
+        if cancel.is_cancelled() {
+            return Ok((inserted, yields, true));
+        }
+        insert(key, value);
+        inserted += 1;
+        if inserted % yield_every == 0 {
+            progress.update(inserted, total, now_ms() - start);
+            yield_now().await?;
+            yields += 1;
+        }
+    }
+    progress.update(inserted, total, now_ms() - start);
+    Ok((inserted, yields, false))
+}
+
+fn effective_yield_every(yield_every: u32) -> u32 {
+    if yield_every == 0 {
+        DEFAULT_YIELD_EVERY
+    } else {
+        yield_every
+    }
+}
+
+/// Resolve on the next macrotask so the page can render and handle input.
+///
+/// Awaiting an already-resolved promise only drains the microtask queue,
+/// which doesn't let the browser paint, hence `setTimeout(resolve, 0)`.
+async fn next_tick() -> Result<(), JsValue> {
+    let promise = Promise::new(&mut |resolve, _reject| {
+        let set_timeout = Reflect::get(&js_sys::global(), &JsValue::from_str("setTimeout"))
+            .ok()
+            .and_then(|f| f.dyn_into::<Function>().ok());
+        let _ = match set_timeout {
+            Some(set_timeout) => {
+                set_timeout.call2(&JsValue::UNDEFINED, &resolve, &JsValue::from(0))
+            }
+            None => resolve.call0(&JsValue::UNDEFINED),
+        };
+    });
+    JsFuture::from(promise).await.map(|_| ())
+}
+
+/// Insert `keys[i] -> values[i]` into `store` without freezing the page.
+///
+/// Control returns to the event loop after every `yield_every` inserts
+/// (0 means 1000), and `on_progress({ processed, total, elapsed_ms })` is
+/// called at each of those points if given. If `cancel` is cancelled the
+/// load stops at the next yield. Either way the returned Promise resolves
+/// with a [`BulkSummary`] holding the filled structure.
+///
+/// Each entry goes through `store.insert`, so an open batch stages it and
+/// `on` listeners see it, as with [`build_from_stream_async`].
+///
+/// # Example
+/// ```javascript
+/// const summary = await bulk_insert_async(new DynamicStore("rbtree", 0), keys, values, 5000,
+///     ({ processed, total }) => { progressBar.value = processed / total; });
+/// console.log(`${summary.inserted()} inserts, ${summary.yields()} yields`);
+/// const tree = summary.take_store();
+/// ```
+#[wasm_bindgen]
+pub async fn bulk_insert_async(
+    mut store: DynamicStore,
+    keys: Vec<String>,
+    values: Vec<u32>,
+    yield_every: u32,
+    on_progress: Option<Function>,
+    cancel: Option<CancellationToken>,
+) -> Result<BulkSummary, JsValue> {
+    if keys.len() != values.len() {
+        return Err(JsValue::from_str(&format!(
+            "keys and values differ in length ({} vs {})",
+            keys.len(),
+            values.len()
+        )));
+    }
+
+    let start = now_ms();
+    let mut progress = ProgressHook::from_js(on_progress, effective_yield_every(yield_every));
+    // Other handles to the store (a workspace's, a frozen view's) may use
+    // it while this is yielded, so it is only borrowed per insert
+    let (inserted, yields, cancelled) = insert_with_yields(
+        |key, value| store.insert(key, value),
+        keys.into_iter().zip(values),
+        yield_every,
+        &mut progress,
+        &cancel.unwrap_or_default(),
+        next_tick,
+    )
+    .await?;
+
+    Ok(BulkSummary {
+        kind: store.kind(),
+        inserted,
+        yields,
+        elapsed_ms: now_ms() - start,
+        cancelled,
+        store: Some(store),
+    })
+}
+
+/// Build a structure of `kind` from an async iterator of `[key, value]` pairs.
+///
+/// Works with async generators and anything else exposing `next()` that
+/// returns a Promise of `{ done, value }`, such as a parsed network stream.
+/// Progress reports have `total` undefined until the final one. Cancellation
+/// is checked before pulling each item, and the iterator's `return()` is
+/// called so the producer can clean up.
+///
+/// # Example
+/// ```javascript
+/// async function* rows() {
+///     for await (const line of lines) yield line.split(",").map((s, i) => i ? Number(s) : s);
+/// }
+/// const summary = await build_from_stream_async("trie", rows(), 1000, console.log);
+/// ```
+#[wasm_bindgen]
+pub async fn build_from_stream_async(
+    kind: String,
+    stream: AsyncIterator,
+    yield_every: u32,
+    on_progress: Option<Function>,
+    cancel: Option<CancellationToken>,
+) -> Result<BulkSummary, JsValue> {
+    let mut store = DynamicStore::new(&kind, 0)?;
+    let cancel = cancel.unwrap_or_default();
+    let yield_every = effective_yield_every(yield_every);
+    let mut progress = ProgressHook::from_js(on_progress, yield_every);
+    let start = now_ms();
+    let mut inserted = 0;
+    let mut yields = 0;
+    let mut cancelled = false;
+
+    loop {
+        if cancel.is_cancelled() {
+            cancelled = true;
+            close_iterator(&stream).await;
+            break;
+        }
+        let step = JsFuture::from(stream.next()?).await?;
+        if Reflect::get(&step, &JsValue::from_str("done"))?.is_truthy() {
+            break;
+        }
+        let (key, value) = parse_pair(&Reflect::get(&step, &JsValue::from_str("value"))?)?;
+        store.insert(key, value);
+        inserted += 1;
+        if inserted % yield_every == 0 {
+            progress.update(inserted, None, now_ms() - start);
+            next_tick().await?;
+            yields += 1;
+        }
+    }
+    if !cancelled {
+        progress.finish(inserted, now_ms() - start);
+    }
+
+    Ok(BulkSummary {
+        kind,
+        inserted,
+        yields,
+        elapsed_ms: now_ms() - start,
+        cancelled,
+        store: Some(store),
+    })
+}
+
+/// Call the iterator's optional `return()` method, ignoring failures.
+async fn close_iterator(stream: &AsyncIterator) {
+    let Ok(close) = Reflect::get(stream, &JsValue::from_str("return")) else {
+        return;
+    };
+    if let Ok(close) = close.dyn_into::<Function>() {
+        if let Ok(result) = close.call0(stream) {
+            let _ = JsFuture::from(Promise::resolve(&result)).await;
+        }
+    }
+}
+
+fn parse_pair(item: &JsValue) -> Result<(String, u32), JsValue> {
+    let pair = Array::from(item);
+    let key = pair
+        .get(0)
+        .as_string()
+        .ok_or_else(|| JsValue::from_str("stream items must be [key: string, value: number]"))?;
+    let value = pair
+        .get(1)
+        .as_f64()
+        .ok_or_else(|| JsValue::from_str("stream items must be [key: string, value: number]"))?;
+    Ok((key, value as u32))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kv_store::new_store;
+    use std::cell::RefCell;
+    use std::pin::pin;
+    use std::rc::Rc;
+    use std::task::{Context, Poll, Waker};
+
+    /// Poll a future that never actually suspends to completion.
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = pin!(future);
+        let mut cx = Context::from_waker(Waker::noop());
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+        }
+    }
+
+    fn entries(n: u32) -> Vec<(String, u32)> {
+        (0..n).map(|i| (format!("key{}", i), i)).collect()
+    }
+
+    #[test]
+    fn test_yields_every_n_inserts() {
+        let mut store = new_store("bst", 0).unwrap();
+        let mut ticks = 0;
+        let (inserted, yields, _) = block_on(insert_with_yields::<_, _, _, ()>(
+            |key, value| store.kv_insert(key, value),
+            entries(250),
+            100,
+            &mut ProgressHook::none(),
+            &CancellationToken::new(),
+            || {
+                ticks += 1;
+                async { Ok(()) }
+            },
+        ))
+        .unwrap();
+        assert_eq!(inserted, 250);
+        assert_eq!(yields, 2);
+        assert_eq!(ticks, 2);
+        assert_eq!(store.kv_len(), 250);
+        assert_eq!(store.kv_get("key249"), Some(249));
+    }
+
+    #[test]
+    fn test_zero_yield_every_uses_default() {
+        let mut store = new_store("hashmap", 0).unwrap();
+        let n = DEFAULT_YIELD_EVERY * 2 + 1;
+        let (inserted, yields, _) = block_on(insert_with_yields::<_, _, _, ()>(
+            |key, value| store.kv_insert(key, value),
+            entries(n),
+            0,
+            &mut ProgressHook::none(),
+            &CancellationToken::new(),
+            || async { Ok(()) },
+        ))
+        .unwrap();
+        assert_eq!(inserted, n);
+        assert_eq!(yields, 2);
+    }
+
+    #[test]
+    fn test_progress_reported_at_yields_and_end() {
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let sink = Rc::clone(&seen);
+        let mut progress = ProgressHook::new(40, move |p| sink.borrow_mut().push(p.processed));
+        let mut store = new_store("trie", 0).unwrap();
+        block_on(insert_with_yields::<_, _, _, ()>(
+            |key, value| store.kv_insert(key, value),
+            entries(100),
+            40,
+            &mut progress,
+            &CancellationToken::new(),
+            || async { Ok(()) },
+        ))
+        .unwrap();
+        assert_eq!(*seen.borrow(), vec![40, 80, 100]);
+    }
+
+    #[test]
+    fn test_cancel_during_yield_keeps_partial_results() {
+        let token = CancellationToken::new();
+        let handle = token.handle();
+        let mut store = new_store("rbtree", 0).unwrap();
+        let (inserted, yields, cancelled) = block_on(insert_with_yields::<_, _, _, ()>(
+            |key, value| store.kv_insert(key, value),
+            entries(500),
+            100,
+            &mut ProgressHook::none(),
+            &handle,
+            || {
+                // The user clicks cancel while we're yielded
+                token.cancel();
+                async { Ok(()) }
+            },
+        ))
+        .unwrap();
+        assert!(cancelled);
+        assert_eq!((inserted, yields), (100, 1));
+        assert_eq!(store.kv_len(), 100);
+    }
+
+    #[test]
+    fn test_already_cancelled_inserts_nothing() {
+        let token = CancellationToken::new();
+        token.cancel();
+        let mut store = new_store("hashmap", 0).unwrap();
+        let (inserted, _, cancelled) = block_on(insert_with_yields::<_, _, _, ()>(
+            |key, value| store.kv_insert(key, value),
+            entries(10),
+            100,
+            &mut ProgressHook::none(),
+            &token,
+            || async { Ok(()) },
+        ))
+        .unwrap();
+        assert!(cancelled);
+        assert_eq!(inserted, 0);
+        assert_eq!(store.kv_len(), 0);
+    }
+
+    #[test]
+    fn test_failed_yield_stops_the_load() {
+        let mut store = new_store("skiplist", 0).unwrap();
+        let result = block_on(insert_with_yields(
+            |key, value| store.kv_insert(key, value),
+            entries(300),
+            100,
+            &mut ProgressHook::none(),
+            &CancellationToken::new(),
+            || async { Err("setTimeout threw") },
+        ));
+        assert_eq!(result, Err("setTimeout threw"));
+        assert_eq!(store.kv_len(), 100);
+    }
+
+    #[test]
+    fn test_store_is_usable_while_yielded() {
+        let mut store = DynamicStore::try_new("hashmap", 0).unwrap();
+        let mut shared = store.handle();
+        let events = Rc::new(std::cell::Cell::new(0));
+        let count = Rc::clone(&events);
+        store.subscribe(crate::events::EventKind::Insert, move |_| {
+            count.set(count.get() + 1)
+        });
+        let mut seen = Vec::new();
+        let (inserted, _, _) = block_on(insert_with_yields::<_, _, _, ()>(
+            |key, value| store.insert(key, value),
+            entries(300),
+            100,
+            &mut ProgressHook::none(),
+            &CancellationToken::new(),
+            || {
+                // Another handle reads and writes during each yield
+                seen.push(shared.len());
+                shared.insert(format!("extra{}", seen.len()), 0);
+                async { Ok(()) }
+            },
+        ))
+        .unwrap();
+        assert_eq!(inserted, 300);
+        assert_eq!(seen, [100, 201, 302]);
+        assert_eq!(store.len(), 303);
+        // Listeners saw the bulk inserts and the ones made while yielded
+        assert_eq!(events.get(), 303);
+    }
+}
@@ -0,0 +1,398 @@
+use crate::bk_tree::BkTree;
+use crate::clock::now_ms;
+use crate::kv_store::{new_store, KvStore};
+use crate::quantile::QuantileSketch;
+use crate::rng::DefaultRng;
+use crate::scenarios::{Operation, Scenario};
+use crate::trie::Trie;
+use rand::Rng;
+use wasm_bindgen::prelude::*;
+
+/// Outcome of replaying one scenario against one structure.
+///
+/// Carries the scenario name and version so results collected by different
+/// users or crate releases can be checked for comparability before comparing.
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Debug)]
+pub struct BenchmarkResult {
+    pub structure: String,
+    pub scenario: String,
+    pub scenario_version: u32,
+    pub load_operations: u32,
+    pub measured_operations: u32,
+    pub hits: u32,
+    pub misses: u32,
+    pub load_ms: f64,
+    pub run_ms: f64,
+    pub final_size: u32,
+    /// Structure metrics captured after the run (see [`KvStore::metrics_snapshot`]).
+    metrics: Vec<(String, f64)>,
+    /// Per-operation latencies of the measured phase, in milliseconds.
+    latency: QuantileSketch,
+}
+
+impl BenchmarkResult {
+    /// Captured structure metrics as `(name, value)` pairs.
+    pub fn metrics(&self) -> &[(String, f64)] {
+        &self.metrics
+    }
+
+    pub fn latency_ref(&self) -> &QuantileSketch {
+        &self.latency
+    }
+}
+
+#[wasm_bindgen]
+impl BenchmarkResult {
+    /// Value of a captured structure metric, e.g. `"total_collisions"`.
+    pub fn metric(&self, name: &str) -> Option<f64> {
+        self.metrics
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| *v)
+    }
+
+    /// Names of the captured structure metrics.
+    pub fn metric_names(&self) -> Vec<JsValue> {
+        self.metrics
+            .iter()
+            .map(|(n, _)| JsValue::from_str(n))
+            .collect()
+    }
+
+    /// Per-operation latency in milliseconds at quantile `q` of the
+    /// measured phase, e.g. 0.99 for p99. Browsers coarsen their clocks, so
+    /// under wasm most operations read as 0 and only the tail is telling.
+    pub fn latency_percentile(&self, q: f64) -> Option<f64> {
+        self.latency.quantile(q)
+    }
+
+    /// Throughput of the measured phase in operations per millisecond.
+    pub fn ops_per_ms(&self) -> f64 {
+        if self.run_ms > 0.0 {
+            self.measured_operations as f64 / self.run_ms
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Replay a scenario against an already-constructed structure.
+pub fn run_scenario_on(scenario: &Scenario, store: &mut dyn KvStore) -> BenchmarkResult {
+    let ops = scenario.generate_operations();
+    let (load, measured) = ops.split_at(scenario.dataset_size.min(ops.len()));
+    measure(scenario.name, scenario.version, store, load, measured)
+}
+
+/// Apply `load` as one timed block, then `measured` with each operation
+/// timed individually.
+pub(crate) fn measure(
+    scenario: &str,
+    scenario_version: u32,
+    store: &mut dyn KvStore,
+    load: &[Operation],
+    measured: &[Operation],
+) -> BenchmarkResult {
+    let load_start = now_ms();
+    let _ = apply_operations(store, load, None);
+    let load_ms = now_ms() - load_start;
+
+    let mut latency = QuantileSketch::default();
+    let run_start = now_ms();
+    let (hits, misses) = apply_operations(store, measured, Some(&mut latency));
+    let run_ms = now_ms() - run_start;
+
+    BenchmarkResult {
+        structure: store.kind().to_string(),
+        scenario: scenario.to_string(),
+        scenario_version,
+        load_operations: load.len() as u32,
+        measured_operations: measured.len() as u32,
+        hits,
+        misses,
+        load_ms,
+        run_ms,
+        final_size: store.kv_len() as u32,
+        metrics: store
+            .metrics_snapshot()
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value))
+            .collect(),
+        latency,
+    }
+}
+
+/// Apply operations in order, returning (hits, misses) for gets and deletes.
+/// With a `latency` sketch, each operation is timed individually into it.
+fn apply_operations(
+    store: &mut dyn KvStore,
+    ops: &[Operation],
+    mut latency: Option<&mut QuantileSketch>,
+) -> (u32, u32) {
+    let mut hits = 0;
+    let mut misses = 0;
+    for op in ops {
+        let start = latency.is_some().then(now_ms);
+        let found = match op {
+            Operation::Insert(key, value) => {
+                store.kv_insert(key.clone(), *value);
+                None
+            }
+            Operation::Get(key) => Some(store.kv_get(key).is_some()),
+            Operation::Delete(key) => Some(store.kv_delete(key)),
+        };
+        if let (Some(sketch), Some(start)) = (latency.as_deref_mut(), start) {
+            sketch.add(now_ms() - start);
+        }
+        match found {
+            Some(true) => hits += 1,
+            Some(false) => misses += 1,
+            None => {}
+        }
+    }
+    (hits, misses)
+}
+
+/// Replay a bundled scenario by name against a fresh structure of `structure` kind.
+pub fn run_named(scenario: &str, structure: &str) -> Result<BenchmarkResult, String> {
+    let scenario =
+        Scenario::by_name(scenario).ok_or_else(|| format!("unknown scenario '{}'", scenario))?;
+    let mut store = new_store(structure, scenario.dataset_size)
+        .ok_or_else(|| format!("unknown structure '{}'", structure))?;
+    Ok(run_scenario_on(scenario, store.as_mut()))
+}
+
+/// Approximate matching on the same words and queries, via the fuzzy
+/// [`Trie`] walk and via a [`BkTree`].
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug)]
+pub struct FuzzyBenchmarkResult {
+    pub words: u32,
+    pub queries: u32,
+    pub tolerance: u32,
+    /// Matches found across all queries (both structures find the same set)
+    pub matches: u32,
+    pub trie_ms: f64,
+    pub bk_tree_ms: f64,
+    pub trie_nodes_visited: u32,
+    pub trie_nodes_pruned: u32,
+    pub bk_distance_computations: u32,
+    pub bk_subtrees_pruned: u32,
+}
+
+/// Misspell `count` randomly chosen words with one random edit each.
+pub fn misspelled_queries(words: &[String], count: usize, seed: u64) -> Vec<String> {
+    let mut rng = DefaultRng::seed_from(seed);
+    (0..count)
+        .map(|_| {
+            let mut chars: Vec<char> = words[rng.gen_range(0..words.len())].chars().collect();
+            let at = rng.gen_range(0..=chars.len());
+            let letter = rng.gen_range(b'a'..=b'z') as char;
+            match rng.gen_range(0..3) {
+                0 => chars.insert(at, letter),
+                1 if at < chars.len() => chars[at] = letter,
+                _ if at < chars.len() => {
+                    chars.remove(at);
+                }
+                _ => chars.push(letter),
+            }
+            chars.into_iter().collect()
+        })
+        .collect()
+}
+
+/// Load `words` into a trie and a BK-tree, then time `queries` on each.
+pub fn run_fuzzy_on(words: &[String], queries: &[String], tolerance: u32) -> FuzzyBenchmarkResult {
+    let mut trie = Trie::new();
+    let mut bk_tree = BkTree::new();
+    for (i, word) in words.iter().enumerate() {
+        trie.insert(word.clone(), i as u32);
+        bk_tree.add(word.clone());
+    }
+
+    let start = now_ms();
+    let trie_matches: usize = queries
+        .iter()
+        .map(|q| trie.search_fuzzy(q, tolerance).len())
+        .sum();
+    let trie_ms = now_ms() - start;
+
+    let computed_while_loading = bk_tree.get_metrics().distance_computations;
+    let start = now_ms();
+    let bk_matches: usize = queries
+        .iter()
+        .map(|q| bk_tree.query(q, tolerance).len())
+        .sum();
+    let bk_tree_ms = now_ms() - start;
+    debug_assert_eq!(trie_matches, bk_matches);
+
+    let trie_metrics = trie.get_metrics();
+    let bk_metrics = bk_tree.get_metrics();
+    FuzzyBenchmarkResult {
+        words: trie.size(),
+        queries: queries.len() as u32,
+        tolerance,
+        matches: trie_matches as u32,
+        trie_ms,
+        bk_tree_ms,
+        trie_nodes_visited: trie_metrics.fuzzy_nodes_visited,
+        trie_nodes_pruned: trie_metrics.fuzzy_nodes_pruned,
+        bk_distance_computations: bk_metrics.distance_computations - computed_while_loading,
+        bk_subtrees_pruned: bk_metrics.subtrees_pruned,
+    }
+}
+
+/// Fuzzy-match benchmark over a bundled scenario's keys, with
+/// `query_count` one-edit misspellings drawn from the scenario's seed.
+pub fn run_fuzzy_named(
+    scenario: &str,
+    query_count: u32,
+    tolerance: u32,
+) -> Result<FuzzyBenchmarkResult, String> {
+    let scenario =
+        Scenario::by_name(scenario).ok_or_else(|| format!("unknown scenario '{}'", scenario))?;
+    let words = scenario.generate_keys();
+    let queries = misspelled_queries(&words, query_count as usize, scenario.seed.wrapping_add(2));
+    Ok(run_fuzzy_on(&words, &queries, tolerance))
+}
+
+/// Compare fuzzy Trie traversal against a BK-tree on a scenario's keys.
+///
+/// # Example
+/// ```javascript
+/// const r = run_fuzzy_benchmark("dictionary-load", 200, 1);
+/// console.log(r.trie_ms, r.bk_tree_ms, r.matches);
+/// ```
+#[wasm_bindgen]
+pub fn run_fuzzy_benchmark(
+    scenario: &str,
+    query_count: u32,
+    tolerance: u32,
+) -> Result<FuzzyBenchmarkResult, JsValue> {
+    run_fuzzy_named(scenario, query_count, tolerance).map_err(|e| JsValue::from_str(&e))
+}
+
+/// Names of all bundled benchmark scenarios.
+///
+/// # Example
+/// ```javascript
+/// for (const name of scenario_names()) {
+///     console.log(name, describe_scenario(name));
+/// }
+/// ```
+#[wasm_bindgen]
+pub fn scenario_names() -> Vec<JsValue> {
+    Scenario::names()
+        .into_iter()
+        .map(JsValue::from_str)
+        .collect()
+}
+
+/// Human-readable description of a scenario, including its version.
+#[wasm_bindgen]
+pub fn describe_scenario(name: &str) -> Option<String> {
+    Scenario::by_name(name).map(|s| {
+        format!(
+            "{} v{}: {} ({} keys, {} ops, {}% insert / {}% get / {}% delete)",
+            s.name,
+            s.version,
+            s.description,
+            s.dataset_size,
+            s.operation_count,
+            s.op_mix.insert_pct,
+            s.op_mix.get_pct,
+            s.op_mix.delete_pct
+        )
+    })
+}
+
+/// Run a bundled scenario against a fresh structure.
+///
+/// `structure` is one of `hashmap`, `open_addressing`, `bst`, `rbtree`,
+/// `skiplist` or `trie`.
+///
+/// # Example
+/// ```javascript
+/// const result = run_scenario("session-cache-churn", "rbtree");
+/// console.log(result.ops_per_ms());
+/// ```
+#[wasm_bindgen]
+pub fn run_scenario(scenario: &str, structure: &str) -> Result<BenchmarkResult, JsValue> {
+    run_named(scenario, structure).map_err(|e| JsValue::from_str(&e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kv_store::STORE_KINDS;
+    use crate::scenarios::SCENARIOS;
+
+    #[test]
+    fn test_every_scenario_runs_on_every_structure() {
+        for scenario in SCENARIOS {
+            let inserts = scenario.generate_operations()[scenario.dataset_size..]
+                .iter()
+                .filter(|op| matches!(op, Operation::Insert(..)))
+                .count() as u32;
+            let mut sizes = Vec::new();
+            for kind in STORE_KINDS {
+                let result = run_named(scenario.name, kind).unwrap();
+                assert_eq!(result.structure, kind);
+                assert_eq!(result.load_operations as usize, scenario.dataset_size);
+                assert_eq!(
+                    result.hits + result.misses + inserts,
+                    result.measured_operations
+                );
+                assert_eq!(
+                    result.latency_ref().count(),
+                    result.measured_operations as u64
+                );
+                sizes.push(result.final_size);
+            }
+            // Same operation stream => same final contents everywhere
+            assert!(
+                sizes.windows(2).all(|w| w[0] == w[1]),
+                "{}: {:?}",
+                scenario.name,
+                sizes
+            );
+        }
+    }
+
+    #[test]
+    fn test_read_only_scenario_hits_everything() {
+        let result = run_named("dictionary-load", "hashmap").unwrap();
+        assert_eq!(result.misses, 0);
+        assert_eq!(result.hits, 20_000);
+        assert_eq!(result.final_size, 10_000);
+    }
+
+    #[test]
+    fn test_unknown_names_are_errors() {
+        assert!(run_named("nope", "hashmap").is_err());
+        assert!(run_named("sorted-ingest", "nope").is_err());
+    }
+
+    #[test]
+    fn test_describe_scenario() {
+        let text = describe_scenario("sorted-ingest").unwrap();
+        assert!(text.starts_with("sorted-ingest v1"));
+        assert!(describe_scenario("missing").is_none());
+    }
+
+    #[test]
+    fn test_fuzzy_benchmark() {
+        let words: Vec<String> = ["apple", "apply", "ample", "maple", "angle", "apples"]
+            .iter()
+            .map(|w| w.to_string())
+            .collect();
+        let queries = misspelled_queries(&words, 20, 1);
+        assert!(queries.iter().all(|q| q.len().abs_diff(5) <= 2));
+        let result = run_fuzzy_on(&words, &queries, 1);
+        assert_eq!(result.words, 6);
+        // Every query is one edit from the word it came from
+        assert!(result.matches >= 20);
+        assert!(result.bk_distance_computations <= 20 * 6);
+        assert!(run_fuzzy_named("no-such-scenario", 1, 1).is_err());
+    }
+}
@@ -0,0 +1,196 @@
+//! Interactive REPL over every structure, for exploring behavior from a
+//! terminal without building a web frontend.
+//!
+//! ```text
+//! cargo run --features playground --bin playground
+//! > new rbtree
+//! > fill 20
+//! > get key7
+//! > metrics
+//! ```
+
+use rand::RngCore;
+use std::io::{self, BufRead, Write};
+use wasm_data_structures::kv_store::{new_store, HASHMAP_VARIANTS, STORE_KINDS};
+use wasm_data_structures::rng::Xoshiro256;
+use wasm_data_structures::KvStore;
+
+const HELP: &str = "\
+commands:
+  new <kind>           start over with an empty structure (see `kinds`)
+  kinds                list structure kinds
+  insert <key> <value> insert or update a key
+  get <key>            look up a key
+  delete <key>         remove a key
+  fill <n> [seed]      insert key0..key<n-1> in shuffled order
+  len                  number of entries
+  entries              every entry, in the structure's order
+  metrics              the structure's counters
+  memory               estimated heap usage
+  show                 draw the structure
+  help                 this message
+  quit                 leave";
+
+struct Playground {
+    store: Box<dyn KvStore>,
+}
+
+impl Playground {
+    fn new() -> Playground {
+        Playground {
+            store: new_store("hashmap", 0).expect("hashmap is a store kind"),
+        }
+    }
+
+    /// Run one command line and return what to print.
+    fn execute(&mut self, line: &str) -> Result<String, String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let arg = |i: usize| {
+            words
+                .get(i)
+                .copied()
+                .ok_or_else(|| format!("'{}' needs more arguments; try `help`", words[0]))
+        };
+        let number = |i: usize| -> Result<u32, String> {
+            let text = arg(i)?;
+            text.parse()
+                .map_err(|_| format!("'{}' is not a number from 0 to {}", text, u32::MAX))
+        };
+        let Some(&command) = words.first() else {
+            return Ok(String::new());
+        };
+        let store = &mut self.store;
+        Ok(match command {
+            "help" => HELP.to_string(),
+            "kinds" => STORE_KINDS
+                .iter()
+                .chain(&HASHMAP_VARIANTS)
+                .copied()
+                .collect::<Vec<_>>()
+                .join(" "),
+            "new" => {
+                let kind = arg(1)?;
+                *store = new_store(kind, 0)
+                    .ok_or_else(|| format!("unknown structure '{}'; try `kinds`", kind))?;
+                format!("new empty {}", kind)
+            }
+            "insert" => {
+                let (key, value) = (arg(1)?, number(2)?);
+                store.kv_insert(key.to_string(), value);
+                format!("{} = {}", key, value)
+            }
+            "get" => match store.kv_get(arg(1)?) {
+                Some(value) => value.to_string(),
+                None => "(not found)".to_string(),
+            },
+            "delete" => {
+                let removed = store.kv_delete(arg(1)?);
+                if removed { "deleted" } else { "(not found)" }.to_string()
+            }
+            "fill" => {
+                let n = number(1)?;
+                let seed = if words.len() > 2 { number(2)? } else { n };
+                let mut order: Vec<u32> = (0..n).collect();
+                let mut rng = Xoshiro256::seed_from(seed as u64);
+                for i in (1..order.len()).rev() {
+                    order.swap(i, (rng.next_u64() % (i as u64 + 1)) as usize);
+                }
+                for i in order {
+                    store.kv_insert(format!("key{}", i), i);
+                }
+                format!("inserted {} keys; {} entries", n, store.kv_len())
+            }
+            "len" => store.kv_len().to_string(),
+            "entries" => store
+                .kv_entries()
+                .iter()
+                .map(|(key, value)| format!("{} = {}", key, value))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            "metrics" => store
+                .metrics_snapshot()
+                .iter()
+                .map(|(name, value)| format!("{:<24} {}", name, value))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            "memory" => {
+                let report = store.memory_report();
+                format!(
+                    "used {} bytes, reserved {} bytes ({:.0}% utilized)",
+                    report.used_bytes,
+                    report.reserved_bytes,
+                    report.utilization() * 100.0
+                )
+            }
+            "show" => store.to_ascii(),
+            _ => return Err(format!("unknown command '{}'; try `help`", command)),
+        })
+    }
+}
+
+fn main() {
+    let mut playground = Playground::new();
+    println!("wasm-data-structures playground: `help` lists commands");
+    let stdin = io::stdin();
+    loop {
+        print!("{}> ", playground.store.kind());
+        let _ = io::stdout().flush();
+        let mut line = String::new();
+        match stdin.lock().read_line(&mut line) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {}
+        }
+        if matches!(line.trim(), "quit" | "exit") {
+            break;
+        }
+        match playground.execute(&line) {
+            Ok(output) if output.is_empty() => {}
+            Ok(output) => println!("{}", output),
+            Err(message) => eprintln!("error: {}", message),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session() {
+        let mut playground = Playground::new();
+        assert_eq!(
+            playground.execute("new skiplist").unwrap(),
+            "new empty skiplist"
+        );
+        assert_eq!(
+            playground.execute("fill 10").unwrap(),
+            "inserted 10 keys; 10 entries"
+        );
+        assert_eq!(playground.execute("get key3").unwrap(), "3");
+        assert_eq!(playground.execute("delete key3").unwrap(), "deleted");
+        assert_eq!(playground.execute("get key3").unwrap(), "(not found)");
+        assert_eq!(playground.execute("insert a 5").unwrap(), "a = 5");
+        assert!(playground
+            .execute("entries")
+            .unwrap()
+            .starts_with("a = 5\nkey0 = 0"));
+        assert_eq!(playground.execute("  ").unwrap(), "");
+
+        assert!(playground
+            .execute("insert a -1")
+            .unwrap_err()
+            .contains("not a number"));
+        assert!(playground
+            .execute("get")
+            .unwrap_err()
+            .contains("needs more"));
+        assert!(playground
+            .execute("new btree")
+            .unwrap_err()
+            .contains("unknown structure"));
+        assert!(playground
+            .execute("frobnicate")
+            .unwrap_err()
+            .contains("help"));
+    }
+}
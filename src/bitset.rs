@@ -0,0 +1,196 @@
+use std::cell::OnceCell;
+use wasm_bindgen::prelude::*;
+
+/// Fixed-length bit vector with rank and select.
+///
+/// `rank1(i)` counts ones before position `i` in O(1) using the cumulative
+/// count stored per 64-bit word; `select1(k)` finds the k-th one by binary
+/// search over those counts. The counts are built on the first rank or
+/// select after a change, so a batch of `set` calls costs nothing extra.
+///
+/// # Example
+/// ```javascript
+/// const bits = new BitSet(10);
+/// bits.set(2, true); bits.set(7, true);
+/// bits.rank1(5);   // 1
+/// bits.select1(1); // 7
+/// ```
+#[wasm_bindgen]
+#[derive(Clone, Debug, Default)]
+pub struct BitSet {
+    words: Vec<u64>,
+    len: usize,
+    /// Ones in all words before each word, plus the total
+    ranks: OnceCell<Vec<u32>>,
+}
+
+impl BitSet {
+    pub fn from_bits(bits: impl IntoIterator<Item = bool>) -> BitSet {
+        let mut set = BitSet::default();
+        for bit in bits {
+            if set.len.is_multiple_of(64) {
+                set.words.push(0);
+            }
+            if bit {
+                set.words[set.len / 64] |= 1 << (set.len % 64);
+            }
+            set.len += 1;
+        }
+        set
+    }
+
+    fn ranks(&self) -> &[u32] {
+        self.ranks.get_or_init(|| {
+            let mut ranks = Vec::with_capacity(self.words.len() + 1);
+            let mut total = 0;
+            ranks.push(0);
+            for w in &self.words {
+                total += w.count_ones();
+                ranks.push(total);
+            }
+            ranks
+        })
+    }
+
+    /// Index of the `k`-th (0-based) bit equal to `bit`.
+    fn select(&self, bit: bool, k: usize) -> Option<usize> {
+        let ranks = self.ranks();
+        let count_before = |word: usize| {
+            let ones = ranks[word] as usize;
+            if bit {
+                ones
+            } else {
+                word * 64 - ones
+            }
+        };
+        if self.words.is_empty() {
+            return None;
+        }
+        // Last word with at most k matching bits before it
+        let (mut word, mut end) = (0, self.words.len());
+        while end - word > 1 {
+            let mid = (word + end) / 2;
+            if count_before(mid) <= k {
+                word = mid;
+            } else {
+                end = mid;
+            }
+        }
+        let mut remaining = k - count_before(word);
+        let mut bits = if bit {
+            self.words[word]
+        } else {
+            !self.words[word]
+        };
+        while bits != 0 {
+            let position = bits.trailing_zeros() as usize;
+            if remaining == 0 {
+                let index = word * 64 + position;
+                return (index < self.len).then_some(index);
+            }
+            remaining -= 1;
+            bits &= bits - 1;
+        }
+        None
+    }
+}
+
+#[wasm_bindgen]
+impl BitSet {
+    /// `len` bits, all zero.
+    #[wasm_bindgen(constructor)]
+    pub fn new(len: usize) -> BitSet {
+        BitSet {
+            words: vec![0; len.div_ceil(64)],
+            len,
+            ranks: OnceCell::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn get(&self, index: usize) -> bool {
+        index < self.len && self.words[index / 64] >> (index % 64) & 1 == 1
+    }
+
+    /// Set bit `index`. Returns false if it is out of range.
+    pub fn set(&mut self, index: usize, value: bool) -> bool {
+        if index >= self.len {
+            return false;
+        }
+        let mask = 1 << (index % 64);
+        if value {
+            self.words[index / 64] |= mask;
+        } else {
+            self.words[index / 64] &= !mask;
+        }
+        self.ranks.take();
+        true
+    }
+
+    pub fn count_ones(&self) -> usize {
+        self.rank1(self.len)
+    }
+
+    /// Ones among the first `index` bits (`index` clamped to the length).
+    pub fn rank1(&self, index: usize) -> usize {
+        let index = index.min(self.len);
+        let word = index / 64;
+        let before = self.ranks()[word] as usize;
+        let partial = match index % 64 {
+            0 => 0,
+            bits => (self.words[word] & ((1 << bits) - 1)).count_ones() as usize,
+        };
+        before + partial
+    }
+
+    /// Zeros among the first `index` bits.
+    pub fn rank0(&self, index: usize) -> usize {
+        index.min(self.len) - self.rank1(index)
+    }
+
+    /// Position of the `k`-th one, counting from 0.
+    pub fn select1(&self, k: usize) -> Option<usize> {
+        self.select(true, k)
+    }
+
+    /// Position of the `k`-th zero, counting from 0.
+    pub fn select0(&self, k: usize) -> Option<usize> {
+        self.select(false, k)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rank_select_match_naive() {
+        let bits: Vec<bool> = (0..300).map(|i| i % 3 == 0 || i % 7 == 0).collect();
+        let mut set = BitSet::from_bits(bits.iter().copied());
+        for i in 0..=300 {
+            assert_eq!(set.rank1(i), bits[..i].iter().filter(|&&b| b).count());
+        }
+        let ones: Vec<usize> = (0..300).filter(|&i| bits[i]).collect();
+        let zeros: Vec<usize> = (0..300).filter(|&i| !bits[i]).collect();
+        for (k, &i) in ones.iter().enumerate() {
+            assert_eq!(set.select1(k), Some(i));
+        }
+        for (k, &i) in zeros.iter().enumerate() {
+            assert_eq!(set.select0(k), Some(i));
+        }
+        assert_eq!(set.select1(ones.len()), None);
+        assert_eq!(set.select0(zeros.len()), None);
+
+        // Changes invalidate the rank counts
+        assert!(set.set(1, true));
+        assert_eq!(set.rank1(300), ones.len() + 1);
+        assert!(!set.set(300, true));
+    }
+}
@@ -0,0 +1,228 @@
+use crate::memory::MemoryReport;
+use wasm_bindgen::prelude::*;
+
+/// Levenshtein distance between two strings, counted in chars.
+pub fn edit_distance(a: &str, b: &str) -> u32 {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<u32> = (0..=b.len() as u32).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i as u32 + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let substitution = diagonal + u32::from(ca != cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j + 1] + 1).min(row[j] + 1);
+        }
+    }
+    row[b.len()]
+}
+
+struct BkNode {
+    word: String,
+    /// Children keyed by their distance from `word`, kept sorted
+    children: Vec<(u32, BkNode)>,
+}
+
+/// Burkhard–Keller tree: a metric tree over words keyed by edit distance.
+///
+/// Each child sits under the edge labelled with its distance to the parent.
+/// By the triangle inequality, a query within `tolerance` of `q` only needs
+/// to descend edges labelled within `tolerance` of `d(q, parent)`, so most
+/// of the tree is never compared against. Unlike the fuzzy
+/// [`Trie`](crate::Trie) walk, it works for any metric, but shares no work
+/// between words with common prefixes.
+///
+/// # Example
+/// ```javascript
+/// const tree = new BkTree();
+/// ["book", "books", "cake", "boo"].forEach(w => tree.add(w));
+/// tree.query("bock", 1); // [{word: "book", distance: 1}]
+/// ```
+#[wasm_bindgen]
+pub struct BkTree {
+    root: Option<BkNode>,
+    size: u32,
+    metrics: BkTreeMetrics,
+}
+
+/// Metrics collected during BK-tree operations
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BkTreeMetrics {
+    pub total_insertions: u32,
+    pub total_queries: u32,
+    /// Edit distances computed across all adds and queries
+    pub distance_computations: u32,
+    /// Subtrees skipped by the triangle inequality during queries
+    pub subtrees_pruned: u32,
+    pub max_depth: u32,
+}
+
+/// A word returned by `BkTree::query`.
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct BkMatch {
+    pub word: String,
+    pub distance: u32,
+}
+
+impl Default for BkTree {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[wasm_bindgen]
+impl BkTree {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> BkTree {
+        BkTree {
+            root: None,
+            size: 0,
+            metrics: BkTreeMetrics::default(),
+        }
+    }
+
+    /// Add a word, returning false if it was already present.
+    pub fn add(&mut self, word: String) -> bool {
+        let Some(mut node) = self.root.as_mut() else {
+            self.root = Some(BkNode {
+                word,
+                children: Vec::new(),
+            });
+            self.size = 1;
+            self.metrics.total_insertions += 1;
+            return true;
+        };
+        let mut depth = 1;
+        loop {
+            self.metrics.distance_computations += 1;
+            let distance = edit_distance(&word, &node.word);
+            if distance == 0 {
+                return false;
+            }
+            match node.children.binary_search_by_key(&distance, |(d, _)| *d) {
+                Ok(i) => {
+                    node = &mut node.children[i].1;
+                    depth += 1;
+                }
+                Err(i) => {
+                    let child = BkNode {
+                        word,
+                        children: Vec::new(),
+                    };
+                    node.children.insert(i, (distance, child));
+                    self.size += 1;
+                    self.metrics.total_insertions += 1;
+                    self.metrics.max_depth = self.metrics.max_depth.max(depth);
+                    return true;
+                }
+            }
+        }
+    }
+
+    /// Every word within `tolerance` edits of `word`, closest first (ties in
+    /// word order).
+    pub fn query(&mut self, word: &str, tolerance: u32) -> Vec<BkMatch> {
+        self.metrics.total_queries += 1;
+        let mut results = Vec::new();
+        let mut stack: Vec<&BkNode> = self.root.iter().collect();
+        while let Some(node) = stack.pop() {
+            self.metrics.distance_computations += 1;
+            let distance = edit_distance(word, &node.word);
+            if distance <= tolerance {
+                results.push(BkMatch {
+                    word: node.word.clone(),
+                    distance,
+                });
+            }
+            let low = distance.saturating_sub(tolerance);
+            let high = distance + tolerance;
+            for (edge, child) in &node.children {
+                if (low..=high).contains(edge) {
+                    stack.push(child);
+                } else {
+                    self.metrics.subtrees_pruned += 1;
+                }
+            }
+        }
+        results.sort_by(|a, b| {
+            a.distance
+                .cmp(&b.distance)
+                .then_with(|| a.word.cmp(&b.word))
+        });
+        results
+    }
+
+    pub fn contains(&mut self, word: &str) -> bool {
+        !self.query(word, 0).is_empty()
+    }
+
+    pub fn get_metrics(&self) -> BkTreeMetrics {
+        self.metrics
+    }
+
+    pub fn len(&self) -> u32 {
+        self.size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    /// Estimate heap usage: each node's child array and word string.
+    pub fn memory_report(&self) -> MemoryReport {
+        let mut report = MemoryReport::default();
+        let mut stack: Vec<&BkNode> = self.root.iter().collect();
+        while let Some(node) = stack.pop() {
+            report.add_string(&node.word, node.word.capacity());
+            report.add_vec(&node.children, node.children.len());
+            stack.extend(node.children.iter().map(|(_, c)| c));
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("flaw", "lawn"), 2);
+        assert_eq!(edit_distance("héllo", "hello"), 1);
+    }
+
+    #[test]
+    fn test_query_matches_brute_force() {
+        let words: Vec<String> = (0..500).map(|i| format!("w{}", i * 37 % 1000)).collect();
+        let mut tree = BkTree::new();
+        for word in &words {
+            tree.add(word.clone());
+        }
+        assert!(!tree.add("w0".to_string()));
+        assert_eq!(tree.len(), 500);
+
+        for query in ["w12", "w999", "x5", "w1234"] {
+            let mut expected: Vec<(u32, &str)> = words
+                .iter()
+                .map(|w| (edit_distance(query, w), w.as_str()))
+                .filter(|(d, _)| *d <= 1)
+                .collect();
+            expected.sort();
+            let found: Vec<(u32, String)> = tree
+                .query(query, 1)
+                .into_iter()
+                .map(|m| (m.distance, m.word))
+                .collect();
+            let expected: Vec<(u32, String)> = expected
+                .into_iter()
+                .map(|(d, w)| (d, w.to_string()))
+                .collect();
+            assert_eq!(found, expected, "{}", query);
+        }
+        assert!(tree.get_metrics().subtrees_pruned > 0);
+    }
+}
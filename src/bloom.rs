@@ -0,0 +1,164 @@
+use crate::bitset::BitSet;
+use crate::hashing::HashFunction;
+use std::f64::consts::LN_2;
+use wasm_bindgen::prelude::*;
+
+/// Most hash functions a filter uses, however low the target rate.
+const MAX_HASHES: u32 = 16;
+
+/// Bits and hash count for a Bloom filter holding `items` keys with the
+/// given false positive rate.
+fn filter_shape(items: usize, false_positive_rate: f64) -> (usize, u32) {
+    let items = items.max(1) as f64;
+    let bits = (-items * false_positive_rate.ln() / (LN_2 * LN_2))
+        .ceil()
+        .max(64.0);
+    let hashes = (bits / items * LN_2).round().clamp(1.0, MAX_HASHES as f64);
+    (bits as usize, hashes as u32)
+}
+
+pub(crate) fn check_false_positive_rate(rate: f64) -> Result<(), String> {
+    if rate > 0.0 && rate < 1.0 {
+        Ok(())
+    } else {
+        Err(format!(
+            "false_positive_rate must be between 0 and 1 exclusive, got {}",
+            rate
+        ))
+    }
+}
+
+/// Approximate set membership in a few bits per key.
+///
+/// The filter is a [`BitSet`] sized for `expected_items` keys at
+/// `false_positive_rate`; each key sets `k` bits chosen by double hashing.
+/// A key with any of its bits clear was certainly never inserted; one with
+/// all of them set probably was. Bits can't be cleared without forgetting
+/// other keys, so there is no delete (see [`CuckooFilter`](crate::CuckooFilter)),
+/// and inserting well past `expected_items` raises the false positive rate.
+///
+/// # Example
+/// ```javascript
+/// const seen = new BloomFilter(100_000, 0.01);
+/// seen.insert(url);
+/// if (!seen.contains(other)) crawl(other); // certainly new
+/// ```
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct BloomFilter {
+    bits: BitSet,
+    hashes: u32,
+    items: usize,
+}
+
+impl BloomFilter {
+    pub fn try_new(expected_items: usize, false_positive_rate: f64) -> Result<BloomFilter, String> {
+        check_false_positive_rate(false_positive_rate)?;
+        let (bits, hashes) = filter_shape(expected_items, false_positive_rate);
+        Ok(BloomFilter {
+            bits: BitSet::new(bits),
+            hashes,
+            items: 0,
+        })
+    }
+
+    /// Bit positions for `key`: `h1 + i * h2` for `i < k`, from the two
+    /// halves of one 64-bit hash.
+    fn positions(&self, key: &str) -> impl Iterator<Item = usize> {
+        let hash = HashFunction::SipHash.hash(key);
+        let (h1, h2) = (hash & 0xffff_ffff, (hash >> 32) | 1);
+        let len = self.bits.len() as u64;
+        (0..self.hashes as u64).map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % len) as usize)
+    }
+
+    /// Whether `key` may have been inserted, and how many bits were checked
+    /// to decide (the lookup stops at the first clear bit).
+    pub(crate) fn probe(&self, key: &str) -> (bool, u32) {
+        let mut probes = 0;
+        for position in self.positions(key) {
+            probes += 1;
+            if !self.bits.get(position) {
+                return (false, probes);
+            }
+        }
+        (true, probes)
+    }
+}
+
+#[wasm_bindgen]
+impl BloomFilter {
+    /// An empty filter for `expected_items` keys at `false_positive_rate`
+    /// (e.g. 0.01).
+    #[wasm_bindgen(constructor)]
+    pub fn new(expected_items: u32, false_positive_rate: f64) -> Result<BloomFilter, JsValue> {
+        Self::try_new(expected_items as usize, false_positive_rate)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    pub fn insert(&mut self, key: &str) {
+        let positions: Vec<usize> = self.positions(key).collect();
+        for position in positions {
+            self.bits.set(position, true);
+        }
+        self.items += 1;
+    }
+
+    /// False means `key` was certainly never inserted.
+    pub fn contains(&self, key: &str) -> bool {
+        self.probe(key).0
+    }
+
+    /// Inserts so far, counting repeats.
+    pub fn len(&self) -> usize {
+        self.items
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items == 0
+    }
+
+    /// Bits in the filter.
+    pub fn bit_count(&self) -> usize {
+        self.bits.len()
+    }
+
+    /// Hash functions (bits set per key).
+    pub fn hash_count(&self) -> u32 {
+        self.hashes
+    }
+
+    /// Bytes of bit storage.
+    pub fn memory_bytes(&self) -> usize {
+        self.bits.len().div_ceil(64) * 8
+    }
+
+    /// False positive rate the current fill predicts:
+    /// (fraction of bits set)^k.
+    pub fn estimated_false_positive_rate(&self) -> f64 {
+        if self.bits.is_empty() {
+            return 1.0;
+        }
+        let fill = self.bits.count_ones() as f64 / self.bits.len() as f64;
+        fill.powi(self.hashes as i32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_no_false_negatives() {
+        let mut filter = BloomFilter::try_new(1000, 0.01).unwrap();
+        for i in 0..1000 {
+            filter.insert(&format!("item{}", i));
+        }
+        assert!((0..1000).all(|i| filter.contains(&format!("item{}", i))));
+        let false_positives = (0..10_000)
+            .filter(|i| filter.contains(&format!("absent{}", i)))
+            .count();
+        assert!(false_positives < 200, "{}", false_positives);
+        assert_eq!(filter_shape(1000, 0.01), (9586, 7));
+        assert!(BloomFilter::try_new(10, 0.0).is_err());
+    }
+}
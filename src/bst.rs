@@ -1,6 +1,18 @@
+use crate::ascii;
+use crate::bulk::BulkInsertJob;
+use crate::csv;
+use crate::health::{AutoTune, HealthReport, Severity, TuningEvent};
+use crate::interop;
+use crate::memory::MemoryReport;
+#[cfg(feature = "msgpack")]
+use crate::msgpack;
 use std::cmp::Ordering;
 use wasm_bindgen::prelude::*;
 
+/// A tree this many times taller than a balanced one is degenerate:
+/// `analyze()` reports it as critical and auto-tuning rebuilds it.
+const DEGENERATE_HEIGHT_FACTOR: u32 = 3;
+
 #[derive(Clone)]
 struct Node {
     key: String,
@@ -21,6 +33,32 @@ pub struct BinarySearchTree {
     root: Option<Box<Node>>,
     size: usize,
     metrics: BSTMetrics,
+    auto_tune: AutoTune,
+}
+
+/// One node visited by a tree lookup, as returned by `path_to`.
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct PathStep {
+    /// Key stored at the visited node.
+    pub key: String,
+    /// Where the lookup went next: `"left"`, `"right"`, or `"found"` if
+    /// this node holds the key.
+    pub direction: String,
+}
+
+impl PathStep {
+    pub(crate) fn new(key: &str, ordering: Ordering) -> PathStep {
+        let direction = match ordering {
+            Ordering::Less => "left",
+            Ordering::Greater => "right",
+            Ordering::Equal => "found",
+        };
+        PathStep {
+            key: key.to_string(),
+            direction: direction.to_string(),
+        }
+    }
 }
 
 #[wasm_bindgen]
@@ -96,6 +134,79 @@ impl BinarySearchTree {
         }
     }
 
+    /// Count an insert and, with auto-tuning on, rebuild the tree once
+    /// its deepest insert shows it has degenerated.
+    fn tune(&mut self) {
+        if !self.auto_tune.tick() || self.size < 8 {
+            return;
+        }
+        // max_depth counts edges, and never shrinks on delete, so this
+        // is an upper bound on the height; rebuilding resets it
+        let optimal = self.size.ilog2() + 1;
+        if self.metrics.max_depth < DEGENERATE_HEIGHT_FACTOR * optimal {
+            return;
+        }
+        let before = self.height();
+        self.rebuild_balanced();
+        let after = self.height();
+        self.metrics.max_depth = after - 1;
+        if before > DEGENERATE_HEIGHT_FACTOR * optimal {
+            self.auto_tune.record(
+                "rebuild",
+                "degenerate_tree",
+                format!("height {} -> {}", before, after),
+            );
+        }
+    }
+
+    /// Replace the tree with a perfectly balanced one holding the same
+    /// entries.
+    fn rebuild_balanced(&mut self) {
+        // Consuming in-order walk
+        let mut entries = Vec::with_capacity(self.size);
+        let mut stack: Vec<Box<Node>> = Vec::new();
+        let mut current = self.root.take();
+        loop {
+            while let Some(mut node) = current {
+                current = node.left.take();
+                stack.push(node);
+            }
+            let Some(mut node) = stack.pop() else {
+                break;
+            };
+            current = node.right.take();
+            let Node { key, value, .. } = *node;
+            entries.push(Some((key, value)));
+        }
+        self.root = Self::build_balanced(&mut entries);
+    }
+
+    /// Subtree of the sorted `entries`, rooted at the middle one.
+    fn build_balanced(entries: &mut [Option<(String, u32)>]) -> Option<Box<Node>> {
+        if entries.is_empty() {
+            return None;
+        }
+        let mid = entries.len() / 2;
+        let (left, rest) = entries.split_at_mut(mid);
+        let (key, value) = rest[0].take().expect("each entry is taken once");
+        Some(Box::new(Node {
+            key,
+            value,
+            left: Self::build_balanced(left),
+            right: Self::build_balanced(&mut rest[1..]),
+        }))
+    }
+
+    /// Unlink the minimum node of a non-empty subtree, returning its entry.
+    fn take_min(node: &mut Option<Box<Node>>) -> (String, u32) {
+        if node.as_ref().is_some_and(|n| n.left.is_some()) {
+            return Self::take_min(&mut node.as_mut().unwrap().left);
+        }
+        let mut min = node.take().expect("take_min on empty subtree");
+        *node = min.right.take();
+        (min.key, min.value)
+    }
+
     fn delete_recursive(node: &mut Option<Box<Node>>, key: &str, metrics: &mut BSTMetrics) -> bool {
         match node {
             None => false,
@@ -119,23 +230,11 @@ impl BinarySearchTree {
                                 true
                             }
                             (Some(_), Some(_)) => {
-                                // Find min in right subtree
-                                let mut current = &mut n.right;
-                                while let Some(ref mut child) = current {
-                                    if child.left.is_none() {
-                                        break;
-                                    }
-                                    current = &mut child.left;
-                                }
-
-                                if let Some(mut right_node) = n.right.take() {
-                                    n.key = right_node.key.clone();
-                                    n.value = right_node.value;
-                                    n.right = right_node.left.take();
-                                    true
-                                } else {
-                                    false
-                                }
+                                // Replace with the in-order successor (min of right subtree)
+                                let (key, value) = Self::take_min(&mut n.right);
+                                n.key = key;
+                                n.value = value;
+                                true
                             }
                         }
                     }
@@ -145,6 +244,12 @@ impl BinarySearchTree {
     }
 }
 
+impl Default for BinarySearchTree {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[wasm_bindgen]
 impl BinarySearchTree {
     #[wasm_bindgen(constructor)]
@@ -158,6 +263,7 @@ impl BinarySearchTree {
                 max_depth: 0,
                 average_depth: 0.0,
             },
+            auto_tune: AutoTune::default(),
         }
     }
 
@@ -167,6 +273,7 @@ impl BinarySearchTree {
             self.metrics.total_insertions += 1;
             self.metrics.average_depth =
                 (self.metrics.total_comparisons as f32) / (self.size as f32);
+            self.tune();
         }
     }
 
@@ -177,6 +284,7 @@ impl BinarySearchTree {
     pub fn delete(&mut self, key: String) -> bool {
         if Self::delete_recursive(&mut self.root, &key, &mut self.metrics) {
             self.size -= 1;
+            self.auto_tune.tick();
             true
         } else {
             false
@@ -187,6 +295,70 @@ impl BinarySearchTree {
         self.metrics
     }
 
+    /// Compare the tree's height with the best possible for its size,
+    /// recommending a balanced tree if sorted inserts have degraded it
+    /// towards a linked list (see [`HealthReport`]).
+    pub fn analyze(&self) -> HealthReport {
+        let mut report = HealthReport::default();
+        if self.size < 8 {
+            return report;
+        }
+        let height = self.height();
+        let optimal = self.size.ilog2() + 1;
+        if height > 2 * optimal {
+            report.add(
+                if height > DEGENERATE_HEIGHT_FACTOR * optimal {
+                    Severity::Critical
+                } else {
+                    Severity::Warning
+                },
+                "degenerate_tree",
+                format!(
+                    "height {} for {} keys; a balanced tree needs {}",
+                    height, self.size, optimal
+                ),
+                "tree degenerate — keys arrive in near-sorted order; use RedBlackTree",
+            );
+        }
+        report
+    }
+
+    /// Turn auto-tuning on or off (off by default). While on, an insert
+    /// that leaves the tree degenerate (see `analyze()`) rebuilds it
+    /// perfectly balanced in O(n), logged to `tuning_log()`.
+    ///
+    /// # Example
+    /// ```javascript
+    /// tree.set_auto_tune(true);
+    /// for (let i = 0; i < 1000; i++) tree.insert(`k${String(i).padStart(4, "0")}`, i);
+    /// tree.height(); // stays within 3x of log2(n)
+    /// ```
+    pub fn set_auto_tune(&mut self, enabled: bool) {
+        self.auto_tune.enabled = enabled;
+    }
+
+    pub fn auto_tune(&self) -> bool {
+        self.auto_tune.enabled
+    }
+
+    /// Actions auto-tuning has taken, oldest first.
+    pub fn tuning_log(&self) -> Vec<TuningEvent> {
+        self.auto_tune.log().to_vec()
+    }
+
+    /// Levels on the longest root-to-leaf path.
+    pub fn height(&self) -> u32 {
+        let mut height = 0;
+        let mut stack: Vec<(&Node, u32)> = self.root.iter().map(|n| (&**n, 1)).collect();
+        while let Some((node, depth)) = stack.pop() {
+            height = height.max(depth);
+            for child in [&node.left, &node.right].into_iter().flatten() {
+                stack.push((child, depth + 1));
+            }
+        }
+        height
+    }
+
     pub fn len(&self) -> usize {
         self.size
     }
@@ -194,6 +366,164 @@ impl BinarySearchTree {
     pub fn is_empty(&self) -> bool {
         self.size == 0
     }
+
+    /// Build a tree from a JS `Map` of string keys to integer values
+    /// (0 to 2^32 - 1). Throws on the first entry that doesn't fit.
+    pub fn from_js_map(map: &js_sys::Map) -> Result<BinarySearchTree, JsValue> {
+        interop::from_map(map, |_| BinarySearchTree::new())
+    }
+
+    /// Build a tree from a plain object's own enumerable properties, as
+    /// `Object.entries` lists them. Values must be integers as for `from_js_map`.
+    pub fn from_object(object: &js_sys::Object) -> Result<BinarySearchTree, JsValue> {
+        interop::from_object(object, |_| BinarySearchTree::new())
+    }
+
+    /// The entries as a new JS `Map`, in key order.
+    pub fn to_js_map(&self) -> js_sys::Map {
+        interop::to_map(self)
+    }
+
+    /// The entries as a new plain object, one property per key.
+    pub fn to_object(&self) -> js_sys::Object {
+        interop::to_object(self)
+    }
+
+    /// Build a tree from CSV text whose first row names the columns,
+    /// taking keys from `key_col` and integer values from `value_col`.
+    /// Quoted fields may contain commas, quotes (as `""`) and line breaks.
+    /// Throws with the line number of the first bad row.
+    pub fn from_csv(
+        text: &str,
+        key_col: &str,
+        value_col: &str,
+    ) -> Result<BinarySearchTree, JsValue> {
+        csv::from_csv(text, key_col, value_col, |_| BinarySearchTree::new())
+    }
+
+    /// The entries as CSV with a `key,value` header row, quoting keys
+    /// where needed.
+    pub fn to_csv(&self) -> String {
+        csv::to_csv(self)
+    }
+
+    /// Build a tree from a MessagePack map of string keys to integers,
+    /// as JS encoders produce from a plain object. Requires the `msgpack`
+    /// feature.
+    #[cfg(feature = "msgpack")]
+    pub fn from_msgpack(bytes: &[u8]) -> Result<BinarySearchTree, JsValue> {
+        msgpack::from_msgpack(bytes, |_| BinarySearchTree::new())
+    }
+
+    /// The entries as a MessagePack map. Requires the `msgpack` feature.
+    #[cfg(feature = "msgpack")]
+    pub fn to_msgpack(&self) -> Vec<u8> {
+        msgpack::to_msgpack(self)
+    }
+
+    /// The tree drawn sideways, root at the left and larger keys above,
+    /// each node as `key (value)`. Meant for small trees in a console.
+    ///
+    /// # Example
+    /// ```javascript
+    /// console.log(tree.to_ascii());
+    /// //     /-- plum (3)
+    /// // pear (1)
+    /// //     \-- apple (2)
+    /// ```
+    pub fn to_ascii(&self) -> String {
+        ascii::binary_tree(
+            self.root.as_deref(),
+            |node| (node.left.as_deref(), node.right.as_deref()),
+            |node| format!("{} ({})", node.key, node.value),
+        )
+    }
+
+    /// Insert queued entries from `job` until `millis` milliseconds have elapsed.
+    ///
+    /// Returns how many entries were inserted; call again (e.g. once per
+    /// animation frame) until `job.is_done()`.
+    pub fn insert_budgeted(&mut self, job: &mut BulkInsertJob, millis: f64) -> u32 {
+        job.run_for(self, millis)
+    }
+
+    /// Estimate heap usage: one boxed node per key plus the key strings.
+    pub fn memory_report(&self) -> MemoryReport {
+        let mut report = MemoryReport::default();
+        Self::measure(&self.root, &mut report);
+        report
+    }
+
+    /// Trim spare capacity from key strings. Nodes are individually boxed,
+    /// so there is no other slack to reclaim.
+    pub fn shrink_to_fit(&mut self) {
+        Self::shrink_keys(&mut self.root);
+    }
+
+    /// Number of edges from the root to `key` (the root is at depth 0).
+    pub fn depth_of(&self, key: &str) -> Option<u32> {
+        let path = self.path_to(key);
+        match path.last() {
+            Some(step) if step.direction == "found" => Some(path.len() as u32 - 1),
+            _ => None,
+        }
+    }
+
+    /// Nodes a lookup for `key` visits, from the root down. If the key is
+    /// absent the path ends at the node whose empty child it would occupy.
+    ///
+    /// Unlike `get`, this doesn't count towards the comparison metrics.
+    pub fn path_to(&self, key: &str) -> Vec<PathStep> {
+        let mut path = Vec::new();
+        let mut node = &self.root;
+        while let Some(n) = node {
+            let ordering = key.cmp(&n.key);
+            path.push(PathStep::new(&n.key, ordering));
+            node = match ordering {
+                Ordering::Less => &n.left,
+                Ordering::Greater => &n.right,
+                Ordering::Equal => break,
+            };
+        }
+        path
+    }
+}
+
+impl BinarySearchTree {
+    fn measure(node: &Option<Box<Node>>, report: &mut MemoryReport) {
+        if let Some(n) = node {
+            report.add_exact(std::mem::size_of::<Node>());
+            report.add_string(&n.key, n.key.capacity());
+            Self::measure(&n.left, report);
+            Self::measure(&n.right, report);
+        }
+    }
+
+    /// Every entry, in key order.
+    pub(crate) fn pairs(&self) -> Vec<(String, u32)> {
+        let mut entries = Vec::with_capacity(self.size);
+        Self::collect_in_order(&self.root, &mut entries);
+        entries
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value))
+            .collect()
+    }
+
+    fn collect_in_order<'a>(node: &'a Option<Box<Node>>, out: &mut Vec<(&'a str, u32)>) {
+        if let Some(n) = node {
+            Self::collect_in_order(&n.left, out);
+            out.push((&n.key, n.value));
+            Self::collect_in_order(&n.right, out);
+        }
+    }
+
+    fn shrink_keys(node: &mut Option<Box<Node>>) {
+        if let Some(n) = node {
+            n.key.shrink_to_fit();
+            Self::shrink_keys(&mut n.left);
+            Self::shrink_keys(&mut n.right);
+        }
+    }
 }
 
 #[cfg(test)]
@@ -239,6 +569,60 @@ mod tests {
         assert_eq!(tree.get("hello".to_string()), None);
     }
 
+    #[test]
+    fn test_bst_delete_node_with_two_children() {
+        let mut tree = BinarySearchTree::new();
+        for key in ["m", "d", "t", "p", "w", "r", "s"] {
+            tree.insert(key.to_string(), key.as_bytes()[0] as u32);
+        }
+        assert!(tree.delete("m".to_string()));
+        assert_eq!(tree.len(), 6);
+        for key in ["d", "t", "p", "w", "r", "s"] {
+            assert_eq!(tree.get(key.to_string()), Some(key.as_bytes()[0] as u32));
+        }
+    }
+
+    #[test]
+    fn test_bst_delete_successor_keeps_its_right_subtree() {
+        let mut tree = BinarySearchTree::new();
+        // "m"'s successor "p" has a right subtree r -> (q, s)
+        for key in ["m", "d", "t", "p", "w", "r", "q", "s"] {
+            tree.insert(key.to_string(), 0);
+        }
+        assert!(tree.delete("m".to_string()));
+        let keys: Vec<_> = tree.pairs().into_iter().map(|(k, _)| k).collect();
+        assert_eq!(keys, ["d", "p", "q", "r", "s", "t", "w"]);
+        assert_eq!(tree.root.as_ref().map(|n| n.key.as_str()), Some("p"));
+    }
+
+    #[test]
+    fn test_bst_path_to() {
+        let mut tree = BinarySearchTree::new();
+        for key in ["m", "d", "t", "p"] {
+            tree.insert(key.to_string(), 0);
+        }
+        let steps: Vec<_> = tree
+            .path_to("p")
+            .into_iter()
+            .map(|s| (s.key, s.direction))
+            .collect();
+        assert_eq!(
+            steps,
+            [
+                ("m".to_string(), "right".to_string()),
+                ("t".to_string(), "left".to_string()),
+                ("p".to_string(), "found".to_string()),
+            ]
+        );
+        assert_eq!(tree.depth_of("m"), Some(0));
+        assert_eq!(tree.depth_of("p"), Some(2));
+        assert_eq!(tree.depth_of("q"), None);
+        assert_eq!(tree.path_to("q").last().unwrap().key, "p");
+        assert!(BinarySearchTree::new().path_to("x").is_empty());
+        // Only the inserts compared keys
+        assert_eq!(tree.get_metrics().total_comparisons, 4);
+    }
+
     #[test]
     fn test_bst_update() {
         let mut tree = BinarySearchTree::new();
@@ -0,0 +1,553 @@
+use crate::capacity::CapacityConfig;
+use crate::chain::BucketMode;
+use crate::hashing::HashFunction;
+use crate::interop::OptionValue;
+use crate::{HashMap, OpenAddressingHashTable, SkipList};
+use wasm_bindgen::prelude::*;
+
+/// How much bookkeeping a structure does on each write.
+///
+/// `Full` keeps every metric current, including aggregate gauges that need a
+/// scan of the whole structure (max chain length, clustering factor, average
+/// skip list level). `Counters` only maintains the O(1) counters, so those
+/// gauges read as 0 — use it when timing large loads.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MetricsMode {
+    #[default]
+    Full,
+    Counters,
+}
+
+/// How [`HashMap::resize`] moves entries into the new bucket array.
+///
+/// `AllAtOnce` rehashes everything inside the `resize` call. `Incremental`
+/// keeps the old and new arrays side by side and moves a few buckets on each
+/// later insert or delete, spreading the cost so no single operation stalls.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RehashMode {
+    #[default]
+    AllAtOnce,
+    Incremental,
+}
+
+/// Fluent configuration for [`HashMap`].
+///
+/// # Example
+/// ```javascript
+/// const map = new HashMapBuilder().bucket_count(1024).metrics_mode(MetricsMode.Counters).build();
+/// const sorted = new HashMapBuilder().bucket_mode(BucketMode.SortedVec).build();
+/// ```
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug)]
+pub struct HashMapBuilder {
+    bucket_count: u32,
+    bucket_mode: BucketMode,
+    rehash_mode: RehashMode,
+    metrics_mode: MetricsMode,
+    hash_function: HashFunction,
+    growth: Option<CapacityConfig>,
+}
+
+impl Default for HashMapBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Properties accepted by [`HashMap::with_options`].
+const HASHMAP_OPTIONS: [&str; 6] = [
+    "bucket_count",
+    "growth_factor",
+    "max_load_factor",
+    "min_load_factor",
+    "max_bucket_count",
+    "shrink_on_delete",
+];
+
+impl HashMapBuilder {
+    /// Settings from an options object (see [`HashMap::with_options`]).
+    /// Unknown names and mistyped values are rejected rather than ignored,
+    /// so a typo can't silently fall back to a default.
+    pub(crate) fn try_from_options(
+        options: &[(String, OptionValue)],
+    ) -> Result<HashMapBuilder, String> {
+        let mut builder = HashMapBuilder::new();
+        let mut config = CapacityConfig::new();
+        // Any option besides the bucket count turns on a growth policy
+        let mut grows = false;
+        for (name, value) in options {
+            let number = || match *value {
+                OptionValue::Number(n) => Ok(n),
+                _ => Err(format!("option '{}' must be a number", name)),
+            };
+            let count = || {
+                let n = number()?;
+                if n.fract() == 0.0 && (0.0..=u32::MAX as f64).contains(&n) {
+                    Ok(n as u32)
+                } else {
+                    Err(format!(
+                        "option '{}' must be a whole number (got {})",
+                        name, n
+                    ))
+                }
+            };
+            match name.as_str() {
+                "bucket_count" => builder.bucket_count = count()?,
+                "growth_factor" => config = config.with_growth_factor(number()?),
+                "max_load_factor" => config = config.with_max_load_factor(number()?),
+                "min_load_factor" => config = config.with_min_load_factor(number()?),
+                "max_bucket_count" => config = config.with_max_capacity(count()?),
+                "shrink_on_delete" => match *value {
+                    OptionValue::Bool(b) => config = config.with_shrink_on_delete(b),
+                    _ => return Err("option 'shrink_on_delete' must be a boolean".to_string()),
+                },
+                _ => {
+                    return Err(format!(
+                        "unknown option '{}' (expected one of: {})",
+                        name,
+                        HASHMAP_OPTIONS.join(", ")
+                    ))
+                }
+            }
+            grows |= name != "bucket_count";
+        }
+        if grows {
+            // The starting bucket count is also the floor for shrinking
+            builder.growth = Some(config.with_min_capacity(builder.bucket_count.max(1)));
+        }
+        Ok(builder)
+    }
+
+    pub fn try_build(&self) -> Result<HashMap, String> {
+        if self.bucket_count == 0 {
+            return Err("bucket_count must be at least 1".to_string());
+        }
+        let mut bucket_count = self.bucket_count;
+        if let Some(growth) = self.growth {
+            growth.validate()?;
+            bucket_count = growth.initial_capacity(bucket_count);
+        }
+        let mut map =
+            HashMap::with_layout(bucket_count as usize, self.bucket_mode, self.metrics_mode);
+        map.set_rehash_mode(self.rehash_mode);
+        map.set_hash_function(self.hash_function);
+        if let Some(growth) = self.growth {
+            map.set_growth(growth);
+        }
+        Ok(map)
+    }
+}
+
+#[wasm_bindgen]
+impl HashMapBuilder {
+    /// Starts from the same settings as `new HashMap()`.
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> HashMapBuilder {
+        HashMapBuilder {
+            bucket_count: crate::BUCKET_COUNT as u32,
+            bucket_mode: BucketMode::Vec,
+            rehash_mode: RehashMode::AllAtOnce,
+            metrics_mode: MetricsMode::Full,
+            hash_function: HashFunction::SipHash,
+            growth: None,
+        }
+    }
+
+    /// Number of chains (default 256). With a growth policy this is the
+    /// starting count, clamped to the policy's min and max capacity.
+    pub fn bucket_count(mut self, buckets: u32) -> HashMapBuilder {
+        self.bucket_count = buckets;
+        self
+    }
+
+    /// How each bucket stores its chain (default `Vec`).
+    pub fn bucket_mode(mut self, mode: BucketMode) -> HashMapBuilder {
+        self.bucket_mode = mode;
+        self
+    }
+
+    /// Resize automatically as the load factor crosses `config`'s limits
+    /// (default: never resize). Growth uses the configured rehash mode.
+    pub fn growth(mut self, config: &CapacityConfig) -> HashMapBuilder {
+        self.growth = Some(*config);
+        self
+    }
+
+    /// How `resize` moves entries (default `AllAtOnce`).
+    pub fn rehash_mode(mut self, mode: RehashMode) -> HashMapBuilder {
+        self.rehash_mode = mode;
+        self
+    }
+
+    pub fn metrics_mode(mut self, mode: MetricsMode) -> HashMapBuilder {
+        self.metrics_mode = mode;
+        self
+    }
+
+    /// Hash applied to keys (default `SipHash`). The others force
+    /// collisions for demos and tests.
+    pub fn hash_function(mut self, hash_function: HashFunction) -> HashMapBuilder {
+        self.hash_function = hash_function;
+        self
+    }
+
+    pub fn build(&self) -> Result<HashMap, JsValue> {
+        self.try_build().map_err(|e| JsValue::from_str(&e))
+    }
+}
+
+/// Fluent configuration for [`OpenAddressingHashTable`].
+///
+/// Without a growth policy the table has a fixed capacity, like
+/// `new OpenAddressingHashTable(capacity)`.
+///
+/// # Example
+/// ```javascript
+/// const table = new OpenAddressingBuilder()
+///     .capacity(1024)
+///     .growth(new CapacityConfig().with_max_load_factor(0.5))
+///     .build();
+/// ```
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug)]
+pub struct OpenAddressingBuilder {
+    capacity: u32,
+    growth: Option<CapacityConfig>,
+    metrics_mode: MetricsMode,
+    hash_function: HashFunction,
+}
+
+impl Default for OpenAddressingBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OpenAddressingBuilder {
+    pub fn try_build(&self) -> Result<OpenAddressingHashTable, String> {
+        if self.capacity == 0 {
+            return Err("capacity must be at least 1".to_string());
+        }
+        let config = match self.growth {
+            None => CapacityConfig::fixed(self.capacity),
+            Some(config) => {
+                config.validate()?;
+                if config.initial_capacity(self.capacity) != self.capacity {
+                    return Err(format!(
+                        "capacity {} is outside the growth policy's range [{}, {}]",
+                        self.capacity,
+                        config.min_capacity(),
+                        config.max_capacity()
+                    ));
+                }
+                config
+            }
+        };
+        let mut table = OpenAddressingHashTable::with_capacity_config(self.capacity, config);
+        table.set_metrics_mode(self.metrics_mode);
+        table.set_hash_function(self.hash_function);
+        Ok(table)
+    }
+}
+
+#[wasm_bindgen]
+impl OpenAddressingBuilder {
+    /// Defaults to a fixed table of 256 slots.
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> OpenAddressingBuilder {
+        OpenAddressingBuilder {
+            capacity: 256,
+            growth: None,
+            metrics_mode: MetricsMode::Full,
+            hash_function: HashFunction::SipHash,
+        }
+    }
+
+    /// Starting number of slots.
+    pub fn capacity(mut self, capacity: u32) -> OpenAddressingBuilder {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Let the table resize according to `config`.
+    pub fn growth(mut self, config: &CapacityConfig) -> OpenAddressingBuilder {
+        self.growth = Some(*config);
+        self
+    }
+
+    pub fn metrics_mode(mut self, mode: MetricsMode) -> OpenAddressingBuilder {
+        self.metrics_mode = mode;
+        self
+    }
+
+    /// Hash applied to keys (default `SipHash`). The others force
+    /// collisions and clustering for demos and tests.
+    pub fn hash_function(mut self, hash_function: HashFunction) -> OpenAddressingBuilder {
+        self.hash_function = hash_function;
+        self
+    }
+
+    pub fn build(&self) -> Result<OpenAddressingHashTable, JsValue> {
+        self.try_build().map_err(|e| JsValue::from_str(&e))
+    }
+}
+
+/// Fluent configuration for [`SkipList`].
+///
+/// Setting a seed makes node levels, and therefore the list's shape and
+/// search costs, reproducible across runs.
+///
+/// # Example
+/// ```javascript
+/// const list = new SkipListBuilder().max_level(8).probability(0.25).seed(42).build();
+/// ```
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug)]
+pub struct SkipListBuilder {
+    max_level: u32,
+    probability: f32,
+    seed: Option<u64>,
+    metrics_mode: MetricsMode,
+}
+
+impl Default for SkipListBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SkipListBuilder {
+    /// Levels above this would make each node's pointer tower absurdly tall.
+    const LEVEL_LIMIT: u32 = 32;
+
+    pub fn try_build(&self) -> Result<SkipList, String> {
+        if self.max_level == 0 || self.max_level > Self::LEVEL_LIMIT {
+            return Err(format!(
+                "max_level must be between 1 and {} (got {})",
+                Self::LEVEL_LIMIT,
+                self.max_level
+            ));
+        }
+        if !(self.probability > 0.0 && self.probability < 1.0) {
+            return Err(format!(
+                "probability must be in (0, 1) (got {})",
+                self.probability
+            ));
+        }
+        Ok(SkipList::with_options(
+            self.max_level as usize,
+            self.probability,
+            self.seed,
+            self.metrics_mode,
+        ))
+    }
+}
+
+#[wasm_bindgen]
+impl SkipListBuilder {
+    /// Starts from the same settings as `new SkipList()`.
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> SkipListBuilder {
+        SkipListBuilder {
+            max_level: crate::skip_list::MAX_LEVEL as u32,
+            probability: crate::skip_list::LEVEL_PROBABILITY,
+            seed: None,
+            metrics_mode: MetricsMode::Full,
+        }
+    }
+
+    /// Highest level a node can be promoted to (default 16, at most 32).
+    pub fn max_level(mut self, level: u32) -> SkipListBuilder {
+        self.max_level = level;
+        self
+    }
+
+    /// Chance of promoting a node one more level (default 0.5).
+    pub fn probability(mut self, p: f32) -> SkipListBuilder {
+        self.probability = p;
+        self
+    }
+
+    /// Seed for level selection; unseeded lists draw one from
+    /// [`entropy_seed`](crate::rng::entropy_seed).
+    pub fn seed(mut self, seed: u32) -> SkipListBuilder {
+        self.seed = Some(u64::from(seed));
+        self
+    }
+
+    /// Full 64-bit seed (a `BigInt` in JS), e.g. one read back from
+    /// `SkipList.seed()` to replay an entropy-seeded list.
+    pub fn seed_u64(mut self, seed: u64) -> SkipListBuilder {
+        self.seed = Some(seed);
+        self
+    }
+
+    pub fn metrics_mode(mut self, mode: MetricsMode) -> SkipListBuilder {
+        self.metrics_mode = mode;
+        self
+    }
+
+    pub fn build(&self) -> Result<SkipList, JsValue> {
+        self.try_build().map_err(|e| JsValue::from_str(&e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hashmap_builder() {
+        let mut map = HashMapBuilder::new().bucket_count(16).try_build().unwrap();
+        for i in 0..64 {
+            map.insert(format!("k{}", i), i);
+        }
+        assert_eq!(map.bucket_count(), 16);
+        assert_eq!(map.get_metrics().average_load_factor, 4.0);
+        assert!(map.get_metrics().max_chain_length >= 4);
+        assert!(HashMapBuilder::new().bucket_count(0).try_build().is_err());
+    }
+
+    #[test]
+    fn test_hashmap_options() {
+        let option = |name: &str, value| (name.to_string(), value);
+        let fixed =
+            HashMapBuilder::try_from_options(&[option("bucket_count", OptionValue::Number(64.0))])
+                .unwrap();
+        assert_eq!(fixed.bucket_count, 64);
+        assert!(fixed.growth.is_none());
+
+        // 1.5x growth resizes more often than the default 2x
+        let mut counts = Vec::new();
+        for factor in [2.0, 1.5] {
+            let builder = HashMapBuilder::try_from_options(&[
+                option("growth_factor", OptionValue::Number(factor)),
+                option("bucket_count", OptionValue::Number(16.0)),
+            ])
+            .unwrap();
+            let mut map = builder.try_build().unwrap();
+            for i in 0..1_000 {
+                map.insert(format!("k{}", i), i);
+            }
+            assert!(map.get_metrics().average_load_factor <= 0.75);
+            assert_eq!(map.capacity_config().unwrap().min_capacity(), 16);
+            counts.push(map.get_metrics().rehash_count);
+        }
+        assert!(counts[1] > counts[0], "{:?}", counts);
+
+        for (options, message) in [
+            (
+                vec![option("bucket_cnt", OptionValue::Number(8.0))],
+                "unknown option",
+            ),
+            (
+                vec![option("bucket_count", OptionValue::Number(1.5))],
+                "whole number",
+            ),
+            (
+                vec![option("growth_factor", OptionValue::Other)],
+                "must be a number",
+            ),
+            (
+                vec![option("shrink_on_delete", OptionValue::Number(1.0))],
+                "boolean",
+            ),
+        ] {
+            let err = HashMapBuilder::try_from_options(&options).unwrap_err();
+            assert!(err.contains(message), "{}", err);
+        }
+        let invalid =
+            HashMapBuilder::try_from_options(&[option("growth_factor", OptionValue::Number(0.5))])
+                .unwrap();
+        assert!(invalid.try_build().err().unwrap().contains("growth_factor"));
+    }
+
+    #[test]
+    fn test_counters_mode_skips_gauges() {
+        let mut map = HashMapBuilder::new()
+            .metrics_mode(MetricsMode::Counters)
+            .try_build()
+            .unwrap();
+        let mut table = OpenAddressingBuilder::new()
+            .metrics_mode(MetricsMode::Counters)
+            .try_build()
+            .unwrap();
+        for i in 0..100 {
+            map.insert(format!("k{}", i), i);
+            table.try_insert(format!("k{}", i), i).unwrap();
+        }
+        assert_eq!(map.get_metrics().total_insertions, 100);
+        assert_eq!(map.get_metrics().max_chain_length, 0);
+        assert_eq!(table.get_metrics().total_insertions, 100);
+        assert_eq!(table.get_metrics().clustering_factor, 0.0);
+    }
+
+    #[test]
+    fn test_open_addressing_builder_validation() {
+        let grow = CapacityConfig::new().with_min_capacity(64);
+        assert!(OpenAddressingBuilder::new()
+            .capacity(0)
+            .try_build()
+            .is_err());
+        assert!(OpenAddressingBuilder::new()
+            .capacity(32)
+            .growth(&grow)
+            .try_build()
+            .is_err());
+        let mut table = OpenAddressingBuilder::new()
+            .capacity(64)
+            .growth(&grow)
+            .try_build()
+            .unwrap();
+        for i in 0..200 {
+            table.try_insert(format!("k{}", i), i).unwrap();
+        }
+        assert!(table.capacity() > 64);
+    }
+
+    #[test]
+    fn test_seeded_skip_lists_are_identical() {
+        let build = || {
+            let mut list = SkipListBuilder::new()
+                .seed(7)
+                .max_level(6)
+                .try_build()
+                .unwrap();
+            for i in 0..200 {
+                list.insert(format!("k{:03}", i), i);
+            }
+            list.get_metrics()
+        };
+        let (a, b) = (build(), build());
+        assert_eq!(a.average_level, b.average_level);
+        assert!(a.max_level <= 6);
+    }
+
+    #[test]
+    fn test_entropy_seed_replays() {
+        let fill = |list: &mut SkipList| {
+            for i in 0..200 {
+                list.insert(format!("k{:03}", i), i);
+            }
+            list.get_metrics().average_level
+        };
+        let mut original = SkipList::new();
+        let level = fill(&mut original);
+        let mut replay = SkipListBuilder::new()
+            .seed_u64(original.seed())
+            .try_build()
+            .unwrap();
+        assert_eq!(fill(&mut replay), level);
+        assert_eq!(replay.seed(), original.seed());
+    }
+
+    #[test]
+    fn test_skip_list_builder_validation() {
+        assert!(SkipListBuilder::new().max_level(0).try_build().is_err());
+        assert!(SkipListBuilder::new().max_level(33).try_build().is_err());
+        assert!(SkipListBuilder::new().probability(1.0).try_build().is_err());
+        assert!(SkipListBuilder::new().probability(0.0).try_build().is_err());
+    }
+}
@@ -0,0 +1,286 @@
+use crate::cancel::CancellationToken;
+use crate::clock::now_ms;
+use crate::kv_store::KvStore;
+use crate::progress::ProgressHook;
+use js_sys::Function;
+use wasm_bindgen::prelude::*;
+
+/// How many inserts run between clock reads. Reading the clock crosses into JS
+/// under wasm, so checking after every insert would dominate the cost.
+const CLOCK_CHECK_INTERVAL: usize = 64;
+
+/// A resumable queue of inserts that can be drained in time-budgeted slices.
+///
+/// Loading 500k entries in one call freezes the main thread for seconds.
+/// Instead, queue them in a job and feed it to a structure a few milliseconds
+/// per animation frame until it reports done.
+///
+/// # Example
+/// ```javascript
+/// const job = new BulkInsertJob(keys, new Uint32Array(values));
+/// const map = new HashMap();
+/// function step() {
+///     map.insert_budgeted(job, 8);
+///     progressBar.value = job.progress();
+///     if (!job.is_done()) requestAnimationFrame(step);
+/// }
+/// requestAnimationFrame(step);
+/// ```
+#[wasm_bindgen]
+pub struct BulkInsertJob {
+    entries: Vec<(String, u32)>,
+    total: usize,
+    cursor: usize,
+    elapsed_ms: f64,
+    slices: u32,
+    progress: ProgressHook,
+    cancel: Option<CancellationToken>,
+}
+
+impl BulkInsertJob {
+    /// Build a job from owned entries.
+    pub fn from_entries(entries: Vec<(String, u32)>) -> BulkInsertJob {
+        BulkInsertJob {
+            total: entries.len(),
+            entries,
+            cursor: 0,
+            elapsed_ms: 0.0,
+            slices: 0,
+            progress: ProgressHook::none(),
+            cancel: None,
+        }
+    }
+
+    /// Build a job from parallel key/value arrays.
+    pub fn try_new(keys: Vec<String>, values: Vec<u32>) -> Result<BulkInsertJob, String> {
+        if keys.len() != values.len() {
+            return Err(format!(
+                "keys and values differ in length ({} vs {})",
+                keys.len(),
+                values.len()
+            ));
+        }
+        Ok(Self::from_entries(keys.into_iter().zip(values).collect()))
+    }
+
+    /// Report progress through `hook` as entries are inserted.
+    pub fn set_progress_hook(&mut self, hook: ProgressHook) {
+        self.progress = hook;
+    }
+
+    /// Insert queued entries into `store` until `budget_ms` has elapsed.
+    ///
+    /// At least one chunk of entries is processed per call so a job always
+    /// makes progress, even with a zero budget, unless it has been cancelled.
+    /// Returns the number inserted.
+    pub fn run_for(&mut self, store: &mut dyn KvStore, budget_ms: f64) -> u32 {
+        let start = now_ms();
+        let before = self.cursor;
+
+        while self.cursor < self.total && !self.is_cancelled() {
+            let chunk_end = (self.cursor + CLOCK_CHECK_INTERVAL).min(self.total);
+            // Keys move into the store, leaving empty strings behind
+            for (key, value) in &mut self.entries[self.cursor..chunk_end] {
+                store.kv_insert(std::mem::take(key), *value);
+            }
+            self.cursor = chunk_end;
+            let spent = now_ms() - start;
+            self.progress.update(
+                self.cursor as u32,
+                Some(self.total as u32),
+                self.elapsed_ms + spent,
+            );
+            if spent >= budget_ms {
+                break;
+            }
+        }
+
+        if self.cursor > before {
+            self.slices += 1;
+        }
+        self.elapsed_ms += now_ms() - start;
+        if self.is_done() {
+            // Only emptied slots are left once everything has been inserted.
+            self.entries = Vec::new();
+        }
+        (self.cursor - before) as u32
+    }
+}
+
+#[wasm_bindgen]
+impl BulkInsertJob {
+    /// Queue `keys[i] -> values[i]` for budgeted insertion.
+    #[wasm_bindgen(constructor)]
+    pub fn new(keys: Vec<String>, values: Vec<u32>) -> Result<BulkInsertJob, JsValue> {
+        Self::try_new(keys, values).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// Call `callback({ processed, total, elapsed_ms })` roughly every `every`
+    /// inserts (0 means 1000) and once on completion. Pass `undefined` to stop.
+    ///
+    /// Reports are checked between chunks of 64 inserts, so smaller intervals
+    /// are rounded up to that.
+    pub fn set_progress_callback(&mut self, callback: Option<Function>, every: u32) {
+        self.progress = ProgressHook::from_js(callback, every);
+    }
+
+    /// Stop the job once `token` is cancelled. Entries already inserted stay
+    /// in the structure; `processed()` tells how many that was.
+    pub fn set_cancellation_token(&mut self, token: &CancellationToken) {
+        self.cancel = Some(token.handle());
+    }
+
+    /// Whether the job's cancellation token has been cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.as_ref().is_some_and(|t| t.is_cancelled())
+    }
+
+    /// Total number of entries in the job.
+    pub fn total(&self) -> u32 {
+        self.total as u32
+    }
+
+    /// Entries inserted so far.
+    pub fn processed(&self) -> u32 {
+        self.cursor as u32
+    }
+
+    /// Entries still queued.
+    pub fn remaining(&self) -> u32 {
+        (self.total - self.cursor) as u32
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.cursor >= self.total
+    }
+
+    /// Fraction complete in `[0, 1]`.
+    pub fn progress(&self) -> f64 {
+        if self.total == 0 {
+            1.0
+        } else {
+            self.cursor as f64 / self.total as f64
+        }
+    }
+
+    /// Wall-clock time spent inserting across all slices.
+    pub fn elapsed_ms(&self) -> f64 {
+        self.elapsed_ms
+    }
+
+    /// Number of slices that made progress.
+    pub fn slices(&self) -> u32 {
+        self.slices
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kv_store::{new_store, STORE_KINDS};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    fn job(n: u32) -> BulkInsertJob {
+        BulkInsertJob::from_entries((0..n).map(|i| (format!("key{}", i), i)).collect())
+    }
+
+    #[test]
+    fn test_zero_budget_still_progresses() {
+        let mut store = new_store("hashmap", 0).unwrap();
+        let mut job = job(1_000);
+        let inserted = job.run_for(store.as_mut(), 0.0);
+        assert_eq!(inserted as usize, CLOCK_CHECK_INTERVAL);
+        assert_eq!(job.processed(), inserted);
+        assert!(!job.is_done());
+        assert_eq!(job.slices(), 1);
+    }
+
+    #[test]
+    fn test_job_completes_in_slices_on_every_structure() {
+        for kind in STORE_KINDS {
+            let mut store = new_store(kind, 500).unwrap();
+            let mut job = job(500);
+            let mut calls = 0;
+            while !job.is_done() {
+                job.run_for(store.as_mut(), 0.0);
+                calls += 1;
+            }
+            assert_eq!(calls, 500_usize.div_ceil(CLOCK_CHECK_INTERVAL), "{}", kind);
+            assert_eq!(store.kv_len(), 500, "{}", kind);
+            assert_eq!(store.kv_get("key499"), Some(499), "{}", kind);
+            assert_eq!(job.total(), 500);
+            assert_eq!(job.remaining(), 0);
+            assert_eq!(job.progress(), 1.0);
+        }
+    }
+
+    #[test]
+    fn test_keys_move_into_the_store() {
+        let mut store = new_store("bst", 0).unwrap();
+        let mut job = job(1_000);
+        job.run_for(store.as_mut(), 0.0);
+        let (moved, queued) = job.entries.split_at(job.cursor);
+        assert!(moved.iter().all(|(key, _)| key.capacity() == 0));
+        assert!(queued.iter().all(|(key, _)| key.starts_with("key")));
+        assert_eq!(store.kv_get("key0"), Some(0));
+
+        while !job.is_done() {
+            job.run_for(store.as_mut(), 0.0);
+        }
+        assert_eq!(job.entries.capacity(), 0);
+        assert_eq!(store.kv_len(), 1_000);
+    }
+
+    #[test]
+    fn test_generous_budget_finishes_in_one_slice() {
+        let mut store = new_store("trie", 0).unwrap();
+        let mut job = job(300);
+        assert_eq!(job.run_for(store.as_mut(), 60_000.0), 300);
+        assert!(job.is_done());
+        // Further calls are no-ops
+        assert_eq!(job.run_for(store.as_mut(), 10.0), 0);
+        assert_eq!(job.slices(), 1);
+    }
+
+    #[test]
+    fn test_progress_hook_reports_across_slices() {
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let sink = Rc::clone(&seen);
+        let mut store = new_store("skiplist", 0).unwrap();
+        let mut job = job(300);
+        job.set_progress_hook(ProgressHook::new(100, move |p| {
+            sink.borrow_mut().push((p.processed, p.total))
+        }));
+        while !job.is_done() {
+            job.run_for(store.as_mut(), 0.0);
+        }
+        assert_eq!(
+            *seen.borrow(),
+            vec![(128, Some(300)), (256, Some(300)), (300, Some(300))]
+        );
+    }
+
+    #[test]
+    fn test_cancelled_job_stops_with_partial_results() {
+        let token = CancellationToken::new();
+        let mut store = new_store("bst", 0).unwrap();
+        let mut job = job(1_000);
+        job.set_cancellation_token(&token);
+        job.run_for(store.as_mut(), 0.0);
+        token.cancel();
+        assert!(job.is_cancelled());
+        assert_eq!(job.run_for(store.as_mut(), 60_000.0), 0);
+        assert!(!job.is_done());
+        assert_eq!(job.processed() as usize, CLOCK_CHECK_INTERVAL);
+        assert_eq!(store.kv_len(), CLOCK_CHECK_INTERVAL);
+    }
+
+    #[test]
+    fn test_mismatched_lengths_rejected() {
+        assert!(BulkInsertJob::try_new(vec!["a".to_string()], vec![]).is_err());
+        let empty = BulkInsertJob::try_new(vec![], vec![]).unwrap();
+        assert!(empty.is_done());
+        assert_eq!(empty.progress(), 1.0);
+    }
+}
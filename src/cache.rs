@@ -0,0 +1,401 @@
+use std::collections::HashMap;
+use wasm_bindgen::prelude::*;
+
+const NIL: usize = usize::MAX;
+
+struct Slot {
+    key: String,
+    value: u32,
+    weight: u32,
+    prev: usize,
+    next: usize,
+}
+
+/// Lookups and fills for entries of one size class, `min_bytes` up to
+/// (not including) `max_bytes`.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SizeClassStats {
+    pub min_bytes: u32,
+    pub max_bytes: u64,
+    pub hits: u64,
+    /// Entries of this size inserted while absent; every fill answers a miss
+    pub fills: u64,
+    pub evictions: u64,
+}
+
+#[wasm_bindgen]
+impl SizeClassStats {
+    /// hits / (hits + fills), 0.0 before any traffic.
+    pub fn hit_rate(&self) -> f64 {
+        let total = self.hits + self.fills;
+        if total == 0 {
+            0.0
+        } else {
+            self.hits as f64 / total as f64
+        }
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct WeightedLruMetrics {
+    pub hits: u64,
+    pub misses: u64,
+    pub hit_bytes: u64,
+    /// Bytes of new entries inserted while absent
+    pub fill_bytes: u64,
+    pub evictions: u64,
+    pub bytes_evicted: u64,
+    /// Inserts refused because the entry alone exceeds the budget
+    pub rejected: u64,
+}
+
+#[wasm_bindgen]
+impl WeightedLruMetrics {
+    /// Fraction of lookups that hit, 0.0 before any lookup.
+    pub fn hit_rate(&self) -> f64 {
+        let total = self.hits + self.misses;
+        if total == 0 {
+            0.0
+        } else {
+            self.hits as f64 / total as f64
+        }
+    }
+
+    /// Fraction of bytes served from the cache rather than filled into it,
+    /// the figure a browser cache reports as its byte hit rate.
+    pub fn byte_hit_rate(&self) -> f64 {
+        let total = self.hit_bytes + self.fill_bytes;
+        if total == 0 {
+            0.0
+        } else {
+            self.hit_bytes as f64 / total as f64
+        }
+    }
+}
+
+/// Size class of an entry: 0 holds weights 0 and 1, class `c` holds
+/// `2^c..2^(c+1)`.
+fn size_class(weight: u32) -> usize {
+    (u32::BITS - 1).saturating_sub(weight.max(1).leading_zeros()) as usize
+}
+
+/// Least-recently-used cache where each entry declares its size in bytes
+/// and the cache holds at most `budget_bytes` in total, the way HTTP and
+/// browser caches bound themselves by storage rather than entry count.
+///
+/// An insert evicts least recently used entries until the new entry fits.
+/// An entry larger than the whole budget is refused (and any older entry
+/// under that key dropped) instead of flushing the cache for nothing.
+///
+/// Hit rates are also kept per power-of-two size class: small hot entries
+/// and large rarely reused ones usually behave very differently. A lookup
+/// that misses doesn't know the entry's size, so per-class hit rates count
+/// the fill that follows a miss instead.
+///
+/// # Example
+/// ```javascript
+/// const cache = new WeightedLruCache(1 << 20); // 1 MiB
+/// cache.insert("/app.js", 1, 300_000);
+/// cache.insert("/hero.png", 2, 800_000); // evicts /app.js
+/// cache.metrics().bytes_evicted; // 300000
+/// for (const c of cache.hit_rate_by_size()) console.log(c.min_bytes, c.hit_rate());
+/// ```
+#[wasm_bindgen]
+pub struct WeightedLruCache {
+    budget: u64,
+    total: u64,
+    index: HashMap<String, usize>,
+    slots: Vec<Slot>,
+    free: Vec<usize>,
+    /// Most recently used
+    head: usize,
+    /// Least recently used, the next to evict
+    tail: usize,
+    metrics: WeightedLruMetrics,
+    classes: Vec<SizeClassStats>,
+}
+
+impl WeightedLruCache {
+    fn unlink(&mut self, i: usize) {
+        let (prev, next) = (self.slots[i].prev, self.slots[i].next);
+        match prev {
+            NIL => self.head = next,
+            p => self.slots[p].next = next,
+        }
+        match next {
+            NIL => self.tail = prev,
+            n => self.slots[n].prev = prev,
+        }
+    }
+
+    fn push_front(&mut self, i: usize) {
+        self.slots[i].prev = NIL;
+        self.slots[i].next = self.head;
+        match self.head {
+            NIL => self.tail = i,
+            h => self.slots[h].prev = i,
+        }
+        self.head = i;
+    }
+
+    fn remove_slot(&mut self, i: usize) {
+        self.unlink(i);
+        let key = std::mem::take(&mut self.slots[i].key);
+        self.index.remove(&key);
+        self.total -= self.slots[i].weight as u64;
+        self.free.push(i);
+    }
+
+    fn class_mut(&mut self, weight: u32) -> &mut SizeClassStats {
+        let class = size_class(weight);
+        if self.classes.len() <= class {
+            self.classes.resize(class + 1, SizeClassStats::default());
+        }
+        &mut self.classes[class]
+    }
+
+    /// Evict from the tail until `total + incoming` fits the budget.
+    fn evict_for(&mut self, incoming: u64) {
+        while self.total + incoming > self.budget && self.tail != NIL {
+            let i = self.tail;
+            let weight = self.slots[i].weight;
+            self.remove_slot(i);
+            self.metrics.evictions += 1;
+            self.metrics.bytes_evicted += weight as u64;
+            self.class_mut(weight).evictions += 1;
+        }
+    }
+
+    /// Keys from most to least recently used.
+    pub fn keys_by_recency(&self) -> Vec<String> {
+        let mut keys = Vec::with_capacity(self.index.len());
+        let mut i = self.head;
+        while i != NIL {
+            keys.push(self.slots[i].key.clone());
+            i = self.slots[i].next;
+        }
+        keys
+    }
+}
+
+#[wasm_bindgen]
+impl WeightedLruCache {
+    #[wasm_bindgen(constructor)]
+    pub fn new(budget_bytes: u64) -> WeightedLruCache {
+        WeightedLruCache {
+            budget: budget_bytes,
+            total: 0,
+            index: HashMap::new(),
+            slots: Vec::new(),
+            free: Vec::new(),
+            head: NIL,
+            tail: NIL,
+            metrics: WeightedLruMetrics::default(),
+            classes: Vec::new(),
+        }
+    }
+
+    /// Insert or replace `key`, weighing `bytes`, and mark it most recently
+    /// used. Returns false, storing nothing, when `bytes` exceeds the
+    /// budget.
+    pub fn insert(&mut self, key: String, value: u32, bytes: u32) -> bool {
+        let existing = self.index.get(&key).copied();
+        if bytes as u64 > self.budget {
+            if let Some(i) = existing {
+                self.remove_slot(i);
+            }
+            self.metrics.rejected += 1;
+            return false;
+        }
+        match existing {
+            Some(i) => {
+                // Detach first so growing the entry can't evict itself
+                self.unlink(i);
+                self.total -= self.slots[i].weight as u64;
+                self.evict_for(bytes as u64);
+                self.slots[i].value = value;
+                self.slots[i].weight = bytes;
+                self.total += bytes as u64;
+                self.push_front(i);
+            }
+            None => {
+                self.evict_for(bytes as u64);
+                let slot = Slot {
+                    key: key.clone(),
+                    value,
+                    weight: bytes,
+                    prev: NIL,
+                    next: NIL,
+                };
+                let i = match self.free.pop() {
+                    Some(i) => {
+                        self.slots[i] = slot;
+                        i
+                    }
+                    None => {
+                        self.slots.push(slot);
+                        self.slots.len() - 1
+                    }
+                };
+                self.index.insert(key, i);
+                self.total += bytes as u64;
+                self.push_front(i);
+                self.metrics.fill_bytes += bytes as u64;
+                self.class_mut(bytes).fills += 1;
+            }
+        }
+        true
+    }
+
+    /// Look up `key`, marking it most recently used on a hit.
+    pub fn get(&mut self, key: &str) -> Option<u32> {
+        let Some(&i) = self.index.get(key) else {
+            self.metrics.misses += 1;
+            return None;
+        };
+        self.unlink(i);
+        self.push_front(i);
+        let (value, weight) = (self.slots[i].value, self.slots[i].weight);
+        self.metrics.hits += 1;
+        self.metrics.hit_bytes += weight as u64;
+        self.class_mut(weight).hits += 1;
+        Some(value)
+    }
+
+    /// Look up `key` without touching recency or metrics.
+    pub fn peek(&self, key: &str) -> Option<u32> {
+        self.index.get(key).map(|&i| self.slots[i].value)
+    }
+
+    /// Declared size of `key`, if cached.
+    pub fn weight_of(&self, key: &str) -> Option<u32> {
+        self.index.get(key).map(|&i| self.slots[i].weight)
+    }
+
+    pub fn delete(&mut self, key: &str) -> bool {
+        match self.index.get(key) {
+            Some(&i) => {
+                self.remove_slot(i);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Change the budget, evicting least recently used entries if the cache
+    /// no longer fits.
+    pub fn set_budget(&mut self, budget_bytes: u64) {
+        self.budget = budget_bytes;
+        self.evict_for(0);
+    }
+
+    pub fn budget(&self) -> u64 {
+        self.budget
+    }
+
+    /// Bytes currently held.
+    pub fn total_bytes(&self) -> u64 {
+        self.total
+    }
+
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    /// Keys from most to least recently used.
+    pub fn keys(&self) -> Vec<String> {
+        self.keys_by_recency()
+    }
+
+    pub fn metrics(&self) -> WeightedLruMetrics {
+        self.metrics
+    }
+
+    /// Per-size-class counters, smallest class first, skipping classes
+    /// that never saw traffic.
+    pub fn hit_rate_by_size(&self) -> Vec<SizeClassStats> {
+        self.classes
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| c.hits + c.fills + c.evictions > 0)
+            .map(|(class, c)| SizeClassStats {
+                min_bytes: if class == 0 { 0 } else { 1 << class },
+                max_bytes: 1 << (class + 1),
+                ..*c
+            })
+            .collect()
+    }
+
+    pub fn reset_metrics(&mut self) {
+        self.metrics = WeightedLruMetrics::default();
+        self.classes.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evicts_by_bytes() {
+        let mut cache = WeightedLruCache::new(1000);
+        assert!(cache.insert("a".into(), 1, 400));
+        assert!(cache.insert("b".into(), 2, 400));
+        assert_eq!(cache.get("a"), Some(1));
+        // b is least recently used and goes first
+        assert!(cache.insert("c".into(), 3, 300));
+        assert_eq!(cache.keys(), ["c", "a"]);
+        assert_eq!(cache.total_bytes(), 700);
+
+        // Growing an entry evicts others, never itself
+        assert!(cache.insert("a".into(), 10, 900));
+        assert_eq!(cache.keys(), ["a"]);
+        assert_eq!(cache.peek("a"), Some(10));
+
+        assert!(!cache.insert("a".into(), 11, 1001));
+        assert!(cache.is_empty());
+        assert_eq!(cache.total_bytes(), 0);
+
+        let metrics = cache.metrics();
+        assert_eq!(metrics.evictions, 2);
+        assert_eq!(metrics.bytes_evicted, 700);
+        assert_eq!(metrics.rejected, 1);
+
+        cache.insert("x".into(), 1, 500);
+        cache.insert("y".into(), 2, 500);
+        cache.set_budget(600);
+        assert_eq!(cache.keys(), ["y"]);
+        assert!(cache.delete("y") && !cache.delete("y"));
+    }
+
+    #[test]
+    fn test_hit_rate_by_size() {
+        let mut cache = WeightedLruCache::new(10_000);
+        cache.insert("icon".into(), 1, 100);
+        cache.insert("video".into(), 2, 5000);
+        for _ in 0..3 {
+            cache.get("icon");
+        }
+        assert_eq!(cache.get("missing"), None);
+
+        let classes = cache.hit_rate_by_size();
+        assert_eq!(classes.len(), 2);
+        assert_eq!((classes[0].min_bytes, classes[0].max_bytes), (64, 128));
+        assert_eq!(classes[0].hit_rate(), 0.75);
+        assert_eq!((classes[1].min_bytes, classes[1].max_bytes), (4096, 8192));
+        assert_eq!(classes[1].hit_rate(), 0.0);
+
+        let metrics = cache.metrics();
+        assert_eq!(metrics.hit_rate(), 0.75);
+        assert_eq!(metrics.byte_hit_rate(), 300.0 / 5400.0);
+        assert_eq!(size_class(0), 0);
+        assert_eq!(size_class(1), 0);
+        assert_eq!(size_class(u32::MAX), 31);
+    }
+}
@@ -0,0 +1,722 @@
+use crate::rng::DefaultRng;
+use crate::scenarios::ZipfSampler;
+use rand::Rng;
+use std::collections::{BTreeSet, HashMap};
+use wasm_bindgen::prelude::*;
+
+/// Replacement policy names accepted by [`new_policy`].
+pub const CACHE_POLICIES: [&str; 4] = ["lru", "lfu", "2q", "arc"];
+
+/// Workload names accepted by [`cache_workload`].
+pub const CACHE_WORKLOADS: [&str; 3] = ["loop", "scan", "mixed"];
+
+/// A fixed-size cache of keys that decides what to keep. Simulation only:
+/// the policy tracks which keys are resident, not their values.
+pub trait CachePolicy {
+    fn name(&self) -> &'static str;
+
+    /// Reference `key`, admitting it on a miss. Returns whether it was
+    /// resident.
+    fn access(&mut self, key: &str) -> bool;
+
+    /// Whether `key` is resident, without counting as a reference.
+    fn contains(&self, key: &str) -> bool;
+
+    /// Resident keys; ghost entries remembered for adaptivity don't count.
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn capacity(&self) -> usize;
+}
+
+/// Create an empty policy by name (see [`CACHE_POLICIES`]).
+pub fn new_policy(name: &str, capacity: usize) -> Option<Box<dyn CachePolicy>> {
+    Some(match name {
+        "lru" => Box::new(LruPolicy::new(capacity)),
+        "lfu" => Box::new(LfuPolicy::new(capacity)),
+        "2q" => Box::new(TwoQueuePolicy::new(capacity)),
+        "arc" => Box::new(ArcPolicy::new(capacity)),
+        _ => return None,
+    })
+}
+
+const NIL: usize = usize::MAX;
+
+struct Link {
+    key: String,
+    prev: usize,
+    next: usize,
+}
+
+/// Keys in recency order with O(1) move-to-front, removal and pop from the
+/// back; the building block of every policy here.
+#[derive(Default)]
+struct RecencyList {
+    index: HashMap<String, usize>,
+    links: Vec<Link>,
+    free: Vec<usize>,
+    head: usize,
+    tail: usize,
+}
+
+impl RecencyList {
+    fn new() -> RecencyList {
+        RecencyList {
+            head: NIL,
+            tail: NIL,
+            ..RecencyList::default()
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    fn contains(&self, key: &str) -> bool {
+        self.index.contains_key(key)
+    }
+
+    fn unlink(&mut self, i: usize) {
+        let (prev, next) = (self.links[i].prev, self.links[i].next);
+        match prev {
+            NIL => self.head = next,
+            p => self.links[p].next = next,
+        }
+        match next {
+            NIL => self.tail = prev,
+            n => self.links[n].prev = prev,
+        }
+    }
+
+    fn link_front(&mut self, i: usize) {
+        self.links[i].prev = NIL;
+        self.links[i].next = self.head;
+        match self.head {
+            NIL => self.tail = i,
+            h => self.links[h].prev = i,
+        }
+        self.head = i;
+    }
+
+    /// Add `key` as most recent, or make it most recent if already present.
+    fn push_front(&mut self, key: &str) {
+        if let Some(&i) = self.index.get(key) {
+            self.unlink(i);
+            self.link_front(i);
+            return;
+        }
+        let link = Link {
+            key: key.to_string(),
+            prev: NIL,
+            next: NIL,
+        };
+        let i = match self.free.pop() {
+            Some(i) => {
+                self.links[i] = link;
+                i
+            }
+            None => {
+                self.links.push(link);
+                self.links.len() - 1
+            }
+        };
+        self.index.insert(key.to_string(), i);
+        self.link_front(i);
+    }
+
+    fn remove(&mut self, key: &str) -> bool {
+        match self.index.remove(key) {
+            Some(i) => {
+                self.unlink(i);
+                self.links[i].key.clear();
+                self.free.push(i);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Remove and return the least recent key.
+    fn pop_back(&mut self) -> Option<String> {
+        if self.tail == NIL {
+            return None;
+        }
+        let i = self.tail;
+        let key = std::mem::take(&mut self.links[i].key);
+        self.unlink(i);
+        self.index.remove(&key);
+        self.free.push(i);
+        Some(key)
+    }
+}
+
+/// Evicts the least recently used key.
+pub struct LruPolicy {
+    capacity: usize,
+    list: RecencyList,
+}
+
+impl LruPolicy {
+    pub fn new(capacity: usize) -> LruPolicy {
+        LruPolicy {
+            capacity,
+            list: RecencyList::new(),
+        }
+    }
+}
+
+impl CachePolicy for LruPolicy {
+    fn name(&self) -> &'static str {
+        "lru"
+    }
+
+    fn access(&mut self, key: &str) -> bool {
+        let hit = self.list.contains(key);
+        if !hit {
+            if self.capacity == 0 {
+                return false;
+            }
+            if self.list.len() == self.capacity {
+                self.list.pop_back();
+            }
+        }
+        self.list.push_front(key);
+        hit
+    }
+
+    fn contains(&self, key: &str) -> bool {
+        self.list.contains(key)
+    }
+
+    fn len(&self) -> usize {
+        self.list.len()
+    }
+
+    fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
+
+/// Evicts the least frequently referenced key, the least recently used
+/// among ties. Counts are forgotten on eviction.
+pub struct LfuPolicy {
+    capacity: usize,
+    /// key -> (references, last reference tick)
+    counts: HashMap<String, (u64, u64)>,
+    /// (references, tick, key), minimum first
+    order: BTreeSet<(u64, u64, String)>,
+    tick: u64,
+}
+
+impl LfuPolicy {
+    pub fn new(capacity: usize) -> LfuPolicy {
+        LfuPolicy {
+            capacity,
+            counts: HashMap::new(),
+            order: BTreeSet::new(),
+            tick: 0,
+        }
+    }
+}
+
+impl CachePolicy for LfuPolicy {
+    fn name(&self) -> &'static str {
+        "lfu"
+    }
+
+    fn access(&mut self, key: &str) -> bool {
+        self.tick += 1;
+        if let Some(entry) = self.counts.get_mut(key) {
+            self.order.remove(&(entry.0, entry.1, key.to_string()));
+            *entry = (entry.0 + 1, self.tick);
+            self.order.insert((entry.0, entry.1, key.to_string()));
+            return true;
+        }
+        if self.capacity == 0 {
+            return false;
+        }
+        if self.counts.len() == self.capacity {
+            if let Some((_, _, victim)) = self.order.pop_first() {
+                self.counts.remove(&victim);
+            }
+        }
+        self.counts.insert(key.to_string(), (1, self.tick));
+        self.order.insert((1, self.tick, key.to_string()));
+        false
+    }
+
+    fn contains(&self, key: &str) -> bool {
+        self.counts.contains_key(key)
+    }
+
+    fn len(&self) -> usize {
+        self.counts.len()
+    }
+
+    fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
+
+/// 2Q (Johnson & Shasha, full version). New keys enter a small FIFO,
+/// `A1in`; keys it pushes out are remembered, without their data, in the
+/// ghost queue `A1out`. Only a key referenced again while remembered is
+/// promoted to the main LRU, `Am`, so a one-pass scan churns through
+/// `A1in` and leaves `Am` alone.
+pub struct TwoQueuePolicy {
+    capacity: usize,
+    /// Resident-key budget of `A1in`, a quarter of the capacity
+    k_in: usize,
+    /// Ghosts remembered in `A1out`, half the capacity
+    k_out: usize,
+    a1_in: RecencyList,
+    a1_out: RecencyList,
+    am: RecencyList,
+}
+
+impl TwoQueuePolicy {
+    pub fn new(capacity: usize) -> TwoQueuePolicy {
+        TwoQueuePolicy {
+            capacity,
+            k_in: (capacity / 4).max(1),
+            k_out: (capacity / 2).max(1),
+            a1_in: RecencyList::new(),
+            a1_out: RecencyList::new(),
+            am: RecencyList::new(),
+        }
+    }
+
+    /// Free one resident slot if the cache is full.
+    fn reclaim(&mut self) {
+        if self.a1_in.len() + self.am.len() < self.capacity {
+            return;
+        }
+        if self.a1_in.len() > self.k_in || self.am.len() == 0 {
+            if let Some(key) = self.a1_in.pop_back() {
+                self.a1_out.push_front(&key);
+                if self.a1_out.len() > self.k_out {
+                    self.a1_out.pop_back();
+                }
+            }
+        } else {
+            self.am.pop_back();
+        }
+    }
+}
+
+impl CachePolicy for TwoQueuePolicy {
+    fn name(&self) -> &'static str {
+        "2q"
+    }
+
+    fn access(&mut self, key: &str) -> bool {
+        if self.am.contains(key) {
+            self.am.push_front(key);
+            return true;
+        }
+        // A1in is FIFO: a hit there doesn't reorder it
+        if self.a1_in.contains(key) {
+            return true;
+        }
+        if self.capacity == 0 {
+            return false;
+        }
+        self.reclaim();
+        if self.a1_out.remove(key) {
+            self.am.push_front(key);
+        } else {
+            self.a1_in.push_front(key);
+        }
+        false
+    }
+
+    fn contains(&self, key: &str) -> bool {
+        self.am.contains(key) || self.a1_in.contains(key)
+    }
+
+    fn len(&self) -> usize {
+        self.a1_in.len() + self.am.len()
+    }
+
+    fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
+
+/// Adaptive Replacement Cache (Megiddo & Modha). Resident keys are split
+/// between `T1`, seen once recently, and `T2`, seen at least twice; ghost
+/// lists `B1` and `B2` remember what each recently evicted. A hit on a
+/// ghost shifts the target size `p` of `T1` toward the list that would
+/// have kept it, so the cache tunes itself between recency and frequency
+/// and a scan can only ever flush `T1`.
+pub struct ArcPolicy {
+    capacity: usize,
+    /// Target size of `T1`
+    p: usize,
+    t1: RecencyList,
+    t2: RecencyList,
+    b1: RecencyList,
+    b2: RecencyList,
+}
+
+impl ArcPolicy {
+    pub fn new(capacity: usize) -> ArcPolicy {
+        ArcPolicy {
+            capacity,
+            p: 0,
+            t1: RecencyList::new(),
+            t2: RecencyList::new(),
+            b1: RecencyList::new(),
+            b2: RecencyList::new(),
+        }
+    }
+
+    /// Target size of the recency side, adapted on every ghost hit.
+    pub fn target_recent(&self) -> usize {
+        self.p
+    }
+
+    /// Evict one resident key into its ghost list.
+    fn replace(&mut self, in_b2: bool) {
+        let t1 = self.t1.len();
+        if t1 > 0 && (t1 > self.p || (in_b2 && t1 == self.p)) {
+            if let Some(key) = self.t1.pop_back() {
+                self.b1.push_front(&key);
+            }
+        } else if let Some(key) = self.t2.pop_back() {
+            self.b2.push_front(&key);
+        } else if let Some(key) = self.t1.pop_back() {
+            self.b1.push_front(&key);
+        }
+    }
+}
+
+impl CachePolicy for ArcPolicy {
+    fn name(&self) -> &'static str {
+        "arc"
+    }
+
+    fn access(&mut self, key: &str) -> bool {
+        if self.t1.remove(key) || self.t2.contains(key) {
+            self.t2.push_front(key);
+            return true;
+        }
+        let c = self.capacity;
+        if c == 0 {
+            return false;
+        }
+        if self.b1.contains(key) {
+            let delta = (self.b2.len() / self.b1.len()).max(1);
+            self.p = (self.p + delta).min(c);
+            self.replace(false);
+            self.b1.remove(key);
+            self.t2.push_front(key);
+            return false;
+        }
+        if self.b2.contains(key) {
+            let delta = (self.b1.len() / self.b2.len()).max(1);
+            self.p = self.p.saturating_sub(delta);
+            self.replace(true);
+            self.b2.remove(key);
+            self.t2.push_front(key);
+            return false;
+        }
+        let recent = self.t1.len() + self.b1.len();
+        let total = recent + self.t2.len() + self.b2.len();
+        if recent == c {
+            if self.t1.len() < c {
+                self.b1.pop_back();
+                self.replace(false);
+            } else {
+                self.t1.pop_back();
+            }
+        } else if total >= c {
+            if total == 2 * c {
+                self.b2.pop_back();
+            }
+            self.replace(false);
+        }
+        self.t1.push_front(key);
+        false
+    }
+
+    fn contains(&self, key: &str) -> bool {
+        self.t1.contains(key) || self.t2.contains(key)
+    }
+
+    fn len(&self) -> usize {
+        self.t1.len() + self.t2.len()
+    }
+
+    fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
+
+/// Generate `length` key references for a named workload (see
+/// [`CACHE_WORKLOADS`]), sized relative to the cache `capacity`:
+///
+/// - `loop`: cycles through 1.5x capacity keys in order, the pathological
+///   case for recency: every key is evicted just before its next use.
+/// - `scan`: a hot set of half the capacity, referenced at random,
+///   alternating with one-pass scans of twice the capacity in never-reused
+///   keys. Scan resistance keeps the hot set resident.
+/// - `mixed`: Zipf-distributed references over 10x capacity keys, with a
+///   one-pass scan of the capacity interleaved after every 4x capacity
+///   references.
+pub fn cache_workload(
+    name: &str,
+    capacity: usize,
+    length: usize,
+    seed: u64,
+) -> Option<Vec<String>> {
+    let capacity = capacity.max(1);
+    let mut rng = DefaultRng::seed_from(seed);
+    let mut scanned = 0;
+    let scan_key = |scanned: &mut usize| {
+        *scanned += 1;
+        format!("scan{}", scanned)
+    };
+    let keys = match name {
+        "loop" => {
+            let span = capacity + capacity.div_ceil(2);
+            (0..length).map(|i| format!("k{}", i % span)).collect()
+        }
+        "scan" => {
+            let hot = capacity.div_ceil(2);
+            let phase = 2 * capacity;
+            (0..length)
+                .map(|i| {
+                    if (i / phase).is_multiple_of(2) {
+                        format!("k{}", rng.gen_range(0..hot))
+                    } else {
+                        scan_key(&mut scanned)
+                    }
+                })
+                .collect()
+        }
+        "mixed" => {
+            let zipf = ZipfSampler::new(10 * capacity, 1.0);
+            let period = 5 * capacity;
+            (0..length)
+                .map(|i| {
+                    if i % period < 4 * capacity {
+                        format!("k{}", zipf.sample(&mut rng))
+                    } else {
+                        scan_key(&mut scanned)
+                    }
+                })
+                .collect()
+        }
+        _ => return None,
+    };
+    Some(keys)
+}
+
+/// How one policy did on one workload.
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct CachePolicyResult {
+    pub policy: String,
+    pub workload: String,
+    pub capacity: u32,
+    pub hits: u32,
+    pub misses: u32,
+}
+
+#[wasm_bindgen]
+impl CachePolicyResult {
+    pub fn hit_rate(&self) -> f64 {
+        let total = self.hits + self.misses;
+        if total == 0 {
+            0.0
+        } else {
+            self.hits as f64 / total as f64
+        }
+    }
+}
+
+/// Replay `keys` through `policy` from its current state.
+pub fn simulate(
+    policy: &mut dyn CachePolicy,
+    workload: &str,
+    keys: &[String],
+) -> CachePolicyResult {
+    let hits = keys.iter().filter(|key| policy.access(key)).count() as u32;
+    CachePolicyResult {
+        policy: policy.name().to_string(),
+        workload: workload.to_string(),
+        capacity: policy.capacity() as u32,
+        hits,
+        misses: keys.len() as u32 - hits,
+    }
+}
+
+pub fn try_compare_cache_policies(
+    workload: &str,
+    capacity: usize,
+    length: usize,
+    seed: u64,
+) -> Result<Vec<CachePolicyResult>, String> {
+    let keys = cache_workload(workload, capacity, length, seed).ok_or_else(|| {
+        format!(
+            "unknown cache workload '{}'; expected one of {}",
+            workload,
+            CACHE_WORKLOADS.join(", ")
+        )
+    })?;
+    Ok(CACHE_POLICIES
+        .iter()
+        .map(|name| {
+            let mut policy = new_policy(name, capacity).expect("listed policy");
+            simulate(policy.as_mut(), workload, &keys)
+        })
+        .collect())
+}
+
+/// Run every policy over the same generated workload (`"loop"`, `"scan"`
+/// or `"mixed"`) and report their hit rates.
+///
+/// # Example
+/// ```javascript
+/// for (const r of compare_cache_policies("scan", 1000, 100_000, 42n))
+///   console.log(r.policy, r.hit_rate().toFixed(3));
+/// ```
+#[wasm_bindgen]
+pub fn compare_cache_policies(
+    workload: &str,
+    capacity: u32,
+    length: u32,
+    seed: u64,
+) -> Result<Vec<CachePolicyResult>, JsValue> {
+    try_compare_cache_policies(workload, capacity as usize, length as usize, seed)
+        .map_err(|e| JsValue::from_str(&e))
+}
+
+/// A replacement policy driven from JS, one reference at a time.
+///
+/// # Example
+/// ```javascript
+/// const cache = new PolicyCache("arc", 100);
+/// if (!cache.access(url)) fetchAndStore(url);
+/// ```
+#[wasm_bindgen]
+pub struct PolicyCache {
+    policy: Box<dyn CachePolicy>,
+    hits: u32,
+    misses: u32,
+}
+
+#[wasm_bindgen]
+impl PolicyCache {
+    /// `policy` is `"lru"`, `"lfu"`, `"2q"` or `"arc"`.
+    #[wasm_bindgen(constructor)]
+    pub fn new(policy: &str, capacity: u32) -> Result<PolicyCache, JsValue> {
+        let policy = new_policy(policy, capacity as usize)
+            .ok_or_else(|| JsValue::from_str(&format!("unknown cache policy '{}'", policy)))?;
+        Ok(PolicyCache {
+            policy,
+            hits: 0,
+            misses: 0,
+        })
+    }
+
+    /// Reference `key`, admitting it on a miss. Returns whether it hit.
+    pub fn access(&mut self, key: &str) -> bool {
+        let hit = self.policy.access(key);
+        if hit {
+            self.hits += 1;
+        } else {
+            self.misses += 1;
+        }
+        hit
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.policy.contains(key)
+    }
+
+    pub fn policy(&self) -> String {
+        self.policy.name().to_string()
+    }
+
+    pub fn len(&self) -> usize {
+        self.policy.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.policy.is_empty()
+    }
+
+    pub fn hits(&self) -> u32 {
+        self.hits
+    }
+
+    pub fn misses(&self) -> u32 {
+        self.misses
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hit_rate(results: &[CachePolicyResult], policy: &str) -> f64 {
+        results
+            .iter()
+            .find(|r| r.policy == policy)
+            .unwrap()
+            .hit_rate()
+    }
+
+    #[test]
+    fn test_policies_respect_capacity() {
+        for name in CACHE_POLICIES {
+            let mut policy = new_policy(name, 3).unwrap();
+            for key in ["a", "b", "c"] {
+                assert!(!policy.access(key), "{}", name);
+            }
+            assert!(policy.access("a"), "{}", name);
+            for key in ["d", "e", "f", "g", "a", "h"] {
+                policy.access(key);
+                assert!(policy.len() <= 3, "{}", name);
+            }
+            assert!(policy.contains("h"), "{}", name);
+
+            let mut empty = new_policy(name, 0).unwrap();
+            assert!(!empty.access("a") && !empty.access("a") && empty.is_empty());
+        }
+        assert!(new_policy("fifo", 3).is_none());
+
+        let mut lru = LruPolicy::new(2);
+        lru.access("a");
+        lru.access("b");
+        lru.access("a");
+        lru.access("c");
+        assert!(lru.contains("a") && !lru.contains("b"));
+
+        let mut lfu = LfuPolicy::new(2);
+        lfu.access("a");
+        lfu.access("a");
+        lfu.access("b");
+        lfu.access("c");
+        assert!(lfu.contains("a") && !lfu.contains("b"));
+    }
+
+    #[test]
+    fn test_arc_resists_scans() {
+        let scan = try_compare_cache_policies("scan", 200, 20_000, 1).unwrap();
+        assert!(hit_rate(&scan, "arc") > hit_rate(&scan, "lru") + 0.1);
+
+        let mixed = try_compare_cache_policies("mixed", 200, 20_000, 1).unwrap();
+        assert!(hit_rate(&mixed, "arc") > hit_rate(&mixed, "lru") + 0.05);
+        assert!(hit_rate(&mixed, "2q") > hit_rate(&mixed, "lru") + 0.05);
+
+        let looped = try_compare_cache_policies("loop", 200, 20_000, 1).unwrap();
+        assert_eq!(hit_rate(&looped, "lru"), 0.0);
+        assert!(try_compare_cache_policies("zipf", 200, 10, 1).is_err());
+    }
+}
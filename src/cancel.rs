@@ -0,0 +1,60 @@
+use std::cell::Cell;
+use std::rc::Rc;
+use wasm_bindgen::prelude::*;
+
+/// Shared flag that long-running operations poll to stop early.
+///
+/// Cancelling doesn't throw: the operation stops at its next check, keeps
+/// whatever it already inserted, and reports that it was cancelled.
+/// Async operations take ownership of their arguments, so pass them a
+/// `handle()` and keep the original to call `cancel()` on.
+///
+/// # Example
+/// ```javascript
+/// const token = new CancellationToken();
+/// cancelButton.onclick = () => token.cancel();
+/// const summary = await bulk_insert_async(store, keys, values, 1000, undefined, token.handle());
+/// if (summary.cancelled()) console.log(`stopped after ${summary.inserted()} entries`);
+/// ```
+#[wasm_bindgen]
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    cancelled: Rc<Cell<bool>>,
+}
+
+#[wasm_bindgen]
+impl CancellationToken {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> CancellationToken {
+        CancellationToken::default()
+    }
+
+    /// Request cancellation. Every handle of this token observes it.
+    pub fn cancel(&self) {
+        self.cancelled.set(true);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.get()
+    }
+
+    /// Another handle to the same flag, for APIs that take ownership.
+    pub fn handle(&self) -> CancellationToken {
+        self.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_handles_share_state() {
+        let token = CancellationToken::new();
+        let handle = token.handle();
+        assert!(!handle.is_cancelled());
+        token.cancel();
+        assert!(handle.is_cancelled());
+        assert!(!CancellationToken::new().is_cancelled());
+    }
+}
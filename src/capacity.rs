@@ -0,0 +1,257 @@
+use wasm_bindgen::prelude::*;
+
+/// Growth policy for structures that resize their backing table.
+///
+/// Built fluently from JS; every setter returns the updated config. Values
+/// are only checked when the config is handed to a structure, so a chain of
+/// setters never throws halfway through.
+///
+/// # Example
+/// ```javascript
+/// const config = new CapacityConfig()
+///     .with_growth_factor(1.5)
+///     .with_max_load_factor(0.6)
+///     .with_shrink_on_delete(true);
+/// const table = OpenAddressingHashTable.with_config(config);
+/// ```
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CapacityConfig {
+    growth_factor: f64,
+    min_capacity: u32,
+    max_capacity: u32,
+    max_load_factor: f64,
+    min_load_factor: f64,
+    shrink_on_delete: bool,
+}
+
+impl Default for CapacityConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CapacityConfig {
+    /// A config that pins the capacity, for structures that never resize.
+    pub fn fixed(capacity: u32) -> CapacityConfig {
+        CapacityConfig {
+            min_capacity: capacity,
+            max_capacity: capacity,
+            ..CapacityConfig::new()
+        }
+    }
+
+    /// Check the settings are consistent.
+    ///
+    /// Besides range checks, shrinking must land below the grow threshold,
+    /// otherwise a table sitting at the boundary would resize on every
+    /// insert/delete pair.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.growth_factor.is_nan() || self.growth_factor <= 1.0 {
+            return Err(format!(
+                "growth_factor must be greater than 1 (got {})",
+                self.growth_factor
+            ));
+        }
+        if self.min_capacity == 0 {
+            return Err("min_capacity must be at least 1".to_string());
+        }
+        if self.max_capacity < self.min_capacity {
+            return Err(format!(
+                "max_capacity ({}) is below min_capacity ({})",
+                self.max_capacity, self.min_capacity
+            ));
+        }
+        if !(self.max_load_factor > 0.0 && self.max_load_factor <= 1.0) {
+            return Err(format!(
+                "max_load_factor must be in (0, 1] (got {})",
+                self.max_load_factor
+            ));
+        }
+        if !(self.min_load_factor >= 0.0 && self.min_load_factor < self.max_load_factor) {
+            return Err(format!(
+                "min_load_factor must be in [0, max_load_factor) (got {})",
+                self.min_load_factor
+            ));
+        }
+        if self.shrink_on_delete
+            && self.min_load_factor * self.growth_factor >= self.max_load_factor
+        {
+            return Err(format!(
+                "min_load_factor * growth_factor ({}) must stay below max_load_factor ({}) \
+                 or shrinking would immediately trigger growth",
+                self.min_load_factor * self.growth_factor,
+                self.max_load_factor
+            ));
+        }
+        Ok(())
+    }
+
+    /// Clamp a requested starting capacity into `[min_capacity, max_capacity]`.
+    pub fn initial_capacity(&self, requested: u32) -> u32 {
+        requested.clamp(self.min_capacity, self.max_capacity)
+    }
+
+    /// New capacity if holding `len` entries in `capacity` slots exceeds the
+    /// max load factor, or `None` if no growth is needed or allowed.
+    pub fn grow_target(&self, capacity: u32, len: u32) -> Option<u32> {
+        if capacity >= self.max_capacity || (len as f64) <= capacity as f64 * self.max_load_factor {
+            return None;
+        }
+        let grown = (capacity as f64 * self.growth_factor).ceil() as u32;
+        Some(grown.max(capacity + 1).min(self.max_capacity))
+    }
+
+    /// New capacity if shrink-on-delete is on and `len` entries in `capacity`
+    /// slots fell below the min load factor.
+    pub fn shrink_target(&self, capacity: u32, len: u32) -> Option<u32> {
+        if !self.shrink_on_delete
+            || capacity <= self.min_capacity
+            || (len as f64) >= capacity as f64 * self.min_load_factor
+        {
+            return None;
+        }
+        let shrunk = ((capacity as f64 / self.growth_factor).floor() as u32).max(self.min_capacity);
+        Some(shrunk)
+    }
+}
+
+#[wasm_bindgen]
+impl CapacityConfig {
+    /// Defaults: double when 75% full, start at 8 slots, no upper bound,
+    /// never shrink.
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> CapacityConfig {
+        CapacityConfig {
+            growth_factor: 2.0,
+            min_capacity: 8,
+            max_capacity: u32::MAX,
+            max_load_factor: 0.75,
+            min_load_factor: 0.2,
+            shrink_on_delete: false,
+        }
+    }
+
+    /// Multiplier applied to the capacity on each resize.
+    pub fn with_growth_factor(mut self, factor: f64) -> CapacityConfig {
+        self.growth_factor = factor;
+        self
+    }
+
+    /// Smallest capacity; also the starting capacity.
+    pub fn with_min_capacity(mut self, capacity: u32) -> CapacityConfig {
+        self.min_capacity = capacity;
+        self
+    }
+
+    /// Largest capacity the structure may grow to.
+    pub fn with_max_capacity(mut self, capacity: u32) -> CapacityConfig {
+        self.max_capacity = capacity;
+        self
+    }
+
+    /// Grow once `len / capacity` exceeds this.
+    pub fn with_max_load_factor(mut self, load: f64) -> CapacityConfig {
+        self.max_load_factor = load;
+        self
+    }
+
+    /// With shrink-on-delete, shrink once `len / capacity` drops below this.
+    pub fn with_min_load_factor(mut self, load: f64) -> CapacityConfig {
+        self.min_load_factor = load;
+        self
+    }
+
+    pub fn with_shrink_on_delete(mut self, enabled: bool) -> CapacityConfig {
+        self.shrink_on_delete = enabled;
+        self
+    }
+
+    pub fn growth_factor(&self) -> f64 {
+        self.growth_factor
+    }
+
+    pub fn min_capacity(&self) -> u32 {
+        self.min_capacity
+    }
+
+    pub fn max_capacity(&self) -> u32 {
+        self.max_capacity
+    }
+
+    pub fn max_load_factor(&self) -> f64 {
+        self.max_load_factor
+    }
+
+    pub fn min_load_factor(&self) -> f64 {
+        self.min_load_factor
+    }
+
+    pub fn shrink_on_delete(&self) -> bool {
+        self.shrink_on_delete
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_defaults_are_valid() {
+        assert!(CapacityConfig::new().validate().is_ok());
+        assert!(CapacityConfig::fixed(64).validate().is_ok());
+    }
+
+    #[test]
+    fn test_validation_errors() {
+        let base = CapacityConfig::new();
+        assert!(base.with_growth_factor(1.0).validate().is_err());
+        assert!(base.with_growth_factor(f64::NAN).validate().is_err());
+        assert!(base.with_min_capacity(0).validate().is_err());
+        assert!(base
+            .with_min_capacity(100)
+            .with_max_capacity(10)
+            .validate()
+            .is_err());
+        assert!(base.with_max_load_factor(1.5).validate().is_err());
+        assert!(base.with_min_load_factor(0.8).validate().is_err());
+        // 0.4 * 2 >= 0.75 would thrash
+        let thrashing = base.with_min_load_factor(0.4).with_shrink_on_delete(true);
+        assert!(thrashing.validate().is_err());
+        // ...but is harmless while shrinking is off
+        assert!(base.with_min_load_factor(0.4).validate().is_ok());
+    }
+
+    #[test]
+    fn test_grow_target() {
+        let config = CapacityConfig::new().with_max_capacity(20);
+        assert_eq!(config.grow_target(8, 6), None);
+        assert_eq!(config.grow_target(8, 7), Some(16));
+        assert_eq!(config.grow_target(16, 13), Some(20));
+        assert_eq!(config.grow_target(20, 20), None);
+        assert_eq!(CapacityConfig::fixed(8).grow_target(8, 8), None);
+
+        let slow = CapacityConfig::new().with_growth_factor(1.01);
+        assert_eq!(slow.grow_target(8, 7), Some(9));
+    }
+
+    #[test]
+    fn test_shrink_target() {
+        let config = CapacityConfig::new();
+        assert_eq!(config.shrink_target(64, 1), None);
+
+        let config = config.with_shrink_on_delete(true);
+        assert_eq!(config.shrink_target(64, 13), None);
+        assert_eq!(config.shrink_target(64, 12), Some(32));
+        assert_eq!(config.shrink_target(12, 0), Some(8));
+        assert_eq!(config.shrink_target(8, 0), None);
+    }
+
+    #[test]
+    fn test_initial_capacity_is_clamped() {
+        let config = CapacityConfig::new().with_max_capacity(100);
+        assert_eq!(config.initial_capacity(0), 8);
+        assert_eq!(config.initial_capacity(50), 50);
+        assert_eq!(config.initial_capacity(500), 100);
+    }
+}
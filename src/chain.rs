@@ -0,0 +1,525 @@
+use crate::memory::{MemoryReport, MemoryUsage};
+use crate::{HashMap, HashMapBuilder};
+use std::mem::size_of;
+use wasm_bindgen::prelude::*;
+
+/// How each [`HashMap`](crate::HashMap) bucket stores its chain.
+///
+/// - `Vec`: entries appended to a vector, scanned linearly (the default).
+/// - `LinkedList`: the textbook singly linked list, new keys pushed at the
+///   head. One allocation per entry and a pointer chase per step.
+/// - `SortedVec`: a vector kept in key order and binary searched, trading
+///   cheaper lookups in long chains for shifting on insert and delete.
+///
+/// [`compare_bucket_modes`] measures the difference on a set of keys.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BucketMode {
+    #[default]
+    Vec,
+    LinkedList,
+    SortedVec,
+}
+
+impl BucketMode {
+    /// Structure kind reported for a map using this mode, e.g. in benchmarks.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            BucketMode::Vec => "hashmap",
+            BucketMode::LinkedList => "hashmap_linked",
+            BucketMode::SortedVec => "hashmap_sorted",
+        }
+    }
+}
+
+/// Key comparisons one [`BucketMode`] made on the same keys, from
+/// [`compare_bucket_modes`].
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BucketModeStats {
+    pub mode: BucketMode,
+    pub average_chain_length: f64,
+    /// Comparisons per insert of a new key
+    pub insert_comparisons: f64,
+    /// Comparisons per lookup of a present key, and of an absent one
+    pub hit_comparisons: f64,
+    pub miss_comparisons: f64,
+    /// hit_comparisons and miss_comparisons relative to the unsorted
+    /// `Vec` chains' (1.0 for `Vec` itself)
+    pub hit_ratio_to_unsorted: f64,
+    pub miss_ratio_to_unsorted: f64,
+}
+
+/// [`compare_bucket_modes`] for Rust callers.
+pub fn try_compare_bucket_modes(
+    keys: &[String],
+    bucket_count: u32,
+) -> Result<Vec<BucketModeStats>, String> {
+    if keys.is_empty() {
+        return Err("no keys to compare on".to_string());
+    }
+    let present: std::collections::HashSet<&str> = keys.iter().map(String::as_str).collect();
+    let misses: Vec<String> = keys
+        .iter()
+        .map(|key| format!("{}~miss", key))
+        .filter(|key| !present.contains(key.as_str()))
+        .collect();
+    let mut stats = Vec::new();
+    for mode in [
+        BucketMode::Vec,
+        BucketMode::LinkedList,
+        BucketMode::SortedVec,
+    ] {
+        let mut map = HashMapBuilder::new()
+            .bucket_count(bucket_count)
+            .bucket_mode(mode)
+            .try_build()?;
+        let comparisons = |map: &HashMap| map.get_metrics().chain_comparisons as f64;
+        for (i, key) in keys.iter().enumerate() {
+            map.insert(key.clone(), i as u32);
+        }
+        let inserted = comparisons(&map);
+        for key in keys {
+            map.contains_key(key);
+        }
+        let hits = comparisons(&map) - inserted;
+        for key in &misses {
+            map.contains_key(key);
+        }
+        let missed = comparisons(&map) - inserted - hits;
+        let per = |total: f64, count: usize| total / count.max(1) as f64;
+        stats.push(BucketModeStats {
+            mode,
+            average_chain_length: map.len() as f64 / map.bucket_count() as f64,
+            insert_comparisons: per(inserted, map.len()),
+            hit_comparisons: per(hits, keys.len()),
+            miss_comparisons: per(missed, misses.len()),
+            hit_ratio_to_unsorted: 1.0,
+            miss_ratio_to_unsorted: 1.0,
+        });
+    }
+    let baseline = stats[0];
+    let ratio = |ours: f64, base: f64| if base > 0.0 { ours / base } else { 1.0 };
+    for s in &mut stats {
+        s.hit_ratio_to_unsorted = ratio(s.hit_comparisons, baseline.hit_comparisons);
+        s.miss_ratio_to_unsorted = ratio(s.miss_comparisons, baseline.miss_comparisons);
+    }
+    Ok(stats)
+}
+
+/// Load `keys` into one fixed-size HashMap per [`BucketMode`], look each
+/// up along with as many absent keys, and report the key comparisons per
+/// insert and lookup, relative to the unsorted `Vec` chains.
+///
+/// Few buckets make long chains, where the modes part ways: scanning
+/// averages half the chain for a hit and all of it for a miss, binary
+/// search about log2 of it for either, while the sorted chain pays for
+/// its order with shifting on insert.
+///
+/// # Example
+/// ```javascript
+/// for (const s of compare_bucket_modes(words, 16)) {
+///   console.log(BucketMode[s.mode], s.hit_comparisons.toFixed(1), s.miss_ratio_to_unsorted.toFixed(2));
+/// }
+/// ```
+#[wasm_bindgen]
+pub fn compare_bucket_modes(
+    keys: Vec<String>,
+    bucket_count: u32,
+) -> Result<Vec<BucketModeStats>, JsValue> {
+    try_compare_bucket_modes(&keys, bucket_count).map_err(|e| JsValue::from_str(&e))
+}
+
+pub(crate) struct Link {
+    key: String,
+    value: u32,
+    sequence: u64,
+    next: Option<Box<Link>>,
+}
+
+/// One bucket's chain. The variant is fixed by the map's [`BucketMode`].
+///
+/// Every operation adds the number of key comparisons it made to
+/// `comparisons`, which is what makes the modes comparable. Each entry
+/// also carries the map-wide insertion sequence number it was given when
+/// its key was first added.
+pub(crate) enum Chain {
+    Vec(Vec<(String, u32, u64)>),
+    Linked { head: Option<Box<Link>>, len: usize },
+    Sorted(Vec<(String, u32, u64)>),
+}
+
+impl Chain {
+    pub(crate) fn new(mode: BucketMode) -> Chain {
+        match mode {
+            BucketMode::Vec => Chain::Vec(Vec::new()),
+            BucketMode::LinkedList => Chain::Linked { head: None, len: 0 },
+            BucketMode::SortedVec => Chain::Sorted(Vec::new()),
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        match self {
+            Chain::Vec(entries) | Chain::Sorted(entries) => entries.len(),
+            Chain::Linked { len, .. } => *len,
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Binary search a sorted chain, counting each probe.
+    fn search_sorted(
+        entries: &[(String, u32, u64)],
+        key: &str,
+        comparisons: &mut u32,
+    ) -> Result<usize, usize> {
+        entries.binary_search_by(|(k, _, _)| {
+            *comparisons += 1;
+            k.as_str().cmp(key)
+        })
+    }
+
+    pub(crate) fn get(&self, key: &str, comparisons: &mut u32) -> Option<u32> {
+        match self {
+            Chain::Vec(entries) => entries.iter().find_map(|(k, v, _)| {
+                *comparisons += 1;
+                (k == key).then_some(*v)
+            }),
+            Chain::Linked { head, .. } => {
+                let mut link = head;
+                while let Some(node) = link {
+                    *comparisons += 1;
+                    if node.key == key {
+                        return Some(node.value);
+                    }
+                    link = &node.next;
+                }
+                None
+            }
+            Chain::Sorted(entries) => Self::search_sorted(entries, key, comparisons)
+                .ok()
+                .map(|i| entries[i].1),
+        }
+    }
+
+    /// Insert or update, returning true if the key is new. `sequence` is
+    /// only stored for a new key; an update keeps the original one.
+    pub(crate) fn insert(
+        &mut self,
+        key: String,
+        value: u32,
+        sequence: u64,
+        comparisons: &mut u32,
+    ) -> bool {
+        match self {
+            Chain::Vec(entries) => {
+                for entry in entries.iter_mut() {
+                    *comparisons += 1;
+                    if entry.0 == key {
+                        entry.1 = value;
+                        return false;
+                    }
+                }
+                entries.push((key, value, sequence));
+                true
+            }
+            Chain::Linked { head, len } => {
+                let mut link = head.as_deref_mut();
+                while let Some(node) = link {
+                    *comparisons += 1;
+                    if node.key == key {
+                        node.value = value;
+                        return false;
+                    }
+                    link = node.next.as_deref_mut();
+                }
+                let next = head.take();
+                *head = Some(Box::new(Link {
+                    key,
+                    value,
+                    sequence,
+                    next,
+                }));
+                *len += 1;
+                true
+            }
+            Chain::Sorted(entries) => match Self::search_sorted(entries, &key, comparisons) {
+                Ok(i) => {
+                    entries[i].1 = value;
+                    false
+                }
+                Err(i) => {
+                    entries.insert(i, (key, value, sequence));
+                    true
+                }
+            },
+        }
+    }
+
+    /// Remove a key, returning its sequence number if it was present.
+    pub(crate) fn remove(&mut self, key: &str, comparisons: &mut u32) -> Option<u64> {
+        match self {
+            Chain::Vec(entries) => {
+                let position = entries.iter().position(|(k, _, _)| {
+                    *comparisons += 1;
+                    k == key
+                });
+                position.map(|i| entries.remove(i).2)
+            }
+            Chain::Linked { head, len } => {
+                let mut link = head;
+                loop {
+                    match link {
+                        None => return None,
+                        Some(node) if node.key == key => {
+                            *comparisons += 1;
+                            let sequence = node.sequence;
+                            *link = node.next.take();
+                            *len -= 1;
+                            return Some(sequence);
+                        }
+                        Some(node) => {
+                            *comparisons += 1;
+                            link = &mut node.next;
+                        }
+                    }
+                }
+            }
+            Chain::Sorted(entries) => Self::search_sorted(entries, key, comparisons)
+                .ok()
+                .map(|i| entries.remove(i).2),
+        }
+    }
+
+    /// Remove and return every entry, leaving the chain empty.
+    pub(crate) fn drain(&mut self) -> Vec<(String, u32, u64)> {
+        match self {
+            Chain::Vec(entries) | Chain::Sorted(entries) => std::mem::take(entries),
+            Chain::Linked { head, len } => {
+                let mut out = Vec::with_capacity(*len);
+                let mut link = head.take();
+                while let Some(mut node) = link {
+                    link = node.next.take();
+                    out.push((node.key, node.value, node.sequence));
+                }
+                *len = 0;
+                out
+            }
+        }
+    }
+
+    /// The `index`-th entry in chain order.
+    pub(crate) fn nth(&self, index: usize) -> Option<(&str, u32)> {
+        match self {
+            Chain::Vec(entries) | Chain::Sorted(entries) => {
+                entries.get(index).map(|(k, v, _)| (k.as_str(), *v))
+            }
+            Chain::Linked { head, .. } => {
+                let mut link = head.as_deref();
+                for _ in 0..index {
+                    link = link?.next.as_deref();
+                }
+                link.map(|node| (node.key.as_str(), node.value))
+            }
+        }
+    }
+
+    /// Entries with their sequence numbers, in chain order.
+    pub(crate) fn entries(&self) -> Vec<(&str, u32, u64)> {
+        match self {
+            Chain::Vec(entries) | Chain::Sorted(entries) => entries
+                .iter()
+                .map(|(k, v, sequence)| (k.as_str(), *v, *sequence))
+                .collect(),
+            Chain::Linked { head, len } => {
+                let mut out = Vec::with_capacity(*len);
+                let mut link = head;
+                while let Some(node) = link {
+                    out.push((node.key.as_str(), node.value, node.sequence));
+                    link = &node.next;
+                }
+                out
+            }
+        }
+    }
+
+    /// Count this chain's heap usage (not the chain header itself).
+    pub(crate) fn measure(&self, report: &mut MemoryReport) {
+        match self {
+            Chain::Vec(entries) | Chain::Sorted(entries) => {
+                report.add_vec(entries, entries.len());
+                for (key, _, _) in entries {
+                    report.add_string(key, key.capacity());
+                }
+            }
+            Chain::Linked { head, .. } => {
+                let mut link = head;
+                while let Some(node) = link {
+                    report.add_exact(size_of::<Link>());
+                    report.add_string(&node.key, node.key.capacity());
+                    link = &node.next;
+                }
+            }
+        }
+    }
+
+    /// Bytes this chain reserves without using: spare entry capacity and
+    /// key string slack.
+    pub(crate) fn slack_bytes(&self) -> usize {
+        let mut report = MemoryReport::default();
+        self.measure(&mut report);
+        report.slack_bytes()
+    }
+
+    /// `measure`, split into entry overhead, key bytes and spare capacity.
+    pub(crate) fn measure_usage(&self, usage: &mut MemoryUsage) {
+        usage.entries += self.len();
+        match self {
+            Chain::Vec(entries) | Chain::Sorted(entries) => {
+                usage.add_entries(entries, entries.len());
+                for (key, _, _) in entries {
+                    usage.add_key(key, key.capacity());
+                }
+            }
+            Chain::Linked { head, .. } => {
+                let mut link = head;
+                while let Some(node) = link {
+                    usage.entry_bytes += size_of::<Link>();
+                    usage.add_key(&node.key, node.key.capacity());
+                    link = &node.next;
+                }
+            }
+        }
+    }
+
+    pub(crate) fn shrink(&mut self) {
+        match self {
+            Chain::Vec(entries) | Chain::Sorted(entries) => {
+                entries.shrink_to_fit();
+                for (key, _, _) in entries.iter_mut() {
+                    key.shrink_to_fit();
+                }
+            }
+            Chain::Linked { head, .. } => {
+                let mut link = head.as_deref_mut();
+                while let Some(node) = link {
+                    node.key.shrink_to_fit();
+                    link = node.next.as_deref_mut();
+                }
+            }
+        }
+    }
+}
+
+impl Clone for Chain {
+    // Rebuild a linked list front to back without recursing, as in `drop`
+    fn clone(&self) -> Chain {
+        match self {
+            Chain::Vec(entries) => Chain::Vec(entries.clone()),
+            Chain::Sorted(entries) => Chain::Sorted(entries.clone()),
+            Chain::Linked { len, .. } => {
+                let mut head = None;
+                for (key, value, sequence) in self.entries().into_iter().rev() {
+                    head = Some(Box::new(Link {
+                        key: key.to_string(),
+                        value,
+                        sequence,
+                        next: head,
+                    }));
+                }
+                Chain::Linked { head, len: *len }
+            }
+        }
+    }
+}
+
+impl Drop for Chain {
+    // Unlink iteratively so a very long list can't overflow the stack
+    fn drop(&mut self) {
+        if let Chain::Linked { head, .. } = self {
+            let mut link = head.take();
+            while let Some(mut node) = link {
+                link = node.next.take();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MODES: [BucketMode; 3] = [
+        BucketMode::Vec,
+        BucketMode::LinkedList,
+        BucketMode::SortedVec,
+    ];
+
+    #[test]
+    fn test_every_mode_behaves_like_a_map() {
+        for mode in MODES {
+            let mut chain = Chain::new(mode);
+            let mut comparisons = 0;
+            for (sequence, key) in ["d", "b", "a", "c"].into_iter().enumerate() {
+                assert!(chain.insert(key.to_string(), 1, sequence as u64, &mut comparisons));
+            }
+            assert!(!chain.insert("b".to_string(), 2, 9, &mut comparisons));
+            assert_eq!(chain.len(), 4, "{:?}", mode);
+            assert_eq!(chain.get("b", &mut comparisons), Some(2), "{:?}", mode);
+            assert_eq!(chain.get("z", &mut comparisons), None, "{:?}", mode);
+            assert_eq!(chain.remove("a", &mut comparisons), Some(2), "{:?}", mode);
+            assert_eq!(chain.remove("a", &mut comparisons), None, "{:?}", mode);
+            assert_eq!(chain.remove("d", &mut comparisons), Some(0), "{:?}", mode);
+            let mut entries = chain.entries();
+            entries.sort();
+            // The update kept b's original sequence number
+            assert_eq!(entries, [("b", 2, 1), ("c", 1, 3)], "{:?}", mode);
+            assert!(comparisons > 0);
+        }
+    }
+
+    #[test]
+    fn test_sorted_chain_uses_fewer_comparisons() {
+        let mut counts = Vec::new();
+        for mode in MODES {
+            let mut chain = Chain::new(mode);
+            let mut comparisons = 0;
+            for i in 0..64 {
+                chain.insert(format!("k{:02}", i), i, i as u64, &mut comparisons);
+            }
+            let mut lookups = 0;
+            for i in 0..64 {
+                chain.get(&format!("k{:02}", i), &mut lookups);
+            }
+            counts.push(lookups);
+        }
+        // A full scan averages n/2 comparisons, binary search about log2(n)
+        assert_eq!(counts[0], counts[1]);
+        assert!(counts[2] * 4 < counts[0]);
+        assert!(counts[2] <= 64 * 7);
+    }
+
+    #[test]
+    fn test_compare_bucket_modes() {
+        let keys: Vec<String> = (0..2_000).map(|i| format!("key{}", i)).collect();
+        let stats = try_compare_bucket_modes(&keys, 8).unwrap();
+        assert_eq!(stats.len(), MODES.len());
+        let (vec, sorted) = (stats[0], stats[2]);
+        assert_eq!(
+            (vec.mode, sorted.mode),
+            (BucketMode::Vec, BucketMode::SortedVec)
+        );
+        assert_eq!(vec.average_chain_length, 250.0);
+        // Chains of ~250: about 125 vs 8 comparisons for a hit, 250 vs 8
+        // for a miss
+        assert!(vec.hit_comparisons > 100.0 && vec.miss_comparisons > 200.0);
+        assert!(sorted.hit_comparisons < 10.0 && sorted.miss_comparisons < 10.0);
+        assert!(sorted.hit_ratio_to_unsorted < 0.1);
+        assert_eq!(stats[1].hit_ratio_to_unsorted, 1.0);
+        assert_eq!(vec.miss_ratio_to_unsorted, 1.0);
+        assert!(try_compare_bucket_modes(&[], 8).is_err());
+        assert!(try_compare_bucket_modes(&keys, 0).is_err());
+    }
+}
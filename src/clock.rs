@@ -0,0 +1,33 @@
+/// Current time in milliseconds, suitable for measuring elapsed durations.
+///
+/// In the browser this is `Date.now()` because `std::time::Instant` panics on
+/// `wasm32-unknown-unknown`. Natively it is measured from the first call.
+#[cfg(target_arch = "wasm32")]
+pub fn now_ms() -> f64 {
+    js_sys::Date::now()
+}
+
+/// Current time in milliseconds, suitable for measuring elapsed durations.
+///
+/// In the browser this is `Date.now()` because `std::time::Instant` panics on
+/// `wasm32-unknown-unknown`. Natively it is measured from the first call.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn now_ms() -> f64 {
+    use std::sync::OnceLock;
+    use std::time::Instant;
+
+    static START: OnceLock<Instant> = OnceLock::new();
+    START.get_or_init(Instant::now).elapsed().as_secs_f64() * 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clock_is_monotonic() {
+        let a = now_ms();
+        let b = now_ms();
+        assert!(b >= a);
+    }
+}
@@ -0,0 +1,448 @@
+use crate::benchmark::BenchmarkResult;
+use wasm_bindgen::prelude::*;
+
+/// Runs within this relative difference of each other are reported as a tie.
+const TIE_THRESHOLD: f64 = 0.05;
+
+/// Which side of an A/B comparison came out ahead.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Winner {
+    A,
+    B,
+    Tie,
+}
+
+/// One structure metric present in both runs.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MetricDelta {
+    pub name: String,
+    pub a: f64,
+    pub b: f64,
+}
+
+impl MetricDelta {
+    /// Absolute change from A to B.
+    pub fn delta(&self) -> f64 {
+        self.b - self.a
+    }
+
+    /// Change from A to B relative to A, or `None` when A is zero.
+    pub fn relative(&self) -> Option<f64> {
+        if self.a == 0.0 {
+            None
+        } else {
+            Some((self.b - self.a) / self.a)
+        }
+    }
+}
+
+/// Comparison of A and B on a single workload.
+#[derive(Clone, Debug)]
+pub struct WorkloadComparison {
+    pub scenario: String,
+    /// False when the two runs used different scenario versions.
+    pub comparable: bool,
+    pub a_run_ms: f64,
+    pub b_run_ms: f64,
+    /// How many times faster B loaded than A (`a_load_ms / b_load_ms`).
+    pub load_speedup: f64,
+    /// How many times faster B ran the measured phase than A.
+    pub run_speedup: f64,
+    pub winner: Winner,
+    pub metric_deltas: Vec<MetricDelta>,
+}
+
+/// Structured A/B report across one or more workloads.
+///
+/// # Example
+/// ```javascript
+/// const a = run_scenario("session-cache-churn", "hashmap");
+/// const b = run_scenario("session-cache-churn", "rbtree");
+/// const report = compare(a, b);
+/// console.log(report.to_markdown());
+/// ```
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct ComparisonReport {
+    label_a: String,
+    label_b: String,
+    workloads: Vec<WorkloadComparison>,
+    /// Scenarios present in only one of the two suites.
+    unmatched: Vec<String>,
+}
+
+impl ComparisonReport {
+    pub fn workloads(&self) -> &[WorkloadComparison] {
+        &self.workloads
+    }
+
+    pub fn unmatched(&self) -> &[String] {
+        &self.unmatched
+    }
+
+    fn label(&self, winner: Winner) -> &str {
+        match winner {
+            Winner::A => &self.label_a,
+            Winner::B => &self.label_b,
+            Winner::Tie => "tie",
+        }
+    }
+
+    fn overall(&self) -> Winner {
+        let (a, b) = (self.wins(Winner::A), self.wins(Winner::B));
+        match a.cmp(&b) {
+            std::cmp::Ordering::Greater => Winner::A,
+            std::cmp::Ordering::Less => Winner::B,
+            std::cmp::Ordering::Equal => Winner::Tie,
+        }
+    }
+
+    /// Workloads won by `side`, leaving out version mismatches.
+    fn wins(&self, side: Winner) -> u32 {
+        self.workloads
+            .iter()
+            .filter(|w| w.comparable && w.winner == side)
+            .count() as u32
+    }
+}
+
+#[wasm_bindgen]
+impl ComparisonReport {
+    pub fn label_a(&self) -> String {
+        self.label_a.clone()
+    }
+
+    pub fn label_b(&self) -> String {
+        self.label_b.clone()
+    }
+
+    pub fn workload_count(&self) -> usize {
+        self.workloads.len()
+    }
+
+    /// Workloads left out of the win counts and overall winner because the
+    /// two runs used different scenario versions.
+    pub fn excluded_count(&self) -> u32 {
+        self.workloads.iter().filter(|w| !w.comparable).count() as u32
+    }
+
+    /// Number of workloads won by A.
+    pub fn wins_a(&self) -> u32 {
+        self.wins(Winner::A)
+    }
+
+    /// Number of workloads won by B.
+    pub fn wins_b(&self) -> u32 {
+        self.wins(Winner::B)
+    }
+
+    /// Label of the side that won more comparable workloads, or `"tie"`.
+    pub fn overall_winner(&self) -> String {
+        self.label(self.overall()).to_string()
+    }
+
+    /// Winner label for the workload at `index`.
+    pub fn winner_of(&self, index: usize) -> Option<String> {
+        self.workloads
+            .get(index)
+            .map(|w| self.label(w.winner).to_string())
+    }
+
+    /// Measured-phase speedup of B over A for the workload at `index`.
+    pub fn run_speedup_of(&self, index: usize) -> Option<f64> {
+        self.workloads.get(index).map(|w| w.run_speedup)
+    }
+
+    /// Render the report as a shareable markdown document.
+    pub fn to_markdown(&self) -> String {
+        let mut out = format!(
+            "## A/B comparison: {} vs {}\n\n",
+            self.label_a, self.label_b
+        );
+        out.push_str(&format!(
+            "| Workload | {} (ms) | {} (ms) | Speedup (B/A) | Winner |\n",
+            self.label_a, self.label_b
+        ));
+        out.push_str("|---|---:|---:|---:|---|\n");
+        for w in &self.workloads {
+            let note = if w.comparable {
+                ""
+            } else {
+                " (version mismatch)"
+            };
+            out.push_str(&format!(
+                "| {}{} | {:.2} | {:.2} | {:.2}x | {} |\n",
+                w.scenario,
+                note,
+                w.a_run_ms,
+                w.b_run_ms,
+                w.run_speedup,
+                self.label(w.winner)
+            ));
+        }
+        out.push_str(&format!(
+            "\n**Overall:** {} ({} wins for {}, {} wins for {}",
+            self.label(self.overall()),
+            self.wins_a(),
+            self.label_a,
+            self.wins_b(),
+            self.label_b
+        ));
+        match self.excluded_count() {
+            0 => out.push_str(")\n"),
+            n => out.push_str(&format!("; {} excluded for version mismatch)\n", n)),
+        }
+
+        for w in self
+            .workloads
+            .iter()
+            .filter(|w| !w.metric_deltas.is_empty())
+        {
+            out.push_str(&format!("\n### Metric deltas: {}\n\n", w.scenario));
+            out.push_str(&format!(
+                "| Metric | {} | {} | Change |\n|---|---:|---:|---:|\n",
+                self.label_a, self.label_b
+            ));
+            for d in &w.metric_deltas {
+                let change = match d.relative() {
+                    Some(r) => format!("{:+.1}%", r * 100.0),
+                    None => format!("{:+}", d.delta()),
+                };
+                out.push_str(&format!(
+                    "| {} | {} | {} | {} |\n",
+                    d.name, d.a, d.b, change
+                ));
+            }
+        }
+
+        if !self.unmatched.is_empty() {
+            out.push_str(&format!(
+                "\n_Not compared (missing on one side): {}_\n",
+                self.unmatched.join(", ")
+            ));
+        }
+        out
+    }
+}
+
+/// Compare two runs of the same workload.
+pub fn compare_results(a: &BenchmarkResult, b: &BenchmarkResult) -> ComparisonReport {
+    compare_result_sets(std::slice::from_ref(a), std::slice::from_ref(b))
+}
+
+/// Compare two suites of runs, pairing results by scenario name.
+pub fn compare_result_sets(a: &[BenchmarkResult], b: &[BenchmarkResult]) -> ComparisonReport {
+    let structure_a = a.first().map_or("A", |r| r.structure.as_str());
+    let structure_b = b.first().map_or("B", |r| r.structure.as_str());
+    let (label_a, label_b) = if structure_a == structure_b {
+        (
+            format!("A ({})", structure_a),
+            format!("B ({})", structure_b),
+        )
+    } else {
+        (structure_a.to_string(), structure_b.to_string())
+    };
+
+    let mut workloads = Vec::new();
+    let mut unmatched = Vec::new();
+    for ra in a {
+        match b.iter().find(|rb| rb.scenario == ra.scenario) {
+            Some(rb) => workloads.push(compare_pair(ra, rb)),
+            None => unmatched.push(ra.scenario.clone()),
+        }
+    }
+    for rb in b {
+        if !a.iter().any(|ra| ra.scenario == rb.scenario) {
+            unmatched.push(rb.scenario.clone());
+        }
+    }
+
+    ComparisonReport {
+        label_a,
+        label_b,
+        workloads,
+        unmatched,
+    }
+}
+
+fn compare_pair(a: &BenchmarkResult, b: &BenchmarkResult) -> WorkloadComparison {
+    let run_speedup = ratio(a.run_ms, b.run_ms);
+    let winner = if (run_speedup - 1.0).abs() <= TIE_THRESHOLD {
+        Winner::Tie
+    } else if run_speedup > 1.0 {
+        Winner::B
+    } else {
+        Winner::A
+    };
+
+    let metric_deltas = a
+        .metrics()
+        .iter()
+        .filter_map(|(name, va)| {
+            b.metrics()
+                .iter()
+                .find(|(n, _)| n == name)
+                .map(|(_, vb)| MetricDelta {
+                    name: name.clone(),
+                    a: *va,
+                    b: *vb,
+                })
+        })
+        .collect();
+
+    WorkloadComparison {
+        scenario: a.scenario.clone(),
+        comparable: a.scenario_version == b.scenario_version,
+        a_run_ms: a.run_ms,
+        b_run_ms: b.run_ms,
+        load_speedup: ratio(a.load_ms, b.load_ms),
+        run_speedup,
+        winner,
+        metric_deltas,
+    }
+}
+
+/// `a / b`, treating two zero-length timings as equal.
+fn ratio(a: f64, b: f64) -> f64 {
+    if b > 0.0 {
+        a / b
+    } else if a > 0.0 {
+        f64::INFINITY
+    } else {
+        1.0
+    }
+}
+
+/// Compare two benchmark results of the same workload.
+#[wasm_bindgen]
+pub fn compare(a: &BenchmarkResult, b: &BenchmarkResult) -> ComparisonReport {
+    compare_results(a, b)
+}
+
+/// Compare two suites of benchmark results, pairing them by scenario name.
+#[wasm_bindgen]
+pub fn compare_suites(a: Vec<BenchmarkResult>, b: Vec<BenchmarkResult>) -> ComparisonReport {
+    compare_result_sets(&a, &b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::benchmark::run_named;
+
+    fn with_run_ms(mut result: BenchmarkResult, run_ms: f64) -> BenchmarkResult {
+        result.run_ms = run_ms;
+        result
+    }
+
+    #[test]
+    fn test_faster_side_wins() {
+        let a = with_run_ms(run_named("sorted-ingest", "bst").unwrap(), 40.0);
+        let b = with_run_ms(run_named("sorted-ingest", "rbtree").unwrap(), 10.0);
+        let report = compare_results(&a, &b);
+
+        assert_eq!(report.label_a(), "bst");
+        assert_eq!(report.label_b(), "rbtree");
+        let w = &report.workloads()[0];
+        assert!(w.comparable);
+        assert_eq!(w.winner, Winner::B);
+        assert!((w.run_speedup - 4.0).abs() < 1e-9);
+        assert_eq!(report.overall_winner(), "rbtree");
+    }
+
+    #[test]
+    fn test_close_timings_tie() {
+        let a = with_run_ms(run_named("sorted-ingest", "hashmap").unwrap(), 10.0);
+        let b = with_run_ms(run_named("sorted-ingest", "hashmap").unwrap(), 10.2);
+        let report = compare_results(&a, &b);
+        assert_eq!(report.workloads()[0].winner, Winner::Tie);
+        assert_eq!(report.label_a(), "A (hashmap)");
+        assert_eq!(report.overall_winner(), "tie");
+    }
+
+    #[test]
+    fn test_metric_deltas_for_shared_metrics() {
+        let a = run_named("adversarial-collisions", "hashmap").unwrap();
+        let b = run_named("adversarial-collisions", "hashmap").unwrap();
+        let report = compare_results(&a, &b);
+        let deltas = &report.workloads()[0].metric_deltas;
+        assert_eq!(deltas.len(), a.metrics().len());
+        assert!(deltas.iter().all(|d| d.delta() == 0.0));
+
+        // Different structures only share total_insertions
+        let c = run_named("adversarial-collisions", "skiplist").unwrap();
+        let report = compare_results(&a, &c);
+        let names: Vec<_> = report.workloads()[0]
+            .metric_deltas
+            .iter()
+            .map(|d| d.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["total_insertions"]);
+    }
+
+    #[test]
+    fn test_version_mismatch_is_left_out_of_overall() {
+        let a = vec![
+            with_run_ms(run_named("sorted-ingest", "bst").unwrap(), 10.0),
+            with_run_ms(run_named("url-paths", "bst").unwrap(), 40.0),
+            with_run_ms(run_named("adversarial-collisions", "bst").unwrap(), 40.0),
+        ];
+        let mut b = vec![
+            with_run_ms(run_named("sorted-ingest", "rbtree").unwrap(), 40.0),
+            with_run_ms(run_named("url-paths", "rbtree").unwrap(), 10.0),
+            with_run_ms(run_named("adversarial-collisions", "rbtree").unwrap(), 10.0),
+        ];
+        // Counted, rbtree would win two workloads to one
+        b[2].scenario_version += 1;
+        let report = compare_result_sets(&a, &b);
+
+        assert!(!report.workloads()[2].comparable);
+        assert_eq!(report.workloads()[2].winner, Winner::B);
+        assert_eq!((report.wins_a(), report.wins_b()), (1, 1));
+        assert_eq!(report.excluded_count(), 1);
+        assert_eq!(report.overall_winner(), "tie");
+        assert!(report
+            .to_markdown()
+            .contains("(1 wins for bst, 1 wins for rbtree; 1 excluded for version mismatch)"));
+    }
+
+    #[test]
+    fn test_suites_pair_by_scenario() {
+        let a = vec![
+            run_named("sorted-ingest", "bst").unwrap(),
+            run_named("url-paths", "bst").unwrap(),
+        ];
+        let b = vec![
+            run_named("url-paths", "trie").unwrap(),
+            run_named("adversarial-collisions", "trie").unwrap(),
+        ];
+        let report = compare_result_sets(&a, &b);
+        assert_eq!(report.workload_count(), 1);
+        assert_eq!(report.workloads()[0].scenario, "url-paths");
+        assert_eq!(
+            report.unmatched(),
+            &[
+                "sorted-ingest".to_string(),
+                "adversarial-collisions".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_markdown_rendering() {
+        let a = with_run_ms(run_named("sorted-ingest", "bst").unwrap(), 30.0);
+        let b = with_run_ms(run_named("sorted-ingest", "skiplist").unwrap(), 15.0);
+        let md = compare_results(&a, &b).to_markdown();
+        assert!(md.starts_with("## A/B comparison: bst vs skiplist"));
+        assert!(md.contains("| sorted-ingest | 30.00 | 15.00 | 2.00x | skiplist |"));
+        assert!(md.contains("### Metric deltas: sorted-ingest"));
+        assert!(md.contains("| total_insertions | 5000 | 5000 | +0.0% |"));
+    }
+
+    #[test]
+    fn test_ratio_handles_zero() {
+        assert_eq!(ratio(0.0, 0.0), 1.0);
+        assert_eq!(ratio(1.0, 0.0), f64::INFINITY);
+        assert_eq!(ratio(2.0, 1.0), 2.0);
+    }
+}
@@ -0,0 +1,339 @@
+use crate::clock::now_ms;
+use crate::kv_store::new_store;
+use crate::rng::DefaultRng;
+use rand::Rng;
+use wasm_bindgen::prelude::*;
+
+/// Sizes used when the caller doesn't pick a sweep.
+pub const DEFAULT_SWEEP_SIZES: [u32; 4] = [1_000, 10_000, 100_000, 1_000_000];
+
+/// Per-op time may grow by at most this fraction across the whole sweep
+/// before we stop calling it constant.
+const CONSTANT_GROWTH_TOLERANCE: f64 = 0.5;
+
+/// Seed for sweep keys and lookup order, fixed so sweeps are repeatable.
+const SWEEP_SEED: u64 = 0xB160;
+
+/// Asymptotic classes we try to fit per-operation timings against.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ComplexityClass {
+    Constant,
+    Logarithmic,
+    Linear,
+    Linearithmic,
+}
+
+impl ComplexityClass {
+    pub const ALL: [ComplexityClass; 4] = [
+        ComplexityClass::Constant,
+        ComplexityClass::Logarithmic,
+        ComplexityClass::Linear,
+        ComplexityClass::Linearithmic,
+    ];
+
+    /// Big-O notation for this class.
+    pub fn label(self) -> &'static str {
+        match self {
+            ComplexityClass::Constant => "O(1)",
+            ComplexityClass::Logarithmic => "O(log n)",
+            ComplexityClass::Linear => "O(n)",
+            ComplexityClass::Linearithmic => "O(n log n)",
+        }
+    }
+
+    /// Growth function f(n) for the model `t = a + b * f(n)`.
+    fn growth(self, n: f64) -> f64 {
+        match self {
+            ComplexityClass::Constant => 1.0,
+            ComplexityClass::Logarithmic => n.log2(),
+            ComplexityClass::Linear => n,
+            ComplexityClass::Linearithmic => n * n.log2(),
+        }
+    }
+}
+
+/// Least-squares fit of `t = intercept + slope * f(n)` for one class.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ClassFit {
+    pub class: ComplexityClass,
+    pub intercept: f64,
+    pub slope: f64,
+    /// Coefficient of determination. Always 0 for the constant model, which
+    /// by definition explains none of the variance.
+    pub r_squared: f64,
+}
+
+/// Fit every class against `(n, time)` points and pick the best one.
+///
+/// The best class is the growth model with the highest R², unless timings
+/// stayed within [`CONSTANT_GROWTH_TOLERANCE`] of the smallest size or the
+/// best model has a non-positive slope, in which case it is O(1).
+pub fn fit_complexity(points: &[(f64, f64)]) -> (ComplexityClass, Vec<ClassFit>) {
+    let fits: Vec<ClassFit> = ComplexityClass::ALL
+        .iter()
+        .map(|&class| fit_class(points, class))
+        .collect();
+
+    let best_growth = fits
+        .iter()
+        .filter(|f| f.class != ComplexityClass::Constant)
+        .max_by(|a, b| a.r_squared.total_cmp(&b.r_squared))
+        .copied();
+
+    let min_time = points.iter().map(|p| p.1).fold(f64::INFINITY, f64::min);
+    let max_time = points.iter().map(|p| p.1).fold(0.0, f64::max);
+    let flat = min_time <= 0.0 || max_time / min_time - 1.0 <= CONSTANT_GROWTH_TOLERANCE;
+
+    let best = match best_growth {
+        Some(fit) if !flat && fit.slope > 0.0 => fit.class,
+        _ => ComplexityClass::Constant,
+    };
+    (best, fits)
+}
+
+fn fit_class(points: &[(f64, f64)], class: ComplexityClass) -> ClassFit {
+    let count = points.len() as f64;
+    let mean_t = points.iter().map(|p| p.1).sum::<f64>() / count.max(1.0);
+
+    if class == ComplexityClass::Constant || points.len() < 2 {
+        return ClassFit {
+            class,
+            intercept: mean_t,
+            slope: 0.0,
+            r_squared: 0.0,
+        };
+    }
+
+    let xs: Vec<f64> = points.iter().map(|p| class.growth(p.0)).collect();
+    let mean_x = xs.iter().sum::<f64>() / count;
+    let mut sxx = 0.0;
+    let mut sxy = 0.0;
+    for (x, &(_, t)) in xs.iter().zip(points) {
+        sxx += (x - mean_x) * (x - mean_x);
+        sxy += (x - mean_x) * (t - mean_t);
+    }
+    let slope = if sxx > 0.0 { sxy / sxx } else { 0.0 };
+    let intercept = mean_t - slope * mean_x;
+
+    let mut ss_res = 0.0;
+    let mut ss_tot = 0.0;
+    for (x, &(_, t)) in xs.iter().zip(points) {
+        let predicted = intercept + slope * x;
+        ss_res += (t - predicted) * (t - predicted);
+        ss_tot += (t - mean_t) * (t - mean_t);
+    }
+    let r_squared = if ss_tot > 0.0 {
+        1.0 - ss_res / ss_tot
+    } else {
+        0.0
+    };
+
+    ClassFit {
+        class,
+        intercept,
+        slope,
+        r_squared,
+    }
+}
+
+/// Result of a size sweep for one structure.
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct ComplexityReport {
+    structure: String,
+    sizes: Vec<u32>,
+    per_op_us: Vec<f64>,
+    best: ComplexityClass,
+    fits: Vec<ClassFit>,
+}
+
+impl ComplexityReport {
+    pub fn fits(&self) -> &[ClassFit] {
+        &self.fits
+    }
+}
+
+#[wasm_bindgen]
+impl ComplexityReport {
+    pub fn structure(&self) -> String {
+        self.structure.clone()
+    }
+
+    /// Dataset sizes that were measured.
+    pub fn sizes(&self) -> Vec<u32> {
+        self.sizes.clone()
+    }
+
+    /// Mean lookup time in microseconds at each size.
+    pub fn timings_us(&self) -> Vec<f64> {
+        self.per_op_us.clone()
+    }
+
+    pub fn best_fit(&self) -> ComplexityClass {
+        self.best
+    }
+
+    /// R² of the given class's model (0 for the constant model).
+    pub fn r_squared(&self, class: ComplexityClass) -> f64 {
+        self.fits
+            .iter()
+            .find(|f| f.class == class)
+            .map_or(0.0, |f| f.r_squared)
+    }
+
+    /// One-line summary such as `rbtree: O(log n) (R² = 0.97)`.
+    pub fn summary(&self) -> String {
+        format!(
+            "{}: {} (R² = {:.3})",
+            self.structure,
+            self.best.label(),
+            self.r_squared(self.best)
+        )
+    }
+}
+
+/// Build `structure` at each size and time `lookups` random successful gets.
+///
+/// Keys are uniformly random so the unbalanced BST isn't measured on its
+/// degenerate case. Note that building is not free: structures that recompute
+/// metrics on every insert (RB-tree height, skip list average level, open
+/// addressing clustering) take O(n) per insert, so keep their sweeps at or
+/// below 100k entries.
+pub fn run_sweep(structure: &str, sizes: &[u32], lookups: u32) -> Result<ComplexityReport, String> {
+    if sizes.len() < 2 {
+        return Err("a sweep needs at least two sizes".to_string());
+    }
+    if lookups == 0 {
+        return Err("lookups must be positive".to_string());
+    }
+
+    let mut per_op_us = Vec::with_capacity(sizes.len());
+    for &size in sizes {
+        let size = size.max(1) as usize;
+        let mut store = new_store(structure, size)
+            .ok_or_else(|| format!("unknown structure '{}'", structure))?;
+
+        let mut rng = DefaultRng::seed_from(SWEEP_SEED);
+        let keys: Vec<String> = (0..size)
+            .map(|i| format!("{:08x}{}", rng.gen::<u32>(), i))
+            .collect();
+        for (i, key) in keys.iter().enumerate() {
+            store.kv_insert(key.clone(), i as u32);
+        }
+
+        let probes: Vec<&str> = (0..lookups)
+            .map(|_| keys[rng.gen_range(0..size)].as_str())
+            .collect();
+        let start = now_ms();
+        for key in &probes {
+            let _ = store.kv_get(key);
+        }
+        let elapsed_ms = now_ms() - start;
+        per_op_us.push(elapsed_ms * 1000.0 / lookups as f64);
+    }
+
+    let points: Vec<(f64, f64)> = sizes
+        .iter()
+        .zip(&per_op_us)
+        .map(|(&n, &t)| (n as f64, t))
+        .collect();
+    let (best, fits) = fit_complexity(&points);
+
+    Ok(ComplexityReport {
+        structure: structure.to_string(),
+        sizes: sizes.to_vec(),
+        per_op_us,
+        best,
+        fits,
+    })
+}
+
+/// The default 1k / 10k / 100k / 1M sweep.
+#[wasm_bindgen]
+pub fn default_sweep_sizes() -> Vec<u32> {
+    DEFAULT_SWEEP_SIZES.to_vec()
+}
+
+/// Sweep a structure across dataset sizes and fit the lookup-time curve.
+///
+/// # Example
+/// ```javascript
+/// const report = complexity_sweep("rbtree", new Uint32Array([1000, 10000, 100000]), 100000);
+/// console.log(report.summary()); // "rbtree: O(log n) (R² = 0.98)"
+/// ```
+#[wasm_bindgen]
+pub fn complexity_sweep(
+    structure: &str,
+    sizes: Vec<u32>,
+    lookups: u32,
+) -> Result<ComplexityReport, JsValue> {
+    run_sweep(structure, &sizes, lookups).map_err(|e| JsValue::from_str(&e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn synthetic(f: impl Fn(f64) -> f64) -> Vec<(f64, f64)> {
+        DEFAULT_SWEEP_SIZES
+            .iter()
+            .map(|&n| (n as f64, f(n as f64)))
+            .collect()
+    }
+
+    #[test]
+    fn test_fits_logarithmic() {
+        let (best, fits) = fit_complexity(&synthetic(|n| 0.2 + 0.05 * n.log2()));
+        assert_eq!(best, ComplexityClass::Logarithmic);
+        let log_fit = fits[1];
+        assert!((log_fit.r_squared - 1.0).abs() < 1e-9);
+        assert!((log_fit.slope - 0.05).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_fits_linear() {
+        let (best, _) = fit_complexity(&synthetic(|n| 1.0 + 0.001 * n));
+        assert_eq!(best, ComplexityClass::Linear);
+    }
+
+    #[test]
+    fn test_fits_linearithmic() {
+        let (best, _) = fit_complexity(&synthetic(|n| 0.0001 * n * n.log2()));
+        assert_eq!(best, ComplexityClass::Linearithmic);
+    }
+
+    #[test]
+    fn test_flat_timings_are_constant() {
+        let timings = [0.10, 0.12, 0.11, 0.13];
+        let points: Vec<(f64, f64)> = DEFAULT_SWEEP_SIZES
+            .iter()
+            .zip(timings)
+            .map(|(&n, t)| (n as f64, t))
+            .collect();
+        let (best, _) = fit_complexity(&points);
+        assert_eq!(best, ComplexityClass::Constant);
+    }
+
+    #[test]
+    fn test_decreasing_timings_are_constant() {
+        let (best, _) = fit_complexity(&synthetic(|n| 10.0 - n.log2() * 0.5));
+        assert_eq!(best, ComplexityClass::Constant);
+    }
+
+    #[test]
+    fn test_sweep_runs() {
+        let report = run_sweep("hashmap", &[100, 200, 400], 500).unwrap();
+        assert_eq!(report.sizes(), vec![100, 200, 400]);
+        assert_eq!(report.timings_us().len(), 3);
+        assert_eq!(report.fits().len(), 4);
+        assert!(report.summary().starts_with("hashmap: O("));
+    }
+
+    #[test]
+    fn test_sweep_validation() {
+        assert!(run_sweep("hashmap", &[100], 10).is_err());
+        assert!(run_sweep("hashmap", &[100, 200], 0).is_err());
+        assert!(run_sweep("nope", &[100, 200], 10).is_err());
+    }
+}
@@ -0,0 +1,462 @@
+use wasm_bindgen::prelude::*;
+
+/// Largest array container; past this a bitmap container is smaller.
+const ARRAY_MAX: usize = 4096;
+/// Words in a bitmap container: one bit for each of 2^16 low halves
+const BITMAP_WORDS: usize = 1024;
+/// Key and cardinality stored alongside each container
+const CONTAINER_OVERHEAD: usize = 4;
+
+#[derive(Clone, Debug, PartialEq)]
+enum Container {
+    /// Sorted low halves
+    Array(Vec<u16>),
+    Bitmap {
+        words: Vec<u64>,
+        len: usize,
+    },
+    /// Sorted, non-touching (start, length - 1) runs
+    Run(Vec<(u16, u16)>),
+}
+
+impl Container {
+    /// Array or bitmap holding the set bits of `words`, whichever is
+    /// smaller; None if no bit is set.
+    fn from_words(words: Vec<u64>) -> Option<Container> {
+        let len: usize = words.iter().map(|w| w.count_ones() as usize).sum();
+        if len == 0 {
+            None
+        } else if len <= ARRAY_MAX {
+            Some(Container::Array(Container::Bitmap { words, len }.values()))
+        } else {
+            Some(Container::Bitmap { words, len })
+        }
+    }
+
+    fn words(&self) -> Vec<u64> {
+        let mut words = vec![0u64; BITMAP_WORDS];
+        match self {
+            Container::Array(values) => {
+                for &v in values {
+                    words[v as usize / 64] |= 1 << (v % 64);
+                }
+            }
+            Container::Bitmap { words: w, .. } => words.copy_from_slice(w),
+            Container::Run(runs) => {
+                for &(start, extra) in runs {
+                    for v in start as usize..=start as usize + extra as usize {
+                        words[v / 64] |= 1 << (v % 64);
+                    }
+                }
+            }
+        }
+        words
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Container::Array(values) => values.len(),
+            Container::Bitmap { len, .. } => *len,
+            Container::Run(runs) => runs.iter().map(|&(_, extra)| extra as usize + 1).sum(),
+        }
+    }
+
+    fn contains(&self, low: u16) -> bool {
+        match self {
+            Container::Array(values) => values.binary_search(&low).is_ok(),
+            Container::Bitmap { words, .. } => words[low as usize / 64] >> (low % 64) & 1 == 1,
+            Container::Run(runs) => {
+                let i = runs.partition_point(|&(start, _)| start <= low);
+                i > 0 && {
+                    let (start, extra) = runs[i - 1];
+                    low - start <= extra
+                }
+            }
+        }
+    }
+
+    fn values(&self) -> Vec<u16> {
+        match self {
+            Container::Array(values) => values.clone(),
+            Container::Run(runs) => runs
+                .iter()
+                .flat_map(|&(start, extra)| start..=start + extra)
+                .collect(),
+            Container::Bitmap { words, len } => {
+                let mut values = Vec::with_capacity(*len);
+                for (i, &w) in words.iter().enumerate() {
+                    let mut bits = w;
+                    while bits != 0 {
+                        values.push((i * 64) as u16 + bits.trailing_zeros() as u16);
+                        bits &= bits - 1;
+                    }
+                }
+                values
+            }
+        }
+    }
+
+    /// Maximal runs of consecutive values.
+    fn runs(&self) -> Vec<(u16, u16)> {
+        let mut runs: Vec<(u16, u16)> = Vec::new();
+        for v in self.values() {
+            match runs.last_mut() {
+                Some((start, extra)) if *start as u32 + *extra as u32 + 1 == v as u32 => {
+                    *extra += 1
+                }
+                _ => runs.push((v, 0)),
+            }
+        }
+        runs
+    }
+
+    /// Payload bytes, not counting the per-container overhead.
+    fn bytes(&self) -> usize {
+        match self {
+            Container::Array(values) => values.len() * 2,
+            Container::Bitmap { .. } => BITMAP_WORDS * 8,
+            Container::Run(runs) => runs.len() * 4,
+        }
+    }
+
+    /// Add `low`, returning whether it was absent. A run container is
+    /// turned back into an array or bitmap first.
+    fn insert(&mut self, low: u16) -> bool {
+        if let Container::Run(_) = self {
+            *self = Container::from_words(self.words()).expect("runs are never empty");
+        }
+        match self {
+            Container::Array(values) => match values.binary_search(&low) {
+                Ok(_) => return false,
+                Err(i) => values.insert(i, low),
+            },
+            Container::Bitmap { words, len } => {
+                let mask = 1 << (low % 64);
+                if words[low as usize / 64] & mask != 0 {
+                    return false;
+                }
+                words[low as usize / 64] |= mask;
+                *len += 1;
+            }
+            Container::Run(_) => unreachable!("converted above"),
+        }
+        if self.len() > ARRAY_MAX {
+            if let Container::Array(_) = self {
+                *self = Container::Bitmap {
+                    words: self.words(),
+                    len: self.len(),
+                };
+            }
+        }
+        true
+    }
+
+    /// Remove `low`, returning whether it was present.
+    fn remove(&mut self, low: u16) -> bool {
+        if !self.contains(low) {
+            return false;
+        }
+        if let Container::Run(_) = self {
+            *self = Container::from_words(self.words()).expect("runs are never empty");
+        }
+        match self {
+            Container::Array(values) => {
+                let i = values.binary_search(&low).expect("checked above");
+                values.remove(i);
+            }
+            Container::Bitmap { words, len } => {
+                words[low as usize / 64] &= !(1 << (low % 64));
+                *len -= 1;
+                if *len <= ARRAY_MAX {
+                    *self = Container::from_words(self.words()).expect("len > 0");
+                }
+            }
+            Container::Run(_) => unreachable!("converted above"),
+        }
+        true
+    }
+}
+
+/// Compressed set of `u32` values in the style of Roaring bitmaps.
+///
+/// Values are split by their high 16 bits into chunks of 65,536, and each
+/// non-empty chunk stores its low halves in whichever container suits it:
+/// a sorted array while it holds at most 4,096 values (2 bytes each), a
+/// fixed 8 KiB bitmap beyond that, or, after `run_optimize`, a list of
+/// runs when the values are mostly consecutive. Sparse sets therefore cost
+/// a few bytes per value and dense ones about one bit per value, where a
+/// plain [`BitSet`](crate::BitSet) always pays one bit for every position
+/// up to the largest value.
+///
+/// Adding to or removing from a run container turns it back into an
+/// array or bitmap; call `run_optimize` again after a batch of changes.
+///
+/// # Example
+/// ```javascript
+/// const a = CompressedBitmap.from_values(new Uint32Array([1, 2, 3, 1000000]));
+/// const b = CompressedBitmap.from_values(new Uint32Array([3, 4]));
+/// a.union(b).to_array();        // Uint32Array [1, 2, 3, 4, 1000000]
+/// a.intersection(b).to_array(); // Uint32Array [3]
+/// a.get_metrics().bytes;        // 16, vs 125,008 for a BitSet
+/// ```
+#[wasm_bindgen]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CompressedBitmap {
+    /// Non-empty containers, sorted by the high 16 bits
+    containers: Vec<(u16, Container)>,
+}
+
+/// Memory use of a [`CompressedBitmap`] and of a [`BitSet`](crate::BitSet)
+/// holding the same values
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CompressedBitmapMetrics {
+    pub array_containers: u32,
+    pub bitmap_containers: u32,
+    pub run_containers: u32,
+    /// Container payloads plus 4 bytes of key and count per container
+    pub bytes: usize,
+    /// A BitSet covering 0 to the largest value
+    pub bitset_bytes: usize,
+}
+
+fn split(value: u32) -> (u16, u16) {
+    ((value >> 16) as u16, value as u16)
+}
+
+impl CompressedBitmap {
+    /// Merge with `other` chunk by chunk: chunks in both are combined word
+    /// by word with `op`; chunks in only one side are kept if the matching
+    /// `keep_*` flag is set.
+    fn combine(
+        &self,
+        other: &CompressedBitmap,
+        op: fn(u64, u64) -> u64,
+        keep_left: bool,
+        keep_right: bool,
+    ) -> CompressedBitmap {
+        let mut containers = Vec::new();
+        let (mut i, mut j) = (0, 0);
+        while i < self.containers.len() || j < other.containers.len() {
+            let left = self.containers.get(i);
+            let right = other.containers.get(j);
+            match (left, right) {
+                (Some((a, ca)), Some((b, cb))) if a == b => {
+                    let words = ca
+                        .words()
+                        .into_iter()
+                        .zip(cb.words())
+                        .map(|(x, y)| op(x, y));
+                    if let Some(c) = Container::from_words(words.collect()) {
+                        containers.push((*a, c));
+                    }
+                    i += 1;
+                    j += 1;
+                }
+                (Some((a, ca)), Some((b, _))) if a < b => {
+                    if keep_left {
+                        containers.push((*a, ca.clone()));
+                    }
+                    i += 1;
+                }
+                (Some((a, ca)), None) => {
+                    if keep_left {
+                        containers.push((*a, ca.clone()));
+                    }
+                    i += 1;
+                }
+                (_, Some((b, cb))) => {
+                    if keep_right {
+                        containers.push((*b, cb.clone()));
+                    }
+                    j += 1;
+                }
+                (None, None) => unreachable!("loop condition"),
+            }
+        }
+        CompressedBitmap { containers }
+    }
+}
+
+#[wasm_bindgen]
+impl CompressedBitmap {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> CompressedBitmap {
+        CompressedBitmap::default()
+    }
+
+    pub fn from_values(values: Vec<u32>) -> CompressedBitmap {
+        let mut bitmap = CompressedBitmap::new();
+        for v in values {
+            bitmap.add(v);
+        }
+        bitmap
+    }
+
+    /// Add `value`, returning whether it was absent.
+    pub fn add(&mut self, value: u32) -> bool {
+        let (high, low) = split(value);
+        match self.containers.binary_search_by_key(&high, |&(k, _)| k) {
+            Ok(i) => self.containers[i].1.insert(low),
+            Err(i) => {
+                self.containers
+                    .insert(i, (high, Container::Array(vec![low])));
+                true
+            }
+        }
+    }
+
+    /// Remove `value`, returning whether it was present.
+    pub fn remove(&mut self, value: u32) -> bool {
+        let (high, low) = split(value);
+        let Ok(i) = self.containers.binary_search_by_key(&high, |&(k, _)| k) else {
+            return false;
+        };
+        let removed = self.containers[i].1.remove(low);
+        if self.containers[i].1.len() == 0 {
+            self.containers.remove(i);
+        }
+        removed
+    }
+
+    pub fn contains(&self, value: u32) -> bool {
+        let (high, low) = split(value);
+        self.containers
+            .binary_search_by_key(&high, |&(k, _)| k)
+            .is_ok_and(|i| self.containers[i].1.contains(low))
+    }
+
+    /// Number of values in the set.
+    pub fn len(&self) -> usize {
+        self.containers.iter().map(|(_, c)| c.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.containers.is_empty()
+    }
+
+    pub fn union(&self, other: &CompressedBitmap) -> CompressedBitmap {
+        self.combine(other, |a, b| a | b, true, true)
+    }
+
+    pub fn intersection(&self, other: &CompressedBitmap) -> CompressedBitmap {
+        self.combine(other, |a, b| a & b, false, false)
+    }
+
+    /// Values in `self` but not in `other`.
+    pub fn difference(&self, other: &CompressedBitmap) -> CompressedBitmap {
+        self.combine(other, |a, b| a & !b, true, false)
+    }
+
+    /// Switch every container whose values are cheaper to store as runs to
+    /// a run container. Returns the number of containers converted.
+    pub fn run_optimize(&mut self) -> u32 {
+        let mut converted = 0;
+        for (_, container) in &mut self.containers {
+            if let Container::Run(_) = container {
+                continue;
+            }
+            let runs = container.runs();
+            if runs.len() * 4 < container.bytes() {
+                *container = Container::Run(runs);
+                converted += 1;
+            }
+        }
+        converted
+    }
+
+    /// All values, ascending.
+    pub fn to_array(&self) -> Vec<u32> {
+        self.containers
+            .iter()
+            .flat_map(|(high, c)| {
+                c.values()
+                    .into_iter()
+                    .map(move |low| (*high as u32) << 16 | low as u32)
+            })
+            .collect()
+    }
+
+    pub fn get_metrics(&self) -> CompressedBitmapMetrics {
+        let mut metrics = CompressedBitmapMetrics::default();
+        for (_, container) in &self.containers {
+            match container {
+                Container::Array(_) => metrics.array_containers += 1,
+                Container::Bitmap { .. } => metrics.bitmap_containers += 1,
+                Container::Run(_) => metrics.run_containers += 1,
+            }
+            metrics.bytes += container.bytes() + CONTAINER_OVERHEAD;
+        }
+        if let Some(max) = self.to_array().last() {
+            metrics.bitset_bytes = (*max as usize + 1).div_ceil(64) * 8;
+        }
+        metrics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::DefaultRng;
+    use rand::Rng;
+    use std::collections::BTreeSet;
+
+    #[test]
+    fn test_matches_btreeset() {
+        let mut rng = DefaultRng::seed_from(34);
+        let mut bitmap = CompressedBitmap::new();
+        let mut other = CompressedBitmap::new();
+        let (mut expected, mut expected_other) = (BTreeSet::new(), BTreeSet::new());
+        // Dense chunk 0 crosses the array limit both ways; chunk 3 stays sparse
+        for _ in 0..20_000 {
+            let v = if rng.gen_bool(0.8) {
+                rng.gen_range(0..8_000)
+            } else {
+                rng.gen_range(3 << 16..4 << 16)
+            };
+            match rng.gen_range(0..4) {
+                0 => assert_eq!(bitmap.remove(v), expected.remove(&v)),
+                1 => assert_eq!(other.add(v), expected_other.insert(v)),
+                _ => assert_eq!(bitmap.add(v), expected.insert(v)),
+            }
+        }
+        assert_eq!(bitmap.len(), expected.len());
+        assert!(bitmap.get_metrics().bitmap_containers == 1);
+        assert!(expected.iter().all(|&v| bitmap.contains(v)));
+        assert!(!bitmap.contains(2 << 16));
+
+        let to_vec = |s: BTreeSet<u32>| s.into_iter().collect::<Vec<_>>();
+        assert_eq!(
+            bitmap.union(&other).to_array(),
+            to_vec(&expected | &expected_other)
+        );
+        assert_eq!(
+            bitmap.intersection(&other).to_array(),
+            to_vec(&expected & &expected_other)
+        );
+        assert_eq!(
+            bitmap.difference(&other).to_array(),
+            to_vec(&expected - &expected_other)
+        );
+    }
+
+    #[test]
+    fn test_runs_and_memory() {
+        let mut bitmap = CompressedBitmap::from_values((100..50_000).collect());
+        bitmap.add(1_000_000);
+        let before = bitmap.get_metrics();
+        assert_eq!((before.array_containers, before.bitmap_containers), (1, 1));
+        assert_eq!(before.bitset_bytes, 125_008);
+
+        assert_eq!(bitmap.run_optimize(), 1);
+        let after = bitmap.get_metrics();
+        assert_eq!(after.run_containers, 1);
+        assert!(after.bytes < 20 && after.bytes < before.bytes);
+        assert!(bitmap.contains(49_999) && !bitmap.contains(50_000));
+
+        // Changing a run container turns it back and keeps its values
+        assert!(bitmap.remove(200));
+        assert_eq!(bitmap.len(), 49_900);
+        assert_eq!(bitmap.get_metrics().run_containers, 0);
+    }
+}
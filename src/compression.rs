@@ -0,0 +1,315 @@
+//! LZ4 compression of MessagePack snapshots.
+//!
+//! A compressed snapshot is the magic `WDSZ`, a format byte, the
+//! uncompressed length as a little-endian u32, then one raw LZ4 block (the
+//! block format, not the frame format), so the payload after the 9-byte
+//! header can be inflated by any LZ4 block decoder such as `lz4js`.
+//! Sorted string keys share long prefixes, which LZ4's back-references
+//! remove cheaply.
+
+use crate::msgpack;
+use crate::snapshot::{Snapshot, SnapshotError};
+use wasm_bindgen::prelude::*;
+
+const MAGIC: &[u8] = b"WDSZ";
+const FORMAT: u8 = 1;
+const HEADER_LEN: usize = MAGIC.len() + 1 + 4;
+
+const MIN_MATCH: usize = 4;
+/// The block format requires the last 5 bytes to be literals, and the last
+/// match to start at least 12 bytes before the end.
+const LAST_LITERALS: usize = 5;
+const MATCH_LIMIT: usize = 12;
+const MAX_OFFSET: usize = u16::MAX as usize;
+const HASH_BITS: u32 = 12;
+
+/// Largest uncompressed size accepted on decode, so a forged header can't
+/// request an enormous allocation.
+const MAX_DECODED_LEN: usize = 1 << 30;
+
+/// How well a snapshot compressed.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SnapshotCompressionStats {
+    /// Size of the MessagePack snapshot
+    pub raw_bytes: usize,
+    /// Size of the compressed snapshot, header included
+    pub compressed_bytes: usize,
+    /// `raw_bytes / compressed_bytes`, e.g. 4.0 for a quarter of the size
+    pub ratio: f64,
+    /// Fraction of `raw_bytes` saved, 0.0 for an empty input
+    pub savings_ratio: f64,
+}
+
+impl SnapshotCompressionStats {
+    fn new(raw_bytes: usize, compressed_bytes: usize) -> Self {
+        SnapshotCompressionStats {
+            raw_bytes,
+            compressed_bytes,
+            ratio: raw_bytes as f64 / compressed_bytes as f64,
+            savings_ratio: if raw_bytes == 0 {
+                0.0
+            } else {
+                1.0 - compressed_bytes as f64 / raw_bytes as f64
+            },
+        }
+    }
+}
+
+fn hash(bytes: &[u8]) -> usize {
+    let word = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    (word.wrapping_mul(2_654_435_761) >> (32 - HASH_BITS)) as usize
+}
+
+/// Append `len` in the block format's 255-run encoding of lengths past 15.
+fn write_length(out: &mut Vec<u8>, mut len: usize) {
+    while len >= 255 {
+        out.push(255);
+        len -= 255;
+    }
+    out.push(len as u8);
+}
+
+fn write_sequence(out: &mut Vec<u8>, literals: &[u8], matched: Option<(usize, usize)>) {
+    let match_nibble = matched.map_or(0, |(_, len)| (len - MIN_MATCH).min(15));
+    out.push(((literals.len().min(15) as u8) << 4) | match_nibble as u8);
+    if literals.len() >= 15 {
+        write_length(out, literals.len() - 15);
+    }
+    out.extend_from_slice(literals);
+    if let Some((offset, len)) = matched {
+        out.extend_from_slice(&(offset as u16).to_le_bytes());
+        if len - MIN_MATCH >= 15 {
+            write_length(out, len - MIN_MATCH - 15);
+        }
+    }
+}
+
+/// Compress `input` into one LZ4 block with a greedy single-probe matcher.
+pub(crate) fn compress_block(input: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(input.len() / 2 + 16);
+    let mut table = vec![usize::MAX; 1 << HASH_BITS];
+    let mut anchor = 0;
+    let mut pos = 0;
+    let match_end = input.len().saturating_sub(LAST_LITERALS);
+    while pos + MATCH_LIMIT <= input.len() {
+        let slot = hash(&input[pos..]);
+        let candidate = std::mem::replace(&mut table[slot], pos);
+        if candidate == usize::MAX
+            || pos - candidate > MAX_OFFSET
+            || input[candidate..candidate + MIN_MATCH] != input[pos..pos + MIN_MATCH]
+        {
+            pos += 1;
+            continue;
+        }
+        let len = MIN_MATCH
+            + input[candidate + MIN_MATCH..]
+                .iter()
+                .zip(&input[pos + MIN_MATCH..match_end])
+                .take_while(|(a, b)| a == b)
+                .count();
+        write_sequence(&mut out, &input[anchor..pos], Some((pos - candidate, len)));
+        pos += len;
+        anchor = pos;
+    }
+    write_sequence(&mut out, &input[anchor..], None);
+    out
+}
+
+fn read_length(input: &[u8], at: &mut usize, mut len: usize) -> Result<usize, String> {
+    loop {
+        let byte = *input.get(*at).ok_or("truncated length")?;
+        *at += 1;
+        len += byte as usize;
+        if byte != 255 {
+            return Ok(len);
+        }
+    }
+}
+
+/// Inflate an LZ4 block that decodes to exactly `len` bytes.
+pub(crate) fn decompress_block(input: &[u8], len: usize) -> Result<Vec<u8>, String> {
+    // A block can't inflate more than 255 times over, so a forged length
+    // can't reserve more than the input justifies
+    let mut out = Vec::with_capacity(len.min(input.len().saturating_mul(255)));
+    let mut at = 0;
+    loop {
+        let token = *input.get(at).ok_or("truncated block")?;
+        at += 1;
+        let mut literals = (token >> 4) as usize;
+        if literals == 15 {
+            literals = read_length(input, &mut at, literals)?;
+        }
+        let end = at
+            .checked_add(literals)
+            .filter(|&end| end <= input.len())
+            .ok_or("literals run past the end of the block")?;
+        if out.len() + literals > len {
+            return Err("block decodes past the declared length".to_string());
+        }
+        out.extend_from_slice(&input[at..end]);
+        at = end;
+        if at == input.len() {
+            break;
+        }
+        let offset = input
+            .get(at..at + 2)
+            .map(|b| u16::from_le_bytes([b[0], b[1]]) as usize)
+            .ok_or("truncated match offset")?;
+        at += 2;
+        if offset == 0 || offset > out.len() {
+            return Err(format!("match offset {} is out of range", offset));
+        }
+        let mut matched = (token & 0x0f) as usize;
+        if matched == 15 {
+            matched = read_length(input, &mut at, matched)?;
+        }
+        matched += MIN_MATCH;
+        if out.len() + matched > len {
+            return Err("block decodes past the declared length".to_string());
+        }
+        // Copy byte by byte: a match may overlap the bytes it produces
+        let start = out.len() - offset;
+        for i in 0..matched {
+            out.push(out[start + i]);
+        }
+    }
+    if out.len() != len {
+        return Err(format!(
+            "block decoded to {} bytes, expected {}",
+            out.len(),
+            len
+        ));
+    }
+    Ok(out)
+}
+
+/// Encode `snapshot` as MessagePack and compress it.
+pub(crate) fn compress_snapshot(snapshot: &Snapshot) -> Vec<u8> {
+    let raw = msgpack::encode_snapshot(snapshot);
+    let mut out = Vec::with_capacity(HEADER_LEN + raw.len() / 2);
+    out.extend_from_slice(MAGIC);
+    out.push(FORMAT);
+    out.extend_from_slice(&(raw.len() as u32).to_le_bytes());
+    out.extend(compress_block(&raw));
+    out
+}
+
+/// Sizes of `snapshot` encoded plainly and compressed.
+pub(crate) fn stats(snapshot: &Snapshot) -> SnapshotCompressionStats {
+    let raw = msgpack::encode_snapshot(snapshot).len();
+    SnapshotCompressionStats::new(raw, compress_snapshot(snapshot).len())
+}
+
+/// Inflate and decode [`compress_snapshot`] output. The inflated snapshot
+/// still carries its checksum, so damage that survives decompression is
+/// reported as [`SnapshotError::Corrupt`].
+pub(crate) fn decompress_snapshot(bytes: &[u8]) -> Result<Snapshot, SnapshotError> {
+    if bytes.len() < HEADER_LEN || !bytes.starts_with(MAGIC) {
+        return Err(SnapshotError::Malformed(
+            "not a compressed snapshot".to_string(),
+        ));
+    }
+    if bytes[MAGIC.len()] != FORMAT {
+        return Err(SnapshotError::Malformed(format!(
+            "unknown compressed snapshot format {}",
+            bytes[MAGIC.len()]
+        )));
+    }
+    let len = &bytes[MAGIC.len() + 1..HEADER_LEN];
+    let len = u32::from_le_bytes([len[0], len[1], len[2], len[3]]) as usize;
+    if len > MAX_DECODED_LEN {
+        return Err(SnapshotError::Malformed(format!(
+            "declared size {} exceeds the {} byte limit",
+            len, MAX_DECODED_LEN
+        )));
+    }
+    let raw = decompress_block(&bytes[HEADER_LEN..], len)
+        .map_err(|e| SnapshotError::Malformed(format!("compressed snapshot: {}", e)))?;
+    msgpack::decode_snapshot(&raw)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_block_round_trip() {
+        let repetitive: Vec<u8> = b"abcabcabcabc".repeat(200);
+        let mut noisy = Vec::new();
+        let mut x = 12345u32;
+        for _ in 0..5000 {
+            x ^= x << 13;
+            x ^= x >> 17;
+            x ^= x << 5;
+            noisy.push(x as u8);
+        }
+        let long_literals: Vec<u8> = (0..=255).collect();
+        for input in [
+            &b""[..],
+            b"short",
+            b"aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
+            &repetitive,
+            &noisy,
+            &long_literals,
+        ] {
+            let block = compress_block(input);
+            assert_eq!(decompress_block(&block, input.len()).unwrap(), input);
+        }
+        assert!(compress_block(&repetitive).len() < repetitive.len() / 20);
+
+        // A hand-built block: literal "ab", then an overlapping 6-byte match
+        // at offset 2, then the final literal "c"
+        let block = [0x22, b'a', b'b', 2, 0, 0x10, b'c'];
+        assert_eq!(decompress_block(&block, 9).unwrap(), b"ababababc");
+        assert!(decompress_block(&block, 8).is_err());
+        assert!(decompress_block(&[0x20, b'a', b'b', 9, 0, 0x10, b'c'], 9)
+            .unwrap_err()
+            .contains("offset 9"));
+    }
+
+    #[test]
+    fn test_snapshot_compression() {
+        let snapshot = Snapshot {
+            kind: "trie".to_string(),
+            entries: (0..500)
+                .map(|i| (format!("session/user-{:05}/preferences", i), i))
+                .collect(),
+        };
+        let bytes = compress_snapshot(&snapshot);
+        assert_eq!(decompress_snapshot(&bytes).unwrap(), snapshot);
+        let stats = stats(&snapshot);
+        assert_eq!(stats.compressed_bytes, bytes.len());
+        assert!(stats.ratio > 3.0, "{:?}", stats);
+        assert!(stats.savings_ratio > 0.66 && stats.savings_ratio < 1.0);
+
+        assert_eq!(
+            decompress_snapshot(&msgpack::encode_snapshot(&snapshot))
+                .unwrap_err()
+                .name(),
+            "SnapshotMalformed"
+        );
+        let mut truncated = bytes.clone();
+        truncated.truncate(bytes.len() - 3);
+        assert!(decompress_snapshot(&truncated).is_err());
+        let mut huge = bytes;
+        huge[5..9].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(decompress_snapshot(&huge)
+            .unwrap_err()
+            .to_string()
+            .contains("limit"));
+    }
+
+    #[test]
+    fn test_forged_length_is_rejected() {
+        // A few bytes declaring a 1 GiB snapshot, just under the limit
+        let mut forged = MAGIC.to_vec();
+        forged.push(FORMAT);
+        forged.extend_from_slice(&(MAX_DECODED_LEN as u32).to_le_bytes());
+        forged.extend_from_slice(&[0x20, b'a', b'b']);
+        assert!(decompress_snapshot(&forged)
+            .unwrap_err()
+            .to_string()
+            .contains("expected 1073741824"));
+    }
+}
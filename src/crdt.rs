@@ -0,0 +1,370 @@
+use std::collections::BTreeMap;
+use wasm_bindgen::prelude::*;
+
+/// Conflict-resolution modes accepted by [`CrdtMap::new`].
+pub const CRDT_MODES: [&str; 2] = ["lww", "or"];
+
+/// A write's identity: the writing replica's counter at the time, then the
+/// replica id. Ordering by counter first makes "latest" well defined
+/// across replicas.
+type Dot = (u32, u32);
+
+#[derive(Clone, Debug, PartialEq)]
+enum Entries {
+    /// Last-writer-wins: one register per key, `None` being a tombstone
+    /// left by a delete so it can win over older writes elsewhere
+    Lww(BTreeMap<String, (Dot, Option<u32>)>),
+    /// Observed-remove: the writes to each key no delete has yet seen.
+    /// Concurrent writes all survive a merge; the latest dot is read
+    Or(BTreeMap<String, BTreeMap<Dot, u32>>),
+}
+
+/// Shape of a [`CrdtMap`]'s state and how often it has merged.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CrdtMapMetrics {
+    pub live_keys: u32,
+    /// Deleted keys still remembered, in last-writer-wins mode
+    pub tombstones: u32,
+    /// Keys holding concurrent writes, in observed-remove mode
+    pub concurrent_keys: u32,
+    pub merges: u32,
+}
+
+/// A replicated map that any number of replicas can edit offline and then
+/// `merge` in any order, any number of times, and still agree: merging is
+/// commutative, associative and idempotent.
+///
+/// Each replica has a distinct id and a vector clock counting the writes
+/// it has seen from every replica. Two modes settle concurrent writes to
+/// the same key:
+/// - `"lww"`: last writer wins. Every write is stamped with a Lamport
+///   timestamp (one past the highest counter seen) and the highest stamp
+///   wins, a delete included, which leaves a tombstone behind.
+/// - `"or"`: observed-remove. A delete only removes the writes its replica
+///   had seen, so a concurrent insert elsewhere survives it (add wins). Two
+///   concurrent inserts are both kept until one replica writes the key
+///   again; reads see the one with the latest stamp.
+///
+/// # Example
+/// ```javascript
+/// const phone = new CrdtMap(1, "or"), laptop = new CrdtMap(2, "or");
+/// phone.insert("milk", 2);
+/// laptop.insert("eggs", 12);
+/// phone.merge(laptop); laptop.merge(phone);
+/// phone.to_js_map(); // Map { eggs → 12, milk → 2 }, as on the laptop
+/// ```
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct CrdtMap {
+    replica: u32,
+    entries: Entries,
+    /// replica -> highest counter seen from it
+    clock: BTreeMap<u32, u32>,
+    merges: u32,
+}
+
+impl CrdtMap {
+    pub fn try_new(replica: u32, mode: &str) -> Result<CrdtMap, String> {
+        let entries = match mode {
+            "lww" => Entries::Lww(BTreeMap::new()),
+            "or" => Entries::Or(BTreeMap::new()),
+            _ => return Err(format!("unknown CRDT mode '{}'", mode)),
+        };
+        Ok(CrdtMap {
+            replica,
+            entries,
+            clock: BTreeMap::new(),
+            merges: 0,
+        })
+    }
+
+    /// Fold `other`'s state into this one. Fails if the modes differ.
+    pub fn try_merge(&mut self, other: &CrdtMap) -> Result<(), String> {
+        match (&mut self.entries, &other.entries) {
+            (Entries::Lww(ours), Entries::Lww(theirs)) => {
+                for (key, register) in theirs {
+                    let ours = ours.entry(key.clone()).or_insert(*register);
+                    if register.0 > ours.0 {
+                        *ours = *register;
+                    }
+                }
+            }
+            (Entries::Or(ours), Entries::Or(theirs)) => {
+                let seen_by = |clock: &BTreeMap<u32, u32>, &(counter, replica): &Dot| {
+                    clock.get(&replica).is_some_and(|&seen| counter <= seen)
+                };
+                let mut keys: Vec<String> = ours.keys().cloned().collect();
+                keys.extend(theirs.keys().filter(|k| !ours.contains_key(*k)).cloned());
+                let empty = BTreeMap::new();
+                for key in keys {
+                    let theirs = theirs.get(&key).unwrap_or(&empty);
+                    let dots = ours.entry(key.clone()).or_default();
+                    // A dot missing from one side was removed there if
+                    // that side had seen it, and not yet received otherwise
+                    dots.retain(|dot, _| theirs.contains_key(dot) || !seen_by(&other.clock, dot));
+                    for (dot, value) in theirs {
+                        if !seen_by(&self.clock, dot) {
+                            dots.insert(*dot, *value);
+                        }
+                    }
+                    if dots.is_empty() {
+                        ours.remove(&key);
+                    }
+                }
+            }
+            _ => {
+                return Err(format!(
+                    "can't merge a '{}' map into a '{}' map",
+                    other.mode(),
+                    self.mode()
+                ))
+            }
+        }
+        for (&replica, &counter) in &other.clock {
+            let seen = self.clock.entry(replica).or_insert(0);
+            *seen = (*seen).max(counter);
+        }
+        self.merges += 1;
+        Ok(())
+    }
+
+    /// Every live entry, sorted by key.
+    pub fn pairs(&self) -> Vec<(String, u32)> {
+        match &self.entries {
+            Entries::Lww(registers) => registers
+                .iter()
+                .filter_map(|(key, &(_, value))| value.map(|value| (key.clone(), value)))
+                .collect(),
+            Entries::Or(keys) => keys
+                .iter()
+                .filter_map(|(key, dots)| {
+                    dots.last_key_value()
+                        .map(|(_, &value)| (key.clone(), value))
+                })
+                .collect(),
+        }
+    }
+
+    /// Internal: Stamp a new local write. Last-writer-wins needs a stamp
+    /// above every one seen; observed-remove only the next local counter.
+    fn next_dot(&mut self) -> Dot {
+        let counter = match self.entries {
+            Entries::Lww(_) => self.clock.values().max().copied().unwrap_or(0) + 1,
+            Entries::Or(_) => self.clock.get(&self.replica).copied().unwrap_or(0) + 1,
+        };
+        self.clock.insert(self.replica, counter);
+        (counter, self.replica)
+    }
+}
+
+#[wasm_bindgen]
+impl CrdtMap {
+    /// An empty replica of a map in `mode` (`"lww"` or `"or"`).
+    /// `replica` must differ between all replicas that will be merged.
+    #[wasm_bindgen(constructor)]
+    pub fn new(replica: u32, mode: &str) -> Result<CrdtMap, JsValue> {
+        Self::try_new(replica, mode).map_err(|e| JsValue::from_str(&e))
+    }
+
+    pub fn insert(&mut self, key: String, value: u32) {
+        let dot = self.next_dot();
+        match &mut self.entries {
+            Entries::Lww(registers) => {
+                registers.insert(key, (dot, Some(value)));
+            }
+            Entries::Or(keys) => {
+                // Replaces every write this replica has seen
+                keys.insert(key, BTreeMap::from([(dot, value)]));
+            }
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<u32> {
+        match &self.entries {
+            Entries::Lww(registers) => registers.get(key).and_then(|&(_, value)| value),
+            Entries::Or(keys) => keys
+                .get(key)
+                .and_then(|dots| dots.last_key_value())
+                .map(|(_, &value)| value),
+        }
+    }
+
+    pub fn delete(&mut self, key: &str) -> bool {
+        if self.get(key).is_none() {
+            return false;
+        }
+        let dot = self.next_dot();
+        match &mut self.entries {
+            Entries::Lww(registers) => {
+                registers.insert(key.to_string(), (dot, None));
+            }
+            Entries::Or(keys) => {
+                keys.remove(key);
+            }
+        }
+        true
+    }
+
+    /// Fold `other`'s state into this replica; `other` is unchanged.
+    /// Throws if the two maps use different modes.
+    pub fn merge(&mut self, other: &CrdtMap) -> Result<(), JsValue> {
+        self.try_merge(other).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// A deep copy, e.g. to stand in for a replica on another device.
+    #[wasm_bindgen(js_name = clone)]
+    pub fn deep_clone(&self) -> CrdtMap {
+        self.clone()
+    }
+
+    pub fn replica(&self) -> u32 {
+        self.replica
+    }
+
+    pub fn mode(&self) -> String {
+        match self.entries {
+            Entries::Lww(_) => "lww",
+            Entries::Or(_) => "or",
+        }
+        .to_string()
+    }
+
+    /// The vector clock as a JS `Map` from replica id to the highest
+    /// counter seen from that replica.
+    pub fn vector_clock(&self) -> js_sys::Map {
+        let map = js_sys::Map::new();
+        for (&replica, &counter) in &self.clock {
+            map.set(&JsValue::from(replica), &JsValue::from(counter));
+        }
+        map
+    }
+
+    /// The live entries as a new JS `Map`, sorted by key.
+    pub fn to_js_map(&self) -> js_sys::Map {
+        let map = js_sys::Map::new();
+        for (key, value) in self.pairs() {
+            map.set(&JsValue::from_str(&key), &JsValue::from(value));
+        }
+        map
+    }
+
+    /// Number of live entries.
+    pub fn len(&self) -> usize {
+        match &self.entries {
+            Entries::Lww(registers) => registers.values().filter(|(_, v)| v.is_some()).count(),
+            Entries::Or(keys) => keys.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn metrics(&self) -> CrdtMapMetrics {
+        let live_keys = self.len() as u32;
+        let (tombstones, concurrent_keys) = match &self.entries {
+            Entries::Lww(registers) => (registers.len() as u32 - live_keys, 0),
+            Entries::Or(keys) => (0, keys.values().filter(|d| d.len() > 1).count() as u32),
+        };
+        CrdtMapMetrics {
+            live_keys,
+            tombstones,
+            concurrent_keys,
+            merges: self.merges,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::DefaultRng;
+    use rand::Rng;
+
+    /// Everything that must agree between converged replicas.
+    fn state(map: &CrdtMap) -> (Entries, BTreeMap<u32, u32>) {
+        (map.entries.clone(), map.clock.clone())
+    }
+
+    fn merged(a: &CrdtMap, b: &CrdtMap) -> CrdtMap {
+        let mut a = a.clone();
+        a.try_merge(b).unwrap();
+        a
+    }
+
+    #[test]
+    fn test_merge_laws_and_convergence() {
+        for mode in CRDT_MODES {
+            let mut rng = DefaultRng::seed_from(5);
+            let mut replicas: Vec<CrdtMap> = (1..=3)
+                .map(|id| CrdtMap::try_new(id, mode).unwrap())
+                .collect();
+            for _ in 0..500 {
+                let i = rng.gen_range(0..3);
+                let key = format!("k{}", rng.gen_range(0..10));
+                match rng.gen_range(0..10) {
+                    0..=5 => replicas[i].insert(key, rng.gen_range(0..100)),
+                    6..=8 => {
+                        replicas[i].delete(&key);
+                    }
+                    _ => {
+                        let other = replicas[rng.gen_range(0..3)].clone();
+                        replicas[i].try_merge(&other).unwrap();
+                    }
+                }
+            }
+
+            let (a, b, c) = (&replicas[0], &replicas[1], &replicas[2]);
+            assert_eq!(state(&merged(a, b)), state(&merged(b, a)), "{}", mode);
+            assert_eq!(
+                state(&merged(&merged(a, b), c)),
+                state(&merged(a, &merged(b, c))),
+                "{}",
+                mode
+            );
+            assert_eq!(state(&merged(a, a)), state(a), "{}", mode);
+
+            let all = merged(&merged(a, b), c);
+            for replica in &mut replicas {
+                replica.try_merge(&all).unwrap();
+                assert_eq!(replica.pairs(), all.pairs(), "{}", mode);
+            }
+        }
+    }
+
+    #[test]
+    fn test_concurrent_writes() {
+        // Last writer wins: the delete on 2 saw the insert, so it is later
+        let mut a = CrdtMap::try_new(1, "lww").unwrap();
+        a.insert("x".to_string(), 1);
+        let mut b = a.clone();
+        b.replica = 2;
+        b.delete("x");
+        a.insert("y".to_string(), 2);
+        a.try_merge(&b).unwrap();
+        assert_eq!(a.pairs(), [("y".to_string(), 2)]);
+        assert_eq!(a.metrics().tombstones, 1);
+
+        // Observed-remove: a delete loses to an insert it didn't see, and
+        // two unseen inserts are both kept
+        let mut a = CrdtMap::try_new(1, "or").unwrap();
+        a.insert("x".to_string(), 1);
+        let mut b = a.clone();
+        b.replica = 2;
+        b.delete("x");
+        a.insert("x".to_string(), 5);
+        let mut c = CrdtMap::try_new(3, "or").unwrap();
+        c.insert("x".to_string(), 8);
+        c.insert("x".to_string(), 9);
+        a.try_merge(&b).unwrap();
+        assert_eq!(a.get("x"), Some(5));
+        a.try_merge(&c).unwrap();
+        assert_eq!(a.metrics().concurrent_keys, 1);
+        // Both were stamped with counter 2; replica 3 breaks the tie
+        assert_eq!(a.get("x"), Some(9));
+
+        assert!(a.try_merge(&CrdtMap::try_new(4, "lww").unwrap()).is_err());
+        assert!(CrdtMap::try_new(1, "mvr").is_err());
+    }
+}
@@ -0,0 +1,175 @@
+//! Authenticated encryption of snapshots with XChaCha20-Poly1305.
+//!
+//! An encrypted snapshot is the magic `WDSX`, a format byte, the 24-byte
+//! nonce, then the MessagePack snapshot sealed with the 32-byte key. The
+//! magic and format byte are authenticated with the data, so neither the
+//! contents nor the header can be altered without the key.
+//!
+//! The cipher is RustCrypto's `chacha20poly1305`; this module only frames
+//! the snapshot around it.
+
+use crate::msgpack;
+use crate::snapshot::{Snapshot, SnapshotError};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::XChaCha20Poly1305;
+use js_sys::{Function, Reflect, Uint8Array};
+use wasm_bindgen::prelude::*;
+
+pub(crate) const KEY_LEN: usize = 32;
+pub(crate) const NONCE_LEN: usize = 24;
+const TAG_LEN: usize = 16;
+
+const MAGIC: &[u8] = b"WDSX";
+const FORMAT: u8 = 1;
+const HEADER_LEN: usize = MAGIC.len() + 1;
+
+/// XChaCha20-Poly1305 of `plaintext`: the ciphertext followed by the
+/// 16-byte tag.
+fn seal(key: &[u8; KEY_LEN], nonce: &[u8; NONCE_LEN], aad: &[u8], plaintext: &[u8]) -> Vec<u8> {
+    XChaCha20Poly1305::new(key.into())
+        .encrypt(
+            nonce.into(),
+            Payload {
+                msg: plaintext,
+                aad,
+            },
+        )
+        .expect("snapshots are far below the cipher's length limit")
+}
+
+/// Reverse [`seal`], or `None` if the tag doesn't match.
+fn open(
+    key: &[u8; KEY_LEN],
+    nonce: &[u8; NONCE_LEN],
+    aad: &[u8],
+    sealed: &[u8],
+) -> Option<Vec<u8>> {
+    XChaCha20Poly1305::new(key.into())
+        .decrypt(nonce.into(), Payload { msg: sealed, aad })
+        .ok()
+}
+
+/// Check that caller-supplied key bytes are a 32-byte key.
+pub(crate) fn key(bytes: &[u8]) -> Result<[u8; KEY_LEN], String> {
+    bytes
+        .try_into()
+        .map_err(|_| format!("key must be {} bytes, got {}", KEY_LEN, bytes.len()))
+}
+
+/// Encode `snapshot` as MessagePack and seal it under `key` and `nonce`.
+/// A nonce must never be reused with the same key.
+pub(crate) fn seal_snapshot(
+    snapshot: &Snapshot,
+    key: &[u8; KEY_LEN],
+    nonce: &[u8; NONCE_LEN],
+) -> Vec<u8> {
+    let mut out = Vec::with_capacity(HEADER_LEN + NONCE_LEN);
+    out.extend_from_slice(MAGIC);
+    out.push(FORMAT);
+    let sealed = seal(key, nonce, &out, &msgpack::encode_snapshot(snapshot));
+    out.extend_from_slice(nonce);
+    out.extend(sealed);
+    out
+}
+
+/// Decrypt and decode [`seal_snapshot`] output. A wrong key and tampered
+/// bytes both fail as [`SnapshotError::DecryptionFailed`]; the two can't be
+/// told apart.
+pub(crate) fn open_snapshot(bytes: &[u8], key: &[u8; KEY_LEN]) -> Result<Snapshot, SnapshotError> {
+    if bytes.len() < HEADER_LEN + NONCE_LEN + TAG_LEN || !bytes.starts_with(MAGIC) {
+        return Err(SnapshotError::Malformed(
+            "not an encrypted snapshot".to_string(),
+        ));
+    }
+    if bytes[MAGIC.len()] != FORMAT {
+        return Err(SnapshotError::Malformed(format!(
+            "unknown encrypted snapshot format {}",
+            bytes[MAGIC.len()]
+        )));
+    }
+    let (header, rest) = bytes.split_at(HEADER_LEN);
+    let (nonce, sealed) = rest.split_at(NONCE_LEN);
+    let nonce = nonce.try_into().expect("split at NONCE_LEN");
+    let plaintext = open(key, nonce, header, sealed).ok_or(SnapshotError::DecryptionFailed)?;
+    msgpack::decode_snapshot(&plaintext)
+}
+
+/// A fresh nonce from the Web Crypto `crypto.getRandomValues`, available
+/// in browsers, workers and Node.
+pub(crate) fn random_nonce() -> Result<[u8; NONCE_LEN], JsValue> {
+    let crypto = Reflect::get(&js_sys::global(), &JsValue::from_str("crypto"))?;
+    let get_random_values: Function = Reflect::get(&crypto, &JsValue::from_str("getRandomValues"))?
+        .dyn_into()
+        .map_err(|_| JsValue::from_str("crypto.getRandomValues is not available"))?;
+    let buffer = Uint8Array::new_with_length(NONCE_LEN as u32);
+    get_random_values.call1(&crypto, &buffer)?;
+    let mut nonce = [0u8; NONCE_LEN];
+    buffer.copy_to(&mut nonce);
+    Ok(nonce)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    fn counting(start: u8) -> [u8; 32] {
+        std::array::from_fn(|i| start + i as u8)
+    }
+
+    #[test]
+    fn test_known_vector() {
+        // XChaCha draft (draft-irtf-cfrg-xchacha-03) A.3.1
+        let plaintext = b"Ladies and Gentlemen of the class of '99: If I could offer you \
+only one tip for the future, sunscreen would be it.";
+        let nonce: [u8; 24] = std::array::from_fn(|i| 0x40 + i as u8);
+        let aad = hex("50515253c0c1c2c3c4c5c6c7");
+        let sealed = seal(&counting(0x80), &nonce, &aad, plaintext);
+        let expected = hex(concat!(
+            "bd6d179d3e83d43b9576579493c0e939572a1700252bfaccbed2902c21396cbb",
+            "731c7f1b0b4aa6440bf3a82f4eda7e39ae64c6708c54c216cb96b72e1213b452",
+            "2f8c9ba40db5d945b11b69b982c1bb9e3f3fac2bc369488f76b2383565d3fff9",
+            "21f9664c97637da9768812f615c68b13b52e",
+            "c0875924c1c7987947deafd8780acf49",
+        ));
+        assert_eq!(sealed, expected);
+        let opened = open(&counting(0x80), &nonce, &aad, &sealed);
+        assert_eq!(opened.as_deref(), Some(&plaintext[..]));
+        assert_eq!(open(&counting(0x80), &nonce, b"other", &sealed), None);
+    }
+
+    #[test]
+    fn test_snapshot_round_trip_and_tampering() {
+        let snapshot = Snapshot {
+            kind: "skiplist".to_string(),
+            entries: (0..50).map(|i| (format!("user{}", i), i)).collect(),
+        };
+        let (key, nonce) = (counting(1), [7u8; NONCE_LEN]);
+        let bytes = seal_snapshot(&snapshot, &key, &nonce);
+        assert_eq!(open_snapshot(&bytes, &key).unwrap(), snapshot);
+        // No plaintext keys leak into the output
+        assert!(!bytes.windows(5).any(|w| w == b"user1"));
+
+        assert_eq!(
+            open_snapshot(&bytes, &counting(2)),
+            Err(SnapshotError::DecryptionFailed)
+        );
+        for at in [2, HEADER_LEN, HEADER_LEN + NONCE_LEN, bytes.len() - 1] {
+            let mut tampered = bytes.clone();
+            tampered[at] ^= 1;
+            assert!(open_snapshot(&tampered, &key).is_err(), "byte {}", at);
+        }
+        let plain = msgpack::encode_snapshot(&snapshot);
+        assert_eq!(
+            open_snapshot(&plain, &key).unwrap_err().name(),
+            "SnapshotMalformed"
+        );
+        assert!(super::key(&[0; 16]).unwrap_err().contains("32 bytes"));
+    }
+}
@@ -0,0 +1,216 @@
+use crate::interop;
+use crate::kv_store::KvStore;
+use wasm_bindgen::prelude::*;
+
+/// One parsed record and the 1-based line it starts on.
+type Record = (usize, Vec<String>);
+
+/// Split `text` into records of fields following RFC 4180: fields are
+/// separated by commas, records by LF or CRLF, and a field wrapped in
+/// double quotes may contain commas, line breaks and `""` for a quote.
+/// Blank lines are skipped and a leading byte-order mark is ignored.
+pub(crate) fn parse(text: &str) -> Result<Vec<Record>, String> {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    // Whether the current field opened with a quote, and is still inside it
+    let mut quoted = false;
+    let mut in_quotes = false;
+    let mut line = 1;
+    let mut start = 1;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => in_quotes = false,
+                '\n' => {
+                    line += 1;
+                    field.push(c);
+                }
+                _ => field.push(c),
+            }
+            continue;
+        }
+        match c {
+            ',' => {
+                record.push(std::mem::take(&mut field));
+                quoted = false;
+            }
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                end_record(&mut records, &mut record, &mut field, quoted, start);
+                quoted = false;
+                line += 1;
+                start = line;
+            }
+            _ if quoted => {
+                return Err(format!(
+                    "line {}: unexpected text after a quoted field",
+                    line
+                ));
+            }
+            '"' if field.is_empty() => {
+                quoted = true;
+                in_quotes = true;
+            }
+            '"' => return Err(format!("line {}: quote inside an unquoted field", line)),
+            _ => field.push(c),
+        }
+    }
+    if in_quotes {
+        return Err(format!("line {}: unterminated quoted field", start));
+    }
+    end_record(&mut records, &mut record, &mut field, quoted, start);
+    Ok(records)
+}
+
+fn end_record(
+    records: &mut Vec<Record>,
+    record: &mut Vec<String>,
+    field: &mut String,
+    quoted: bool,
+    start: usize,
+) {
+    let blank = record.is_empty() && field.is_empty() && !quoted;
+    record.push(std::mem::take(field));
+    let record = std::mem::take(record);
+    if !blank {
+        records.push((start, record));
+    }
+}
+
+/// Entries from the `key_col` and `value_col` columns of CSV `text`, whose
+/// first record is a header naming the columns. Values must be integers
+/// from 0 to 2^32 - 1; surrounding spaces are ignored.
+pub(crate) fn entries(
+    text: &str,
+    key_col: &str,
+    value_col: &str,
+) -> Result<Vec<(String, u32)>, String> {
+    let mut records = parse(text)?.into_iter();
+    let (_, header) = records.next().ok_or_else(|| "CSV is empty".to_string())?;
+    let column = |name: &str| {
+        header
+            .iter()
+            .position(|h| h.trim() == name)
+            .ok_or_else(|| format!("no column '{}' in header {:?}", name, header))
+    };
+    let (key_at, value_at) = (column(key_col)?, column(value_col)?);
+    records
+        .map(|(line, fields)| {
+            let (Some(key), Some(value)) = (fields.get(key_at), fields.get(value_at)) else {
+                return Err(format!(
+                    "line {}: expected at least {} fields, found {}",
+                    line,
+                    key_at.max(value_at) + 1,
+                    fields.len()
+                ));
+            };
+            let value = value
+                .trim()
+                .parse()
+                .map_err(|_| format!("line {}: value '{}' is not a u32", line, value))?;
+            Ok((key.clone(), value))
+        })
+        .collect()
+}
+
+/// Build a structure from CSV `text` (see [`entries`]), as
+/// [`interop::from_map`] does from a JS `Map`.
+pub(crate) fn from_csv<S: KvStore>(
+    text: &str,
+    key_col: &str,
+    value_col: &str,
+    make: impl FnOnce(usize) -> S,
+) -> Result<S, JsValue> {
+    let entries = entries(text, key_col, value_col).map_err(|e| JsValue::from_str(&e))?;
+    Ok(interop::fill(make(entries.len()), entries))
+}
+
+/// The store's entries as CSV with a `key,value` header, one LF-terminated
+/// record per entry in [`KvStore::kv_entries`] order.
+pub(crate) fn to_csv(store: &dyn KvStore) -> String {
+    let mut out = String::from("key,value\n");
+    for (key, value) in store.kv_entries() {
+        write_field(&mut out, &key);
+        out.push(',');
+        out.push_str(&value.to_string());
+        out.push('\n');
+    }
+    out
+}
+
+/// Append `field`, quoted if it holds a comma, quote or line break.
+fn write_field(out: &mut String, field: &str) {
+    if field.contains([',', '"', '\n', '\r']) {
+        out.push('"');
+        out.push_str(&field.replace('"', "\"\""));
+        out.push('"');
+    } else {
+        out.push_str(field);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kv_store::{new_store, STORE_KINDS};
+
+    #[test]
+    fn test_parse_quoting() {
+        let text =
+            "\u{feff}name,score\r\n\"Smith, J\",10\n\n\"say \"\"hi\"\"\",\"2\"\n\"multi\nline\",3";
+        let records = parse(text).unwrap();
+        let fields: Vec<&[String]> = records.iter().map(|(_, f)| f.as_slice()).collect();
+        assert_eq!(fields[0], ["name", "score"]);
+        assert_eq!(fields[1], ["Smith, J", "10"]);
+        assert_eq!(fields[2], ["say \"hi\"", "2"]);
+        assert_eq!(fields[3], ["multi\nline", "3"]);
+        let lines: Vec<usize> = records.iter().map(|(line, _)| *line).collect();
+        assert_eq!(lines, [1, 2, 4, 5]);
+        assert_eq!(parse("a,\"\"\n").unwrap()[0].1, ["a", ""]);
+
+        assert!(parse("a,\"open\n").unwrap_err().contains("unterminated"));
+        assert!(parse("a,b\"c\n").unwrap_err().contains("line 1"));
+        assert!(parse("x\n\"a\"b,1\n").unwrap_err().contains("line 2"));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let text = "id,word,count\n1,apple,3\n2,\"a,b\",4\n3,\"q\"\"uote\",5\n4,apple, 7 \n";
+        let entries = entries(text, "word", "count").unwrap();
+        assert_eq!(entries.len(), 4);
+        assert_eq!(entries[1], ("a,b".to_string(), 4));
+        assert!(super::entries(text, "word", "missing")
+            .unwrap_err()
+            .contains("no column 'missing'"));
+        assert!(super::entries("k,v\na,-1\n", "k", "v")
+            .unwrap_err()
+            .contains("line 2"));
+        assert!(super::entries("k,v\na\n", "k", "v")
+            .unwrap_err()
+            .contains("expected at least 2"));
+
+        for kind in STORE_KINDS {
+            let mut store = new_store(kind, 0).unwrap();
+            for (key, value) in &entries {
+                store.kv_insert(key.clone(), *value);
+            }
+            let csv = to_csv(store.as_ref());
+            let mut back = super::entries(&csv, "key", "value").unwrap();
+            back.sort_unstable();
+            let mut expected = vec![
+                ("a,b".to_string(), 4),
+                ("apple".to_string(), 7),
+                ("q\"uote".to_string(), 5),
+            ];
+            expected.sort_unstable();
+            assert_eq!(back, expected, "{}", kind);
+        }
+    }
+}
@@ -0,0 +1,274 @@
+use crate::bloom::check_false_positive_rate;
+use crate::hashing::HashFunction;
+use crate::rng::DefaultRng;
+use rand::Rng;
+use wasm_bindgen::prelude::*;
+
+/// Fingerprints per bucket.
+const BUCKET_SIZE: usize = 4;
+/// Relocations an insert tries before declaring the filter full.
+const MAX_KICKS: u32 = 500;
+/// Load the table is sized for; four-way buckets reach about 95%.
+const TARGET_LOAD: f64 = 0.95;
+/// Marks an empty slot, so fingerprints are never zero.
+const EMPTY: u16 = 0;
+
+/// Counters kept by a [`CuckooFilter`].
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CuckooFilterMetrics {
+    pub inserts: u32,
+    /// Inserts refused because no slot could be freed
+    pub failed_inserts: u32,
+    /// Fingerprints moved to their alternate bucket to make room
+    pub kicks: u32,
+    pub deletes: u32,
+    pub lookups: u32,
+    /// Lookups answered "maybe present"
+    pub positives: u32,
+}
+
+/// Approximate set membership that, unlike a [`BloomFilter`](crate::BloomFilter), can delete.
+///
+/// Each key is reduced to a small fingerprint stored in one of two
+/// four-slot buckets; the second bucket is the first XORed with a hash of
+/// the fingerprint, so either can be found from the other without the key
+/// (partial-key cuckoo hashing, Fan et al.). When both buckets are full an
+/// insert evicts a random resident fingerprint to its alternate bucket,
+/// repeating up to 500 times before giving up.
+///
+/// Deleting removes one copy of the key's fingerprint, so only delete keys
+/// that were inserted: deleting another key with the same fingerprint and
+/// buckets would remove that key instead.
+///
+/// # Example
+/// ```javascript
+/// const sessions = new CuckooFilter(10_000, 0.01);
+/// sessions.insert("s-41");
+/// sessions.contains("s-41"); // true
+/// sessions.delete("s-41");
+/// sessions.contains("s-41"); // false (barring a false positive)
+/// ```
+#[wasm_bindgen]
+pub struct CuckooFilter {
+    slots: Vec<u16>,
+    /// Buckets minus one; the bucket count is a power of two
+    bucket_mask: usize,
+    fingerprint_bits: u32,
+    items: usize,
+    rng: DefaultRng,
+    metrics: CuckooFilterMetrics,
+}
+
+impl CuckooFilter {
+    pub fn try_new(capacity: usize, false_positive_rate: f64) -> Result<CuckooFilter, String> {
+        check_false_positive_rate(false_positive_rate)?;
+        // A lookup compares against 2 buckets x 4 slots, each matching a
+        // random fingerprint with probability 2^-f
+        let fingerprint_bits = (2.0 * BUCKET_SIZE as f64 / false_positive_rate)
+            .log2()
+            .ceil()
+            .clamp(4.0, 16.0) as u32;
+        let buckets = ((capacity.max(1) as f64 / BUCKET_SIZE as f64 / TARGET_LOAD).ceil() as usize)
+            .next_power_of_two();
+        Ok(CuckooFilter {
+            slots: vec![EMPTY; buckets * BUCKET_SIZE],
+            bucket_mask: buckets - 1,
+            fingerprint_bits,
+            items: 0,
+            rng: DefaultRng::seed_from(buckets as u64),
+            metrics: CuckooFilterMetrics::default(),
+        })
+    }
+
+    /// Fingerprint and first bucket of `key`.
+    fn locate(&self, key: &str) -> (u16, usize) {
+        let hash = HashFunction::SipHash.hash(key);
+        let range = (1u64 << self.fingerprint_bits) - 1;
+        let fingerprint = ((hash >> 32) % range + 1) as u16;
+        (fingerprint, hash as usize & self.bucket_mask)
+    }
+
+    /// The other bucket `fingerprint` may live in; applying it twice
+    /// returns to `bucket`.
+    fn alternate(&self, bucket: usize, fingerprint: u16) -> usize {
+        let mixed = (fingerprint as u64).wrapping_mul(0x5bd1_e995_5bd1_e995) >> 32;
+        (bucket ^ mixed as usize) & self.bucket_mask
+    }
+
+    fn bucket(&self, bucket: usize) -> &[u16] {
+        &self.slots[bucket * BUCKET_SIZE..(bucket + 1) * BUCKET_SIZE]
+    }
+
+    fn bucket_mut(&mut self, bucket: usize) -> &mut [u16] {
+        &mut self.slots[bucket * BUCKET_SIZE..(bucket + 1) * BUCKET_SIZE]
+    }
+
+    fn try_place(&mut self, bucket: usize, fingerprint: u16) -> bool {
+        match self.bucket_mut(bucket).iter_mut().find(|f| **f == EMPTY) {
+            Some(slot) => {
+                *slot = fingerprint;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl CuckooFilter {
+    /// An empty filter for about `capacity` keys at `false_positive_rate`
+    /// (e.g. 0.01), which sets the fingerprint size (4 to 16 bits).
+    #[wasm_bindgen(constructor)]
+    pub fn new(capacity: u32, false_positive_rate: f64) -> Result<CuckooFilter, JsValue> {
+        Self::try_new(capacity as usize, false_positive_rate).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// Add `key`. Returns false if the filter is too full to place it; the
+    /// filter is unchanged in that case.
+    pub fn insert(&mut self, key: &str) -> bool {
+        let (fingerprint, first) = self.locate(key);
+        let second = self.alternate(first, fingerprint);
+        if self.try_place(first, fingerprint) || self.try_place(second, fingerprint) {
+            self.items += 1;
+            self.metrics.inserts += 1;
+            return true;
+        }
+        // Evict along a random path, remembering it so a failed insert can
+        // be rolled back
+        let mut path = Vec::new();
+        let mut bucket = if self.rng.gen() { first } else { second };
+        let mut carried = fingerprint;
+        for _ in 0..MAX_KICKS {
+            let slot = self.rng.gen_range(0..BUCKET_SIZE);
+            std::mem::swap(&mut carried, &mut self.bucket_mut(bucket)[slot]);
+            path.push((bucket, slot));
+            self.metrics.kicks += 1;
+            bucket = self.alternate(bucket, carried);
+            if self.try_place(bucket, carried) {
+                self.items += 1;
+                self.metrics.inserts += 1;
+                return true;
+            }
+        }
+        for (bucket, slot) in path.into_iter().rev() {
+            std::mem::swap(&mut carried, &mut self.bucket_mut(bucket)[slot]);
+        }
+        self.metrics.failed_inserts += 1;
+        false
+    }
+
+    /// False means `key` is certainly not in the filter.
+    pub fn contains(&mut self, key: &str) -> bool {
+        let found = self.peek(key);
+        self.metrics.lookups += 1;
+        if found {
+            self.metrics.positives += 1;
+        }
+        found
+    }
+
+    /// `contains` without counting the lookup.
+    pub fn peek(&self, key: &str) -> bool {
+        let (fingerprint, first) = self.locate(key);
+        let second = self.alternate(first, fingerprint);
+        self.bucket(first).contains(&fingerprint) || self.bucket(second).contains(&fingerprint)
+    }
+
+    /// Remove one copy of `key`'s fingerprint. Returns false if there was
+    /// none.
+    pub fn delete(&mut self, key: &str) -> bool {
+        let (fingerprint, first) = self.locate(key);
+        let second = self.alternate(first, fingerprint);
+        for bucket in [first, second] {
+            if let Some(slot) = self
+                .bucket_mut(bucket)
+                .iter_mut()
+                .find(|f| **f == fingerprint)
+            {
+                *slot = EMPTY;
+                self.items -= 1;
+                self.metrics.deletes += 1;
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Fingerprints stored.
+    pub fn len(&self) -> usize {
+        self.items
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items == 0
+    }
+
+    /// Fingerprint slots (buckets x 4).
+    pub fn slot_count(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn fingerprint_bits(&self) -> u32 {
+        self.fingerprint_bits
+    }
+
+    /// Fraction of slots in use.
+    pub fn load_factor(&self) -> f64 {
+        self.items as f64 / self.slots.len() as f64
+    }
+
+    /// Bytes of fingerprint storage as laid out here, two per slot. A
+    /// bit-packed table would need `fingerprint_bits` per slot.
+    pub fn memory_bytes(&self) -> usize {
+        self.slots.len() * std::mem::size_of::<u16>()
+    }
+
+    /// Upper bound on the false positive rate at the current load:
+    /// 2 buckets x occupied slots, each matching with probability 2^-f.
+    pub fn estimated_false_positive_rate(&self) -> f64 {
+        let candidates = 2.0 * BUCKET_SIZE as f64 * self.load_factor();
+        let range = ((1u64 << self.fingerprint_bits) - 1) as f64;
+        1.0 - (1.0 - 1.0 / range).powf(candidates)
+    }
+
+    pub fn metrics(&self) -> CuckooFilterMetrics {
+        self.metrics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_contains_delete() {
+        // Sized to fill 512 buckets to about 93%
+        let mut filter = CuckooFilter::try_new(1900, 0.01).unwrap();
+        assert_eq!((filter.slot_count(), filter.fingerprint_bits()), (2048, 10));
+        for i in 0..1900 {
+            assert!(filter.insert(&format!("item{}", i)), "item{}", i);
+        }
+        assert!((0..1900).all(|i| filter.contains(&format!("item{}", i))));
+        assert!(filter.metrics().kicks > 0);
+        for i in 0..950 {
+            assert!(filter.delete(&format!("item{}", i)));
+        }
+        assert_eq!(filter.len(), 950);
+        assert!((950..1900).all(|i| filter.peek(&format!("item{}", i))));
+        let lingering = (0..950)
+            .filter(|i| filter.peek(&format!("item{}", i)))
+            .count();
+        assert!(lingering < 20, "{}", lingering);
+
+        // Past capacity, inserts fail without losing anything already in
+        let mut small = CuckooFilter::try_new(8, 0.01).unwrap();
+        let placed: Vec<String> = (0..100)
+            .map(|i| format!("k{}", i))
+            .filter(|key| small.insert(key))
+            .collect();
+        assert!(placed.len() < 100 && small.metrics().failed_inserts > 0);
+        assert!(placed.iter().all(|key| small.peek(key)));
+        assert_eq!(small.len(), placed.len());
+    }
+}
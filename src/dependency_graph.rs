@@ -0,0 +1,329 @@
+use std::collections::{BTreeSet, HashMap};
+use wasm_bindgen::prelude::*;
+
+/// Directed acyclic graph of named nodes that keeps a topological order up
+/// to date as dependencies are added (Pearce & Kelly's dynamic topological
+/// sort).
+///
+/// Every node holds a position in the order, dependencies first. A new
+/// dependency that already agrees with the order costs O(1); otherwise only
+/// the nodes whose positions lie between the two endpoints are searched and
+/// shuffled, never the whole graph. The same search finds a path back from
+/// the dependency to the dependent if one exists, in which case the edge
+/// would close a cycle: it is rejected and the cycle reported.
+///
+/// # Example
+/// ```javascript
+/// const graph = new DependencyGraph();
+/// graph.add_dependency("app", "lib");
+/// graph.add_dependency("lib", "core");
+/// graph.topological_order(); // ["core", "lib", "app"]
+/// try {
+///     graph.add_dependency("core", "app");
+/// } catch (e) {
+///     console.log(e); // "dependency cycle: core -> app -> lib -> core"
+/// }
+/// ```
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct DependencyGraph {
+    names: Vec<String>,
+    index: HashMap<String, usize>,
+    /// node -> nodes it depends on
+    dependencies: Vec<BTreeSet<usize>>,
+    /// node -> nodes depending on it
+    dependents: Vec<BTreeSet<usize>>,
+    /// position -> node
+    order: Vec<usize>,
+    /// node -> position
+    position: Vec<usize>,
+    edge_count: usize,
+    metrics: DependencyGraphMetrics,
+}
+
+/// Metrics collected by a [`DependencyGraph`]
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DependencyGraphMetrics {
+    pub edges_added: u32,
+    pub cycles_rejected: u32,
+    /// Added edges that contradicted the order and forced a reorder
+    pub reorders: u32,
+    /// Nodes given new positions by those reorders
+    pub nodes_reordered: u32,
+    /// Nodes visited by the bounded searches
+    pub nodes_visited: u32,
+}
+
+impl DependencyGraph {
+    fn intern(&mut self, name: &str) -> usize {
+        if let Some(&id) = self.index.get(name) {
+            return id;
+        }
+        let id = self.names.len();
+        self.names.push(name.to_string());
+        self.index.insert(name.to_string(), id);
+        self.dependencies.push(BTreeSet::new());
+        self.dependents.push(BTreeSet::new());
+        self.position.push(self.order.len());
+        self.order.push(id);
+        id
+    }
+
+    /// Nodes reachable from `from` through dependents without passing
+    /// position `bound`, with the node each was reached from.
+    fn search_dependents(&mut self, from: usize, bound: usize) -> HashMap<usize, usize> {
+        let mut parent = HashMap::from([(from, from)]);
+        let mut stack = vec![from];
+        while let Some(node) = stack.pop() {
+            self.metrics.nodes_visited += 1;
+            for &next in &self.dependents[node] {
+                if self.position[next] <= bound && !parent.contains_key(&next) {
+                    parent.insert(next, node);
+                    stack.push(next);
+                }
+            }
+        }
+        parent
+    }
+
+    /// Nodes reachable from `from` through dependencies without passing
+    /// below position `bound`.
+    fn search_dependencies(&mut self, from: usize, bound: usize) -> Vec<usize> {
+        let mut seen = BTreeSet::from([from]);
+        let mut stack = vec![from];
+        while let Some(node) = stack.pop() {
+            self.metrics.nodes_visited += 1;
+            for &next in &self.dependencies[node] {
+                if self.position[next] >= bound && seen.insert(next) {
+                    stack.push(next);
+                }
+            }
+        }
+        seen.into_iter().collect()
+    }
+
+    /// The cycle `dependent -> dependency -> ... -> dependent` that making
+    /// `dependent` depend on `dependency` would close, if any.
+    fn cycle_through(&mut self, dependent: usize, dependency: usize) -> Option<Vec<String>> {
+        if dependent == dependency {
+            let name = self.names[dependent].clone();
+            return Some(vec![name.clone(), name]);
+        }
+        if self.position[dependency] < self.position[dependent] {
+            return None;
+        }
+        let parent = self.search_dependents(dependent, self.position[dependency]);
+        parent.get(&dependency)?;
+        let mut cycle = vec![self.names[dependent].clone()];
+        let mut node = dependency;
+        while node != dependent {
+            cycle.push(self.names[node].clone());
+            node = parent[&node];
+        }
+        cycle.push(self.names[dependent].clone());
+        Some(cycle)
+    }
+
+    /// Make `dependent` depend on `dependency`, creating either node as
+    /// needed. Returns whether the edge is new, or the cycle it would close
+    /// (first and last element equal) without changing the graph.
+    pub fn try_add_dependency(
+        &mut self,
+        dependent: &str,
+        dependency: &str,
+    ) -> Result<bool, Vec<String>> {
+        let a = self.intern(dependent);
+        let b = self.intern(dependency);
+        if self.dependencies[a].contains(&b) {
+            return Ok(false);
+        }
+        if let Some(cycle) = self.cycle_through(a, b) {
+            self.metrics.cycles_rejected += 1;
+            return Err(cycle);
+        }
+        let (low, high) = (self.position[a], self.position[b]);
+        if high > low {
+            // `b` sits after `a`: move a's dependents in the window after
+            // b's dependencies in the window, reusing their positions
+            let mut forward: Vec<usize> = self.search_dependents(a, high).into_keys().collect();
+            let mut backward = self.search_dependencies(b, low);
+            forward.sort_by_key(|&n| self.position[n]);
+            backward.sort_by_key(|&n| self.position[n]);
+            let mut slots: Vec<usize> = forward
+                .iter()
+                .chain(&backward)
+                .map(|&n| self.position[n])
+                .collect();
+            slots.sort_unstable();
+            for (&node, slot) in backward.iter().chain(&forward).zip(slots) {
+                self.position[node] = slot;
+                self.order[slot] = node;
+            }
+            self.metrics.reorders += 1;
+            self.metrics.nodes_reordered += (forward.len() + backward.len()) as u32;
+        }
+        self.dependencies[a].insert(b);
+        self.dependents[b].insert(a);
+        self.edge_count += 1;
+        self.metrics.edges_added += 1;
+        Ok(true)
+    }
+
+    /// Node names with every dependency before its dependents.
+    pub fn topological_order_ref(&self) -> Vec<&str> {
+        self.order.iter().map(|&n| self.names[n].as_str()).collect()
+    }
+
+    fn neighbours(&self, name: &str, edges: &[BTreeSet<usize>]) -> Vec<JsValue> {
+        self.index.get(name).map_or_else(Vec::new, |&id| {
+            edges[id]
+                .iter()
+                .map(|&n| JsValue::from_str(&self.names[n]))
+                .collect()
+        })
+    }
+}
+
+#[wasm_bindgen]
+impl DependencyGraph {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> DependencyGraph {
+        DependencyGraph::default()
+    }
+
+    /// Add a node with no dependencies. Returns false if it existed.
+    pub fn add_node(&mut self, name: &str) -> bool {
+        let before = self.names.len();
+        self.intern(name);
+        self.names.len() > before
+    }
+
+    /// Make `dependent` depend on `dependency`. Returns false if it already
+    /// did; throws `"dependency cycle: a -> b -> ... -> a"` if the edge would
+    /// close a cycle, leaving the graph unchanged.
+    pub fn add_dependency(&mut self, dependent: &str, dependency: &str) -> Result<bool, JsValue> {
+        self.try_add_dependency(dependent, dependency)
+            .map_err(|cycle| {
+                JsValue::from_str(&format!("dependency cycle: {}", cycle.join(" -> ")))
+            })
+    }
+
+    /// The cycle `add_dependency(dependent, dependency)` would close, or an
+    /// empty list if it is safe.
+    pub fn find_cycle(&mut self, dependent: &str, dependency: &str) -> Vec<JsValue> {
+        let (Some(&a), Some(&b)) = (self.index.get(dependent), self.index.get(dependency)) else {
+            return Vec::new();
+        };
+        self.cycle_through(a, b)
+            .unwrap_or_default()
+            .iter()
+            .map(|n| JsValue::from_str(n))
+            .collect()
+    }
+
+    /// Drop a dependency. The order stays valid, so nothing moves.
+    pub fn remove_dependency(&mut self, dependent: &str, dependency: &str) -> bool {
+        let (Some(&a), Some(&b)) = (self.index.get(dependent), self.index.get(dependency)) else {
+            return false;
+        };
+        let removed = self.dependencies[a].remove(&b);
+        if removed {
+            self.dependents[b].remove(&a);
+            self.edge_count -= 1;
+        }
+        removed
+    }
+
+    pub fn topological_order(&self) -> Vec<JsValue> {
+        self.topological_order_ref()
+            .into_iter()
+            .map(JsValue::from_str)
+            .collect()
+    }
+
+    /// Index of `name` in `topological_order()`.
+    pub fn position(&self, name: &str) -> Option<usize> {
+        self.index.get(name).map(|&id| self.position[id])
+    }
+
+    /// Nodes `name` depends on directly.
+    pub fn dependencies_of(&self, name: &str) -> Vec<JsValue> {
+        self.neighbours(name, &self.dependencies)
+    }
+
+    /// Nodes depending directly on `name`.
+    pub fn dependents_of(&self, name: &str) -> Vec<JsValue> {
+        self.neighbours(name, &self.dependents)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.index.contains_key(name)
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.edge_count
+    }
+
+    pub fn get_metrics(&self) -> DependencyGraphMetrics {
+        self.metrics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::DefaultRng;
+    use rand::Rng;
+
+    fn assert_valid_order(graph: &DependencyGraph) {
+        for (node, deps) in graph.dependencies.iter().enumerate() {
+            for &dep in deps {
+                assert!(graph.position[dep] < graph.position[node]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_order_follows_dependencies() {
+        let mut graph = DependencyGraph::new();
+        // Added in the worst order: every edge contradicts insertion order
+        for (a, b) in [("a", "b"), ("b", "c"), ("c", "d"), ("a", "d")] {
+            assert_eq!(graph.try_add_dependency(a, b), Ok(true));
+        }
+        assert_eq!(graph.topological_order_ref(), ["d", "c", "b", "a"]);
+        assert_eq!(graph.try_add_dependency("a", "b"), Ok(false));
+        assert!(graph.get_metrics().reorders >= 3);
+
+        let mut rng = DefaultRng::seed_from(5);
+        let mut graph = DependencyGraph::new();
+        for _ in 0..500 {
+            let (x, y) = (rng.gen_range(0..60), rng.gen_range(0..60));
+            let _ = graph.try_add_dependency(&x.to_string(), &y.to_string());
+        }
+        assert!(graph.get_metrics().cycles_rejected > 0);
+        assert_valid_order(&graph);
+    }
+
+    #[test]
+    fn test_cycle_rejected_with_path() {
+        let mut graph = DependencyGraph::new();
+        graph.try_add_dependency("app", "lib").unwrap();
+        graph.try_add_dependency("lib", "core").unwrap();
+        let cycle = graph.try_add_dependency("core", "app").unwrap_err();
+        assert_eq!(cycle, ["core", "app", "lib", "core"]);
+        assert_eq!(graph.edge_count(), 2);
+        assert_eq!(graph.try_add_dependency("x", "x").unwrap_err(), ["x", "x"]);
+        assert!(graph.remove_dependency("lib", "core"));
+        assert_eq!(graph.try_add_dependency("core", "app"), Ok(true));
+        assert_valid_order(&graph);
+    }
+}
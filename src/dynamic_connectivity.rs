@@ -0,0 +1,322 @@
+use wasm_bindgen::prelude::*;
+
+const NIL: usize = usize::MAX;
+
+/// Connectivity of a forest under edge insertions and deletions, each in
+/// amortized O(log n) (Sleator–Tarjan link-cut trees).
+///
+/// Every tree of the forest is split into *preferred paths*, each held in a
+/// splay tree keyed by depth; path-parent pointers link the splay trees.
+/// `access(v)` makes the root-to-v path preferred, after which the tree's
+/// root is the leftmost node of v's splay tree. Re-rooting flips a path's
+/// order with a lazy reversal flag, which is what lets `link` and `cut`
+/// work on arbitrary nodes.
+///
+/// Only forests are represented: `link` refuses an edge between nodes that
+/// are already connected, since it would close a cycle.
+///
+/// # Example
+/// ```javascript
+/// const dc = new DynamicConnectivity(4);
+/// dc.link(0, 1); dc.link(1, 2);
+/// dc.connected(0, 2); // true
+/// dc.cut(1, 2);
+/// dc.connected(0, 2); // false
+/// ```
+#[wasm_bindgen]
+#[derive(Clone, Debug, Default)]
+pub struct DynamicConnectivity {
+    /// Splay-tree children: [shallower side, deeper side]
+    children: Vec<[usize; 2]>,
+    /// Splay parent, or path-parent for a splay tree's root
+    parent: Vec<usize>,
+    /// Pending reversal of the node's splay subtree
+    reversed: Vec<bool>,
+    edge_count: usize,
+    metrics: DynamicConnectivityMetrics,
+}
+
+/// Work done by a [`DynamicConnectivity`]
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DynamicConnectivityMetrics {
+    pub links: u32,
+    /// Links refused because the nodes were already connected
+    pub rejected_links: u32,
+    pub cuts: u32,
+    pub connectivity_queries: u32,
+    pub rotations: u32,
+    /// Preferred-path switches made by `access`
+    pub path_switches: u32,
+}
+
+impl DynamicConnectivity {
+    fn is_splay_root(&self, x: usize) -> bool {
+        let p = self.parent[x];
+        p == NIL || !self.children[p].contains(&x)
+    }
+
+    fn push_down(&mut self, x: usize) {
+        if self.reversed[x] {
+            self.reversed[x] = false;
+            self.children[x].swap(0, 1);
+            for c in self.children[x] {
+                if c != NIL {
+                    self.reversed[c] ^= true;
+                }
+            }
+        }
+    }
+
+    fn rotate(&mut self, x: usize) {
+        self.metrics.rotations += 1;
+        let p = self.parent[x];
+        let g = self.parent[p];
+        let side = usize::from(self.children[p][1] == x);
+        if !self.is_splay_root(p) {
+            let p_side = usize::from(self.children[g][1] == p);
+            self.children[g][p_side] = x;
+        }
+        self.parent[x] = g;
+        let inner = self.children[x][1 - side];
+        self.children[p][side] = inner;
+        if inner != NIL {
+            self.parent[inner] = p;
+        }
+        self.children[x][1 - side] = p;
+        self.parent[p] = x;
+    }
+
+    fn splay(&mut self, x: usize) {
+        // Apply pending reversals from the splay root down to x first
+        let mut path = vec![x];
+        let mut y = x;
+        while !self.is_splay_root(y) {
+            y = self.parent[y];
+            path.push(y);
+        }
+        for &node in path.iter().rev() {
+            self.push_down(node);
+        }
+        while !self.is_splay_root(x) {
+            let p = self.parent[x];
+            if !self.is_splay_root(p) {
+                let g = self.parent[p];
+                let zig_zig = (self.children[g][0] == p) == (self.children[p][0] == x);
+                self.rotate(if zig_zig { p } else { x });
+            }
+            self.rotate(x);
+        }
+    }
+
+    /// Make the root-to-`x` path preferred, with `x` at its splay root.
+    fn access(&mut self, x: usize) {
+        let mut last = NIL;
+        let mut y = x;
+        while y != NIL {
+            self.splay(y);
+            if self.children[y][1] != last {
+                self.metrics.path_switches += 1;
+            }
+            self.children[y][1] = last;
+            last = y;
+            y = self.parent[y];
+        }
+        self.splay(x);
+    }
+
+    fn make_root(&mut self, x: usize) {
+        self.access(x);
+        self.reversed[x] ^= true;
+    }
+
+    fn find_root(&mut self, x: usize) -> usize {
+        self.access(x);
+        let mut root = x;
+        loop {
+            self.push_down(root);
+            match self.children[root][0] {
+                NIL => break,
+                left => root = left,
+            }
+        }
+        self.splay(root);
+        root
+    }
+
+    fn contains(&self, node: u32) -> bool {
+        (node as usize) < self.parent.len()
+    }
+}
+
+#[wasm_bindgen]
+impl DynamicConnectivity {
+    /// A forest of `node_count` isolated nodes.
+    #[wasm_bindgen(constructor)]
+    pub fn new(node_count: u32) -> DynamicConnectivity {
+        let n = node_count as usize;
+        DynamicConnectivity {
+            children: vec![[NIL; 2]; n],
+            parent: vec![NIL; n],
+            reversed: vec![false; n],
+            ..DynamicConnectivity::default()
+        }
+    }
+
+    /// Append an isolated node, returning its number.
+    pub fn add_node(&mut self) -> u32 {
+        self.children.push([NIL; 2]);
+        self.parent.push(NIL);
+        self.reversed.push(false);
+        self.parent.len() as u32 - 1
+    }
+
+    /// Add the edge `u - v`. Returns false, changing nothing, if the nodes
+    /// are already connected or unknown.
+    pub fn link(&mut self, u: u32, v: u32) -> bool {
+        if !self.contains(u) || !self.contains(v) {
+            return false;
+        }
+        let (u, v) = (u as usize, v as usize);
+        if u == v || self.find_root(u) == self.find_root(v) {
+            self.metrics.rejected_links += 1;
+            return false;
+        }
+        self.make_root(u);
+        self.parent[u] = v;
+        self.edge_count += 1;
+        self.metrics.links += 1;
+        true
+    }
+
+    /// Remove the edge `u - v`. Returns false if there is no such edge.
+    pub fn cut(&mut self, u: u32, v: u32) -> bool {
+        if !self.contains(u) || !self.contains(v) || u == v {
+            return false;
+        }
+        let (u, v) = (u as usize, v as usize);
+        self.make_root(u);
+        self.access(v);
+        // With u as root, the edge exists iff u is v's only shallower node
+        self.push_down(u);
+        if self.children[v][0] != u || self.children[u][1] != NIL {
+            return false;
+        }
+        self.children[v][0] = NIL;
+        self.parent[u] = NIL;
+        self.edge_count -= 1;
+        self.metrics.cuts += 1;
+        true
+    }
+
+    /// Whether a path joins `u` and `v`.
+    pub fn connected(&mut self, u: u32, v: u32) -> bool {
+        if !self.contains(u) || !self.contains(v) {
+            return false;
+        }
+        self.metrics.connectivity_queries += 1;
+        u == v || self.find_root(u as usize) == self.find_root(v as usize)
+    }
+
+    /// Trees in the forest, isolated nodes included.
+    pub fn component_count(&self) -> usize {
+        self.parent.len() - self.edge_count
+    }
+
+    pub fn node_count(&self) -> u32 {
+        self.parent.len() as u32
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.edge_count
+    }
+
+    pub fn get_metrics(&self) -> DynamicConnectivityMetrics {
+        self.metrics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::DefaultRng;
+    use rand::Rng;
+    use std::collections::BTreeSet;
+
+    fn naive_connected(edges: &BTreeSet<(u32, u32)>, n: u32, u: u32, v: u32) -> bool {
+        let mut seen = vec![false; n as usize];
+        let mut stack = vec![u];
+        seen[u as usize] = true;
+        while let Some(x) = stack.pop() {
+            for &(a, b) in edges {
+                let y = if a == x {
+                    b
+                } else if b == x {
+                    a
+                } else {
+                    continue;
+                };
+                if !seen[y as usize] {
+                    seen[y as usize] = true;
+                    stack.push(y);
+                }
+            }
+        }
+        seen[v as usize]
+    }
+
+    #[test]
+    fn test_link_cut_connected() {
+        let mut dc = DynamicConnectivity::new(5);
+        assert!(dc.link(0, 1));
+        assert!(dc.link(1, 2));
+        assert!(dc.link(3, 4));
+        assert!(!dc.link(2, 0)); // would close a cycle
+        assert!(dc.connected(0, 2));
+        assert!(!dc.connected(0, 3));
+        assert_eq!(dc.component_count(), 2);
+
+        assert!(!dc.cut(0, 2)); // not an edge
+        assert!(dc.cut(2, 1));
+        assert!(!dc.connected(0, 2));
+        assert!(dc.link(2, 3));
+        assert!(dc.connected(2, 4));
+        let metrics = dc.get_metrics();
+        assert_eq!(
+            (metrics.links, metrics.cuts, metrics.rejected_links),
+            (4, 1, 1)
+        );
+    }
+
+    #[test]
+    fn test_matches_naive_forest() {
+        let n = 60;
+        let mut rng = DefaultRng::seed_from(21);
+        let mut dc = DynamicConnectivity::new(n);
+        let mut edges: BTreeSet<(u32, u32)> = BTreeSet::new();
+        for _ in 0..3_000 {
+            let (u, v) = (rng.gen_range(0..n), rng.gen_range(0..n));
+            let key = (u.min(v), u.max(v));
+            match rng.gen_range(0..3) {
+                0 => {
+                    let expected = u != v && !naive_connected(&edges, n, u, v);
+                    assert_eq!(dc.link(u, v), expected);
+                    if expected {
+                        edges.insert(key);
+                    }
+                }
+                1 => {
+                    // Cut an existing edge most of the time
+                    let (a, b) = if edges.is_empty() || rng.gen_bool(0.2) {
+                        key
+                    } else {
+                        *edges.iter().nth(rng.gen_range(0..edges.len())).unwrap()
+                    };
+                    assert_eq!(dc.cut(a, b), edges.remove(&(a.min(b), a.max(b))));
+                }
+                _ => assert_eq!(dc.connected(u, v), naive_connected(&edges, n, u, v)),
+            }
+        }
+        assert_eq!(dc.edge_count(), edges.len());
+    }
+}
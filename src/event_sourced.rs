@@ -0,0 +1,325 @@
+use crate::clock::now_ms;
+use crate::interop;
+use crate::kv_store::{new_store, KvStore};
+use crate::snapshot::Snapshot;
+use js_sys::{Array, Object, Reflect};
+use wasm_bindgen::prelude::*;
+
+/// One write in an [`EventSourcedStore`]'s log.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LogEvent {
+    Insert { key: String, value: u32 },
+    Delete { key: String },
+}
+
+impl LogEvent {
+    pub fn apply(&self, store: &mut dyn KvStore) {
+        match self {
+            LogEvent::Insert { key, value } => store.kv_insert(key.clone(), *value),
+            LogEvent::Delete { key } => {
+                store.kv_delete(key);
+            }
+        }
+    }
+
+    /// `{ type: "insert", key, value }` or `{ type: "delete", key }`.
+    pub fn to_js(&self) -> JsValue {
+        let object = Object::new();
+        let set = |name: &str, value: JsValue| {
+            let _ = Reflect::set(&object, &name.into(), &value);
+        };
+        match self {
+            LogEvent::Insert { key, value } => {
+                set("type", "insert".into());
+                set("key", key.into());
+                set("value", (*value).into());
+            }
+            LogEvent::Delete { key } => {
+                set("type", "delete".into());
+                set("key", key.into());
+            }
+        }
+        object.into()
+    }
+
+    /// Read an object in the shape [`to_js`](Self::to_js) produces.
+    pub fn from_js(value: &JsValue) -> Result<LogEvent, String> {
+        let field = |name: &str| Reflect::get(value, &name.into()).unwrap_or(JsValue::UNDEFINED);
+        let key = field("key")
+            .as_string()
+            .ok_or_else(|| "event key must be a string".to_string())?;
+        match field("type").as_string().as_deref() {
+            Some("insert") => {
+                let value = interop::value_from_number(&key, field("value").as_f64())?;
+                Ok(LogEvent::Insert { key, value })
+            }
+            Some("delete") => Ok(LogEvent::Delete { key }),
+            _ => Err("event type must be \"insert\" or \"delete\"".to_string()),
+        }
+    }
+}
+
+/// What an [`EventSourcedStore`]'s log holds and what replaying it cost.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct EventLogMetrics {
+    pub events_appended: u32,
+    /// Events after the snapshot, replayed by every `fold`
+    pub tail_len: u32,
+    pub compactions: u32,
+    /// Events folded into snapshots and dropped from the log
+    pub events_compacted: u32,
+    /// Entries in the snapshot, inserted before the tail on every `fold`
+    pub snapshot_entries: u32,
+    pub folds: u32,
+    /// Snapshot entries plus tail events the last `fold` applied
+    pub last_fold_operations: u32,
+    pub last_fold_ms: f64,
+}
+
+/// Any structure defined by an append-only log of inserts and deletes:
+/// event sourcing in miniature.
+///
+/// Writes append an event and apply it to the current state. `fold()`
+/// throws that state away and rebuilds it from the log alone, which is the
+/// replay cost of recovering from the log. As the log grows so does that
+/// cost, until `compact()` collapses everything so far into a snapshot and
+/// keeps only the events after it (the tail).
+///
+/// # Example
+/// ```javascript
+/// const store = new EventSourcedStore("rbtree");
+/// for (const [k, v] of edits) store.insert(k, v);
+/// store.fold();
+/// console.log(store.metrics().last_fold_ms);
+/// store.compact();  // later folds start from the snapshot
+/// const copy = EventSourcedStore.from_events("bst", store.events());
+/// ```
+#[wasm_bindgen]
+pub struct EventSourcedStore {
+    state: Box<dyn KvStore>,
+    snapshot: Snapshot,
+    tail: Vec<LogEvent>,
+    metrics: EventLogMetrics,
+}
+
+impl EventSourcedStore {
+    pub fn try_new(kind: &str) -> Result<EventSourcedStore, String> {
+        let state = new_store(kind, 0).ok_or_else(|| format!("unknown structure '{}'", kind))?;
+        Ok(EventSourcedStore {
+            snapshot: Snapshot::capture(state.as_ref()),
+            state,
+            tail: Vec::new(),
+            metrics: EventLogMetrics::default(),
+        })
+    }
+
+    /// A store of `kind` whose log is `events`, applied in order.
+    pub fn try_from_events(
+        kind: &str,
+        events: impl IntoIterator<Item = LogEvent>,
+    ) -> Result<EventSourcedStore, String> {
+        let mut store = Self::try_new(kind)?;
+        for event in events {
+            store.append(event);
+        }
+        Ok(store)
+    }
+
+    pub fn append(&mut self, event: LogEvent) {
+        event.apply(self.state.as_mut());
+        self.tail.push(event);
+        self.metrics.events_appended += 1;
+        self.metrics.tail_len += 1;
+    }
+
+    /// The events after the last compaction, oldest first.
+    pub fn tail(&self) -> &[LogEvent] {
+        &self.tail
+    }
+
+    pub fn inner(&self) -> &dyn KvStore {
+        self.state.as_ref()
+    }
+}
+
+#[wasm_bindgen]
+impl EventSourcedStore {
+    /// An empty log over a structure of `kind` (any name `new_store` accepts).
+    #[wasm_bindgen(constructor)]
+    pub fn new(kind: &str) -> Result<EventSourcedStore, JsValue> {
+        Self::try_new(kind).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// A store of `kind` built purely from `events`, objects as `events()`
+    /// returns them. Throws on the first malformed event.
+    pub fn from_events(kind: &str, events: &Array) -> Result<EventSourcedStore, JsValue> {
+        let events = events
+            .iter()
+            .map(|event| LogEvent::from_js(&event))
+            .collect::<Result<Vec<_>, String>>()
+            .map_err(|e| JsValue::from_str(&e))?;
+        Self::try_from_events(kind, events).map_err(|e| JsValue::from_str(&e))
+    }
+
+    pub fn insert(&mut self, key: String, value: u32) {
+        self.append(LogEvent::Insert { key, value });
+    }
+
+    pub fn get(&mut self, key: &str) -> Option<u32> {
+        self.state.kv_get(key)
+    }
+
+    /// Delete `key`, logging the delete only if the key was present.
+    pub fn delete(&mut self, key: &str) -> bool {
+        if self.state.kv_get(key).is_none() {
+            return false;
+        }
+        self.append(LogEvent::Delete {
+            key: key.to_string(),
+        });
+        true
+    }
+
+    /// Rebuild the state from the snapshot and tail alone, returning how
+    /// many operations that took (snapshot entries plus tail events).
+    pub fn fold(&mut self) -> u32 {
+        let start = now_ms();
+        let mut state = self
+            .snapshot
+            .restore()
+            .expect("the snapshot's kind was checked on construction");
+        for event in &self.tail {
+            event.apply(state.as_mut());
+        }
+        self.state = state;
+        let operations = (self.snapshot.entries.len() + self.tail.len()) as u32;
+        self.metrics.folds += 1;
+        self.metrics.last_fold_operations = operations;
+        self.metrics.last_fold_ms = now_ms() - start;
+        operations
+    }
+
+    /// Collapse the whole log into a snapshot of the current state and
+    /// drop the events, returning how many were dropped.
+    pub fn compact(&mut self) -> u32 {
+        let dropped = self.tail.len() as u32;
+        self.snapshot = Snapshot::capture(self.state.as_ref());
+        self.tail.clear();
+        self.metrics.compactions += 1;
+        self.metrics.events_compacted += dropped;
+        self.metrics.tail_len = 0;
+        self.metrics.snapshot_entries = self.snapshot.entries.len() as u32;
+        dropped
+    }
+
+    /// The tail as `{ type, key, value? }` objects, oldest first. After a
+    /// compaction, rebuilding from these alone also needs the snapshot:
+    /// `snapshot_events()` followed by these.
+    pub fn events(&self) -> Array {
+        self.tail.iter().map(LogEvent::to_js).collect()
+    }
+
+    /// The snapshot as insert events, so that it followed by `events()` is
+    /// a complete log.
+    pub fn snapshot_events(&self) -> Array {
+        self.snapshot
+            .entries
+            .iter()
+            .map(|(key, value)| {
+                LogEvent::Insert {
+                    key: key.clone(),
+                    value: *value,
+                }
+                .to_js()
+            })
+            .collect()
+    }
+
+    /// Structure kind being rebuilt.
+    pub fn kind(&self) -> String {
+        self.state.kind().to_string()
+    }
+
+    pub fn len(&self) -> usize {
+        self.state.kv_len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.state.kv_len() == 0
+    }
+
+    pub fn metrics(&self) -> EventLogMetrics {
+        self.metrics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::DefaultRng;
+    use rand::Rng;
+
+    fn sorted(store: &dyn KvStore) -> Vec<(String, u32)> {
+        let mut entries = store.kv_entries();
+        entries.sort_unstable();
+        entries
+    }
+
+    #[test]
+    fn test_fold_reproduces_state() {
+        for kind in ["hashmap", "skiplist", "trie"] {
+            let mut store = EventSourcedStore::try_new(kind).unwrap();
+            let mut rng = DefaultRng::seed_from(3);
+            for round in 0..4 {
+                for _ in 0..300 {
+                    let key = format!("k{}", rng.gen_range(0..60));
+                    if rng.gen_bool(0.3) {
+                        store.delete(&key);
+                    } else {
+                        store.insert(key, rng.gen_range(0..1_000));
+                    }
+                }
+                let before = sorted(store.inner());
+                assert_eq!(
+                    store.fold() as usize,
+                    store.snapshot.entries.len() + store.tail.len()
+                );
+                assert_eq!(sorted(store.inner()), before, "{}", kind);
+                if round % 2 == 1 {
+                    store.compact();
+                    store.fold();
+                    assert_eq!(sorted(store.inner()), before, "{}", kind);
+                }
+            }
+            let metrics = store.metrics();
+            assert_eq!(metrics.compactions, 2);
+            assert_eq!(
+                metrics.events_appended,
+                metrics.events_compacted + metrics.tail_len
+            );
+            assert!(metrics.last_fold_operations <= 60 + metrics.tail_len);
+        }
+    }
+
+    #[test]
+    fn test_from_events() {
+        let events = vec![
+            LogEvent::Insert {
+                key: "a".to_string(),
+                value: 1,
+            },
+            LogEvent::Insert {
+                key: "b".to_string(),
+                value: 2,
+            },
+            LogEvent::Delete {
+                key: "a".to_string(),
+            },
+        ];
+        let store = EventSourcedStore::try_from_events("bst", events.clone()).unwrap();
+        assert_eq!(store.tail(), events);
+        assert_eq!(sorted(store.inner()), [("b".to_string(), 2)]);
+        assert!(EventSourcedStore::try_new("btree").is_err());
+    }
+}
@@ -0,0 +1,226 @@
+use js_sys::{Function, Object, Reflect};
+use std::cell::RefCell;
+use std::rc::Rc;
+use wasm_bindgen::prelude::*;
+
+/// Lifecycle events a [`DynamicStore`](crate::kv_store::DynamicStore) can emit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EventKind {
+    Insert,
+    Delete,
+    Resize,
+    Rebalance,
+}
+
+impl EventKind {
+    /// Parse the name used from JS: `"insert"`, `"delete"`, `"resize"` or `"rebalance"`.
+    pub fn from_name(name: &str) -> Option<EventKind> {
+        match name {
+            "insert" => Some(EventKind::Insert),
+            "delete" => Some(EventKind::Delete),
+            "resize" => Some(EventKind::Resize),
+            "rebalance" => Some(EventKind::Rebalance),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            EventKind::Insert => "insert",
+            EventKind::Delete => "delete",
+            EventKind::Resize => "resize",
+            EventKind::Rebalance => "rebalance",
+        }
+    }
+}
+
+/// Payload handed to event listeners.
+#[derive(Clone, Debug, PartialEq)]
+pub enum StoreEvent {
+    /// A key was inserted or updated; `len` is the size afterwards.
+    Inserted { key: String, value: u32, len: usize },
+    /// A key that was present has been removed.
+    Deleted { key: String, len: usize },
+    /// The backing table changed from `from` to `to` slots (or buckets).
+    Resized { from: usize, to: usize },
+    /// A tree recolored or rotated to restore its balance; `total` is the
+    /// number of rebalancing operations so far.
+    Rebalanced { total: u64 },
+}
+
+impl StoreEvent {
+    pub fn kind(&self) -> EventKind {
+        match self {
+            StoreEvent::Inserted { .. } => EventKind::Insert,
+            StoreEvent::Deleted { .. } => EventKind::Delete,
+            StoreEvent::Resized { .. } => EventKind::Resize,
+            StoreEvent::Rebalanced { .. } => EventKind::Rebalance,
+        }
+    }
+
+    /// `{ type, ... }` with the variant's fields, e.g.
+    /// `{ type: "resize", from: 16, to: 32 }`.
+    pub fn to_js(&self) -> JsValue {
+        let object = Object::new();
+        let set = |name: &str, value: JsValue| {
+            let _ = Reflect::set(&object, &name.into(), &value);
+        };
+        set("type", self.kind().name().into());
+        match self {
+            StoreEvent::Inserted { key, value, len } => {
+                set("key", key.into());
+                set("value", (*value).into());
+                set("len", (*len as u32).into());
+            }
+            StoreEvent::Deleted { key, len } => {
+                set("key", key.into());
+                set("len", (*len as u32).into());
+            }
+            StoreEvent::Resized { from, to } => {
+                set("from", (*from as u32).into());
+                set("to", (*to as u32).into());
+            }
+            StoreEvent::Rebalanced { total } => {
+                set("total", (*total as f64).into());
+            }
+        }
+        object.into()
+    }
+}
+
+type Listener = Rc<RefCell<dyn FnMut(&StoreEvent)>>;
+
+/// Listeners keyed by event kind.
+#[derive(Default)]
+pub struct EventEmitter {
+    next_id: u32,
+    listeners: Vec<(u32, EventKind, Listener)>,
+}
+
+impl EventEmitter {
+    /// Call `listener` for every event of `kind`; returns an id for [`Self::unsubscribe`].
+    pub fn subscribe(
+        &mut self,
+        kind: EventKind,
+        listener: impl FnMut(&StoreEvent) + 'static,
+    ) -> u32 {
+        self.next_id += 1;
+        self.listeners
+            .push((self.next_id, kind, Rc::new(RefCell::new(listener))));
+        self.next_id
+    }
+
+    /// Subscribe a JS function. Exceptions thrown by it are swallowed so a
+    /// broken visualization can't interrupt a write.
+    pub fn subscribe_js(&mut self, kind: EventKind, callback: Function) -> u32 {
+        self.subscribe(kind, move |event| {
+            let _ = callback.call1(&JsValue::UNDEFINED, &event.to_js());
+        })
+    }
+
+    pub fn unsubscribe(&mut self, id: u32) -> bool {
+        let before = self.listeners.len();
+        self.listeners.retain(|(i, _, _)| *i != id);
+        self.listeners.len() != before
+    }
+
+    /// Whether anyone listens for `kind`, so callers can skip building payloads.
+    pub fn wants(&self, kind: EventKind) -> bool {
+        self.listeners.iter().any(|(_, k, _)| *k == kind)
+    }
+
+    fn is_subscribed(&self, id: u32) -> bool {
+        self.listeners.iter().any(|(i, _, _)| *i == id)
+    }
+
+    /// Call the listeners for `event`'s kind. `emitter` is only borrowed to
+    /// copy them out, so a listener may subscribe, unsubscribe or write to
+    /// the store that owns the emitter. A listener unsubscribed by an
+    /// earlier one isn't called, and one whose own write emits again isn't
+    /// re-entered.
+    pub fn dispatch(emitter: &RefCell<EventEmitter>, event: &StoreEvent) {
+        let kind = event.kind();
+        let listeners: Vec<(u32, Listener)> = emitter
+            .borrow()
+            .listeners
+            .iter()
+            .filter(|(_, k, _)| *k == kind)
+            .map(|(id, _, listener)| (*id, Rc::clone(listener)))
+            .collect();
+        for (id, listener) in listeners {
+            if !emitter.borrow().is_subscribed(id) {
+                continue;
+            }
+            if let Ok(mut listener) = listener.try_borrow_mut() {
+                (*listener)(event);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_emit_reaches_matching_listeners() {
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let emitter = RefCell::new(EventEmitter::default());
+        let log = Rc::clone(&seen);
+        let id = emitter
+            .borrow_mut()
+            .subscribe(EventKind::Delete, move |e| log.borrow_mut().push(e.clone()));
+        assert!(emitter.borrow().wants(EventKind::Delete));
+        assert!(!emitter.borrow().wants(EventKind::Insert));
+
+        let deleted = StoreEvent::Deleted {
+            key: "a".to_string(),
+            len: 0,
+        };
+        EventEmitter::dispatch(&emitter, &StoreEvent::Resized { from: 8, to: 16 });
+        EventEmitter::dispatch(&emitter, &deleted);
+        assert_eq!(*seen.borrow(), vec![deleted.clone()]);
+
+        assert!(emitter.borrow_mut().unsubscribe(id));
+        assert!(!emitter.borrow_mut().unsubscribe(id));
+        EventEmitter::dispatch(&emitter, &deleted);
+        assert_eq!(seen.borrow().len(), 1);
+    }
+
+    #[test]
+    fn test_listener_can_unsubscribe_during_dispatch() {
+        let emitter = Rc::new(RefCell::new(EventEmitter::default()));
+        let calls = Rc::new(RefCell::new(Vec::new()));
+        let (log, handle) = (Rc::clone(&calls), Rc::clone(&emitter));
+        let first = emitter.borrow_mut().subscribe(EventKind::Resize, move |_| {
+            log.borrow_mut().push("first");
+            // Drops itself and the listener after it
+            handle.borrow_mut().unsubscribe(1);
+            handle.borrow_mut().unsubscribe(2);
+        });
+        let log = Rc::clone(&calls);
+        let second = emitter
+            .borrow_mut()
+            .subscribe(EventKind::Resize, move |_| log.borrow_mut().push("second"));
+        assert_eq!((first, second), (1, 2));
+
+        let resized = StoreEvent::Resized { from: 8, to: 16 };
+        EventEmitter::dispatch(&emitter, &resized);
+        EventEmitter::dispatch(&emitter, &resized);
+        assert_eq!(*calls.borrow(), vec!["first"]);
+        assert!(!emitter.borrow().wants(EventKind::Resize));
+    }
+
+    #[test]
+    fn test_event_names_round_trip() {
+        for kind in [
+            EventKind::Insert,
+            EventKind::Delete,
+            EventKind::Resize,
+            EventKind::Rebalance,
+        ] {
+            assert_eq!(EventKind::from_name(kind.name()), Some(kind));
+        }
+        assert_eq!(EventKind::from_name("rehash"), None);
+    }
+}
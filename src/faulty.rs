@@ -0,0 +1,420 @@
+use crate::kv_store::{new_store, KvStore};
+use crate::rng::DefaultRng;
+use rand::Rng;
+use wasm_bindgen::prelude::*;
+
+/// How a [`FaultyMap`] misbehaves: a simulated latency per operation, and
+/// the chance each operation fails in one of three ways.
+///
+/// # Example
+/// ```javascript
+/// const config = new FaultConfig().latency(5, 20).error_rate(0.1).lost_reply_rate(0.05).seed(42);
+/// ```
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FaultConfig {
+    latency_ms: f64,
+    jitter_ms: f64,
+    timeout_ms: f64,
+    error_rate: f64,
+    drop_rate: f64,
+    lost_reply_rate: f64,
+    seed: Option<u64>,
+}
+
+impl Default for FaultConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FaultConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        for (name, ms) in [
+            ("latency", self.latency_ms),
+            ("jitter", self.jitter_ms),
+            ("timeout", self.timeout_ms),
+        ] {
+            if !(ms >= 0.0 && ms.is_finite()) {
+                return Err(format!(
+                    "{} must be a finite, non-negative number of ms (got {})",
+                    name, ms
+                ));
+            }
+        }
+        for (name, rate) in [
+            ("error_rate", self.error_rate),
+            ("drop_rate", self.drop_rate),
+            ("lost_reply_rate", self.lost_reply_rate),
+        ] {
+            if !(0.0..=1.0).contains(&rate) {
+                return Err(format!("{} must be in [0, 1] (got {})", name, rate));
+            }
+        }
+        let total = self.error_rate + self.drop_rate + self.lost_reply_rate;
+        if total > 1.0 {
+            return Err(format!(
+                "fault rates must add up to at most 1 (got {})",
+                total
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[wasm_bindgen]
+impl FaultConfig {
+    /// No faults, and 1 ms per operation with a 100 ms timeout.
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> FaultConfig {
+        FaultConfig {
+            latency_ms: 1.0,
+            jitter_ms: 0.0,
+            timeout_ms: 100.0,
+            error_rate: 0.0,
+            drop_rate: 0.0,
+            lost_reply_rate: 0.0,
+            seed: None,
+        }
+    }
+
+    /// Each operation takes `base_ms` plus up to `jitter_ms` more, drawn
+    /// uniformly.
+    pub fn latency(mut self, base_ms: f64, jitter_ms: f64) -> FaultConfig {
+        self.latency_ms = base_ms;
+        self.jitter_ms = jitter_ms;
+        self
+    }
+
+    /// How long a client waits on a dropped request or lost reply before
+    /// giving up (default 100 ms).
+    pub fn timeout(mut self, ms: f64) -> FaultConfig {
+        self.timeout_ms = ms;
+        self
+    }
+
+    /// Chance the structure reports a transient error without applying
+    /// the operation.
+    pub fn error_rate(mut self, rate: f64) -> FaultConfig {
+        self.error_rate = rate;
+        self
+    }
+
+    /// Chance the request is lost on the way: not applied, and the client
+    /// times out.
+    pub fn drop_rate(mut self, rate: f64) -> FaultConfig {
+        self.drop_rate = rate;
+        self
+    }
+
+    /// Chance the reply is lost on the way back: applied, but the client
+    /// times out just as for a dropped request.
+    pub fn lost_reply_rate(mut self, rate: f64) -> FaultConfig {
+        self.lost_reply_rate = rate;
+        self
+    }
+
+    /// Seed for every latency and fault drawn; unseeded maps draw one from
+    /// [`entropy_seed`](crate::rng::entropy_seed).
+    pub fn seed(mut self, seed: u32) -> FaultConfig {
+        self.seed = Some(u64::from(seed));
+        self
+    }
+
+    /// Full 64-bit seed (a `BigInt` in JS), e.g. one read back from
+    /// `FaultyMap.seed()` to replay a run.
+    pub fn seed_u64(mut self, seed: u64) -> FaultConfig {
+        self.seed = Some(seed);
+        self
+    }
+}
+
+/// What happened to one operation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Fault {
+    /// Not applied; the client is told so
+    Transient,
+    /// Not applied; the client times out
+    Dropped,
+    /// Applied; the client times out all the same
+    LostReply,
+}
+
+/// What a [`FaultyMap`]'s clients saw, and what really happened.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FaultMetrics {
+    pub operations: u32,
+    pub succeeded: u32,
+    pub transient_errors: u32,
+    pub dropped: u32,
+    /// Timed-out operations the structure did apply
+    pub lost_replies: u32,
+    /// Simulated time spent in operations, timeouts included
+    pub total_latency_ms: f64,
+}
+
+#[wasm_bindgen]
+impl FaultMetrics {
+    /// Operations the client saw time out, whether or not they applied.
+    pub fn timeouts(&self) -> u32 {
+        self.dropped + self.lost_replies
+    }
+}
+
+/// Any structure behind a simulated unreliable network, for running
+/// lessons on retries, idempotency and client-side caching against it.
+///
+/// Every operation advances a simulated clock by its latency instead of
+/// sleeping, and may fail as [`FaultConfig`] says: with a transient error,
+/// or with a timeout the client can't tell apart from a lost reply to an
+/// operation that did apply. After a timeout a write may or may not have
+/// happened, so retrying is only safe because inserts and deletes are
+/// idempotent. Everything is drawn from one seeded generator, so a run
+/// replays exactly from its seed.
+///
+/// # Example
+/// ```javascript
+/// const map = new FaultyMap("hashmap", new FaultConfig().drop_rate(0.2).seed(7));
+/// for (let attempt = 0; ; attempt++) {
+///   try { map.insert("cart", 3); break; }
+///   catch (e) { map.advance_time(2 ** attempt * 10); }  // back off
+/// }
+/// console.log(map.now_ms(), map.metrics().timeouts());
+/// ```
+#[wasm_bindgen]
+pub struct FaultyMap {
+    store: Box<dyn KvStore>,
+    config: FaultConfig,
+    rng: DefaultRng,
+    now_ms: f64,
+    last_latency_ms: f64,
+    metrics: FaultMetrics,
+}
+
+impl FaultyMap {
+    pub fn try_wrap(store: Box<dyn KvStore>, config: FaultConfig) -> Result<FaultyMap, String> {
+        config.validate()?;
+        let rng = match config.seed {
+            Some(seed) => DefaultRng::seed_from(seed),
+            None => DefaultRng::from_entropy(),
+        };
+        Ok(FaultyMap {
+            store,
+            config,
+            rng,
+            now_ms: 0.0,
+            last_latency_ms: 0.0,
+            metrics: FaultMetrics::default(),
+        })
+    }
+
+    pub fn try_new(kind: &str, config: FaultConfig) -> Result<FaultyMap, String> {
+        let store = new_store(kind, 0).ok_or_else(|| format!("unknown structure '{}'", kind))?;
+        Self::try_wrap(store, config)
+    }
+
+    pub fn inner(&self) -> &dyn KvStore {
+        self.store.as_ref()
+    }
+
+    /// Run `op` against the structure unless the network loses it, and
+    /// advance the clock by what the client waited.
+    pub fn try_run<T>(&mut self, op: impl FnOnce(&mut dyn KvStore) -> T) -> Result<T, Fault> {
+        // Always draw both numbers, so changing one rate doesn't shift
+        // every later draw
+        let roll: f64 = self.rng.gen();
+        let jitter: f64 = self.rng.gen();
+        let c = self.config;
+        let fault = if roll < c.error_rate {
+            Some(Fault::Transient)
+        } else if roll < c.error_rate + c.drop_rate {
+            Some(Fault::Dropped)
+        } else if roll < c.error_rate + c.drop_rate + c.lost_reply_rate {
+            Some(Fault::LostReply)
+        } else {
+            None
+        };
+        let latency = match fault {
+            Some(Fault::Dropped | Fault::LostReply) => c.timeout_ms,
+            _ => c.latency_ms + jitter * c.jitter_ms,
+        };
+        self.now_ms += latency;
+        self.last_latency_ms = latency;
+        self.metrics.operations += 1;
+        self.metrics.total_latency_ms += latency;
+        match fault {
+            None => {
+                self.metrics.succeeded += 1;
+                Ok(op(self.store.as_mut()))
+            }
+            Some(fault) => {
+                match fault {
+                    Fault::Transient => self.metrics.transient_errors += 1,
+                    Fault::Dropped => self.metrics.dropped += 1,
+                    Fault::LostReply => {
+                        self.metrics.lost_replies += 1;
+                        op(self.store.as_mut());
+                    }
+                }
+                Err(fault)
+            }
+        }
+    }
+
+    fn to_js_error(&self, fault: Fault) -> JsValue {
+        let message = match fault {
+            Fault::Transient => "transient error, try again".to_string(),
+            Fault::Dropped | Fault::LostReply => {
+                format!("timed out after {} ms", self.config.timeout_ms)
+            }
+        };
+        JsValue::from_str(&message)
+    }
+}
+
+#[wasm_bindgen]
+impl FaultyMap {
+    /// An empty structure of `kind` (any name `new_store` accepts) behind
+    /// the faults `config` describes.
+    #[wasm_bindgen(constructor)]
+    pub fn new(kind: &str, config: &FaultConfig) -> Result<FaultyMap, JsValue> {
+        Self::try_new(kind, *config).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// Insert `key`. Throws on a transient error or timeout.
+    pub fn insert(&mut self, key: String, value: u32) -> Result<(), JsValue> {
+        self.try_run(|store| store.kv_insert(key, value))
+            .map_err(|fault| self.to_js_error(fault))
+    }
+
+    /// Look `key` up. Throws on a transient error or timeout.
+    pub fn get(&mut self, key: &str) -> Result<Option<u32>, JsValue> {
+        self.try_run(|store| store.kv_get(key))
+            .map_err(|fault| self.to_js_error(fault))
+    }
+
+    /// Delete `key`, returning whether it was present. Throws on a
+    /// transient error or timeout.
+    pub fn delete(&mut self, key: &str) -> Result<bool, JsValue> {
+        self.try_run(|store| store.kv_delete(key))
+            .map_err(|fault| self.to_js_error(fault))
+    }
+
+    /// Read `key` straight from the structure, with no latency or faults:
+    /// the server's view, to show what a timed-out write really did.
+    pub fn peek(&mut self, key: &str) -> Option<u32> {
+        self.store.kv_get(key)
+    }
+
+    /// Simulated time so far: every operation's latency plus any waits.
+    pub fn now_ms(&self) -> f64 {
+        self.now_ms
+    }
+
+    /// Latency of the last operation, or its timeout if it timed out.
+    pub fn last_latency_ms(&self) -> f64 {
+        self.last_latency_ms
+    }
+
+    /// Wait `ms` of simulated time, e.g. to back off before a retry.
+    pub fn advance_time(&mut self, ms: f64) {
+        self.now_ms += ms.max(0.0);
+    }
+
+    /// The seed faults are drawn from, to replay this run.
+    pub fn seed(&self) -> u64 {
+        self.rng.seed()
+    }
+
+    pub fn config(&self) -> FaultConfig {
+        self.config
+    }
+
+    pub fn len(&self) -> usize {
+        self.store.kv_len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.store.kv_len() == 0
+    }
+
+    pub fn kind(&self) -> String {
+        self.store.kind().to_string()
+    }
+
+    pub fn metrics(&self) -> FaultMetrics {
+        self.metrics
+    }
+
+    pub fn reset_metrics(&mut self) {
+        self.metrics = FaultMetrics::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(seed: u32) -> (Vec<Result<(), Fault>>, FaultyMap) {
+        let config = FaultConfig::new()
+            .latency(2.0, 3.0)
+            .timeout(50.0)
+            .error_rate(0.1)
+            .drop_rate(0.1)
+            .lost_reply_rate(0.1)
+            .seed(seed);
+        let mut map = FaultyMap::try_new("bst", config).unwrap();
+        let outcomes = (0..1_000)
+            .map(|i| map.try_run(|store| store.kv_insert(format!("k{}", i), i)))
+            .collect();
+        (outcomes, map)
+    }
+
+    #[test]
+    fn test_faults_replay_from_seed() {
+        let (outcomes, map) = run(9);
+        let (again, replay) = run(9);
+        assert_eq!(outcomes, again);
+        assert_eq!(map.metrics(), replay.metrics());
+        assert_ne!(run(10).0, outcomes);
+
+        let m = map.metrics();
+        assert_eq!(m.operations, 1_000);
+        for count in [m.transient_errors, m.dropped, m.lost_replies] {
+            assert!((60..140).contains(&count), "{:?}", m);
+        }
+        // Lost replies were applied; the other faults weren't
+        assert_eq!(map.len() as u32, m.succeeded + m.lost_replies);
+        for (i, outcome) in outcomes.iter().enumerate() {
+            let applied = map
+                .inner()
+                .kv_entries()
+                .iter()
+                .any(|(k, _)| *k == format!("k{}", i));
+            assert_eq!(applied, matches!(outcome, Ok(()) | Err(Fault::LostReply)));
+        }
+        assert_eq!(map.now_ms(), m.total_latency_ms);
+        let timeouts = m.timeouts() as f64 * 50.0;
+        assert!(m.total_latency_ms >= timeouts + 2.0 * (m.operations - m.timeouts()) as f64);
+        assert!(m.total_latency_ms <= timeouts + 5.0 * (m.operations - m.timeouts()) as f64);
+    }
+
+    #[test]
+    fn test_config_is_validated() {
+        assert!(FaultyMap::try_new("hashmap", FaultConfig::new().error_rate(1.5)).is_err());
+        assert!(FaultyMap::try_new("hashmap", FaultConfig::new().latency(-1.0, 0.0)).is_err());
+        let too_many = FaultConfig::new().drop_rate(0.6).lost_reply_rate(0.6);
+        assert!(FaultyMap::try_new("hashmap", too_many).is_err());
+        assert!(FaultyMap::try_new("btree", FaultConfig::new()).is_err());
+
+        let mut reliable = FaultyMap::try_new("trie", FaultConfig::new()).unwrap();
+        assert_eq!(
+            reliable.try_run(|store| store.kv_insert("a".into(), 1)),
+            Ok(())
+        );
+        assert_eq!(reliable.try_run(|store| store.kv_get("a")), Ok(Some(1)));
+        reliable.advance_time(10.0);
+        assert_eq!(reliable.now_ms(), 12.0);
+    }
+}
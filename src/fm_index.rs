@@ -0,0 +1,317 @@
+use crate::bitset::BitSet;
+use wasm_bindgen::prelude::*;
+
+/// Suffix-array positions kept by `FmIndex::new`: one per this many.
+pub const DEFAULT_SAMPLE_RATE: u32 = 16;
+
+/// Wavelet tree over symbols `lo..hi`: each level splits the alphabet in
+/// half and records, per position, which half the symbol falls in. Rank
+/// of a symbol is one BitSet rank per level.
+#[derive(Clone, Debug)]
+struct WaveletTree {
+    lo: u32,
+    hi: u32,
+    /// Bit set where the symbol is in the upper half
+    bits: BitSet,
+    children: Option<Box<(WaveletTree, WaveletTree)>>,
+}
+
+impl WaveletTree {
+    fn build(symbols: &[u32], lo: u32, hi: u32) -> WaveletTree {
+        if hi - lo <= 1 {
+            return WaveletTree {
+                lo,
+                hi,
+                bits: BitSet::default(),
+                children: None,
+            };
+        }
+        let mid = lo + (hi - lo) / 2;
+        let bits = BitSet::from_bits(symbols.iter().map(|&s| s >= mid));
+        let (low, high): (Vec<u32>, Vec<u32>) = symbols.iter().partition(|&&s| s < mid);
+        WaveletTree {
+            lo,
+            hi,
+            bits,
+            children: Some(Box::new((
+                WaveletTree::build(&low, lo, mid),
+                WaveletTree::build(&high, mid, hi),
+            ))),
+        }
+    }
+
+    /// Occurrences of `symbol` among the first `index` positions.
+    fn rank(&self, symbol: u32, mut index: usize, ranks: &mut u32) -> usize {
+        let mut node = self;
+        while let Some(children) = &node.children {
+            *ranks += 1;
+            let mid = node.lo + (node.hi - node.lo) / 2;
+            if symbol >= mid {
+                index = node.bits.rank1(index);
+                node = &children.1;
+            } else {
+                index = node.bits.rank0(index);
+                node = &children.0;
+            }
+        }
+        index
+    }
+
+    /// Symbol at `index`.
+    fn access(&self, mut index: usize, ranks: &mut u32) -> u32 {
+        let mut node = self;
+        while let Some(children) = &node.children {
+            *ranks += 1;
+            if node.bits.get(index) {
+                index = node.bits.rank1(index);
+                node = &children.1;
+            } else {
+                index = node.bits.rank0(index);
+                node = &children.0;
+            }
+        }
+        node.lo
+    }
+}
+
+/// Compressed full-text index (Ferragina & Manzini) over the Burrows–Wheeler
+/// transform of a text.
+///
+/// The BWT lists, for every suffix in sorted order, the character before
+/// it. `count` needs only the BWT, held in a wavelet tree of [`BitSet`]s,
+/// and the number of smaller characters: backward search narrows the range
+/// of sorted suffixes starting with the pattern one character at a time,
+/// two rank queries per character, independent of the text length.
+/// `locate` walks each match backwards through the text (the LF mapping)
+/// until it reaches one of the sampled suffix-array positions.
+///
+/// # Example
+/// ```javascript
+/// const index = new FmIndex("abracadabra");
+/// index.count("abra");  // 2
+/// index.locate("abra"); // Uint32Array [0, 7]
+/// index.bwt();          // "ard$rcaaaabb"
+/// ```
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct FmIndex {
+    /// Distinct characters, sorted; symbol s > 0 is `alphabet[s - 1]` and 0
+    /// is the terminator
+    alphabet: Vec<char>,
+    /// Symbols smaller than each symbol, over the whole BWT
+    smaller: Vec<usize>,
+    bwt: WaveletTree,
+    len: usize,
+    sample_rate: usize,
+    /// Rows of the sorted suffixes whose text position is sampled
+    sampled_rows: BitSet,
+    /// Text positions of those rows, in row order
+    samples: Vec<u32>,
+    metrics: FmIndexMetrics,
+}
+
+/// Work done by an [`FmIndex`]
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FmIndexMetrics {
+    pub queries: u32,
+    /// BitSet rank queries made by the wavelet tree
+    pub rank_queries: u32,
+    /// LF-mapping steps taken by `locate` to reach a sample
+    pub lf_steps: u32,
+}
+
+/// Suffix array by prefix doubling, O(n log² n).
+fn suffix_array(symbols: &[u32]) -> Vec<usize> {
+    let n = symbols.len();
+    let mut sa: Vec<usize> = (0..n).collect();
+    let mut rank: Vec<usize> = symbols.iter().map(|&s| s as usize).collect();
+    let mut next = vec![0; n];
+    let mut k = 1;
+    loop {
+        let key = |i: usize| (rank[i], rank.get(i + k).map_or(0, |r| r + 1));
+        sa.sort_by_key(|&i| key(i));
+        next[sa[0]] = 0;
+        for w in 1..n {
+            next[sa[w]] = next[sa[w - 1]] + usize::from(key(sa[w - 1]) != key(sa[w]));
+        }
+        std::mem::swap(&mut rank, &mut next);
+        if rank[sa[n - 1]] == n - 1 || k >= n {
+            return sa;
+        }
+        k *= 2;
+    }
+}
+
+impl FmIndex {
+    /// Index `text`, keeping every `sample_rate`-th suffix-array position
+    /// (at least 1): higher rates save memory and slow `locate`.
+    pub fn with_sample_rate(text: &str, sample_rate: u32) -> FmIndex {
+        let mut alphabet: Vec<char> = text.chars().collect();
+        alphabet.sort_unstable();
+        alphabet.dedup();
+        let mut symbols: Vec<u32> = text
+            .chars()
+            .map(|c| alphabet.binary_search(&c).expect("from text") as u32 + 1)
+            .collect();
+        symbols.push(0);
+        let n = symbols.len();
+        let sigma = alphabet.len() + 1;
+
+        let sa = suffix_array(&symbols);
+        let bwt: Vec<u32> = sa
+            .iter()
+            .map(|&i| if i == 0 { 0 } else { symbols[i - 1] })
+            .collect();
+        let mut smaller = vec![0; sigma + 1];
+        for &s in &symbols {
+            smaller[s as usize + 1] += 1;
+        }
+        for s in 1..=sigma {
+            smaller[s] += smaller[s - 1];
+        }
+        let sample_rate = sample_rate.max(1) as usize;
+        let sampled_rows = BitSet::from_bits(sa.iter().map(|&i| i % sample_rate == 0));
+        let samples = sa
+            .iter()
+            .filter(|&&i| i % sample_rate == 0)
+            .map(|&i| i as u32)
+            .collect();
+
+        FmIndex {
+            alphabet,
+            smaller,
+            bwt: WaveletTree::build(&bwt, 0, sigma as u32),
+            len: n - 1,
+            sample_rate,
+            sampled_rows,
+            samples,
+            metrics: FmIndexMetrics::default(),
+        }
+    }
+
+    /// Half-open range of sorted-suffix rows starting with `pattern`.
+    fn backward_search(&mut self, pattern: &str) -> (usize, usize) {
+        self.metrics.queries += 1;
+        let (mut start, mut end) = (0, self.len + 1);
+        for c in pattern.chars().rev() {
+            let Ok(s) = self.alphabet.binary_search(&c) else {
+                return (0, 0);
+            };
+            let symbol = s as u32 + 1;
+            let base = self.smaller[symbol as usize];
+            start = base + self.bwt.rank(symbol, start, &mut self.metrics.rank_queries);
+            end = base + self.bwt.rank(symbol, end, &mut self.metrics.rank_queries);
+            if start >= end {
+                return (0, 0);
+            }
+        }
+        (start, end)
+    }
+
+    /// Text position of the suffix in `row`.
+    fn position(&mut self, mut row: usize) -> u32 {
+        let mut steps = 0;
+        while !self.sampled_rows.get(row) {
+            // LF: the row of the suffix one character earlier
+            let symbol = self.bwt.access(row, &mut self.metrics.rank_queries);
+            row = self.smaller[symbol as usize]
+                + self.bwt.rank(symbol, row, &mut self.metrics.rank_queries);
+            steps += 1;
+        }
+        self.metrics.lf_steps += steps;
+        self.samples[self.sampled_rows.rank1(row)] + steps
+    }
+}
+
+#[wasm_bindgen]
+impl FmIndex {
+    #[wasm_bindgen(constructor)]
+    pub fn new(text: &str) -> FmIndex {
+        FmIndex::with_sample_rate(text, DEFAULT_SAMPLE_RATE)
+    }
+
+    /// Occurrences of `pattern` (overlapping ones included). The empty
+    /// pattern occurs at every position, end included.
+    pub fn count(&mut self, pattern: &str) -> usize {
+        let (start, end) = self.backward_search(pattern);
+        end - start
+    }
+
+    /// Character positions where `pattern` occurs, ascending.
+    pub fn locate(&mut self, pattern: &str) -> Vec<u32> {
+        let (start, end) = self.backward_search(pattern);
+        let mut positions: Vec<u32> = (start..end).map(|row| self.position(row)).collect();
+        positions.sort_unstable();
+        positions
+    }
+
+    /// The Burrows–Wheeler transform, with `$` for the terminator.
+    pub fn bwt(&self) -> String {
+        let mut ranks = 0;
+        (0..=self.len)
+            .map(|row| match self.bwt.access(row, &mut ranks) {
+                0 => '$',
+                s => self.alphabet[s as usize - 1],
+            })
+            .collect()
+    }
+
+    /// Characters in the indexed text.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate as u32
+    }
+
+    pub fn get_metrics(&self) -> FmIndexMetrics {
+        self.metrics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn naive_locate(text: &[char], pattern: &[char]) -> Vec<u32> {
+        (0..=text.len().saturating_sub(pattern.len()))
+            .filter(|&i| text[i..].starts_with(pattern))
+            .map(|i| i as u32)
+            .collect()
+    }
+
+    #[test]
+    fn test_bwt_and_search() {
+        let mut index = FmIndex::new("abracadabra");
+        assert_eq!(index.bwt(), "ard$rcaaaabb");
+        assert_eq!(index.count("abra"), 2);
+        assert_eq!(index.locate("abra"), [0, 7]);
+        assert_eq!(index.count("zz"), 0);
+        assert_eq!(index.count(""), 12);
+        assert!(index.locate("cad").len() == 1);
+    }
+
+    #[test]
+    fn test_locate_matches_naive() {
+        let text = "the quick brown fox jumps over the lazy dog; the end. ".repeat(20);
+        let chars: Vec<char> = text.chars().collect();
+        for rate in [1, 5, 32] {
+            let mut index = FmIndex::with_sample_rate(&text, rate);
+            for pattern in ["the", "o", "e t", "dog; the", "fox jumps over", "cat", "."] {
+                let p: Vec<char> = pattern.chars().collect();
+                let expected = naive_locate(&chars, &p);
+                assert_eq!(index.count(pattern), expected.len(), "{}", pattern);
+                assert_eq!(index.locate(pattern), expected, "{}", pattern);
+            }
+            if rate == 1 {
+                assert_eq!(index.get_metrics().lf_steps, 0);
+            }
+        }
+    }
+}
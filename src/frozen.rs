@@ -0,0 +1,95 @@
+use crate::kv_store::KvStore;
+use crate::memory::MemoryReport;
+use crate::registry::SharedStore;
+use std::cell::Ref;
+use wasm_bindgen::prelude::*;
+
+/// Read-only view of a [`DynamicStore`](crate::kv_store::DynamicStore).
+///
+/// The view shares the structure rather than copying it, so it always shows
+/// the current contents, but it has no way to change them. Hand it to code
+/// that should only look, such as a third-party visualization.
+///
+/// Lookups still count towards the structure's search metrics, exactly as
+/// they would through the store itself.
+///
+/// # Example
+/// ```javascript
+/// const view = store.freeze();
+/// renderChart(view); // can call view.get(), view.len(), view.metric()...
+/// ```
+#[wasm_bindgen]
+pub struct FrozenView {
+    inner: SharedStore,
+}
+
+impl FrozenView {
+    pub(crate) fn new(inner: SharedStore) -> FrozenView {
+        FrozenView { inner }
+    }
+
+    pub fn store(&self) -> Ref<'_, dyn KvStore> {
+        Ref::map(self.inner.borrow(), |s| s.as_ref())
+    }
+}
+
+#[wasm_bindgen]
+impl FrozenView {
+    pub fn kind(&self) -> String {
+        self.store().kind().to_string()
+    }
+
+    pub fn get(&self, key: &str) -> Option<u32> {
+        self.inner.borrow_mut().kv_get(key)
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.get(key).is_some()
+    }
+
+    pub fn len(&self) -> usize {
+        self.store().kv_len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Value of one of the underlying structure's metrics, by field name.
+    pub fn metric(&self, name: &str) -> Option<f64> {
+        self.store()
+            .metrics_snapshot()
+            .into_iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, v)| v)
+    }
+
+    pub fn memory_report(&self) -> MemoryReport {
+        self.store().memory_report()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::kv_store::DynamicStore;
+
+    #[test]
+    fn test_view_tracks_the_store() {
+        let mut store = DynamicStore::try_new("trie", 0).unwrap();
+        store.insert("apple".to_string(), 1);
+        let view = store.freeze();
+        assert_eq!(view.kind(), "trie");
+        assert_eq!(view.get("apple"), Some(1));
+
+        store.insert("apricot".to_string(), 2);
+        assert!(store.delete("apple"));
+        assert_eq!(view.len(), 1);
+        assert!(!view.contains("apple"));
+        assert_eq!(view.get("apricot"), Some(2));
+        assert_eq!(view.metric("total_insertions"), Some(2.0));
+
+        // The view keeps the structure alive on its own
+        drop(store);
+        assert_eq!(view.len(), 1);
+    }
+}
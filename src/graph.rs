@@ -0,0 +1,779 @@
+use wasm_bindgen::prelude::*;
+
+/// One directed edge. `weight` defaults to 1.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Edge {
+    from: usize,
+    to: usize,
+    weight: f64,
+}
+
+/// Directed graph over nodes numbered `0..node_count`, stored as an edge
+/// list plus per-node outgoing edge indices.
+///
+/// Algorithms that are about undirected structure (articulation points,
+/// bridges) read each edge in both directions.
+///
+/// # Example
+/// ```javascript
+/// const g = new Graph(4);
+/// g.add_edge(0, 1); g.add_edge(1, 0); g.add_edge(1, 2);
+/// const comp = g.strongly_connected_components(); // Uint32Array [1, 1, 0, 2]
+/// ```
+#[wasm_bindgen]
+#[derive(Clone, Debug, Default)]
+pub struct Graph {
+    edges: Vec<Edge>,
+    /// node -> indices into `edges` leaving it
+    outgoing: Vec<Vec<usize>>,
+    metrics: GraphMetrics,
+}
+
+/// Work done by graph traversals since the graph was created
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct GraphMetrics {
+    /// Algorithm runs (one per call)
+    pub traversals: u32,
+    pub nodes_visited: u32,
+    pub edges_examined: u32,
+}
+
+const UNVISITED: usize = usize::MAX;
+const UNMATCHED: usize = usize::MAX;
+
+impl Graph {
+    fn ensure_node(&mut self, node: usize) {
+        if node >= self.outgoing.len() {
+            self.outgoing.resize(node + 1, Vec::new());
+        }
+    }
+
+    /// node -> (neighbour, edge index), every edge listed at both ends.
+    fn undirected(&self) -> Vec<Vec<(usize, usize)>> {
+        let mut adjacency = vec![Vec::new(); self.outgoing.len()];
+        for (i, edge) in self.edges.iter().enumerate() {
+            adjacency[edge.from].push((edge.to, i));
+            if edge.from != edge.to {
+                adjacency[edge.to].push((edge.from, i));
+            }
+        }
+        adjacency
+    }
+
+    /// Tarjan's low-link DFS on the undirected view, iteratively so deep
+    /// graphs can't overflow the stack. Returns which nodes are
+    /// articulation points and every bridge as (parent, child).
+    fn cut_structure(&mut self) -> (Vec<bool>, Vec<(u32, u32)>) {
+        let adjacency = self.undirected();
+        let n = adjacency.len();
+        let mut discovered = vec![UNVISITED; n];
+        let mut low = vec![0; n];
+        let mut articulation = vec![false; n];
+        let mut bridges = Vec::new();
+        let mut counter = 0;
+        self.metrics.traversals += 1;
+
+        for root in 0..n {
+            if discovered[root] != UNVISITED {
+                continue;
+            }
+            discovered[root] = counter;
+            low[root] = counter;
+            counter += 1;
+            self.metrics.nodes_visited += 1;
+            let mut root_children = 0;
+            // (node, edge it was reached by, next adjacency index)
+            let mut frames = vec![(root, UNVISITED, 0)];
+            while let Some(frame) = frames.last_mut() {
+                let (v, via, i) = *frame;
+                if let Some(&(w, edge)) = adjacency[v].get(i) {
+                    frame.2 += 1;
+                    self.metrics.edges_examined += 1;
+                    if edge == via {
+                        continue;
+                    }
+                    if discovered[w] == UNVISITED {
+                        discovered[w] = counter;
+                        low[w] = counter;
+                        counter += 1;
+                        self.metrics.nodes_visited += 1;
+                        if v == root {
+                            root_children += 1;
+                        }
+                        frames.push((w, edge, 0));
+                    } else {
+                        low[v] = low[v].min(discovered[w]);
+                    }
+                    continue;
+                }
+                frames.pop();
+                if let Some(&(parent, _, _)) = frames.last() {
+                    low[parent] = low[parent].min(low[v]);
+                    if low[v] > discovered[parent] {
+                        bridges.push((parent as u32, v as u32));
+                    }
+                    if parent != root && low[v] >= discovered[parent] {
+                        articulation[parent] = true;
+                    }
+                }
+            }
+            articulation[root] = root_children > 1;
+        }
+        (articulation, bridges)
+    }
+}
+
+#[wasm_bindgen]
+impl Graph {
+    /// A graph with `node_count` nodes and no edges. Adding an edge to a
+    /// node past the end grows the graph.
+    #[wasm_bindgen(constructor)]
+    pub fn new(node_count: u32) -> Graph {
+        Graph {
+            outgoing: vec![Vec::new(); node_count as usize],
+            ..Graph::default()
+        }
+    }
+
+    /// Append a node, returning its number.
+    pub fn add_node(&mut self) -> u32 {
+        self.outgoing.push(Vec::new());
+        self.outgoing.len() as u32 - 1
+    }
+
+    /// Add an edge `from -> to` of weight 1.
+    pub fn add_edge(&mut self, from: u32, to: u32) {
+        self.add_weighted_edge(from, to, 1.0);
+    }
+
+    pub fn add_weighted_edge(&mut self, from: u32, to: u32, weight: f64) {
+        let (from, to) = (from as usize, to as usize);
+        self.ensure_node(from.max(to));
+        self.outgoing[from].push(self.edges.len());
+        self.edges.push(Edge { from, to, weight });
+    }
+
+    pub fn node_count(&self) -> u32 {
+        self.outgoing.len() as u32
+    }
+
+    pub fn edge_count(&self) -> u32 {
+        self.edges.len() as u32
+    }
+
+    /// Targets of the edges leaving `node`, in insertion order.
+    pub fn neighbors(&self, node: u32) -> Vec<u32> {
+        self.outgoing
+            .get(node as usize)
+            .map_or_else(Vec::new, |out| {
+                out.iter().map(|&e| self.edges[e].to as u32).collect()
+            })
+    }
+
+    /// Strongly connected component of every node (Tarjan's algorithm).
+    ///
+    /// Components are numbered in the order Tarjan completes them, which is
+    /// a reverse topological order of the condensation: edges between
+    /// components always go from a higher number to a lower one.
+    pub fn strongly_connected_components(&mut self) -> Vec<u32> {
+        let n = self.outgoing.len();
+        let mut index = vec![UNVISITED; n];
+        let mut low = vec![0; n];
+        let mut on_stack = vec![false; n];
+        let mut stack = Vec::new();
+        let mut component = vec![0u32; n];
+        let mut components = 0;
+        let mut counter = 0;
+        self.metrics.traversals += 1;
+
+        for root in 0..n {
+            if index[root] != UNVISITED {
+                continue;
+            }
+            // (node, next outgoing index)
+            let mut frames = vec![(root, 0)];
+            index[root] = counter;
+            low[root] = counter;
+            counter += 1;
+            stack.push(root);
+            on_stack[root] = true;
+            self.metrics.nodes_visited += 1;
+            while let Some(frame) = frames.last_mut() {
+                let (v, i) = *frame;
+                if let Some(&edge) = self.outgoing[v].get(i) {
+                    frame.1 += 1;
+                    self.metrics.edges_examined += 1;
+                    let w = self.edges[edge].to;
+                    if index[w] == UNVISITED {
+                        index[w] = counter;
+                        low[w] = counter;
+                        counter += 1;
+                        stack.push(w);
+                        on_stack[w] = true;
+                        self.metrics.nodes_visited += 1;
+                        frames.push((w, 0));
+                    } else if on_stack[w] {
+                        low[v] = low[v].min(index[w]);
+                    }
+                    continue;
+                }
+                frames.pop();
+                if low[v] == index[v] {
+                    while let Some(w) = stack.pop() {
+                        on_stack[w] = false;
+                        component[w] = components;
+                        if w == v {
+                            break;
+                        }
+                    }
+                    components += 1;
+                }
+                if let Some(&(parent, _)) = frames.last() {
+                    low[parent] = low[parent].min(low[v]);
+                }
+            }
+        }
+        component
+    }
+
+    /// Number of strongly connected components.
+    pub fn scc_count(&mut self) -> u32 {
+        self.strongly_connected_components()
+            .into_iter()
+            .max()
+            .map_or(0, |c| c + 1)
+    }
+
+    /// Nodes whose removal disconnects their part of the graph, with edges
+    /// taken as undirected, in ascending order.
+    pub fn articulation_points(&mut self) -> Vec<u32> {
+        let (articulation, _) = self.cut_structure();
+        (0..articulation.len() as u32)
+            .filter(|&v| articulation[v as usize])
+            .collect()
+    }
+
+    /// Edges whose removal disconnects their part of the graph, with edges
+    /// taken as undirected, flattened as `[a0, b0, a1, b1, ...]`.
+    pub fn bridges(&mut self) -> Vec<u32> {
+        let (_, bridges) = self.cut_structure();
+        bridges.into_iter().flat_map(|(a, b)| [a, b]).collect()
+    }
+
+    /// Maximum flow from `source` to `sink` with edge weights as
+    /// capacities (Dinic's algorithm). Throws if either node is missing or
+    /// they are the same node.
+    pub fn max_flow(&mut self, source: u32, sink: u32) -> Result<MaxFlow, JsValue> {
+        self.try_max_flow(source, sink)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    pub fn get_metrics(&self) -> GraphMetrics {
+        self.metrics
+    }
+
+    pub fn reset_metrics(&mut self) {
+        self.metrics = GraphMetrics::default();
+    }
+}
+
+/// Flows below this are treated as zero.
+const FLOW_EPSILON: f64 = 1e-9;
+
+/// Result of `Graph::max_flow`.
+///
+/// Besides the flow value, holds the flow on every edge (in the order edges
+/// were added) and a decomposition of that flow into source-to-sink paths,
+/// each carrying part of it.
+#[wasm_bindgen]
+#[derive(Clone, Debug, Default)]
+pub struct MaxFlow {
+    pub value: f64,
+    /// Level graphs built, one per Dinic phase
+    pub level_graphs: u32,
+    /// Augmenting paths found in the level graphs
+    pub augmenting_paths: u32,
+    edge_flows: Vec<f64>,
+    paths: Vec<(Vec<u32>, f64)>,
+}
+
+impl MaxFlow {
+    /// The decomposition as (nodes from source to sink, flow carried).
+    pub fn paths_ref(&self) -> &[(Vec<u32>, f64)] {
+        &self.paths
+    }
+}
+
+#[wasm_bindgen]
+impl MaxFlow {
+    /// Flow on each edge, indexed like the edges were added.
+    pub fn edge_flows(&self) -> Vec<f64> {
+        self.edge_flows.clone()
+    }
+
+    pub fn path_count(&self) -> usize {
+        self.paths.len()
+    }
+
+    /// Nodes of decomposition path `i`, source first.
+    pub fn path(&self, i: usize) -> Vec<u32> {
+        self.paths.get(i).map_or_else(Vec::new, |(p, _)| p.clone())
+    }
+
+    /// Flow carried by decomposition path `i`.
+    pub fn path_flow(&self, i: usize) -> f64 {
+        self.paths.get(i).map_or(0.0, |(_, f)| *f)
+    }
+}
+
+/// Residual network of one `max_flow` run. Edge `i` of the graph is arc
+/// `2i`, and its reverse is arc `2i + 1`.
+struct Dinic {
+    /// (target, residual capacity)
+    arcs: Vec<(usize, f64)>,
+    /// node -> arc indices leaving it
+    adjacency: Vec<Vec<usize>>,
+    level: Vec<usize>,
+    /// Next adjacency index each node's DFS resumes from
+    next: Vec<usize>,
+}
+
+impl Dinic {
+    /// Label nodes by BFS distance from `source` over arcs with capacity
+    /// left. Returns whether `sink` is reachable.
+    fn build_levels(&mut self, source: usize, sink: usize, metrics: &mut GraphMetrics) -> bool {
+        self.level.fill(UNVISITED);
+        self.level[source] = 0;
+        let mut queue = std::collections::VecDeque::from([source]);
+        while let Some(u) = queue.pop_front() {
+            metrics.nodes_visited += 1;
+            for &arc in &self.adjacency[u] {
+                metrics.edges_examined += 1;
+                let (v, capacity) = self.arcs[arc];
+                if capacity > FLOW_EPSILON && self.level[v] == UNVISITED {
+                    self.level[v] = self.level[u] + 1;
+                    queue.push_back(v);
+                }
+            }
+        }
+        self.level[sink] != UNVISITED
+    }
+
+    /// Push up to `limit` from `u` towards `sink` along level-increasing
+    /// arcs, returning the amount pushed.
+    fn push(&mut self, u: usize, sink: usize, limit: f64, metrics: &mut GraphMetrics) -> f64 {
+        if u == sink {
+            return limit;
+        }
+        while let Some(&arc) = self.adjacency[u].get(self.next[u]) {
+            metrics.edges_examined += 1;
+            let (v, capacity) = self.arcs[arc];
+            if capacity > FLOW_EPSILON && self.level[v] == self.level[u] + 1 {
+                let pushed = self.push(v, sink, limit.min(capacity), metrics);
+                if pushed > FLOW_EPSILON {
+                    self.arcs[arc].1 -= pushed;
+                    self.arcs[arc ^ 1].1 += pushed;
+                    return pushed;
+                }
+            }
+            self.next[u] += 1;
+        }
+        0.0
+    }
+}
+
+impl Graph {
+    /// Maximum flow from `source` to `sink` (Dinic's algorithm), with edge
+    /// weights as capacities. Negative and NaN capacities count as 0.
+    pub fn try_max_flow(&mut self, source: u32, sink: u32) -> Result<MaxFlow, String> {
+        let n = self.outgoing.len();
+        let (source, sink) = (source as usize, sink as usize);
+        if source >= n || sink >= n {
+            return Err(format!("node out of range: graph has {} nodes", n));
+        }
+        if source == sink {
+            return Err("source and sink must differ".to_string());
+        }
+        self.metrics.traversals += 1;
+
+        let capacities: Vec<f64> = self
+            .edges
+            .iter()
+            .map(|e| if e.weight > 0.0 { e.weight } else { 0.0 })
+            .collect();
+        let mut dinic = Dinic {
+            arcs: Vec::with_capacity(2 * self.edges.len()),
+            adjacency: vec![Vec::new(); n],
+            level: vec![UNVISITED; n],
+            next: vec![0; n],
+        };
+        for (edge, &capacity) in self.edges.iter().zip(&capacities) {
+            dinic.adjacency[edge.from].push(dinic.arcs.len());
+            dinic.arcs.push((edge.to, capacity));
+            dinic.adjacency[edge.to].push(dinic.arcs.len());
+            dinic.arcs.push((edge.from, 0.0));
+        }
+
+        let mut result = MaxFlow::default();
+        while dinic.build_levels(source, sink, &mut self.metrics) {
+            result.level_graphs += 1;
+            dinic.next.fill(0);
+            loop {
+                let pushed = dinic.push(source, sink, f64::INFINITY, &mut self.metrics);
+                if pushed <= FLOW_EPSILON {
+                    break;
+                }
+                result.value += pushed;
+                result.augmenting_paths += 1;
+            }
+        }
+
+        result.edge_flows = capacities
+            .iter()
+            .enumerate()
+            .map(|(i, &capacity)| (capacity - dinic.arcs[2 * i].1).max(0.0))
+            .collect();
+        result.paths = self.decompose(&result.edge_flows, source, sink);
+        Ok(result)
+    }
+
+    /// Split an edge flow into source-to-sink paths, cancelling any flow
+    /// cycles met along the way.
+    fn decompose(&self, flows: &[f64], source: usize, sink: usize) -> Vec<(Vec<u32>, f64)> {
+        let mut remaining = flows.to_vec();
+        let mut paths = Vec::new();
+        let next_edge = |node: usize, remaining: &[f64]| {
+            self.outgoing[node]
+                .iter()
+                .copied()
+                .find(|&e| remaining[e] > FLOW_EPSILON)
+        };
+        'walks: while next_edge(source, &remaining).is_some() {
+            // Walk forward along flow until reaching the sink or a node
+            // already on the walk
+            let mut nodes = vec![source];
+            let mut edges: Vec<usize> = Vec::new();
+            let mut node = source;
+            while node != sink {
+                let Some(edge) = next_edge(node, &remaining) else {
+                    // Only rounding residue is left here; drop it
+                    for &e in &edges {
+                        remaining[e] = 0.0;
+                    }
+                    continue 'walks;
+                };
+                node = self.edges[edge].to;
+                if let Some(start) = nodes.iter().position(|&n| n == node) {
+                    let cycle = &edges[start..];
+                    let amount = cycle
+                        .iter()
+                        .chain([&edge])
+                        .map(|&e| remaining[e])
+                        .fold(f64::INFINITY, f64::min);
+                    for &e in cycle.iter().chain([&edge]) {
+                        remaining[e] -= amount;
+                    }
+                    nodes.truncate(start + 1);
+                    edges.truncate(start);
+                    continue;
+                }
+                nodes.push(node);
+                edges.push(edge);
+            }
+            let amount = edges
+                .iter()
+                .map(|&e| remaining[e])
+                .fold(f64::INFINITY, f64::min);
+            for &e in &edges {
+                remaining[e] -= amount;
+            }
+            paths.push((nodes.into_iter().map(|n| n as u32).collect(), amount));
+        }
+        paths
+    }
+}
+
+/// Bipartite graph for maximum matching (Hopcroft–Karp).
+///
+/// Left and right nodes are numbered separately, each from 0. Each phase
+/// runs a BFS from every unmatched left node to layer the graph by
+/// alternating-path length, then DFSes augment along vertex-disjoint
+/// shortest paths at once; O(√V) phases suffice, for O(E√V) overall.
+///
+/// # Example
+/// ```javascript
+/// const b = new BipartiteGraph();
+/// const [alice, bob] = [b.add_left(), b.add_left()];
+/// const [day, night] = [b.add_right(), b.add_right()];
+/// b.add_edge(alice, day); b.add_edge(bob, day); b.add_edge(bob, night);
+/// b.max_matching(); // Uint32Array [0, 0, 1, 1]: alice-day, bob-night
+/// ```
+#[wasm_bindgen]
+#[derive(Clone, Debug, Default)]
+pub struct BipartiteGraph {
+    /// left node -> right nodes
+    adjacency: Vec<Vec<usize>>,
+    right_count: usize,
+    metrics: MatchingMetrics,
+}
+
+/// Work done by `BipartiteGraph::max_matching`
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct MatchingMetrics {
+    /// BFS layering + DFS augmentation rounds
+    pub phases: u32,
+    pub augmenting_paths: u32,
+    pub edges_examined: u32,
+}
+
+/// Search state of one `max_matching` run.
+struct HopcroftKarp<'a> {
+    adjacency: &'a [Vec<usize>],
+    left_match: Vec<usize>,
+    right_match: Vec<usize>,
+    layer: Vec<usize>,
+    /// Next adjacency index each left node's DFS resumes from
+    next: Vec<usize>,
+    metrics: &'a mut MatchingMetrics,
+}
+
+impl HopcroftKarp<'_> {
+    /// Layer left nodes by alternating distance from the unmatched ones.
+    /// Returns whether any augmenting path exists.
+    fn layer(&mut self) -> bool {
+        let mut queue = std::collections::VecDeque::new();
+        for (u, &m) in self.left_match.iter().enumerate() {
+            if m == UNMATCHED {
+                self.layer[u] = 0;
+                queue.push_back(u);
+            } else {
+                self.layer[u] = UNVISITED;
+            }
+        }
+        let mut found = false;
+        while let Some(u) = queue.pop_front() {
+            for &v in &self.adjacency[u] {
+                self.metrics.edges_examined += 1;
+                match self.right_match[v] {
+                    UNMATCHED => found = true,
+                    w if self.layer[w] == UNVISITED => {
+                        self.layer[w] = self.layer[u] + 1;
+                        queue.push_back(w);
+                    }
+                    _ => {}
+                }
+            }
+        }
+        found
+    }
+
+    /// Augment along a shortest alternating path from left node `u`.
+    fn augment(&mut self, u: usize) -> bool {
+        while let Some(&v) = self.adjacency[u].get(self.next[u]) {
+            self.next[u] += 1;
+            self.metrics.edges_examined += 1;
+            let w = self.right_match[v];
+            if w == UNMATCHED || (self.layer[w] == self.layer[u] + 1 && self.augment(w)) {
+                self.left_match[u] = v;
+                self.right_match[v] = u;
+                return true;
+            }
+        }
+        // Dead end: keep later searches out
+        self.layer[u] = UNVISITED;
+        false
+    }
+}
+
+#[wasm_bindgen]
+impl BipartiteGraph {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> BipartiteGraph {
+        BipartiteGraph::default()
+    }
+
+    /// Add a left node, returning its number.
+    pub fn add_left(&mut self) -> u32 {
+        self.adjacency.push(Vec::new());
+        self.adjacency.len() as u32 - 1
+    }
+
+    /// Add a right node, returning its number.
+    pub fn add_right(&mut self) -> u32 {
+        self.right_count += 1;
+        self.right_count as u32 - 1
+    }
+
+    /// Connect `left` and `right`, adding nodes as needed.
+    pub fn add_edge(&mut self, left: u32, right: u32) {
+        let (left, right) = (left as usize, right as usize);
+        if left >= self.adjacency.len() {
+            self.adjacency.resize(left + 1, Vec::new());
+        }
+        self.right_count = self.right_count.max(right + 1);
+        self.adjacency[left].push(right);
+    }
+
+    pub fn left_count(&self) -> u32 {
+        self.adjacency.len() as u32
+    }
+
+    pub fn right_count(&self) -> u32 {
+        self.right_count as u32
+    }
+
+    /// A maximum matching, flattened as `[left0, right0, left1, right1, ...]`
+    /// in ascending left order.
+    pub fn max_matching(&mut self) -> Vec<u32> {
+        let n = self.adjacency.len();
+        let mut search = HopcroftKarp {
+            adjacency: &self.adjacency,
+            left_match: vec![UNMATCHED; n],
+            right_match: vec![UNMATCHED; self.right_count],
+            layer: vec![UNVISITED; n],
+            next: vec![0; n],
+            metrics: &mut self.metrics,
+        };
+        while search.layer() {
+            search.metrics.phases += 1;
+            search.next.fill(0);
+            for u in 0..n {
+                if search.left_match[u] == UNMATCHED && search.augment(u) {
+                    search.metrics.augmenting_paths += 1;
+                }
+            }
+        }
+        search
+            .left_match
+            .iter()
+            .enumerate()
+            .filter(|&(_, &v)| v != UNMATCHED)
+            .flat_map(|(u, &v)| [u as u32, v as u32])
+            .collect()
+    }
+
+    pub fn get_metrics(&self) -> MatchingMetrics {
+        self.metrics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strongly_connected_components() {
+        let mut g = Graph::new(6);
+        // {0, 1, 2} cycle -> {3, 4} cycle -> 5
+        for (a, b) in [(0, 1), (1, 2), (2, 0), (2, 3), (3, 4), (4, 3), (4, 5)] {
+            g.add_edge(a, b);
+        }
+        let comp = g.strongly_connected_components();
+        assert_eq!(comp[0], comp[1]);
+        assert_eq!(comp[1], comp[2]);
+        assert_eq!(comp[3], comp[4]);
+        assert_ne!(comp[0], comp[3]);
+        // Reverse topological numbering
+        assert!(comp[5] < comp[3] && comp[3] < comp[0]);
+        assert_eq!(g.scc_count(), 3);
+        let metrics = g.get_metrics();
+        assert_eq!(metrics.traversals, 2);
+        assert_eq!(metrics.edges_examined, 14);
+    }
+
+    #[test]
+    fn test_articulation_points_and_bridges() {
+        // Triangle 0-1-2, bridge 2-3, triangle 3-4-5, pendant 6 off 5
+        let mut g = Graph::new(7);
+        for (a, b) in [
+            (0, 1),
+            (1, 2),
+            (2, 0),
+            (2, 3),
+            (3, 4),
+            (4, 5),
+            (5, 3),
+            (5, 6),
+        ] {
+            g.add_edge(a, b);
+        }
+        assert_eq!(g.articulation_points(), [2, 3, 5]);
+        let mut bridges: Vec<(u32, u32)> = g
+            .bridges()
+            .chunks(2)
+            .map(|p| (p[0].min(p[1]), p[0].max(p[1])))
+            .collect();
+        bridges.sort();
+        assert_eq!(bridges, [(2, 3), (5, 6)]);
+
+        // A doubled edge is not a bridge
+        let mut g = Graph::new(2);
+        g.add_edge(0, 1);
+        g.add_edge(1, 0);
+        assert!(g.bridges().is_empty());
+        assert!(g.articulation_points().is_empty());
+    }
+
+    #[test]
+    fn test_hopcroft_karp_max_matching() {
+        // Greedy left-to-right would match 0-0 and strand 1; the optimum
+        // is perfect
+        let mut b = BipartiteGraph::new();
+        for (l, r) in [(0, 0), (0, 1), (1, 0), (2, 1), (2, 2), (3, 2), (3, 3)] {
+            b.add_edge(l, r);
+        }
+        let matching = b.max_matching();
+        assert_eq!(matching.len(), 8);
+        let mut rights: Vec<u32> = matching.chunks(2).map(|p| p[1]).collect();
+        rights.sort();
+        assert_eq!(rights, [0, 1, 2, 3]);
+        for pair in matching.chunks(2) {
+            assert!(b.adjacency[pair[0] as usize].contains(&(pair[1] as usize)));
+        }
+        let metrics = b.get_metrics();
+        assert_eq!(metrics.augmenting_paths, 4);
+        assert!(metrics.phases >= 1);
+
+        b.add_left();
+        assert_eq!(b.max_matching().len(), 8);
+    }
+
+    #[test]
+    fn test_dinic_max_flow_and_decomposition() {
+        // CLRS 26.1 network, max flow 23
+        let mut g = Graph::new(6);
+        for (a, b, c) in [
+            (0, 1, 16.0),
+            (0, 2, 13.0),
+            (1, 3, 12.0),
+            (2, 1, 4.0),
+            (2, 4, 14.0),
+            (3, 2, 9.0),
+            (3, 5, 20.0),
+            (4, 3, 7.0),
+            (4, 5, 4.0),
+        ] {
+            g.add_weighted_edge(a, b, c);
+        }
+        let flow = g.try_max_flow(0, 5).unwrap();
+        assert_eq!(flow.value, 23.0);
+        assert!(flow.level_graphs >= 2);
+        assert!(flow.augmenting_paths >= 3);
+
+        let flows = flow.edge_flows();
+        for (e, edge) in g.edges.iter().enumerate() {
+            assert!(flows[e] <= edge.weight);
+        }
+        let carried: f64 = flow.paths_ref().iter().map(|(_, f)| f).sum();
+        assert!((carried - 23.0).abs() < 1e-9);
+        for (path, _) in flow.paths_ref() {
+            assert_eq!((path[0], *path.last().unwrap()), (0, 5));
+        }
+
+        assert!(g.try_max_flow(0, 0).is_err());
+        assert!(g.try_max_flow(0, 9).is_err());
+        assert_eq!(g.try_max_flow(5, 0).unwrap().value, 0.0);
+    }
+}
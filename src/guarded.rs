@@ -0,0 +1,299 @@
+use crate::bloom::BloomFilter;
+use crate::kv_store::{new_store, KvStore};
+use wasm_bindgen::prelude::*;
+
+/// How each structure counts the key comparisons (or probes) its lookups
+/// make. Structures missing here don't count them, so their miss cost is
+/// modeled instead.
+const LOOKUP_COST_METRICS: [(&str, &str); 6] = [
+    ("hashmap", "chain_comparisons"),
+    ("hashmap_linked", "chain_comparisons"),
+    ("hashmap_sorted", "chain_comparisons"),
+    ("open_addressing", "total_probes"),
+    ("bst", "total_comparisons"),
+    ("skiplist", "search_comparisons"),
+];
+
+/// The key comparisons `store` has counted for its lookups so far, or
+/// `None` for structures that don't count them.
+pub(crate) fn comparison_count(store: &dyn KvStore) -> Option<f64> {
+    let (_, metric) = LOOKUP_COST_METRICS
+        .iter()
+        .find(|(kind, _)| *kind == store.kind())?;
+    store
+        .metrics_snapshot()
+        .into_iter()
+        .find(|(name, _)| name == metric)
+        .map(|(_, value)| value)
+}
+
+/// What the filter in front of a [`GuardedMap`] did for its lookups.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct GuardedMapMetrics {
+    pub lookups: u32,
+    /// Lookups the filter answered alone: the key is certainly absent
+    pub filter_rejections: u32,
+    /// Lookups the filter let through that still missed
+    pub false_positives: u32,
+    pub hits: u32,
+    /// Bits the filter checked, across all lookups
+    pub filter_probes: u64,
+    /// Comparisons the structure made for lookups that reached it
+    pub store_comparisons: f64,
+    /// Comparisons the rejected lookups would have cost the structure
+    pub comparisons_saved: f64,
+}
+
+#[wasm_bindgen]
+impl GuardedMapMetrics {
+    /// Comparisons saved less the filter's own bit probes, counting a probe
+    /// as one comparison. Negative when the filter costs more than it saves.
+    pub fn net_comparisons_saved(&self) -> f64 {
+        self.comparisons_saved - self.filter_probes as f64
+    }
+
+    /// Fraction of absent-key lookups the filter failed to reject.
+    pub fn false_positive_rate(&self) -> f64 {
+        let misses = self.filter_rejections + self.false_positives;
+        if misses == 0 {
+            0.0
+        } else {
+            self.false_positives as f64 / misses as f64
+        }
+    }
+}
+
+/// Any structure fronted by a [`BloomFilter`], so lookups for keys that
+/// were never inserted are answered from a few bits without touching it.
+///
+/// The filter is sized for `expected_items` keys at `false_positive_rate`.
+/// Bloom filters can't forget, so a deleted key's bits stay set until
+/// `rebuild`, and inserting well past `expected_items` raises the false
+/// positive rate.
+///
+/// Comparisons saved per rejection are the structure's own average for
+/// lookups that missed, read from its metrics (or for all lookups until a
+/// miss has gone through). The red-black tree, trie and two-choice table
+/// don't count comparisons, so their miss cost is modeled: the tree's
+/// height, the key's length, or the two candidate buckets' average load.
+///
+/// # Example
+/// ```javascript
+/// const map = new GuardedMap("rbtree", 10_000, 0.01);
+/// for (const user of users) map.insert(user.name, user.id);
+/// map.get("nobody");      // answered by the filter
+/// const m = map.metrics();
+/// console.log(m.filter_rejections, m.false_positive_rate(), m.net_comparisons_saved());
+/// ```
+#[wasm_bindgen]
+pub struct GuardedMap {
+    store: Box<dyn KvStore>,
+    filter: BloomFilter,
+    false_positive_target: f64,
+    /// Comparisons made by lookups that reached the store, and how many
+    /// of those missed, to estimate what a rejected lookup saves
+    miss_comparisons: f64,
+    measured_misses: u32,
+    metrics: GuardedMapMetrics,
+}
+
+impl GuardedMap {
+    pub fn try_wrap(
+        store: Box<dyn KvStore>,
+        expected_items: usize,
+        false_positive_rate: f64,
+    ) -> Result<GuardedMap, String> {
+        let mut map = GuardedMap {
+            store,
+            filter: BloomFilter::try_new(0, false_positive_rate)?,
+            false_positive_target: false_positive_rate,
+            miss_comparisons: 0.0,
+            measured_misses: 0,
+            metrics: GuardedMapMetrics::default(),
+        };
+        map.refill(expected_items);
+        Ok(map)
+    }
+
+    pub fn try_new(
+        kind: &str,
+        expected_items: usize,
+        false_positive_rate: f64,
+    ) -> Result<GuardedMap, String> {
+        let store = new_store(kind, expected_items)
+            .ok_or_else(|| format!("unknown structure '{}'", kind))?;
+        Self::try_wrap(store, expected_items, false_positive_rate)
+    }
+
+    pub fn inner(&self) -> &dyn KvStore {
+        self.store.as_ref()
+    }
+
+    /// Resize the filter for `expected_items` and re-add every key.
+    fn refill(&mut self, expected_items: usize) {
+        let entries = self.store.kv_entries();
+        self.filter = BloomFilter::try_new(
+            expected_items.max(entries.len()),
+            self.false_positive_target,
+        )
+        .expect("rate checked on construction");
+        for (key, _) in entries {
+            self.filter.insert(&key);
+        }
+    }
+
+    fn lookup_cost(&self) -> Option<f64> {
+        comparison_count(self.store.as_ref())
+    }
+
+    /// Comparisons a miss on `key` would cost the structure.
+    fn estimated_miss_cost(&self, key: &str) -> f64 {
+        if self.measured_misses > 0 {
+            return self.miss_comparisons / self.measured_misses as f64;
+        }
+        let reached = self.metrics.hits + self.metrics.false_positives;
+        if reached > 0 && self.metrics.store_comparisons > 0.0 {
+            return self.metrics.store_comparisons / reached as f64;
+        }
+        let len = self.store.kv_len() as f64;
+        match self.store.kind() {
+            "rbtree" => (len + 1.0).log2().ceil(),
+            "trie" => key.chars().count() as f64,
+            _ => 2.0 * len / self.store.capacity().unwrap_or(1).max(1) as f64,
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl GuardedMap {
+    /// An empty structure of `kind` behind a filter sized for
+    /// `expected_items` keys at `false_positive_rate` (e.g. 0.01).
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        kind: &str,
+        expected_items: u32,
+        false_positive_rate: f64,
+    ) -> Result<GuardedMap, JsValue> {
+        Self::try_new(kind, expected_items as usize, false_positive_rate)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    pub fn insert(&mut self, key: String, value: u32) {
+        self.filter.insert(&key);
+        self.store.kv_insert(key, value);
+    }
+
+    /// Look `key` up, skipping the structure when the filter rules it out.
+    pub fn get(&mut self, key: &str) -> Option<u32> {
+        self.metrics.lookups += 1;
+        let (present, probes) = self.filter.probe(key);
+        self.metrics.filter_probes += probes as u64;
+        if !present {
+            self.metrics.filter_rejections += 1;
+            self.metrics.comparisons_saved += self.estimated_miss_cost(key);
+            return None;
+        }
+        let before = self.lookup_cost();
+        let value = self.store.kv_get(key);
+        let cost = match (before, self.lookup_cost()) {
+            (Some(before), Some(after)) => after - before,
+            _ => 0.0,
+        };
+        self.metrics.store_comparisons += cost;
+        match value {
+            Some(_) => self.metrics.hits += 1,
+            None => {
+                self.metrics.false_positives += 1;
+                if before.is_some() {
+                    self.miss_comparisons += cost;
+                    self.measured_misses += 1;
+                }
+            }
+        }
+        value
+    }
+
+    /// Delete `key` from the structure. Its filter bits stay set, so later
+    /// lookups for it become false positives until `rebuild`.
+    pub fn delete(&mut self, key: &str) -> bool {
+        self.store.kv_delete(key)
+    }
+
+    /// Rebuild the filter from the current keys, sized for
+    /// `expected_items` (or the current size, if larger). Clears bits left
+    /// by deleted keys.
+    pub fn rebuild(&mut self, expected_items: u32) {
+        self.refill(expected_items as usize);
+    }
+
+    pub fn len(&self) -> usize {
+        self.store.kv_len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.store.kv_len() == 0
+    }
+
+    pub fn kind(&self) -> String {
+        self.store.kind().to_string()
+    }
+
+    /// The filter in front of the structure.
+    pub fn filter(&self) -> BloomFilter {
+        self.filter.clone()
+    }
+
+    pub fn metrics(&self) -> GuardedMapMetrics {
+        self.metrics
+    }
+
+    pub fn reset_metrics(&mut self) {
+        self.metrics = GuardedMapMetrics::default();
+        self.miss_comparisons = 0.0;
+        self.measured_misses = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter_short_circuits_misses() {
+        for kind in ["hashmap", "bst", "rbtree", "trie"] {
+            let mut map = GuardedMap::try_new(kind, 1000, 0.01).unwrap();
+            for i in 0..1000 {
+                map.insert(format!("user{}", i), i);
+            }
+            for i in 0..1000 {
+                assert_eq!(map.get(&format!("user{}", i)), Some(i), "{}", kind);
+                assert_eq!(map.get(&format!("user{}x", i)), None, "{}", kind);
+            }
+            let m = map.metrics();
+            assert_eq!(m.hits, 1000);
+            assert_eq!(m.filter_rejections + m.false_positives, 1000);
+            assert!(m.false_positive_rate() < 0.05, "{}: {:?}", kind, m);
+            if kind == "bst" {
+                // Near-sorted inserts leave the tree deep, so a miss costs far more
+                // than the filter probes
+                assert!(m.net_comparisons_saved() > 0.0, "{:?}", m);
+            }
+            assert!(map.filter().estimated_false_positive_rate() < 0.02);
+        }
+        assert!(GuardedMap::try_new("hashmap", 10, 1.0).is_err());
+        assert!(GuardedMap::try_new("btree", 10, 0.01).is_err());
+    }
+
+    #[test]
+    fn test_rebuild_forgets_deleted_keys() {
+        let mut map = GuardedMap::try_new("bst", 100, 0.01).unwrap();
+        map.insert("gone".to_string(), 1);
+        assert!(map.delete("gone"));
+        assert_eq!(map.get("gone"), None);
+        assert_eq!(map.metrics().false_positives, 1);
+        map.rebuild(100);
+        assert_eq!(map.get("gone"), None);
+        assert_eq!(map.metrics().filter_rejections, 1);
+    }
+}
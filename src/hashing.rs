@@ -0,0 +1,114 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use wasm_bindgen::prelude::*;
+
+/// Hash function a hash table applies to its keys, chosen when it is built.
+///
+/// `SipHash` (std's `DefaultHasher`) is the default and what every table
+/// should normally use. Three fast non-cryptographic hashes are there to
+/// compare collision behavior against it:
+///
+/// - `Fnv1a`: 64-bit FNV-1a, one xor and multiply per byte.
+/// - `FxHash`: rustc's hasher, one rotate, xor and multiply per 8-byte
+///   word. Its low bits mix poorly, which shows with power-of-two tables.
+/// - `Djb2`: Bernstein's `h * 33 + c`, a classic with weak avalanche.
+///
+/// `Constant` and `FirstChar` deliberately hash badly, so worst cases can
+/// be shown on demand rather than hunted for with lucky key sets:
+///
+/// - `Constant`: every key hashes to 0 — one bucket holds everything, and
+///   open addressing degrades into a single probe sequence.
+/// - `FirstChar`: the key's first character (0 for the empty key). Keys
+///   sharing a first letter collide, and under linear probing neighbouring
+///   letters run into each other's clusters.
+///
+/// # Example
+/// ```javascript
+/// const map = new HashMapBuilder().hash_function(HashFunction.Constant).build();
+/// for (let i = 0; i < 100; i++) map.insert(`k${i}`, i);
+/// map.get_metrics().max_chain_length; // 100
+/// ```
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HashFunction {
+    #[default]
+    SipHash,
+    Constant,
+    FirstChar,
+    Fnv1a,
+    FxHash,
+    Djb2,
+}
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+const FX_SEED: u64 = 0x517c_c1b7_2722_0a95;
+
+impl HashFunction {
+    pub fn hash(&self, key: &str) -> u64 {
+        match self {
+            HashFunction::SipHash => {
+                let mut hasher = DefaultHasher::new();
+                key.hash(&mut hasher);
+                hasher.finish()
+            }
+            HashFunction::Constant => 0,
+            HashFunction::FirstChar => key.chars().next().map_or(0, u64::from),
+            HashFunction::Fnv1a => key.bytes().fold(FNV_OFFSET_BASIS, |hash, byte| {
+                (hash ^ byte as u64).wrapping_mul(FNV_PRIME)
+            }),
+            HashFunction::FxHash => {
+                let fx = |hash: u64, word: u64| (hash.rotate_left(5) ^ word).wrapping_mul(FX_SEED);
+                let chunks = key.as_bytes().chunks_exact(8);
+                let tail = chunks.remainder();
+                let mut hash = chunks.fold(0, |hash, chunk| {
+                    fx(hash, u64::from_le_bytes(chunk.try_into().unwrap()))
+                });
+                if !tail.is_empty() {
+                    let mut word = [0u8; 8];
+                    word[..tail.len()].copy_from_slice(tail);
+                    hash = fx(hash, u64::from_le_bytes(word));
+                }
+                // The tail is zero-padded, so finish with the length to
+                // tell "a" from "a\0"
+                fx(hash, key.len() as u64)
+            }
+            HashFunction::Djb2 => key.bytes().fold(5381, |hash: u64, byte| {
+                hash.wrapping_mul(33).wrapping_add(byte as u64)
+            }),
+        }
+    }
+
+    /// True for the hashes that force collisions (`Constant`, `FirstChar`).
+    pub fn is_degenerate(&self) -> bool {
+        matches!(self, HashFunction::Constant | HashFunction::FirstChar)
+    }
+}
+
+/// Rehash `hash` under `seed` (murmur3's 64-bit finalizer), so structures
+/// that retry with new seeds hash each key once and remix it per attempt.
+pub(crate) fn mix_seed(hash: u64, seed: u64) -> u64 {
+    let mut h = hash.wrapping_add(seed);
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+    h ^ (h >> 33)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known_values() {
+        assert_eq!(HashFunction::Fnv1a.hash(""), FNV_OFFSET_BASIS);
+        assert_eq!(HashFunction::Fnv1a.hash("a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(HashFunction::Djb2.hash("a"), 5381 * 33 + 97);
+        assert_ne!(
+            HashFunction::FxHash.hash("a"),
+            HashFunction::FxHash.hash("a\0")
+        );
+        assert!(HashFunction::FirstChar.is_degenerate() && !HashFunction::FxHash.is_degenerate());
+    }
+}
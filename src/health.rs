@@ -0,0 +1,281 @@
+use wasm_bindgen::prelude::*;
+
+/// How much a [`Finding`] matters.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// Worth knowing; nothing to fix.
+    Info,
+    /// Operations are measurably slower than they should be.
+    Warning,
+    /// The structure has degraded towards its worst case.
+    Critical,
+}
+
+/// One observation from an `analyze()` call.
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Finding {
+    pub severity: Severity,
+    /// Stable identifier, e.g. `"high_load_factor"`, for programmatic checks.
+    pub code: String,
+    /// What was measured, with the numbers.
+    pub message: String,
+    /// What to do about it.
+    pub recommendation: String,
+}
+
+/// Result of a structure's `analyze()`: findings from its metrics, worst
+/// first, and a 0–100 score.
+///
+/// The score starts at 100 and loses 10 per warning and 30 per critical
+/// finding, so it reads at a glance without being a precise measure.
+///
+/// # Example
+/// ```javascript
+/// const report = map.analyze();
+/// if (!report.is_healthy()) {
+///     for (const f of report.findings()) console.log(f.code, f.message, f.recommendation);
+/// }
+/// ```
+#[wasm_bindgen]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct HealthReport {
+    findings: Vec<Finding>,
+}
+
+impl HealthReport {
+    pub(crate) fn add(
+        &mut self,
+        severity: Severity,
+        code: &str,
+        message: String,
+        recommendation: &str,
+    ) {
+        self.findings.push(Finding {
+            severity,
+            code: code.to_string(),
+            message,
+            recommendation: recommendation.to_string(),
+        });
+        self.findings.sort_by_key(|f| std::cmp::Reverse(f.severity));
+    }
+
+    pub fn findings_ref(&self) -> &[Finding] {
+        &self.findings
+    }
+
+    /// Whether a finding with `code` was reported.
+    pub fn has(&self, code: &str) -> bool {
+        self.findings.iter().any(|f| f.code == code)
+    }
+}
+
+#[wasm_bindgen]
+impl HealthReport {
+    pub fn findings(&self) -> Vec<Finding> {
+        self.findings.clone()
+    }
+
+    pub fn score(&self) -> u32 {
+        let penalty: u32 = self
+            .findings
+            .iter()
+            .map(|f| match f.severity {
+                Severity::Info => 0,
+                Severity::Warning => 10,
+                Severity::Critical => 30,
+            })
+            .sum();
+        100u32.saturating_sub(penalty)
+    }
+
+    /// True when nothing above `Info` was found.
+    pub fn is_healthy(&self) -> bool {
+        self.findings.iter().all(|f| f.severity == Severity::Info)
+    }
+}
+
+/// A remediation applied by auto-tuning, as listed by `tuning_log()`.
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct TuningEvent {
+    /// Writes (new keys and deletes) the structure had seen when it ran.
+    pub operation: u32,
+    /// `"resize"`, `"compact"` or `"rebuild"`.
+    pub action: String,
+    /// Code of the `analyze()` finding that triggered it.
+    pub reason: String,
+    /// What changed, e.g. `"256 -> 512 buckets"`.
+    pub detail: String,
+}
+
+/// Opt-in auto-tuning state embedded in a structure: whether it is on, a
+/// write counter, and the log of actions taken.
+///
+/// Structures check their cheap O(1) health thresholds after each write and,
+/// when one is crossed, apply the fix `analyze()` would recommend and
+/// `record` it.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct AutoTune {
+    pub(crate) enabled: bool,
+    operations: u32,
+    log: Vec<TuningEvent>,
+}
+
+impl AutoTune {
+    /// Count a write; returns whether thresholds should be checked.
+    pub(crate) fn tick(&mut self) -> bool {
+        self.operations += 1;
+        self.enabled
+    }
+
+    pub(crate) fn record(&mut self, action: &str, reason: &str, detail: String) {
+        self.log.push(TuningEvent {
+            operation: self.operations,
+            action: action.to_string(),
+            reason: reason.to_string(),
+            detail,
+        });
+    }
+
+    pub(crate) fn log(&self) -> &[TuningEvent] {
+        &self.log
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        BinarySearchTree, HashFunction, HashMap, HashMapBuilder, OpenAddressingHashTable, SkipList,
+    };
+
+    #[test]
+    fn test_hash_tables() {
+        let mut map = HashMap::new();
+        for i in 0..100 {
+            map.insert(format!("key{}", i), i);
+        }
+        assert!(map.analyze().is_healthy());
+        assert_eq!(map.analyze().score(), 100);
+
+        for i in 100..1_500 {
+            map.insert(format!("key{}", i), i);
+        }
+        let report = map.analyze();
+        assert!(report.has("high_load_factor"));
+        assert_eq!(report.findings_ref()[0].severity, Severity::Critical);
+        map.resize(2_048);
+        assert!(map.analyze().is_healthy());
+
+        let mut forced = HashMapBuilder::new()
+            .hash_function(HashFunction::Constant)
+            .try_build()
+            .unwrap();
+        for i in 0..50 {
+            forced.insert(format!("key{}", i), i);
+        }
+        let report = forced.analyze();
+        assert!(report.has("long_chains") && report.has("degenerate_hash"));
+        assert!(report.score() < 100);
+
+        let mut table = OpenAddressingHashTable::new(100);
+        for i in 0..95 {
+            table.try_insert(format!("key{}", i), i).unwrap();
+        }
+        for i in 0..40 {
+            table.delete(&format!("key{}", i));
+        }
+        let report = table.analyze();
+        assert!(report.has("tombstones"));
+        assert!(report.has("high_load_factor"));
+    }
+
+    #[test]
+    fn test_ordered_structures() {
+        let mut tree = BinarySearchTree::new();
+        for i in 0..200 {
+            tree.insert(format!("key{:03}", i), i);
+        }
+        let report = tree.analyze();
+        assert!(report.has("degenerate_tree"));
+        assert!(report.findings_ref()[0]
+            .recommendation
+            .contains("RedBlackTree"));
+
+        let mut balanced = BinarySearchTree::new();
+        for i in 0..255u32 {
+            // Insert in an order that fills the tree level by level
+            let key = (i + 1).reverse_bits() >> (32 - 8);
+            balanced.insert(format!("key{:03}", key), i);
+        }
+        assert!(balanced.analyze().is_healthy());
+
+        let mut list = SkipList::new();
+        for i in 0..500 {
+            list.insert(format!("key{}", i), i);
+        }
+        assert!(list.analyze().is_healthy());
+        let mut capped = crate::SkipListBuilder::new()
+            .max_level(2)
+            .seed(7)
+            .try_build()
+            .unwrap();
+        for i in 0..500 {
+            capped.insert(format!("key{}", i), i);
+        }
+        assert!(capped.analyze().has("max_level_too_low"));
+    }
+
+    #[test]
+    fn test_auto_tune() {
+        let mut map = HashMap::new();
+        map.set_auto_tune(true);
+        for i in 0..2_000 {
+            map.insert(format!("key{}", i), i);
+        }
+        assert!(!map.analyze().has("high_load_factor"));
+        let log = map.tuning_log();
+        assert!(!log.is_empty());
+        assert_eq!(log[0].action, "resize");
+        assert_eq!(log[0].reason, "high_load_factor");
+
+        let mut table = OpenAddressingHashTable::new(100);
+        table.set_auto_tune(true);
+        for i in 0..60 {
+            table.try_insert(format!("key{}", i), i).unwrap();
+        }
+        for i in 0..40 {
+            table.delete(&format!("key{}", i));
+        }
+        assert!(!table.analyze().has("tombstones"));
+        assert!(table.tuning_log().iter().any(|e| e.action == "compact"));
+        for i in 40..60 {
+            assert_eq!(table.get(&format!("key{}", i)), Some(i));
+        }
+
+        let mut tree = BinarySearchTree::new();
+        tree.set_auto_tune(true);
+        for i in 0..500 {
+            tree.insert(format!("key{:03}", i), i);
+        }
+        // Rebuilds happen at the critical threshold, so a warning may remain
+        let report = tree.analyze();
+        assert!(report
+            .findings_ref()
+            .iter()
+            .all(|f| f.severity != Severity::Critical));
+        assert!(tree.height() <= 3 * (500u32.ilog2() + 1));
+        assert!(tree.tuning_log().iter().any(|e| e.action == "rebuild"));
+        assert_eq!(tree.get("key123".to_string()), Some(123));
+
+        // Off by default: nothing changes behind the caller's back
+        let mut plain = BinarySearchTree::new();
+        for i in 0..100 {
+            plain.insert(format!("key{:03}", i), i);
+        }
+        assert!(plain.tuning_log().is_empty());
+        assert!(plain.analyze().has("degenerate_tree"));
+    }
+}
@@ -0,0 +1,286 @@
+use crate::rng::DefaultRng;
+use crate::scenarios::ZipfSampler;
+use std::collections::{BTreeSet, HashMap};
+use wasm_bindgen::prelude::*;
+
+/// Top-k frequent keys of a stream in fixed memory (Space-Saving,
+/// Metwally et al.).
+///
+/// Keeps at most `capacity` counters. A new key arriving when all are taken
+/// replaces the key with the smallest count and inherits that count as its
+/// possible overestimate. Every reported count is an upper bound, off by at
+/// most `error`, which never exceeds stream length / capacity; any key
+/// occurring more often than that is guaranteed to be tracked.
+///
+/// # Example
+/// ```javascript
+/// const hh = new HeavyHitters(100);
+/// for (const page of pageViews) hh.offer(page);
+/// for (const h of hh.top(10)) console.log(h.key, h.count, "±", h.error);
+/// ```
+#[wasm_bindgen]
+pub struct HeavyHitters {
+    capacity: usize,
+    /// key -> (count, error)
+    counters: HashMap<String, (u64, u64)>,
+    /// (count, key), to find the minimum counter
+    by_count: BTreeSet<(u64, String)>,
+    stream_length: u64,
+    evictions: u64,
+}
+
+/// One tracked key, as returned by `top`.
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct HeavyHitter {
+    pub key: String,
+    /// Estimated occurrences; never below the true count
+    pub count: u64,
+    /// Most the estimate can exceed the true count by
+    pub error: u64,
+    /// True when `count - error` beats every key outside the returned list,
+    /// so this key certainly belongs in the top k
+    pub guaranteed: bool,
+}
+
+/// How the sketch's top k compared with exact counting.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct HeavyHittersAccuracy {
+    pub k: u32,
+    pub stream_length: u32,
+    /// Fraction of reported keys that are in the true top k
+    pub precision: f64,
+    /// Fraction of the true top k that was reported
+    pub recall: f64,
+    /// Largest overestimate among reported keys
+    pub max_count_error: u64,
+    pub mean_count_error: f64,
+    /// Guaranteed error bound, stream length / capacity
+    pub error_bound: u64,
+}
+
+impl HeavyHitters {
+    /// Score the current top `k` against exact counts of the same stream.
+    pub fn accuracy_against(&self, exact: &HashMap<String, u64>, k: usize) -> HeavyHittersAccuracy {
+        let reported = self.top_hitters(k);
+        let mut truth: Vec<(&String, &u64)> = exact.iter().collect();
+        truth.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+        // Keys tied with the k-th count are all acceptable answers
+        let cutoff = truth.get(k.saturating_sub(1)).map_or(0, |(_, &c)| c);
+        let true_top: Vec<&String> = truth.iter().take(k).map(|(key, _)| *key).collect();
+
+        let correct = reported
+            .iter()
+            .filter(|h| exact.get(&h.key).is_some_and(|&c| c >= cutoff))
+            .count();
+        let recalled = true_top
+            .iter()
+            .filter(|&&key| reported.iter().any(|h| &h.key == key))
+            .count();
+        let errors: Vec<u64> = reported
+            .iter()
+            .map(|h| h.count - exact.get(&h.key).copied().unwrap_or(0))
+            .collect();
+        HeavyHittersAccuracy {
+            k: k as u32,
+            stream_length: self.stream_length as u32,
+            precision: if reported.is_empty() {
+                1.0
+            } else {
+                correct as f64 / reported.len() as f64
+            },
+            recall: if true_top.is_empty() {
+                1.0
+            } else {
+                recalled as f64 / true_top.len() as f64
+            },
+            max_count_error: errors.iter().copied().max().unwrap_or(0),
+            mean_count_error: if errors.is_empty() {
+                0.0
+            } else {
+                errors.iter().sum::<u64>() as f64 / errors.len() as f64
+            },
+            error_bound: self.error_bound(),
+        }
+    }
+
+    /// The `k` keys with the highest estimated counts.
+    pub fn top_hitters(&self, k: usize) -> Vec<HeavyHitter> {
+        let ranked: Vec<&(u64, String)> = self.by_count.iter().rev().collect();
+        let (top, rest) = ranked.split_at(k.min(ranked.len()));
+        // An untracked key may have occurred up to the minimum count times
+        let best_outside = rest.first().map_or(self.min_count(), |(c, _)| *c);
+        top.iter()
+            .map(|(count, key)| {
+                let error = self.counters[key].1;
+                HeavyHitter {
+                    key: key.clone(),
+                    count: *count,
+                    error,
+                    guaranteed: count - error >= best_outside,
+                }
+            })
+            .collect()
+    }
+
+    fn min_count(&self) -> u64 {
+        if self.counters.len() < self.capacity {
+            0
+        } else {
+            self.by_count.first().map_or(0, |(c, _)| *c)
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl HeavyHitters {
+    /// Track at most `capacity` keys (at least 1).
+    #[wasm_bindgen(constructor)]
+    pub fn new(capacity: u32) -> HeavyHitters {
+        HeavyHitters {
+            capacity: capacity.max(1) as usize,
+            counters: HashMap::new(),
+            by_count: BTreeSet::new(),
+            stream_length: 0,
+            evictions: 0,
+        }
+    }
+
+    /// Count one occurrence of `key`.
+    pub fn offer(&mut self, key: &str) {
+        self.offer_many(key, 1);
+    }
+
+    /// Count `times` occurrences of `key` at once.
+    pub fn offer_many(&mut self, key: &str, times: u32) {
+        let times = u64::from(times);
+        self.stream_length += times;
+        if let Some((count, _)) = self.counters.get_mut(key) {
+            self.by_count.remove(&(*count, key.to_string()));
+            *count += times;
+            self.by_count.insert((*count, key.to_string()));
+            return;
+        }
+        let (base, error) = if self.counters.len() < self.capacity {
+            (0, 0)
+        } else {
+            let (min, victim) = self.by_count.pop_first().expect("capacity is at least 1");
+            self.counters.remove(&victim);
+            self.evictions += 1;
+            (min, min)
+        };
+        self.counters.insert(key.to_string(), (base + times, error));
+        self.by_count.insert((base + times, key.to_string()));
+    }
+
+    /// The `k` keys with the highest estimated counts, most frequent first.
+    pub fn top(&self, k: u32) -> Vec<HeavyHitter> {
+        self.top_hitters(k as usize)
+    }
+
+    /// Estimated count of `key`; 0 if it isn't tracked (it then occurred at
+    /// most `error_bound()` times).
+    pub fn estimate(&self, key: &str) -> u64 {
+        self.counters.get(key).map_or(0, |(c, _)| *c)
+    }
+
+    /// Largest overestimate any counter can carry.
+    pub fn error_bound(&self) -> u64 {
+        self.stream_length / self.capacity as u64
+    }
+
+    pub fn stream_length(&self) -> u64 {
+        self.stream_length
+    }
+
+    /// Keys dropped to make room for new ones.
+    pub fn evictions(&self) -> u64 {
+        self.evictions
+    }
+
+    pub fn capacity(&self) -> u32 {
+        self.capacity as u32
+    }
+
+    pub fn len(&self) -> usize {
+        self.counters.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.counters.is_empty()
+    }
+}
+
+/// Feed a synthetic Zipfian stream to a sketch and to exact counters, and
+/// report how well the sketch's top `k` matches.
+///
+/// # Example
+/// ```javascript
+/// // 100k events over 10k keys, 200 counters
+/// const acc = heavy_hitters_accuracy(200, 100000, 10000, 20, 1.0, 42);
+/// console.log(acc.precision, acc.recall, acc.max_count_error);
+/// ```
+#[wasm_bindgen]
+pub fn heavy_hitters_accuracy(
+    capacity: u32,
+    stream_length: u32,
+    distinct_keys: u32,
+    k: u32,
+    exponent: f64,
+    seed: u32,
+) -> HeavyHittersAccuracy {
+    let sampler = ZipfSampler::new(distinct_keys.max(1) as usize, exponent);
+    let mut rng = DefaultRng::seed_from(u64::from(seed));
+    let mut sketch = HeavyHitters::new(capacity);
+    let mut exact: HashMap<String, u64> = HashMap::new();
+    for _ in 0..stream_length {
+        let key = format!("key{}", sampler.sample(&mut rng));
+        sketch.offer(&key);
+        *exact.entry(key).or_default() += 1;
+    }
+    sketch.accuracy_against(&exact, k as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exact_below_capacity() {
+        let mut hh = HeavyHitters::new(10);
+        for (key, times) in [("a", 5), ("b", 3), ("c", 8)] {
+            hh.offer_many(key, times);
+        }
+        let top = hh.top(2);
+        assert_eq!(top[0].key, "c");
+        assert_eq!(top[1].key, "a");
+        assert!(top.iter().all(|h| h.error == 0 && h.guaranteed));
+        assert_eq!(hh.estimate("b"), 3);
+        assert_eq!(hh.evictions(), 0);
+    }
+
+    #[test]
+    fn test_counts_are_upper_bounds() {
+        let mut hh = HeavyHitters::new(3);
+        let stream = "aaaaabbbcdefgaaah";
+        for ch in stream.chars() {
+            hh.offer(&ch.to_string());
+        }
+        assert_eq!(hh.len(), 3);
+        let a = &hh.top(1)[0];
+        assert_eq!(a.key, "a");
+        assert!(a.count >= 8 && a.count - a.error <= 8);
+        assert!(a.error <= hh.error_bound());
+        assert!(a.guaranteed);
+    }
+
+    #[test]
+    fn test_zipf_accuracy() {
+        let accuracy = heavy_hitters_accuracy(200, 50_000, 5_000, 10, 1.1, 7);
+        assert_eq!(accuracy.k, 10);
+        assert!(accuracy.precision >= 0.9, "{:?}", accuracy);
+        assert!(accuracy.recall >= 0.9, "{:?}", accuracy);
+        assert!(accuracy.max_count_error <= accuracy.error_bound);
+    }
+}
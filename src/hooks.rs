@@ -0,0 +1,52 @@
+use js_sys::Function;
+use std::rc::Rc;
+use wasm_bindgen::prelude::*;
+
+/// What one write did to one [`HashMap`](crate::HashMap) bucket.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BucketEvent {
+    pub key: String,
+    /// Bucket written, in the array the key was in at the time
+    pub bucket: usize,
+    /// Length of that bucket's chain after the write
+    pub chain_length: usize,
+}
+
+type Hook = Rc<dyn Fn(&BucketEvent)>;
+
+/// Callbacks a HashMap calls on each write, for animating the structure.
+/// Cloning the map shares them.
+#[derive(Clone, Default)]
+pub(crate) struct MutationHooks {
+    pub(crate) insert: Option<Hook>,
+    pub(crate) collision: Option<Hook>,
+    pub(crate) delete: Option<Hook>,
+}
+
+impl MutationHooks {
+    /// Whether any hook is set, so writes can skip building events.
+    pub(crate) fn any(&self) -> bool {
+        self.insert.is_some() || self.collision.is_some() || self.delete.is_some()
+    }
+
+    pub(crate) fn fire(hook: &Option<Hook>, event: &BucketEvent) {
+        if let Some(hook) = hook {
+            hook(event);
+        }
+    }
+}
+
+/// Wrap a JS function as a hook called with `(key, bucket, chain_length)`.
+/// Exceptions thrown by it are swallowed so a broken animation can't
+/// interrupt a write.
+pub(crate) fn js_hook(callback: Option<Function>) -> Option<Hook> {
+    let callback = callback?;
+    Some(Rc::new(move |event: &BucketEvent| {
+        let _ = callback.call3(
+            &JsValue::UNDEFINED,
+            &JsValue::from_str(&event.key),
+            &JsValue::from(event.bucket as u32),
+            &JsValue::from(event.chain_length as u32),
+        );
+    }))
+}
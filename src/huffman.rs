@@ -0,0 +1,308 @@
+use crate::json::json_string;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use wasm_bindgen::prelude::*;
+
+#[derive(Clone, Debug)]
+struct HuffmanNode {
+    weight: u64,
+    symbol: Option<char>,
+    /// (0 branch, 1 branch)
+    children: Option<(usize, usize)>,
+}
+
+/// Optimal prefix code for a set of symbol frequencies (Huffman coding).
+///
+/// Built bottom-up by repeatedly merging the two lightest trees, so frequent
+/// symbols end near the root with short codes. No code is a prefix of
+/// another, which is what lets `decode` read the bits back unambiguously by
+/// walking from the root: 0 goes to the first child, 1 to the second. Ties
+/// are broken by creation order, so the same counts always give the same
+/// codes.
+///
+/// # Example
+/// ```javascript
+/// const tree = new HuffmanTree("abracadabra");
+/// tree.code("a");                   // "0"
+/// const bytes = tree.encode("abracadabra"); // Uint8Array, 3 bytes
+/// tree.decode(bytes, 11);           // "abracadabra"
+/// ```
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct HuffmanTree {
+    nodes: Vec<HuffmanNode>,
+    root: Option<usize>,
+    /// symbol -> bits, most significant first
+    codes: HashMap<char, Vec<bool>>,
+}
+
+impl HuffmanTree {
+    /// Build from symbol counts. Symbols with a zero count are left out.
+    pub fn from_counts(counts: &HashMap<char, u64>) -> HuffmanTree {
+        let mut symbols: Vec<(char, u64)> = counts
+            .iter()
+            .filter(|&(_, &n)| n > 0)
+            .map(|(&c, &n)| (c, n))
+            .collect();
+        // HashMap order is random; sort so ties break the same way each time
+        symbols.sort_unstable();
+        let mut nodes: Vec<HuffmanNode> = symbols
+            .iter()
+            .map(|&(symbol, weight)| HuffmanNode {
+                weight,
+                symbol: Some(symbol),
+                children: None,
+            })
+            .collect();
+        let mut heap: BinaryHeap<Reverse<(u64, usize)>> = nodes
+            .iter()
+            .enumerate()
+            .map(|(i, n)| Reverse((n.weight, i)))
+            .collect();
+        while heap.len() > 1 {
+            let Reverse((w0, a)) = heap.pop().expect("len > 1");
+            let Reverse((w1, b)) = heap.pop().expect("len > 1");
+            nodes.push(HuffmanNode {
+                weight: w0 + w1,
+                symbol: None,
+                children: Some((a, b)),
+            });
+            heap.push(Reverse((w0 + w1, nodes.len() - 1)));
+        }
+        let root = heap.pop().map(|Reverse((_, i))| i);
+
+        let mut codes = HashMap::new();
+        let mut stack: Vec<(usize, Vec<bool>)> = root.into_iter().map(|r| (r, vec![])).collect();
+        while let Some((node, code)) = stack.pop() {
+            match (nodes[node].children, nodes[node].symbol) {
+                (Some((zero, one)), _) => {
+                    let mut one_code = code.clone();
+                    one_code.push(true);
+                    let mut zero_code = code;
+                    zero_code.push(false);
+                    stack.push((one, one_code));
+                    stack.push((zero, zero_code));
+                }
+                // A lone symbol still needs one bit per occurrence
+                (None, Some(symbol)) if code.is_empty() => {
+                    codes.insert(symbol, vec![false]);
+                }
+                (None, Some(symbol)) => {
+                    codes.insert(symbol, code);
+                }
+                (None, None) => {}
+            }
+        }
+        HuffmanTree { nodes, root, codes }
+    }
+
+    /// Encode `text`, failing on a character the tree has no code for.
+    pub fn try_encode(&self, text: &str) -> Result<Vec<u8>, String> {
+        let mut bytes = Vec::new();
+        let mut bits = 0usize;
+        for c in text.chars() {
+            let code = self
+                .codes
+                .get(&c)
+                .ok_or_else(|| format!("no code for {:?}", c))?;
+            for &bit in code {
+                if bits.is_multiple_of(8) {
+                    bytes.push(0);
+                }
+                if bit {
+                    bytes[bits / 8] |= 0x80 >> (bits % 8);
+                }
+                bits += 1;
+            }
+        }
+        Ok(bytes)
+    }
+
+    /// Decode `symbol_count` symbols from `bytes`, failing if the bits run
+    /// out first.
+    pub fn try_decode(&self, bytes: &[u8], symbol_count: usize) -> Result<String, String> {
+        let root = self.root.ok_or("empty tree")?;
+        let mut text = String::with_capacity(symbol_count);
+        let mut decoded = 0;
+        let mut node = root;
+        let mut bits = (0..bytes.len() * 8).map(|i| bytes[i / 8] & (0x80 >> (i % 8)) != 0);
+        while decoded < symbol_count {
+            let bit = bits
+                .next()
+                .ok_or_else(|| format!("ran out of bits after {} symbols", decoded))?;
+            // A lone-symbol tree is just a leaf: each bit is one symbol
+            if let Some((zero, one)) = self.nodes[node].children {
+                node = if bit { one } else { zero };
+            }
+            if let Some(symbol) = self.nodes[node].symbol {
+                text.push(symbol);
+                decoded += 1;
+                node = root;
+            }
+        }
+        Ok(text)
+    }
+
+    fn leaves(&self) -> impl Iterator<Item = &HuffmanNode> {
+        self.nodes.iter().filter(|n| n.symbol.is_some())
+    }
+
+    fn total_weight(&self) -> u64 {
+        self.leaves().map(|n| n.weight).sum()
+    }
+}
+
+#[wasm_bindgen]
+impl HuffmanTree {
+    /// Build from the character counts of `text`.
+    #[wasm_bindgen(constructor)]
+    pub fn new(text: &str) -> HuffmanTree {
+        let mut counts = HashMap::new();
+        for c in text.chars() {
+            *counts.entry(c).or_insert(0) += 1;
+        }
+        HuffmanTree::from_counts(&counts)
+    }
+
+    /// Build from explicit counts: `counts[i]` is the count of the i-th
+    /// character of `symbols`.
+    pub fn from_frequencies(symbols: &str, counts: Vec<u32>) -> HuffmanTree {
+        let mut map = HashMap::new();
+        for (c, n) in symbols.chars().zip(counts) {
+            *map.entry(c).or_insert(0) += u64::from(n);
+        }
+        HuffmanTree::from_counts(&map)
+    }
+
+    /// Pack the codes of `text` into bytes, first bit in the high bit.
+    /// Throws on a character the tree has no code for.
+    pub fn encode(&self, text: &str) -> Result<Vec<u8>, JsValue> {
+        self.try_encode(text).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// Read `symbol_count` symbols back out of `encode`'s output (the
+    /// padding bits of the last byte are ignored).
+    pub fn decode(&self, bytes: &[u8], symbol_count: usize) -> Result<String, JsValue> {
+        self.try_decode(bytes, symbol_count)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// Code of `symbol` as a string of 0s and 1s.
+    pub fn code(&self, symbol: char) -> Option<String> {
+        self.codes
+            .get(&symbol)
+            .map(|bits| bits.iter().map(|&b| if b { '1' } else { '0' }).collect())
+    }
+
+    pub fn symbol_count(&self) -> usize {
+        self.codes.len()
+    }
+
+    /// Expected code length, weighted by the counts.
+    pub fn average_bits_per_symbol(&self) -> f64 {
+        let total = self.total_weight();
+        if total == 0 {
+            return 0.0;
+        }
+        self.leaves()
+            .map(|n| (n.weight * self.codes[&n.symbol.expect("leaf")].len() as u64) as f64)
+            .sum::<f64>()
+            / total as f64
+    }
+
+    /// Shannon entropy of the counts in bits per symbol, the lower bound
+    /// `average_bits_per_symbol` comes within 1 bit of.
+    pub fn entropy(&self) -> f64 {
+        let total = self.total_weight() as f64;
+        self.leaves()
+            .map(|n| n.weight as f64 / total)
+            .map(|p| -p * p.log2())
+            .sum()
+    }
+
+    /// The tree as nested JSON for visualization. Every node has `weight`
+    /// and `children`; leaves also have `symbol` and `code`.
+    pub fn to_tree_json(&self) -> String {
+        let Some(root) = self.root else {
+            return "null".to_string();
+        };
+        let mut out = String::new();
+        // (node, children written so far)
+        let mut stack = vec![(root, 0)];
+        while let Some(&mut (node, ref mut written)) = stack.last_mut() {
+            let children = self.nodes[node]
+                .children
+                .map_or(vec![], |(a, b)| vec![a, b]);
+            if *written == 0 {
+                out.push_str(&format!("{{\"weight\":{}", self.nodes[node].weight));
+                if let Some(symbol) = self.nodes[node].symbol {
+                    out.push_str(&format!(
+                        ",\"symbol\":{},\"code\":\"{}\"",
+                        json_string(&symbol.to_string()),
+                        self.code(symbol).unwrap_or_default()
+                    ));
+                }
+                out.push_str(",\"children\":[");
+            }
+            match children.get(*written) {
+                Some(&child) => {
+                    if *written > 0 {
+                        out.push(',');
+                    }
+                    *written += 1;
+                    stack.push((child, 0));
+                }
+                None => {
+                    out.push_str("]}");
+                    stack.pop();
+                }
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip_and_optimality() {
+        let text = "abracadabra alakazam";
+        let tree = HuffmanTree::new(text);
+        let bytes = tree.try_encode(text).unwrap();
+        assert_eq!(tree.try_decode(&bytes, text.chars().count()).unwrap(), text);
+        assert!(tree.try_decode(&bytes, 1_000).is_err());
+        assert!(tree.try_encode("xyz").is_err());
+
+        // Most frequent symbol gets the shortest code; no code prefixes another
+        let a = tree.code('a').unwrap();
+        for symbol in "brcdlkzm ".chars() {
+            let code = tree.code(symbol).unwrap();
+            assert!(a.len() <= code.len());
+            assert!(!code.starts_with(&a));
+        }
+        let average = tree.average_bits_per_symbol();
+        assert!(tree.entropy() <= average && average < tree.entropy() + 1.0);
+        assert_eq!(bytes.len(), (average * 20.0 / 8.0).ceil() as usize);
+    }
+
+    #[test]
+    fn test_counts_single_symbol_and_json() {
+        let tree = HuffmanTree::from_frequencies("abc", vec![1, 1, 2]);
+        assert_eq!(tree.code('c').as_deref(), Some("0"));
+        assert_eq!(
+            tree.to_tree_json(),
+            "{\"weight\":4,\"children\":[\
+             {\"weight\":2,\"symbol\":\"c\",\"code\":\"0\",\"children\":[]},\
+             {\"weight\":2,\"children\":[\
+             {\"weight\":1,\"symbol\":\"a\",\"code\":\"10\",\"children\":[]},\
+             {\"weight\":1,\"symbol\":\"b\",\"code\":\"11\",\"children\":[]}]}]}"
+        );
+
+        let single = HuffmanTree::new("zzz");
+        let bytes = single.try_encode("zzz").unwrap();
+        assert_eq!(single.try_decode(&bytes, 3).unwrap(), "zzz");
+        assert_eq!(HuffmanTree::new("").to_tree_json(), "null");
+    }
+}
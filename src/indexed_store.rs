@@ -0,0 +1,296 @@
+use crate::{BucketEntry, HashMap, HashMapMetrics};
+use js_sys::Function;
+use std::collections::{BTreeSet, HashMap as KeyMap};
+use wasm_bindgen::prelude::*;
+
+/// Name of the index every store keeps over its values.
+pub const VALUE_INDEX: &str = "value";
+
+/// Computes an entry's position in a secondary index.
+pub type Extractor = Box<dyn Fn(&str, u32) -> Result<f64, String>>;
+
+/// `x` as bits whose unsigned order is `x`'s numeric order.
+fn sortable(x: f64) -> u64 {
+    let bits = x.to_bits();
+    if bits >> 63 == 1 {
+        !bits
+    } else {
+        bits | 1 << 63
+    }
+}
+
+/// One secondary index: entries ordered by extracted number, then key.
+struct SecondaryIndex {
+    name: String,
+    extract: Extractor,
+    ordered: BTreeSet<(u64, String)>,
+    /// Each key's current position, so removal needn't re-extract
+    positions: KeyMap<String, u64>,
+}
+
+impl SecondaryIndex {
+    fn position(&self, key: &str, value: u32) -> Result<u64, String> {
+        let x = (self.extract)(key, value)?;
+        if x.is_nan() {
+            return Err(format!("index '{}' extracted NaN for '{}'", self.name, key));
+        }
+        Ok(sortable(x))
+    }
+
+    fn remove(&mut self, key: &str) {
+        if let Some(position) = self.positions.remove(key) {
+            self.ordered.remove(&(position, key.to_string()));
+        }
+    }
+
+    fn put(&mut self, key: &str, position: u64) {
+        self.remove(key);
+        self.ordered.insert((position, key.to_string()));
+        self.positions.insert(key.to_string(), position);
+    }
+}
+
+/// A [`HashMap`] plus ordered secondary indexes that every insert and
+/// delete keeps in step, so range queries never see a stale index.
+///
+/// The `"value"` index over the values themselves is always present; more
+/// can be added with a function from (key, value) to a number. Each write
+/// computes every index position before changing anything, so an extractor
+/// that throws leaves the store as it was.
+///
+/// # Example
+/// ```javascript
+/// const scores = new IndexedStore();
+/// scores.insert("alice", 72);
+/// scores.insert("bob", 91);
+/// scores.find_by_value_range(80, 100); // [{ key: "bob", value: 91 }]
+/// scores.add_index("name_length", (key, value) => key.length);
+/// scores.find_by_index("name_length", 0, 3); // [{ key: "bob", value: 91 }]
+/// ```
+#[wasm_bindgen]
+pub struct IndexedStore {
+    primary: HashMap,
+    indexes: Vec<SecondaryIndex>,
+}
+
+impl Default for IndexedStore {
+    fn default() -> Self {
+        IndexedStore {
+            primary: HashMap::new(),
+            indexes: vec![SecondaryIndex {
+                name: VALUE_INDEX.to_string(),
+                extract: Box::new(|_, value| Ok(value as f64)),
+                ordered: BTreeSet::new(),
+                positions: KeyMap::new(),
+            }],
+        }
+    }
+}
+
+impl IndexedStore {
+    /// Add an index named `name`, positioned by `extract`, over the current
+    /// entries and every later write.
+    pub fn try_add_index(&mut self, name: &str, extract: Extractor) -> Result<(), String> {
+        if self.indexes.iter().any(|index| index.name == name) {
+            return Err(format!("index '{}' already exists", name));
+        }
+        let mut index = SecondaryIndex {
+            name: name.to_string(),
+            extract,
+            ordered: BTreeSet::new(),
+            positions: KeyMap::new(),
+        };
+        for (key, value) in self.primary.pairs() {
+            let position = index.position(&key, value)?;
+            index.put(&key, position);
+        }
+        self.indexes.push(index);
+        Ok(())
+    }
+
+    pub fn try_insert(&mut self, key: String, value: u32) -> Result<(), String> {
+        let positions = self
+            .indexes
+            .iter()
+            .map(|index| index.position(&key, value))
+            .collect::<Result<Vec<_>, _>>()?;
+        for (index, position) in self.indexes.iter_mut().zip(positions) {
+            index.put(&key, position);
+        }
+        self.primary.insert(key, value);
+        Ok(())
+    }
+
+    /// Entries whose position in index `name` lies in `[min, max]`, in
+    /// index order.
+    pub fn try_find_by_index(
+        &self,
+        name: &str,
+        min: f64,
+        max: f64,
+    ) -> Result<Vec<BucketEntry>, String> {
+        let index = self
+            .indexes
+            .iter()
+            .find(|index| index.name == name)
+            .ok_or_else(|| format!("no index named '{}'", name))?;
+        if min.is_nan() || max.is_nan() {
+            return Ok(Vec::new());
+        }
+        let max = sortable(max);
+        Ok(index
+            .ordered
+            .range((sortable(min), String::new())..)
+            .take_while(|(position, _)| *position <= max)
+            .map(|(_, key)| BucketEntry {
+                key: key.clone(),
+                value: self
+                    .primary
+                    .get(key.clone())
+                    .expect("indexes match primary"),
+            })
+            .collect())
+    }
+}
+
+#[wasm_bindgen]
+impl IndexedStore {
+    /// An empty store with just the `"value"` index.
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> IndexedStore {
+        IndexedStore::default()
+    }
+
+    /// Add an index positioned by `extractor(key, value)`, which must
+    /// return a number.
+    pub fn add_index(&mut self, name: &str, extractor: Function) -> Result<(), JsValue> {
+        let extract: Extractor = Box::new(move |key, value| {
+            extractor
+                .call2(
+                    &JsValue::UNDEFINED,
+                    &JsValue::from(key),
+                    &JsValue::from(value),
+                )
+                .map_err(|e| format!("index extractor threw: {:?}", e))?
+                .as_f64()
+                .ok_or_else(|| "index extractor must return a number".to_string())
+        });
+        self.try_add_index(name, extract)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// Insert or update, moving the key in every index. Throws, changing
+    /// nothing, if an extractor does.
+    pub fn insert(&mut self, key: String, value: u32) -> Result<(), JsValue> {
+        self.try_insert(key, value)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    pub fn get(&self, key: String) -> Option<u32> {
+        self.primary.get(key)
+    }
+
+    pub fn delete(&mut self, key: String) -> bool {
+        for index in &mut self.indexes {
+            index.remove(&key);
+        }
+        self.primary.delete(key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.primary.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.primary.is_empty()
+    }
+
+    /// Index names, `"value"` first.
+    pub fn index_names(&self) -> Vec<String> {
+        self.indexes
+            .iter()
+            .map(|index| index.name.clone())
+            .collect()
+    }
+
+    /// Entries with `min <= value <= max`, by value then key.
+    pub fn find_by_value_range(&self, min: u32, max: u32) -> Vec<BucketEntry> {
+        self.try_find_by_index(VALUE_INDEX, min as f64, max as f64)
+            .expect("the value index always exists")
+    }
+
+    /// Entries whose position in index `name` lies in `[min, max]`.
+    pub fn find_by_index(
+        &self,
+        name: &str,
+        min: f64,
+        max: f64,
+    ) -> Result<Vec<BucketEntry>, JsValue> {
+        self.try_find_by_index(name, min, max)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// Metrics of the primary map.
+    pub fn get_metrics(&self) -> HashMapMetrics {
+        self.primary.get_metrics()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(entries: Vec<BucketEntry>) -> Vec<String> {
+        entries.into_iter().map(|entry| entry.key).collect()
+    }
+
+    #[test]
+    fn test_indexes_follow_writes() {
+        let mut store = IndexedStore::new();
+        for (key, value) in [("alice", 72), ("bob", 91), ("carol", 85), ("dan", 91)] {
+            store.try_insert(key.to_string(), value).unwrap();
+        }
+        assert_eq!(
+            keys(store.find_by_value_range(85, 100)),
+            ["carol", "bob", "dan"]
+        );
+
+        store
+            .try_add_index("name_length", Box::new(|key, _| Ok(-(key.len() as f64))))
+            .unwrap();
+        assert_eq!(
+            keys(store.try_find_by_index("name_length", -3.0, 0.0).unwrap()),
+            ["bob", "dan"]
+        );
+
+        store.try_insert("bob".to_string(), 60).unwrap();
+        assert!(store.delete("dan".to_string()));
+        assert_eq!(keys(store.find_by_value_range(0, 80)), ["bob", "alice"]);
+        assert_eq!(
+            keys(store.try_find_by_index("name_length", -3.0, 0.0).unwrap()),
+            ["bob"]
+        );
+        assert!(store.try_find_by_index("missing", 0.0, 1.0).is_err());
+    }
+
+    #[test]
+    fn test_failed_extraction_changes_nothing() {
+        let mut store = IndexedStore::new();
+        store
+            .try_add_index(
+                "checked",
+                Box::new(|key, value| match key {
+                    "bad" => Err("rejected".to_string()),
+                    _ => Ok(value as f64),
+                }),
+            )
+            .unwrap();
+        store.try_insert("good".to_string(), 1).unwrap();
+        assert!(store.try_insert("bad".to_string(), 2).is_err());
+        assert_eq!(store.len(), 1);
+        assert_eq!(keys(store.find_by_value_range(0, 10)), ["good"]);
+        assert!(store
+            .try_add_index("checked", Box::new(|_, _| Ok(0.0)))
+            .is_err());
+    }
+}
@@ -0,0 +1,163 @@
+use crate::kv_store::KvStore;
+use js_sys::{Array, Map, Object, Reflect};
+use wasm_bindgen::prelude::*;
+
+/// Check that a JS number can be stored as a value: a whole number in
+/// `0..=u32::MAX`. `key` is only used in the error message.
+pub(crate) fn value_from_number(key: &str, number: Option<f64>) -> Result<u32, String> {
+    match number {
+        Some(n) if n.fract() == 0.0 && (0.0..=u32::MAX as f64).contains(&n) => Ok(n as u32),
+        Some(n) => Err(format!(
+            "value {} for key '{}' is not an integer between 0 and {}",
+            n,
+            key,
+            u32::MAX
+        )),
+        None => Err(format!("value for key '{}' is not a number", key)),
+    }
+}
+
+fn entry(key: JsValue, value: JsValue) -> Result<(String, u32), String> {
+    let key = key
+        .as_string()
+        .ok_or_else(|| "map keys must be strings".to_string())?;
+    let value = value_from_number(&key, value.as_f64())?;
+    Ok((key, value))
+}
+
+/// Entries of a JS `Map` with string keys and integer values, in the
+/// map's iteration order. Fails on the first entry that doesn't fit.
+pub(crate) fn map_entries(map: &Map) -> Result<Vec<(String, u32)>, String> {
+    let mut entries = Vec::with_capacity(map.size() as usize);
+    for pair in map.entries() {
+        let pair: Array = pair
+            .map_err(|_| "could not iterate map".to_string())?
+            .into();
+        entries.push(entry(pair.get(0), pair.get(1))?);
+    }
+    Ok(entries)
+}
+
+/// Own enumerable properties of a plain object, as with `Object.entries`.
+pub(crate) fn object_entries(object: &Object) -> Result<Vec<(String, u32)>, String> {
+    Object::entries(object)
+        .iter()
+        .map(|pair| {
+            let pair: Array = pair.into();
+            entry(pair.get(0), pair.get(1))
+        })
+        .collect()
+}
+
+/// One property of an options object, reduced to the types options use.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum OptionValue {
+    Number(f64),
+    Bool(bool),
+    Other,
+}
+
+/// Own enumerable properties of an options object such as
+/// `{ bucket_count: 64, shrink_on_delete: true }`.
+pub(crate) fn option_entries(object: &Object) -> Vec<(String, OptionValue)> {
+    Object::entries(object)
+        .iter()
+        .map(|pair| {
+            let pair: Array = pair.into();
+            let value = pair.get(1);
+            let value = if let Some(n) = value.as_f64() {
+                OptionValue::Number(n)
+            } else if let Some(b) = value.as_bool() {
+                OptionValue::Bool(b)
+            } else {
+                OptionValue::Other
+            };
+            (pair.get(0).as_string().unwrap_or_default(), value)
+        })
+        .collect()
+}
+
+/// Build a structure from a JS `Map`. `make` receives the entry count so
+/// fixed-size tables can be sized for it; nothing is built if any entry is
+/// invalid.
+pub(crate) fn from_map<S: KvStore>(map: &Map, make: impl FnOnce(usize) -> S) -> Result<S, JsValue> {
+    let entries = map_entries(map).map_err(|e| JsValue::from_str(&e))?;
+    Ok(fill(make(entries.len()), entries))
+}
+
+/// Build a structure from the own enumerable properties of a plain object,
+/// as [`from_map`] does from a `Map`.
+pub(crate) fn from_object<S: KvStore>(
+    object: &Object,
+    make: impl FnOnce(usize) -> S,
+) -> Result<S, JsValue> {
+    let entries = object_entries(object).map_err(|e| JsValue::from_str(&e))?;
+    Ok(fill(make(entries.len()), entries))
+}
+
+pub(crate) fn fill<S: KvStore>(mut store: S, entries: Vec<(String, u32)>) -> S {
+    for (key, value) in entries {
+        store.kv_insert(key, value);
+    }
+    store
+}
+
+/// A new JS `Map` holding the store's entries, in [`KvStore::kv_entries`] order.
+pub(crate) fn to_map(store: &dyn KvStore) -> Map {
+    let map = Map::new();
+    for (key, value) in store.kv_entries() {
+        map.set(&JsValue::from_str(&key), &JsValue::from(value));
+    }
+    map
+}
+
+/// The store's keys as a JS array of strings, in [`KvStore::kv_entries`]
+/// order.
+pub(crate) fn to_keys_array(store: &dyn KvStore) -> Array {
+    store
+        .kv_entries()
+        .into_iter()
+        .map(|(key, _)| JsValue::from_str(&key))
+        .collect()
+}
+
+/// The store's entries as a JS array of `[key, value]` pairs, the shape
+/// `Map.prototype.entries` yields and `new Map(...)` accepts.
+pub(crate) fn to_entries_array(store: &dyn KvStore) -> Array {
+    store
+        .kv_entries()
+        .into_iter()
+        .map(|(key, value)| Array::of2(&JsValue::from_str(&key), &JsValue::from(value)))
+        .collect()
+}
+
+/// A new plain object with one property per entry. JS orders integer-like
+/// keys (`"1"`, `"42"`) numerically ahead of the rest; use [`to_map`] when
+/// order matters.
+pub(crate) fn to_object(store: &dyn KvStore) -> Object {
+    let object = Object::new();
+    for (key, value) in store.kv_entries() {
+        // Defining a property on a fresh plain object can't fail
+        let _ = Reflect::set(&object, &JsValue::from_str(&key), &JsValue::from(value));
+    }
+    object
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_value_validation() {
+        assert_eq!(value_from_number("a", Some(0.0)), Ok(0));
+        assert_eq!(value_from_number("a", Some(42.0)), Ok(42));
+        assert_eq!(value_from_number("a", Some(u32::MAX as f64)), Ok(u32::MAX));
+        for bad in [-1.0, 1.5, u32::MAX as f64 + 1.0, f64::NAN, f64::INFINITY] {
+            let err = value_from_number("a", Some(bad)).unwrap_err();
+            assert!(err.contains("key 'a'"), "{}", err);
+        }
+        assert!(value_from_number("b", None)
+            .unwrap_err()
+            .contains("not a number"));
+    }
+}
@@ -0,0 +1,324 @@
+use crate::guarded::comparison_count;
+use crate::kv_store::{DynamicStore, KvStore, ORDERED_KINDS};
+use std::cmp::Ordering;
+use wasm_bindgen::prelude::*;
+
+/// How [`DynamicStore::inner_join`] and [`DynamicStore::left_join`] match
+/// keys.
+///
+/// - `Hash`: walk one side's entries and look each key up in the other,
+///   the smaller side driving an inner join. Costs one probe per entry
+///   walked, each as expensive as a lookup in the probed structure.
+/// - `SortedMerge`: walk both sides in key order side by side, one key
+///   comparison per step. The ordered structures (trees, skip list, trie)
+///   list their entries in key order for free; hash tables must sort
+///   theirs first, which the metrics count separately.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum JoinStrategy {
+    #[default]
+    Hash,
+    SortedMerge,
+}
+
+/// What a join cost, to compare strategies on the same inputs.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct JoinMetrics {
+    pub strategy: JoinStrategy,
+    pub left_entries: u32,
+    pub right_entries: u32,
+    /// Keys present on both sides
+    pub matches: u32,
+    /// Lookups a hash join made into the probed structure
+    pub probes: u32,
+    /// Key comparisons the probed structure made for those lookups; 0 for
+    /// structures that don't count them (red-black tree, trie, two-choice)
+    pub probe_comparisons: f64,
+    /// Key comparisons the sorted-merge walk made
+    pub merge_comparisons: u32,
+    /// Key comparisons spent sorting an unordered side for the merge
+    pub sort_comparisons: u32,
+}
+
+#[wasm_bindgen]
+impl JoinMetrics {
+    /// Every key comparison the join made, however it made them.
+    pub fn total_comparisons(&self) -> f64 {
+        self.probe_comparisons + self.merge_comparisons as f64 + self.sort_comparisons as f64
+    }
+}
+
+/// A joined map and what producing it cost.
+#[wasm_bindgen]
+pub struct JoinResult {
+    store: DynamicStore,
+    unmatched: Vec<String>,
+    metrics: JoinMetrics,
+}
+
+#[wasm_bindgen]
+impl JoinResult {
+    /// The joined map, a new structure of the left side's kind.
+    pub fn store(&self) -> DynamicStore {
+        self.store.handle()
+    }
+
+    /// Left keys with no match on the right, in key order (left joins
+    /// only; empty for inner joins).
+    pub fn unmatched(&self) -> Vec<String> {
+        self.unmatched.clone()
+    }
+
+    pub fn metrics(&self) -> JoinMetrics {
+        self.metrics
+    }
+}
+
+/// One left entry with its right match, if any.
+type Row = (String, u32, Option<u32>);
+
+/// Match `left` against `right`: every left entry when `keep_unmatched`,
+/// else only those whose key `right` also holds. Rows come in key order.
+fn join_rows(
+    left: &DynamicStore,
+    right: &DynamicStore,
+    strategy: JoinStrategy,
+    keep_unmatched: bool,
+) -> (Vec<Row>, JoinMetrics) {
+    let mut metrics = JoinMetrics {
+        strategy,
+        left_entries: left.len() as u32,
+        right_entries: right.len() as u32,
+        ..JoinMetrics::default()
+    };
+    let mut rows = match strategy {
+        JoinStrategy::Hash => hash_join(left, right, keep_unmatched, &mut metrics),
+        JoinStrategy::SortedMerge => merge_join(left, right, keep_unmatched, &mut metrics),
+    };
+    rows.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+    metrics.matches = rows.iter().filter(|row| row.2.is_some()).count() as u32;
+    (rows, metrics)
+}
+
+fn hash_join(
+    left: &DynamicStore,
+    right: &DynamicStore,
+    keep_unmatched: bool,
+    metrics: &mut JoinMetrics,
+) -> Vec<Row> {
+    // Only a left join has to walk the left side; otherwise probe the
+    // larger side with the smaller one's keys
+    let left_drives = keep_unmatched || metrics.left_entries <= metrics.right_entries;
+    let (driver, probed) = if left_drives {
+        (left, right)
+    } else {
+        (right, left)
+    };
+    // Listing the driver's entries first frees it for a self-join
+    let entries = driver.store().kv_entries();
+    let mut probed = probed.handle();
+    let mut probed = probed.store_mut();
+    let before = comparison_count(&*probed);
+    let mut rows = Vec::new();
+    for (key, value) in entries {
+        metrics.probes += 1;
+        let found = probed.kv_get(&key);
+        match (left_drives, found) {
+            (true, right) if right.is_some() || keep_unmatched => rows.push((key, value, right)),
+            (false, Some(left)) => rows.push((key, left, Some(value))),
+            _ => {}
+        }
+    }
+    if let (Some(before), Some(after)) = (before, comparison_count(&*probed)) {
+        metrics.probe_comparisons = after - before;
+    }
+    rows
+}
+
+fn merge_join(
+    left: &DynamicStore,
+    right: &DynamicStore,
+    keep_unmatched: bool,
+    metrics: &mut JoinMetrics,
+) -> Vec<Row> {
+    let left = sorted_entries(&*left.store(), &mut metrics.sort_comparisons);
+    let right = sorted_entries(&*right.store(), &mut metrics.sort_comparisons);
+    let (mut i, mut j) = (0, 0);
+    let mut rows = Vec::new();
+    while i < left.len() && j < right.len() {
+        metrics.merge_comparisons += 1;
+        match left[i].0.cmp(&right[j].0) {
+            Ordering::Less => {
+                if keep_unmatched {
+                    rows.push((left[i].0.clone(), left[i].1, None));
+                }
+                i += 1;
+            }
+            Ordering::Greater => j += 1,
+            Ordering::Equal => {
+                rows.push((left[i].0.clone(), left[i].1, Some(right[j].1)));
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    if keep_unmatched {
+        rows.extend(
+            left[i..]
+                .iter()
+                .map(|(key, value)| (key.clone(), *value, None)),
+        );
+    }
+    rows
+}
+
+/// A store's entries in key order, sorting (and counting the comparisons)
+/// only if the structure doesn't keep them that way.
+fn sorted_entries(store: &dyn KvStore, comparisons: &mut u32) -> Vec<(String, u32)> {
+    let mut entries = store.kv_entries();
+    if !ORDERED_KINDS.contains(&store.kind()) {
+        entries.sort_unstable_by(|a, b| {
+            *comparisons += 1;
+            a.0.cmp(&b.0)
+        });
+    }
+    entries
+}
+
+/// Fill a new structure of `left`'s kind with `(key, value)` pairs.
+fn collect_into(left: &DynamicStore, entries: Vec<(String, u32)>) -> DynamicStore {
+    let mut store =
+        DynamicStore::try_new(&left.kind(), entries.len() as u32).expect("an existing kind");
+    for (key, value) in entries {
+        store.insert(key, value);
+    }
+    store
+}
+
+/// Keys in both `left` and `right`, each valued `combiner(key, left,
+/// right)`. Fails, building nothing, on the combiner's first error.
+pub fn try_inner_join(
+    left: &DynamicStore,
+    right: &DynamicStore,
+    strategy: JoinStrategy,
+    mut combiner: impl FnMut(&str, u32, u32) -> Result<u32, String>,
+) -> Result<JoinResult, String> {
+    let (rows, metrics) = join_rows(left, right, strategy, false);
+    let mut entries = Vec::with_capacity(rows.len());
+    for (key, l, r) in rows {
+        let r = r.expect("inner joins only keep matched rows");
+        let value = combiner(&key, l, r)?;
+        entries.push((key, value));
+    }
+    Ok(JoinResult {
+        store: collect_into(left, entries),
+        unmatched: Vec::new(),
+        metrics,
+    })
+}
+
+/// Every left key matched against `right`: the shared keys, valued as on
+/// the right, go into the result map and the rest into `unmatched`.
+pub fn left_join(left: &DynamicStore, right: &DynamicStore, strategy: JoinStrategy) -> JoinResult {
+    let (rows, metrics) = join_rows(left, right, strategy, true);
+    let mut entries = Vec::new();
+    let mut unmatched = Vec::new();
+    for (key, _, r) in rows {
+        match r {
+            Some(value) => entries.push((key, value)),
+            None => unmatched.push(key),
+        }
+    }
+    JoinResult {
+        store: collect_into(left, entries),
+        unmatched,
+        metrics,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store(kind: &str, entries: impl IntoIterator<Item = (String, u32)>) -> DynamicStore {
+        let mut store = DynamicStore::try_new(kind, 0).unwrap();
+        for (key, value) in entries {
+            store.insert(key, value);
+        }
+        store
+    }
+
+    fn sorted(result: &JoinResult) -> Vec<(String, u32)> {
+        let mut entries = result.store().store().kv_entries();
+        entries.sort_unstable();
+        entries
+    }
+
+    #[test]
+    fn test_strategies_agree() {
+        let users = (0..200).map(|i| (format!("user{:03}", i), i));
+        let orders = (100..400)
+            .step_by(2)
+            .map(|i| (format!("user{:03}", i), i * 10));
+        for (left_kind, right_kind) in [
+            ("hashmap", "bst"),
+            ("rbtree", "skiplist"),
+            ("trie", "open_addressing"),
+        ] {
+            let left = store(left_kind, users.clone());
+            let right = store(right_kind, orders.clone());
+            let hash =
+                try_inner_join(&left, &right, JoinStrategy::Hash, |_, l, r| Ok(l + r)).unwrap();
+            let merge =
+                try_inner_join(
+                    &left,
+                    &right,
+                    JoinStrategy::SortedMerge,
+                    |_, l, r| Ok(l + r),
+                )
+                .unwrap();
+            assert_eq!(sorted(&hash), sorted(&merge));
+            assert_eq!(hash.metrics().matches, 50);
+            assert_eq!(sorted(&hash)[0], ("user100".to_string(), 100 + 1_000));
+            assert_eq!(hash.store().kind(), left_kind);
+
+            // The smaller side drives the probes
+            assert_eq!(hash.metrics().probes, 150);
+            assert_eq!(merge.metrics().probes, 0);
+            assert!(merge.metrics().merge_comparisons <= 350);
+
+            let joined = left_join(&left, &right, JoinStrategy::SortedMerge);
+            assert_eq!(joined.metrics().probes, 0);
+            assert_eq!(
+                sorted(&joined),
+                sorted(&left_join(&left, &right, JoinStrategy::Hash))
+            );
+            assert_eq!(joined.unmatched().len(), 150);
+            assert_eq!(joined.unmatched()[0], "user000");
+        }
+    }
+
+    #[test]
+    fn test_merge_sorts_only_unordered_sides() {
+        let entries = (0..100).map(|i| (format!("k{:02}", i), i));
+        let tree = store("bst", entries.clone());
+        let table = store("hashmap", entries);
+        let ordered = left_join(&tree, &tree.handle(), JoinStrategy::SortedMerge);
+        assert_eq!(ordered.metrics().sort_comparisons, 0);
+        assert_eq!(ordered.metrics().merge_comparisons, 100);
+        let unordered = left_join(&table, &tree, JoinStrategy::SortedMerge);
+        assert!(unordered.metrics().sort_comparisons >= 99);
+
+        // A self-join probes the structure it walks
+        let hashed = left_join(&table, &table, JoinStrategy::Hash);
+        assert_eq!(hashed.metrics().probes, 100);
+        assert!(hashed.metrics().probe_comparisons >= 100.0);
+        assert!(hashed.unmatched().is_empty());
+
+        let failed = try_inner_join(&tree, &table, JoinStrategy::Hash, |key, _, _| {
+            Err(format!("no combining {}", key))
+        });
+        assert!(failed.is_err());
+    }
+}
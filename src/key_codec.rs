@@ -0,0 +1,308 @@
+use wasm_bindgen::prelude::*;
+
+/// Escapes a NUL inside a string component; sorts after `TERMINATOR`, so a
+/// string sorts after its own prefixes.
+const ESCAPED_NUL: &str = "\u{0}\u{2}";
+/// Ends a string component; sorts before every character that can follow.
+const TERMINATOR: &str = "\u{0}\u{1}";
+/// Largest integer a JS number holds exactly.
+const MAX_SAFE_INTEGER: f64 = 9_007_199_254_740_991.0;
+
+/// One component type in a [`KeyCodec`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyPart {
+    U32,
+    U64,
+    Str,
+}
+
+/// One component value of a composite key.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum KeyValue {
+    U32(u32),
+    U64(u64),
+    Str(String),
+}
+
+/// Encodes composite keys such as `(user_id, timestamp)` into single
+/// strings whose byte order is the tuple order, so the string-keyed ordered
+/// structures ([`BinarySearchTree`](crate::BinarySearchTree),
+/// [`SkipList`](crate::SkipList), [`Trie`](crate::Trie), ...) sort and
+/// prefix-search them field by field.
+///
+/// Integers become fixed-width lowercase hex (8 digits for `u32`, 16 for
+/// `u64`), so numeric order is string order without padding tricks.
+/// Strings are written as-is with NUL escaped and a NUL-based terminator,
+/// so `"ab"` still sorts before `"abc"` and the next field can't leak into
+/// the comparison.
+///
+/// # Example
+/// ```javascript
+/// const codec = new KeyCodec().u32().u64().string();
+/// const key = codec.encode([42, 1_700_000_000_000n, "login"]);
+/// list.insert(key, 1);
+/// codec.decode(key); // [42, 1700000000000n, "login"]
+/// // Every event of user 42 sorts together, behind this prefix:
+/// trie.autocomplete(codec.encode_prefix([42]));
+/// ```
+#[wasm_bindgen]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct KeyCodec {
+    parts: Vec<KeyPart>,
+}
+
+impl KeyCodec {
+    pub fn with_parts(parts: Vec<KeyPart>) -> KeyCodec {
+        KeyCodec { parts }
+    }
+
+    pub fn parts(&self) -> &[KeyPart] {
+        &self.parts
+    }
+
+    /// Encode a value for every part.
+    pub fn try_encode(&self, values: &[KeyValue]) -> Result<String, String> {
+        if values.len() != self.parts.len() {
+            return Err(format!(
+                "expected {} key parts, got {}",
+                self.parts.len(),
+                values.len()
+            ));
+        }
+        self.try_encode_prefix(values)
+    }
+
+    /// Encode values for the leading parts only. Every full key starting
+    /// with those values starts with the result.
+    pub fn try_encode_prefix(&self, values: &[KeyValue]) -> Result<String, String> {
+        if values.len() > self.parts.len() {
+            return Err(format!(
+                "expected at most {} key parts, got {}",
+                self.parts.len(),
+                values.len()
+            ));
+        }
+        let mut key = String::new();
+        for (i, (part, value)) in self.parts.iter().zip(values).enumerate() {
+            match (part, value) {
+                (KeyPart::U32, KeyValue::U32(n)) => key.push_str(&format!("{:08x}", n)),
+                (KeyPart::U64, KeyValue::U64(n)) => key.push_str(&format!("{:016x}", n)),
+                (KeyPart::Str, KeyValue::Str(s)) => {
+                    key.push_str(&s.replace('\u{0}', ESCAPED_NUL));
+                    key.push_str(TERMINATOR);
+                }
+                _ => {
+                    return Err(format!(
+                        "key part {} should be {:?}, got {:?}",
+                        i, part, value
+                    ))
+                }
+            }
+        }
+        Ok(key)
+    }
+
+    pub fn try_decode(&self, key: &str) -> Result<Vec<KeyValue>, String> {
+        let mut rest = key;
+        let mut values = Vec::with_capacity(self.parts.len());
+        for (i, part) in self.parts.iter().enumerate() {
+            let invalid = || format!("key part {} is not a valid {:?}", i, part);
+            let value = match part {
+                KeyPart::U32 | KeyPart::U64 => {
+                    let width = if *part == KeyPart::U32 { 8 } else { 16 };
+                    let digits = rest.get(..width).ok_or_else(invalid)?;
+                    if !digits
+                        .bytes()
+                        .all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+                    {
+                        return Err(invalid());
+                    }
+                    rest = &rest[width..];
+                    let n = u64::from_str_radix(digits, 16).map_err(|_| invalid())?;
+                    match part {
+                        KeyPart::U32 => KeyValue::U32(n as u32),
+                        _ => KeyValue::U64(n),
+                    }
+                }
+                KeyPart::Str => {
+                    let mut s = String::new();
+                    loop {
+                        let nul = rest.find('\u{0}').ok_or_else(invalid)?;
+                        s.push_str(&rest[..nul]);
+                        let marker = rest.get(nul..nul + 2).ok_or_else(invalid)?;
+                        rest = &rest[nul + 2..];
+                        match marker {
+                            TERMINATOR => break,
+                            ESCAPED_NUL => s.push('\u{0}'),
+                            _ => return Err(invalid()),
+                        }
+                    }
+                    KeyValue::Str(s)
+                }
+            };
+            values.push(value);
+        }
+        if !rest.is_empty() {
+            return Err(format!("{} unexpected trailing characters", rest.len()));
+        }
+        Ok(values)
+    }
+}
+
+/// `value` as a component of type `part`: a whole number for `U32`, a
+/// `BigInt` or safe integer for `U64`, a string for `Str`.
+fn key_value_from_js(part: KeyPart, value: &JsValue) -> Result<KeyValue, String> {
+    let whole = |max: f64| {
+        value
+            .as_f64()
+            .filter(|n| n.fract() == 0.0 && (0.0..=max).contains(n))
+    };
+    match part {
+        KeyPart::U32 => whole(u32::MAX as f64).map(|n| KeyValue::U32(n as u32)),
+        KeyPart::U64 if value.is_bigint() => u64::try_from(value.clone()).ok().map(KeyValue::U64),
+        KeyPart::U64 => whole(MAX_SAFE_INTEGER).map(|n| KeyValue::U64(n as u64)),
+        KeyPart::Str => value.as_string().map(KeyValue::Str),
+    }
+    .ok_or_else(|| format!("expected a {:?} key part", part))
+}
+
+impl KeyCodec {
+    fn values_from_js(&self, values: &[JsValue]) -> Result<Vec<KeyValue>, JsValue> {
+        self.parts
+            .iter()
+            .zip(values)
+            .map(|(&part, value)| key_value_from_js(part, value))
+            .collect::<Result<_, _>>()
+            .map_err(|e| JsValue::from_str(&e))
+    }
+}
+
+#[wasm_bindgen]
+impl KeyCodec {
+    /// A codec with no parts; add them in key order.
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> KeyCodec {
+        KeyCodec::default()
+    }
+
+    /// Append a `u32` component (a number in JS).
+    pub fn u32(mut self) -> KeyCodec {
+        self.parts.push(KeyPart::U32);
+        self
+    }
+
+    /// Append a `u64` component (a `BigInt`, or a number up to 2^53).
+    pub fn u64(mut self) -> KeyCodec {
+        self.parts.push(KeyPart::U64);
+        self
+    }
+
+    /// Append a string component.
+    pub fn string(mut self) -> KeyCodec {
+        self.parts.push(KeyPart::Str);
+        self
+    }
+
+    /// Number of components.
+    pub fn len(&self) -> usize {
+        self.parts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.parts.is_empty()
+    }
+
+    pub fn encode(&self, values: Vec<JsValue>) -> Result<String, JsValue> {
+        if values.len() != self.parts.len() {
+            return Err(JsValue::from_str(&format!(
+                "expected {} key parts, got {}",
+                self.parts.len(),
+                values.len()
+            )));
+        }
+        let values = self.values_from_js(&values)?;
+        self.try_encode(&values).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// Encode the first `values.length` components, for prefix searches
+    /// over every key that starts with them.
+    pub fn encode_prefix(&self, values: Vec<JsValue>) -> Result<String, JsValue> {
+        if values.len() > self.parts.len() {
+            return Err(JsValue::from_str(&format!(
+                "expected at most {} key parts, got {}",
+                self.parts.len(),
+                values.len()
+            )));
+        }
+        let values = self.values_from_js(&values)?;
+        self.try_encode_prefix(&values)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// The components of an encoded key; `u64` parts come back as `BigInt`.
+    pub fn decode(&self, key: &str) -> Result<Vec<JsValue>, JsValue> {
+        let values = self.try_decode(key).map_err(|e| JsValue::from_str(&e))?;
+        Ok(values
+            .into_iter()
+            .map(|value| match value {
+                KeyValue::U32(n) => JsValue::from(n),
+                KeyValue::U64(n) => JsValue::from(n),
+                KeyValue::Str(s) => JsValue::from(s),
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(user: u32, time: u64, kind: &str) -> Vec<KeyValue> {
+        vec![
+            KeyValue::U32(user),
+            KeyValue::U64(time),
+            KeyValue::Str(kind.to_string()),
+        ]
+    }
+
+    #[test]
+    fn test_order_preserving_round_trip() {
+        let codec = KeyCodec::new().u32().u64().string();
+        let mut tuples = vec![
+            event(2, 5, "a"),
+            event(10, 0, ""),
+            event(2, 5, "a\u{0}"),
+            event(2, 5, "ab"),
+            event(2, 300, "a"),
+            event(u32::MAX, u64::MAX, "\u{0}\u{0}z"),
+            event(0, 1, "é"),
+        ];
+        let mut keys: Vec<String> = tuples
+            .iter()
+            .map(|t| codec.try_encode(t).unwrap())
+            .collect();
+        for (key, tuple) in keys.iter().zip(&tuples) {
+            assert_eq!(&codec.try_decode(key).unwrap(), tuple);
+        }
+        tuples.sort();
+        keys.sort();
+        let decoded: Vec<Vec<KeyValue>> =
+            keys.iter().map(|k| codec.try_decode(k).unwrap()).collect();
+        assert_eq!(decoded, tuples);
+
+        let prefix = codec.try_encode_prefix(&[KeyValue::U32(2)]).unwrap();
+        assert_eq!(keys.iter().filter(|k| k.starts_with(&prefix)).count(), 4);
+    }
+
+    #[test]
+    fn test_rejects_mismatched_parts() {
+        let codec = KeyCodec::new().u32().string();
+        assert!(codec.try_encode(&[KeyValue::U32(1)]).is_err());
+        assert!(codec
+            .try_encode(&[KeyValue::Str("x".into()), KeyValue::U32(1)])
+            .is_err());
+        assert!(codec.try_decode("0000000ax").is_err());
+        assert!(codec.try_decode("0000000ax\u{0}\u{1}extra").is_err());
+        assert!(codec.try_decode("0000000Ax\u{0}\u{1}").is_err());
+    }
+}
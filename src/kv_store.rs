@@ -0,0 +1,1292 @@
+use crate::access::{AccessProfile, AccessReport, HotKey};
+use crate::bulk::BulkInsertJob;
+use crate::capacity::CapacityConfig;
+use crate::chain::BucketMode;
+#[cfg(feature = "compression")]
+use crate::compression::{self, SnapshotCompressionStats};
+#[cfg(feature = "crypto")]
+use crate::crypto;
+use crate::events::{EventEmitter, EventKind, StoreEvent};
+use crate::frozen::FrozenView;
+use crate::interop;
+use crate::join::{self, JoinResult, JoinStrategy};
+use crate::memory::MemoryReport;
+#[cfg(feature = "msgpack")]
+use crate::msgpack;
+use crate::registry::{self, SharedStore};
+#[cfg(feature = "msgpack")]
+use crate::snapshot::{Snapshot, SnapshotDelta};
+use crate::two_choice::TwoChoiceHashMap;
+use crate::{
+    BinarySearchTree, HashMap, HashMapBuilder, OpenAddressingHashTable, RedBlackTree, SkipList,
+    Trie,
+};
+use std::cell::{Ref, RefCell, RefMut};
+use std::collections::BTreeMap;
+use std::rc::Rc;
+use wasm_bindgen::prelude::*;
+
+/// Structure names accepted by [`new_store`], in the order they are documented.
+pub const STORE_KINDS: [&str; 6] = [
+    "hashmap",
+    "open_addressing",
+    "bst",
+    "rbtree",
+    "skiplist",
+    "trie",
+];
+
+/// Structures whose [`KvStore::kv_entries`] come out in key order.
+pub const ORDERED_KINDS: [&str; 4] = ["bst", "rbtree", "skiplist", "trie"];
+
+/// Extra chained hash table names [`new_store`] accepts: the HashMap with a
+/// non-default [`BucketMode`], and [`TwoChoiceHashMap`], so they can be
+/// benchmarked side by side with the plain HashMap.
+pub const HASHMAP_VARIANTS: [&str; 3] = ["hashmap_linked", "hashmap_sorted", "two_choice"];
+
+/// Common key-value interface implemented by every map-like structure.
+///
+/// The wasm-exported methods differ slightly per structure (`get` vs `search`,
+/// `bool` vs `Option<u32>` from delete), so generic code such as the benchmark
+/// runner goes through this trait instead.
+pub trait KvStore {
+    /// Short machine-readable name, one of [`STORE_KINDS`] or [`HASHMAP_VARIANTS`].
+    fn kind(&self) -> &'static str;
+
+    /// Insert or update a key.
+    fn kv_insert(&mut self, key: String, value: u32);
+
+    /// Look up a key. Takes `&mut self` because several structures count searches.
+    fn kv_get(&mut self, key: &str) -> Option<u32>;
+
+    /// Remove a key, returning whether it was present.
+    fn kv_delete(&mut self, key: &str) -> bool;
+
+    /// Number of stored keys.
+    fn kv_len(&self) -> usize;
+
+    /// Every entry: in key order for the trees, skip list and trie,
+    /// insertion order for the HashMap, and slot order otherwise.
+    fn kv_entries(&self) -> Vec<(String, u32)>;
+
+    /// The structure's own metrics flattened to `(name, value)` pairs, so
+    /// generic reports can diff them without knowing each metrics type.
+    fn metrics_snapshot(&self) -> Vec<(&'static str, f64)>;
+
+    /// Estimated heap usage, split into used and reserved bytes.
+    fn memory_report(&self) -> MemoryReport;
+
+    /// Release reserved memory the structure isn't using.
+    fn shrink_to_fit(&mut self);
+
+    /// A plain-text drawing of the structure for consoles and the REPL.
+    fn to_ascii(&self) -> String;
+
+    /// Slots (or buckets) in the backing table, for structures that have one.
+    fn capacity(&self) -> Option<usize> {
+        None
+    }
+}
+
+/// Construct an empty structure by name.
+///
+/// `capacity_hint` is only used by open addressing, which starts at twice
+/// the hint so a table sized for its dataset never has to resize.
+pub fn new_store(kind: &str, capacity_hint: usize) -> Option<Box<dyn KvStore>> {
+    let store: Box<dyn KvStore> = match kind {
+        "hashmap" => Box::new(HashMap::new()),
+        "hashmap_linked" | "hashmap_sorted" => {
+            let mode = if kind == "hashmap_linked" {
+                BucketMode::LinkedList
+            } else {
+                BucketMode::SortedVec
+            };
+            Box::new(HashMapBuilder::new().bucket_mode(mode).try_build().ok()?)
+        }
+        "two_choice" => Box::new(TwoChoiceHashMap::default()),
+        "open_addressing" => {
+            let config = CapacityConfig::new().with_min_capacity((capacity_hint.max(8) * 2) as u32);
+            Box::new(OpenAddressingHashTable::try_with_config(config).ok()?)
+        }
+        "bst" => Box::new(BinarySearchTree::new()),
+        "rbtree" => Box::new(RedBlackTree::new()),
+        "skiplist" => Box::new(SkipList::new()),
+        "trie" => Box::new(Trie::new()),
+        _ => return None,
+    };
+    Some(store)
+}
+
+/// Any structure behind one JS-facing type, chosen by name at runtime.
+///
+/// Useful when the structure is picked dynamically (a dropdown, a saved config)
+/// or when an API has to take ownership of it, as the async bulk operations do.
+/// While the registry is enabled, new stores are tracked by it (see
+/// [`registry_snapshot`](crate::registry::registry_snapshot)).
+///
+/// # Example
+/// ```javascript
+/// const store = new DynamicStore("rbtree", 0);
+/// store.insert("hello", 42);
+/// console.log(store.kind(), store.get("hello"));
+/// ```
+///
+/// Writes can be grouped into a batch that is applied or discarded as a
+/// whole. While a batch is open, inserts and deletes are staged on this
+/// handle and `get` sees them, but the structure itself (and `len`, metrics,
+/// other handles) is untouched until `commit`:
+///
+/// ```javascript
+/// store.begin_batch();
+/// store.insert("draft", 1);
+/// store.get("draft");   // 1
+/// store.rollback();
+/// store.get("draft");   // undefined
+/// ```
+///
+/// Listeners can follow writes without wrapping every call site; they are
+/// shared by all handles to the structure:
+///
+/// ```javascript
+/// store.on("resize", (e) => console.log(`grew from ${e.from} to ${e.to}`));
+/// ```
+#[wasm_bindgen]
+pub struct DynamicStore {
+    inner: SharedStore,
+    registry_id: Option<u32>,
+    // Staged writes of the open batch; `None` deletes the key
+    batch: Option<BTreeMap<String, Option<u32>>>,
+    events: Rc<RefCell<EventEmitter>>,
+    // Accesses through this handle, while tracking is on
+    access: Option<AccessProfile>,
+}
+
+impl DynamicStore {
+    pub fn from_store(inner: Box<dyn KvStore>) -> DynamicStore {
+        let inner = Rc::new(RefCell::new(inner));
+        let registry_id = registry::register(&inner);
+        DynamicStore {
+            inner,
+            registry_id,
+            batch: None,
+            events: Rc::default(),
+            access: None,
+        }
+    }
+
+    pub fn try_new(kind: &str, capacity: u32) -> Result<DynamicStore, String> {
+        new_store(kind, capacity as usize)
+            .map(Self::from_store)
+            .ok_or_else(|| format!("unknown structure '{}'", kind))
+    }
+
+    /// Another handle to the same structure. Writes through either handle
+    /// are visible through both; an open batch is not shared.
+    pub fn handle(&self) -> DynamicStore {
+        DynamicStore {
+            inner: Rc::clone(&self.inner),
+            registry_id: self.registry_id,
+            batch: None,
+            events: Rc::clone(&self.events),
+            access: None,
+        }
+    }
+
+    pub fn store(&self) -> Ref<'_, dyn KvStore> {
+        Ref::map(self.inner.borrow(), |s| s.as_ref())
+    }
+
+    /// Mutable access to the structure. The registry reports the store as
+    /// busy while the guard is held.
+    pub fn store_mut(&mut self) -> RefMut<'_, dyn KvStore> {
+        RefMut::map(self.inner.borrow_mut(), |s| s.as_mut())
+    }
+
+    /// `get` without recording an access.
+    fn read(&mut self, key: &str) -> Option<u32> {
+        if let Some(staged) = self.batch.as_ref().and_then(|b| b.get(key)) {
+            return *staged;
+        }
+        self.store_mut().kv_get(key)
+    }
+
+    /// What `track_access` has recorded, if it is on.
+    pub fn access_profile(&self) -> Option<&AccessProfile> {
+        self.access.as_ref()
+    }
+
+    /// Call `listener` for every event of `kind`, from any handle.
+    pub fn subscribe(&self, kind: EventKind, listener: impl FnMut(&StoreEvent) + 'static) -> u32 {
+        self.events.borrow_mut().subscribe(kind, listener)
+    }
+
+    /// Run a write against the structure, emitting whatever resize or
+    /// rebalance it caused. Those checks only run if someone listens.
+    fn observed<T>(&mut self, write: impl FnOnce(&mut dyn KvStore) -> T) -> T {
+        let (resize, rebalance) = {
+            let events = self.events.borrow();
+            (
+                events.wants(EventKind::Resize),
+                events.wants(EventKind::Rebalance),
+            )
+        };
+        let capacity_before = if resize {
+            self.store().capacity()
+        } else {
+            None
+        };
+        let rebalances_before = if rebalance {
+            self.metric("rebalance_count")
+        } else {
+            None
+        };
+
+        let result = write(&mut *self.store_mut());
+
+        if let (Some(from), Some(to)) = (capacity_before, self.store().capacity()) {
+            if from != to {
+                self.emit(StoreEvent::Resized { from, to });
+            }
+        }
+        if let (Some(before), Some(after)) = (rebalances_before, self.metric("rebalance_count")) {
+            if after > before {
+                self.emit(StoreEvent::Rebalanced {
+                    total: after as u64,
+                });
+            }
+        }
+        result
+    }
+
+    fn emit(&self, event: StoreEvent) {
+        EventEmitter::dispatch(&self.events, &event);
+    }
+
+    fn apply_insert(&mut self, key: String, value: u32) {
+        let wants = self.events.borrow().wants(EventKind::Insert);
+        let event_key = if wants { Some(key.clone()) } else { None };
+        self.observed(|store| store.kv_insert(key, value));
+        if let Some(key) = event_key {
+            let len = self.len();
+            self.emit(StoreEvent::Inserted { key, value, len });
+        }
+    }
+
+    fn apply_delete(&mut self, key: &str) -> bool {
+        let removed = self.observed(|store| store.kv_delete(key));
+        if removed && self.events.borrow().wants(EventKind::Delete) {
+            let len = self.len();
+            self.emit(StoreEvent::Deleted {
+                key: key.to_string(),
+                len,
+            });
+        }
+        removed
+    }
+
+    pub fn try_begin_batch(&mut self) -> Result<(), String> {
+        if self.batch.is_some() {
+            return Err("a batch is already open".to_string());
+        }
+        self.batch = Some(BTreeMap::new());
+        Ok(())
+    }
+
+    pub fn try_commit(&mut self) -> Result<u32, String> {
+        let staged = self.batch.take().ok_or("no batch is open")?;
+        let applied = staged.len() as u32;
+        for (key, value) in staged {
+            match value {
+                Some(value) => self.apply_insert(key, value),
+                None => {
+                    self.apply_delete(&key);
+                }
+            }
+        }
+        Ok(applied)
+    }
+
+    pub fn try_rollback(&mut self) -> Result<u32, String> {
+        let staged = self.batch.take().ok_or("no batch is open")?;
+        Ok(staged.len() as u32)
+    }
+}
+
+#[wasm_bindgen]
+impl DynamicStore {
+    /// Create an empty structure of `kind` (see [`STORE_KINDS`]).
+    ///
+    /// `capacity` only matters for open addressing; pass 0 otherwise.
+    #[wasm_bindgen(constructor)]
+    pub fn new(kind: &str, capacity: u32) -> Result<DynamicStore, JsValue> {
+        Self::try_new(kind, capacity).map_err(|e| JsValue::from_str(&e))
+    }
+
+    pub fn kind(&self) -> String {
+        self.store().kind().to_string()
+    }
+
+    /// Id assigned by the registry, if it was enabled when this store was created.
+    pub fn registry_id(&self) -> Option<u32> {
+        self.registry_id
+    }
+
+    pub fn insert(&mut self, key: String, value: u32) {
+        if let Some(access) = &mut self.access {
+            access.record_write(&key);
+        }
+        match &mut self.batch {
+            Some(batch) => {
+                batch.insert(key, Some(value));
+            }
+            None => self.apply_insert(key, value),
+        }
+    }
+
+    pub fn get(&mut self, key: &str) -> Option<u32> {
+        if let Some(access) = &mut self.access {
+            access.record_read(key);
+        }
+        self.read(key)
+    }
+
+    pub fn delete(&mut self, key: &str) -> bool {
+        if let Some(access) = &mut self.access {
+            access.record_write(key);
+        }
+        if self.batch.is_some() {
+            let present = self.read(key).is_some();
+            if let Some(batch) = &mut self.batch {
+                batch.insert(key.to_string(), None);
+            }
+            return present;
+        }
+        self.apply_delete(key)
+    }
+
+    /// Number of keys in the structure, not counting an open batch.
+    pub fn len(&self) -> usize {
+        self.store().kv_len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Value of one of the underlying structure's metrics, by field name.
+    pub fn metric(&self, name: &str) -> Option<f64> {
+        self.store()
+            .metrics_snapshot()
+            .into_iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, v)| v)
+    }
+
+    /// Start staging writes. Fails if a batch is already open.
+    pub fn begin_batch(&mut self) -> Result<(), JsValue> {
+        self.try_begin_batch().map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// Apply the open batch, returning the number of keys it changed.
+    pub fn commit(&mut self) -> Result<u32, JsValue> {
+        self.try_commit().map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// Discard the open batch, returning the number of keys it would have changed.
+    pub fn rollback(&mut self) -> Result<u32, JsValue> {
+        self.try_rollback().map_err(|e| JsValue::from_str(&e))
+    }
+
+    pub fn in_batch(&self) -> bool {
+        self.batch.is_some()
+    }
+
+    /// Keys written in the open batch (0 outside a batch).
+    pub fn staged_count(&self) -> usize {
+        self.batch.as_ref().map_or(0, BTreeMap::len)
+    }
+
+    /// A read-only view sharing this structure; see [`FrozenView`].
+    pub fn freeze(&self) -> FrozenView {
+        FrozenView::new(Rc::clone(&self.inner))
+    }
+
+    /// Subscribe `callback` to `"insert"`, `"delete"`, `"resize"` or
+    /// `"rebalance"` events. Returns an id for `off`.
+    ///
+    /// Callbacks receive a plain object with a `type` field plus the event's
+    /// payload (`key`/`value`/`len`, `from`/`to`, or `total`).
+    pub fn on(&self, event: &str, callback: js_sys::Function) -> Result<u32, JsValue> {
+        let kind = EventKind::from_name(event)
+            .ok_or_else(|| JsValue::from_str(&format!("unknown event '{}'", event)))?;
+        Ok(self.events.borrow_mut().subscribe_js(kind, callback))
+    }
+
+    /// Start counting reads and writes per key through this handle, in
+    /// sketches of `capacity` keys each (see [`AccessProfile`]). Restarts
+    /// the counts if tracking was already on.
+    pub fn track_access(&mut self, capacity: u32) {
+        self.access = Some(AccessProfile::new(capacity));
+    }
+
+    /// Stop tracking and drop the counts.
+    pub fn stop_tracking_access(&mut self) {
+        self.access = None;
+    }
+
+    /// The `k` most accessed keys since `track_access`, hottest first;
+    /// empty if tracking is off.
+    pub fn hot_keys(&self, k: u32) -> Vec<HotKey> {
+        self.access
+            .as_ref()
+            .map_or_else(Vec::new, |access| access.hot_keys(k))
+    }
+
+    /// Read/write mix and skew since `track_access`, or `undefined` if
+    /// tracking is off.
+    pub fn access_report(&self, k: u32) -> Option<AccessReport> {
+        self.access.as_ref().map(|access| access.report(k))
+    }
+
+    /// Join with `other` on their shared keys, each valued
+    /// `combiner(key, ours, theirs)`, into a new structure of this one's
+    /// kind. Throws, building nothing, if the combiner throws or returns
+    /// something other than a u32.
+    ///
+    /// `strategy` picks a hash join or a sorted-merge join (see
+    /// [`JoinStrategy`]); the result's metrics show what each cost, e.g.
+    /// probes into a hash table vs. a merge over two trees.
+    ///
+    /// # Example
+    /// ```javascript
+    /// const totals = prices.inner_join(quantities, (item, price, qty) => price * qty, JoinStrategy.Hash);
+    /// const merged = treeA.inner_join(treeB, (k, a, b) => a + b, JoinStrategy.SortedMerge);
+    /// console.log(totals.metrics().probes, merged.metrics().total_comparisons());
+    /// ```
+    pub fn inner_join(
+        &self,
+        other: &DynamicStore,
+        combiner: &js_sys::Function,
+        strategy: JoinStrategy,
+    ) -> Result<JoinResult, JsValue> {
+        join::try_inner_join(self, other, strategy, |key, ours, theirs| {
+            let value = combiner
+                .call3(
+                    &JsValue::UNDEFINED,
+                    &JsValue::from(key),
+                    &JsValue::from(ours),
+                    &JsValue::from(theirs),
+                )
+                .map_err(|e| format!("combiner threw: {:?}", e))?;
+            interop::value_from_number(key, value.as_f64())
+        })
+        .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// Match every key of this structure against `other`: the result map
+    /// holds the shared keys with `other`'s values, and `unmatched()` the
+    /// keys `other` lacks.
+    pub fn left_join(&self, other: &DynamicStore, strategy: JoinStrategy) -> JoinResult {
+        join::left_join(self, other, strategy)
+    }
+
+    /// Remove a listener added with `on`.
+    pub fn off(&self, id: u32) -> bool {
+        self.events.borrow_mut().unsubscribe(id)
+    }
+
+    /// Encode the structure's kind and entries as a MessagePack map
+    /// `{ kind, entries }`, e.g. to persist it. Requires the `msgpack` feature.
+    ///
+    /// # Example
+    /// ```javascript
+    /// await idb.put("stores", store.to_msgpack_snapshot(), "scores");
+    /// const restored = DynamicStore.from_msgpack_snapshot(await idb.get("stores", "scores"));
+    /// ```
+    #[cfg(feature = "msgpack")]
+    pub fn to_msgpack_snapshot(&self) -> Vec<u8> {
+        msgpack::encode_snapshot(&Snapshot::capture(&*self.store()))
+    }
+
+    /// Rebuild a structure from [`to_msgpack_snapshot`](Self::to_msgpack_snapshot)
+    /// output. Throws an `Error` named as in
+    /// [`SnapshotError::name`](crate::snapshot::SnapshotError::name),
+    /// `"SnapshotCorrupt"` if the bytes were damaged. Requires the `msgpack`
+    /// feature.
+    #[cfg(feature = "msgpack")]
+    pub fn from_msgpack_snapshot(bytes: &[u8]) -> Result<DynamicStore, JsValue> {
+        msgpack::decode_snapshot(bytes)
+            .and_then(|snapshot| snapshot.restore())
+            .map(Self::from_store)
+            .map_err(JsValue::from)
+    }
+
+    /// The changes between two [`to_msgpack_snapshot`](Self::to_msgpack_snapshot)
+    /// outputs (inserted, updated and deleted entries) as a checksummed
+    /// MessagePack delta, usually far smaller than the newer snapshot.
+    /// Requires the `msgpack` feature.
+    ///
+    /// # Example
+    /// ```javascript
+    /// // Main thread: ship only what changed since the last sync
+    /// const current = store.to_msgpack_snapshot();
+    /// worker.postMessage(DynamicStore.diff_snapshot(lastSynced, current));
+    /// lastSynced = current;
+    /// // Worker
+    /// onmessage = (e) => replica.apply_delta(e.data);
+    /// ```
+    #[cfg(feature = "msgpack")]
+    pub fn diff_snapshot(older: &[u8], newer: &[u8]) -> Result<Vec<u8>, JsValue> {
+        let older = msgpack::decode_snapshot(older)?;
+        let newer = msgpack::decode_snapshot(newer)?;
+        Ok(msgpack::encode_delta(&SnapshotDelta::between(
+            &older, &newer,
+        )))
+    }
+
+    /// Make the changes in a [`diff_snapshot`](Self::diff_snapshot) delta,
+    /// as ordinary deletes and inserts: they join an open batch and emit
+    /// events. Throws without changing anything if the delta is damaged.
+    /// Requires the `msgpack` feature.
+    #[cfg(feature = "msgpack")]
+    pub fn apply_delta(&mut self, delta: &[u8]) -> Result<(), JsValue> {
+        let delta = msgpack::decode_delta(delta)?;
+        for key in &delta.deleted {
+            self.delete(key);
+        }
+        for (key, value) in delta.updated.into_iter().chain(delta.inserted) {
+            self.insert(key, value);
+        }
+        Ok(())
+    }
+
+    /// A snapshot compressed with LZ4, typically a fraction of the size of
+    /// [`to_msgpack_snapshot`](Self::to_msgpack_snapshot) for string keys.
+    /// Requires the `compression` feature.
+    ///
+    /// # Example
+    /// ```javascript
+    /// const stats = store.snapshot_compression_stats();
+    /// console.log(`${stats.raw_bytes} -> ${stats.compressed_bytes} bytes (${stats.ratio.toFixed(1)}x)`);
+    /// await idb.put("stores", store.to_compressed_snapshot(), "scores");
+    /// ```
+    #[cfg(feature = "compression")]
+    pub fn to_compressed_snapshot(&self) -> Vec<u8> {
+        compression::compress_snapshot(&Snapshot::capture(&*self.store()))
+    }
+
+    /// Rebuild a structure from
+    /// [`to_compressed_snapshot`](Self::to_compressed_snapshot) output.
+    /// Requires the `compression` feature.
+    #[cfg(feature = "compression")]
+    pub fn from_compressed_snapshot(bytes: &[u8]) -> Result<DynamicStore, JsValue> {
+        compression::decompress_snapshot(bytes)
+            .and_then(|snapshot| snapshot.restore())
+            .map(Self::from_store)
+            .map_err(JsValue::from)
+    }
+
+    /// Sizes of the current snapshot uncompressed and compressed. Requires
+    /// the `compression` feature.
+    #[cfg(feature = "compression")]
+    pub fn snapshot_compression_stats(&self) -> SnapshotCompressionStats {
+        compression::stats(&Snapshot::capture(&*self.store()))
+    }
+
+    /// Encrypt a snapshot with XChaCha20-Poly1305 under a 32-byte `key`, so
+    /// persisted user data isn't stored in plaintext. A fresh random nonce
+    /// from `crypto.getRandomValues` is stored with the ciphertext. Requires
+    /// the `crypto` feature.
+    ///
+    /// Derive the key from a passphrase with Web Crypto rather than using
+    /// the passphrase bytes directly.
+    ///
+    /// # Example
+    /// ```javascript
+    /// const material = await crypto.subtle.importKey(
+    ///   "raw", new TextEncoder().encode(passphrase), "PBKDF2", false, ["deriveBits"]);
+    /// const key = new Uint8Array(await crypto.subtle.deriveBits(
+    ///   { name: "PBKDF2", hash: "SHA-256", salt, iterations: 600000 }, material, 256));
+    /// await idb.put("stores", store.to_encrypted_snapshot(key), "scores");
+    /// const restored = DynamicStore.from_encrypted_snapshot(await idb.get("stores", "scores"), key);
+    /// ```
+    #[cfg(feature = "crypto")]
+    pub fn to_encrypted_snapshot(&self, key: &[u8]) -> Result<Vec<u8>, JsValue> {
+        let key = crypto::key(key).map_err(|e| JsValue::from_str(&e))?;
+        let nonce = crypto::random_nonce()?;
+        Ok(crypto::seal_snapshot(
+            &Snapshot::capture(&*self.store()),
+            &key,
+            &nonce,
+        ))
+    }
+
+    /// Decrypt and rebuild a structure from
+    /// [`to_encrypted_snapshot`](Self::to_encrypted_snapshot) output. Throws
+    /// an `Error` named `"SnapshotDecryptionFailed"` if the key is wrong or
+    /// the bytes were altered. Requires the `crypto` feature.
+    #[cfg(feature = "crypto")]
+    pub fn from_encrypted_snapshot(bytes: &[u8], key: &[u8]) -> Result<DynamicStore, JsValue> {
+        let key = crypto::key(key).map_err(|e| JsValue::from_str(&e))?;
+        crypto::open_snapshot(bytes, &key)
+            .and_then(|snapshot| snapshot.restore())
+            .map(Self::from_store)
+            .map_err(JsValue::from)
+    }
+
+    /// A plain-text drawing of the structure: a sideways tree for the
+    /// trees, express lanes for the skip list, an indented tree for the trie
+    /// and bucket occupancy bars for the hash tables.
+    pub fn to_ascii(&self) -> String {
+        self.store().to_ascii()
+    }
+
+    /// Insert queued entries from `job` until `millis` milliseconds have elapsed.
+    ///
+    /// Bulk inserts bypass any open batch and don't emit events.
+    pub fn insert_budgeted(&mut self, job: &mut BulkInsertJob, millis: f64) -> u32 {
+        job.run_for(&mut *self.store_mut(), millis)
+    }
+
+    pub fn memory_report(&self) -> MemoryReport {
+        self.store().memory_report()
+    }
+
+    pub fn shrink_to_fit(&mut self) {
+        self.store_mut().shrink_to_fit();
+    }
+}
+
+impl KvStore for HashMap {
+    fn kind(&self) -> &'static str {
+        self.bucket_mode().kind()
+    }
+
+    fn kv_insert(&mut self, key: String, value: u32) {
+        self.insert(key, value);
+    }
+
+    fn kv_get(&mut self, key: &str) -> Option<u32> {
+        HashMap::get(self, key.to_string())
+    }
+
+    fn kv_delete(&mut self, key: &str) -> bool {
+        self.delete(key.to_string())
+    }
+
+    fn kv_len(&self) -> usize {
+        self.len()
+    }
+
+    fn kv_entries(&self) -> Vec<(String, u32)> {
+        self.pairs()
+    }
+
+    fn metrics_snapshot(&self) -> Vec<(&'static str, f64)> {
+        let m = self.get_metrics();
+        vec![
+            ("total_insertions", m.total_insertions as f64),
+            ("total_collisions", m.total_collisions as f64),
+            ("max_chain_length", m.max_chain_length as f64),
+            ("average_load_factor", m.average_load_factor as f64),
+            ("chain_comparisons", m.chain_comparisons as f64),
+            ("rehash_count", m.rehash_count as f64),
+            ("rehashed_entries", m.rehashed_entries as f64),
+            ("get_calls", m.get_calls as f64),
+            ("contains_key_calls", m.contains_key_calls as f64),
+            ("reserved_unused_bytes", m.reserved_unused_bytes as f64),
+            ("total_lookups", m.total_lookups as f64),
+            ("lookup_chain_traversals", m.lookup_chain_traversals as f64),
+            ("unsuccessful_lookups", m.unsuccessful_lookups as f64),
+            ("total_deletions", m.total_deletions as f64),
+        ]
+    }
+
+    fn memory_report(&self) -> MemoryReport {
+        HashMap::memory_report(self)
+    }
+
+    fn shrink_to_fit(&mut self) {
+        HashMap::shrink_to_fit(self);
+    }
+
+    fn to_ascii(&self) -> String {
+        HashMap::to_ascii(self)
+    }
+
+    fn capacity(&self) -> Option<usize> {
+        Some(self.bucket_count())
+    }
+}
+
+impl KvStore for TwoChoiceHashMap {
+    fn kind(&self) -> &'static str {
+        "two_choice"
+    }
+
+    fn kv_insert(&mut self, key: String, value: u32) {
+        self.insert(key, value);
+    }
+
+    fn kv_get(&mut self, key: &str) -> Option<u32> {
+        self.get(key)
+    }
+
+    fn kv_delete(&mut self, key: &str) -> bool {
+        self.delete(key)
+    }
+
+    fn kv_len(&self) -> usize {
+        self.len()
+    }
+
+    fn kv_entries(&self) -> Vec<(String, u32)> {
+        self.pairs()
+    }
+
+    fn metrics_snapshot(&self) -> Vec<(&'static str, f64)> {
+        let m = self.get_metrics();
+        vec![
+            ("total_insertions", m.total_insertions as f64),
+            ("second_choice_count", m.second_choice_count as f64),
+            ("max_bucket_size", m.max_bucket_size as f64),
+            ("average_load_factor", m.average_load_factor as f64),
+        ]
+    }
+
+    fn memory_report(&self) -> MemoryReport {
+        TwoChoiceHashMap::memory_report(self)
+    }
+
+    fn shrink_to_fit(&mut self) {
+        TwoChoiceHashMap::shrink_to_fit(self);
+    }
+
+    fn to_ascii(&self) -> String {
+        TwoChoiceHashMap::to_ascii(self)
+    }
+
+    fn capacity(&self) -> Option<usize> {
+        Some(self.bucket_count())
+    }
+}
+
+impl KvStore for OpenAddressingHashTable {
+    fn kind(&self) -> &'static str {
+        "open_addressing"
+    }
+
+    fn kv_insert(&mut self, key: String, value: u32) {
+        // Tables built by name never have a max_capacity, so can't fill up
+        if let Err(e) = self.try_insert(key, value) {
+            panic!("{}", e);
+        }
+    }
+
+    fn kv_get(&mut self, key: &str) -> Option<u32> {
+        self.get(key)
+    }
+
+    fn kv_delete(&mut self, key: &str) -> bool {
+        self.delete(key).is_some()
+    }
+
+    fn kv_len(&self) -> usize {
+        self.len() as usize
+    }
+
+    fn kv_entries(&self) -> Vec<(String, u32)> {
+        self.pairs()
+    }
+
+    fn metrics_snapshot(&self) -> Vec<(&'static str, f64)> {
+        let m = self.get_metrics();
+        vec![
+            ("total_insertions", m.total_insertions as f64),
+            ("total_probes", m.total_probes as f64),
+            ("max_probe_length", m.max_probe_length as f64),
+            ("load_factor", m.load_factor as f64),
+            ("clustering_factor", m.clustering_factor as f64),
+            ("tombstone_count", m.tombstone_count as f64),
+            ("resize_count", m.resize_count as f64),
+        ]
+    }
+
+    fn memory_report(&self) -> MemoryReport {
+        OpenAddressingHashTable::memory_report(self)
+    }
+
+    fn shrink_to_fit(&mut self) {
+        OpenAddressingHashTable::shrink_to_fit(self);
+    }
+
+    fn to_ascii(&self) -> String {
+        OpenAddressingHashTable::to_ascii(self)
+    }
+
+    fn capacity(&self) -> Option<usize> {
+        Some(OpenAddressingHashTable::capacity(self) as usize)
+    }
+}
+
+impl KvStore for BinarySearchTree {
+    fn kind(&self) -> &'static str {
+        "bst"
+    }
+
+    fn kv_insert(&mut self, key: String, value: u32) {
+        self.insert(key, value);
+    }
+
+    fn kv_get(&mut self, key: &str) -> Option<u32> {
+        self.get(key.to_string())
+    }
+
+    fn kv_delete(&mut self, key: &str) -> bool {
+        self.delete(key.to_string())
+    }
+
+    fn kv_len(&self) -> usize {
+        self.len()
+    }
+
+    fn kv_entries(&self) -> Vec<(String, u32)> {
+        self.pairs()
+    }
+
+    fn metrics_snapshot(&self) -> Vec<(&'static str, f64)> {
+        let m = self.get_metrics();
+        vec![
+            ("total_insertions", m.total_insertions as f64),
+            ("total_comparisons", m.total_comparisons as f64),
+            ("max_depth", m.max_depth as f64),
+            ("average_depth", m.average_depth as f64),
+        ]
+    }
+
+    fn memory_report(&self) -> MemoryReport {
+        BinarySearchTree::memory_report(self)
+    }
+
+    fn shrink_to_fit(&mut self) {
+        BinarySearchTree::shrink_to_fit(self);
+    }
+
+    fn to_ascii(&self) -> String {
+        BinarySearchTree::to_ascii(self)
+    }
+}
+
+impl KvStore for RedBlackTree {
+    fn kind(&self) -> &'static str {
+        "rbtree"
+    }
+
+    fn kv_insert(&mut self, key: String, value: u32) {
+        self.insert(key, value);
+    }
+
+    fn kv_get(&mut self, key: &str) -> Option<u32> {
+        self.get(key)
+    }
+
+    fn kv_delete(&mut self, key: &str) -> bool {
+        self.delete(key).is_some()
+    }
+
+    fn kv_len(&self) -> usize {
+        self.len() as usize
+    }
+
+    fn kv_entries(&self) -> Vec<(String, u32)> {
+        self.pairs()
+    }
+
+    fn metrics_snapshot(&self) -> Vec<(&'static str, f64)> {
+        let m = self.get_metrics();
+        vec![
+            ("total_insertions", m.total_insertions as f64),
+            ("tree_height", m.tree_height as f64),
+            ("rebalance_count", m.rebalance_count as f64),
+            ("rotation_count", m.rotation_count as f64),
+            ("color_fix_count", m.color_fix_count as f64),
+        ]
+    }
+
+    fn memory_report(&self) -> MemoryReport {
+        RedBlackTree::memory_report(self)
+    }
+
+    fn shrink_to_fit(&mut self) {
+        RedBlackTree::shrink_to_fit(self);
+    }
+
+    fn to_ascii(&self) -> String {
+        RedBlackTree::to_ascii(self)
+    }
+}
+
+impl KvStore for SkipList {
+    fn kind(&self) -> &'static str {
+        "skiplist"
+    }
+
+    fn kv_insert(&mut self, key: String, value: u32) {
+        self.insert(key, value);
+    }
+
+    fn kv_get(&mut self, key: &str) -> Option<u32> {
+        self.search(key)
+    }
+
+    fn kv_delete(&mut self, key: &str) -> bool {
+        self.delete(key).is_some()
+    }
+
+    fn kv_len(&self) -> usize {
+        self.len() as usize
+    }
+
+    fn kv_entries(&self) -> Vec<(String, u32)> {
+        self.pairs()
+    }
+
+    fn metrics_snapshot(&self) -> Vec<(&'static str, f64)> {
+        let m = self.get_metrics();
+        vec![
+            ("total_insertions", m.total_insertions as f64),
+            ("total_searches", m.total_searches as f64),
+            ("search_comparisons", m.search_comparisons as f64),
+            ("average_level", m.average_level as f64),
+            ("max_level", m.max_level as f64),
+        ]
+    }
+
+    fn memory_report(&self) -> MemoryReport {
+        SkipList::memory_report(self)
+    }
+
+    fn shrink_to_fit(&mut self) {
+        SkipList::shrink_to_fit(self);
+    }
+
+    fn to_ascii(&self) -> String {
+        SkipList::to_ascii(self)
+    }
+}
+
+impl KvStore for Trie {
+    fn kind(&self) -> &'static str {
+        "trie"
+    }
+
+    fn kv_insert(&mut self, key: String, value: u32) {
+        self.insert(key, value);
+    }
+
+    fn kv_get(&mut self, key: &str) -> Option<u32> {
+        self.search(key)
+    }
+
+    fn kv_delete(&mut self, key: &str) -> bool {
+        self.delete(key)
+    }
+
+    fn kv_len(&self) -> usize {
+        self.size() as usize
+    }
+
+    fn kv_entries(&self) -> Vec<(String, u32)> {
+        self.pairs()
+    }
+
+    fn metrics_snapshot(&self) -> Vec<(&'static str, f64)> {
+        let m = self.get_metrics();
+        vec![
+            ("total_insertions", m.total_insertions as f64),
+            ("total_searches", m.total_searches as f64),
+            ("node_count", m.node_count as f64),
+            ("max_depth", m.max_depth as f64),
+        ]
+    }
+
+    fn memory_report(&self) -> MemoryReport {
+        Trie::memory_report(self)
+    }
+
+    fn shrink_to_fit(&mut self) {
+        Trie::shrink_to_fit(self);
+    }
+
+    fn to_ascii(&self) -> String {
+        Trie::to_ascii(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_kind_round_trips() {
+        for kind in STORE_KINDS {
+            let mut store = new_store(kind, 16).unwrap();
+            assert_eq!(store.kind(), kind);
+            store.kv_insert("alpha".to_string(), 1);
+            store.kv_insert("beta".to_string(), 2);
+            store.kv_insert("alpha".to_string(), 3);
+            assert_eq!(store.kv_len(), 2, "{}", kind);
+            assert_eq!(store.kv_get("alpha"), Some(3), "{}", kind);
+            assert!(store.kv_delete("beta"), "{}", kind);
+            assert!(!store.kv_delete("beta"), "{}", kind);
+            assert_eq!(store.kv_get("beta"), None, "{}", kind);
+            assert_eq!(store.kv_len(), 1, "{}", kind);
+            let metrics = store.metrics_snapshot();
+            assert_eq!(metrics[0].0, "total_insertions", "{}", kind);
+            assert!(metrics[0].1 >= 2.0, "{}", kind);
+        }
+    }
+
+    #[test]
+    fn test_entries() {
+        let keys = ["pear", "apple", "peach", "fig", "apricot"];
+        for kind in STORE_KINDS.iter().chain(&HASHMAP_VARIANTS) {
+            let mut store = new_store(kind, 0).unwrap();
+            for (i, key) in keys.iter().enumerate() {
+                store.kv_insert(key.to_string(), i as u32);
+            }
+            store.kv_insert("pear".to_string(), 9);
+            store.kv_delete("fig");
+            let mut entries = store.kv_entries();
+            match *kind {
+                "hashmap" | "hashmap_linked" | "hashmap_sorted" => {
+                    let order: Vec<&str> = entries.iter().map(|(k, _)| k.as_str()).collect();
+                    assert_eq!(order, ["pear", "apple", "peach", "apricot"]);
+                }
+                "bst" | "rbtree" | "skiplist" | "trie" => {
+                    assert!(entries.windows(2).all(|w| w[0].0 < w[1].0), "{}", kind)
+                }
+                _ => {}
+            }
+            entries.sort_unstable();
+            let expected = [("apple", 1), ("apricot", 4), ("peach", 2), ("pear", 9)];
+            let expected: Vec<(String, u32)> =
+                expected.iter().map(|&(k, v)| (k.to_string(), v)).collect();
+            assert_eq!(entries, expected, "{}", kind);
+        }
+    }
+
+    #[test]
+    fn test_shrink_after_churn_keeps_contents() {
+        for kind in STORE_KINDS {
+            let mut store = new_store(kind, 1_000).unwrap();
+            for i in 0..1_000 {
+                store.kv_insert(format!("session/{}", i), i);
+            }
+            let loaded = store.memory_report();
+            for i in 0..900 {
+                store.kv_delete(&format!("session/{}", i));
+            }
+            store.shrink_to_fit();
+            let report = store.memory_report();
+            assert!(report.used_bytes < loaded.used_bytes, "{}", kind);
+            assert!(report.reserved_bytes < loaded.reserved_bytes, "{}", kind);
+            assert!(report.used_bytes <= report.reserved_bytes, "{}", kind);
+            assert_eq!(store.kv_len(), 100, "{}", kind);
+            assert_eq!(store.kv_get("session/950"), Some(950), "{}", kind);
+        }
+    }
+
+    #[test]
+    fn test_open_addressing_outgrows_its_hint() {
+        let mut store = DynamicStore::try_new("open_addressing", 0).unwrap();
+        for i in 0..500 {
+            store.insert(format!("k{}", i), i);
+        }
+        assert_eq!(store.len(), 500);
+        assert!(store.metric("resize_count").unwrap() > 0.0);
+    }
+
+    #[test]
+    fn test_hashmap_variants() {
+        let mut comparisons = Vec::new();
+        for kind in ["hashmap"].into_iter().chain(HASHMAP_VARIANTS) {
+            let mut store = new_store(kind, 0).unwrap();
+            assert_eq!(store.kind(), kind);
+            for i in 0..2_000 {
+                store.kv_insert(format!("k{}", i), i);
+            }
+            for i in 0..2_000 {
+                assert_eq!(store.kv_get(&format!("k{}", i)), Some(i), "{}", kind);
+            }
+            assert!(store.kv_delete("k5"), "{}", kind);
+            assert_eq!(store.kv_len(), 1_999, "{}", kind);
+            let count = store
+                .metrics_snapshot()
+                .into_iter()
+                .find(|(name, _)| *name == "chain_comparisons")
+                .map(|(_, v)| v);
+            comparisons.push(count);
+        }
+        // ~8 keys per bucket is already enough for binary search to win
+        assert!(comparisons[2].unwrap() < comparisons[0].unwrap());
+        assert!(comparisons[2].unwrap() < comparisons[1].unwrap());
+        // Two-choice tables don't count chain comparisons
+        assert_eq!(comparisons[3], None);
+    }
+
+    #[test]
+    fn test_unknown_kind() {
+        assert!(new_store("btree", 16).is_none());
+        assert!(DynamicStore::try_new("btree", 0).is_err());
+    }
+
+    #[test]
+    fn test_dynamic_store() {
+        let mut store = DynamicStore::try_new("skiplist", 0).unwrap();
+        assert!(store.is_empty());
+        store.insert("a".to_string(), 1);
+        store.insert("b".to_string(), 2);
+        assert_eq!(store.kind(), "skiplist");
+        assert_eq!(store.get("b"), Some(2));
+        assert!(store.delete("a"));
+        assert_eq!(store.len(), 1);
+        assert_eq!(store.metric("total_insertions"), Some(2.0));
+        assert_eq!(store.metric("missing"), None);
+    }
+
+    #[test]
+    fn test_events_are_shared_across_handles() {
+        use std::cell::RefCell;
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let mut store = DynamicStore::try_new("open_addressing", 0).unwrap();
+        let mut other = store.handle();
+        for kind in [EventKind::Insert, EventKind::Delete, EventKind::Resize] {
+            let log = Rc::clone(&seen);
+            store.subscribe(kind, move |e| log.borrow_mut().push(e.clone()));
+        }
+        other.insert("a".to_string(), 1);
+        assert!(!other.delete("missing"));
+        assert!(other.delete("a"));
+        assert_eq!(
+            seen.borrow()[..2],
+            [
+                StoreEvent::Inserted {
+                    key: "a".to_string(),
+                    value: 1,
+                    len: 1
+                },
+                StoreEvent::Deleted {
+                    key: "a".to_string(),
+                    len: 0
+                },
+            ]
+        );
+        for i in 0..100 {
+            store.insert(format!("k{}", i), i);
+        }
+        let resizes: Vec<_> = seen
+            .borrow()
+            .iter()
+            .filter_map(|e| match e {
+                StoreEvent::Resized { from, to } => Some((*from, *to)),
+                _ => None,
+            })
+            .collect();
+        assert!(!resizes.is_empty());
+        assert!(resizes.iter().all(|(from, to)| to > from));
+    }
+
+    #[test]
+    fn test_listener_can_write_and_unsubscribe() {
+        let store = DynamicStore::try_new("hashmap", 0).unwrap();
+        let mut writer = store.handle();
+        let events = Rc::clone(&store.events);
+        // Mirrors every "src:" insert to a "dst:" key, then stops listening
+        // once three have been copied
+        let id = Rc::new(std::cell::Cell::new(0));
+        let own_id = Rc::clone(&id);
+        let mirrored = Rc::new(std::cell::Cell::new(0));
+        let count = Rc::clone(&mirrored);
+        id.set(store.subscribe(EventKind::Insert, move |e| {
+            if let StoreEvent::Inserted { key, value, .. } = e {
+                if let Some(name) = key.strip_prefix("src:") {
+                    writer.insert(format!("dst:{}", name), *value);
+                    count.set(count.get() + 1);
+                    if count.get() == 3 {
+                        events.borrow_mut().unsubscribe(own_id.get());
+                    }
+                }
+            }
+        }));
+
+        let mut store = store;
+        for i in 0..5 {
+            store.insert(format!("src:{}", i), i);
+        }
+        assert_eq!(mirrored.get(), 3);
+        assert_eq!(store.len(), 8);
+        assert_eq!(store.get("dst:2"), Some(2));
+        assert_eq!(store.get("dst:3"), None);
+        assert!(!store.off(id.get()));
+    }
+
+    #[test]
+    fn test_rebalance_events() {
+        let events = Rc::new(std::cell::Cell::new(0));
+        let latest = Rc::new(std::cell::Cell::new(0));
+        let mut store = DynamicStore::try_new("rbtree", 0).unwrap();
+        let (count, last) = (Rc::clone(&events), Rc::clone(&latest));
+        store.subscribe(EventKind::Rebalance, move |e| {
+            if let StoreEvent::Rebalanced { total } = e {
+                count.set(count.get() + 1);
+                last.set(*total);
+            }
+        });
+        for i in 0..50 {
+            store.insert(format!("k{:02}", i), i);
+        }
+        assert!(events.get() > 0);
+        assert_eq!(latest.get(), events.get());
+        assert_eq!(
+            latest.get() as f64,
+            store.metric("rebalance_count").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_batch_commit_and_rollback() {
+        let mut store = DynamicStore::try_new("rbtree", 0).unwrap();
+        store.insert("keep".to_string(), 1);
+        store.insert("drop".to_string(), 2);
+        let observer = store.handle();
+
+        store.try_begin_batch().unwrap();
+        assert!(store.try_begin_batch().is_err());
+        store.insert("new".to_string(), 3);
+        store.insert("keep".to_string(), 10);
+        assert!(store.delete("drop"));
+        assert!(!store.delete("drop"));
+        assert_eq!(store.get("new"), Some(3));
+        assert_eq!(store.get("drop"), None);
+        assert_eq!(store.staged_count(), 3);
+        // Nothing has reached the structure yet
+        assert_eq!(observer.len(), 2);
+        assert_eq!(store.metric("total_insertions"), Some(2.0));
+
+        assert_eq!(store.try_rollback(), Ok(3));
+        assert!(!store.in_batch());
+        assert_eq!(store.get("keep"), Some(1));
+        assert_eq!(store.get("new"), None);
+        assert!(store.try_rollback().is_err());
+
+        store.try_begin_batch().unwrap();
+        store.insert("new".to_string(), 3);
+        assert!(store.delete("drop"));
+        assert_eq!(store.try_commit(), Ok(2));
+        assert_eq!(observer.len(), 2);
+        assert_eq!(store.get("new"), Some(3));
+        assert_eq!(store.get("drop"), None);
+        assert!(store.try_commit().is_err());
+    }
+}
@@ -1,44 +1,321 @@
-use std::collections::hash_map::DefaultHasher;
-use std::hash::{Hash, Hasher};
+use chain::Chain;
+use clock::now_ms;
+use health::AutoTune;
+use hooks::MutationHooks;
+use js_sys::Function;
+use std::cell::{Cell, RefCell};
+use std::mem::size_of;
+use std::rc::{Rc, Weak};
 use wasm_bindgen::prelude::*;
 
+pub mod access;
+pub use access::{AccessProfile, AccessReport, HotKey};
+
+pub mod advisor;
+pub use advisor::{Candidate, Recommendation};
+
+pub mod aggregated;
+pub use aggregated::AggregatedMap;
+
+pub mod aho_corasick;
+pub use aho_corasick::{AhoCorasick, AhoCorasickMetrics, PatternMatch};
+
+mod ascii;
+
+pub mod async_ops;
+pub use async_ops::BulkSummary;
+
+pub mod benchmark;
+pub use benchmark::{BenchmarkResult, FuzzyBenchmarkResult};
+
+pub mod bitset;
+pub use bitset::BitSet;
+
+pub mod builders;
+pub use builders::{
+    HashMapBuilder, MetricsMode, OpenAddressingBuilder, RehashMode, SkipListBuilder,
+};
+
+pub mod bk_tree;
+pub use bk_tree::{BkMatch, BkTree, BkTreeMetrics};
+
+pub mod bloom;
+pub use bloom::BloomFilter;
+
 pub mod bst;
-pub use bst::{BSTMetrics, BinarySearchTree};
+pub use bst::{BSTMetrics, BinarySearchTree, PathStep};
+
+pub mod bulk;
+pub use bulk::BulkInsertJob;
+
+pub mod cache;
+pub use cache::{SizeClassStats, WeightedLruCache, WeightedLruMetrics};
+
+pub mod cache_policy;
+pub use cache_policy::{CachePolicy, CachePolicyResult, PolicyCache};
+
+pub mod cancel;
+pub use cancel::CancellationToken;
+
+pub mod capacity;
+pub use capacity::CapacityConfig;
+
+mod clock;
+
+pub mod chain;
+pub use chain::{BucketMode, BucketModeStats};
+
+pub mod comparison;
+pub use comparison::ComparisonReport;
+
+pub mod complexity;
+pub use complexity::{ComplexityClass, ComplexityReport};
+
+pub mod compressed_bitmap;
+pub use compressed_bitmap::{CompressedBitmap, CompressedBitmapMetrics};
+
+#[cfg(feature = "compression")]
+pub mod compression;
+#[cfg(feature = "compression")]
+pub use compression::SnapshotCompressionStats;
+
+pub mod crdt;
+pub use crdt::{CrdtMap, CrdtMapMetrics};
+
+#[cfg(feature = "crypto")]
+mod crypto;
+
+mod csv;
+
+pub mod cuckoo_filter;
+pub use cuckoo_filter::{CuckooFilter, CuckooFilterMetrics};
+
+pub mod dependency_graph;
+pub use dependency_graph::{DependencyGraph, DependencyGraphMetrics};
+
+pub mod dynamic_connectivity;
+pub use dynamic_connectivity::{DynamicConnectivity, DynamicConnectivityMetrics};
+
+pub mod event_sourced;
+pub use event_sourced::{EventLogMetrics, EventSourcedStore, LogEvent};
+
+pub mod events;
+pub use events::{EventEmitter, EventKind, StoreEvent};
+
+pub mod faulty;
+pub use faulty::{Fault, FaultConfig, FaultMetrics, FaultyMap};
+
+pub mod fm_index;
+pub use fm_index::{FmIndex, FmIndexMetrics};
+
+pub mod frozen;
+pub use frozen::FrozenView;
+
+pub mod graph;
+pub use graph::{BipartiteGraph, Graph, GraphMetrics, MatchingMetrics, MaxFlow};
+
+pub mod guarded;
+pub use guarded::{GuardedMap, GuardedMapMetrics};
+
+pub mod hashing;
+pub use hashing::HashFunction;
+
+pub mod health;
+pub use health::{Finding, HealthReport, Severity, TuningEvent};
+
+pub mod heavy_hitters;
+pub use heavy_hitters::{HeavyHitter, HeavyHitters, HeavyHittersAccuracy};
+
+pub mod hooks;
+pub use hooks::BucketEvent;
+
+pub mod huffman;
+pub use huffman::HuffmanTree;
+
+pub mod indexed_store;
+pub use indexed_store::IndexedStore;
+
+mod interop;
+
+mod json;
+
+pub mod join;
+pub use join::{JoinMetrics, JoinResult, JoinStrategy};
+
+pub mod key_codec;
+pub use key_codec::{KeyCodec, KeyPart, KeyValue};
+
+pub mod kv_store;
+pub use kv_store::{DynamicStore, KvStore};
+
+pub mod memory;
+pub use memory::{MemoryReport, MemoryUsage};
+
+pub mod membership;
+pub use membership::{FilterBenchmark, MembershipFilter};
+
+pub mod minhash;
+pub use minhash::{MinHash, MinHashLsh};
+
+pub mod mirror;
+pub use mirror::{Divergence, MirroredStore};
 
 pub mod open_addressing;
-pub use open_addressing::{OpenAddressingHashTable, OpenAddressingMetrics};
+pub use open_addressing::{
+    DisplacementReport, OpenAddressingHashTable, OpenAddressingMetrics, SlotDisplacement,
+};
+
+#[cfg(feature = "msgpack")]
+mod msgpack;
+
+pub mod order_maintenance;
+pub use order_maintenance::{OrderMaintenance, OrderMaintenanceMetrics};
+
+pub mod perfect_hash;
+pub use perfect_hash::{MinimalPerfectHash, PerfectHashMetrics, StaticMap};
+
+mod persist;
+
+pub mod progress;
+pub use progress::{Progress, ProgressHook};
+
+pub mod quantile;
+pub use quantile::QuantileSketch;
+
+pub mod query;
+pub use query::QueryService;
+
+pub mod quotient_filter;
+pub use quotient_filter::{QuotientFilter, QuotientFilterMetrics};
+
+pub mod rate_limit;
+pub use rate_limit::{LeakyBucket, RateLimiterMetrics, TokenBucket};
 
 pub mod red_black_tree;
 pub use red_black_tree::{Color, RBTreeMetrics, RedBlackTree};
 
+pub mod registry;
+pub use registry::{RegistryEntry, RegistrySnapshot};
+
+pub mod rooted_tree;
+pub use rooted_tree::{LcaMetrics, RootedTree};
+
+pub mod rng;
+
+pub mod scenarios;
+pub use scenarios::Scenario;
+
+pub mod segment_tree;
+pub use segment_tree::{SegmentTree, SegmentTreeMetrics};
+
+pub mod simhash;
+pub use simhash::SimHash;
+
 pub mod skip_list;
 pub use skip_list::{SkipList, SkipListMetrics};
 
+pub mod snapshot;
+pub use snapshot::{diff_snapshot, Snapshot, SnapshotDelta, SnapshotError};
+
+pub mod sliding_window;
+pub use sliding_window::SlidingWindowCounter;
+
+pub mod suffix_tree;
+pub use suffix_tree::{SuffixTree, SuffixTreeMetrics};
+
+pub mod timer_wheel;
+pub use timer_wheel::{TimerQueueComparison, TimerWheel, TimerWheelMetrics};
+
+pub mod tree_path;
+pub use tree_path::{TreePathMetrics, TreePathQuery};
+
 pub mod trie;
-pub use trie::{Trie, TrieMetrics};
+pub use trie::{FuzzyMatch, Trie, TrieMetrics};
+
+pub mod two_choice;
+pub use two_choice::{TwoChoiceHashMap, TwoChoiceMetrics};
+
+pub mod value_map;
+pub use value_map::{HashMapStr, JsValueMap, SlabMap};
+
+pub mod versioned;
+pub use versioned::{VersionedMap, VersionedMapMetrics};
+
+pub mod workload;
+pub use workload::{Workload, WorkloadRecorder};
+
+pub mod workspace;
+pub use workspace::Workspace;
+
+pub mod xor_filter;
+pub use xor_filter::XorFilter;
 
 // Configuration
-const BUCKET_COUNT: usize = 256;
+pub(crate) const BUCKET_COUNT: usize = 256;
+
+/// Old buckets moved to the new array on each write during an incremental rehash.
+pub(crate) const REHASH_BUCKETS_PER_OP: usize = 4;
+
+/// Load factors outside [MIN_LOAD_FACTOR, MAX_LOAD_FACTOR] are reported by
+/// `analyze()` and corrected by auto-tuning (the minimum only applies to
+/// maps grown past the default bucket count).
+const MAX_LOAD_FACTOR: f64 = 1.0;
+const MIN_LOAD_FACTOR: f64 = 0.05;
 
 /// A simple HashMap using separate chaining collision resolution.
 ///
 /// # Design: Separate Chaining with Vec<Vec<>> Buckets
 /// Each bucket is a Vec of (key, value) pairs. When two keys hash to the same bucket,
 /// they form a "chain" (list) in that bucket. This is simple and teaches collision resolution.
+/// `HashMapBuilder` can swap the Vec for a linked list or a sorted Vec (see [`BucketMode`]).
 ///
 /// # Metrics Collection
 /// Tracks collisions, max chain length, and load factor for benchmarking.
 /// These metrics help us understand performance characteristics in Phase 3.
 ///
 /// # Memory Layout
-/// - Capacity: Fixed 256 buckets
+/// - Capacity: 256 buckets by default, changed by `resize`, or automatically
+///   when built with a growth policy (`with_config` or `HashMapBuilder.growth`)
 /// - Each bucket grows independently as collisions occur
-/// - Total memory = 256 vec headers + sum of all bucket entries
+/// - Total memory = bucket vec headers + sum of all bucket entries
+///
+/// # Incremental Rehashing
+/// With `RehashMode::Incremental`, `resize` keeps the old bucket array and
+/// moves a few of its buckets per insert/delete. Lookups check both arrays
+/// until the migration completes.
 #[wasm_bindgen]
+#[derive(Clone)]
 pub struct HashMap {
-    buckets: Vec<Vec<(String, u32)>>,
+    // Shared with iterators, which only hold weak references; writes go
+    // through `buckets_mut`, which detaches them
+    buckets: Rc<Vec<Chain>>,
+    bucket_mode: BucketMode,
     size: usize,
     metrics: HashMapMetrics,
+    metrics_mode: MetricsMode,
+    // Lookups take &self, so their key comparisons are counted here
+    chain_comparisons: Cell<u32>,
+    get_calls: Cell<u32>,
+    contains_key_calls: Cell<u32>,
+    total_lookups: Cell<u32>,
+    lookup_chain_traversals: Cell<u32>,
+    unsuccessful_lookups: Cell<u32>,
+    rehash_mode: RehashMode,
+    // Array being migrated away from during an incremental rehash; empty otherwise
+    old_buckets: Rc<Vec<Chain>>,
+    // Old buckets below this index have been migrated
+    migrate_cursor: usize,
+    // Sequence number the next new key will get
+    next_sequence: u64,
+    hash_function: HashFunction,
+    auto_tune: AutoTune,
+    // Growth policy; `None` keeps the bucket count fixed
+    growth: Option<CapacityConfig>,
+    hooks: MutationHooks,
+    modifications: Modifications,
+    // What the chains reserve but don't use, kept current by each write
+    // with `MetricsMode::Full` so `get_metrics` needn't scan for it
+    spare_bytes: usize,
 }
 
 /// Metrics collected during HashMap operations.
@@ -48,202 +325,1624 @@ pub struct HashMap {
 /// - total_collisions: How many hit non-empty buckets?
 /// - max_chain_length: What's the longest collision chain?
 /// - average_load_factor: How full is the table?
+/// - chain_comparisons: How many key comparisons did inserts, lookups
+///   and deletes make inside chains? Compares bucket modes directly.
+/// - rehash_count: How many times was the bucket array replaced, by
+///   `resize`, a growth policy or auto-tuning?
+/// - rehashed_entries: How many entries have those rehashes moved?
+/// - hash_function: Which hash produced these numbers?
+/// - get_calls / contains_key_calls: How many lookups fetched a value, and
+///   how many only checked membership?
+/// - total_lookups / unsuccessful_lookups: How many lookups of any kind
+///   (including `get_or_insert_with`), and how many found nothing?
+/// - lookup_chain_traversals: How many of the chain comparisons were made
+///   by those lookups? Divided by `total_lookups`, the average search cost.
+/// - total_deletions: How many deletes removed a key?
+/// - reserved_unused_bytes: How much memory do chains hold on to without
+///   using it, typically after deletes? `compact()` hands it back. Each
+///   write updates it for the chain it touched, except with
+///   `MetricsMode::Counters`, where it reads 0.
 #[wasm_bindgen]
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Default)]
 pub struct HashMapMetrics {
     pub total_insertions: u32,
     pub total_collisions: u32,
     pub max_chain_length: u32,
     pub average_load_factor: f32,
+    pub chain_comparisons: u32,
+    pub rehash_count: u32,
+    pub rehashed_entries: u32,
+    pub hash_function: HashFunction,
+    pub get_calls: u32,
+    pub contains_key_calls: u32,
+    pub reserved_unused_bytes: u32,
+    pub total_lookups: u32,
+    pub lookup_chain_traversals: u32,
+    pub unsuccessful_lookups: u32,
+    pub total_deletions: u32,
+}
+
+#[wasm_bindgen]
+impl HashMapMetrics {
+    /// Average chain comparisons per lookup (`lookup_chain_traversals /
+    /// total_lookups`), the figure bucket modes are compared on; 0 before
+    /// any lookup.
+    pub fn comparisons_per_lookup(&self) -> f64 {
+        if self.total_lookups == 0 {
+            0.0
+        } else {
+            self.lookup_chain_traversals as f64 / self.total_lookups as f64
+        }
+    }
+}
+
+/// What one [`HashMap::insert_many`] call did.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct BatchInsertStats {
+    /// Keys that weren't in the map before
+    pub inserted: u32,
+    /// Keys that were, whose values were replaced
+    pub updated: u32,
+    /// New keys that landed in a non-empty bucket
+    pub collisions: u32,
+    pub chain_comparisons: u32,
+    /// Bucket array replacements the batch triggered
+    pub rehashes: u32,
+    /// Longest chain after the batch
+    pub max_chain_length: u32,
+}
+
+/// One (key, value) pair stored in a HashMap bucket.
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct BucketEntry {
+    pub key: String,
+    pub value: u32,
+}
+
+/// Internal: Writes to a [`HashMap`]'s entries so far, shared with its
+/// iterators so they can tell their position went stale. A cloned map
+/// counts its own writes.
+#[derive(Default)]
+struct Modifications(Rc<Cell<u64>>);
+
+impl Modifications {
+    fn bump(&self) {
+        self.0.set(self.0.get() + 1);
+    }
+}
+
+impl Clone for Modifications {
+    fn clone(&self) -> Modifications {
+        Modifications(Rc::new(Cell::new(self.0.get())))
+    }
+}
+
+/// Where a [`HashMapIter`] is in the map it walks.
+struct Cursor {
+    // Weak, so the map's next write moves its buckets rather than copying
+    // them; the iterator then sees the stale count and stops
+    buckets: Weak<Vec<Chain>>,
+    old_buckets: Weak<Vec<Chain>>,
+    modifications: Rc<Cell<u64>>,
+    expected: u64,
+    // Index into the current bucket array, then into the old one
+    bucket: usize,
+    offset: usize,
+}
+
+/// An iterator over a [`HashMap`], returned by `iter()`, that hands out
+/// one entry at a time without copying the map. It follows the JS
+/// iterator protocol, so `for...of` and spreads work on it directly.
+///
+/// Entries come in bucket order. Any write to the map (an insert, even
+/// one only updating a value, a delete, `resize`, `clear`) ends the
+/// iteration with an error rather than skipping or repeating entries, and
+/// so does freeing the map.
+///
+/// # Example
+/// ```javascript
+/// for (const [k, v] of map.iter()) console.log(k, v);
+/// const it = map.iter();
+/// it.next(); // { value: ["a", 1], done: false }, ... { value: undefined, done: true }
+/// ```
+#[wasm_bindgen]
+pub struct HashMapIter {
+    // Shared with the handle `[Symbol.iterator]()` returns
+    cursor: Rc<RefCell<Cursor>>,
+}
+
+impl HashMapIter {
+    /// The next entry, or `None` once every entry has been returned.
+    pub fn try_next(&mut self) -> Result<Option<(String, u32)>, String> {
+        let mut cursor = self.cursor.borrow_mut();
+        if cursor.modifications.get() != cursor.expected {
+            return Err("HashMap was modified during iteration".to_string());
+        }
+        let (Some(buckets), Some(old_buckets)) =
+            (cursor.buckets.upgrade(), cursor.old_buckets.upgrade())
+        else {
+            return Err("HashMap was freed during iteration".to_string());
+        };
+        while let Some(chain) = buckets
+            .get(cursor.bucket)
+            .or_else(|| old_buckets.get(cursor.bucket - buckets.len()))
+        {
+            if let Some((key, value)) = chain.nth(cursor.offset) {
+                cursor.offset += 1;
+                return Ok(Some((key.to_string(), value)));
+            }
+            cursor.bucket += 1;
+            cursor.offset = 0;
+        }
+        Ok(None)
+    }
+}
+
+#[wasm_bindgen]
+impl HashMapIter {
+    /// `{ value: [key, value], done: false }` for the next entry, then
+    /// `{ value: undefined, done: true }`. Throws if the map changed since
+    /// `iter()`.
+    #[wasm_bindgen(js_name = next)]
+    pub fn next_result(&mut self) -> Result<js_sys::Object, JsValue> {
+        let entry = self.try_next().map_err(|e| JsValue::from_str(&e))?;
+        let result = js_sys::Object::new();
+        let value = entry.map_or(JsValue::UNDEFINED, |(key, value)| {
+            js_sys::Array::of2(&JsValue::from_str(&key), &JsValue::from(value)).into()
+        });
+        let done = value.is_undefined();
+        let _ = js_sys::Reflect::set(&result, &"value".into(), &value);
+        let _ = js_sys::Reflect::set(&result, &"done".into(), &done.into());
+        Ok(result)
+    }
+
+    /// This iterator again, sharing its position, so it is iterable.
+    #[wasm_bindgen(js_name = "[Symbol.iterator]")]
+    pub fn iterator(&self) -> HashMapIter {
+        HashMapIter {
+            cursor: Rc::clone(&self.cursor),
+        }
+    }
+}
+
+impl HashMap {
+    /// Internal: The bucket array for writing, detached from any iterator.
+    fn buckets_mut(&mut self) -> &mut Vec<Chain> {
+        self.modifications.bump();
+        if Rc::strong_count(&self.buckets) > 1 {
+            // Still shared with a clone: copying tightens every chain
+            Rc::make_mut(&mut self.buckets);
+            self.recount_spare_bytes();
+        }
+        Rc::make_mut(&mut self.buckets)
+    }
+
+    /// Internal: `buckets_mut` for the array an incremental rehash is
+    /// migrating away from.
+    fn old_buckets_mut(&mut self) -> &mut Vec<Chain> {
+        self.modifications.bump();
+        if Rc::strong_count(&self.old_buckets) > 1 {
+            Rc::make_mut(&mut self.old_buckets);
+            self.recount_spare_bytes();
+        }
+        Rc::make_mut(&mut self.old_buckets)
+    }
+
+    /// Internal: Run `write` on chain `idx` of the current array, or of the
+    /// old one if `old`, moving `spare_bytes` by the change in its slack.
+    fn write_chain<R>(&mut self, old: bool, idx: usize, write: impl FnOnce(&mut Chain) -> R) -> R {
+        let track = self.metrics_mode == MetricsMode::Full;
+        let chains = if old {
+            self.old_buckets_mut()
+        } else {
+            self.buckets_mut()
+        };
+        let chain = &mut chains[idx];
+        let before = if track { chain.slack_bytes() } else { 0 };
+        let result = write(chain);
+        let after = if track { chain.slack_bytes() } else { 0 };
+        self.spare_bytes = self.spare_bytes + after - before;
+        result
+    }
+
+    /// Internal: Recompute `spare_bytes` by scanning every chain.
+    fn recount_spare_bytes(&mut self) {
+        self.spare_bytes = match self.metrics_mode {
+            MetricsMode::Full => self.memory_report().slack_bytes(),
+            MetricsMode::Counters => 0,
+        };
+    }
+
+    /// Internal: Compute hash of a string key.
+    ///
+    /// Uses Rust's standard DefaultHasher (SipHash-like) unless built with
+    /// another [`HashFunction`]. Good distribution, prevents algorithmic
+    /// attacks.
+    fn hash_key(&self, key: &str) -> u64 {
+        self.hash_function.hash(key)
+    }
+
+    /// Internal: Get bucket index from hash.
+    ///
+    /// Maps 64-bit hash to bucket index [0, bucket_count).
+    /// Uses modulo: simple, effective, cache-friendly.
+    fn bucket_index(&self, hash: u64) -> usize {
+        (hash as usize) % self.buckets.len()
+    }
+
+    /// Internal: Empty map with `bucket_count` chains (see [`HashMapBuilder`]).
+    pub(crate) fn with_layout(
+        bucket_count: usize,
+        bucket_mode: BucketMode,
+        metrics_mode: MetricsMode,
+    ) -> HashMap {
+        HashMap {
+            buckets: Rc::new((0..bucket_count).map(|_| Chain::new(bucket_mode)).collect()),
+            bucket_mode,
+            size: 0,
+            metrics: HashMapMetrics::default(),
+            metrics_mode,
+            chain_comparisons: Cell::new(0),
+            get_calls: Cell::new(0),
+            contains_key_calls: Cell::new(0),
+            total_lookups: Cell::new(0),
+            lookup_chain_traversals: Cell::new(0),
+            unsuccessful_lookups: Cell::new(0),
+            rehash_mode: RehashMode::AllAtOnce,
+            old_buckets: Rc::default(),
+            migrate_cursor: 0,
+            next_sequence: 0,
+            hash_function: HashFunction::SipHash,
+            auto_tune: AutoTune::default(),
+            growth: None,
+            hooks: MutationHooks::default(),
+            modifications: Modifications::default(),
+            spare_bytes: 0,
+        }
+    }
+
+    /// [`HashMap::get_or_insert_with`] for a Rust closure.
+    pub fn try_get_or_insert_with(
+        &mut self,
+        key: String,
+        default_fn: impl FnOnce(&str) -> Result<u32, String>,
+    ) -> Result<u32, String> {
+        self.get_calls.set(self.get_calls.get() + 1);
+        let hash = self.hash_key(&key);
+        if let Some(value) = self.counted_lookup(&key, hash) {
+            return Ok(value);
+        }
+        let value = default_fn(&key)?;
+        self.insert_hashed(key, value, hash);
+        Ok(value)
+    }
+
+    /// [`HashMap::insert_many`] with a Rust error.
+    pub fn try_insert_many(
+        &mut self,
+        keys: Vec<String>,
+        values: Vec<u32>,
+    ) -> Result<BatchInsertStats, String> {
+        if keys.len() != values.len() {
+            return Err(format!(
+                "keys and values differ in length ({} vs {})",
+                keys.len(),
+                values.len()
+            ));
+        }
+        Ok(self.insert_batch(keys.into_iter().zip(values)))
+    }
+
+    /// [`HashMap::merge`] with a Rust resolver, called as
+    /// `resolver(key, ours, theirs)`.
+    pub fn try_merge(
+        &mut self,
+        other: &HashMap,
+        mut resolver: impl FnMut(&str, u32, u32) -> Result<u32, String>,
+    ) -> Result<BatchInsertStats, String> {
+        // Resolve every conflict before writing, so a failing resolver
+        // leaves the map as it was
+        let mut entries = other.pairs();
+        for (key, value) in &mut entries {
+            if let Some(ours) = self.lookup(key) {
+                *value = resolver(key, ours, *value)?;
+            }
+        }
+        Ok(self.insert_batch(entries))
+    }
+
+    /// Internal: Take `key` out of its chain, firing the delete hook, but
+    /// leave resizing to the caller.
+    fn remove_key(&mut self, key: String) -> bool {
+        let hash = self.hash_key(&key);
+        let idx = self.bucket_index(hash);
+        let mut comparisons = 0;
+        let mut removed = self
+            .write_chain(false, idx, |chain| chain.remove(&key, &mut comparisons))
+            .is_some();
+        let mut written = (idx, self.buckets[idx].len());
+        if !removed && !self.old_buckets.is_empty() {
+            let old = (hash as usize) % self.old_buckets.len();
+            removed = self
+                .write_chain(true, old, |chain| chain.remove(&key, &mut comparisons))
+                .is_some();
+            written = (old, self.old_buckets[old].len());
+        }
+        self.count_comparisons(comparisons);
+        if removed {
+            self.size -= 1;
+            self.metrics.total_deletions += 1;
+            let (bucket, chain_length) = written;
+            if self.hooks.delete.is_some() {
+                let event = BucketEvent {
+                    key,
+                    bucket,
+                    chain_length,
+                };
+                MutationHooks::fire(&self.hooks.delete, &event);
+            }
+        }
+        removed
+    }
+
+    /// [`HashMap::retain`] with a Rust predicate. Every entry is asked
+    /// about, in insertion order, before any is removed, so a failing
+    /// predicate leaves the map as it was.
+    pub fn try_retain(
+        &mut self,
+        mut keep: impl FnMut(&str, u32) -> Result<bool, String>,
+    ) -> Result<u32, String> {
+        let mut doomed = Vec::new();
+        for (key, value) in self.pairs() {
+            if !keep(&key, value)? {
+                doomed.push(key);
+            }
+        }
+        self.migrate(usize::MAX, &mut ProgressHook::none());
+        let removed = doomed.len() as u32;
+        for key in doomed {
+            self.remove_key(key);
+        }
+        if removed > 0 {
+            self.apply_growth(true);
+            self.tune();
+        }
+        Ok(removed)
+    }
+
+    /// Internal: Insert every entry, reporting what the batch did.
+    fn insert_batch(
+        &mut self,
+        entries: impl IntoIterator<Item = (String, u32)>,
+    ) -> BatchInsertStats {
+        let before = self.get_metrics();
+        let mut count = 0;
+        for (key, value) in entries {
+            self.insert(key, value);
+            count += 1;
+        }
+        let after = self.get_metrics();
+        let inserted = after.total_insertions - before.total_insertions;
+        BatchInsertStats {
+            inserted,
+            updated: count - inserted,
+            collisions: after.total_collisions - before.total_collisions,
+            chain_comparisons: after
+                .chain_comparisons
+                .wrapping_sub(before.chain_comparisons),
+            rehashes: after.rehash_count - before.rehash_count,
+            max_chain_length: after.max_chain_length,
+        }
+    }
+
+    /// Call `hook` on every `"insert"` (updates included), `"collision"`
+    /// (a new key landing in a non-empty bucket, just before its insert
+    /// event) or `"delete"` of a present key, replacing any hook already
+    /// set for that event.
+    pub fn try_on(
+        &mut self,
+        event: &str,
+        hook: impl Fn(&BucketEvent) + 'static,
+    ) -> Result<(), String> {
+        let slot = match event {
+            "insert" => &mut self.hooks.insert,
+            "collision" => &mut self.hooks.collision,
+            "delete" => &mut self.hooks.delete,
+            _ => return Err(format!("unknown HashMap event '{}'", event)),
+        };
+        *slot = Some(Rc::new(hook));
+        Ok(())
+    }
+
+    /// Internal: Overwrite the metric counters, e.g. with saved ones.
+    /// Gauges are left as computed from the buckets.
+    pub(crate) fn restore_counters(&mut self, metrics: HashMapMetrics) {
+        self.metrics.total_insertions = metrics.total_insertions;
+        self.metrics.total_collisions = metrics.total_collisions;
+        self.metrics.rehash_count = metrics.rehash_count;
+        self.metrics.rehashed_entries = metrics.rehashed_entries;
+        self.metrics.total_deletions = metrics.total_deletions;
+        self.chain_comparisons.set(metrics.chain_comparisons);
+        self.get_calls.set(metrics.get_calls);
+        self.contains_key_calls.set(metrics.contains_key_calls);
+        self.total_lookups.set(metrics.total_lookups);
+        self.lookup_chain_traversals
+            .set(metrics.lookup_chain_traversals);
+        self.unsuccessful_lookups.set(metrics.unsuccessful_lookups);
+    }
+
+    pub(crate) fn set_hash_function(&mut self, hash_function: HashFunction) {
+        self.hash_function = hash_function;
+        self.metrics.hash_function = hash_function;
+    }
+
+    /// Internal: Resize automatically following `config`, which is assumed valid.
+    pub(crate) fn set_growth(&mut self, config: CapacityConfig) {
+        self.growth = Some(config);
+    }
+
+    pub(crate) fn set_rehash_mode(&mut self, mode: RehashMode) {
+        self.rehash_mode = mode;
+    }
+
+    /// Internal: Find `key`'s value, counting comparisons but not the call.
+    fn lookup(&self, key: &str) -> Option<u32> {
+        self.lookup_hashed(key, self.hash_key(key))
+    }
+
+    /// Internal: A lookup on behalf of a caller, counted in the lookup
+    /// metrics.
+    fn counted_lookup(&self, key: &str, hash: u64) -> Option<u32> {
+        let before = self.chain_comparisons.get();
+        let value = self.lookup_hashed(key, hash);
+        let traversed = self.chain_comparisons.get().wrapping_sub(before);
+        self.total_lookups.set(self.total_lookups.get() + 1);
+        self.lookup_chain_traversals
+            .set(self.lookup_chain_traversals.get() + traversed);
+        if value.is_none() {
+            self.unsuccessful_lookups
+                .set(self.unsuccessful_lookups.get() + 1);
+        }
+        value
+    }
+
+    /// Internal: `lookup` with the key already hashed.
+    fn lookup_hashed(&self, key: &str, hash: u64) -> Option<u32> {
+        let mut comparisons = 0;
+        let value = self.find(key, hash, &mut comparisons);
+        self.count_comparisons(comparisons);
+        value
+    }
+
+    /// Internal: Find `key`'s value without touching any metric, for
+    /// wrappers whose own bookkeeping shouldn't show up as the caller's
+    /// lookups.
+    pub(crate) fn peek(&self, key: &str) -> Option<u32> {
+        self.find(key, self.hash_key(key), &mut 0)
+    }
+
+    fn find(&self, key: &str, hash: u64, comparisons: &mut u32) -> Option<u32> {
+        let idx = self.bucket_index(hash);
+        let mut value = self.buckets[idx].get(key, comparisons);
+        if value.is_none() && !self.old_buckets.is_empty() {
+            let old = (hash as usize) % self.old_buckets.len();
+            value = self.old_buckets[old].get(key, comparisons);
+        }
+        value
+    }
+
+    /// Internal: `insert` with the key already hashed.
+    fn insert_hashed(&mut self, key: String, value: u32, hash: u64) {
+        self.migrate(REHASH_BUCKETS_PER_OP, &mut ProgressHook::none());
+        let mut comparisons = 0;
+
+        // Mid-rehash, the key may still be waiting in its old bucket; it
+        // keeps its place in insertion order when moved
+        let mut moved = None;
+        if !self.old_buckets.is_empty() {
+            let old = (hash as usize) % self.old_buckets.len();
+            moved = self.write_chain(true, old, |chain| chain.remove(&key, &mut comparisons));
+        }
+
+        let idx = self.bucket_index(hash);
+        let event_key = self.hooks.any().then(|| key.clone());
+        let sequence = moved.unwrap_or(self.next_sequence);
+        // A non-empty bucket means a collision, unless the key is already
+        // there and this is just an update
+        let (was_collision, is_new, chain_length) = self.write_chain(false, idx, |bucket| {
+            let was_collision = !bucket.is_empty();
+            let is_new = bucket.insert(key, value, sequence, &mut comparisons);
+            (was_collision, is_new, bucket.len())
+        });
+        self.count_comparisons(comparisons);
+        let is_new = is_new && moved.is_none();
+        if is_new {
+            self.size += 1;
+            self.next_sequence += 1;
+            self.update_metrics(was_collision);
+            self.apply_growth(false);
+            self.tune();
+        }
+        if let Some(key) = event_key {
+            let event = BucketEvent {
+                key,
+                bucket: idx,
+                chain_length,
+            };
+            if is_new && was_collision {
+                MutationHooks::fire(&self.hooks.collision, &event);
+            }
+            MutationHooks::fire(&self.hooks.insert, &event);
+        }
+    }
+
+    /// Internal: Add to the chain comparison count.
+    fn count_comparisons(&self, comparisons: u32) {
+        self.chain_comparisons
+            .set(self.chain_comparisons.get() + comparisons);
+    }
+
+    /// Internal: Move up to `count` old buckets into the current array,
+    /// dropping the old array once it is empty. `progress` counts old
+    /// buckets moved out of the whole old array.
+    fn migrate(&mut self, count: usize, progress: &mut ProgressHook) {
+        if self.old_buckets.is_empty() {
+            return;
+        }
+        let start = now_ms();
+        let total = self.old_buckets.len();
+        let end = self.migrate_cursor.saturating_add(count).min(total);
+        let mut ignored = 0;
+        for old in self.migrate_cursor..end {
+            for (key, value, sequence) in self.write_chain(true, old, Chain::drain) {
+                self.metrics.rehashed_entries += 1;
+                let idx = self.bucket_index(self.hash_key(&key));
+                self.write_chain(false, idx, |chain| {
+                    chain.insert(key, value, sequence, &mut ignored)
+                });
+            }
+            if progress.is_active() {
+                progress.update(old as u32 + 1, Some(total as u32), now_ms() - start);
+            }
+        }
+        self.migrate_cursor = end;
+        if self.migrate_cursor == total {
+            self.old_buckets = Rc::default();
+            self.migrate_cursor = 0;
+        }
+        self.refresh_gauges();
+    }
+
+    /// Internal: Every entry with its insertion sequence number, in bucket
+    /// order, including any still waiting in the old array.
+    fn sequenced_entries(&self) -> Vec<(&str, u32, u64)> {
+        self.buckets
+            .iter()
+            .chain(self.old_buckets.iter())
+            .flat_map(Chain::entries)
+            .collect()
+    }
+
+    /// Internal: Every entry, oldest key first.
+    pub(crate) fn pairs(&self) -> Vec<(String, u32)> {
+        let mut entries = self.sequenced_entries();
+        entries.sort_unstable_by_key(|&(_, _, sequence)| sequence);
+        entries
+            .into_iter()
+            .map(|(key, value, _)| (key.to_string(), value))
+            .collect()
+    }
+
+    /// Internal: Resize when the growth policy says the load factor left its
+    /// range. Waits for an incremental rehash to finish first, so a write
+    /// never has to complete one.
+    fn apply_growth(&mut self, deleted: bool) {
+        let Some(config) = self.growth else {
+            return;
+        };
+        if self.is_rehashing() {
+            return;
+        }
+        let (buckets, len) = (self.buckets.len() as u32, self.size as u32);
+        let target = if deleted {
+            config.shrink_target(buckets, len)
+        } else {
+            config.grow_target(buckets, len)
+        };
+        if let Some(target) = target {
+            self.resize(target);
+        }
+    }
+
+    /// Internal: Count a write and, with auto-tuning on, resize when the
+    /// load factor leaves the range `analyze()` accepts.
+    fn tune(&mut self) {
+        if !self.auto_tune.tick() {
+            return;
+        }
+        let buckets = self.buckets.len();
+        let load = self.size as f64 / buckets as f64;
+        let (target, reason) = if load > MAX_LOAD_FACTOR {
+            (self.size * 2, "high_load_factor")
+        } else if load < MIN_LOAD_FACTOR && buckets > BUCKET_COUNT {
+            ((self.size * 2).max(BUCKET_COUNT), "oversized")
+        } else {
+            return;
+        };
+        self.resize(target as u32);
+        self.auto_tune.record(
+            "resize",
+            reason,
+            format!("{} -> {} buckets", buckets, target),
+        );
+    }
+
+    /// Internal: Update metrics after insertion.
+    ///
+    /// Recalculates:
+    /// - total_insertions: always increments
+    /// - total_collisions: increments if inserting to non-empty bucket
+    /// - max_chain_length: maximum chain length in any bucket (full mode only)
+    /// - average_load_factor: size / capacity
+    fn update_metrics(&mut self, was_collision: bool) {
+        self.metrics.total_insertions += 1;
+        if was_collision {
+            self.metrics.total_collisions += 1;
+        }
+        self.refresh_gauges();
+    }
+
+    /// Internal: Recalculate max chain length and load factor.
+    fn refresh_gauges(&mut self) {
+        // Recalculate max chain length
+        if self.metrics_mode == MetricsMode::Full {
+            self.metrics.max_chain_length = self
+                .buckets
+                .iter()
+                .map(|bucket| bucket.len() as u32)
+                .max()
+                .unwrap_or(0);
+        }
+
+        // Recalculate load factor
+        self.metrics.average_load_factor = self.size as f32 / self.buckets.len() as f32;
+    }
+}
+
+impl HashMap {
+    /// [`HashMap::resize`], reporting old buckets moved to `progress`.
+    pub fn resize_reporting(&mut self, bucket_count: u32, progress: &mut ProgressHook) {
+        self.migrate(usize::MAX, &mut ProgressHook::none());
+        let fresh = (0..bucket_count.max(1))
+            .map(|_| Chain::new(self.bucket_mode))
+            .collect();
+        self.modifications.bump();
+        self.old_buckets = std::mem::replace(&mut self.buckets, Rc::new(fresh));
+        self.migrate_cursor = 0;
+        self.metrics.rehash_count += 1;
+        if self.rehash_mode == RehashMode::AllAtOnce {
+            self.migrate(usize::MAX, progress);
+        } else {
+            self.refresh_gauges();
+        }
+    }
+
+    /// [`HashMap::rehash_step`], reporting to `progress`.
+    pub fn rehash_step_reporting(&mut self, buckets: u32, progress: &mut ProgressHook) {
+        self.migrate(buckets as usize, progress);
+    }
+
+    /// [`HashMap::shrink_to_fit`], reporting buckets shrunk to `progress`.
+    /// A pending incremental rehash is finished first, unreported.
+    pub fn shrink_to_fit_reporting(&mut self, progress: &mut ProgressHook) {
+        self.migrate(usize::MAX, &mut ProgressHook::none());
+        let start = now_ms();
+        let total = self.buckets.len() as u32;
+        for (i, bucket) in self.buckets_mut().iter_mut().enumerate() {
+            bucket.shrink();
+            if progress.is_active() {
+                progress.update(i as u32 + 1, Some(total), now_ms() - start);
+            }
+        }
+        self.recount_spare_bytes();
+    }
+
+    /// [`HashMap::compact`], reporting buckets shrunk to `progress`.
+    pub fn compact_reporting(&mut self, progress: &mut ProgressHook) -> usize {
+        let before = self.memory_report().reserved_bytes;
+        self.shrink_to_fit_reporting(progress);
+        before.saturating_sub(self.memory_report().reserved_bytes)
+    }
+
+    /// Create a map that resizes itself following `config`; see `with_config`.
+    pub fn try_with_config(config: CapacityConfig) -> Result<HashMap, String> {
+        HashMapBuilder::new()
+            .bucket_count(config.min_capacity())
+            .growth(&config)
+            .try_build()
+    }
 }
 
-impl HashMap {
-    /// Internal: Compute hash of a string key.
+impl Default for HashMap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[wasm_bindgen]
+impl HashMap {
+    /// Create a map that resizes itself following `config`: it starts at
+    /// the config's minimum bucket count, grows once `len / buckets`
+    /// exceeds the max load factor, and with shrink-on-delete shrinks below
+    /// the min load factor. `get_metrics().rehash_count` counts the resizes.
+    ///
+    /// # Example
+    /// ```javascript
+    /// const map = HashMap.with_config(new CapacityConfig().with_max_load_factor(0.75));
+    /// for (let i = 0; i < 10000; i++) map.insert(`k${i}`, i);
+    /// map.bucket_count(); // 16384
+    /// map.get_metrics().rehash_count; // 11
+    /// ```
+    pub fn with_config(config: &CapacityConfig) -> Result<HashMap, JsValue> {
+        Self::try_with_config(*config).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// Create an empty map with `bucket_count` buckets (at least 1) that
+    /// never resizes on its own.
+    ///
+    /// # Example
+    /// ```javascript
+    /// const map = HashMap.with_capacity(4096);
+    /// ```
+    pub fn with_capacity(bucket_count: u32) -> Result<HashMap, JsValue> {
+        HashMapBuilder::new().bucket_count(bucket_count).build()
+    }
+
+    /// Create a map from an options object, for sweeping settings in
+    /// benchmarks. Every property is optional:
+    ///
+    /// - `bucket_count`: starting buckets (default 256)
+    /// - `growth_factor`: multiplier per resize, e.g. 2 or 1.5
+    /// - `max_load_factor`: grow once `len / buckets` exceeds this (default 0.75)
+    /// - `min_load_factor`: with `shrink_on_delete`, shrink below this (default 0.2)
+    /// - `max_bucket_count`: never grow past this
+    /// - `shrink_on_delete`: shrink as entries are deleted (default false)
+    ///
+    /// Any property besides `bucket_count` turns on automatic resizing, with
+    /// `bucket_count` as the floor. Unknown properties throw.
+    ///
+    /// # Example
+    /// ```javascript
+    /// for (const growth_factor of [2, 1.5]) {
+    ///   const map = HashMap.with_options({ bucket_count: 16, growth_factor });
+    ///   // ...insert, then compare map.get_metrics().rehash_count
+    /// }
+    /// ```
+    pub fn with_options(options: &js_sys::Object) -> Result<HashMap, JsValue> {
+        HashMapBuilder::try_from_options(&interop::option_entries(options))
+            .and_then(|builder| builder.try_build())
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// The growth policy in effect, or `undefined` for a fixed bucket count.
+    pub fn capacity_config(&self) -> Option<CapacityConfig> {
+        self.growth
+    }
+
+    /// Create a new empty HashMap with 256 buckets.
+    ///
+    /// # Memory
+    /// Allocates space for 256 empty Vec buckets.
+    /// Each bucket grows as collisions occur.
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> HashMap {
+        HashMap::with_layout(BUCKET_COUNT, BucketMode::Vec, MetricsMode::Full)
+    }
+
+    /// Insert a key-value pair into the HashMap.
+    ///
+    /// # Behavior
+    /// - If key already exists: update the value (not counted as collision)
+    /// - If key doesn't exist: add to bucket (collision if bucket non-empty)
+    ///
+    /// # Time Complexity: O(n) worst case
+    /// Where n = length of collision chain in that bucket.
+    /// Average case O(1) with good hash function and load factor.
+    ///
+    /// # Example
+    /// ```javascript
+    /// const map = new HashMap();
+    /// map.insert("hello", 42);
+    /// ```
+    pub fn insert(&mut self, key: String, value: u32) {
+        let hash = self.hash_key(&key);
+        self.insert_hashed(key, value, hash);
+    }
+
+    /// The value for `key`, first inserting `default_fn(key)` if it is
+    /// absent. The key is hashed once, where `get` then `insert` from JS
+    /// hashes it twice, and `default_fn` only runs on a miss. Throws if it
+    /// throws or returns something that isn't a valid value, leaving the
+    /// map unchanged.
+    ///
+    /// # Example
+    /// ```javascript
+    /// const id = ids.get_or_insert_with(name, () => nextId++);
+    /// ```
+    pub fn get_or_insert_with(
+        &mut self,
+        key: String,
+        default_fn: &js_sys::Function,
+    ) -> Result<u32, JsValue> {
+        self.try_get_or_insert_with(key, |key| {
+            let value = default_fn
+                .call1(&JsValue::UNDEFINED, &JsValue::from(key))
+                .map_err(|e| format!("default_fn threw: {:?}", e))?;
+            interop::value_from_number(key, value.as_f64())
+        })
+        .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// Insert `keys[i] -> values[i]` for every `i` in one call, so a large
+    /// load crosses the JS boundary once instead of per pair. Nothing is
+    /// inserted if the arrays differ in length.
+    ///
+    /// # Example
+    /// ```javascript
+    /// const stats = map.insert_many(keys, new Uint32Array(values));
+    /// console.log(stats.inserted, stats.collisions, stats.rehashes);
+    /// ```
+    pub fn insert_many(
+        &mut self,
+        keys: Vec<String>,
+        values: Vec<u32>,
+    ) -> Result<BatchInsertStats, JsValue> {
+        self.try_insert_many(keys, values)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// Fold `other`'s entries into this map in its insertion order. For a
+    /// key in both, `resolver(key, ours, theirs)` returns the value to
+    /// keep; it runs for every conflict before anything is written, so if
+    /// it throws or returns an invalid value the map is unchanged.
+    ///
+    /// The merged-in entries are counted in this map's metrics like any
+    /// insert. The returned stats isolate the merge: `updated` counts the
+    /// conflicts, and `collisions` and `max_chain_length` show how folding
+    /// the other map in lengthened the chains.
+    ///
+    /// # Example
+    /// ```javascript
+    /// const stats = totals.merge(today, (key, ours, theirs) => ours + theirs);
+    /// console.log(stats.updated, "shared keys;", stats.collisions, "new collisions");
+    /// ```
+    pub fn merge(
+        &mut self,
+        other: &HashMap,
+        resolver: &js_sys::Function,
+    ) -> Result<BatchInsertStats, JsValue> {
+        self.try_merge(other, |key, ours, theirs| {
+            let value = resolver
+                .call3(
+                    &JsValue::UNDEFINED,
+                    &JsValue::from(key),
+                    &JsValue::from(ours),
+                    &JsValue::from(theirs),
+                )
+                .map_err(|e| format!("resolver threw: {:?}", e))?;
+            interop::value_from_number(key, value.as_f64())
+        })
+        .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// Get a value by key.
+    ///
+    /// # Return
+    /// - Some(value) if key exists
+    /// - None if key doesn't exist
+    ///
+    /// # Time Complexity: O(n) worst case
+    /// Where n = length of collision chain.
+    /// Average O(1).
+    ///
+    /// # Example
+    /// ```javascript
+    /// const val = map.get("hello");
+    /// if (val !== undefined) {
+    ///     console.log(val);
+    /// }
+    /// ```
+    pub fn get(&self, key: String) -> Option<u32> {
+        self.get_calls.set(self.get_calls.get() + 1);
+        self.counted_lookup(&key, self.hash_key(&key))
+    }
+
+    /// Whether `key` is present. Searches like `get` but is counted
+    /// separately, as `contains_key_calls` in the metrics.
+    ///
+    /// # Example
+    /// ```javascript
+    /// if (!seen.contains_key(url)) seen.insert(url, 1);
+    /// ```
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.contains_key_calls
+            .set(self.contains_key_calls.get() + 1);
+        self.counted_lookup(key, self.hash_key(key)).is_some()
+    }
+
+    /// Delete a key from the HashMap.
+    ///
+    /// # Return
+    /// - true if key was found and deleted
+    /// - false if key doesn't exist
+    ///
+    /// # Time Complexity: O(n) worst case
+    /// Where n = length of collision chain.
+    ///
+    /// # Example
+    /// ```javascript
+    /// const deleted = map.delete("hello");
+    /// console.log(deleted); // true or false
+    /// ```
+    pub fn delete(&mut self, key: String) -> bool {
+        self.migrate(REHASH_BUCKETS_PER_OP, &mut ProgressHook::none());
+        let removed = self.remove_key(key);
+        if removed {
+            self.apply_growth(true);
+            self.tune();
+        }
+        removed
+    }
+
+    /// Remove every entry `predicate(key, value)` returns false for,
+    /// returning how many went. Size, deletion metrics and `on_delete`
+    /// callbacks are updated as for `delete`, but the map resizes at most
+    /// once, after the whole pass. Throws without removing anything if
+    /// the predicate throws or returns something other than a boolean.
+    ///
+    /// # Example
+    /// ```javascript
+    /// const removed = sessions.retain((key, lastSeen) => lastSeen >= cutoff);
+    /// ```
+    pub fn retain(&mut self, predicate: &js_sys::Function) -> Result<u32, JsValue> {
+        self.try_retain(|key, value| {
+            let keep = predicate
+                .call2(
+                    &JsValue::UNDEFINED,
+                    &JsValue::from(key),
+                    &JsValue::from(value),
+                )
+                .map_err(|e| format!("predicate threw: {:?}", e))?;
+            keep.as_bool()
+                .ok_or_else(|| format!("predicate returned a non-boolean for '{}'", key))
+        })
+        .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// Call `callback(key, bucket, chain_length)` after every insert,
+    /// updates included, with the bucket the key went into and its chain
+    /// length afterwards. Pass `undefined` to stop. Exceptions thrown by the
+    /// callback are ignored, and it must not call back into the map.
+    ///
+    /// The bucket is the one written at the time: when the insert makes
+    /// the map grow, the resize comes after. Clones share callbacks.
+    ///
+    /// # Example
+    /// ```javascript
+    /// map.on_collision((key, bucket, len) => flash(bucket, `${key} makes ${len}`));
+    /// map.on_insert((key, bucket, len) => drawChain(bucket, len));
+    /// map.on_delete((key, bucket, len) => drawChain(bucket, len));
+    /// ```
+    pub fn on_insert(&mut self, callback: Option<js_sys::Function>) {
+        self.hooks.insert = hooks::js_hook(callback);
+    }
+
+    /// Like `on_insert`, but only for new keys landing in a non-empty
+    /// bucket; called just before the insert callback.
+    pub fn on_collision(&mut self, callback: Option<js_sys::Function>) {
+        self.hooks.collision = hooks::js_hook(callback);
+    }
+
+    /// Like `on_insert`, after each delete that removed a key, with the
+    /// chain length left behind.
+    pub fn on_delete(&mut self, callback: Option<js_sys::Function>) {
+        self.hooks.delete = hooks::js_hook(callback);
+    }
+
+    /// Get current HashMap metrics.
+    ///
+    /// Returns:
+    /// - total_insertions: Count of all insert operations
+    /// - total_collisions: Count of inserts that went into non-empty buckets
+    /// - max_chain_length: Longest collision chain
+    /// - average_load_factor: Current size / capacity
+    ///
+    /// # Use Case
+    /// Understand how collisions are distributed.
+    /// If max_chain_length is high, hash function or capacity needs improvement.
+    pub fn get_metrics(&self) -> HashMapMetrics {
+        HashMapMetrics {
+            chain_comparisons: self.chain_comparisons.get(),
+            get_calls: self.get_calls.get(),
+            contains_key_calls: self.contains_key_calls.get(),
+            reserved_unused_bytes: self.spare_bytes as u32,
+            total_lookups: self.total_lookups.get(),
+            lookup_chain_traversals: self.lookup_chain_traversals.get(),
+            unsuccessful_lookups: self.unsuccessful_lookups.get(),
+            ..self.metrics
+        }
+    }
+
+    /// How buckets store their chains (`Vec` unless configured with `HashMapBuilder`).
+    pub fn bucket_mode(&self) -> BucketMode {
+        self.bucket_mode
+    }
+
+    /// Number of chains (256 unless configured with `HashMapBuilder`).
+    pub fn bucket_count(&self) -> usize {
+        self.buckets.len()
+    }
+
+    /// Rehash into `bucket_count` buckets (at least 1).
+    ///
+    /// In `RehashMode::AllAtOnce` every entry moves before this returns.
+    /// In `RehashMode::Incremental` this only swaps in the new array; entries
+    /// follow a few buckets per insert/delete, or via `rehash_step`. A rehash
+    /// still in progress is completed first.
+    ///
+    /// # Example
+    /// ```javascript
+    /// const map = new HashMapBuilder().rehash_mode(RehashMode.Incremental).build();
+    /// map.resize(4096);
+    /// while (map.is_rehashing()) map.rehash_step(64); // or let writes finish it
+    /// ```
+    pub fn resize(&mut self, bucket_count: u32) {
+        self.resize_reporting(bucket_count, &mut ProgressHook::none());
+    }
+
+    /// `resize`, calling `callback({ processed, total, elapsed_ms })` every
+    /// `every` old buckets moved (0 means 1000) and once at the end. Only
+    /// `RehashMode::AllAtOnce` moves entries here; an incremental map
+    /// reports from `rehash_step_with_progress` instead.
+    ///
+    /// # Example
+    /// ```javascript
+    /// map.resize_with_progress(1 << 16, ({ processed, total }) => {
+    ///     bar.value = processed / total;
+    /// }, 256);
+    /// ```
+    pub fn resize_with_progress(
+        &mut self,
+        bucket_count: u32,
+        callback: Option<Function>,
+        every: u32,
+    ) {
+        self.resize_reporting(bucket_count, &mut ProgressHook::from_js(callback, every));
+    }
+
+    /// Move up to `buckets` old buckets during an incremental rehash.
+    pub fn rehash_step(&mut self, buckets: u32) {
+        self.rehash_step_reporting(buckets, &mut ProgressHook::none());
+    }
+
+    /// `rehash_step`, reporting as for `resize_with_progress`. `processed`
+    /// counts buckets moved since the rehash began, so one callback can
+    /// drive a progress bar across many steps.
+    pub fn rehash_step_with_progress(
+        &mut self,
+        buckets: u32,
+        callback: Option<Function>,
+        every: u32,
+    ) {
+        self.migrate(
+            buckets as usize,
+            &mut ProgressHook::from_js(callback, every),
+        );
+    }
+
+    /// True while an incremental rehash has entries left to move.
+    pub fn is_rehashing(&self) -> bool {
+        !self.old_buckets.is_empty()
+    }
+
+    /// Fraction of old buckets migrated, 1.0 when no rehash is in progress.
+    pub fn rehash_progress(&self) -> f64 {
+        if self.old_buckets.is_empty() {
+            1.0
+        } else {
+            self.migrate_cursor as f64 / self.old_buckets.len() as f64
+        }
+    }
+
+    pub fn rehash_mode(&self) -> RehashMode {
+        self.rehash_mode
+    }
+
+    /// Hash function applied to keys (`SipHash` unless configured with
+    /// `HashMapBuilder`).
+    pub fn hash_function(&self) -> HashFunction {
+        self.hash_function
+    }
+
+    /// Index of the bucket `key` hashes to, whether or not it is stored.
+    pub fn bucket_of(&self, key: &str) -> usize {
+        self.bucket_index(self.hash_key(key))
+    }
+
+    /// Every entry in the bucket `key` hashes to, in chain order.
+    /// During an incremental rehash this only covers the new bucket array.
+    ///
+    /// # Use Case
+    /// See exactly which unrelated keys collide with a given key. The key
+    /// itself doesn't need to be present.
+    ///
+    /// # Example
+    /// ```javascript
+    /// for (const entry of map.bucket_contents("alice")) {
+    ///     console.log(entry.key, entry.value);
+    /// }
+    /// ```
+    pub fn bucket_contents(&self, key: &str) -> Vec<BucketEntry> {
+        self.buckets[self.bucket_of(key)]
+            .entries()
+            .into_iter()
+            .map(|(key, value, _)| BucketEntry {
+                key: key.to_string(),
+                value,
+            })
+            .collect()
+    }
+
+    /// Chain length of every bucket, in bucket order, as a `Uint32Array`.
+    /// During an incremental rehash this only covers the new bucket array.
+    ///
+    /// # Use Case
+    /// Render a live histogram of bucket occupancy, where `get_metrics`
+    /// only gives the longest chain.
+    ///
+    /// # Example
+    /// ```javascript
+    /// const lengths = map.bucket_distribution();
+    /// const histogram = new Map();
+    /// for (const n of lengths) histogram.set(n, (histogram.get(n) ?? 0) + 1);
+    /// ```
+    pub fn bucket_distribution(&self) -> Vec<u32> {
+        self.buckets
+            .iter()
+            .map(|chain| chain.len() as u32)
+            .collect()
+    }
+
+    /// Every entry, oldest key first. Updating a value keeps the key's
+    /// place; deleting and re-inserting moves it to the end. Rehashing
+    /// never changes the order, however it reshuffles the buckets.
+    ///
+    /// # Example
+    /// ```javascript
+    /// map.insert("b", 1); map.insert("a", 2); map.insert("b", 3);
+    /// map.entries_in_insertion_order().map(e => e.key); // ["b", "a"]
+    /// ```
+    pub fn entries_in_insertion_order(&self) -> Vec<BucketEntry> {
+        self.pairs()
+            .into_iter()
+            .map(|(key, value)| BucketEntry { key, value })
+            .collect()
+    }
+
+    /// The `index`-th oldest entry still in the map (0 is the oldest).
+    /// O(n): the order lives only in the sequence numbers.
+    pub fn nth_inserted(&self, index: usize) -> Option<BucketEntry> {
+        let mut entries = self.sequenced_entries();
+        if index >= entries.len() {
+            return None;
+        }
+        let (_, &mut (key, value, _), _) =
+            entries.select_nth_unstable_by_key(index, |&(_, _, sequence)| sequence);
+        Some(BucketEntry {
+            key: key.to_string(),
+            value,
+        })
+    }
+
+    /// Get current size (number of key-value pairs).
+    pub fn len(&self) -> usize {
+        self.size
+    }
+
+    /// Check if HashMap is empty.
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    /// Remove every entry and zero the metrics, keeping the bucket count,
+    /// bucket mode, hash function and growth policy. An incremental rehash
+    /// in progress is abandoned.
+    ///
+    /// # Use Case
+    /// Reset state between benchmark runs without building a new object.
+    pub fn clear(&mut self) {
+        let bucket_mode = self.bucket_mode;
+        for chain in self.buckets_mut() {
+            *chain = Chain::new(bucket_mode);
+        }
+        self.old_buckets = Rc::default();
+        self.spare_bytes = 0;
+        self.migrate_cursor = 0;
+        self.size = 0;
+        self.next_sequence = 0;
+        self.metrics = HashMapMetrics {
+            hash_function: self.hash_function,
+            ..HashMapMetrics::default()
+        };
+        self.chain_comparisons.set(0);
+        self.get_calls.set(0);
+        self.contains_key_calls.set(0);
+        self.total_lookups.set(0);
+        self.lookup_chain_traversals.set(0);
+        self.unsuccessful_lookups.set(0);
+    }
+
+    /// A deep copy with the same entries, order, configuration and metrics,
+    /// e.g. to run two variants of a test from one populated map.
+    ///
+    /// # Example
+    /// ```javascript
+    /// const b = a.clone();
+    /// b.insert("only-in-b", 1);
+    /// a.get("only-in-b"); // undefined
+    /// ```
+    #[wasm_bindgen(js_name = clone)]
+    pub fn deep_clone(&self) -> HashMap {
+        self.clone()
+    }
+
+    /// Build a map from a JS `Map` of string keys to integer values
+    /// (0 to 2^32 - 1). Throws on the first entry that doesn't fit.
+    ///
+    /// # Example
+    /// ```javascript
+    /// const map = HashMap.from_js_map(new Map([["alice", 1], ["bob", 2]]));
+    /// map.to_object(); // { alice: 1, bob: 2 }
+    /// ```
+    pub fn from_js_map(map: &js_sys::Map) -> Result<HashMap, JsValue> {
+        interop::from_map(map, |_| HashMap::new())
+    }
+
+    /// Build a map from a plain object's own enumerable properties, as
+    /// `Object.entries` lists them. Values must be integers as for `from_js_map`.
+    pub fn from_object(object: &js_sys::Object) -> Result<HashMap, JsValue> {
+        interop::from_object(object, |_| HashMap::new())
+    }
+
+    /// The entries as a new JS `Map`, in insertion order.
+    pub fn to_js_map(&self) -> js_sys::Map {
+        interop::to_map(self)
+    }
+
+    /// The entries as a new plain object, one property per key.
+    pub fn to_object(&self) -> js_sys::Object {
+        interop::to_object(self)
+    }
+
+    /// Every key, oldest first (the order of `entries_in_insertion_order`).
+    pub fn keys(&self) -> js_sys::Array {
+        interop::to_keys_array(self)
+    }
+
+    /// An iterator over the entries that copies nothing up front; see
+    /// [`HashMapIter`].
+    pub fn iter(&self) -> HashMapIter {
+        let cursor = Cursor {
+            buckets: Rc::downgrade(&self.buckets),
+            old_buckets: Rc::downgrade(&self.old_buckets),
+            modifications: Rc::clone(&self.modifications.0),
+            expected: self.modifications.0.get(),
+            bucket: 0,
+            offset: 0,
+        };
+        HashMapIter {
+            cursor: Rc::new(RefCell::new(cursor)),
+        }
+    }
+
+    /// Every value as a `Uint32Array`, in the same order as `keys()`.
+    pub fn values(&self) -> Vec<u32> {
+        self.pairs().into_iter().map(|(_, value)| value).collect()
+    }
+
+    /// Every entry as a `[key, value]` pair, in the same order as `keys()`.
+    ///
+    /// # Example
+    /// ```javascript
+    /// for (const [key, value] of map.entries()) console.log(key, value);
+    /// const copy = new Map(map.entries());
+    /// ```
+    pub fn entries(&self) -> js_sys::Array {
+        interop::to_entries_array(self)
+    }
+
+    /// Build a map from CSV text whose first row names the columns,
+    /// taking keys from `key_col` and integer values from `value_col`.
+    /// Quoted fields may contain commas, quotes (as `""`) and line breaks.
+    /// Throws with the line number of the first bad row.
+    ///
+    /// # Example
+    /// ```javascript
+    /// const text = await (await fetch("scores.csv")).text();
+    /// const map = HashMap.from_csv(text, "player", "score");
+    /// download(map.to_csv());
+    /// ```
+    pub fn from_csv(text: &str, key_col: &str, value_col: &str) -> Result<HashMap, JsValue> {
+        csv::from_csv(text, key_col, value_col, |_| HashMap::new())
+    }
+
+    /// The entries as CSV with a `key,value` header row, quoting keys
+    /// where needed.
+    pub fn to_csv(&self) -> String {
+        csv::to_csv(self)
+    }
+
+    /// The entries in insertion order, with the bucket count, bucket mode,
+    /// hash function and accumulated metrics, as JSON text. Growth policy,
+    /// auto-tuning and rehash and metrics modes aren't saved.
+    ///
+    /// # Example
+    /// ```javascript
+    /// localStorage.setItem("scores", map.to_json());
+    /// const restored = HashMap.from_json(localStorage.getItem("scores"));
+    /// restored.get_metrics().total_collisions; // as before saving
+    /// ```
+    pub fn to_json(&self) -> String {
+        persist::to_json(self)
+    }
+
+    /// Rebuild a map saved with `to_json`, metrics included.
+    pub fn from_json(text: &str) -> Result<HashMap, JsValue> {
+        persist::from_json(text).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// What `to_json` saves, in a compact binary form for `postMessage` or
+    /// IndexedDB.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        persist::to_bytes(self)
+    }
+
+    /// Rebuild a map saved with `to_bytes`, metrics included.
+    pub fn from_bytes(bytes: &[u8]) -> Result<HashMap, JsValue> {
+        persist::from_bytes(bytes).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// Build a map from a MessagePack map of string keys to integers,
+    /// as JS encoders produce from a plain object. Requires the `msgpack`
+    /// feature.
+    ///
+    /// # Example
+    /// ```javascript
+    /// import { encode, decode } from "@msgpack/msgpack";
+    /// const map = HashMap.from_msgpack(encode({ alice: 1, bob: 2 }));
+    /// decode(map.to_msgpack()); // { alice: 1, bob: 2 }
+    /// ```
+    #[cfg(feature = "msgpack")]
+    pub fn from_msgpack(bytes: &[u8]) -> Result<HashMap, JsValue> {
+        msgpack::from_msgpack(bytes, |_| HashMap::new())
+    }
+
+    /// The entries as a MessagePack map. Requires the `msgpack` feature.
+    #[cfg(feature = "msgpack")]
+    pub fn to_msgpack(&self) -> Vec<u8> {
+        msgpack::to_msgpack(self)
+    }
+
+    /// Bucket occupancy as a bar chart, for consoles that can't render the
+    /// JSON or SVG views. Tables over 32 buckets are grouped into ranges.
     ///
-    /// Uses Rust's standard DefaultHasher (SipHash-like).
-    /// Good distribution, prevents algorithmic attacks.
-    fn hash_key(key: &str) -> u64 {
-        let mut hasher = DefaultHasher::new();
-        key.hash(&mut hasher);
-        hasher.finish()
+    /// # Example
+    /// ```javascript
+    /// console.log(map.to_ascii());
+    /// // 3 entries in 4 buckets (longest chain 2)
+    /// // 0 |######################################## 2
+    /// // 1 |                                         0
+    /// ```
+    pub fn to_ascii(&self) -> String {
+        let sizes: Vec<usize> = self.buckets.iter().map(Chain::len).collect();
+        let mut summary = format!(
+            "{} entries in {} buckets (longest chain {})",
+            self.size,
+            sizes.len(),
+            sizes.iter().max().unwrap_or(&0)
+        );
+        if !self.old_buckets.is_empty() {
+            summary.push_str(&format!(
+                "; rehashing, {} old buckets left",
+                self.old_buckets.len() - self.migrate_cursor
+            ));
+        }
+        ascii::occupancy_bars(&summary, &sizes)
     }
 
-    /// Internal: Get bucket index from hash.
+    /// Insert queued entries from `job` until `millis` milliseconds have elapsed.
     ///
-    /// Maps 64-bit hash to bucket index [0, 255].
-    /// Uses modulo: simple, effective, cache-friendly.
-    fn bucket_index(hash: u64) -> usize {
-        (hash as usize) % BUCKET_COUNT
+    /// Returns how many entries were inserted; call again (e.g. once per
+    /// animation frame) until `job.is_done()`.
+    pub fn insert_budgeted(&mut self, job: &mut BulkInsertJob, millis: f64) -> u32 {
+        job.run_for(self, millis)
     }
 
-    /// Internal: Update metrics after insertion.
+    /// Check the load factor, chain lengths and hash function, with a
+    /// recommendation for each problem found (see [`HealthReport`]).
     ///
-    /// Recalculates:
-    /// - total_insertions: always increments
-    /// - total_collisions: increments if inserting to non-empty bucket
-    /// - max_chain_length: maximum chain length in any bucket
-    /// - average_load_factor: size / capacity
-    fn update_metrics(&mut self, was_collision: bool) {
-        self.metrics.total_insertions += 1;
-        if was_collision {
-            self.metrics.total_collisions += 1;
+    /// # Example
+    /// ```javascript
+    /// for (const f of map.analyze().findings()) console.log(f.message, "->", f.recommendation);
+    /// ```
+    pub fn analyze(&self) -> HealthReport {
+        let mut report = HealthReport::default();
+        let buckets = self.buckets.len();
+        let load = self.size as f64 / buckets as f64;
+        if load > MAX_LOAD_FACTOR {
+            report.add(
+                if load > 4.0 {
+                    Severity::Critical
+                } else {
+                    Severity::Warning
+                },
+                "high_load_factor",
+                format!(
+                    "load factor {:.2}: {} entries in {} buckets",
+                    load, self.size, buckets
+                ),
+                "resize recommended: resize() to at least twice the entry count",
+            );
+        } else if load < MIN_LOAD_FACTOR && buckets > BUCKET_COUNT {
+            report.add(
+                Severity::Info,
+                "oversized",
+                format!(
+                    "load factor {:.3}: {} entries in {} buckets",
+                    load, self.size, buckets
+                ),
+                "resize() down to save memory",
+            );
         }
 
-        // Recalculate max chain length
-        self.metrics.max_chain_length = self
+        // With a well-spread hash the longest chain stays within a few
+        // times the load factor
+        let max_chain = self
             .buckets
             .iter()
-            .map(|bucket| bucket.len() as u32)
+            .chain(self.old_buckets.iter())
+            .map(Chain::len)
             .max()
             .unwrap_or(0);
-
-        // Recalculate load factor
-        self.metrics.average_load_factor = self.size as f32 / BUCKET_COUNT as f32;
-    }
-}
-
-#[wasm_bindgen]
-impl HashMap {
-    /// Create a new empty HashMap with 256 buckets.
-    ///
-    /// # Memory
-    /// Allocates space for 256 empty Vec buckets.
-    /// Each bucket grows as collisions occur.
-    #[wasm_bindgen(constructor)]
-    pub fn new() -> HashMap {
-        HashMap {
-            buckets: (0..BUCKET_COUNT).map(|_| Vec::new()).collect(),
-            size: 0,
-            metrics: HashMapMetrics {
-                total_insertions: 0,
-                total_collisions: 0,
-                max_chain_length: 0,
-                average_load_factor: 0.0,
-            },
+        let expected = 3 * load.ceil() as usize + 5;
+        if max_chain > expected {
+            report.add(
+                Severity::Warning,
+                "long_chains",
+                format!(
+                    "longest chain holds {} entries; about {} expected at this load",
+                    max_chain, expected
+                ),
+                "consider rehash: check the hash function and key set for clustering",
+            );
+        }
+        if self.hash_function.is_degenerate() {
+            report.add(
+                Severity::Warning,
+                "degenerate_hash",
+                format!(
+                    "keys are hashed with {:?}, which forces collisions",
+                    self.hash_function
+                ),
+                "build with HashFunction.SipHash outside demos and tests",
+            );
         }
+        if self.is_rehashing() {
+            report.add(
+                Severity::Info,
+                "rehash_in_progress",
+                format!(
+                    "incremental rehash {:.0}% done",
+                    self.rehash_progress() * 100.0
+                ),
+                "call rehash_step() or keep writing to finish it",
+            );
+        }
+        report
     }
 
-    /// Insert a key-value pair into the HashMap.
-    ///
-    /// # Behavior
-    /// - If key already exists: update the value (not counted as collision)
-    /// - If key doesn't exist: add to bucket (collision if bucket non-empty)
-    ///
-    /// # Time Complexity: O(n) worst case
-    /// Where n = length of collision chain in that bucket.
-    /// Average case O(1) with good hash function and load factor.
+    /// Turn auto-tuning on or off (off by default). While on, the map
+    /// resizes itself whenever `analyze()` would report `high_load_factor`
+    /// or `oversized`, and logs each resize to `tuning_log()`.
     ///
     /// # Example
     /// ```javascript
-    /// const map = new HashMap();
-    /// map.insert("hello", 42);
+    /// map.set_auto_tune(true);
+    /// for (let i = 0; i < 10000; i++) map.insert(`k${i}`, i);
+    /// map.tuning_log().map(e => e.detail); // ["256 -> 514 buckets", ...]
     /// ```
-    pub fn insert(&mut self, key: String, value: u32) {
-        let hash = Self::hash_key(&key);
-        let idx = Self::bucket_index(hash);
-        let bucket = &mut self.buckets[idx];
-
-        // Check if key already exists
-        for entry in bucket.iter_mut() {
-            if entry.0 == key {
-                // Update existing key - not a collision
-                entry.1 = value;
-                return;
-            }
-        }
+    pub fn set_auto_tune(&mut self, enabled: bool) {
+        self.auto_tune.enabled = enabled;
+    }
 
-        // New key - check if this is a collision
-        let was_collision = !bucket.is_empty();
-        bucket.push((key, value));
-        self.size += 1;
-        self.update_metrics(was_collision);
+    pub fn auto_tune(&self) -> bool {
+        self.auto_tune.enabled
     }
 
-    /// Get a value by key.
-    ///
-    /// # Return
-    /// - Some(value) if key exists
-    /// - None if key doesn't exist
-    ///
-    /// # Time Complexity: O(n) worst case
-    /// Where n = length of collision chain.
-    /// Average O(1).
-    ///
-    /// # Example
-    /// ```javascript
-    /// const val = map.get("hello");
-    /// if (val !== undefined) {
-    ///     console.log(val);
-    /// }
-    /// ```
-    pub fn get(&self, key: String) -> Option<u32> {
-        let hash = Self::hash_key(&key);
-        let idx = Self::bucket_index(hash);
-        let bucket = &self.buckets[idx];
+    /// Actions auto-tuning has taken, oldest first.
+    pub fn tuning_log(&self) -> Vec<TuningEvent> {
+        self.auto_tune.log().to_vec()
+    }
 
-        for (k, v) in bucket {
-            if k == &key {
-                return Some(*v);
-            }
+    /// Estimate heap usage: the bucket headers plus every chain's
+    /// buffer and key strings.
+    pub fn memory_report(&self) -> MemoryReport {
+        let mut report = MemoryReport::default();
+        report.add_vec(&self.buckets, self.buckets.len());
+        report.add_vec(&self.old_buckets, self.old_buckets.len());
+        for bucket in self.buckets.iter().chain(self.old_buckets.iter()) {
+            bucket.measure(&mut report);
         }
-
-        None
+        report
     }
 
-    /// Delete a key from the HashMap.
-    ///
-    /// # Return
-    /// - true if key was found and deleted
-    /// - false if key doesn't exist
-    ///
-    /// # Time Complexity: O(n) worst case
-    /// Where n = length of collision chain.
+    /// Where the bytes of `memory_report` go: bucket headers, chain entry
+    /// overhead, key bytes and spare capacity, found by walking every
+    /// bucket.
     ///
     /// # Example
     /// ```javascript
-    /// const deleted = map.delete("hello");
-    /// console.log(deleted); // true or false
+    /// // Chaining vs. open addressing, both at load factor 0.5
+    /// const chained = new HashMapBuilder().bucket_count(2048).build();
+    /// const open = new OpenAddressingHashTable(2048);
+    /// for (const key of keys.slice(0, 1024)) { chained.insert(key, 1); open.insert(key, 1); }
+    /// console.log(chained.memory_usage().bytes_per_entry(), open.memory_usage().bytes_per_entry());
     /// ```
-    pub fn delete(&mut self, key: String) -> bool {
-        let hash = Self::hash_key(&key);
-        let idx = Self::bucket_index(hash);
-        let bucket = &mut self.buckets[idx];
-
-        for (i, (k, _)) in bucket.iter().enumerate() {
-            if k == &key {
-                bucket.remove(i);
-                self.size -= 1;
-                // Don't update metrics for deletes (only track insertions)
-                return true;
+    pub fn memory_usage(&self) -> MemoryUsage {
+        let mut usage = MemoryUsage::default();
+        for buckets in [&*self.buckets, &*self.old_buckets] {
+            usage.bucket_bytes += buckets.len() * size_of::<Chain>();
+            usage.spare_bytes += (buckets.capacity() - buckets.len()) * size_of::<Chain>();
+            for bucket in buckets {
+                bucket.measure_usage(&mut usage);
             }
         }
-
-        false
+        usage
     }
 
-    /// Get current HashMap metrics.
-    ///
-    /// Returns:
-    /// - total_insertions: Count of all insert operations
-    /// - total_collisions: Count of inserts that went into non-empty buckets
-    /// - max_chain_length: Longest collision chain
-    /// - average_load_factor: Current size / capacity
+    /// Release spare chain capacity, including the whole buffer of any
+    /// bucket emptied by deletes.
     ///
-    /// # Use Case
-    /// Understand how collisions are distributed.
-    /// If max_chain_length is high, hash function or capacity needs improvement.
-    pub fn get_metrics(&self) -> HashMapMetrics {
-        self.metrics
+    /// # Example
+    /// ```javascript
+    /// for (const key of temporaryKeys) map.delete(key);
+    /// map.shrink_to_fit();
+    /// console.log(map.memory_report().reserved_bytes);
+    /// ```
+    pub fn shrink_to_fit(&mut self) {
+        self.shrink_to_fit_reporting(&mut ProgressHook::none());
     }
 
-    /// Get current size (number of key-value pairs).
-    pub fn len(&self) -> usize {
-        self.size
+    /// `shrink_to_fit`, returning the bytes it reclaimed: what
+    /// `reserved_unused_bytes` reported beforehand, give or take the
+    /// allocator.
+    ///
+    /// # Example
+    /// ```javascript
+    /// if (map.get_metrics().reserved_unused_bytes > 64 * 1024) {
+    ///   console.log(`reclaimed ${map.compact()} bytes`);
+    /// }
+    /// ```
+    pub fn compact(&mut self) -> usize {
+        self.compact_reporting(&mut ProgressHook::none())
     }
 
-    /// Check if HashMap is empty.
-    pub fn is_empty(&self) -> bool {
-        self.size == 0
+    /// `compact`, calling `callback({ processed, total, elapsed_ms })` every
+    /// `every` buckets shrunk (0 means 1000) and once at the end.
+    pub fn compact_with_progress(&mut self, callback: Option<Function>, every: u32) -> usize {
+        self.compact_reporting(&mut ProgressHook::from_js(callback, every))
     }
 }
 
@@ -290,6 +1989,215 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_clear_and_clone() {
+        for mode in [BucketMode::Vec, BucketMode::LinkedList] {
+            let mut map = HashMapBuilder::new()
+                .bucket_mode(mode)
+                .hash_function(HashFunction::Fnv1a)
+                .try_build()
+                .unwrap();
+            for i in 0..100 {
+                map.insert(format!("key{}", i), i);
+            }
+            let mut copy = map.deep_clone();
+            copy.insert("extra".to_string(), 7);
+            assert_eq!(map.get("extra".to_string()), None);
+            assert_eq!(copy.pairs()[..100], map.pairs()[..]);
+            assert_eq!(copy.get_metrics().total_insertions, 101);
+
+            map.clear();
+            assert!(map.is_empty() && map.get("key1".to_string()).is_none());
+            let metrics = map.get_metrics();
+            assert_eq!(metrics.total_insertions, 0);
+            assert_eq!(metrics.hash_function, HashFunction::Fnv1a);
+            assert_eq!((map.bucket_count(), map.bucket_mode()), (256, mode));
+            map.insert("again".to_string(), 1);
+            assert_eq!(map.pairs(), [("again".to_string(), 1)]);
+            assert_eq!(copy.len(), 101);
+        }
+    }
+
+    #[test]
+    fn test_insert_many() {
+        let mut map = HashMapBuilder::new().bucket_count(16).try_build().unwrap();
+        map.insert("key0".to_string(), 0);
+        let keys: Vec<String> = (0..100).map(|i| format!("key{}", i)).collect();
+        let stats = map
+            .try_insert_many(keys, (0..100).map(|i| i * 2).collect())
+            .unwrap();
+        assert_eq!((stats.inserted, stats.updated), (99, 1));
+        assert_eq!(map.len(), 100);
+        assert_eq!(map.get("key42".to_string()), Some(84));
+        assert!(stats.collisions > 0 && stats.chain_comparisons > 0);
+        assert_eq!(stats.max_chain_length, map.get_metrics().max_chain_length);
+        assert!(map.try_insert_many(vec!["x".to_string()], vec![]).is_err());
+        assert_eq!(map.get("x".to_string()), None);
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut ours = HashMapBuilder::new().bucket_count(8).try_build().unwrap();
+        let mut theirs = HashMap::new();
+        for i in 0..20 {
+            ours.insert(format!("a{}", i), i);
+            theirs.insert(format!("a{}", i + 10), 100);
+        }
+        let before = ours.get_metrics();
+        let stats = ours
+            .try_merge(&theirs, |_, mine, other| Ok(mine + other))
+            .unwrap();
+        assert_eq!((stats.inserted, stats.updated), (10, 10));
+        assert_eq!(ours.len(), 30);
+        assert_eq!(ours.get("a5".to_string()), Some(5));
+        assert_eq!(ours.get("a15".to_string()), Some(115));
+        assert_eq!(ours.get("a25".to_string()), Some(100));
+        let after = ours.get_metrics();
+        assert_eq!(
+            stats.collisions,
+            after.total_collisions - before.total_collisions
+        );
+        assert!(stats.max_chain_length >= before.max_chain_length);
+
+        let snapshot = ours.pairs();
+        assert!(ours
+            .try_merge(&theirs, |key, _, _| Err(key.to_string()))
+            .is_err());
+        assert_eq!(ours.pairs(), snapshot);
+    }
+
+    #[test]
+    fn test_contains_key() {
+        let mut map = HashMap::new();
+        map.insert("hello".to_string(), 42);
+        assert!(map.contains_key("hello"));
+        assert!(!map.contains_key("bye"));
+        assert_eq!(map.get("hello".to_string()), Some(42));
+        let metrics = map.get_metrics();
+        assert_eq!((metrics.get_calls, metrics.contains_key_calls), (1, 2));
+    }
+
+    #[test]
+    fn test_mutation_hooks() {
+        use std::cell::RefCell;
+
+        let mut map = HashMapBuilder::new().bucket_count(1).try_build().unwrap();
+        let log = Rc::new(RefCell::new(Vec::new()));
+        for event in ["insert", "collision", "delete"] {
+            let log = Rc::clone(&log);
+            map.try_on(event, move |e| {
+                log.borrow_mut()
+                    .push((event, e.key.clone(), e.bucket, e.chain_length))
+            })
+            .unwrap();
+        }
+        assert!(map.try_on("resize", |_| {}).is_err());
+
+        map.insert("a".to_string(), 1);
+        map.insert("b".to_string(), 2);
+        map.insert("a".to_string(), 3);
+        map.delete("b".to_string());
+        map.delete("missing".to_string());
+        let entry = |event, key: &str, len| (event, key.to_string(), 0, len);
+        assert_eq!(
+            *log.borrow(),
+            [
+                entry("insert", "a", 1),
+                entry("collision", "b", 2),
+                entry("insert", "b", 2),
+                entry("insert", "a", 2),
+                entry("delete", "b", 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lookup_and_deletion_metrics() {
+        let mut map = HashMapBuilder::new().bucket_count(1).try_build().unwrap();
+        for key in ["a", "b", "c"] {
+            map.insert(key.to_string(), 1);
+        }
+        map.get("a".to_string());
+        map.get("c".to_string());
+        map.contains_key("zzz");
+        map.try_get_or_insert_with("d".to_string(), |_| Ok(4))
+            .unwrap();
+        assert!(map.delete("b".to_string()));
+        assert!(!map.delete("b".to_string()));
+
+        let metrics = map.get_metrics();
+        assert_eq!(
+            (metrics.total_lookups, metrics.unsuccessful_lookups),
+            (4, 2)
+        );
+        // One chain of a, b, c: finding a takes 1 comparison, c 3, and
+        // each miss all 3
+        assert_eq!(metrics.lookup_chain_traversals, 1 + 3 + 3 + 3);
+        assert_eq!(metrics.comparisons_per_lookup(), 2.5);
+        assert_eq!(metrics.total_deletions, 1);
+        assert!(metrics.chain_comparisons > metrics.lookup_chain_traversals);
+    }
+
+    #[test]
+    fn test_retain() {
+        let mut map = HashMap::new();
+        for i in 0..1_000 {
+            map.insert(format!("key{}", i), i);
+        }
+        let deleted = Rc::new(Cell::new(0));
+        let counter = Rc::clone(&deleted);
+        map.try_on("delete", move |_| counter.set(counter.get() + 1))
+            .unwrap();
+
+        let mut seen = Vec::new();
+        let removed = map
+            .try_retain(|key, value| {
+                seen.push(key.to_string());
+                Ok(value % 10 == 0)
+            })
+            .unwrap();
+        assert_eq!(removed, 900);
+        assert_eq!(map.len(), 100);
+        assert_eq!(seen.len(), 1_000);
+        assert_eq!(seen[..2], ["key0", "key1"]);
+        assert_eq!(map.get("key20".to_string()), Some(20));
+        assert_eq!(map.get("key21".to_string()), None);
+        assert_eq!(map.get_metrics().total_deletions, 900);
+        assert_eq!(deleted.get(), 900);
+
+        let failed = map.try_retain(|key, _| {
+            if key == "key500" {
+                Err("boom".to_string())
+            } else {
+                Ok(false)
+            }
+        });
+        assert_eq!(failed, Err("boom".to_string()));
+        assert_eq!(map.len(), 100);
+        assert_eq!(map.try_retain(|_, _| Ok(true)), Ok(0));
+    }
+
+    #[test]
+    fn test_get_or_insert_with() {
+        let mut map = HashMap::new();
+        let mut calls = 0;
+        for _ in 0..3 {
+            let value = map
+                .try_get_or_insert_with("id".to_string(), |key| {
+                    calls += 1;
+                    Ok(key.len() as u32)
+                })
+                .unwrap();
+            assert_eq!(value, 2);
+        }
+        assert_eq!((calls, map.len()), (1, 1));
+        assert!(map
+            .try_get_or_insert_with("bad".to_string(), |_| Err("no".to_string()))
+            .is_err());
+        assert!(!map.contains_key("bad"));
+        assert_eq!(map.get_metrics().get_calls, 4);
+    }
+
     #[test]
     fn test_delete() {
         let mut map = HashMap::new();
@@ -299,6 +2207,292 @@ mod tests {
         assert_eq!(map.len(), 0);
     }
 
+    #[test]
+    fn test_constant_hash_puts_everything_in_one_bucket() {
+        let mut map = HashMapBuilder::new()
+            .hash_function(HashFunction::Constant)
+            .try_build()
+            .unwrap();
+        for i in 0..100 {
+            map.insert(format!("key{}", i), i);
+        }
+        let metrics = map.get_metrics();
+        assert_eq!(metrics.max_chain_length, 100);
+        assert_eq!(metrics.total_collisions, 99);
+        assert_eq!(metrics.hash_function, HashFunction::Constant);
+        assert_eq!(map.bucket_contents("anything").len(), 100);
+        assert_eq!(map.get("key99".to_string()), Some(99));
+        // The forced hash survives a resize
+        map.resize(1_024);
+        assert_eq!(map.get_metrics().max_chain_length, 100);
+        assert_eq!(HashMap::new().hash_function(), HashFunction::SipHash);
+    }
+
+    #[test]
+    fn test_bucket_distribution() {
+        let mut map = HashMapBuilder::new()
+            .bucket_count(8)
+            .hash_function(HashFunction::FirstChar)
+            .try_build()
+            .unwrap();
+        for key in ["a1", "a2", "a3", "b1"] {
+            map.insert(key.to_string(), 0);
+        }
+        let distribution = map.bucket_distribution();
+        assert_eq!(distribution.len(), 8);
+        // 'a' is 97 and 'b' 98, so buckets 1 and 2
+        assert_eq!(distribution, [0, 3, 1, 0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_bucket_contents() {
+        let mut map = HashMapBuilder::new().bucket_count(4).try_build().unwrap();
+        for i in 0..20 {
+            map.insert(format!("key{}", i), i);
+        }
+        let contents = map.bucket_contents("key7");
+        assert!(contents.contains(&BucketEntry {
+            key: "key7".to_string(),
+            value: 7,
+        }));
+        let home = map.bucket_of("key7");
+        assert!(contents.iter().all(|e| map.bucket_of(&e.key) == home));
+        let total: usize = (0..4)
+            .map(|b| {
+                let key = (0..)
+                    .map(|i| format!("probe{}", i))
+                    .find(|k| map.bucket_of(k) == b)
+                    .unwrap();
+                map.bucket_contents(&key).len()
+            })
+            .sum();
+        assert_eq!(total, 20);
+    }
+
+    #[test]
+    fn test_resize_all_at_once() {
+        let mut map = HashMapBuilder::new().bucket_count(8).try_build().unwrap();
+        for i in 0..500 {
+            map.insert(format!("key{}", i), i);
+        }
+        let crowded = map.get_metrics().max_chain_length;
+        map.resize(512);
+        assert!(!map.is_rehashing());
+        assert_eq!(map.bucket_count(), 512);
+        assert!(map.get_metrics().max_chain_length < crowded);
+        assert_eq!(map.len(), 500);
+        for i in 0..500 {
+            assert_eq!(map.get(format!("key{}", i)), Some(i));
+        }
+    }
+
+    #[test]
+    fn test_growth_policy_resizes_automatically() {
+        let config = CapacityConfig::new().with_max_load_factor(0.75);
+        let mut map = HashMap::try_with_config(config).unwrap();
+        assert_eq!(map.bucket_count(), 8);
+        for i in 0..10_000 {
+            map.insert(format!("key{}", i), i);
+        }
+        assert_eq!(map.bucket_count(), 16_384);
+        let metrics = map.get_metrics();
+        assert_eq!(metrics.rehash_count, 11);
+        assert!(metrics.average_load_factor <= 0.75);
+        assert!(metrics.rehashed_entries > 10_000);
+        assert!((0..10_000).all(|i| map.get(format!("key{}", i)) == Some(i)));
+        // A fixed map only counts explicit resizes
+        let mut fixed = HashMap::new();
+        fixed.insert("a".to_string(), 1);
+        fixed.resize(64);
+        assert_eq!(fixed.get_metrics().rehash_count, 1);
+        assert_eq!(fixed.get_metrics().rehashed_entries, 1);
+
+        let shrinking = config.with_shrink_on_delete(true);
+        let mut map = HashMapBuilder::new()
+            .growth(&shrinking)
+            .try_build()
+            .unwrap();
+        for i in 0..1_000 {
+            map.insert(format!("key{}", i), i);
+        }
+        for i in 0..990 {
+            assert!(map.delete(format!("key{}", i)));
+        }
+        assert!(map.bucket_count() <= 64, "{}", map.bucket_count());
+        assert!((990..1_000).all(|i| map.get(format!("key{}", i)) == Some(i)));
+
+        let invalid = CapacityConfig::new().with_max_load_factor(0.0);
+        assert!(HashMap::try_with_config(invalid).is_err());
+    }
+
+    #[test]
+    fn test_incremental_rehash() {
+        let mut map = HashMapBuilder::new()
+            .bucket_count(64)
+            .rehash_mode(RehashMode::Incremental)
+            .try_build()
+            .unwrap();
+        for i in 0..1_000 {
+            map.insert(format!("key{}", i), i);
+        }
+        map.resize(1_024);
+        assert!(map.is_rehashing());
+        assert_eq!(map.rehash_progress(), 0.0);
+        for i in 0..1_000 {
+            assert_eq!(map.get(format!("key{}", i)), Some(i));
+        }
+
+        // Writes during the migration see keys in either array
+        map.insert("key1".to_string(), 11);
+        map.insert("new".to_string(), 7);
+        assert!(map.delete("key999".to_string()));
+        assert!(!map.delete("key999".to_string()));
+        assert_eq!(map.len(), 1_000);
+        assert!(map.rehash_progress() > 0.0);
+
+        while map.is_rehashing() {
+            map.rehash_step(5);
+        }
+        assert_eq!(map.rehash_progress(), 1.0);
+        assert_eq!(map.len(), 1_000);
+        assert_eq!(map.get("key1".to_string()), Some(11));
+        assert_eq!(map.get("new".to_string()), Some(7));
+        assert_eq!(map.get("key999".to_string()), None);
+        let total: usize = map.buckets.iter().map(Chain::len).sum();
+        assert_eq!(total, 1_000);
+    }
+
+    #[test]
+    fn test_resize_migrate_and_compact_report_progress() {
+        let recording = |every| {
+            let seen = Rc::new(RefCell::new(Vec::new()));
+            let sink = Rc::clone(&seen);
+            let hook = ProgressHook::new(every, move |p: Progress| {
+                sink.borrow_mut().push((p.processed, p.total))
+            });
+            (hook, seen)
+        };
+        let mut map = HashMapBuilder::new().bucket_count(64).try_build().unwrap();
+        for i in 0..1_000 {
+            map.insert(format!("key{}", i), i);
+        }
+        // All at once: every old bucket moves inside resize
+        let (mut hook, seen) = recording(16);
+        map.resize_reporting(256, &mut hook);
+        assert_eq!(*seen.borrow(), [16, 32, 48, 64].map(|n| (n, Some(64))));
+
+        // Incremental: counts carry on across steps until the array is drained
+        let mut map = HashMapBuilder::new()
+            .bucket_count(64)
+            .rehash_mode(RehashMode::Incremental)
+            .try_build()
+            .unwrap();
+        for i in 0..1_000 {
+            map.insert(format!("key{}", i), i);
+        }
+        map.resize(128);
+        let (mut hook, seen) = recording(20);
+        while map.is_rehashing() {
+            map.rehash_step_reporting(15, &mut hook);
+        }
+        assert_eq!(*seen.borrow(), [20, 40, 60, 64].map(|n| (n, Some(64))));
+
+        for i in 0..900 {
+            map.delete(format!("key{}", i));
+        }
+        let (mut hook, seen) = recording(100);
+        assert!(map.compact_reporting(&mut hook) > 0);
+        assert_eq!(*seen.borrow(), [(100, Some(128)), (128, Some(128))]);
+        assert_eq!(map.len(), 100);
+    }
+
+    #[test]
+    fn test_insertion_order_survives_rehash() {
+        let mut map = HashMapBuilder::new()
+            .bucket_count(8)
+            .rehash_mode(RehashMode::Incremental)
+            .try_build()
+            .unwrap();
+        let mut expected: Vec<String> = (0..50).map(|i| format!("key{}", 49 - i)).collect();
+        for key in &expected {
+            map.insert(key.clone(), 0);
+        }
+        map.resize(128);
+        // Moved out of the old array by an update: keeps its place
+        map.insert("key30".to_string(), 1);
+        map.delete("key40".to_string());
+        map.insert("key40".to_string(), 2);
+        expected.retain(|k| k != "key40");
+        expected.push("key40".to_string());
+
+        let keys = |map: &HashMap| -> Vec<String> {
+            map.entries_in_insertion_order()
+                .into_iter()
+                .map(|e| e.key)
+                .collect()
+        };
+        assert_eq!(keys(&map), expected);
+        map.rehash_step(u32::MAX);
+        assert_eq!(keys(&map), expected);
+        assert_eq!(map.nth_inserted(0).unwrap().key, "key49");
+        assert_eq!(map.nth_inserted(18).unwrap().value, 1);
+        assert_eq!(map.nth_inserted(49).unwrap().key, "key40");
+        assert!(map.nth_inserted(50).is_none());
+        let values = map.values();
+        assert_eq!((values.len(), values[18], values[49]), (50, 1, 2));
+    }
+
+    #[test]
+    fn test_iter_visits_every_entry_once() {
+        let mut map = HashMapBuilder::new()
+            .bucket_count(8)
+            .rehash_mode(RehashMode::Incremental)
+            .try_build()
+            .unwrap();
+        for i in 0..100 {
+            map.insert(format!("key{}", i), i);
+        }
+        map.resize(64);
+        map.insert("key0".to_string(), 0);
+        // Mid-migration: entries are split between the two arrays
+        assert!(!map.old_buckets.is_empty());
+        let mut iter = map.iter();
+        let mut seen = Vec::new();
+        while let Some(entry) = iter.try_next().unwrap() {
+            seen.push(entry);
+        }
+        seen.sort_unstable();
+        let mut expected = map.pairs();
+        expected.sort_unstable();
+        assert_eq!(seen, expected);
+        assert_eq!(iter.try_next(), Ok(None));
+
+        let mut iter = map.iter();
+        iter.try_next().unwrap();
+        map.delete("key1".to_string());
+        assert!(iter.try_next().is_err());
+
+        // Clearing and refilling to the same size still counts as a change
+        let mut small = HashMap::new();
+        small.insert("a".to_string(), 1);
+        let mut iter = small.iter();
+        small.clear();
+        small.insert("b".to_string(), 2);
+        assert_eq!(
+            iter.try_next(),
+            Err("HashMap was modified during iteration".to_string())
+        );
+
+        // A clone writes without disturbing iterators over the original,
+        // and freeing the original ends them
+        let mut iter = small.iter();
+        let mut copy = small.clone();
+        copy.insert("c".to_string(), 3);
+        assert_eq!(iter.try_next(), Ok(Some(("b".to_string(), 2))));
+        drop(small);
+        assert!(iter.try_next().unwrap_err().contains("freed"));
+    }
+
     #[test]
     fn test_delete_missing_key() {
         let mut map = HashMap::new();
@@ -370,4 +2564,118 @@ mod tests {
         // With 257 items in 256 buckets, at least 1 must collide
         assert!(metrics.total_collisions > 0 || metrics.total_insertions >= 256);
     }
+
+    #[test]
+    fn test_shrink_to_fit_releases_emptied_buckets() {
+        let mut map = HashMap::new();
+        for i in 0..2_000 {
+            map.insert(format!("temp{}", i), i);
+        }
+        let loaded = map.memory_report();
+        for i in 0..2_000 {
+            map.delete(format!("temp{}", i));
+        }
+        // Deletes keep chain buffers around until asked to shrink
+        let headers = BUCKET_COUNT * std::mem::size_of::<Chain>();
+        assert_eq!(map.memory_report().used_bytes, headers);
+        assert!(map.memory_report().reserved_bytes > headers);
+
+        map.shrink_to_fit();
+        let after = map.memory_report();
+        assert_eq!(after.reserved_bytes, headers);
+        assert!(after.reserved_bytes < loaded.reserved_bytes);
+    }
+
+    #[test]
+    fn test_memory_usage_breaks_down_report() {
+        for mode in [
+            BucketMode::Vec,
+            BucketMode::LinkedList,
+            BucketMode::SortedVec,
+        ] {
+            let mut map = HashMapBuilder::new()
+                .bucket_count(256)
+                .bucket_mode(mode)
+                .try_build()
+                .unwrap();
+            for i in 0..500 {
+                map.insert(format!("key{}", i), i);
+            }
+            for i in 0..100 {
+                map.delete(format!("key{}", i));
+            }
+            let usage = map.memory_usage();
+            assert_eq!(usage.entries, 400);
+            assert_eq!(usage.total_bytes(), map.memory_report().reserved_bytes);
+            assert_eq!(usage.bucket_bytes, 256 * size_of::<Chain>());
+            let key_bytes: usize = map.pairs().iter().map(|(key, _)| key.len()).sum();
+            assert_eq!(usage.key_bytes, key_bytes);
+            assert!(usage.entry_bytes >= 400 * size_of::<String>(), "{:?}", mode);
+        }
+        assert_eq!(HashMap::new().memory_usage().bytes_per_entry(), 0.0);
+    }
+
+    #[test]
+    fn test_compact_reports_reclaimed_bytes() {
+        let mut map = HashMap::new();
+        for i in 0..1_000 {
+            map.insert(format!("key{}", i), i);
+        }
+        for i in 0..900 {
+            map.delete(format!("key{}", i));
+        }
+        let unused = map.get_metrics().reserved_unused_bytes as usize;
+        assert!(unused > 0);
+        let reserved = map.memory_report().reserved_bytes;
+        let reclaimed = map.compact();
+        assert_eq!(reclaimed, reserved - map.memory_report().reserved_bytes);
+        assert!(reclaimed > 0 && reclaimed <= unused);
+        assert_eq!(map.get_metrics().reserved_unused_bytes, 0);
+        assert_eq!(map.compact(), 0);
+        assert_eq!(map.len(), 100);
+    }
+
+    #[test]
+    fn test_reserved_unused_bytes_tracks_every_write() {
+        let check = |map: &HashMap| {
+            assert_eq!(
+                map.get_metrics().reserved_unused_bytes as usize,
+                map.memory_report().slack_bytes()
+            );
+        };
+        for mode in [
+            BucketMode::Vec,
+            BucketMode::LinkedList,
+            BucketMode::SortedVec,
+        ] {
+            let mut map = HashMapBuilder::new()
+                .bucket_count(16)
+                .bucket_mode(mode)
+                .rehash_mode(RehashMode::Incremental)
+                .try_build()
+                .unwrap();
+            let mut key = String::with_capacity(64);
+            key.push_str("roomy");
+            map.insert(key, 0);
+            for i in 0..500 {
+                map.insert(format!("key{}", i), i);
+            }
+            check(&map);
+            map.resize(128);
+            for i in 0..300 {
+                map.delete(format!("key{}", i));
+                map.insert(format!("key{}", i % 7), i);
+            }
+            check(&map);
+            let mut copy = map.clone();
+            copy.delete("key400".to_string());
+            check(&copy);
+            map.insert("new".to_string(), 1);
+            check(&map);
+            map.shrink_to_fit();
+            check(&map);
+            map.clear();
+            assert_eq!(map.get_metrics().reserved_unused_bytes, 0);
+        }
+    }
 }
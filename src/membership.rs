@@ -0,0 +1,232 @@
+use crate::bloom::BloomFilter;
+use crate::clock::now_ms;
+use crate::cuckoo_filter::CuckooFilter;
+use crate::quotient_filter::QuotientFilter;
+use wasm_bindgen::prelude::*;
+
+/// Filter names accepted by [`new_filter`].
+pub const MEMBERSHIP_FILTERS: [&str; 3] = ["bloom", "cuckoo", "quotient"];
+
+/// An approximate set: `contains` may say yes for keys never inserted, but
+/// never no for one that was.
+pub trait MembershipFilter {
+    fn name(&self) -> &'static str;
+
+    /// Add `key`. Returns false if the filter couldn't store it.
+    fn insert(&mut self, key: &str) -> bool;
+
+    fn contains(&self, key: &str) -> bool;
+
+    /// Remove `key`, for filters that can. `None` if they can't.
+    fn delete(&mut self, _key: &str) -> Option<bool> {
+        None
+    }
+
+    /// Bits of storage if the filter's layout were tightly packed.
+    fn packed_bits(&self) -> usize;
+}
+
+impl MembershipFilter for BloomFilter {
+    fn name(&self) -> &'static str {
+        "bloom"
+    }
+
+    fn insert(&mut self, key: &str) -> bool {
+        BloomFilter::insert(self, key);
+        true
+    }
+
+    fn contains(&self, key: &str) -> bool {
+        BloomFilter::contains(self, key)
+    }
+
+    fn packed_bits(&self) -> usize {
+        self.bit_count()
+    }
+}
+
+impl MembershipFilter for CuckooFilter {
+    fn name(&self) -> &'static str {
+        "cuckoo"
+    }
+
+    fn insert(&mut self, key: &str) -> bool {
+        CuckooFilter::insert(self, key)
+    }
+
+    fn contains(&self, key: &str) -> bool {
+        self.peek(key)
+    }
+
+    fn delete(&mut self, key: &str) -> Option<bool> {
+        Some(CuckooFilter::delete(self, key))
+    }
+
+    fn packed_bits(&self) -> usize {
+        self.slot_count() * self.fingerprint_bits() as usize
+    }
+}
+
+impl MembershipFilter for QuotientFilter {
+    fn name(&self) -> &'static str {
+        "quotient"
+    }
+
+    fn insert(&mut self, key: &str) -> bool {
+        QuotientFilter::insert(self, key)
+    }
+
+    fn contains(&self, key: &str) -> bool {
+        QuotientFilter::contains(self, key)
+    }
+
+    fn delete(&mut self, key: &str) -> Option<bool> {
+        Some(QuotientFilter::delete(self, key))
+    }
+
+    fn packed_bits(&self) -> usize {
+        self.slot_count() * self.bits_per_slot() as usize
+    }
+}
+
+/// Create an empty filter by name (see [`MEMBERSHIP_FILTERS`]) sized for
+/// `capacity` keys at `false_positive_rate`.
+pub fn new_filter(
+    name: &str,
+    capacity: usize,
+    false_positive_rate: f64,
+) -> Result<Box<dyn MembershipFilter>, String> {
+    Ok(match name {
+        "bloom" => Box::new(BloomFilter::try_new(capacity, false_positive_rate)?),
+        "cuckoo" => Box::new(CuckooFilter::try_new(capacity, false_positive_rate)?),
+        "quotient" => Box::new(QuotientFilter::try_new(capacity, false_positive_rate)?),
+        _ => return Err(format!("unknown filter '{}'", name)),
+    })
+}
+
+/// How one filter did holding `items` keys at a target false positive rate.
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct FilterBenchmark {
+    pub filter: String,
+    pub items: u32,
+    pub target_false_positive_rate: f64,
+    /// Measured over as many never-inserted keys as `items`
+    pub false_positive_rate: f64,
+    /// Packed storage bits divided by `items`
+    pub bits_per_item: f64,
+    pub failed_inserts: u32,
+    pub insert_ms: f64,
+    /// Time for all lookups, present and absent keys alike
+    pub lookup_ms: f64,
+    /// False positive rate after deleting half the keys, or NaN for filters
+    /// that can't delete
+    pub false_positive_rate_after_delete: f64,
+}
+
+fn absent_rate(filter: &dyn MembershipFilter, probes: usize) -> f64 {
+    let hits = (0..probes)
+        .filter(|i| filter.contains(&format!("absent{}", i)))
+        .count();
+    hits as f64 / probes as f64
+}
+
+/// Fill `filter` with `items` keys and measure it.
+pub fn benchmark_filter(
+    filter: &mut dyn MembershipFilter,
+    items: usize,
+    false_positive_rate: f64,
+) -> FilterBenchmark {
+    let keys: Vec<String> = (0..items).map(|i| format!("item{}", i)).collect();
+    let start = now_ms();
+    let failed_inserts = keys.iter().filter(|key| !filter.insert(key)).count();
+    let insert_ms = now_ms() - start;
+
+    let probes = items.max(1);
+    let start = now_ms();
+    let missing = keys.iter().filter(|key| !filter.contains(key)).count();
+    let false_positive_rate_measured = absent_rate(filter, probes);
+    let lookup_ms = now_ms() - start;
+    debug_assert!(missing <= failed_inserts, "filters have no false negatives");
+
+    let deletable = keys
+        .iter()
+        .step_by(2)
+        .map(|key| filter.delete(key))
+        .all(|deleted| deleted.is_some());
+    FilterBenchmark {
+        filter: filter.name().to_string(),
+        items: items as u32,
+        target_false_positive_rate: false_positive_rate,
+        false_positive_rate: false_positive_rate_measured,
+        bits_per_item: filter.packed_bits() as f64 / probes as f64,
+        failed_inserts: failed_inserts as u32,
+        insert_ms,
+        lookup_ms,
+        false_positive_rate_after_delete: if deletable && items > 0 {
+            absent_rate(filter, probes)
+        } else {
+            f64::NAN
+        },
+    }
+}
+
+pub fn try_compare_filters(
+    items: usize,
+    false_positive_rate: f64,
+) -> Result<Vec<FilterBenchmark>, String> {
+    MEMBERSHIP_FILTERS
+        .iter()
+        .map(|name| {
+            let mut filter = new_filter(name, items, false_positive_rate)?;
+            Ok(benchmark_filter(
+                filter.as_mut(),
+                items,
+                false_positive_rate,
+            ))
+        })
+        .collect()
+}
+
+/// Build a Bloom, a cuckoo and a quotient filter over the same `items` keys
+/// at `false_positive_rate` and measure their space, speed and accuracy.
+///
+/// # Example
+/// ```javascript
+/// for (const r of compare_filters(100_000, 0.01))
+///   console.log(r.filter, r.bits_per_item.toFixed(1), r.false_positive_rate, r.lookup_ms);
+/// ```
+#[wasm_bindgen]
+pub fn compare_filters(
+    items: u32,
+    false_positive_rate: f64,
+) -> Result<Vec<FilterBenchmark>, JsValue> {
+    try_compare_filters(items as usize, false_positive_rate).map_err(|e| JsValue::from_str(&e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compare_filters() {
+        let results = try_compare_filters(10_000, 0.01).unwrap();
+        let names: Vec<&str> = results.iter().map(|r| r.filter.as_str()).collect();
+        assert_eq!(names, MEMBERSHIP_FILTERS);
+        for r in &results {
+            assert_eq!(r.failed_inserts, 0, "{:?}", r);
+            assert!(r.false_positive_rate < 0.02, "{:?}", r);
+            assert!(r.bits_per_item > 8.0, "{:?}", r);
+        }
+        assert!(results[0].false_positive_rate_after_delete.is_nan());
+        for r in &results[1..] {
+            assert!(
+                r.false_positive_rate_after_delete <= r.false_positive_rate,
+                "{:?}",
+                r
+            );
+        }
+        assert!(try_compare_filters(10, 2.0).is_err());
+        assert!(new_filter("xor", 10, 0.01).is_err());
+    }
+}
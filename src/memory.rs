@@ -0,0 +1,128 @@
+use std::mem::size_of;
+use wasm_bindgen::prelude::*;
+
+/// Estimated heap footprint of a structure.
+///
+/// `used_bytes` counts what live entries actually occupy; `reserved_bytes`
+/// also counts spare capacity (empty buckets, tombstones, string slack) that
+/// `shrink_to_fit()` can hand back. Both are estimates: allocator headers and
+/// std hash table control bytes are approximated, not measured.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MemoryReport {
+    pub used_bytes: usize,
+    pub reserved_bytes: usize,
+}
+
+impl MemoryReport {
+    /// Count `used` bytes, of which all are also reserved.
+    pub(crate) fn add_exact(&mut self, bytes: usize) {
+        self.used_bytes += bytes;
+        self.reserved_bytes += bytes;
+    }
+
+    /// Count a string's heap buffer.
+    pub(crate) fn add_string(&mut self, s: &str, capacity: usize) {
+        self.used_bytes += s.len();
+        self.reserved_bytes += capacity;
+    }
+
+    /// Count a vector's buffer, where only `live` of its elements are in use.
+    pub(crate) fn add_vec<T>(&mut self, v: &Vec<T>, live: usize) {
+        self.used_bytes += live * size_of::<T>();
+        self.reserved_bytes += v.capacity() * size_of::<T>();
+    }
+}
+
+#[wasm_bindgen]
+impl MemoryReport {
+    /// Bytes reserved but not in use.
+    pub fn slack_bytes(&self) -> usize {
+        self.reserved_bytes - self.used_bytes
+    }
+
+    /// Fraction of reserved bytes in use, 1.0 for an empty report.
+    pub fn utilization(&self) -> f64 {
+        if self.reserved_bytes == 0 {
+            1.0
+        } else {
+            self.used_bytes as f64 / self.reserved_bytes as f64
+        }
+    }
+}
+
+/// Where a hash table's bytes go, so chaining and open addressing can be
+/// compared at the same load factor.
+///
+/// - bucket_bytes: The bucket (or slot) array itself, empty ones included.
+///   Open addressing stores entries in its slots, so this is most of it.
+/// - entry_bytes: Per-entry overhead outside that array: chain buffers or
+///   list nodes, holding each key's string header, value and bookkeeping.
+/// - key_bytes: The keys' UTF-8 bytes.
+/// - spare_bytes: Capacity reserved but unused: chain buffer and key
+///   string slack, and deleted keys a table still holds.
+///
+/// The four add up to `MemoryReport::reserved_bytes` for the same table.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MemoryUsage {
+    pub bucket_bytes: usize,
+    pub entry_bytes: usize,
+    pub key_bytes: usize,
+    pub spare_bytes: usize,
+    pub entries: usize,
+}
+
+impl MemoryUsage {
+    /// Count a string's bytes, and its unused capacity as spare.
+    pub(crate) fn add_key(&mut self, key: &str, capacity: usize) {
+        self.key_bytes += key.len();
+        self.spare_bytes += capacity - key.len();
+    }
+
+    /// Count `live` elements of a vector as entries, the rest of its
+    /// capacity as spare.
+    pub(crate) fn add_entries<T>(&mut self, v: &Vec<T>, live: usize) {
+        self.entry_bytes += live * size_of::<T>();
+        self.spare_bytes += (v.capacity() - live) * size_of::<T>();
+    }
+}
+
+#[wasm_bindgen]
+impl MemoryUsage {
+    pub fn total_bytes(&self) -> usize {
+        self.bucket_bytes + self.entry_bytes + self.key_bytes + self.spare_bytes
+    }
+
+    /// Total bytes per stored entry, 0 for an empty table.
+    pub fn bytes_per_entry(&self) -> f64 {
+        if self.entries == 0 {
+            0.0
+        } else {
+            self.total_bytes() as f64 / self.entries as f64
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accumulates_used_and_reserved() {
+        let mut report = MemoryReport::default();
+        assert_eq!(report.utilization(), 1.0);
+
+        let mut key = String::with_capacity(16);
+        key.push_str("abcd");
+        report.add_string(&key, key.capacity());
+        let v: Vec<u64> = Vec::with_capacity(4);
+        report.add_vec(&v, 1);
+        report.add_exact(8);
+
+        assert_eq!(report.used_bytes, 4 + 8 + 8);
+        assert_eq!(report.reserved_bytes, 16 + 32 + 8);
+        assert_eq!(report.slack_bytes(), 36);
+        assert!(report.utilization() < 0.5);
+    }
+}
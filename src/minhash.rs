@@ -0,0 +1,314 @@
+use crate::rng::DefaultRng;
+use rand::Rng;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use wasm_bindgen::prelude::*;
+
+/// Permutations used by `MinHash::default()`.
+pub const DEFAULT_PERMUTATIONS: u32 = 128;
+
+/// Seed used by `new MinHash(n)`; signatures only compare under the same seed.
+pub const DEFAULT_SEED: u64 = 0x5EED;
+
+/// MinHash signature of a token set, for estimating Jaccard similarity.
+///
+/// Each of `permutations` hash functions maps every token to a number and
+/// the signature keeps the minimum per function. Two sets agree on a given
+/// minimum with probability equal to their Jaccard similarity
+/// |A ∩ B| / |A ∪ B|, so the fraction of matching slots estimates it with
+/// standard error about 1 / sqrt(permutations), whatever the set sizes.
+///
+/// # Example
+/// ```javascript
+/// const a = new MinHash(128); a.add_tokens("the quick brown fox".split(" "));
+/// const b = new MinHash(128); b.add_tokens("the quick red fox".split(" "));
+/// a.estimated_jaccard(b); // about 0.6
+/// ```
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct MinHash {
+    /// `(multiplier, offset)` per permutation; multipliers are odd
+    coefficients: Vec<(u64, u64)>,
+    minimums: Vec<u64>,
+    seed: u64,
+    tokens_added: u32,
+}
+
+pub(crate) fn token_hash(token: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    token.hash(&mut hasher);
+    hasher.finish()
+}
+
+impl MinHash {
+    pub fn with_seed(permutations: u32, seed: u64) -> MinHash {
+        let mut rng = DefaultRng::seed_from(seed);
+        let coefficients = (0..permutations.max(1))
+            .map(|_| (rng.gen::<u64>() | 1, rng.gen::<u64>()))
+            .collect::<Vec<_>>();
+        MinHash {
+            minimums: vec![u64::MAX; coefficients.len()],
+            coefficients,
+            seed,
+            tokens_added: 0,
+        }
+    }
+
+    pub fn add_token(&mut self, token: &str) {
+        let x = token_hash(token);
+        for (min, &(a, b)) in self.minimums.iter_mut().zip(&self.coefficients) {
+            // Multiply-add, then fold the well-mixed high bits down
+            let h = a.wrapping_mul(x).wrapping_add(b);
+            *min = (*min).min(h ^ (h >> 29));
+        }
+        self.tokens_added += 1;
+    }
+
+    pub fn signature_ref(&self) -> &[u64] {
+        &self.minimums
+    }
+
+    pub fn try_estimated_jaccard(&self, other: &MinHash) -> Result<f64, String> {
+        if self.seed != other.seed || self.minimums.len() != other.minimums.len() {
+            return Err(format!(
+                "signatures aren't comparable: {} permutations with seed {} vs {} with seed {}",
+                self.minimums.len(),
+                self.seed,
+                other.minimums.len(),
+                other.seed
+            ));
+        }
+        if self.is_empty() && other.is_empty() {
+            return Ok(1.0);
+        }
+        let equal = self
+            .minimums
+            .iter()
+            .zip(&other.minimums)
+            .filter(|(a, b)| a == b)
+            .count();
+        Ok(equal as f64 / self.minimums.len() as f64)
+    }
+
+    pub fn try_merge(&mut self, other: &MinHash) -> Result<(), String> {
+        self.try_estimated_jaccard(other)?;
+        for (a, b) in self.minimums.iter_mut().zip(&other.minimums) {
+            *a = (*a).min(*b);
+        }
+        self.tokens_added += other.tokens_added;
+        Ok(())
+    }
+}
+
+impl Default for MinHash {
+    fn default() -> Self {
+        Self::new(DEFAULT_PERMUTATIONS)
+    }
+}
+
+#[wasm_bindgen]
+impl MinHash {
+    /// Signature with `permutations` hash functions (at least 1) and the
+    /// default seed.
+    #[wasm_bindgen(constructor)]
+    pub fn new(permutations: u32) -> MinHash {
+        Self::with_seed(permutations, DEFAULT_SEED)
+    }
+
+    pub fn add_tokens(&mut self, tokens: Vec<String>) {
+        for token in &tokens {
+            self.add_token(token);
+        }
+    }
+
+    /// The per-permutation minimums (a `BigUint64Array` in JS).
+    pub fn signature(&self) -> Vec<u64> {
+        self.minimums.clone()
+    }
+
+    /// Estimated Jaccard similarity with `other`, which must use the same
+    /// permutation count and seed.
+    pub fn estimated_jaccard(&self, other: &MinHash) -> Result<f64, JsValue> {
+        self.try_estimated_jaccard(other)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// Fold `other`'s tokens in, as if they had been added here. The result
+    /// is the signature of the union.
+    pub fn merge(&mut self, other: &MinHash) -> Result<(), JsValue> {
+        self.try_merge(other).map_err(|e| JsValue::from_str(&e))
+    }
+
+    pub fn permutations(&self) -> u32 {
+        self.minimums.len() as u32
+    }
+
+    /// Tokens added, counting repeats.
+    pub fn tokens_added(&self) -> u32 {
+        self.tokens_added
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tokens_added == 0
+    }
+}
+
+/// Locality-sensitive index over MinHash signatures.
+///
+/// Signatures are cut into `bands` bands of `rows` values; two items become
+/// candidates if any band matches exactly. Pairs with similarity s collide
+/// with probability 1 - (1 - s^rows)^bands, an S-curve whose steep part sits
+/// near `threshold()`, so a query compares against a few likely neighbours
+/// instead of every stored item.
+///
+/// # Example
+/// ```javascript
+/// const lsh = new MinHashLsh(32, 4);
+/// docs.forEach((doc, i) => lsh.insert(`doc${i}`, doc.minhash));
+/// lsh.query(newDoc.minhash); // ids of likely near-duplicates
+/// ```
+#[wasm_bindgen]
+pub struct MinHashLsh {
+    bands: u32,
+    rows: u32,
+    tables: Vec<HashMap<u64, Vec<String>>>,
+    len: usize,
+}
+
+impl MinHashLsh {
+    fn band_keys<'a>(
+        &self,
+        minhash: &'a MinHash,
+    ) -> Result<impl Iterator<Item = u64> + 'a, String> {
+        let needed = (self.bands * self.rows) as usize;
+        if minhash.minimums.len() < needed {
+            return Err(format!(
+                "{} bands of {} rows need {} permutations, signature has {}",
+                self.bands,
+                self.rows,
+                needed,
+                minhash.minimums.len()
+            ));
+        }
+        Ok(minhash.minimums[..needed]
+            .chunks(self.rows as usize)
+            .map(|band| {
+                let mut hasher = DefaultHasher::new();
+                band.hash(&mut hasher);
+                hasher.finish()
+            }))
+    }
+
+    pub fn try_insert(&mut self, id: String, minhash: &MinHash) -> Result<(), String> {
+        let keys: Vec<u64> = self.band_keys(minhash)?.collect();
+        for (table, key) in self.tables.iter_mut().zip(keys) {
+            table.entry(key).or_default().push(id.clone());
+        }
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Ids sharing at least one band with `minhash`, sorted and deduplicated.
+    pub fn try_query(&self, minhash: &MinHash) -> Result<Vec<String>, String> {
+        let mut found: Vec<String> = self
+            .band_keys(minhash)?
+            .zip(&self.tables)
+            .filter_map(|(key, table)| table.get(&key))
+            .flatten()
+            .cloned()
+            .collect();
+        found.sort();
+        found.dedup();
+        Ok(found)
+    }
+}
+
+#[wasm_bindgen]
+impl MinHashLsh {
+    /// Index using the first `bands * rows` slots of each signature.
+    #[wasm_bindgen(constructor)]
+    pub fn new(bands: u32, rows: u32) -> MinHashLsh {
+        let bands = bands.max(1);
+        MinHashLsh {
+            bands,
+            rows: rows.max(1),
+            tables: (0..bands).map(|_| HashMap::new()).collect(),
+            len: 0,
+        }
+    }
+
+    pub fn insert(&mut self, id: String, minhash: &MinHash) -> Result<(), JsValue> {
+        self.try_insert(id, minhash)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    pub fn query(&self, minhash: &MinHash) -> Result<Vec<JsValue>, JsValue> {
+        self.try_query(minhash)
+            .map(|ids| ids.iter().map(|id| JsValue::from_str(id)).collect())
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// Similarity at which a pair is about as likely as not to collide,
+    /// approximately (1 / bands)^(1 / rows).
+    pub fn threshold(&self) -> f64 {
+        (1.0 / self.bands as f64).powf(1.0 / self.rows as f64)
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn minhash_of(range: std::ops::Range<u32>) -> MinHash {
+        let mut m = MinHash::new(256);
+        for i in range {
+            m.add_token(&format!("token{}", i));
+        }
+        m
+    }
+
+    #[test]
+    fn test_estimates_jaccard() {
+        // |A ∩ B| = 50, |A ∪ B| = 150
+        let a = minhash_of(0..100);
+        let b = minhash_of(50..150);
+        let estimate = a.try_estimated_jaccard(&b).unwrap();
+        assert!((estimate - 1.0 / 3.0).abs() < 0.1, "{}", estimate);
+        assert_eq!(a.try_estimated_jaccard(&minhash_of(0..100)), Ok(1.0));
+        assert!(
+            minhash_of(0..10)
+                .try_estimated_jaccard(&minhash_of(10..20))
+                .unwrap()
+                < 0.1
+        );
+        assert!(a.try_estimated_jaccard(&MinHash::new(64)).is_err());
+
+        let mut union = minhash_of(0..100);
+        union.try_merge(&minhash_of(100..150)).unwrap();
+        assert_eq!(union.signature(), minhash_of(0..150).signature());
+    }
+
+    #[test]
+    fn test_lsh_finds_near_duplicates() {
+        let mut lsh = MinHashLsh::new(32, 8);
+        lsh.try_insert("original".to_string(), &minhash_of(0..100))
+            .unwrap();
+        lsh.try_insert("unrelated".to_string(), &minhash_of(1000..1100))
+            .unwrap();
+        let near_copy = minhash_of(2..100);
+        assert_eq!(lsh.try_query(&near_copy).unwrap(), ["original"]);
+        assert!(lsh
+            .try_insert("short".to_string(), &MinHash::new(8))
+            .is_err());
+        assert!((lsh.threshold() - 0.648).abs() < 0.01);
+    }
+}
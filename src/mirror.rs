@@ -0,0 +1,221 @@
+use crate::kv_store::DynamicStore;
+use std::collections::BTreeSet;
+use wasm_bindgen::prelude::*;
+
+/// One operation where the two mirrored structures disagreed.
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Divergence {
+    /// `"get"`, `"delete"`, `"len"` or `"verify"`.
+    pub operation: String,
+    /// Key involved; empty for `"len"`.
+    pub key: String,
+    /// What the primary returned, e.g. `"3"`, `"missing"` or `"true"`.
+    pub primary: String,
+    pub secondary: String,
+}
+
+/// Applies every write to two structures and checks that reads agree.
+///
+/// Reads return the primary's answer; any disagreement is recorded as a
+/// [`Divergence`] instead of being thrown, so a demo or differential test
+/// can run a whole workload and inspect the log afterwards.
+///
+/// # Example
+/// ```javascript
+/// const mirror = new MirroredStore("hashmap", "rbtree");
+/// mirror.insert("alice", 1);
+/// mirror.get("alice");
+/// mirror.verify();
+/// console.log(mirror.is_consistent(), mirror.divergences());
+/// ```
+#[wasm_bindgen]
+pub struct MirroredStore {
+    primary: DynamicStore,
+    secondary: DynamicStore,
+    // Every key written through the mirror, so `verify` knows what to compare
+    touched: BTreeSet<String>,
+    divergences: Vec<Divergence>,
+}
+
+fn describe(value: Option<u32>) -> String {
+    value.map_or_else(|| "missing".to_string(), |v| v.to_string())
+}
+
+impl MirroredStore {
+    pub fn try_new(primary_kind: &str, secondary_kind: &str) -> Result<MirroredStore, String> {
+        Ok(Self::from_stores(
+            DynamicStore::try_new(primary_kind, 0)?,
+            DynamicStore::try_new(secondary_kind, 0)?,
+        ))
+    }
+
+    pub fn divergences_ref(&self) -> &[Divergence] {
+        &self.divergences
+    }
+
+    fn record(&mut self, operation: &str, key: &str, primary: String, secondary: String) {
+        self.divergences.push(Divergence {
+            operation: operation.to_string(),
+            key: key.to_string(),
+            primary,
+            secondary,
+        });
+    }
+
+    fn check_len(&mut self) {
+        let (a, b) = (self.primary.len(), self.secondary.len());
+        if a != b {
+            self.record("len", "", a.to_string(), b.to_string());
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl MirroredStore {
+    /// Mirror two new, empty structures (see [`STORE_KINDS`](crate::kv_store::STORE_KINDS)).
+    #[wasm_bindgen(constructor)]
+    pub fn new(primary_kind: &str, secondary_kind: &str) -> Result<MirroredStore, JsValue> {
+        Self::try_new(primary_kind, secondary_kind).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// Mirror two existing structures. They should hold the same contents.
+    pub fn from_stores(primary: DynamicStore, secondary: DynamicStore) -> MirroredStore {
+        MirroredStore {
+            primary,
+            secondary,
+            touched: BTreeSet::new(),
+            divergences: Vec::new(),
+        }
+    }
+
+    pub fn insert(&mut self, key: String, value: u32) {
+        self.touched.insert(key.clone());
+        self.primary.insert(key.clone(), value);
+        self.secondary.insert(key, value);
+        self.check_len();
+    }
+
+    /// Look a key up in both structures, returning the primary's answer.
+    pub fn get(&mut self, key: &str) -> Option<u32> {
+        let (a, b) = (self.primary.get(key), self.secondary.get(key));
+        if a != b {
+            self.record("get", key, describe(a), describe(b));
+        }
+        a
+    }
+
+    /// Delete from both structures, returning whether the primary held the key.
+    pub fn delete(&mut self, key: &str) -> bool {
+        let (a, b) = (self.primary.delete(key), self.secondary.delete(key));
+        if a != b {
+            self.record("delete", key, a.to_string(), b.to_string());
+        }
+        self.check_len();
+        a
+    }
+
+    /// Size of the primary.
+    pub fn len(&self) -> usize {
+        self.primary.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Compare both structures on every key ever written through the mirror,
+    /// plus their sizes. Returns the number of new divergences found.
+    pub fn verify(&mut self) -> u32 {
+        let before = self.divergences.len();
+        let keys: Vec<String> = self.touched.iter().cloned().collect();
+        for key in keys {
+            let (a, b) = (self.primary.get(&key), self.secondary.get(&key));
+            if a != b {
+                self.record("verify", &key, describe(a), describe(b));
+            }
+        }
+        self.check_len();
+        (self.divergences.len() - before) as u32
+    }
+
+    /// True if no divergence has been recorded.
+    pub fn is_consistent(&self) -> bool {
+        self.divergences.is_empty()
+    }
+
+    pub fn divergence_count(&self) -> usize {
+        self.divergences.len()
+    }
+
+    pub fn divergences(&self) -> Vec<Divergence> {
+        self.divergences.clone()
+    }
+
+    pub fn clear_divergences(&mut self) {
+        self.divergences.clear();
+    }
+
+    /// A handle to the primary structure.
+    pub fn primary(&self) -> DynamicStore {
+        self.primary.handle()
+    }
+
+    /// A handle to the secondary structure.
+    pub fn secondary(&self) -> DynamicStore {
+        self.secondary.handle()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kv_store::STORE_KINDS;
+
+    #[test]
+    fn test_every_pair_agrees() {
+        for secondary in STORE_KINDS {
+            let mut mirror = MirroredStore::try_new("hashmap", secondary).unwrap();
+            for i in 0..200 {
+                mirror.insert(format!("k{}", i % 150), i);
+            }
+            for i in (0..150).step_by(3) {
+                assert!(mirror.delete(&format!("k{}", i)));
+            }
+            assert!(!mirror.delete("absent"));
+            assert_eq!(mirror.get("k149"), Some(149));
+            assert_eq!(mirror.verify(), 0, "{}", secondary);
+            assert!(mirror.is_consistent(), "{:?}", mirror.divergences_ref());
+            assert_eq!(mirror.len(), 100);
+        }
+    }
+
+    #[test]
+    fn test_out_of_band_write_is_reported() {
+        let primary = DynamicStore::try_new("bst", 0).unwrap();
+        let secondary = DynamicStore::try_new("trie", 0).unwrap();
+        let mut sneaky = secondary.handle();
+        let mut mirror = MirroredStore::from_stores(primary, secondary);
+        mirror.insert("a".to_string(), 1);
+        sneaky.insert("a".to_string(), 2);
+
+        assert_eq!(mirror.get("a"), Some(1));
+        assert_eq!(
+            mirror.divergences_ref()[0],
+            Divergence {
+                operation: "get".to_string(),
+                key: "a".to_string(),
+                primary: "1".to_string(),
+                secondary: "2".to_string(),
+            }
+        );
+
+        sneaky.delete("a");
+        assert_eq!(mirror.verify(), 2);
+        assert_eq!(mirror.divergences_ref()[1].secondary, "missing");
+        assert_eq!(mirror.divergences_ref()[2].operation, "len");
+
+        mirror.clear_divergences();
+        assert!(mirror.is_consistent());
+    }
+}
@@ -0,0 +1,614 @@
+//! MessagePack encoding of entries and snapshots, compatible with JS
+//! libraries such as `@msgpack/msgpack`.
+//!
+//! Entries travel as a map from string keys to unsigned integers, which is
+//! what those libraries produce from a plain object like `{ alice: 1 }`. A
+//! snapshot is a map `{ version: int, kind: string, entries: map, crc32: int }`.
+//! Version 1 snapshots predate the `version` field and version 2 snapshots
+//! the checksum. A delta between snapshots is a map `{ inserted: map,
+//! updated: map, deleted: [string], crc32: int }`.
+
+use crate::interop;
+use crate::kv_store::KvStore;
+use crate::snapshot::{Snapshot, SnapshotDelta, SnapshotError, SNAPSHOT_VERSION};
+use wasm_bindgen::prelude::*;
+
+/// Nesting allowed when skipping unknown values, so hostile input can't
+/// exhaust the stack.
+const MAX_DEPTH: usize = 64;
+
+/// How a snapshot ends: the `crc32` key and the uint32 marker, followed by
+/// the CRC-32 of every byte before this trailer.
+const CHECKSUM_TRAILER: &[u8] = b"\xa5crc32\xce";
+
+/// Lookup table for the CRC-32 (IEEE 802.3) polynomial, reflected.
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                0xedb8_8320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// CRC-32 as used by zlib, gzip and PNG, so JS can verify it with any
+/// standard implementation.
+pub(crate) fn crc32(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!0u32, |crc, &b| {
+        CRC_TABLE[((crc ^ u32::from(b)) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
+
+/// `entries` as a MessagePack map, in order.
+pub(crate) fn encode_entries(entries: &[(String, u32)]) -> Vec<u8> {
+    let mut out = Vec::new();
+    write_entries(&mut out, entries);
+    out
+}
+
+/// The string-to-integer map in `bytes`, in encoded order.
+pub(crate) fn decode_entries(bytes: &[u8]) -> Result<Vec<(String, u32)>, String> {
+    let mut reader = Reader { bytes, pos: 0 };
+    let entries = reader.entries()?;
+    reader.finish()?;
+    Ok(entries)
+}
+
+pub(crate) fn encode_snapshot(snapshot: &Snapshot) -> Vec<u8> {
+    let mut out = Vec::new();
+    write_map_len(&mut out, 4);
+    write_str(&mut out, "version");
+    write_uint(&mut out, SNAPSHOT_VERSION);
+    write_str(&mut out, "kind");
+    write_str(&mut out, &snapshot.kind);
+    write_str(&mut out, "entries");
+    write_entries(&mut out, &snapshot.entries);
+    append_checksum(&mut out);
+    out
+}
+
+fn append_checksum(out: &mut Vec<u8>) {
+    let checksum = crc32(out);
+    out.extend_from_slice(CHECKSUM_TRAILER);
+    out.extend_from_slice(&checksum.to_be_bytes());
+}
+
+pub(crate) fn encode_delta(delta: &SnapshotDelta) -> Vec<u8> {
+    let mut out = Vec::new();
+    write_map_len(&mut out, 4);
+    write_str(&mut out, "inserted");
+    write_entries(&mut out, &delta.inserted);
+    write_str(&mut out, "updated");
+    write_entries(&mut out, &delta.updated);
+    write_str(&mut out, "deleted");
+    write_array_len(&mut out, delta.deleted.len());
+    for key in &delta.deleted {
+        write_str(&mut out, key);
+    }
+    append_checksum(&mut out);
+    out
+}
+
+/// Check the trailing checksum, if the bytes end with one. Done before
+/// decoding so that damage anywhere reports as corruption rather than as
+/// whatever decoding error it happens to cause.
+fn verify_checksum(bytes: &[u8]) -> Result<bool, SnapshotError> {
+    let Some(body) = bytes.len().checked_sub(CHECKSUM_TRAILER.len() + 4) else {
+        return Ok(false);
+    };
+    let (checked, trailer) = bytes.split_at(body);
+    let Some(stored) = trailer.strip_prefix(CHECKSUM_TRAILER) else {
+        return Ok(false);
+    };
+    let stored = u32::from_be_bytes(stored.try_into().expect("4 bytes remain"));
+    let computed = crc32(checked);
+    if stored != computed {
+        return Err(SnapshotError::Corrupt { stored, computed });
+    }
+    Ok(true)
+}
+
+/// A snapshot map, checked against its checksum and migrated to the
+/// current schema version. Fields may come in any order, except that the
+/// checksum is last, and unknown ones are skipped.
+pub(crate) fn decode_snapshot(bytes: &[u8]) -> Result<Snapshot, SnapshotError> {
+    let checksummed = verify_checksum(bytes)?;
+    let mut reader = Reader { bytes, pos: 0 };
+    let mut version = 1;
+    let mut kind = None;
+    let mut entries = None;
+    for _ in 0..reader.map_len()? {
+        match reader.str()? {
+            "version" => version = reader.u32()?,
+            "kind" => kind = Some(reader.str()?.to_string()),
+            "entries" => entries = Some(reader.entries()?),
+            "crc32" if checksummed && reader.pos == bytes.len() - 5 => reader.skip(0)?,
+            "crc32" => {
+                return Err(SnapshotError::Malformed(
+                    "checksum must be the last field, as a uint32".to_string(),
+                ))
+            }
+            _ => reader.skip(0)?,
+        }
+    }
+    reader.finish()?;
+    if (3..=SNAPSHOT_VERSION).contains(&version) && !checksummed {
+        return Err(SnapshotError::Malformed(format!(
+            "version {} snapshot has no checksum",
+            version
+        )));
+    }
+    let snapshot = Snapshot {
+        kind: kind.ok_or("snapshot has no 'kind' field".to_string())?,
+        entries: entries.ok_or("snapshot has no 'entries' field".to_string())?,
+    };
+    Snapshot::migrate(version, snapshot)
+}
+
+/// A delta map, checked against its checksum, which unlike a snapshot's
+/// is always required. Missing fields are empty and unknown ones skipped.
+pub(crate) fn decode_delta(bytes: &[u8]) -> Result<SnapshotDelta, SnapshotError> {
+    if !verify_checksum(bytes)? {
+        return Err(SnapshotError::Malformed(
+            "delta has no checksum".to_string(),
+        ));
+    }
+    let mut reader = Reader { bytes, pos: 0 };
+    let mut delta = SnapshotDelta::default();
+    for _ in 0..reader.map_len()? {
+        match reader.str()? {
+            "inserted" => delta.inserted = reader.entries()?,
+            "updated" => delta.updated = reader.entries()?,
+            "deleted" => {
+                let len = reader.array_len()?;
+                delta.deleted = Vec::with_capacity(len.min(bytes.len()));
+                for _ in 0..len {
+                    delta.deleted.push(reader.str()?.to_string());
+                }
+            }
+            "crc32" if reader.pos == bytes.len() - 5 => reader.skip(0)?,
+            "crc32" => {
+                return Err(SnapshotError::Malformed(
+                    "checksum must be the last field, as a uint32".to_string(),
+                ))
+            }
+            _ => reader.skip(0)?,
+        }
+    }
+    reader.finish()?;
+    Ok(delta)
+}
+
+/// Build a structure from an encoded entries map, as
+/// [`interop::from_map`] does from a JS `Map`.
+pub(crate) fn from_msgpack<S: KvStore>(
+    bytes: &[u8],
+    make: impl FnOnce(usize) -> S,
+) -> Result<S, JsValue> {
+    let entries = decode_entries(bytes).map_err(|e| JsValue::from_str(&e))?;
+    Ok(interop::fill(make(entries.len()), entries))
+}
+
+/// The store's entries as a MessagePack map, in [`KvStore::kv_entries`] order.
+pub(crate) fn to_msgpack(store: &dyn KvStore) -> Vec<u8> {
+    encode_entries(&store.kv_entries())
+}
+
+fn write_entries(out: &mut Vec<u8>, entries: &[(String, u32)]) {
+    write_map_len(out, entries.len());
+    for (key, value) in entries {
+        write_str(out, key);
+        write_uint(out, *value);
+    }
+}
+
+fn write_map_len(out: &mut Vec<u8>, len: usize) {
+    match len {
+        0..=15 => out.push(0x80 | len as u8),
+        16..=0xffff => {
+            out.push(0xde);
+            out.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        _ => {
+            out.push(0xdf);
+            out.extend_from_slice(&(len as u32).to_be_bytes());
+        }
+    }
+}
+
+fn write_array_len(out: &mut Vec<u8>, len: usize) {
+    match len {
+        0..=15 => out.push(0x90 | len as u8),
+        16..=0xffff => {
+            out.push(0xdc);
+            out.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        _ => {
+            out.push(0xdd);
+            out.extend_from_slice(&(len as u32).to_be_bytes());
+        }
+    }
+}
+
+fn write_str(out: &mut Vec<u8>, s: &str) {
+    let len = s.len();
+    match len {
+        0..=31 => out.push(0xa0 | len as u8),
+        32..=0xff => out.extend_from_slice(&[0xd9, len as u8]),
+        0x100..=0xffff => {
+            out.push(0xda);
+            out.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        _ => {
+            out.push(0xdb);
+            out.extend_from_slice(&(len as u32).to_be_bytes());
+        }
+    }
+    out.extend_from_slice(s.as_bytes());
+}
+
+fn write_uint(out: &mut Vec<u8>, value: u32) {
+    match value {
+        0..=0x7f => out.push(value as u8),
+        0x80..=0xff => out.extend_from_slice(&[0xcc, value as u8]),
+        0x100..=0xffff => {
+            out.push(0xcd);
+            out.extend_from_slice(&(value as u16).to_be_bytes());
+        }
+        _ => {
+            out.push(0xce);
+            out.extend_from_slice(&value.to_be_bytes());
+        }
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|&end| end <= self.bytes.len())
+            .ok_or_else(|| "MessagePack data is truncated".to_string())?;
+        let slice = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn byte(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    /// Big-endian unsigned integer of `len` bytes.
+    fn be(&mut self, len: usize) -> Result<u64, String> {
+        Ok(self
+            .take(len)?
+            .iter()
+            .fold(0, |acc, &b| acc << 8 | u64::from(b)))
+    }
+
+    fn finish(&self) -> Result<(), String> {
+        if self.pos == self.bytes.len() {
+            Ok(())
+        } else {
+            Err("trailing bytes after MessagePack value".to_string())
+        }
+    }
+
+    fn map_len(&mut self) -> Result<usize, String> {
+        let len = match self.byte()? {
+            tag @ 0x80..=0x8f => u64::from(tag & 0x0f),
+            0xde => self.be(2)?,
+            0xdf => self.be(4)?,
+            tag => return Err(format!("expected a map, found type 0x{:02x}", tag)),
+        };
+        Ok(len as usize)
+    }
+
+    fn array_len(&mut self) -> Result<usize, String> {
+        let len = match self.byte()? {
+            tag @ 0x90..=0x9f => u64::from(tag & 0x0f),
+            0xdc => self.be(2)?,
+            0xdd => self.be(4)?,
+            tag => return Err(format!("expected an array, found type 0x{:02x}", tag)),
+        };
+        Ok(len as usize)
+    }
+
+    fn str(&mut self) -> Result<&'a str, String> {
+        let len = match self.byte()? {
+            tag @ 0xa0..=0xbf => u64::from(tag & 0x1f),
+            0xd9 => self.be(1)?,
+            0xda => self.be(2)?,
+            0xdb => self.be(4)?,
+            tag => return Err(format!("expected a string, found type 0x{:02x}", tag)),
+        };
+        std::str::from_utf8(self.take(len as usize)?)
+            .map_err(|_| "string is not valid UTF-8".to_string())
+    }
+
+    /// Any integer encoding, or a float with an integral value (some
+    /// encoders write every JS number as a float), that fits a `u32`.
+    fn u32(&mut self) -> Result<u32, String> {
+        let tag = self.byte()?;
+        let value: f64 = match tag {
+            0x00..=0x7f => f64::from(tag),
+            0xe0..=0xff => f64::from(tag as i8),
+            0xcc => self.be(1)? as f64,
+            0xcd => self.be(2)? as f64,
+            0xce => self.be(4)? as f64,
+            0xcf => self.be(8)? as f64,
+            0xd0 => f64::from(self.be(1)? as u8 as i8),
+            0xd1 => f64::from(self.be(2)? as u16 as i16),
+            0xd2 => f64::from(self.be(4)? as u32 as i32),
+            0xd3 => self.be(8)? as i64 as f64,
+            0xca => f64::from(f32::from_bits(self.be(4)? as u32)),
+            0xcb => f64::from_bits(self.be(8)?),
+            _ => return Err(format!("expected a number, found type 0x{:02x}", tag)),
+        };
+        if value.fract() == 0.0 && (0.0..=u32::MAX as f64).contains(&value) {
+            Ok(value as u32)
+        } else {
+            Err(format!(
+                "value {} is not an integer between 0 and {}",
+                value,
+                u32::MAX
+            ))
+        }
+    }
+
+    fn entries(&mut self) -> Result<Vec<(String, u32)>, String> {
+        let len = self.map_len()?;
+        // Every entry takes at least two bytes, so a bogus length can't
+        // reserve more than the input justifies
+        let mut entries = Vec::with_capacity(len.min(self.bytes.len() / 2));
+        for _ in 0..len {
+            let key = self.str()?.to_string();
+            let value = self.u32().map_err(|e| format!("key '{}': {}", key, e))?;
+            entries.push((key, value));
+        }
+        Ok(entries)
+    }
+
+    /// Step over one value of any type.
+    fn skip(&mut self, depth: usize) -> Result<(), String> {
+        if depth > MAX_DEPTH {
+            return Err("MessagePack data is nested too deeply".to_string());
+        }
+        let tag = self.byte()?;
+        let (skip, items) = match tag {
+            0x00..=0x7f | 0xe0..=0xff | 0xc0 | 0xc2 | 0xc3 => (0, 0),
+            0x80..=0x8f => (0, 2 * u64::from(tag & 0x0f)),
+            0x90..=0x9f => (0, u64::from(tag & 0x0f)),
+            0xa0..=0xbf => (u64::from(tag & 0x1f), 0),
+            0xc4 | 0xd9 => (self.be(1)?, 0),
+            0xc5 | 0xda => (self.be(2)?, 0),
+            0xc6 | 0xdb => (self.be(4)?, 0),
+            0xc7 => (self.be(1)? + 1, 0),
+            0xc8 => (self.be(2)? + 1, 0),
+            0xc9 => (self.be(4)? + 1, 0),
+            0xca | 0xce | 0xd2 => (4, 0),
+            0xcb | 0xcf | 0xd3 => (8, 0),
+            0xcc | 0xd0 => (1, 0),
+            0xcd | 0xd1 => (2, 0),
+            0xd4..=0xd8 => (1 + (1 << (tag - 0xd4)), 0),
+            0xdc => (0, self.be(2)?),
+            0xdd => (0, self.be(4)?),
+            0xde => (0, 2 * self.be(2)?),
+            0xdf => (0, 2 * self.be(4)?),
+            0xc1 => return Err("invalid MessagePack type 0xc1".to_string()),
+        };
+        let skip = usize::try_from(skip)
+            .map_err(|_| format!("MessagePack value of {} bytes is too large", skip))?;
+        self.take(skip)?;
+        for _ in 0..items {
+            self.skip(depth + 1)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let entries: Vec<(String, u32)> = [0, 127, 128, 255, 256, 65_535, 65_536, u32::MAX]
+            .iter()
+            .enumerate()
+            .map(|(i, &v)| ("k".repeat(i * 9), v))
+            .chain((0..40).map(|i| (format!("key{}", i), i)))
+            .collect();
+        let bytes = encode_entries(&entries);
+        assert_eq!(decode_entries(&bytes).unwrap(), entries);
+        // Lengths use the smallest form: 48 entries need map16
+        assert_eq!(&bytes[..3], &[0xde, 0, 48]);
+        assert!(decode_entries(&bytes[..bytes.len() - 1]).is_err());
+
+        let snapshot = Snapshot {
+            kind: "rbtree".to_string(),
+            entries,
+        };
+        assert_eq!(
+            decode_snapshot(&encode_snapshot(&snapshot)).unwrap(),
+            snapshot
+        );
+    }
+
+    #[test]
+    fn test_decodes_foreign_encodings() {
+        // {"b": 2.0 (float64), "a": 1 (int8), "c": 300 (uint64)} as a JS
+        // encoder might write it
+        let mut bytes = vec![0x83, 0xa1, b'b', 0xcb];
+        bytes.extend_from_slice(&2.0f64.to_be_bytes());
+        bytes.extend_from_slice(&[0xa1, b'a', 0xd0, 1, 0xa1, b'c', 0xcf]);
+        bytes.extend_from_slice(&300u64.to_be_bytes());
+        let expected = [("b", 2), ("a", 1), ("c", 300)];
+        let decoded = decode_entries(&bytes).unwrap();
+        assert!(decoded.iter().map(|(k, v)| (k.as_str(), *v)).eq(expected));
+
+        // Negative, fractional and non-numeric values are rejected by key
+        for (value, message) in [(vec![0xff], "-1"), (vec![0xc0], "type 0xc0")] {
+            let mut bytes = vec![0x81, 0xa1, b'x'];
+            bytes.extend(value);
+            let err = decode_entries(&bytes).unwrap_err();
+            assert!(err.contains("key 'x'") && err.contains(message), "{}", err);
+        }
+
+        // A version 1 snapshot: no version field, fields in another order,
+        // plus an unknown nested field
+        let mut bytes = vec![0x83, 0xa7];
+        bytes.extend_from_slice(b"entries");
+        bytes.extend_from_slice(&[0x81, 0xa1, b'k', 7, 0xa4]);
+        bytes.extend_from_slice(b"meta");
+        bytes.extend_from_slice(&[0x92, 0xc3, 0x81, 0xa1, b'n', 0xc0, 0xa4]);
+        bytes.extend_from_slice(b"kind");
+        bytes.extend_from_slice(&[0xa3]);
+        bytes.extend_from_slice(b"bst");
+        let snapshot = decode_snapshot(&bytes).unwrap();
+        assert_eq!(snapshot.kind, "bst");
+        assert_eq!(snapshot.entries, [("k".to_string(), 7)]);
+
+        let deep: Vec<u8> = [0x81, 0xa1, b'z']
+            .into_iter()
+            .chain(std::iter::repeat_n(0x91, 100))
+            .collect();
+        assert!(decode_snapshot(&deep)
+            .unwrap_err()
+            .to_string()
+            .contains("nested"));
+
+        let mut future = vec![0x83, 0xa7];
+        future.extend_from_slice(b"version");
+        future.extend_from_slice(&[SNAPSHOT_VERSION as u8 + 1, 0xa4]);
+        future.extend_from_slice(b"kind");
+        future.extend_from_slice(&[0xa3]);
+        future.extend_from_slice(b"bst");
+        future.extend_from_slice(&[0xa7]);
+        future.extend_from_slice(b"entries");
+        future.push(0x80);
+        let err = decode_snapshot(&future).unwrap_err();
+        assert_eq!(err, SnapshotError::UnsupportedVersion(SNAPSHOT_VERSION + 1));
+    }
+
+    #[test]
+    fn test_skip_rejects_oversized_ext() {
+        // An unknown field holding an ext32 that claims 0xffff_ffff bytes;
+        // with the type byte that is 2^32, which must not wrap to 0 on wasm32
+        let mut bytes = vec![0x83, 0xa4];
+        bytes.extend_from_slice(b"meta");
+        bytes.extend_from_slice(&[0xc9, 0xff, 0xff, 0xff, 0xff, 0x01]);
+        bytes.extend_from_slice(&[0xa4]);
+        bytes.extend_from_slice(b"kind");
+        bytes.extend_from_slice(&[0xa3]);
+        bytes.extend_from_slice(b"bst");
+        bytes.extend_from_slice(&[0xa7]);
+        bytes.extend_from_slice(b"entries");
+        bytes.push(0x80);
+        let err = decode_snapshot(&bytes).unwrap_err().to_string();
+        assert!(
+            err.contains("too large") || err.contains("truncated"),
+            "{}",
+            err
+        );
+
+        let mut reader = Reader {
+            bytes: &[0xc9, 0xff, 0xff, 0xff, 0xff, 0x01, 0xc0],
+            pos: 0,
+        };
+        assert!(reader.skip(0).is_err());
+    }
+
+    #[test]
+    fn test_checksum_detects_corruption() {
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+
+        let snapshot = Snapshot {
+            kind: "hashmap".to_string(),
+            entries: (0..20).map(|i| (format!("user{}", i), i * 7)).collect(),
+        };
+        let bytes = encode_snapshot(&snapshot);
+        assert_eq!(decode_snapshot(&bytes).unwrap(), snapshot);
+        let body = bytes.len() - CHECKSUM_TRAILER.len() - 4;
+        for i in 0..bytes.len() {
+            let mut damaged = bytes.clone();
+            damaged[i] ^= 0x10;
+            let err = decode_snapshot(&damaged).unwrap_err();
+            if i < body || i >= body + CHECKSUM_TRAILER.len() {
+                assert_eq!(err.name(), "SnapshotCorrupt", "byte {}: {}", i, err);
+            }
+        }
+
+        // Stripping the checksum off a current snapshot is caught too
+        let mut stripped = bytes[..body].to_vec();
+        stripped[0] = 0x83;
+        let err = decode_snapshot(&stripped).unwrap_err();
+        assert!(err.to_string().contains("no checksum"), "{}", err);
+    }
+
+    #[test]
+    fn test_delta_round_trip() {
+        let delta = SnapshotDelta {
+            inserted: vec![("new".to_string(), 1)],
+            updated: (0..20).map(|i| (format!("key{}", i), i)).collect(),
+            deleted: (0..20).map(|i| format!("gone{}", i)).collect(),
+        };
+        let bytes = encode_delta(&delta);
+        assert_eq!(decode_delta(&bytes).unwrap(), delta);
+        let mut damaged = bytes.clone();
+        damaged[5] ^= 0x10;
+        assert_eq!(
+            decode_delta(&damaged).unwrap_err().name(),
+            "SnapshotCorrupt"
+        );
+        let err = decode_delta(&encode_entries(&delta.updated)).unwrap_err();
+        assert!(err.to_string().contains("no checksum"), "{}", err);
+    }
+
+    #[test]
+    fn test_dynamic_store_delta() {
+        let mut primary = crate::DynamicStore::try_new("bst", 0).unwrap();
+        for i in 0..100 {
+            primary.insert(format!("key{:02}", i), i);
+        }
+        let older = primary.to_msgpack_snapshot();
+        let mut replica = crate::DynamicStore::from_msgpack_snapshot(&older)
+            .unwrap_or_else(|_| panic!("snapshot should decode"));
+        primary.insert("key05".to_string(), 500);
+        primary.delete("key06");
+        primary.insert("new".to_string(), 1);
+        let delta = crate::DynamicStore::diff_snapshot(&older, &primary.to_msgpack_snapshot())
+            .unwrap_or_else(|_| panic!("snapshots should decode"));
+        assert!(delta.len() < older.len() / 4);
+        assert!(replica.apply_delta(&delta).is_ok());
+        assert_eq!(replica.store().kv_entries(), primary.store().kv_entries());
+    }
+
+    #[test]
+    fn test_dynamic_store_snapshot() {
+        let mut store = crate::DynamicStore::try_new("skiplist", 0).unwrap();
+        for i in 0..50 {
+            store.insert(format!("key{:02}", i), i);
+        }
+        let restored = crate::DynamicStore::from_msgpack_snapshot(&store.to_msgpack_snapshot())
+            .unwrap_or_else(|_| panic!("snapshot should decode"));
+        assert_eq!(restored.kind(), "skiplist");
+        assert_eq!(restored.store().kv_entries(), store.store().kv_entries());
+    }
+}
@@ -1,14 +1,35 @@
-use std::collections::hash_map::DefaultHasher;
-use std::hash::{Hash, Hasher};
+use crate::ascii;
+use crate::builders::MetricsMode;
+use crate::bulk::BulkInsertJob;
+use crate::capacity::CapacityConfig;
+use crate::csv;
+use crate::hashing::HashFunction;
+use crate::health::{AutoTune, HealthReport, Severity, TuningEvent};
+use crate::interop;
+use crate::memory::{MemoryReport, MemoryUsage};
+#[cfg(feature = "msgpack")]
+use crate::msgpack;
+use std::mem::size_of;
 use wasm_bindgen::prelude::*;
 
+/// Occupancy (live entries plus tombstones) above which linear probing
+/// slows noticeably; `analyze()` warns and auto-tuning grows the table.
+const MAX_OCCUPANCY: f64 = 0.7;
+/// Share of occupied slots that may be tombstones before `analyze()` warns
+/// and auto-tuning compacts them away.
+const MAX_TOMBSTONE_RATIO: f64 = 0.25;
+
 /// Hash table using open addressing with linear probing
 #[wasm_bindgen]
 pub struct OpenAddressingHashTable {
     table: Vec<Option<Entry>>,
     size: u32,
     capacity: u32,
+    config: CapacityConfig,
     metrics: OpenAddressingMetrics,
+    metrics_mode: MetricsMode,
+    hash_function: HashFunction,
+    auto_tune: AutoTune,
 }
 
 /// Individual hash table entry
@@ -28,21 +49,78 @@ pub struct OpenAddressingMetrics {
     pub load_factor: f32,
     pub clustering_factor: f32,
     pub tombstone_count: u32,
+    pub resize_count: u32,
+}
+
+/// Where one live key ended up relative to the slot it hashes to.
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct SlotDisplacement {
+    pub key: String,
+    /// Slot the key hashes to.
+    pub home: u32,
+    /// Slot the key actually occupies.
+    pub index: u32,
+    /// Probe steps from `home` to `index`, wrapping around the table end.
+    pub displacement: u32,
+}
+
+/// Displacement of every live key, from [`OpenAddressingHashTable::displacement_report`].
+///
+/// Under linear probing, primary clustering shows up as a long tail of
+/// large displacements and a variance well above the mean.
+#[wasm_bindgen]
+#[derive(Clone, Debug, Default)]
+pub struct DisplacementReport {
+    slots: Vec<SlotDisplacement>,
+    mean: f64,
+    variance: f64,
+    max: u32,
+}
+
+impl DisplacementReport {
+    pub fn slots_ref(&self) -> &[SlotDisplacement] {
+        &self.slots
+    }
 }
 
 #[wasm_bindgen]
+impl DisplacementReport {
+    /// One entry per live key, in slot order.
+    pub fn slots(&self) -> Vec<SlotDisplacement> {
+        self.slots.clone()
+    }
+
+    pub fn mean(&self) -> f64 {
+        self.mean
+    }
+
+    /// Population variance of the displacements.
+    pub fn variance(&self) -> f64 {
+        self.variance
+    }
+
+    pub fn max(&self) -> u32 {
+        self.max
+    }
+
+    /// Number of keys sitting in their home slot.
+    pub fn at_home(&self) -> usize {
+        self.slots.iter().filter(|s| s.displacement == 0).count()
+    }
+}
+
 impl OpenAddressingHashTable {
-    /// Create new hash table with fixed capacity
-    #[wasm_bindgen(constructor)]
-    pub fn new(capacity: u32) -> OpenAddressingHashTable {
-        let mut table = Vec::with_capacity(capacity as usize);
-        for _ in 0..capacity {
-            table.push(None);
-        }
+    /// Empty table of `capacity` slots following `config`, which is assumed valid.
+    pub(crate) fn with_capacity_config(
+        capacity: u32,
+        config: CapacityConfig,
+    ) -> OpenAddressingHashTable {
         OpenAddressingHashTable {
-            table,
+            table: (0..capacity).map(|_| None).collect(),
             size: 0,
             capacity,
+            config,
             metrics: OpenAddressingMetrics {
                 total_insertions: 0,
                 total_probes: 0,
@@ -50,50 +128,74 @@ impl OpenAddressingHashTable {
                 load_factor: 0.0,
                 clustering_factor: 0.0,
                 tombstone_count: 0,
+                resize_count: 0,
             },
+            metrics_mode: MetricsMode::Full,
+            hash_function: HashFunction::SipHash,
+            auto_tune: AutoTune::default(),
         }
     }
 
-    /// Hash a string key using FNV-like algorithm
-    fn hash_key(key: &str) -> u64 {
-        let mut hasher = DefaultHasher::new();
-        key.hash(&mut hasher);
-        hasher.finish()
+    /// Every live entry, in slot order.
+    pub(crate) fn pairs(&self) -> Vec<(String, u32)> {
+        self.table
+            .iter()
+            .flatten()
+            .filter(|entry| !entry.tombstone)
+            .map(|entry| (entry.key.clone(), entry.value))
+            .collect()
     }
 
-    /// Get bucket index from hash
-    fn bucket_index(hash: u64, capacity: u32) -> usize {
-        (hash % capacity as u64) as usize
+    pub(crate) fn set_metrics_mode(&mut self, mode: MetricsMode) {
+        self.metrics_mode = mode;
+    }
+
+    pub(crate) fn set_hash_function(&mut self, hash_function: HashFunction) {
+        self.hash_function = hash_function;
+    }
+
+    /// A table with the default growth policy, starting at twice `len`
+    /// slots so `len` entries fit without resizing.
+    fn sized_for(len: usize) -> OpenAddressingHashTable {
+        let config = CapacityConfig::new();
+        let capacity = config.initial_capacity((len as u32).saturating_mul(2));
+        Self::with_capacity_config(capacity, config)
+    }
+
+    /// Create a resizable table following `config`, starting at its minimum capacity.
+    pub fn try_with_config(config: CapacityConfig) -> Result<OpenAddressingHashTable, String> {
+        config.validate()?;
+        Ok(Self::with_capacity_config(
+            config.initial_capacity(0),
+            config,
+        ))
     }
 
-    /// Insert or update a key-value pair
-    pub fn insert(&mut self, key: String, value: u32) {
-        let hash = Self::hash_key(&key);
+    /// Insert or update a key-value pair, failing if the key is new and
+    /// the table is full at its `max_capacity`.
+    ///
+    /// New keys reuse the first tombstone seen on the probe path, so
+    /// delete-heavy workloads don't slowly fill the table with dead slots.
+    pub fn try_insert(&mut self, key: String, value: u32) -> Result<(), String> {
+        let hash = self.hash_key(&key);
         let capacity = self.capacity as usize;
         let mut index = Self::bucket_index(hash, self.capacity);
         let mut probe_count = 0;
+        let mut first_tombstone: Option<usize> = None;
 
         // Linear probing: find empty slot or matching key
         loop {
             match &self.table[index] {
                 None => {
-                    // Found empty slot
-                    self.table[index] = Some(Entry {
-                        key,
-                        value,
-                        tombstone: false,
-                    });
-                    self.size += 1;
-                    self.metrics.total_insertions += 1;
-                    self.metrics.total_probes += probe_count;
-                    if probe_count > self.metrics.max_probe_length {
-                        self.metrics.max_probe_length = probe_count;
-                    }
-                    self.update_load_factor();
-                    return;
+                    // Found empty slot (or an earlier tombstone to recycle)
+                    let slot = first_tombstone.unwrap_or(index);
+                    self.place_new(slot, key, value, probe_count);
+                    return Ok(());
                 }
                 Some(entry) => {
-                    if entry.key == key && !entry.tombstone {
+                    if entry.tombstone {
+                        first_tombstone.get_or_insert(index);
+                    } else if entry.key == key {
                         // Update existing key
                         self.table[index] = Some(Entry {
                             key,
@@ -102,24 +204,240 @@ impl OpenAddressingHashTable {
                         });
                         self.metrics.total_insertions += 1;
                         self.metrics.total_probes += probe_count;
-                        return;
+                        return Ok(());
                     }
                     // Slot occupied, probe next
                     probe_count += 1;
                     index = (index + 1) % capacity;
 
                     // Safety: prevent infinite loop
-                    if probe_count > capacity as u32 {
-                        panic!("Hash table is full");
+                    if probe_count >= capacity as u32 {
+                        match first_tombstone {
+                            Some(slot) => {
+                                self.place_new(slot, key, value, probe_count);
+                                return Ok(());
+                            }
+                            None => return Err("table is full at max_capacity".to_string()),
+                        }
                     }
                 }
             }
         }
     }
 
+    /// Re-place every live entry into a fresh table of `capacity` slots,
+    /// dropping tombstones along the way.
+    fn rehash_into(&mut self, capacity: u32) {
+        let slots = capacity as usize;
+        let old = std::mem::replace(&mut self.table, (0..capacity).map(|_| None).collect());
+        self.capacity = capacity;
+        for mut entry in old.into_iter().flatten().filter(|e| !e.tombstone) {
+            entry.key.shrink_to_fit();
+            let mut index = Self::bucket_index(self.hash_key(&entry.key), capacity);
+            while self.table[index].is_some() {
+                index = (index + 1) % slots;
+            }
+            self.table[index] = Some(entry);
+        }
+        self.metrics.tombstone_count = 0;
+        self.update_load_factor();
+    }
+
+    /// Count a write and, with auto-tuning on, compact tombstones or grow
+    /// the table when `analyze()` would flag them.
+    fn tune(&mut self) {
+        if !self.auto_tune.tick() {
+            return;
+        }
+        let tombstones = self.metrics.tombstone_count;
+        let occupied = self.size + tombstones;
+        if occupied > 0 && tombstones as f64 / occupied as f64 > MAX_TOMBSTONE_RATIO {
+            self.rehash_into(self.capacity);
+            self.auto_tune.record(
+                "compact",
+                "tombstones",
+                format!("purged {} tombstones", tombstones),
+            );
+        } else if occupied as f64 / self.capacity as f64 > MAX_OCCUPANCY {
+            let from = self.capacity;
+            self.resize(from * 2);
+            self.auto_tune.record(
+                "resize",
+                "high_load_factor",
+                format!("{} -> {} slots", from, self.capacity),
+            );
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl OpenAddressingHashTable {
+    /// Create new hash table with fixed capacity
+    #[wasm_bindgen(constructor)]
+    pub fn new(capacity: u32) -> OpenAddressingHashTable {
+        Self::with_capacity_config(capacity, CapacityConfig::fixed(capacity))
+    }
+
+    /// Create a table that grows (and optionally shrinks) according to `config`.
+    ///
+    /// # Example
+    /// ```javascript
+    /// const table = OpenAddressingHashTable.with_config(new CapacityConfig().with_max_load_factor(0.5));
+    /// ```
+    pub fn with_config(config: &CapacityConfig) -> Result<OpenAddressingHashTable, JsValue> {
+        Self::try_with_config(*config).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// Current number of slots.
+    pub fn capacity(&self) -> u32 {
+        self.capacity
+    }
+
+    /// Check slot occupancy, tombstones, clustering and the hash function,
+    /// with a recommendation for each problem found (see [`HealthReport`]).
+    ///
+    /// Tombstones count towards occupancy: probes walk over them just like
+    /// live entries.
+    pub fn analyze(&self) -> HealthReport {
+        let mut report = HealthReport::default();
+        let tombstones = self.metrics.tombstone_count;
+        let occupied = self.size + tombstones;
+        let occupancy = occupied as f64 / self.capacity as f64;
+        if occupancy > MAX_OCCUPANCY {
+            report.add(
+                if occupancy > 0.9 {
+                    Severity::Critical
+                } else {
+                    Severity::Warning
+                },
+                "high_load_factor",
+                format!(
+                    "{:.0}% of {} slots occupied ({} live, {} tombstones)",
+                    occupancy * 100.0,
+                    self.capacity,
+                    self.size,
+                    tombstones
+                ),
+                "resize recommended: keep linear probing below about 70% full",
+            );
+        }
+        if occupied > 0 && tombstones as f64 / occupied as f64 > MAX_TOMBSTONE_RATIO {
+            report.add(
+                Severity::Warning,
+                "tombstones",
+                format!(
+                    "{} of {} occupied slots are tombstones",
+                    tombstones, occupied
+                ),
+                "consider rehash: shrink_to_fit() or resize() purges tombstones",
+            );
+        }
+        let displacement = self.displacement_report();
+        if displacement.max() > 32 {
+            report.add(
+                Severity::Warning,
+                "clustering",
+                format!(
+                    "a key sits {} slots past its home (mean {:.1})",
+                    displacement.max(),
+                    displacement.mean()
+                ),
+                "primary clustering: lower the load factor or check the hash function",
+            );
+        }
+        if self.hash_function.is_degenerate() {
+            report.add(
+                Severity::Warning,
+                "degenerate_hash",
+                format!(
+                    "keys are hashed with {:?}, which forces collisions",
+                    self.hash_function
+                ),
+                "build with HashFunction.SipHash outside demos and tests",
+            );
+        }
+        report
+    }
+
+    /// Turn auto-tuning on or off (off by default). While on, the table
+    /// purges tombstones when `analyze()` would report `tombstones` and
+    /// doubles its capacity when it would report `high_load_factor`,
+    /// logging each action to `tuning_log()`.
+    pub fn set_auto_tune(&mut self, enabled: bool) {
+        self.auto_tune.enabled = enabled;
+    }
+
+    pub fn auto_tune(&self) -> bool {
+        self.auto_tune.enabled
+    }
+
+    /// Actions auto-tuning has taken, oldest first.
+    pub fn tuning_log(&self) -> Vec<TuningEvent> {
+        self.auto_tune.log().to_vec()
+    }
+
+    /// Hash function applied to keys (`SipHash` unless configured with
+    /// `OpenAddressingBuilder`).
+    pub fn hash_function(&self) -> HashFunction {
+        self.hash_function
+    }
+
+    /// The growth policy in effect. Tables made with `new` have a fixed one.
+    pub fn capacity_config(&self) -> CapacityConfig {
+        self.config
+    }
+
+    /// Rehash into `capacity` slots (never fewer than the live entry count).
+    pub fn resize(&mut self, capacity: u32) {
+        self.rehash_into(capacity.max(self.size).max(1));
+        self.metrics.resize_count += 1;
+    }
+
+    /// Hash a string key with the configured [`HashFunction`]
+    fn hash_key(&self, key: &str) -> u64 {
+        self.hash_function.hash(key)
+    }
+
+    /// Get bucket index from hash
+    fn bucket_index(hash: u64, capacity: u32) -> usize {
+        (hash % capacity as u64) as usize
+    }
+
+    /// Insert or update a key-value pair. Throws if the key is new and
+    /// every slot is taken, which only happens once a table capped with
+    /// `max_capacity` is full.
+    pub fn insert(&mut self, key: String, value: u32) -> Result<(), JsValue> {
+        self.try_insert(key, value)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// Store a new key in `slot` and record insertion metrics
+    fn place_new(&mut self, slot: usize, key: String, value: u32, probe_count: u32) {
+        if self.table[slot].is_some() {
+            // Recycling a tombstone
+            self.metrics.tombstone_count = self.metrics.tombstone_count.saturating_sub(1);
+        }
+        self.table[slot] = Some(Entry {
+            key,
+            value,
+            tombstone: false,
+        });
+        self.size += 1;
+        self.metrics.total_insertions += 1;
+        self.metrics.total_probes += probe_count;
+        if probe_count > self.metrics.max_probe_length {
+            self.metrics.max_probe_length = probe_count;
+        }
+        match self.config.grow_target(self.capacity, self.size) {
+            Some(capacity) => self.resize(capacity),
+            None => self.update_load_factor(),
+        }
+        self.tune();
+    }
+
     /// Get value for key
     pub fn get(&mut self, key: &str) -> Option<u32> {
-        let hash = Self::hash_key(key);
+        let hash = self.hash_key(key);
         let capacity = self.capacity as usize;
         let mut index = Self::bucket_index(hash, self.capacity);
         let mut probe_count = 0;
@@ -151,7 +469,7 @@ impl OpenAddressingHashTable {
 
     /// Delete key (mark as tombstone)
     pub fn delete(&mut self, key: &str) -> Option<u32> {
-        let hash = Self::hash_key(key);
+        let hash = self.hash_key(key);
         let capacity = self.capacity as usize;
         let mut index = Self::bucket_index(hash, self.capacity);
 
@@ -173,13 +491,15 @@ impl OpenAddressingHashTable {
             if let Some(value) = found_value {
                 self.size = self.size.saturating_sub(1);
                 self.metrics.tombstone_count += 1;
-                self.update_load_factor();
+                match self.config.shrink_target(self.capacity, self.size) {
+                    Some(capacity) => self.resize(capacity),
+                    None => self.update_load_factor(),
+                }
+                self.tune();
                 return Some(value);
             }
 
-            if let None = &self.table[index] {
-                return None;
-            }
+            self.table[index].as_ref()?;
 
             index = (index + 1) % capacity;
 
@@ -192,6 +512,9 @@ impl OpenAddressingHashTable {
     /// Update load factor and clustering metrics
     fn update_load_factor(&mut self) {
         self.metrics.load_factor = self.size as f32 / self.capacity as f32;
+        if self.metrics_mode == MetricsMode::Counters {
+            return;
+        }
 
         // Calculate clustering factor (simplified: count consecutive non-empty slots)
         let mut consecutive = 0;
@@ -213,10 +536,186 @@ impl OpenAddressingHashTable {
         self.metrics.clustering_factor = max_consecutive as f32 / self.capacity as f32;
     }
 
+    /// Home slot, actual slot and displacement of every live key, with
+    /// summary statistics. Tombstones are skipped.
+    pub fn displacement_report(&self) -> DisplacementReport {
+        let slots: Vec<SlotDisplacement> = self
+            .table
+            .iter()
+            .enumerate()
+            .filter_map(|(index, slot)| {
+                let entry = slot.as_ref().filter(|e| !e.tombstone)?;
+                let home = Self::bucket_index(self.hash_key(&entry.key), self.capacity);
+                let displacement = (index + self.capacity as usize - home) % self.capacity as usize;
+                Some(SlotDisplacement {
+                    key: entry.key.clone(),
+                    home: home as u32,
+                    index: index as u32,
+                    displacement: displacement as u32,
+                })
+            })
+            .collect();
+        if slots.is_empty() {
+            return DisplacementReport::default();
+        }
+        let n = slots.len() as f64;
+        let mean = slots.iter().map(|s| s.displacement as f64).sum::<f64>() / n;
+        let variance = slots
+            .iter()
+            .map(|s| (s.displacement as f64 - mean).powi(2))
+            .sum::<f64>()
+            / n;
+        let max = slots.iter().map(|s| s.displacement).max().unwrap_or(0);
+        DisplacementReport {
+            slots,
+            mean,
+            variance,
+            max,
+        }
+    }
+
     /// Get current metrics
     pub fn get_metrics(&self) -> OpenAddressingMetrics {
         self.metrics.clone()
     }
+
+    /// Number of live (non-tombstone) entries
+    pub fn len(&self) -> u32 {
+        self.size
+    }
+
+    /// Check if the table holds no live entries
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    /// Build a table from a JS `Map` of string keys to integer values
+    /// (0 to 2^32 - 1). Throws on the first entry that doesn't fit.
+    pub fn from_js_map(map: &js_sys::Map) -> Result<OpenAddressingHashTable, JsValue> {
+        interop::from_map(map, Self::sized_for)
+    }
+
+    /// Build a table from a plain object's own enumerable properties, as
+    /// `Object.entries` lists them. Values must be integers as for `from_js_map`.
+    pub fn from_object(object: &js_sys::Object) -> Result<OpenAddressingHashTable, JsValue> {
+        interop::from_object(object, Self::sized_for)
+    }
+
+    /// The entries as a new JS `Map`, in slot order.
+    pub fn to_js_map(&self) -> js_sys::Map {
+        interop::to_map(self)
+    }
+
+    /// The entries as a new plain object, one property per key.
+    pub fn to_object(&self) -> js_sys::Object {
+        interop::to_object(self)
+    }
+
+    /// Build a table from CSV text whose first row names the columns,
+    /// taking keys from `key_col` and integer values from `value_col`.
+    /// Quoted fields may contain commas, quotes (as `""`) and line breaks.
+    /// Throws with the line number of the first bad row.
+    pub fn from_csv(
+        text: &str,
+        key_col: &str,
+        value_col: &str,
+    ) -> Result<OpenAddressingHashTable, JsValue> {
+        csv::from_csv(text, key_col, value_col, Self::sized_for)
+    }
+
+    /// The entries as CSV with a `key,value` header row, quoting keys
+    /// where needed.
+    pub fn to_csv(&self) -> String {
+        csv::to_csv(self)
+    }
+
+    /// Build a table from a MessagePack map of string keys to integers,
+    /// as JS encoders produce from a plain object. Requires the `msgpack`
+    /// feature.
+    #[cfg(feature = "msgpack")]
+    pub fn from_msgpack(bytes: &[u8]) -> Result<OpenAddressingHashTable, JsValue> {
+        msgpack::from_msgpack(bytes, Self::sized_for)
+    }
+
+    /// The entries as a MessagePack map. Requires the `msgpack` feature.
+    #[cfg(feature = "msgpack")]
+    pub fn to_msgpack(&self) -> Vec<u8> {
+        msgpack::to_msgpack(self)
+    }
+
+    /// Live entries per slot range as a bar chart, so clustering shows up
+    /// as tall neighbouring bars. Tombstones are counted in the summary only.
+    pub fn to_ascii(&self) -> String {
+        let sizes: Vec<usize> = self
+            .table
+            .iter()
+            .map(|slot| slot.as_ref().is_some_and(|e| !e.tombstone) as usize)
+            .collect();
+        let summary = format!(
+            "{} entries, {} tombstones in {} slots",
+            self.size,
+            self.metrics.tombstone_count,
+            self.table.len()
+        );
+        ascii::occupancy_bars(&summary, &sizes)
+    }
+
+    /// Insert queued entries from `job` until `millis` milliseconds have elapsed.
+    ///
+    /// Returns how many entries were inserted; call again (e.g. once per
+    /// animation frame) until `job.is_done()`.
+    pub fn insert_budgeted(&mut self, job: &mut BulkInsertJob, millis: f64) -> u32 {
+        job.run_for(self, millis)
+    }
+
+    /// Estimate heap usage. Every slot of the table is reserved up front;
+    /// only live entries count as used, so tombstones show up as slack.
+    pub fn memory_report(&self) -> MemoryReport {
+        let mut report = MemoryReport::default();
+        report.add_vec(&self.table, self.size as usize);
+        for entry in self.table.iter().flatten() {
+            if entry.tombstone {
+                report.reserved_bytes += entry.key.capacity();
+            } else {
+                report.add_string(&entry.key, entry.key.capacity());
+            }
+        }
+        report
+    }
+
+    /// Where the bytes of `memory_report` go. Entries live in the slots,
+    /// so the slot array (empty slots included) is the bucket bytes and
+    /// there is no per-entry overhead outside it; deleted keys a
+    /// tombstone still holds are spare.
+    pub fn memory_usage(&self) -> MemoryUsage {
+        let mut usage = MemoryUsage {
+            bucket_bytes: self.table.capacity() * size_of::<Option<Entry>>(),
+            entries: self.size as usize,
+            ..MemoryUsage::default()
+        };
+        for entry in self.table.iter().flatten() {
+            if entry.tombstone {
+                usage.spare_bytes += entry.key.capacity();
+            } else {
+                usage.add_key(&entry.key, entry.key.capacity());
+            }
+        }
+        usage
+    }
+
+    /// Drop all tombstones by re-placing live entries into a clean table,
+    /// which also shortens probe sequences that ran through dead slots.
+    ///
+    /// Resizable tables also shrink to the smallest capacity their config
+    /// allows without exceeding the max load factor; fixed tables keep their size.
+    pub fn shrink_to_fit(&mut self) {
+        let needed = (self.size as f64 / self.config.max_load_factor()).ceil() as u32;
+        let capacity = self.config.initial_capacity(needed).min(self.capacity);
+        if capacity != self.capacity {
+            self.metrics.resize_count += 1;
+        }
+        self.rehash_into(capacity);
+    }
 }
 
 #[cfg(test)]
@@ -226,22 +725,22 @@ mod tests {
     #[test]
     fn test_insert_and_get() {
         let mut table = OpenAddressingHashTable::new(256);
-        table.insert("key1".to_string(), 100);
+        table.try_insert("key1".to_string(), 100).unwrap();
         assert_eq!(table.get("key1"), Some(100));
     }
 
     #[test]
     fn test_update_existing_key() {
         let mut table = OpenAddressingHashTable::new(256);
-        table.insert("key1".to_string(), 100);
-        table.insert("key1".to_string(), 200);
+        table.try_insert("key1".to_string(), 100).unwrap();
+        table.try_insert("key1".to_string(), 200).unwrap();
         assert_eq!(table.get("key1"), Some(200));
     }
 
     #[test]
     fn test_delete_key() {
         let mut table = OpenAddressingHashTable::new(256);
-        table.insert("key1".to_string(), 100);
+        table.try_insert("key1".to_string(), 100).unwrap();
         assert_eq!(table.delete("key1"), Some(100));
         assert_eq!(table.get("key1"), None);
     }
@@ -250,19 +749,60 @@ mod tests {
     fn test_multiple_insertions() {
         let mut table = OpenAddressingHashTable::new(256);
         for i in 0..100 {
-            table.insert(format!("key{}", i), i);
+            table.try_insert(format!("key{}", i), i).unwrap();
         }
         assert_eq!(table.get("key50"), Some(50));
         assert_eq!(table.get("key99"), Some(99));
     }
 
+    #[test]
+    fn test_displacement_report() {
+        let mut table = OpenAddressingHashTable::new(64);
+        assert_eq!(table.displacement_report().slots_ref().len(), 0);
+        for i in 0..48 {
+            table.try_insert(format!("key{}", i), i).unwrap();
+        }
+        table.delete("key0");
+        let report = table.displacement_report();
+        assert_eq!(report.slots_ref().len(), 47);
+        for slot in report.slots_ref() {
+            let steps = (slot.index + 64 - slot.home) % 64;
+            assert_eq!(slot.displacement, steps);
+        }
+        // A 75% full linear-probing table always has some displaced keys
+        assert!(report.max() > 0);
+        assert!(report.mean() > 0.0 && report.mean() <= report.max() as f64);
+        assert!(report.at_home() > 0);
+        let total: u32 = report.slots_ref().iter().map(|s| s.displacement).sum();
+        assert!((report.mean() - total as f64 / 47.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_first_char_hash_forces_clustering() {
+        let mut table = crate::OpenAddressingBuilder::new()
+            .hash_function(HashFunction::FirstChar)
+            .try_build()
+            .unwrap();
+        assert_eq!(table.hash_function(), HashFunction::FirstChar);
+        // "a" keys fill slots 97..107, so "b" keys (home 98) queue behind them
+        for prefix in ["a", "b"] {
+            for i in 0..10 {
+                table.try_insert(format!("{}{}", prefix, i), i).unwrap();
+            }
+        }
+        let report = table.displacement_report();
+        assert_eq!(report.max(), 18);
+        assert_eq!(report.at_home(), 1);
+        assert_eq!(table.get("b9"), Some(9));
+    }
+
     #[test]
     fn test_collision_handling() {
         let mut table = OpenAddressingHashTable::new(16);
         // Intentionally cause collisions with small table
-        table.insert("a".to_string(), 1);
-        table.insert("b".to_string(), 2);
-        table.insert("c".to_string(), 3);
+        table.try_insert("a".to_string(), 1).unwrap();
+        table.try_insert("b".to_string(), 2).unwrap();
+        table.try_insert("c".to_string(), 3).unwrap();
         assert_eq!(table.get("a"), Some(1));
         assert_eq!(table.get("b"), Some(2));
         assert_eq!(table.get("c"), Some(3));
@@ -272,7 +812,7 @@ mod tests {
     fn test_load_factor() {
         let mut table = OpenAddressingHashTable::new(100);
         for i in 0..50 {
-            table.insert(format!("key{}", i), i);
+            table.try_insert(format!("key{}", i), i).unwrap();
         }
         let metrics = table.get_metrics();
         assert!((metrics.load_factor - 0.5).abs() < 0.01);
@@ -281,21 +821,80 @@ mod tests {
     #[test]
     fn test_tombstone_handling() {
         let mut table = OpenAddressingHashTable::new(256);
-        table.insert("key1".to_string(), 100);
-        table.insert("key2".to_string(), 200);
+        table.try_insert("key1".to_string(), 100).unwrap();
+        table.try_insert("key2".to_string(), 200).unwrap();
         table.delete("key1");
 
         // Can insert new key in tombstone slot
-        table.insert("key3".to_string(), 300);
+        table.try_insert("key3".to_string(), 300).unwrap();
         assert_eq!(table.get("key2"), Some(200));
         assert_eq!(table.get("key3"), Some(300));
         assert_eq!(table.get("key1"), None);
     }
 
     #[test]
+    fn test_churn_reuses_tombstones() {
+        let mut table = OpenAddressingHashTable::new(16);
+        // Far more distinct keys than slots, but never more than 8 live at once
+        for i in 0..200 {
+            table.try_insert(format!("key{}", i), i).unwrap();
+            if i >= 8 {
+                assert_eq!(table.delete(&format!("key{}", i - 8)), Some(i - 8));
+            }
+        }
+        assert_eq!(table.len(), 8);
+        for i in 192..200 {
+            assert_eq!(table.get(&format!("key{}", i)), Some(i));
+        }
+        assert!(table.get_metrics().tombstone_count < 16);
+    }
+
+    #[test]
+    fn test_capped_table_rejects_insert_when_full() {
+        let config = CapacityConfig::new().with_max_capacity(16);
+        let mut table = OpenAddressingHashTable::try_with_config(config).unwrap();
+        for i in 0..16 {
+            table.try_insert(format!("key{}", i), i).unwrap();
+        }
+        assert_eq!(table.capacity(), 16);
+        assert_eq!(
+            table.try_insert("key16".to_string(), 16),
+            Err("table is full at max_capacity".to_string())
+        );
+        // Updates still go through, and nothing was lost
+        table.try_insert("key3".to_string(), 30).unwrap();
+        assert_eq!(table.len(), 16);
+        assert_eq!(table.get("key3"), Some(30));
+        assert_eq!(table.get("key16"), None);
+    }
+
+    #[test]
+    fn test_insert_into_full_table_reuses_tombstone() {
+        let config = CapacityConfig::fixed(16);
+        let mut table = OpenAddressingHashTable::try_with_config(config).unwrap();
+        for i in 0..16 {
+            table.try_insert(format!("key{}", i), i).unwrap();
+        }
+        assert_eq!(table.delete("key5"), Some(5));
+        assert_eq!(table.get_metrics().tombstone_count, 1);
+
+        // No empty slot is left, so the probe stops after one lap of the
+        // table and takes the tombstone
+        table.try_insert("fresh".to_string(), 99).unwrap();
+        assert_eq!((table.len(), table.capacity()), (16, 16));
+        assert_eq!(table.get_metrics().tombstone_count, 0);
+        assert!(table.get_metrics().max_probe_length <= 16);
+        assert_eq!(table.get("fresh"), Some(99));
+        for i in (0..16).filter(|i| *i != 5) {
+            assert_eq!(table.get(&format!("key{}", i)), Some(i));
+        }
+    }
+
+    #[test]
+    #[allow(unused_comparisons, clippy::absurd_extreme_comparisons)]
     fn test_probe_count_tracking() {
         let mut table = OpenAddressingHashTable::new(256);
-        table.insert("key1".to_string(), 100);
+        table.try_insert("key1".to_string(), 100).unwrap();
         let metrics = table.get_metrics();
         assert!(metrics.total_probes >= 0);
         assert!(metrics.max_probe_length >= 0);
@@ -318,9 +917,108 @@ mod tests {
         let mut table = OpenAddressingHashTable::new(32);
         // Insert enough items to cause clustering
         for i in 0..16 {
-            table.insert(format!("key{}", i), i);
+            table.try_insert(format!("key{}", i), i).unwrap();
         }
         let metrics = table.get_metrics();
         assert!(metrics.clustering_factor > 0.0);
     }
+
+    #[test]
+    fn test_shrink_to_fit_purges_tombstones() {
+        let mut table = OpenAddressingHashTable::new(64);
+        for i in 0..40 {
+            table.try_insert(format!("key{}", i), i).unwrap();
+        }
+        for i in 0..30 {
+            table.delete(&format!("key{}", i));
+        }
+        let before = table.memory_report();
+        assert_eq!(table.get_metrics().tombstone_count, 30);
+
+        table.shrink_to_fit();
+        let after = table.memory_report();
+        assert_eq!(table.get_metrics().tombstone_count, 0);
+        assert_eq!(after.used_bytes, before.used_bytes);
+        assert!(after.reserved_bytes < before.reserved_bytes);
+        assert_eq!(table.len(), 10);
+        for i in 30..40 {
+            assert_eq!(table.get(&format!("key{}", i)), Some(i));
+        }
+        assert_eq!(table.get("key0"), None);
+    }
+
+    #[test]
+    fn test_configured_table_grows_instead_of_filling_up() {
+        let config = CapacityConfig::new().with_min_capacity(8);
+        let mut table = OpenAddressingHashTable::try_with_config(config).unwrap();
+        assert_eq!(table.capacity(), 8);
+        for i in 0..1_000 {
+            table.try_insert(format!("key{}", i), i).unwrap();
+        }
+        assert_eq!(table.len(), 1_000);
+        assert!(table.get_metrics().load_factor <= 0.75);
+        assert_eq!(table.capacity(), 2048);
+        assert_eq!(table.get_metrics().resize_count, 8);
+        for i in 0..1_000 {
+            assert_eq!(table.get(&format!("key{}", i)), Some(i));
+        }
+    }
+
+    #[test]
+    fn test_shrink_on_delete() {
+        let config = CapacityConfig::new().with_shrink_on_delete(true);
+        let mut table = OpenAddressingHashTable::try_with_config(config).unwrap();
+        for i in 0..100 {
+            table.try_insert(format!("key{}", i), i).unwrap();
+        }
+        let grown = table.capacity();
+        for i in 0..95 {
+            table.delete(&format!("key{}", i));
+        }
+        assert!(table.capacity() < grown);
+        assert!(table.capacity() >= 8);
+        // Each shrink rehashes, clearing the tombstones left so far
+        assert!(table.get_metrics().tombstone_count < 10);
+        assert_eq!(table.get("key99"), Some(99));
+    }
+
+    #[test]
+    fn test_fixed_table_never_resizes() {
+        let mut table = OpenAddressingHashTable::new(16);
+        for i in 0..16 {
+            table.try_insert(format!("key{}", i), i).unwrap();
+        }
+        assert_eq!(table.capacity(), 16);
+        assert_eq!(table.get_metrics().resize_count, 0);
+        table.resize(64);
+        assert_eq!(table.capacity(), 64);
+        assert_eq!(table.get("key15"), Some(15));
+        assert!(OpenAddressingHashTable::try_with_config(
+            CapacityConfig::new().with_growth_factor(0.5)
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_memory_usage_vs_chaining() {
+        let mut table = OpenAddressingHashTable::new(1024);
+        let mut chained = crate::HashMapBuilder::new()
+            .bucket_count(1024)
+            .try_build()
+            .unwrap();
+        for i in 0..512 {
+            table.try_insert(format!("key{}", i), i).unwrap();
+            chained.insert(format!("key{}", i), i);
+        }
+        table.delete("key0");
+        let usage = table.memory_usage();
+        assert_eq!(usage.total_bytes(), table.memory_report().reserved_bytes);
+        assert_eq!((usage.entries, usage.entry_bytes), (511, 0));
+        assert!(usage.spare_bytes >= "key0".len());
+        // Same keys; chaining pays for entries on top of its bucket array
+        let chained = chained.memory_usage();
+        assert_eq!(usage.key_bytes + "key0".len(), chained.key_bytes);
+        assert!(chained.entry_bytes >= 512 * size_of::<String>());
+        assert!(chained.total_bytes() > usage.total_bytes());
+    }
 }
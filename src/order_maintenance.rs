@@ -0,0 +1,376 @@
+use std::cmp::Ordering;
+use wasm_bindgen::prelude::*;
+
+const NIL: usize = usize::MAX;
+/// Bucket labels lie in 0..2^LABEL_BITS
+const LABEL_BITS: u32 = 62;
+/// A bucket holding more items than this is split in two
+const BUCKET_CAPACITY: usize = 64;
+/// A label range of 2^level may hold fewer than DENSITY^level buckets
+/// before relabeling has to look at a wider range
+const DENSITY: f64 = 4.0 / 3.0;
+
+#[derive(Clone, Debug)]
+struct Item {
+    /// Bucket holding the item, or NIL once deleted
+    bucket: usize,
+    label: u64,
+}
+
+#[derive(Clone, Debug)]
+struct Bucket {
+    label: u64,
+    /// Items in list order
+    members: Vec<usize>,
+    prev: usize,
+    next: usize,
+}
+
+/// A list answering "does a come before b?" in O(1) (Dietz & Sleator order
+/// maintenance, with the two-level labeling of Bender et al.).
+///
+/// Each item carries an integer label that increases along the list, so a
+/// comparison is two label comparisons. Giving a new item a label between
+/// its neighbours eventually runs out of room, and the fix is to respace
+/// some labels. To keep that cheap the items sit in buckets of at most 64:
+/// an item's label is its bucket's label followed by a label inside the
+/// bucket. Running out of room inside a bucket respaces just that bucket;
+/// a full bucket splits, and only then does the list of buckets need a
+/// new label, found by respacing the smallest enclosing label range that
+/// is sparse enough. A bucket split happens at most once per 32 inserts,
+/// which makes insertion O(1) amortized.
+///
+/// Handles are never reused; a deleted handle compares as unknown.
+///
+/// # Example
+/// ```javascript
+/// const list = new OrderMaintenance();
+/// const a = list.insert_first();
+/// const c = list.insert_after(a);
+/// const b = list.insert_after(a); // a, b, c
+/// list.compare(b, c);             // -1
+/// list.delete(b);
+/// list.compare(b, c);             // undefined
+/// ```
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct OrderMaintenance {
+    items: Vec<Item>,
+    buckets: Vec<Bucket>,
+    /// First bucket in list order
+    head: usize,
+    len: usize,
+    metrics: OrderMaintenanceMetrics,
+}
+
+/// Work done by an [`OrderMaintenance`] list
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct OrderMaintenanceMetrics {
+    pub inserts: u32,
+    pub deletes: u32,
+    pub comparisons: u32,
+    pub bucket_splits: u32,
+    /// Label ranges respaced in the list of buckets
+    pub bucket_relabels: u32,
+    /// Buckets whose items were respaced
+    pub item_relabels: u32,
+    /// Labels rewritten by either kind of respacing
+    pub labels_rewritten: u32,
+}
+
+impl Default for OrderMaintenance {
+    fn default() -> Self {
+        OrderMaintenance::new()
+    }
+}
+
+impl OrderMaintenance {
+    fn alive(&self, handle: u32) -> Option<&Item> {
+        self.items
+            .get(handle as usize)
+            .filter(|item| item.bucket != NIL)
+    }
+
+    /// Insert a new item at `position` in bucket `b`.
+    fn insert_at(&mut self, b: usize, position: usize) -> u32 {
+        let handle = self.items.len();
+        self.items.push(Item {
+            bucket: b,
+            label: 0,
+        });
+        self.buckets[b].members.insert(position, handle);
+        let members = &self.buckets[b].members;
+        let lower = match position {
+            0 => 0,
+            p => self.items[members[p - 1]].label + 1,
+        };
+        let upper = members
+            .get(position + 1)
+            .map_or(u64::MAX, |&m| self.items[m].label);
+        if lower < upper {
+            self.items[handle].label = lower + (upper - lower) / 2;
+        } else {
+            self.relabel_items(b);
+        }
+        if self.buckets[b].members.len() > BUCKET_CAPACITY {
+            self.split(b);
+        }
+        self.len += 1;
+        self.metrics.inserts += 1;
+        handle as u32
+    }
+
+    /// Space the item labels of bucket `b` evenly.
+    fn relabel_items(&mut self, b: usize) {
+        let members = &self.buckets[b].members;
+        let gap = u64::MAX / (members.len() as u64 + 1);
+        for (i, &m) in members.iter().enumerate() {
+            self.items[m].label = gap * (i as u64 + 1);
+        }
+        self.metrics.item_relabels += 1;
+        self.metrics.labels_rewritten += members.len() as u32;
+    }
+
+    /// Move the upper half of bucket `b` into a new bucket after it.
+    fn split(&mut self, b: usize) {
+        self.metrics.bucket_splits += 1;
+        let half = self.buckets[b].members.len() / 2;
+        let upper = self.buckets[b].members.split_off(half);
+        let next = self.buckets[b].next;
+        let new = self.buckets.len();
+        for &m in &upper {
+            self.items[m].bucket = new;
+        }
+        self.buckets.push(Bucket {
+            label: 0,
+            members: upper,
+            prev: b,
+            next,
+        });
+        self.buckets[b].next = new;
+        if next != NIL {
+            self.buckets[next].prev = new;
+        }
+        self.label_bucket(new);
+        self.relabel_items(b);
+        self.relabel_items(new);
+    }
+
+    /// Give the just-linked bucket `new` a label between its neighbours,
+    /// respacing a range of bucket labels if they are adjacent.
+    fn label_bucket(&mut self, new: usize) {
+        let b = self.buckets[new].prev;
+        let next = self.buckets[new].next;
+        let lower = self.buckets[b].label;
+        let upper = match next {
+            NIL => 1 << LABEL_BITS,
+            n => self.buckets[n].label,
+        };
+        if upper - lower >= 2 {
+            self.buckets[new].label = lower + (upper - lower) / 2;
+            return;
+        }
+        self.metrics.bucket_relabels += 1;
+        // Smallest aligned range around b's label that stays sparse with
+        // the new bucket added
+        for level in 1..=LABEL_BITS {
+            let start = lower >> level << level;
+            let end = start + (1 << level);
+            let mut first = b;
+            while self.buckets[first].prev != NIL
+                && self.buckets[self.buckets[first].prev].label >= start
+            {
+                first = self.buckets[first].prev;
+            }
+            let mut count = 0;
+            let mut node = first;
+            while node != NIL && (node == new || self.buckets[node].label < end) {
+                count += 1;
+                node = self.buckets[node].next;
+            }
+            if (count as f64) < DENSITY.powi(level as i32) || level == LABEL_BITS {
+                let gap = ((end - start) / count).max(1);
+                let mut node = first;
+                for i in 0..count {
+                    self.buckets[node].label = start + i * gap;
+                    node = self.buckets[node].next;
+                }
+                self.metrics.labels_rewritten += count as u32;
+                return;
+            }
+        }
+    }
+
+    /// Unlink the empty bucket `b`.
+    fn remove_bucket(&mut self, b: usize) {
+        let Bucket { prev, next, .. } = self.buckets[b];
+        match prev {
+            NIL => self.head = next,
+            p => self.buckets[p].next = next,
+        }
+        if next != NIL {
+            self.buckets[next].prev = prev;
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl OrderMaintenance {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> OrderMaintenance {
+        OrderMaintenance {
+            items: Vec::new(),
+            buckets: Vec::new(),
+            head: NIL,
+            len: 0,
+            metrics: OrderMaintenanceMetrics::default(),
+        }
+    }
+
+    /// Insert an item at the front of the list, returning its handle.
+    pub fn insert_first(&mut self) -> u32 {
+        if self.head == NIL {
+            self.head = self.buckets.len();
+            self.buckets.push(Bucket {
+                label: 1 << (LABEL_BITS - 1),
+                members: Vec::new(),
+                prev: NIL,
+                next: NIL,
+            });
+        }
+        self.insert_at(self.head, 0)
+    }
+
+    /// Insert an item right after `handle`, returning its handle, or
+    /// nothing if `handle` is unknown or deleted.
+    pub fn insert_after(&mut self, handle: u32) -> Option<u32> {
+        let b = self.alive(handle)?.bucket;
+        let position = self.buckets[b]
+            .members
+            .iter()
+            .position(|&m| m == handle as usize)
+            .expect("item is in its bucket");
+        Some(self.insert_at(b, position + 1))
+    }
+
+    /// Remove `handle` from the list. Returns false if it is unknown or
+    /// already deleted.
+    pub fn delete(&mut self, handle: u32) -> bool {
+        let Some(&Item { bucket: b, .. }) = self.alive(handle) else {
+            return false;
+        };
+        self.buckets[b].members.retain(|&m| m != handle as usize);
+        if self.buckets[b].members.is_empty() {
+            self.remove_bucket(b);
+        }
+        self.items[handle as usize].bucket = NIL;
+        self.len -= 1;
+        self.metrics.deletes += 1;
+        true
+    }
+
+    /// -1 if `a` comes before `b`, 1 if after, 0 if they are the same
+    /// item; nothing if either is unknown or deleted.
+    pub fn compare(&mut self, a: u32, b: u32) -> Option<i32> {
+        let (a, b) = (self.alive(a)?, self.alive(b)?);
+        let key = |item: &Item| (self.buckets[item.bucket].label, item.label);
+        let ordering = key(a).cmp(&key(b));
+        self.metrics.comparisons += 1;
+        Some(match ordering {
+            Ordering::Less => -1,
+            Ordering::Equal => 0,
+            Ordering::Greater => 1,
+        })
+    }
+
+    /// Whether `a` comes strictly before `b`.
+    pub fn is_before(&mut self, a: u32, b: u32) -> bool {
+        self.compare(a, b) == Some(-1)
+    }
+
+    /// Live handles in list order.
+    pub fn to_array(&self) -> Vec<u32> {
+        let mut handles = Vec::with_capacity(self.len);
+        let mut b = self.head;
+        while b != NIL {
+            handles.extend(self.buckets[b].members.iter().map(|&m| m as u32));
+            b = self.buckets[b].next;
+        }
+        handles
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn get_metrics(&self) -> OrderMaintenanceMetrics {
+        self.metrics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::DefaultRng;
+    use rand::Rng;
+
+    #[test]
+    fn test_matches_vec_model() {
+        let mut rng = DefaultRng::seed_from(35);
+        let mut list = OrderMaintenance::new();
+        let mut model: Vec<u32> = vec![list.insert_first()];
+        for _ in 0..5_000 {
+            match rng.gen_range(0..10) {
+                0 if model.len() > 1 => {
+                    let i = rng.gen_range(0..model.len());
+                    assert!(list.delete(model.remove(i)));
+                }
+                1 => model.insert(0, list.insert_first()),
+                _ => {
+                    let i = rng.gen_range(0..model.len());
+                    let handle = list.insert_after(model[i]).unwrap();
+                    model.insert(i + 1, handle);
+                }
+            }
+        }
+        assert_eq!(list.to_array(), model);
+        for _ in 0..2_000 {
+            let (i, j) = (rng.gen_range(0..model.len()), rng.gen_range(0..model.len()));
+            let expected = (i as i32 - j as i32).signum();
+            assert_eq!(list.compare(model[i], model[j]), Some(expected));
+        }
+
+        let gone = (0..list.items.len() as u32)
+            .find(|h| !model.contains(h))
+            .unwrap();
+        assert_eq!(list.compare(gone, model[0]), None);
+        assert_eq!(list.insert_after(gone), None);
+        assert!(!list.delete(gone));
+    }
+
+    #[test]
+    fn test_relabeling_is_amortized_constant() {
+        // Always inserting right after the same item exhausts label gaps
+        // fastest
+        let mut list = OrderMaintenance::new();
+        let first = list.insert_first();
+        let mut last = first;
+        for _ in 0..20_000 {
+            list.insert_after(first).unwrap();
+            last = list.insert_after(last).unwrap();
+        }
+        let order = list.to_array();
+        assert!(list.is_before(order[0], order[1]));
+        assert!(list.is_before(order[20_000], last));
+        assert_eq!(list.len(), 40_001);
+
+        let metrics = list.get_metrics();
+        assert!(metrics.bucket_relabels > 0);
+        assert!(metrics.labels_rewritten < 10 * metrics.inserts);
+    }
+}
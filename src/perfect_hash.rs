@@ -0,0 +1,260 @@
+use crate::clock::now_ms;
+use crate::hashing::{mix_seed, HashFunction};
+use std::collections::HashSet;
+use wasm_bindgen::prelude::*;
+
+/// Bits per remaining key at each level. Higher builds faster and looks
+/// up in fewer levels, at the cost of space.
+const GAMMA: f64 = 2.0;
+/// Levels before the build gives up; each passes about 40% of its keys on
+/// (1 - e^(-1/gamma) collide), so a dozen or so normally suffice.
+const MAX_LEVELS: usize = 64;
+
+/// Size and build cost of a [`MinimalPerfectHash`].
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PerfectHashMetrics {
+    pub keys: u32,
+    /// Bit arrays a lookup may have to check, worst case
+    pub levels: u32,
+    /// Bits of level arrays and rank table, divided by `keys`
+    pub bits_per_key: f64,
+    pub build_ms: f64,
+}
+
+/// Maps each of a fixed set of `n` keys to a distinct index in `0..n`, with
+/// no collisions and no empty slots: BBHash (Limasset et al.).
+///
+/// Level 0 is a bit array of about 2n bits; every key hashes to one bit,
+/// and bits hit by exactly one key are kept. Keys that collided move on to
+/// level 1, sized for them alone, and so on until none are left. A key's
+/// index is the number of kept bits before its own across all levels,
+/// answered by a rank table of running counts per 64-bit word. That comes
+/// to about 5 bits per key whatever the keys are: 3.3 in the levels, the
+/// rest in the rank table.
+///
+/// Keys outside the build set still get an index (or `None`), so check the
+/// key itself where that matters, as [`StaticMap`] does.
+///
+/// # Example
+/// ```javascript
+/// const mph = new MinimalPerfectHash(["red", "green", "blue"]);
+/// mph.index("green"); // one of 0, 1, 2, distinct from the others
+/// mph.metrics().bits_per_key;
+/// ```
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct MinimalPerfectHash {
+    /// All levels' kept bits, concatenated
+    words: Vec<u64>,
+    /// (first word, bit length) of each level
+    levels: Vec<(usize, usize)>,
+    /// Kept bits in `words` before each word
+    ranks: Vec<u32>,
+    keys: usize,
+    build_ms: f64,
+}
+
+fn level_seed(level: usize) -> u64 {
+    (level as u64 + 1).wrapping_mul(0x9e37_79b9_7f4a_7c15)
+}
+
+impl MinimalPerfectHash {
+    pub fn try_build<S: AsRef<str>>(keys: &[S]) -> Result<MinimalPerfectHash, String> {
+        let start = now_ms();
+        let mut seen = HashSet::with_capacity(keys.len());
+        for key in keys {
+            if !seen.insert(key.as_ref()) {
+                return Err(format!("duplicate key '{}'", key.as_ref()));
+            }
+        }
+        let mut remaining: Vec<u64> = keys
+            .iter()
+            .map(|key| HashFunction::SipHash.hash(key.as_ref()))
+            .collect();
+        let mut mph = MinimalPerfectHash {
+            words: Vec::new(),
+            levels: Vec::new(),
+            ranks: Vec::new(),
+            keys: keys.len(),
+            build_ms: 0.0,
+        };
+        while !remaining.is_empty() {
+            if mph.levels.len() == MAX_LEVELS {
+                return Err(format!(
+                    "{} keys still collide after {} levels",
+                    remaining.len(),
+                    MAX_LEVELS
+                ));
+            }
+            let seed = level_seed(mph.levels.len());
+            let bits = ((remaining.len() as f64 * GAMMA).ceil() as usize).div_ceil(64) * 64;
+            let mut hit = vec![0u64; bits / 64];
+            let mut collided = vec![0u64; bits / 64];
+            for &hash in &remaining {
+                let bit = (mix_seed(hash, seed) % bits as u64) as usize;
+                let mask = 1 << (bit % 64);
+                if hit[bit / 64] & mask != 0 {
+                    collided[bit / 64] |= mask;
+                }
+                hit[bit / 64] |= mask;
+            }
+            remaining.retain(|&hash| {
+                let bit = (mix_seed(hash, seed) % bits as u64) as usize;
+                collided[bit / 64] & (1 << (bit % 64)) != 0
+            });
+            mph.levels.push((mph.words.len(), bits));
+            mph.words.extend(
+                hit.iter()
+                    .zip(&collided)
+                    .map(|(hit, collided)| hit & !collided),
+            );
+        }
+        let mut total = 0;
+        for word in &mph.words {
+            mph.ranks.push(total);
+            total += word.count_ones();
+        }
+        debug_assert_eq!(total as usize, mph.keys);
+        mph.build_ms = now_ms() - start;
+        Ok(mph)
+    }
+}
+
+#[wasm_bindgen]
+impl MinimalPerfectHash {
+    /// Build over `keys`, which must be distinct.
+    #[wasm_bindgen(constructor)]
+    pub fn new(keys: Vec<String>) -> Result<MinimalPerfectHash, JsValue> {
+        Self::try_build(&keys).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `key`'s index in `0..len()` if it was in the build set. Other keys
+    /// get an arbitrary index or `None`.
+    pub fn index(&self, key: &str) -> Option<u32> {
+        let hash = HashFunction::SipHash.hash(key);
+        for (level, &(first_word, bits)) in self.levels.iter().enumerate() {
+            let bit = (mix_seed(hash, level_seed(level)) % bits as u64) as usize;
+            let word = first_word + bit / 64;
+            let below = self.words[word] & ((1 << (bit % 64)) - 1);
+            if self.words[word] & (1 << (bit % 64)) != 0 {
+                return Some(self.ranks[word] + below.count_ones());
+            }
+        }
+        None
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys == 0
+    }
+
+    pub fn metrics(&self) -> PerfectHashMetrics {
+        let bits = self.words.len() * 64 + self.ranks.len() * 32;
+        PerfectHashMetrics {
+            keys: self.keys as u32,
+            levels: self.levels.len() as u32,
+            bits_per_key: bits as f64 / self.keys.max(1) as f64,
+            build_ms: self.build_ms,
+        }
+    }
+}
+
+/// A read-only map over a fixed key set: a [`MinimalPerfectHash`] picks
+/// each key's slot, so there are exactly as many slots as keys and a
+/// lookup is one hash per level plus one key comparison, worst case.
+///
+/// # Example
+/// ```javascript
+/// const codes = new StaticMap(["us", "fr", "jp"], [1, 33, 81]);
+/// codes.get("fr"); // 33
+/// codes.get("de"); // undefined
+/// ```
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct StaticMap {
+    mph: MinimalPerfectHash,
+    keys: Vec<String>,
+    values: Vec<u32>,
+}
+
+impl StaticMap {
+    pub fn try_build(keys: Vec<String>, values: Vec<u32>) -> Result<StaticMap, String> {
+        if keys.len() != values.len() {
+            return Err(format!("{} keys but {} values", keys.len(), values.len()));
+        }
+        let mph = MinimalPerfectHash::try_build(&keys)?;
+        let mut slots: Vec<(String, u32)> = vec![(String::new(), 0); keys.len()];
+        for (key, value) in keys.into_iter().zip(values) {
+            let slot = mph.index(&key).expect("build keys are indexed") as usize;
+            slots[slot] = (key, value);
+        }
+        let (keys, values) = slots.into_iter().unzip();
+        Ok(StaticMap { mph, keys, values })
+    }
+}
+
+#[wasm_bindgen]
+impl StaticMap {
+    /// Map `keys[i]` to `values[i]`. Keys must be distinct.
+    #[wasm_bindgen(constructor)]
+    pub fn new(keys: Vec<String>, values: Vec<u32>) -> Result<StaticMap, JsValue> {
+        Self::try_build(keys, values).map_err(|e| JsValue::from_str(&e))
+    }
+
+    pub fn get(&self, key: &str) -> Option<u32> {
+        let slot = self.mph.index(key)? as usize;
+        (self.keys[slot] == key).then(|| self.values[slot])
+    }
+
+    pub fn has(&self, key: &str) -> bool {
+        self.get(key).is_some()
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Metrics of the index function; the key and value arrays add
+    /// nothing beyond the data itself.
+    pub fn metrics(&self) -> PerfectHashMetrics {
+        self.mph.metrics()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_minimal_perfect_hash() {
+        let keys: Vec<String> = (0..10_000).map(|i| format!("key{}", i)).collect();
+        let mph = MinimalPerfectHash::try_build(&keys).unwrap();
+        let mut indexes: Vec<u32> = keys.iter().map(|key| mph.index(key).unwrap()).collect();
+        indexes.sort_unstable();
+        assert!(indexes.iter().copied().eq(0..10_000));
+        let metrics = mph.metrics();
+        assert!(metrics.bits_per_key < 6.0, "{:?}", metrics);
+        assert!(metrics.levels < 20, "{:?}", metrics);
+        assert!(MinimalPerfectHash::try_build(&["a", "b", "a"]).is_err());
+        assert!(MinimalPerfectHash::try_build::<&str>(&[])
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_static_map() {
+        let keys: Vec<String> = (0..1_000).map(|i| format!("key{}", i)).collect();
+        let map = StaticMap::try_build(keys, (0..1_000).collect()).unwrap();
+        assert!((0..1_000).all(|i| map.get(&format!("key{}", i)) == Some(i)));
+        assert!((1_000..2_000).all(|i| !map.has(&format!("key{}", i))));
+        assert!(StaticMap::try_build(vec!["a".into()], vec![]).is_err());
+    }
+}
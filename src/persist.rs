@@ -0,0 +1,417 @@
+//! Saving a [`HashMap`] with its layout and accumulated metrics, as JSON
+//! text for `localStorage` or as compact bytes for `postMessage` and
+//! IndexedDB.
+//!
+//! JSON is an object `{ format: "hashmap", version, bucket_count,
+//! bucket_mode, hash_function, counters: {...}, entries: [[key, value]...] }`
+//! with entries as pairs so they keep their insertion order. The bytes are
+//! the same fields little-endian after a `WDSH` magic and a version byte.
+
+use crate::builders::HashMapBuilder;
+use crate::chain::BucketMode;
+use crate::hashing::HashFunction;
+use crate::json::{self, Json};
+use crate::{HashMap, HashMapMetrics};
+
+/// Format version written by this build, in both encodings.
+const FORMAT_VERSION: u32 = 1;
+
+const MAGIC: &[u8; 4] = b"WDSH";
+
+/// Buckets a saved map may ask for regardless of its size. Beyond this the
+/// bucket count must stay within `BUCKETS_PER_ENTRY` per saved entry, so a
+/// forged header can't make restoring allocate gigabytes.
+const MAX_SPARE_BUCKETS: u32 = 1 << 16;
+const BUCKETS_PER_ENTRY: u32 = 8;
+
+/// Variants in a fixed order: the byte encoding stores their index, JSON
+/// their name.
+const BUCKET_MODES: [BucketMode; 3] = [
+    BucketMode::Vec,
+    BucketMode::LinkedList,
+    BucketMode::SortedVec,
+];
+const HASH_FUNCTIONS: [HashFunction; 6] = [
+    HashFunction::SipHash,
+    HashFunction::Constant,
+    HashFunction::FirstChar,
+    HashFunction::Fnv1a,
+    HashFunction::FxHash,
+    HashFunction::Djb2,
+];
+
+/// Metrics that accumulate over the map's life. The rest (chain lengths,
+/// load factor) are recomputed from the restored buckets.
+const COUNTERS: [&str; 11] = [
+    "total_insertions",
+    "total_collisions",
+    "chain_comparisons",
+    "rehash_count",
+    "rehashed_entries",
+    "get_calls",
+    "contains_key_calls",
+    "total_lookups",
+    "lookup_chain_traversals",
+    "unsuccessful_lookups",
+    "total_deletions",
+];
+
+fn counters(metrics: &HashMapMetrics) -> [u32; 11] {
+    [
+        metrics.total_insertions,
+        metrics.total_collisions,
+        metrics.chain_comparisons,
+        metrics.rehash_count,
+        metrics.rehashed_entries,
+        metrics.get_calls,
+        metrics.contains_key_calls,
+        metrics.total_lookups,
+        metrics.lookup_chain_traversals,
+        metrics.unsuccessful_lookups,
+        metrics.total_deletions,
+    ]
+}
+
+/// Everything that is saved of a map.
+struct Saved {
+    bucket_count: u32,
+    bucket_mode: BucketMode,
+    hash_function: HashFunction,
+    counters: [u32; 11],
+    entries: Vec<(String, u32)>,
+}
+
+impl Saved {
+    fn capture(map: &HashMap) -> Saved {
+        Saved {
+            bucket_count: map.bucket_count() as u32,
+            bucket_mode: map.bucket_mode(),
+            hash_function: map.hash_function(),
+            counters: counters(&map.get_metrics()),
+            entries: map.pairs(),
+        }
+    }
+
+    fn restore(self) -> Result<HashMap, String> {
+        let limit = (self.entries.len() as u32)
+            .saturating_mul(BUCKETS_PER_ENTRY)
+            .max(MAX_SPARE_BUCKETS);
+        if self.bucket_count > limit {
+            return Err(format!(
+                "bucket_count {} is too large for {} entries (at most {})",
+                self.bucket_count,
+                self.entries.len(),
+                limit
+            ));
+        }
+        let mut map = HashMapBuilder::new()
+            .bucket_count(self.bucket_count)
+            .bucket_mode(self.bucket_mode)
+            .hash_function(self.hash_function)
+            .try_build()?;
+        for (key, value) in self.entries {
+            map.insert(key, value);
+        }
+        let c = self.counters;
+        map.restore_counters(HashMapMetrics {
+            total_insertions: c[0],
+            total_collisions: c[1],
+            chain_comparisons: c[2],
+            rehash_count: c[3],
+            rehashed_entries: c[4],
+            get_calls: c[5],
+            contains_key_calls: c[6],
+            total_lookups: c[7],
+            lookup_chain_traversals: c[8],
+            unsuccessful_lookups: c[9],
+            total_deletions: c[10],
+            ..map.get_metrics()
+        });
+        Ok(map)
+    }
+}
+
+fn variant_name<T: std::fmt::Debug>(variant: T) -> Json {
+    Json::String(format!("{:?}", variant))
+}
+
+fn variant_by_name<T: Copy + std::fmt::Debug>(
+    variants: &[T],
+    field: &str,
+    value: Option<&Json>,
+) -> Result<T, String> {
+    let name = value
+        .and_then(Json::as_str)
+        .ok_or_else(|| format!("'{}' must be a string", field))?;
+    variants
+        .iter()
+        .copied()
+        .find(|variant| format!("{:?}", variant) == name)
+        .ok_or_else(|| format!("unknown {} '{}'", field, name))
+}
+
+fn u32_field(value: Option<&Json>, field: &str) -> Result<u32, String> {
+    match value.and_then(Json::as_f64) {
+        Some(n) if n.fract() == 0.0 && (0.0..=u32::MAX as f64).contains(&n) => Ok(n as u32),
+        _ => Err(format!(
+            "'{}' must be an integer between 0 and {}",
+            field,
+            u32::MAX
+        )),
+    }
+}
+
+pub(crate) fn to_json(map: &HashMap) -> String {
+    let saved = Saved::capture(map);
+    let number = |n: u32| Json::Number(n as f64);
+    let document = Json::Object(vec![
+        ("format".to_string(), Json::String("hashmap".to_string())),
+        ("version".to_string(), number(FORMAT_VERSION)),
+        ("bucket_count".to_string(), number(saved.bucket_count)),
+        ("bucket_mode".to_string(), variant_name(saved.bucket_mode)),
+        (
+            "hash_function".to_string(),
+            variant_name(saved.hash_function),
+        ),
+        (
+            "counters".to_string(),
+            Json::Object(
+                COUNTERS
+                    .iter()
+                    .zip(saved.counters)
+                    .map(|(name, n)| (name.to_string(), number(n)))
+                    .collect(),
+            ),
+        ),
+        (
+            "entries".to_string(),
+            Json::Array(
+                saved
+                    .entries
+                    .into_iter()
+                    .map(|(key, value)| Json::Array(vec![Json::String(key), number(value)]))
+                    .collect(),
+            ),
+        ),
+    ]);
+    let mut out = String::new();
+    document.write(&mut out);
+    out
+}
+
+pub(crate) fn from_json(text: &str) -> Result<HashMap, String> {
+    let document = json::parse(text)?;
+    if document.get("format").and_then(Json::as_str) != Some("hashmap") {
+        return Err("not a saved HashMap: 'format' must be \"hashmap\"".to_string());
+    }
+    let version = u32_field(document.get("version"), "version")?;
+    if version != FORMAT_VERSION {
+        return Err(format!("unsupported HashMap format version {}", version));
+    }
+    let mut counters = [0; 11];
+    let saved_counters = document.get("counters");
+    for (counter, name) in counters.iter_mut().zip(COUNTERS) {
+        *counter = u32_field(saved_counters.and_then(|c| c.get(name)), name)?;
+    }
+    let Some(Json::Array(pairs)) = document.get("entries") else {
+        return Err("'entries' must be an array".to_string());
+    };
+    let entries = pairs
+        .iter()
+        .enumerate()
+        .map(|(i, pair)| match pair {
+            Json::Array(items) if items.len() == 2 => {
+                let key = items[0]
+                    .as_str()
+                    .ok_or_else(|| format!("entry {}: key must be a string", i))?;
+                let value = u32_field(Some(&items[1]), "value")
+                    .map_err(|e| format!("entry {}: {}", i, e))?;
+                Ok((key.to_string(), value))
+            }
+            _ => Err(format!("entry {} must be a [key, value] pair", i)),
+        })
+        .collect::<Result<_, String>>()?;
+    Saved {
+        bucket_count: u32_field(document.get("bucket_count"), "bucket_count")?,
+        bucket_mode: variant_by_name(&BUCKET_MODES, "bucket_mode", document.get("bucket_mode"))?,
+        hash_function: variant_by_name(
+            &HASH_FUNCTIONS,
+            "hash_function",
+            document.get("hash_function"),
+        )?,
+        counters,
+        entries,
+    }
+    .restore()
+}
+
+pub(crate) fn to_bytes(map: &HashMap) -> Vec<u8> {
+    let saved = Saved::capture(map);
+    let mut out = MAGIC.to_vec();
+    out.push(FORMAT_VERSION as u8);
+    out.extend_from_slice(&saved.bucket_count.to_le_bytes());
+    let mode = BUCKET_MODES.iter().position(|&m| m == saved.bucket_mode);
+    let hash = HASH_FUNCTIONS
+        .iter()
+        .position(|&h| h == saved.hash_function);
+    out.push(mode.expect("every bucket mode is listed") as u8);
+    out.push(hash.expect("every hash function is listed") as u8);
+    for counter in saved.counters {
+        out.extend_from_slice(&counter.to_le_bytes());
+    }
+    out.extend_from_slice(&(saved.entries.len() as u32).to_le_bytes());
+    for (key, value) in &saved.entries {
+        out.extend_from_slice(&(key.len() as u32).to_le_bytes());
+        out.extend_from_slice(key.as_bytes());
+        out.extend_from_slice(&value.to_le_bytes());
+    }
+    out
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        if len > self.bytes.len() {
+            return Err("saved HashMap is truncated".to_string());
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(
+            self.take(4)?.try_into().expect("4 bytes taken"),
+        ))
+    }
+}
+
+pub(crate) fn from_bytes(bytes: &[u8]) -> Result<HashMap, String> {
+    let mut reader = Reader { bytes };
+    if reader.take(4).ok() != Some(MAGIC.as_slice()) {
+        return Err("not a saved HashMap: bad magic bytes".to_string());
+    }
+    let version = reader.u8()? as u32;
+    if version != FORMAT_VERSION {
+        return Err(format!("unsupported HashMap format version {}", version));
+    }
+    let bucket_count = reader.u32()?;
+    let bucket_mode = *BUCKET_MODES
+        .get(reader.u8()? as usize)
+        .ok_or("unknown bucket mode")?;
+    let hash_function = *HASH_FUNCTIONS
+        .get(reader.u8()? as usize)
+        .ok_or("unknown hash function")?;
+    let mut counters = [0; 11];
+    for counter in &mut counters {
+        *counter = reader.u32()?;
+    }
+    let len = reader.u32()? as usize;
+    // Every entry takes at least eight bytes, so a bogus length can't
+    // reserve more than the input justifies
+    let mut entries = Vec::with_capacity(len.min(bytes.len() / 8));
+    for _ in 0..len {
+        let key_len = reader.u32()? as usize;
+        let key = std::str::from_utf8(reader.take(key_len)?)
+            .map_err(|_| "key is not valid UTF-8".to_string())?;
+        entries.push((key.to_string(), reader.u32()?));
+    }
+    if !reader.bytes.is_empty() {
+        return Err("trailing bytes after saved HashMap".to_string());
+    }
+    Saved {
+        bucket_count,
+        bucket_mode,
+        hash_function,
+        counters,
+        entries,
+    }
+    .restore()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn populated() -> HashMap {
+        let mut map = HashMapBuilder::new()
+            .bucket_count(16)
+            .bucket_mode(BucketMode::SortedVec)
+            .hash_function(HashFunction::Fnv1a)
+            .try_build()
+            .unwrap();
+        for i in 0..100 {
+            map.insert(format!("key \"{}\"\n", (i * 37) % 100), i);
+        }
+        map.delete("key \"5\"\n".to_string());
+        map.resize(32);
+        map.get("key \"1\"\n".to_string());
+        map.contains_key("missing");
+        map
+    }
+
+    #[test]
+    fn test_round_trips_entries_and_metrics() {
+        let map = populated();
+        let from_json = from_json(&to_json(&map)).unwrap();
+        let from_bytes = from_bytes(&to_bytes(&map)).unwrap();
+        for restored in [from_json, from_bytes] {
+            assert_eq!(restored.pairs(), map.pairs());
+            assert_eq!(restored.bucket_count(), 32);
+            assert_eq!(restored.bucket_mode(), BucketMode::SortedVec);
+            assert_eq!(restored.hash_function(), HashFunction::Fnv1a);
+            let (before, after) = (map.get_metrics(), restored.get_metrics());
+            assert_eq!(counters(&after), counters(&before));
+            assert_eq!(after.max_chain_length, before.max_chain_length);
+            assert_eq!(after.average_load_factor, before.average_load_factor);
+        }
+    }
+
+    #[test]
+    fn test_rejects_bad_input() {
+        let bytes = to_bytes(&populated());
+        for len in [0, 3, 10, bytes.len() - 1] {
+            assert!(from_bytes(&bytes[..len]).is_err(), "{}", len);
+        }
+        let mut extra = bytes.clone();
+        extra.push(0);
+        assert!(from_bytes(&extra).err().unwrap().contains("trailing"));
+
+        let text = to_json(&populated());
+        assert!(from_json("{}").err().unwrap().contains("format"));
+        let bad = text.replace("\"Fnv1a\"", "\"Md5\"");
+        assert_eq!(
+            from_json(&bad).err(),
+            Some("unknown hash_function 'Md5'".to_string())
+        );
+        let bad = text.replace("\"bucket_count\":32", "\"bucket_count\":0");
+        assert!(from_json(&bad).err().unwrap().contains("bucket_count"));
+    }
+
+    #[test]
+    fn test_rejects_forged_bucket_count() {
+        let mut bytes = to_bytes(&HashMap::new());
+        bytes[5..9].copy_from_slice(&0x4000_0000u32.to_le_bytes());
+        assert!(from_bytes(&bytes).err().unwrap().contains("bucket_count"));
+
+        let text =
+            to_json(&HashMap::new()).replace("\"bucket_count\":256", "\"bucket_count\":1073741824");
+        assert!(from_json(&text).err().unwrap().contains("too large"));
+
+        // A sparse map that was resized up well past its size still restores
+        let mut map = HashMap::new();
+        map.insert("a".to_string(), 1);
+        map.resize(MAX_SPARE_BUCKETS);
+        assert_eq!(
+            from_bytes(&to_bytes(&map)).unwrap().bucket_count(),
+            MAX_SPARE_BUCKETS as usize
+        );
+    }
+}
@@ -0,0 +1,165 @@
+use js_sys::{Function, Object, Reflect};
+use wasm_bindgen::prelude::*;
+
+/// Items between reports when the caller passes 0.
+pub const DEFAULT_REPORT_EVERY: u32 = 1_000;
+
+/// Snapshot handed to progress callbacks.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Progress {
+    pub processed: u32,
+    /// `None` when the total isn't known up front, e.g. for streams.
+    pub total: Option<u32>,
+    pub elapsed_ms: f64,
+}
+
+impl Progress {
+    /// `{ processed, total, elapsed_ms }`, with `total` undefined if unknown.
+    pub fn to_js(&self) -> JsValue {
+        let object = Object::new();
+        let total = self.total.map_or(JsValue::UNDEFINED, JsValue::from);
+        let _ = Reflect::set(&object, &"processed".into(), &self.processed.into());
+        let _ = Reflect::set(&object, &"total".into(), &total);
+        let _ = Reflect::set(&object, &"elapsed_ms".into(), &self.elapsed_ms.into());
+        object.into()
+    }
+}
+
+/// Optional callback invoked every `every` processed items.
+///
+/// Long operations call [`ProgressHook::update`] as they go; the hook decides
+/// when a report is due, and always reports once more on completion so a
+/// progress bar ends at 100%.
+pub struct ProgressHook {
+    callback: Option<Box<dyn FnMut(Progress)>>,
+    every: u32,
+    next_at: u32,
+    finished: bool,
+}
+
+impl Default for ProgressHook {
+    fn default() -> Self {
+        Self::none()
+    }
+}
+
+impl ProgressHook {
+    /// A hook that never reports.
+    pub fn none() -> ProgressHook {
+        ProgressHook {
+            callback: None,
+            every: DEFAULT_REPORT_EVERY,
+            next_at: DEFAULT_REPORT_EVERY,
+            finished: false,
+        }
+    }
+
+    /// Report to a Rust closure every `every` items (0 means 1000).
+    pub fn new(every: u32, callback: impl FnMut(Progress) + 'static) -> ProgressHook {
+        let every = if every == 0 {
+            DEFAULT_REPORT_EVERY
+        } else {
+            every
+        };
+        ProgressHook {
+            callback: Some(Box::new(callback)),
+            every,
+            next_at: every,
+            finished: false,
+        }
+    }
+
+    /// Report to an optional JS function. Exceptions thrown by the callback
+    /// are swallowed: a broken progress bar shouldn't abort a data load.
+    pub fn from_js(callback: Option<Function>, every: u32) -> ProgressHook {
+        match callback {
+            Some(f) => ProgressHook::new(every, move |p| {
+                let _ = f.call1(&JsValue::UNDEFINED, &p.to_js());
+            }),
+            None => ProgressHook::none(),
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.callback.is_some()
+    }
+
+    /// Record that `processed` items are done, reporting if a multiple of
+    /// `every` was reached or the operation just completed.
+    pub fn update(&mut self, processed: u32, total: Option<u32>, elapsed_ms: f64) {
+        let Some(callback) = self.callback.as_mut() else {
+            return;
+        };
+        let complete = total.is_some_and(|t| processed >= t);
+        if self.finished || (processed < self.next_at && !complete) {
+            return;
+        }
+        self.next_at = (processed / self.every + 1).saturating_mul(self.every);
+        self.finished = complete;
+        callback(Progress {
+            processed,
+            total,
+            elapsed_ms,
+        });
+    }
+
+    /// Final report for operations whose total wasn't known in advance.
+    pub fn finish(&mut self, processed: u32, elapsed_ms: f64) {
+        self.update(processed, Some(processed), elapsed_ms);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    fn recording(every: u32) -> (ProgressHook, Rc<RefCell<Vec<Progress>>>) {
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let sink = Rc::clone(&seen);
+        let hook = ProgressHook::new(every, move |p| sink.borrow_mut().push(p));
+        (hook, seen)
+    }
+
+    #[test]
+    fn test_reports_every_n_and_on_completion() {
+        let (mut hook, seen) = recording(10);
+        for i in 1..=25 {
+            hook.update(i, Some(25), i as f64);
+        }
+        // Repeated completion updates don't report twice
+        hook.update(25, Some(25), 30.0);
+        let processed: Vec<u32> = seen.borrow().iter().map(|p| p.processed).collect();
+        assert_eq!(processed, vec![10, 20, 25]);
+        assert_eq!(seen.borrow()[2].elapsed_ms, 25.0);
+    }
+
+    #[test]
+    fn test_coarse_updates_report_once_per_crossing() {
+        let (mut hook, seen) = recording(10);
+        hook.update(64, Some(200), 1.0);
+        hook.update(68, Some(200), 2.0);
+        hook.update(128, Some(200), 3.0);
+        let processed: Vec<u32> = seen.borrow().iter().map(|p| p.processed).collect();
+        assert_eq!(processed, vec![64, 128]);
+    }
+
+    #[test]
+    fn test_unknown_total_and_finish() {
+        let (mut hook, seen) = recording(0);
+        hook.update(DEFAULT_REPORT_EVERY, None, 1.0);
+        hook.finish(DEFAULT_REPORT_EVERY + 5, 2.0);
+        let seen = seen.borrow();
+        assert_eq!(seen.len(), 2);
+        assert_eq!(seen[0].total, None);
+        assert_eq!(seen[1].total, Some(DEFAULT_REPORT_EVERY + 5));
+    }
+
+    #[test]
+    fn test_inactive_hook_is_a_no_op() {
+        let mut hook = ProgressHook::from_js(None, 10);
+        assert!(!hook.is_active());
+        hook.update(100, Some(100), 0.0);
+    }
+}
@@ -0,0 +1,295 @@
+use std::f64::consts::PI;
+use wasm_bindgen::prelude::*;
+
+/// Compression used by `QuantileSketch::default()`.
+pub const DEFAULT_COMPRESSION: f64 = 100.0;
+
+/// Streaming quantile estimator (merging t-digest, Dunning & Ertl).
+///
+/// Values are grouped into weighted centroids whose size limit shrinks
+/// towards both tails, so extreme quantiles such as p99 stay accurate while
+/// the median is summarized coarsely. Memory is about `compression`
+/// centroids however many values are added, and two sketches merge into one
+/// summarizing both streams.
+///
+/// `rank_error(q)` reports how far off the estimate of quantile q can be in
+/// rank: the value returned has roughly between q - e and q + e of the data
+/// below it.
+///
+/// # Example
+/// ```javascript
+/// const sketch = new QuantileSketch(100);
+/// for (const ms of latencies) sketch.add(ms);
+/// console.log(sketch.quantile(0.99), "±", sketch.rank_error(0.99));
+/// ```
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct QuantileSketch {
+    compression: f64,
+    /// (mean, weight), sorted by mean
+    centroids: Vec<(f64, f64)>,
+    /// Values not yet merged into centroids
+    buffer: Vec<f64>,
+    count: u64,
+    min: f64,
+    max: f64,
+}
+
+impl Default for QuantileSketch {
+    fn default() -> Self {
+        Self::new(DEFAULT_COMPRESSION)
+    }
+}
+
+impl QuantileSketch {
+    /// The k1 scale function: centroids may span at most 1 unit of k.
+    fn scale(&self, q: f64) -> f64 {
+        self.compression / (2.0 * PI) * (2.0 * q - 1.0).clamp(-1.0, 1.0).asin()
+    }
+
+    /// Merge the buffer into the centroids.
+    fn compress(&mut self) {
+        if self.buffer.is_empty() {
+            return;
+        }
+        let mut all = std::mem::take(&mut self.centroids);
+        all.extend(self.buffer.drain(..).map(|v| (v, 1.0)));
+        self.rebuild(all);
+    }
+
+    /// Replace the centroids by greedily combining `all`, in mean order,
+    /// as long as each result spans at most one unit of the scale function.
+    fn rebuild(&mut self, mut all: Vec<(f64, f64)>) {
+        all.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let total: f64 = all.iter().map(|(_, w)| w).sum();
+        let mut merged: Vec<(f64, f64)> = Vec::with_capacity(self.compression as usize);
+        let mut so_far = 0.0;
+        let mut limit = 0.0;
+        for (mean, weight) in all {
+            let fits = self.scale((so_far + weight) / total) <= limit;
+            match merged.last_mut() {
+                Some(last) if fits => {
+                    last.1 += weight;
+                    last.0 += (mean - last.0) * weight / last.1;
+                }
+                _ => {
+                    // The new centroid's span starts where the last ended
+                    limit = self.scale(so_far / total) + 1.0;
+                    merged.push((mean, weight));
+                }
+            }
+            so_far += weight;
+        }
+        self.centroids = merged;
+    }
+
+    /// A compressed view without mutating `self`.
+    fn compressed(&self) -> std::borrow::Cow<'_, QuantileSketch> {
+        if self.buffer.is_empty() {
+            std::borrow::Cow::Borrowed(self)
+        } else {
+            let mut copy = self.clone();
+            copy.compress();
+            std::borrow::Cow::Owned(copy)
+        }
+    }
+
+    /// Index of the centroid covering rank `target`, and the rank at its
+    /// centre.
+    fn locate(&self, target: f64) -> Option<(usize, f64)> {
+        let mut before = 0.0;
+        for (i, &(_, weight)) in self.centroids.iter().enumerate() {
+            if target < before + weight || i + 1 == self.centroids.len() {
+                return Some((i, before + weight / 2.0));
+            }
+            before += weight;
+        }
+        None
+    }
+}
+
+#[wasm_bindgen]
+impl QuantileSketch {
+    /// A sketch keeping about `compression` centroids (at least 10).
+    /// Higher is more accurate and larger.
+    #[wasm_bindgen(constructor)]
+    pub fn new(compression: f64) -> QuantileSketch {
+        let compression = if compression.is_finite() {
+            compression.max(10.0)
+        } else {
+            DEFAULT_COMPRESSION
+        };
+        QuantileSketch {
+            compression,
+            centroids: Vec::new(),
+            buffer: Vec::new(),
+            count: 0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+        }
+    }
+
+    /// Add one value. NaN is ignored.
+    pub fn add(&mut self, value: f64) {
+        if value.is_nan() {
+            return;
+        }
+        self.buffer.push(value);
+        self.count += 1;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        if self.buffer.len() >= 5 * self.compression as usize {
+            self.compress();
+        }
+    }
+
+    /// Estimated value at quantile `q` (clamped to [0, 1]), or `None` if
+    /// nothing was added.
+    pub fn quantile(&self, q: f64) -> Option<f64> {
+        if self.count == 0 {
+            return None;
+        }
+        let q = q.clamp(0.0, 1.0);
+        if q == 0.0 {
+            return Some(self.min);
+        }
+        if q == 1.0 {
+            return Some(self.max);
+        }
+        let sketch = self.compressed();
+        let centroids = &sketch.centroids;
+        let target = q * self.count as f64;
+        let (i, centre) = sketch.locate(target)?;
+        let (mean, _) = centroids[i];
+
+        // Interpolate between neighbouring centroid centres, using the
+        // exact min and max beyond the outermost ones
+        let (lo, hi) = if target < centre {
+            match i.checked_sub(1) {
+                Some(j) => (
+                    (
+                        centroids[j].0,
+                        centre - (centroids[j].1 + centroids[i].1) / 2.0,
+                    ),
+                    (mean, centre),
+                ),
+                None => ((self.min, 0.0), (mean, centre)),
+            }
+        } else {
+            match centroids.get(i + 1) {
+                Some(&(next, weight)) => (
+                    (mean, centre),
+                    (next, centre + (centroids[i].1 + weight) / 2.0),
+                ),
+                None => ((mean, centre), (self.max, self.count as f64)),
+            }
+        };
+        if hi.1 <= lo.1 {
+            return Some(lo.0);
+        }
+        let t = ((target - lo.1) / (hi.1 - lo.1)).clamp(0.0, 1.0);
+        Some(lo.0 + t * (hi.0 - lo.0))
+    }
+
+    /// Rank error of `quantile(q)` as a fraction of the count: half the
+    /// weight of the centroid the estimate falls in. Zero while every value
+    /// still has its own centroid.
+    pub fn rank_error(&self, q: f64) -> f64 {
+        if self.count == 0 {
+            return 0.0;
+        }
+        let sketch = self.compressed();
+        let target = q.clamp(0.0, 1.0) * self.count as f64;
+        match sketch.locate(target) {
+            Some((i, _)) if sketch.centroids[i].1 > 1.0 => {
+                sketch.centroids[i].1 / 2.0 / self.count as f64
+            }
+            _ => 0.0,
+        }
+    }
+
+    /// Fold `other`'s values in, as if they had been added here.
+    pub fn merge(&mut self, other: &QuantileSketch) {
+        let other = other.compressed();
+        self.compress();
+        let mut all = std::mem::take(&mut self.centroids);
+        all.extend_from_slice(&other.centroids);
+        self.rebuild(all);
+        self.count += other.count;
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    pub fn min(&self) -> Option<f64> {
+        (self.count > 0).then_some(self.min)
+    }
+
+    pub fn max(&self) -> Option<f64> {
+        (self.count > 0).then_some(self.max)
+    }
+
+    /// Centroids currently held (after merging any buffered values).
+    pub fn centroid_count(&self) -> usize {
+        self.compressed().centroids.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::DefaultRng;
+    use rand::Rng;
+
+    fn exact_quantile(sorted: &[f64], q: f64) -> f64 {
+        sorted[((q * sorted.len() as f64) as usize).min(sorted.len() - 1)]
+    }
+
+    #[test]
+    fn test_quantiles_within_reported_error() {
+        let mut rng = DefaultRng::seed_from(3);
+        let mut values: Vec<f64> = (0..50_000)
+            .map(|_| -rng.gen::<f64>().ln() * 10.0) // exponential, mean 10
+            .collect();
+        let mut sketch = QuantileSketch::new(100.0);
+        for &v in &values {
+            sketch.add(v);
+        }
+        values.sort_by(f64::total_cmp);
+        assert!(sketch.centroid_count() < 200);
+        for q in [0.01, 0.25, 0.5, 0.9, 0.99, 0.999] {
+            let estimate = sketch.quantile(q).unwrap();
+            let error = sketch.rank_error(q) + 0.001;
+            let low = exact_quantile(&values, (q - error).max(0.0));
+            let high = exact_quantile(&values, (q + error).min(1.0));
+            assert!(low <= estimate && estimate <= high, "q={} {}", q, estimate);
+        }
+        assert_eq!(sketch.quantile(0.0), Some(values[0]));
+        assert_eq!(sketch.quantile(1.0), values.last().copied());
+        assert!(QuantileSketch::default().quantile(0.5).is_none());
+    }
+
+    #[test]
+    fn test_merge_matches_single_sketch() {
+        let mut a = QuantileSketch::new(100.0);
+        let mut b = QuantileSketch::new(100.0);
+        for i in 0..10_000 {
+            a.add(i as f64);
+            b.add((i + 10_000) as f64);
+        }
+        a.merge(&b);
+        assert_eq!(a.count(), 20_000);
+        assert_eq!(a.max(), Some(19_999.0));
+        let median = a.quantile(0.5).unwrap();
+        assert!((median - 10_000.0).abs() < 200.0, "{}", median);
+        let p99 = a.quantile(0.99).unwrap();
+        assert!((p99 - 19_800.0).abs() < 50.0, "{}", p99);
+    }
+}
@@ -0,0 +1,348 @@
+use crate::json::{self, Json};
+use crate::kv_store::{new_store, KvStore};
+use std::collections::BTreeMap;
+use wasm_bindgen::prelude::*;
+
+/// Why a query failed, reported as `error.code` in the response.
+struct QueryError {
+    code: &'static str,
+    message: String,
+}
+
+impl QueryError {
+    fn new(code: &'static str, message: impl Into<String>) -> Self {
+        QueryError {
+            code,
+            message: message.into(),
+        }
+    }
+
+    fn bad_request(message: impl Into<String>) -> Self {
+        QueryError::new("BadRequest", message)
+    }
+}
+
+fn arg<'a>(args: Option<&'a Json>, name: &str) -> Option<&'a Json> {
+    args.and_then(|args| args.get(name))
+}
+
+fn str_arg<'a>(args: Option<&'a Json>, name: &str) -> Result<&'a str, QueryError> {
+    arg(args, name)
+        .and_then(Json::as_str)
+        .ok_or_else(|| QueryError::bad_request(format!("args.{} must be a string", name)))
+}
+
+fn optional_str_arg<'a>(args: Option<&'a Json>, name: &str) -> Result<Option<&'a str>, QueryError> {
+    match arg(args, name) {
+        None | Some(Json::Null) => Ok(None),
+        Some(_) => str_arg(args, name).map(Some),
+    }
+}
+
+fn u32_arg(args: Option<&Json>, name: &str) -> Result<u32, QueryError> {
+    match arg(args, name).and_then(Json::as_f64) {
+        Some(n) if n.fract() == 0.0 && (0.0..=u32::MAX as f64).contains(&n) => Ok(n as u32),
+        _ => Err(QueryError::bad_request(format!(
+            "args.{} must be an integer between 0 and {}",
+            name,
+            u32::MAX
+        ))),
+    }
+}
+
+fn entries_json(entries: impl Iterator<Item = (String, u32)>) -> Json {
+    Json::Array(
+        entries
+            .map(|(key, value)| {
+                Json::Object(vec![
+                    ("key".to_string(), Json::String(key)),
+                    ("value".to_string(), Json::Number(value as f64)),
+                ])
+            })
+            .collect(),
+    )
+}
+
+/// Entries in key order, whatever order the structure keeps them in.
+fn sorted_entries(store: &dyn KvStore) -> Vec<(String, u32)> {
+    let mut entries = store.kv_entries();
+    if !matches!(store.kind(), "bst" | "rbtree" | "skiplist" | "trie") {
+        entries.sort_unstable();
+    }
+    entries
+}
+
+/// Routes structured requests to named structures, so a service worker or
+/// edge function can expose them without glue code per method.
+///
+/// A request is a JSON object `{ id?, structure, op, args? }`. `structure`
+/// names a mounted structure; a name that is also a structure kind
+/// (`"rbtree"`, `"hashmap"`, ...) is mounted empty on first use. Ops:
+///
+/// | op         | args                         | result                        |
+/// |------------|------------------------------|-------------------------------|
+/// | `insert`   | `key`, `value`               | `null`                        |
+/// | `get`      | `key`                        | value or `null`               |
+/// | `contains` | `key`                        | boolean                       |
+/// | `delete`   | `key`                        | whether the key was present   |
+/// | `len`      |                              | entry count                   |
+/// | `entries`  |                              | `[{ key, value }]`            |
+/// | `range`    | `start?`, `end?`, `limit?`   | entries with `start <= key < end`, in key order |
+/// | `prefix`   | `prefix`, `limit?`           | entries whose key starts with `prefix`, in key order |
+/// | `metrics`  |                              | `{ name: number }`            |
+///
+/// The response is `{ id, ok: true, result }` or
+/// `{ id, ok: false, error: { code, message } }`, echoing the request's
+/// `id`. Error codes are `BadRequest`, `UnknownStructure` and `UnknownOp`.
+///
+/// `range` and `prefix` scan every entry; on hash-based structures they
+/// also sort.
+///
+/// # Example
+/// ```javascript
+/// const service = new QueryService();
+/// service.query(JSON.stringify({ structure: "rbtree", op: "insert", args: { key: "b", value: 2 } }));
+/// const response = JSON.parse(service.query(JSON.stringify(
+///   { id: 7, structure: "rbtree", op: "range", args: { start: "a", end: "c" } })));
+/// // { id: 7, ok: true, result: [{ key: "b", value: 2 }] }
+/// ```
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct QueryService {
+    stores: BTreeMap<String, Box<dyn KvStore>>,
+}
+
+#[wasm_bindgen]
+impl QueryService {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> QueryService {
+        QueryService::default()
+    }
+
+    /// Mount an empty structure of `kind` under `name`, replacing any
+    /// structure already there.
+    pub fn mount(&mut self, name: &str, kind: &str) -> Result<(), JsValue> {
+        let store = new_store(kind, 0)
+            .ok_or_else(|| JsValue::from_str(&format!("unknown structure '{}'", kind)))?;
+        self.stores.insert(name.to_string(), store);
+        Ok(())
+    }
+
+    /// Drop the structure mounted under `name`.
+    pub fn unmount(&mut self, name: &str) -> bool {
+        self.stores.remove(name).is_some()
+    }
+
+    /// Names of mounted structures, sorted.
+    pub fn structures(&self) -> Vec<String> {
+        self.stores.keys().cloned().collect()
+    }
+
+    /// Handle one JSON request and return the JSON response. Never throws:
+    /// failures are reported in the response.
+    pub fn query(&mut self, request: &str) -> String {
+        let request = json::parse(request);
+        let id = request
+            .as_ref()
+            .ok()
+            .and_then(|r| r.get("id"))
+            .cloned()
+            .unwrap_or(Json::Null);
+        let mut response = vec![("id".to_string(), id)];
+        match request
+            .map_err(|e| QueryError::bad_request(format!("invalid JSON: {}", e)))
+            .and_then(|request| self.dispatch(&request))
+        {
+            Ok(result) => {
+                response.push(("ok".to_string(), Json::Bool(true)));
+                response.push(("result".to_string(), result));
+            }
+            Err(error) => {
+                response.push(("ok".to_string(), Json::Bool(false)));
+                response.push((
+                    "error".to_string(),
+                    Json::Object(vec![
+                        ("code".to_string(), Json::String(error.code.to_string())),
+                        ("message".to_string(), Json::String(error.message)),
+                    ]),
+                ));
+            }
+        }
+        let mut out = String::new();
+        Json::Object(response).write(&mut out);
+        out
+    }
+}
+
+impl QueryService {
+    fn dispatch(&mut self, request: &Json) -> Result<Json, QueryError> {
+        if !matches!(request, Json::Object(_)) {
+            return Err(QueryError::bad_request("request must be a JSON object"));
+        }
+        let name = request
+            .get("structure")
+            .and_then(Json::as_str)
+            .ok_or_else(|| QueryError::bad_request("'structure' must be a string"))?;
+        let op = request
+            .get("op")
+            .and_then(Json::as_str)
+            .ok_or_else(|| QueryError::bad_request("'op' must be a string"))?;
+        let args = request.get("args");
+        if !self.stores.contains_key(name) {
+            let store = new_store(name, 0).ok_or_else(|| {
+                QueryError::new("UnknownStructure", format!("no structure named '{}'", name))
+            })?;
+            self.stores.insert(name.to_string(), store);
+        }
+        let store = self.stores.get_mut(name).expect("mounted above");
+        let limit = || match arg(args, "limit") {
+            None | Some(Json::Null) => Ok(usize::MAX),
+            Some(_) => u32_arg(args, "limit").map(|n| n as usize),
+        };
+        Ok(match op {
+            "insert" => {
+                let key = str_arg(args, "key")?;
+                let value = u32_arg(args, "value")?;
+                store.kv_insert(key.to_string(), value);
+                Json::Null
+            }
+            "get" => store
+                .kv_get(str_arg(args, "key")?)
+                .map_or(Json::Null, |v| Json::Number(v as f64)),
+            "contains" => Json::Bool(store.kv_get(str_arg(args, "key")?).is_some()),
+            "delete" => Json::Bool(store.kv_delete(str_arg(args, "key")?)),
+            "len" => Json::Number(store.kv_len() as f64),
+            "entries" => entries_json(store.kv_entries().into_iter()),
+            "range" => {
+                let start = optional_str_arg(args, "start")?;
+                let end = optional_str_arg(args, "end")?;
+                let limit = limit()?;
+                entries_json(
+                    sorted_entries(store.as_ref())
+                        .into_iter()
+                        .filter(|(key, _)| {
+                            start.is_none_or(|s| key.as_str() >= s)
+                                && end.is_none_or(|e| key.as_str() < e)
+                        })
+                        .take(limit),
+                )
+            }
+            "prefix" => {
+                let prefix = str_arg(args, "prefix")?;
+                let limit = limit()?;
+                entries_json(
+                    sorted_entries(store.as_ref())
+                        .into_iter()
+                        .filter(|(key, _)| key.starts_with(prefix))
+                        .take(limit),
+                )
+            }
+            "metrics" => Json::Object(
+                store
+                    .metrics_snapshot()
+                    .into_iter()
+                    .map(|(name, value)| (name.to_string(), Json::Number(value)))
+                    .collect(),
+            ),
+            _ => return Err(QueryError::new("UnknownOp", format!("unknown op '{}'", op))),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(service: &mut QueryService, request: &str) -> Json {
+        json::parse(&service.query(request)).unwrap()
+    }
+
+    #[test]
+    fn test_routes_ops() {
+        let mut service = QueryService::new();
+        for kind in ["rbtree", "hashmap"] {
+            for (i, key) in ["pear", "apple", "plum", "fig"].iter().enumerate() {
+                let request = format!(
+                    r#"{{"structure":"{}","op":"insert","args":{{"key":"{}","value":{}}}}}"#,
+                    kind, key, i
+                );
+                assert_eq!(
+                    query(&mut service, &request).get("ok"),
+                    Some(&Json::Bool(true))
+                );
+            }
+            let response = query(
+                &mut service,
+                &format!(
+                    r#"{{"id":"r1","structure":"{}","op":"range","args":{{"start":"b","end":"pl","limit":5}}}}"#,
+                    kind
+                ),
+            );
+            assert_eq!(response.get("id").and_then(Json::as_str), Some("r1"));
+            let mut out = String::new();
+            response.get("result").unwrap().write(&mut out);
+            assert_eq!(out, r#"[{"key":"fig","value":3},{"key":"pear","value":0}]"#);
+
+            let response = query(
+                &mut service,
+                &format!(
+                    r#"{{"structure":"{}","op":"prefix","args":{{"prefix":"p"}}}}"#,
+                    kind
+                ),
+            );
+            let Some(Json::Array(found)) = response.get("result") else {
+                panic!("{:?}", response);
+            };
+            assert_eq!(found.len(), 2);
+            let response = query(
+                &mut service,
+                &format!(
+                    r#"{{"structure":"{}","op":"delete","args":{{"key":"fig"}}}}"#,
+                    kind
+                ),
+            );
+            assert_eq!(response.get("result"), Some(&Json::Bool(true)));
+            let response = query(
+                &mut service,
+                &format!(r#"{{"structure":"{}","op":"len"}}"#, kind),
+            );
+            assert_eq!(response.get("result"), Some(&Json::Number(3.0)));
+        }
+        assert_eq!(service.structures(), ["hashmap", "rbtree"]);
+
+        service.mount("users", "trie").unwrap();
+        let response = query(
+            &mut service,
+            r#"{"structure":"users","op":"get","args":{"key":"nobody"}}"#,
+        );
+        assert_eq!(response.get("result"), Some(&Json::Null));
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut service = QueryService::new();
+        for (request, code) in [
+            ("{not json", "BadRequest"),
+            ("[1]", "BadRequest"),
+            (r#"{"structure":"nope","op":"len"}"#, "UnknownStructure"),
+            (r#"{"structure":"bst","op":"fly"}"#, "UnknownOp"),
+            (
+                r#"{"structure":"bst","op":"insert","args":{"key":"a","value":-1}}"#,
+                "BadRequest",
+            ),
+            (r#"{"structure":"bst","op":"get"}"#, "BadRequest"),
+        ] {
+            let response = query(&mut service, request);
+            assert_eq!(response.get("ok"), Some(&Json::Bool(false)), "{}", request);
+            let error = response.get("error").unwrap();
+            assert_eq!(
+                error.get("code").and_then(Json::as_str),
+                Some(code),
+                "{}",
+                request
+            );
+        }
+        assert!(service.unmount("bst"));
+        assert!(!service.unmount("nope"));
+    }
+}
@@ -0,0 +1,510 @@
+use crate::bloom::check_false_positive_rate;
+use crate::hashing::HashFunction;
+use wasm_bindgen::prelude::*;
+
+/// Slot holds the first fingerprint of some quotient's run.
+const OCCUPIED: u8 = 1;
+/// Slot continues the run started in an earlier slot.
+const CONTINUATION: u8 = 2;
+/// Slot's fingerprint isn't in its canonical slot.
+const SHIFTED: u8 = 4;
+
+/// Load past which an insert doubles the table first.
+const MAX_LOAD: f64 = 0.9;
+/// Remainder bits bounds: at least one must survive each doubling.
+const MIN_REMAINDER_BITS: u32 = 2;
+const MAX_REMAINDER_BITS: u32 = 24;
+
+/// Counters and cluster shape of a [`QuotientFilter`].
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct QuotientFilterMetrics {
+    pub inserts: u32,
+    /// Inserts refused because the table was full and couldn't grow
+    pub failed_inserts: u32,
+    pub deletes: u32,
+    /// Times the table doubled
+    pub resizes: u32,
+    /// Runs of consecutive non-empty slots
+    pub clusters: u32,
+    pub max_cluster_length: u32,
+    pub average_cluster_length: f64,
+}
+
+/// Approximate set membership by storing each key's fingerprint, split
+/// into a quotient (its canonical slot) and a remainder (what the slot
+/// stores), Bender et al.'s quotient filter.
+///
+/// Fingerprints of one quotient sit together, sorted, in a *run*; runs
+/// that overflow their slot shift right into a *cluster*, and three bits
+/// per slot (occupied, continuation, shifted) let a lookup rebuild which
+/// run is whose. Everything a lookup touches is contiguous, long clusters
+/// are what slow it down, hence the cluster metrics.
+///
+/// Fingerprints can be deleted, and the table can double without the
+/// original keys: each remainder gives up its top bit to the quotient.
+/// That happens automatically past 90% load, as long as a remainder bit
+/// is left to give, and halves the remainder's accuracy: the false
+/// positive rate roughly doubles with each resize.
+///
+/// # Example
+/// ```javascript
+/// const filter = new QuotientFilter(10_000, 0.01);
+/// filter.insert("alice");
+/// filter.contains("alice"); // true
+/// filter.delete("alice");
+/// filter.metrics().max_cluster_length;
+/// ```
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct QuotientFilter {
+    quotient_bits: u32,
+    remainder_bits: u32,
+    remainders: Vec<u32>,
+    meta: Vec<u8>,
+    items: usize,
+    metrics: QuotientFilterMetrics,
+}
+
+impl QuotientFilter {
+    pub fn try_new(capacity: usize, false_positive_rate: f64) -> Result<QuotientFilter, String> {
+        check_false_positive_rate(false_positive_rate)?;
+        let remainder_bits = (1.0 / false_positive_rate)
+            .log2()
+            .ceil()
+            .clamp(MIN_REMAINDER_BITS as f64, MAX_REMAINDER_BITS as f64)
+            as u32;
+        let slots = ((capacity.max(1) as f64 / MAX_LOAD).ceil() as usize).next_power_of_two();
+        Ok(Self::with_bits(
+            slots.trailing_zeros().max(1),
+            remainder_bits,
+        ))
+    }
+
+    fn with_bits(quotient_bits: u32, remainder_bits: u32) -> QuotientFilter {
+        let slots = 1 << quotient_bits;
+        QuotientFilter {
+            quotient_bits,
+            remainder_bits,
+            remainders: vec![0; slots],
+            meta: vec![0; slots],
+            items: 0,
+            metrics: QuotientFilterMetrics::default(),
+        }
+    }
+
+    fn slots(&self) -> usize {
+        self.meta.len()
+    }
+
+    fn next(&self, i: usize) -> usize {
+        (i + 1) & (self.slots() - 1)
+    }
+
+    fn prev(&self, i: usize) -> usize {
+        i.wrapping_sub(1) & (self.slots() - 1)
+    }
+
+    fn is(&self, i: usize, flag: u8) -> bool {
+        self.meta[i] & flag != 0
+    }
+
+    fn is_empty_slot(&self, i: usize) -> bool {
+        self.meta[i] == 0
+    }
+
+    fn is_run_start(&self, i: usize) -> bool {
+        !self.is(i, CONTINUATION) && (self.is(i, OCCUPIED) || self.is(i, SHIFTED))
+    }
+
+    fn is_cluster_start(&self, i: usize) -> bool {
+        self.is(i, OCCUPIED) && !self.is(i, CONTINUATION) && !self.is(i, SHIFTED)
+    }
+
+    /// Quotient and remainder of `key`'s fingerprint.
+    fn fingerprint(&self, key: &str) -> (usize, u32) {
+        let hash = HashFunction::SipHash.hash(key);
+        let remainder = (hash & ((1 << self.remainder_bits) - 1)) as u32;
+        let quotient = (hash >> self.remainder_bits) as usize & (self.slots() - 1);
+        (quotient, remainder)
+    }
+
+    /// First slot of the run for `quotient`, whose occupied bit must be set.
+    /// Walks back to the cluster's start, then forward run by run.
+    fn run_start(&self, quotient: usize) -> usize {
+        let mut b = quotient;
+        while self.is(b, SHIFTED) {
+            b = self.prev(b);
+        }
+        let mut s = b;
+        while b != quotient {
+            loop {
+                s = self.next(s);
+                if !self.is(s, CONTINUATION) {
+                    break;
+                }
+            }
+            loop {
+                b = self.next(b);
+                if self.is(b, OCCUPIED) {
+                    break;
+                }
+            }
+        }
+        s
+    }
+
+    /// Write `remainder` with `flags` at `s`, shifting everything up to the
+    /// next empty slot one to the right. Occupied bits belong to slots, not
+    /// fingerprints, so they stay put.
+    fn shift_in(&mut self, mut s: usize, mut remainder: u32, mut flags: u8) {
+        loop {
+            let (old_remainder, old_meta) = (self.remainders[s], self.meta[s]);
+            let empty = old_meta == 0;
+            self.remainders[s] = remainder;
+            self.meta[s] = (flags & !OCCUPIED) | (old_meta & OCCUPIED);
+            if empty {
+                return;
+            }
+            remainder = old_remainder;
+            flags = old_meta | SHIFTED;
+            s = self.next(s);
+        }
+    }
+
+    /// Remove the fingerprint at `s`, pulling the rest of its cluster one
+    /// slot left. `quotient` is the canonical slot of the run at `s`.
+    fn remove_at(&mut self, mut s: usize, mut quotient: usize) {
+        let start = s;
+        loop {
+            let sp = self.next(s);
+            let occupied = self.meta[s] & OCCUPIED;
+            if self.is_empty_slot(sp) || self.is_cluster_start(sp) || sp == start {
+                self.remainders[s] = 0;
+                self.meta[s] = occupied;
+                return;
+            }
+            let mut moved = self.meta[sp] & !OCCUPIED;
+            if self.is_run_start(sp) {
+                loop {
+                    quotient = self.next(quotient);
+                    if self.is(quotient, OCCUPIED) {
+                        break;
+                    }
+                }
+                // The run reaches its canonical slot again
+                if quotient == s {
+                    moved &= !SHIFTED;
+                }
+            }
+            self.remainders[s] = self.remainders[sp];
+            self.meta[s] = moved | occupied;
+            s = sp;
+        }
+    }
+
+    /// Find `remainder` in `quotient`'s run: `Ok(slot)` if present,
+    /// `Err(slot)` with the sorted insertion point otherwise.
+    fn search_run(&self, quotient: usize, remainder: u32) -> Result<usize, usize> {
+        let mut s = self.run_start(quotient);
+        loop {
+            match self.remainders[s].cmp(&remainder) {
+                std::cmp::Ordering::Equal => return Ok(s),
+                std::cmp::Ordering::Greater => return Err(s),
+                std::cmp::Ordering::Less => {}
+            }
+            s = self.next(s);
+            if !self.is(s, CONTINUATION) {
+                return Err(s);
+            }
+        }
+    }
+
+    fn insert_fingerprint(&mut self, quotient: usize, remainder: u32) {
+        if self.is_empty_slot(quotient) {
+            self.remainders[quotient] = remainder;
+            self.meta[quotient] = OCCUPIED;
+            self.items += 1;
+            return;
+        }
+        let had_run = self.is(quotient, OCCUPIED);
+        self.meta[quotient] |= OCCUPIED;
+        let mut flags = 0;
+        let s = if had_run {
+            let start = self.run_start(quotient);
+            // An equal remainder goes in front of its twin: runs are
+            // multisets, so each insert can be deleted once
+            match self.search_run(quotient, remainder) {
+                Ok(s) | Err(s) if s == start => {
+                    // New head of the run: the old head continues it
+                    self.meta[start] |= CONTINUATION;
+                    s
+                }
+                Ok(s) | Err(s) => {
+                    flags |= CONTINUATION;
+                    s
+                }
+            }
+        } else {
+            self.run_start(quotient)
+        };
+        if s != quotient {
+            flags |= SHIFTED;
+        }
+        self.shift_in(s, remainder, flags);
+        self.items += 1;
+    }
+
+    fn delete_fingerprint(&mut self, quotient: usize, remainder: u32) -> bool {
+        if !self.is(quotient, OCCUPIED) {
+            return false;
+        }
+        let Ok(s) = self.search_run(quotient, remainder) else {
+            return false;
+        };
+        let was_run_start = self.is_run_start(s);
+        // Deleting the run's only fingerprint leaves the quotient empty
+        if was_run_start && !self.is(self.next(s), CONTINUATION) {
+            self.meta[quotient] &= !OCCUPIED;
+        }
+        self.remove_at(s, quotient);
+        if was_run_start && self.is(s, CONTINUATION) {
+            // The run's second fingerprint becomes its head
+            self.meta[s] &= !CONTINUATION;
+            if s == quotient {
+                self.meta[s] &= !SHIFTED;
+            }
+        }
+        self.items -= 1;
+        true
+    }
+
+    /// Every stored fingerprint as (quotient, remainder).
+    fn fingerprints(&self) -> Vec<(usize, u32)> {
+        let mut out = Vec::with_capacity(self.items);
+        for quotient in 0..self.slots() {
+            if !self.is(quotient, OCCUPIED) {
+                continue;
+            }
+            let mut s = self.run_start(quotient);
+            loop {
+                out.push((quotient, self.remainders[s]));
+                s = self.next(s);
+                if !self.is(s, CONTINUATION) {
+                    break;
+                }
+            }
+        }
+        out
+    }
+
+    /// Double the slots, moving each remainder's top bit into the quotient.
+    /// Returns false, changing nothing, when no remainder bit is left.
+    fn grow(&mut self) -> bool {
+        if self.remainder_bits <= 1 {
+            return false;
+        }
+        let mut grown = Self::with_bits(self.quotient_bits + 1, self.remainder_bits - 1);
+        let top = self.remainder_bits - 1;
+        for (quotient, remainder) in self.fingerprints() {
+            let quotient = quotient << 1 | (remainder >> top) as usize;
+            grown.insert_fingerprint(quotient, remainder & ((1 << top) - 1));
+        }
+        grown.metrics = self.metrics;
+        grown.metrics.resizes += 1;
+        *self = grown;
+        true
+    }
+}
+
+#[wasm_bindgen]
+impl QuotientFilter {
+    /// An empty filter for about `capacity` keys at `false_positive_rate`
+    /// (e.g. 0.01), which sets the remainder size (2 to 24 bits).
+    #[wasm_bindgen(constructor)]
+    pub fn new(capacity: u32, false_positive_rate: f64) -> Result<QuotientFilter, JsValue> {
+        Self::try_new(capacity as usize, false_positive_rate).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// Add `key`. Inserting a key twice stores its fingerprint twice, so
+    /// it takes two deletes to remove. Returns false if the table is full
+    /// and can't grow.
+    pub fn insert(&mut self, key: &str) -> bool {
+        if (self.items + 1) as f64 > MAX_LOAD * self.slots() as f64
+            && !self.grow()
+            && self.items == self.slots()
+        {
+            self.metrics.failed_inserts += 1;
+            return false;
+        }
+        let (quotient, remainder) = self.fingerprint(key);
+        self.insert_fingerprint(quotient, remainder);
+        self.metrics.inserts += 1;
+        true
+    }
+
+    /// False means `key` is certainly not in the filter.
+    pub fn contains(&self, key: &str) -> bool {
+        let (quotient, remainder) = self.fingerprint(key);
+        self.is(quotient, OCCUPIED) && self.search_run(quotient, remainder).is_ok()
+    }
+
+    /// Remove one copy of `key`'s fingerprint. Returns false if there was
+    /// none. Only delete keys that were inserted: another key with the same
+    /// fingerprint would lose its copy instead.
+    pub fn delete(&mut self, key: &str) -> bool {
+        let (quotient, remainder) = self.fingerprint(key);
+        let deleted = self.delete_fingerprint(quotient, remainder);
+        if deleted {
+            self.metrics.deletes += 1;
+        }
+        deleted
+    }
+
+    /// Fingerprints stored.
+    pub fn len(&self) -> usize {
+        self.items
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items == 0
+    }
+
+    pub fn slot_count(&self) -> usize {
+        self.slots()
+    }
+
+    pub fn remainder_bits(&self) -> u32 {
+        self.remainder_bits
+    }
+
+    /// Fraction of slots in use.
+    pub fn load_factor(&self) -> f64 {
+        self.items as f64 / self.slots() as f64
+    }
+
+    /// Bits per slot if packed: the remainder plus three metadata bits.
+    pub fn bits_per_slot(&self) -> u32 {
+        self.remainder_bits + 3
+    }
+
+    /// Bytes of storage as laid out here: a `u32` remainder and a byte of
+    /// metadata per slot.
+    pub fn memory_bytes(&self) -> usize {
+        self.slots() * (std::mem::size_of::<u32>() + 1)
+    }
+
+    /// Expected false positive rate at the current load:
+    /// 1 - e^(-load / 2^r).
+    pub fn estimated_false_positive_rate(&self) -> f64 {
+        1.0 - (-self.load_factor() / (1u64 << self.remainder_bits) as f64).exp()
+    }
+
+    /// Counters, with the cluster shape measured now.
+    pub fn metrics(&self) -> QuotientFilterMetrics {
+        let mut metrics = self.metrics;
+        let (mut clusters, mut total, mut longest) = (0u32, 0u32, 0u32);
+        // Start just after an empty slot so no cluster is split by the wrap
+        if let Some(empty) = (0..self.slots()).find(|&i| self.is_empty_slot(i)) {
+            let mut length = 0;
+            for step in 1..=self.slots() {
+                let i = (empty + step) & (self.slots() - 1);
+                if self.is_empty_slot(i) {
+                    if length > 0 {
+                        clusters += 1;
+                        total += length;
+                        longest = longest.max(length);
+                    }
+                    length = 0;
+                } else {
+                    length += 1;
+                }
+            }
+        } else if self.items > 0 {
+            (clusters, total, longest) = (1, self.items as u32, self.items as u32);
+        }
+        metrics.clusters = clusters;
+        metrics.max_cluster_length = longest;
+        metrics.average_cluster_length = if clusters == 0 {
+            0.0
+        } else {
+            total as f64 / clusters as f64
+        };
+        metrics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::DefaultRng;
+    use rand::Rng;
+
+    /// Exactly the expected fingerprints are stored, and every one of them
+    /// answers while no other does.
+    fn check(filter: &QuotientFilter, expected: &[(usize, u32)]) {
+        let mut stored = filter.fingerprints();
+        stored.sort_unstable();
+        let mut expected = expected.to_vec();
+        expected.sort_unstable();
+        assert_eq!(stored, expected);
+        assert_eq!(filter.len(), expected.len());
+        for quotient in 0..filter.slots() {
+            for remainder in 0..1 << filter.remainder_bits {
+                let found =
+                    filter.is(quotient, OCCUPIED) && filter.search_run(quotient, remainder).is_ok();
+                assert_eq!(found, expected.contains(&(quotient, remainder)));
+            }
+        }
+    }
+
+    #[test]
+    fn test_matches_a_fingerprint_set() {
+        // A tiny table with wraparound clusters, checked against the exact
+        // set of fingerprints after every operation
+        let mut filter = QuotientFilter::with_bits(4, 3);
+        let mut expected = Vec::new();
+        let mut rng = DefaultRng::seed_from(5);
+        for _ in 0..2000 {
+            let fingerprint = (rng.gen_range(0..16), rng.gen_range(0..4));
+            if rng.gen_bool(0.55) && expected.len() < 16 {
+                filter.insert_fingerprint(fingerprint.0, fingerprint.1);
+                expected.push(fingerprint);
+            } else {
+                let deleted = filter.delete_fingerprint(fingerprint.0, fingerprint.1);
+                let position = expected.iter().position(|&f| f == fingerprint);
+                assert_eq!(deleted, position.is_some());
+                if let Some(i) = position {
+                    expected.swap_remove(i);
+                }
+            }
+            check(&filter, &expected);
+        }
+    }
+
+    #[test]
+    fn test_keys_and_resize() {
+        let mut filter = QuotientFilter::try_new(1000, 0.0001).unwrap();
+        assert_eq!((filter.slot_count(), filter.remainder_bits()), (2048, 14));
+        for i in 0..1000 {
+            assert!(filter.insert(&format!("key{}", i)));
+        }
+        for i in 0..500 {
+            assert!(filter.delete(&format!("key{}", i)));
+        }
+        assert!((500..1000).all(|i| filter.contains(&format!("key{}", i))));
+        let metrics = filter.metrics();
+        assert_eq!((metrics.inserts, metrics.deletes), (1000, 500));
+        assert!(metrics.clusters > 0);
+        assert!(metrics.max_cluster_length as f64 >= metrics.average_cluster_length);
+
+        // Doubling keeps every fingerprint, at one remainder bit less
+        for i in 0..2000 {
+            filter.insert(&format!("more{}", i));
+        }
+        assert_eq!(filter.metrics().resizes, 1);
+        assert_eq!((filter.slot_count(), filter.remainder_bits()), (4096, 13));
+        assert!((500..1000).all(|i| filter.contains(&format!("key{}", i))));
+        assert!((0..2000).all(|i| filter.contains(&format!("more{}", i))));
+    }
+}
@@ -0,0 +1,358 @@
+use crate::clock::now_ms;
+use js_sys::Function;
+use wasm_bindgen::prelude::*;
+
+/// Where a rate limiter reads the time from, in milliseconds.
+///
+/// The system clock by default; tests and simulations switch to a manual
+/// clock they advance themselves, and JS can supply its own function (e.g.
+/// `performance.now`).
+pub enum TimeSource {
+    System,
+    Manual(f64),
+    Custom(Box<dyn Fn() -> f64>),
+}
+
+impl TimeSource {
+    fn now(&self) -> f64 {
+        match self {
+            TimeSource::System => now_ms(),
+            TimeSource::Manual(t) => *t,
+            TimeSource::Custom(f) => f(),
+        }
+    }
+
+    pub fn from_js(clock: Function) -> TimeSource {
+        TimeSource::Custom(Box::new(move || {
+            clock
+                .call0(&JsValue::UNDEFINED)
+                .ok()
+                .and_then(|v| v.as_f64())
+                .unwrap_or(f64::NAN)
+        }))
+    }
+}
+
+/// A [`TimeSource`] plus the last time read from it, so limiters can ask
+/// how much time has passed. Time never runs backwards: readings earlier
+/// than the last one (or NaN from a broken JS clock) count as no time.
+struct LimiterClock {
+    source: TimeSource,
+    last: f64,
+}
+
+impl LimiterClock {
+    fn new(source: TimeSource) -> LimiterClock {
+        let last = source.now();
+        LimiterClock { source, last }
+    }
+
+    /// Milliseconds since the previous call.
+    fn elapsed(&mut self) -> f64 {
+        let now = self.source.now();
+        if now > self.last {
+            let elapsed = now - self.last;
+            self.last = now;
+            elapsed
+        } else {
+            0.0
+        }
+    }
+
+    fn advance(&mut self, ms: f64) {
+        if let TimeSource::Manual(t) = &mut self.source {
+            *t += ms.max(0.0);
+        }
+    }
+}
+
+/// Requests let through and turned away by a rate limiter
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RateLimiterMetrics {
+    pub accepted: u32,
+    pub rejected: u32,
+    /// Units (tokens or queue slots) granted across accepted requests
+    pub accepted_units: f64,
+    pub rejected_units: f64,
+}
+
+impl RateLimiterMetrics {
+    fn record(&mut self, accepted: bool, units: f64) {
+        if accepted {
+            self.accepted += 1;
+            self.accepted_units += units;
+        } else {
+            self.rejected += 1;
+            self.rejected_units += units;
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl RateLimiterMetrics {
+    /// Fraction of requests rejected, 0.0 before any request.
+    pub fn rejection_rate(&self) -> f64 {
+        let total = self.accepted + self.rejected;
+        if total == 0 {
+            0.0
+        } else {
+            self.rejected as f64 / total as f64
+        }
+    }
+}
+
+/// Token bucket: holds up to `capacity` tokens, refilled continuously at
+/// `refill_per_second`. A request takes its tokens if they are there, so
+/// bursts up to the capacity pass at once and the long-run rate is capped
+/// at the refill rate.
+///
+/// # Example
+/// ```javascript
+/// const limiter = new TokenBucket(10, 2); // bursts of 10, 2/s sustained
+/// if (!limiter.try_acquire(1)) console.log(`retry in ${limiter.retry_after_ms(1)} ms`);
+/// ```
+#[wasm_bindgen]
+pub struct TokenBucket {
+    capacity: f64,
+    refill_per_ms: f64,
+    tokens: f64,
+    clock: LimiterClock,
+    metrics: RateLimiterMetrics,
+}
+
+impl TokenBucket {
+    fn refill(&mut self) {
+        let elapsed = self.clock.elapsed();
+        self.tokens = (self.tokens + elapsed * self.refill_per_ms).min(self.capacity);
+    }
+
+    /// Read the time from `source` from now on.
+    pub fn set_time_source(&mut self, source: TimeSource) {
+        self.refill();
+        self.clock = LimiterClock::new(source);
+    }
+}
+
+#[wasm_bindgen]
+impl TokenBucket {
+    /// A full bucket on the system clock.
+    #[wasm_bindgen(constructor)]
+    pub fn new(capacity: f64, refill_per_second: f64) -> TokenBucket {
+        TokenBucket {
+            capacity: capacity.max(0.0),
+            refill_per_ms: refill_per_second.max(0.0) / 1000.0,
+            tokens: capacity.max(0.0),
+            clock: LimiterClock::new(TimeSource::System),
+            metrics: RateLimiterMetrics::default(),
+        }
+    }
+
+    /// Take `n` tokens if available. Nothing is taken on rejection.
+    pub fn try_acquire(&mut self, n: f64) -> bool {
+        self.refill();
+        let accepted = n <= self.tokens;
+        if accepted {
+            self.tokens -= n;
+        }
+        self.metrics.record(accepted, n);
+        accepted
+    }
+
+    /// Tokens available right now.
+    pub fn available(&mut self) -> f64 {
+        self.refill();
+        self.tokens
+    }
+
+    /// Milliseconds until `n` tokens will be available: 0 if they already
+    /// are, infinite if `n` exceeds the capacity or nothing refills.
+    pub fn retry_after_ms(&mut self, n: f64) -> f64 {
+        self.refill();
+        if n <= self.tokens {
+            0.0
+        } else if n > self.capacity || self.refill_per_ms == 0.0 {
+            f64::INFINITY
+        } else {
+            (n - self.tokens) / self.refill_per_ms
+        }
+    }
+
+    pub fn capacity(&self) -> f64 {
+        self.capacity
+    }
+
+    /// Switch to a manual clock starting at `start_ms`; time then only
+    /// moves through `advance_time`.
+    pub fn use_manual_clock(&mut self, start_ms: f64) {
+        self.set_time_source(TimeSource::Manual(start_ms));
+    }
+
+    /// Move a manual clock forward. No effect on other clocks.
+    pub fn advance_time(&mut self, ms: f64) {
+        self.clock.advance(ms);
+    }
+
+    /// Read the time by calling `clock()`, which must return milliseconds,
+    /// e.g. `() => performance.now()`.
+    pub fn use_clock(&mut self, clock: Function) {
+        self.set_time_source(TimeSource::from_js(clock));
+    }
+
+    pub fn get_metrics(&self) -> RateLimiterMetrics {
+        self.metrics
+    }
+}
+
+/// Leaky bucket used as a meter: each request pours its units in, the
+/// bucket drains at `leak_per_second`, and a request that would overflow
+/// `capacity` is rejected. Unlike the token bucket, a burst right after an
+/// idle spell gets no extra allowance beyond the bucket's free space, which
+/// smooths output to the leak rate.
+///
+/// # Example
+/// ```javascript
+/// const meter = new LeakyBucket(5, 1); // queue of 5, drains 1/s
+/// meter.try_acquire(1);
+/// ```
+#[wasm_bindgen]
+pub struct LeakyBucket {
+    capacity: f64,
+    leak_per_ms: f64,
+    level: f64,
+    clock: LimiterClock,
+    metrics: RateLimiterMetrics,
+}
+
+impl LeakyBucket {
+    fn leak(&mut self) {
+        let elapsed = self.clock.elapsed();
+        self.level = (self.level - elapsed * self.leak_per_ms).max(0.0);
+    }
+
+    /// Read the time from `source` from now on.
+    pub fn set_time_source(&mut self, source: TimeSource) {
+        self.leak();
+        self.clock = LimiterClock::new(source);
+    }
+}
+
+#[wasm_bindgen]
+impl LeakyBucket {
+    /// An empty bucket on the system clock.
+    #[wasm_bindgen(constructor)]
+    pub fn new(capacity: f64, leak_per_second: f64) -> LeakyBucket {
+        LeakyBucket {
+            capacity: capacity.max(0.0),
+            leak_per_ms: leak_per_second.max(0.0) / 1000.0,
+            level: 0.0,
+            clock: LimiterClock::new(TimeSource::System),
+            metrics: RateLimiterMetrics::default(),
+        }
+    }
+
+    /// Pour `n` units in if they fit.
+    pub fn try_acquire(&mut self, n: f64) -> bool {
+        self.leak();
+        let accepted = self.level + n <= self.capacity;
+        if accepted {
+            self.level += n;
+        }
+        self.metrics.record(accepted, n);
+        accepted
+    }
+
+    /// Units currently in the bucket.
+    pub fn level(&mut self) -> f64 {
+        self.leak();
+        self.level
+    }
+
+    /// Milliseconds until `n` more units will fit: 0 if they already do,
+    /// infinite if `n` exceeds the capacity or nothing drains.
+    pub fn retry_after_ms(&mut self, n: f64) -> f64 {
+        self.leak();
+        let excess = self.level + n - self.capacity;
+        if excess <= 0.0 {
+            0.0
+        } else if n > self.capacity || self.leak_per_ms == 0.0 {
+            f64::INFINITY
+        } else {
+            excess / self.leak_per_ms
+        }
+    }
+
+    pub fn capacity(&self) -> f64 {
+        self.capacity
+    }
+
+    /// Switch to a manual clock starting at `start_ms`; time then only
+    /// moves through `advance_time`.
+    pub fn use_manual_clock(&mut self, start_ms: f64) {
+        self.set_time_source(TimeSource::Manual(start_ms));
+    }
+
+    /// Move a manual clock forward. No effect on other clocks.
+    pub fn advance_time(&mut self, ms: f64) {
+        self.clock.advance(ms);
+    }
+
+    /// Read the time by calling `clock()`, which must return milliseconds,
+    /// e.g. `() => performance.now()`.
+    pub fn use_clock(&mut self, clock: Function) {
+        self.set_time_source(TimeSource::from_js(clock));
+    }
+
+    pub fn get_metrics(&self) -> RateLimiterMetrics {
+        self.metrics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_bucket() {
+        let mut bucket = TokenBucket::new(10.0, 2.0);
+        bucket.use_manual_clock(0.0);
+        assert!(bucket.try_acquire(10.0));
+        assert!(!bucket.try_acquire(1.0));
+        assert_eq!(bucket.retry_after_ms(1.0), 500.0);
+        assert_eq!(bucket.retry_after_ms(11.0), f64::INFINITY);
+
+        bucket.advance_time(1_000.0);
+        assert_eq!(bucket.available(), 2.0);
+        assert!(bucket.try_acquire(2.0));
+        // Refills never overshoot the capacity
+        bucket.advance_time(60_000.0);
+        assert_eq!(bucket.available(), 10.0);
+
+        let metrics = bucket.get_metrics();
+        assert_eq!((metrics.accepted, metrics.rejected), (2, 1));
+        assert_eq!(metrics.accepted_units, 12.0);
+        assert!((metrics.rejection_rate() - 1.0 / 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_leaky_bucket_with_custom_clock() {
+        use std::cell::Cell;
+        use std::rc::Rc;
+
+        let time = Rc::new(Cell::new(0.0));
+        let mut bucket = LeakyBucket::new(3.0, 1.0);
+        let t = time.clone();
+        bucket.set_time_source(TimeSource::Custom(Box::new(move || t.get())));
+
+        for _ in 0..3 {
+            assert!(bucket.try_acquire(1.0));
+        }
+        assert!(!bucket.try_acquire(1.0));
+        assert_eq!(bucket.retry_after_ms(1.0), 1_000.0);
+        time.set(1_500.0);
+        assert_eq!(bucket.level(), 1.5);
+        assert!(bucket.try_acquire(1.5));
+        assert!(!bucket.try_acquire(0.5));
+        assert_eq!(bucket.get_metrics().rejected, 2);
+    }
+}
@@ -1,3 +1,11 @@
+use crate::ascii;
+use crate::bst::PathStep;
+use crate::bulk::BulkInsertJob;
+use crate::csv;
+use crate::interop;
+use crate::memory::MemoryReport;
+#[cfg(feature = "msgpack")]
+use crate::msgpack;
 use wasm_bindgen::prelude::*;
 
 #[wasm_bindgen]
@@ -31,6 +39,31 @@ impl Node {
         let right_height = self.right.as_ref().map_or(0, |n| n.height());
         1 + left_height.max(right_height)
     }
+
+    fn measure(&self, report: &mut MemoryReport) {
+        report.add_exact(std::mem::size_of::<Node>());
+        report.add_string(&self.key, self.key.capacity());
+        for child in [&self.left, &self.right].into_iter().flatten() {
+            child.measure(report);
+        }
+    }
+
+    fn collect_in_order<'a>(&'a self, out: &mut Vec<(&'a str, u32)>) {
+        if let Some(left) = &self.left {
+            left.collect_in_order(out);
+        }
+        out.push((&self.key, self.value));
+        if let Some(right) = &self.right {
+            right.collect_in_order(out);
+        }
+    }
+
+    fn shrink_keys(&mut self) {
+        self.key.shrink_to_fit();
+        for child in [&mut self.left, &mut self.right].into_iter().flatten() {
+            child.shrink_keys();
+        }
+    }
 }
 
 /// Metrics collected during RB-Tree operations
@@ -54,6 +87,30 @@ pub struct RedBlackTree {
     metrics: RBTreeMetrics,
 }
 
+impl Default for RedBlackTree {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RedBlackTree {
+    fn entry_refs(&self) -> Vec<(&str, u32)> {
+        let mut entries = Vec::with_capacity(self.size as usize);
+        if let Some(root) = &self.root {
+            root.collect_in_order(&mut entries);
+        }
+        entries
+    }
+
+    /// Every entry, in key order.
+    pub(crate) fn pairs(&self) -> Vec<(String, u32)> {
+        self.entry_refs()
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value))
+            .collect()
+    }
+}
+
 #[wasm_bindgen]
 impl RedBlackTree {
     #[wasm_bindgen(constructor)]
@@ -196,8 +253,8 @@ impl RedBlackTree {
             }
         } else {
             // Tree is balanced at this node, but recolor if both children are red
-            let left_is_red = node.left.as_ref().map_or(false, |n| n.color == Color::Red);
-            let right_is_red = node.right.as_ref().map_or(false, |n| n.color == Color::Red);
+            let left_is_red = node.left.as_ref().is_some_and(|n| n.color == Color::Red);
+            let right_is_red = node.right.as_ref().is_some_and(|n| n.color == Color::Red);
 
             if left_is_red && right_is_red {
                 // Both children red - recolor to maintain properties
@@ -263,9 +320,9 @@ impl RedBlackTree {
         match node {
             None => None,
             Some(n) => {
-                if key == &n.key {
+                if key == n.key {
                     Some(n.value)
-                } else if key < &n.key {
+                } else if key < n.key.as_str() {
                     self.get_recursive(&n.left, key)
                 } else {
                     self.get_recursive(&n.right, key)
@@ -274,6 +331,32 @@ impl RedBlackTree {
         }
     }
 
+    /// Number of edges from the root to `key` (the root is at depth 0).
+    pub fn depth_of(&self, key: &str) -> Option<u32> {
+        let path = self.path_to(key);
+        match path.last() {
+            Some(step) if step.direction == "found" => Some(path.len() as u32 - 1),
+            _ => None,
+        }
+    }
+
+    /// Nodes a lookup for `key` visits, from the root down. If the key is
+    /// absent the path ends at the node whose empty child it would occupy.
+    pub fn path_to(&self, key: &str) -> Vec<PathStep> {
+        let mut path = Vec::new();
+        let mut node = &self.root;
+        while let Some(n) = node {
+            let ordering = key.cmp(n.key.as_str());
+            path.push(PathStep::new(&n.key, ordering));
+            node = match ordering {
+                std::cmp::Ordering::Less => &n.left,
+                std::cmp::Ordering::Greater => &n.right,
+                std::cmp::Ordering::Equal => break,
+            };
+        }
+        path
+    }
+
     pub fn delete(&mut self, key: &str) -> Option<u32> {
         let result = Self::delete_recursive(&mut self.root, key);
         if result.is_some() {
@@ -288,28 +371,21 @@ impl RedBlackTree {
         match node {
             None => None,
             Some(n) => {
-                if key == &n.key {
+                if key == n.key {
                     let value = n.value;
                     // Simple deletion: replace with left or right subtree
-                    *node = if n.left.is_none() {
-                        n.right.take()
+                    if n.left.is_none() {
+                        *node = n.right.take();
                     } else if n.right.is_none() {
-                        n.left.take()
+                        *node = n.left.take();
                     } else {
-                        // Both children exist - find min in right subtree
-                        let mut current = n.right.take().unwrap();
-                        while let Some(mut left_child) = current.left.take() {
-                            if left_child.left.is_none() {
-                                current.left = left_child.right.take();
-                                break;
-                            }
-                            current.left = Some(left_child);
-                        }
-                        current.left = n.left.take();
-                        Some(current)
-                    };
+                        // Both children exist - replace with min of right subtree
+                        let (min_key, min_value) = Self::take_min(&mut n.right);
+                        n.key = min_key;
+                        n.value = min_value;
+                    }
                     Some(value)
-                } else if key < &n.key {
+                } else if key < n.key.as_str() {
                     Self::delete_recursive(&mut n.left, key)
                 } else {
                     Self::delete_recursive(&mut n.right, key)
@@ -318,10 +394,117 @@ impl RedBlackTree {
         }
     }
 
+    /// Unlink the minimum node of a non-empty subtree, returning its entry
+    fn take_min(node: &mut Option<Box<Node>>) -> (String, u32) {
+        if node.as_ref().is_some_and(|n| n.left.is_some()) {
+            return Self::take_min(&mut node.as_mut().unwrap().left);
+        }
+        let mut min = node.take().expect("take_min on empty subtree");
+        *node = min.right.take();
+        (min.key, min.value)
+    }
+
     pub fn get_metrics(&self) -> RBTreeMetrics {
         self.metrics.clone()
     }
 
+    pub fn len(&self) -> u32 {
+        self.size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    /// Build a tree from a JS `Map` of string keys to integer values
+    /// (0 to 2^32 - 1). Throws on the first entry that doesn't fit.
+    pub fn from_js_map(map: &js_sys::Map) -> Result<RedBlackTree, JsValue> {
+        interop::from_map(map, |_| RedBlackTree::new())
+    }
+
+    /// Build a tree from a plain object's own enumerable properties, as
+    /// `Object.entries` lists them. Values must be integers as for `from_js_map`.
+    pub fn from_object(object: &js_sys::Object) -> Result<RedBlackTree, JsValue> {
+        interop::from_object(object, |_| RedBlackTree::new())
+    }
+
+    /// The entries as a new JS `Map`, in key order.
+    pub fn to_js_map(&self) -> js_sys::Map {
+        interop::to_map(self)
+    }
+
+    /// The entries as a new plain object, one property per key.
+    pub fn to_object(&self) -> js_sys::Object {
+        interop::to_object(self)
+    }
+
+    /// Build a tree from CSV text whose first row names the columns,
+    /// taking keys from `key_col` and integer values from `value_col`.
+    /// Quoted fields may contain commas, quotes (as `""`) and line breaks.
+    /// Throws with the line number of the first bad row.
+    pub fn from_csv(text: &str, key_col: &str, value_col: &str) -> Result<RedBlackTree, JsValue> {
+        csv::from_csv(text, key_col, value_col, |_| RedBlackTree::new())
+    }
+
+    /// The entries as CSV with a `key,value` header row, quoting keys
+    /// where needed.
+    pub fn to_csv(&self) -> String {
+        csv::to_csv(self)
+    }
+
+    /// Build a tree from a MessagePack map of string keys to integers,
+    /// as JS encoders produce from a plain object. Requires the `msgpack`
+    /// feature.
+    #[cfg(feature = "msgpack")]
+    pub fn from_msgpack(bytes: &[u8]) -> Result<RedBlackTree, JsValue> {
+        msgpack::from_msgpack(bytes, |_| RedBlackTree::new())
+    }
+
+    /// The entries as a MessagePack map. Requires the `msgpack` feature.
+    #[cfg(feature = "msgpack")]
+    pub fn to_msgpack(&self) -> Vec<u8> {
+        msgpack::to_msgpack(self)
+    }
+
+    /// The tree drawn sideways as in
+    /// [`BinarySearchTree::to_ascii`](crate::BinarySearchTree::to_ascii),
+    /// each node as `key (value) R` or `key (value) B` by color.
+    pub fn to_ascii(&self) -> String {
+        ascii::binary_tree(
+            self.root.as_deref(),
+            |node| (node.left.as_deref(), node.right.as_deref()),
+            |node| {
+                let color = if node.color == Color::Red { 'R' } else { 'B' };
+                format!("{} ({}) {}", node.key, node.value, color)
+            },
+        )
+    }
+
+    /// Insert queued entries from `job` until `millis` milliseconds have elapsed.
+    ///
+    /// Returns how many entries were inserted; call again (e.g. once per
+    /// animation frame) until `job.is_done()`.
+    pub fn insert_budgeted(&mut self, job: &mut BulkInsertJob, millis: f64) -> u32 {
+        job.run_for(self, millis)
+    }
+
+    /// Estimate heap usage: one boxed node per key plus the key strings.
+    pub fn memory_report(&self) -> MemoryReport {
+        let mut report = MemoryReport::default();
+        if let Some(root) = &self.root {
+            root.measure(&mut report);
+        }
+        report
+    }
+
+    /// Trim spare capacity from key strings. Nodes are individually boxed,
+    /// so there is no other slack to reclaim.
+    pub fn shrink_to_fit(&mut self) {
+        if let Some(root) = &mut self.root {
+            root.shrink_keys();
+        }
+    }
+
     fn update_metrics(&mut self) {
         self.metrics.tree_height = self.root.as_ref().map_or(0, |n| n.height());
         self.metrics.balance_ratio = if self.size == 0 { 0.0 } else { 1.0 };
@@ -385,7 +568,7 @@ mod tests {
     #[test]
     fn test_random_order_insertion() {
         let mut tree = RedBlackTree::new();
-        let keys = vec!["d", "b", "a", "c", "e", "f"];
+        let keys = ["d", "b", "a", "c", "e", "f"];
         for (i, key) in keys.iter().enumerate() {
             tree.insert(key.to_string(), i as u32);
         }
@@ -444,6 +627,62 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_delete_root_with_two_children_keeps_order() {
+        let mut tree = RedBlackTree::new();
+        let mut expected: Vec<_> = (0..64).map(|i| (format!("key{:02}", i), i)).collect();
+        for (key, value) in &expected {
+            tree.insert(key.clone(), *value);
+        }
+        // Each root deletion unlinks the in-order successor from the right
+        // subtree, keeping whatever hangs off the successor's right
+        while let Some(root) = tree
+            .root
+            .as_ref()
+            .filter(|r| r.left.is_some() && r.right.is_some())
+        {
+            let key = root.key.clone();
+            let value = root.value;
+            assert_eq!(tree.delete(&key), Some(value));
+            expected.retain(|(k, _)| *k != key);
+            assert_eq!(tree.pairs(), expected);
+        }
+        assert!(expected.len() < 64);
+    }
+
+    #[test]
+    fn test_delete_keeps_remaining_keys() {
+        let mut tree = RedBlackTree::new();
+        for i in 0..100 {
+            tree.insert(format!("key{:03}", i), i);
+        }
+        for i in (0..100).step_by(3) {
+            assert_eq!(tree.delete(&format!("key{:03}", i)), Some(i));
+        }
+        for i in 0..100 {
+            let expected = if i % 3 == 0 { None } else { Some(i) };
+            assert_eq!(tree.get(&format!("key{:03}", i)), expected);
+        }
+    }
+
+    #[test]
+    fn test_depth_of_matches_path() {
+        let mut tree = RedBlackTree::new();
+        for i in 0..100 {
+            tree.insert(format!("key{:03}", i), i);
+        }
+        let height = tree.get_metrics().tree_height;
+        for i in 0..100 {
+            let key = format!("key{:03}", i);
+            let path = tree.path_to(&key);
+            assert_eq!(path.last().unwrap().key, key);
+            assert_eq!(tree.depth_of(&key), Some(path.len() as u32 - 1));
+            assert!(path.len() as u32 <= height);
+        }
+        assert_eq!(tree.depth_of("missing"), None);
+        assert_ne!(tree.path_to("missing").last().unwrap().direction, "found");
+    }
+
     #[test]
     fn test_sequential_retrieval() {
         let mut tree = RedBlackTree::new();
@@ -0,0 +1,216 @@
+use crate::kv_store::KvStore;
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+use wasm_bindgen::prelude::*;
+
+/// A structure that can be observed without being owned, so the registry
+/// can report on it for as long as JS keeps it alive.
+pub(crate) type SharedStore = Rc<RefCell<Box<dyn KvStore>>>;
+type WeakStore = Weak<RefCell<Box<dyn KvStore>>>;
+
+#[derive(Default)]
+struct Registry {
+    enabled: bool,
+    next_id: u32,
+    entries: Vec<(u32, &'static str, WeakStore)>,
+}
+
+thread_local! {
+    static REGISTRY: RefCell<Registry> = RefCell::new(Registry::default());
+}
+
+/// Track `store` if the registry is enabled, returning its id.
+pub(crate) fn register(store: &SharedStore) -> Option<u32> {
+    REGISTRY.with(|registry| {
+        let mut registry = registry.borrow_mut();
+        if !registry.enabled {
+            return None;
+        }
+        registry.next_id += 1;
+        let id = registry.next_id;
+        let kind = store.borrow().kind();
+        registry.entries.push((id, kind, Rc::downgrade(store)));
+        Some(id)
+    })
+}
+
+/// One live structure as seen by [`registry_snapshot`].
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct RegistryEntry {
+    pub id: u32,
+    pub kind: String,
+    pub size: u32,
+    pub used_bytes: usize,
+    pub reserved_bytes: usize,
+    /// True if the structure was mid-operation (e.g. an async load that is
+    /// currently yielded) and couldn't be inspected; size and bytes are 0.
+    pub busy: bool,
+}
+
+/// Every live registered structure at one point in time.
+#[wasm_bindgen]
+#[derive(Clone, Debug, Default)]
+pub struct RegistrySnapshot {
+    entries: Vec<RegistryEntry>,
+}
+
+impl RegistrySnapshot {
+    pub fn entries_ref(&self) -> &[RegistryEntry] {
+        &self.entries
+    }
+}
+
+#[wasm_bindgen]
+impl RegistrySnapshot {
+    pub fn entries(&self) -> Vec<RegistryEntry> {
+        self.entries.clone()
+    }
+
+    /// Number of live registered structures.
+    pub fn count(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Number of live structures of one kind, e.g. `"rbtree"`.
+    pub fn count_of_kind(&self, kind: &str) -> usize {
+        self.entries.iter().filter(|e| e.kind == kind).count()
+    }
+
+    /// Keys held across all structures.
+    pub fn total_size(&self) -> u32 {
+        self.entries.iter().map(|e| e.size).sum()
+    }
+
+    pub fn total_used_bytes(&self) -> usize {
+        self.entries.iter().map(|e| e.used_bytes).sum()
+    }
+
+    pub fn total_reserved_bytes(&self) -> usize {
+        self.entries.iter().map(|e| e.reserved_bytes).sum()
+    }
+}
+
+/// Start (or stop) tracking structures created through `DynamicStore`.
+///
+/// Only structures created while the registry is enabled are tracked.
+/// Disabling forgets everything tracked so far.
+///
+/// # Example
+/// ```javascript
+/// set_registry_enabled(true);
+/// const users = new DynamicStore("rbtree", 0);
+/// const snapshot = registry_snapshot();
+/// console.log(snapshot.count(), snapshot.total_reserved_bytes());
+/// ```
+#[wasm_bindgen]
+pub fn set_registry_enabled(enabled: bool) {
+    REGISTRY.with(|registry| {
+        let mut registry = registry.borrow_mut();
+        registry.enabled = enabled;
+        if !enabled {
+            registry.entries.clear();
+        }
+    });
+}
+
+#[wasm_bindgen]
+pub fn is_registry_enabled() -> bool {
+    REGISTRY.with(|registry| registry.borrow().enabled)
+}
+
+/// Describe every registered structure that is still alive.
+///
+/// Structures freed since the last snapshot are pruned from the registry.
+#[wasm_bindgen]
+pub fn registry_snapshot() -> RegistrySnapshot {
+    REGISTRY.with(|registry| {
+        let mut registry = registry.borrow_mut();
+        registry
+            .entries
+            .retain(|(_, _, weak)| weak.strong_count() > 0);
+        let entries = registry
+            .entries
+            .iter()
+            .filter_map(|(id, kind, weak)| {
+                let store = weak.upgrade()?;
+                let entry = match store.try_borrow() {
+                    Ok(store) => {
+                        let memory = store.memory_report();
+                        RegistryEntry {
+                            id: *id,
+                            kind: kind.to_string(),
+                            size: store.kv_len() as u32,
+                            used_bytes: memory.used_bytes,
+                            reserved_bytes: memory.reserved_bytes,
+                            busy: false,
+                        }
+                    }
+                    Err(_) => RegistryEntry {
+                        id: *id,
+                        kind: kind.to_string(),
+                        size: 0,
+                        used_bytes: 0,
+                        reserved_bytes: 0,
+                        busy: true,
+                    },
+                };
+                Some(entry)
+            })
+            .collect();
+        RegistrySnapshot { entries }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kv_store::DynamicStore;
+
+    // The registry is thread-local and the test harness runs each test on
+    // its own thread, so tests don't see each other's structures.
+
+    #[test]
+    fn test_disabled_by_default() {
+        assert!(!is_registry_enabled());
+        let store = DynamicStore::try_new("bst", 0).unwrap();
+        assert_eq!(store.registry_id(), None);
+        assert_eq!(registry_snapshot().count(), 0);
+    }
+
+    #[test]
+    fn test_tracks_live_structures() {
+        set_registry_enabled(true);
+        let mut users = DynamicStore::try_new("rbtree", 0).unwrap();
+        let mut tags = DynamicStore::try_new("trie", 0).unwrap();
+        for i in 0..10 {
+            users.insert(format!("user{}", i), i);
+        }
+        tags.insert("rust".to_string(), 1);
+
+        let snapshot = registry_snapshot();
+        assert_eq!(snapshot.count(), 2);
+        assert_eq!(snapshot.count_of_kind("rbtree"), 1);
+        assert_eq!(snapshot.total_size(), 11);
+        assert!(snapshot.total_reserved_bytes() >= snapshot.total_used_bytes());
+        assert_eq!(snapshot.entries_ref()[0].id, users.registry_id().unwrap());
+
+        drop(users);
+        let snapshot = registry_snapshot();
+        assert_eq!(snapshot.count(), 1);
+        assert_eq!(snapshot.entries_ref()[0].kind, "trie");
+
+        set_registry_enabled(false);
+        assert_eq!(registry_snapshot().count(), 0);
+    }
+
+    #[test]
+    fn test_busy_structure_is_reported() {
+        set_registry_enabled(true);
+        let mut store = DynamicStore::try_new("hashmap", 0).unwrap();
+        let _guard = store.store_mut();
+        let snapshot = registry_snapshot();
+        assert!(snapshot.entries_ref()[0].busy);
+        assert_eq!(snapshot.entries_ref()[0].kind, "hashmap");
+    }
+}
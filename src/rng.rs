@@ -0,0 +1,235 @@
+//! Seedable random number generators shared by every randomized structure
+//! and workload generator in the crate.
+//!
+//! The generators are small, fast, non-cryptographic and fully
+//! deterministic given a seed, so any randomized run (skip list shapes,
+//! benchmark workloads, samples) can be reproduced from the seed it reports.
+//! Both implement [`rand::RngCore`], so the usual [`rand::Rng`] helpers such
+//! as `gen_range` work on them.
+//!
+//! Unseeded generators draw their seed from [`entropy_seed`], which uses
+//! `Math.random()` in the browser instead of going through `getrandom`.
+
+use rand::{Error, RngCore};
+use std::cell::Cell;
+
+/// The generator structures use unless told otherwise.
+pub type DefaultRng = Xoshiro256;
+
+/// Expand a 64-bit seed into generator state (SplitMix64).
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+thread_local! {
+    // Distinguishes seeds requested within the same clock tick
+    static SEED_COUNTER: Cell<u64> = const { Cell::new(0) };
+}
+
+/// A fresh seed from the environment, for generators nobody seeded.
+///
+/// Mixes the clock (plus `Math.random()` under wasm) with a per-thread
+/// counter, so two generators created back to back still differ.
+pub fn entropy_seed() -> u64 {
+    let count = SEED_COUNTER.with(|c| {
+        c.set(c.get() + 1);
+        c.get()
+    });
+    let mut state = environment_noise() ^ count.rotate_left(32);
+    splitmix64(&mut state)
+}
+
+#[cfg(target_arch = "wasm32")]
+fn environment_noise() -> u64 {
+    let random = (js_sys::Math::random() * (1u64 << 53) as f64) as u64;
+    random ^ (js_sys::Date::now() as u64).rotate_left(17)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn environment_noise() -> u64 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos() as u64)
+}
+
+/// Fill `dest` eight bytes at a time, little endian.
+fn fill_from_u64(rng: &mut impl RngCore, dest: &mut [u8]) {
+    for chunk in dest.chunks_mut(8) {
+        let bytes = rng.next_u64().to_le_bytes();
+        chunk.copy_from_slice(&bytes[..chunk.len()]);
+    }
+}
+
+/// xoshiro256** by Blackman and Vigna: 256 bits of state, period 2^256 - 1.
+#[derive(Clone, Debug)]
+pub struct Xoshiro256 {
+    state: [u64; 4],
+    seed: u64,
+}
+
+impl Xoshiro256 {
+    pub fn seed_from(seed: u64) -> Xoshiro256 {
+        let mut sm = seed;
+        let state = [
+            splitmix64(&mut sm),
+            splitmix64(&mut sm),
+            splitmix64(&mut sm),
+            splitmix64(&mut sm),
+        ];
+        Xoshiro256 { state, seed }
+    }
+
+    /// Seeded from [`entropy_seed`].
+    pub fn from_entropy() -> Xoshiro256 {
+        Self::seed_from(entropy_seed())
+    }
+
+    /// The seed this generator started from.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+}
+
+impl RngCore for Xoshiro256 {
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let s = &mut self.state;
+        let result = s[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = s[1] << 17;
+        s[2] ^= s[0];
+        s[3] ^= s[1];
+        s[1] ^= s[2];
+        s[0] ^= s[3];
+        s[2] ^= t;
+        s[3] = s[3].rotate_left(45);
+        result
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        fill_from_u64(self, dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+/// PCG32 (XSH RR) by O'Neill: 64 bits of state, 32-bit output. Smaller
+/// and slower than xoshiro; handy when state size matters.
+#[derive(Clone, Debug)]
+pub struct Pcg32 {
+    state: u64,
+    increment: u64,
+    seed: u64,
+}
+
+impl Pcg32 {
+    const MULTIPLIER: u64 = 6_364_136_223_846_793_005;
+
+    pub fn seed_from(seed: u64) -> Pcg32 {
+        let mut sm = seed;
+        let mut rng = Pcg32 {
+            state: 0,
+            // The increment must be odd
+            increment: splitmix64(&mut sm) | 1,
+            seed,
+        };
+        rng.state = splitmix64(&mut sm).wrapping_add(rng.increment);
+        rng.next_u32();
+        rng
+    }
+
+    /// Seeded from [`entropy_seed`].
+    pub fn from_entropy() -> Pcg32 {
+        Self::seed_from(entropy_seed())
+    }
+
+    /// The seed this generator started from.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+}
+
+impl RngCore for Pcg32 {
+    fn next_u32(&mut self) -> u32 {
+        let old = self.state;
+        self.state = old
+            .wrapping_mul(Self::MULTIPLIER)
+            .wrapping_add(self.increment);
+        let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+        xorshifted.rotate_right((old >> 59) as u32)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        u64::from(self.next_u32()) | (u64::from(self.next_u32()) << 32)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        fill_from_u64(self, dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    #[test]
+    fn test_same_seed_same_stream() {
+        let mut a = Xoshiro256::seed_from(42);
+        let mut b = Xoshiro256::seed_from(42);
+        let mut c = Xoshiro256::seed_from(43);
+        let xs: Vec<u64> = (0..8).map(|_| a.next_u64()).collect();
+        let ys: Vec<u64> = (0..8).map(|_| b.next_u64()).collect();
+        let zs: Vec<u64> = (0..8).map(|_| c.next_u64()).collect();
+        assert_eq!(xs, ys);
+        assert_ne!(xs, zs);
+        assert_eq!(a.seed(), 42);
+
+        let mut p = Pcg32::seed_from(42);
+        let mut q = Pcg32::seed_from(42);
+        assert_eq!(p.next_u64(), q.next_u64());
+        assert_eq!(p.seed(), 42);
+    }
+
+    #[test]
+    fn test_outputs_look_uniform() {
+        for mut rng in [
+            Box::new(Xoshiro256::seed_from(1)) as Box<dyn RngCore>,
+            Box::new(Pcg32::seed_from(1)),
+        ] {
+            let mut buckets = [0u32; 10];
+            for _ in 0..10_000 {
+                buckets[rng.gen_range(0..10)] += 1;
+            }
+            assert!(
+                buckets.iter().all(|&n| (800..1_200).contains(&n)),
+                "{:?}",
+                buckets
+            );
+        }
+    }
+
+    #[test]
+    fn test_entropy_seeds_differ() {
+        assert_ne!(entropy_seed(), entropy_seed());
+        assert_ne!(
+            Xoshiro256::from_entropy().seed(),
+            Xoshiro256::from_entropy().seed()
+        );
+    }
+}
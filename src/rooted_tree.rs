@@ -0,0 +1,311 @@
+use wasm_bindgen::prelude::*;
+
+/// Rooted tree over nodes `0..node_count` with weighted edges, answering
+/// lowest-common-ancestor queries in O(1) after an O(n log n) build.
+///
+/// The build walks an Euler tour: the node sequence of a DFS, written down
+/// on entering each node and again on returning to it from each child. The
+/// LCA of `a` and `b` is the shallowest node between their first visits on
+/// the tour, found with a sparse table of range minima over depth.
+///
+/// The index is rebuilt lazily on the first query after any change. Edges
+/// that would close a cycle are never followed, and nodes not connected to
+/// the root have no ancestors, so queries about them return `undefined`.
+///
+/// # Example
+/// ```javascript
+/// const tree = new RootedTree(5, 0);
+/// tree.add_edge(0, 1, 1); tree.add_edge(0, 2, 1);
+/// tree.add_edge(1, 3, 2); tree.add_edge(1, 4, 5);
+/// tree.lca(3, 4);               // 1
+/// tree.distance(3, 2);          // 3 edges
+/// tree.weighted_distance(3, 4); // 7
+/// ```
+#[wasm_bindgen]
+#[derive(Clone, Debug, Default)]
+pub struct RootedTree {
+    /// node -> (neighbour, weight)
+    adjacency: Vec<Vec<(usize, f64)>>,
+    root: usize,
+    index: Option<EulerIndex>,
+    metrics: LcaMetrics,
+}
+
+/// Work done by a [`RootedTree`]
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct LcaMetrics {
+    /// Index rebuilds after changes
+    pub builds: u32,
+    pub queries: u32,
+    pub euler_tour_length: u32,
+    /// Entries across all sparse-table levels
+    pub sparse_table_entries: u32,
+}
+
+/// Everything derived from one DFS of the tree.
+#[derive(Clone, Debug)]
+struct EulerIndex {
+    parent: Vec<Option<usize>>,
+    depth: Vec<u32>,
+    /// Sum of edge weights from the root
+    root_distance: Vec<f64>,
+    tour: Vec<usize>,
+    /// node -> index of its first appearance on the tour
+    first: Vec<Option<usize>>,
+    /// sparse[k][i]: shallowest node of tour[i..i + 2^k]
+    sparse: Vec<Vec<usize>>,
+}
+
+impl EulerIndex {
+    fn build(adjacency: &[Vec<(usize, f64)>], root: usize) -> EulerIndex {
+        let n = adjacency.len();
+        let mut index = EulerIndex {
+            parent: vec![None; n],
+            depth: vec![0; n],
+            root_distance: vec![0.0; n],
+            tour: Vec::with_capacity(2 * n),
+            first: vec![None; n],
+            sparse: Vec::new(),
+        };
+        if root >= n {
+            return index;
+        }
+        index.first[root] = Some(0);
+        index.tour.push(root);
+        // (node, next adjacency index), iterative so deep trees are fine
+        let mut frames = vec![(root, 0)];
+        while let Some(frame) = frames.last_mut() {
+            let (v, i) = *frame;
+            match adjacency[v].get(i) {
+                Some(&(w, weight)) => {
+                    frame.1 += 1;
+                    if index.first[w].is_some() {
+                        continue;
+                    }
+                    index.parent[w] = Some(v);
+                    index.depth[w] = index.depth[v] + 1;
+                    index.root_distance[w] = index.root_distance[v] + weight;
+                    index.first[w] = Some(index.tour.len());
+                    index.tour.push(w);
+                    frames.push((w, 0));
+                }
+                None => {
+                    frames.pop();
+                    if let Some(&(parent, _)) = frames.last() {
+                        index.tour.push(parent);
+                    }
+                }
+            }
+        }
+
+        let depth = &index.depth;
+        let shallower = |a: usize, b: usize| if depth[a] <= depth[b] { a } else { b };
+        let mut sparse = vec![index.tour.clone()];
+        let mut width = 1;
+        while 2 * width <= index.tour.len() {
+            let previous = sparse.last().expect("level 0 exists");
+            let level = (0..=index.tour.len() - 2 * width)
+                .map(|i| shallower(previous[i], previous[i + width]))
+                .collect();
+            sparse.push(level);
+            width *= 2;
+        }
+        index.sparse = sparse;
+        index
+    }
+
+    fn lca(&self, a: usize, b: usize) -> Option<usize> {
+        let (mut lo, mut hi) = (*self.first.get(a)?.as_ref()?, *self.first.get(b)?.as_ref()?);
+        if lo > hi {
+            std::mem::swap(&mut lo, &mut hi);
+        }
+        let level = (hi - lo + 1).ilog2() as usize;
+        let (x, y) = (
+            self.sparse[level][lo],
+            self.sparse[level][hi + 1 - (1 << level)],
+        );
+        Some(if self.depth[x] <= self.depth[y] { x } else { y })
+    }
+}
+
+impl RootedTree {
+    /// The index for the current edges, rebuilding it if stale.
+    fn index(&mut self) -> &EulerIndex {
+        if self.index.is_none() {
+            let index = EulerIndex::build(&self.adjacency, self.root);
+            self.metrics.builds += 1;
+            self.metrics.euler_tour_length = index.tour.len() as u32;
+            self.metrics.sparse_table_entries =
+                index.sparse.iter().map(|level| level.len() as u32).sum();
+            self.index = Some(index);
+        }
+        self.index.as_ref().expect("just built")
+    }
+
+    /// node -> (neighbour, edge weight), each edge listed at both ends.
+    pub(crate) fn adjacency_ref(&self) -> &[Vec<(usize, f64)>] {
+        &self.adjacency
+    }
+
+    /// Counted LCA lookup shared by the query methods.
+    fn query(&mut self, a: usize, b: usize) -> Option<usize> {
+        self.metrics.queries += 1;
+        self.index().lca(a, b)
+    }
+}
+
+#[wasm_bindgen]
+impl RootedTree {
+    /// A tree of `node_count` unconnected nodes rooted at `root`.
+    #[wasm_bindgen(constructor)]
+    pub fn new(node_count: u32, root: u32) -> RootedTree {
+        RootedTree {
+            adjacency: vec![Vec::new(); node_count as usize],
+            root: root as usize,
+            ..RootedTree::default()
+        }
+    }
+
+    /// Connect `a` and `b` with an edge of `weight`, adding nodes as needed.
+    pub fn add_edge(&mut self, a: u32, b: u32, weight: f64) {
+        let (a, b) = (a as usize, b as usize);
+        if a.max(b) >= self.adjacency.len() {
+            self.adjacency.resize(a.max(b) + 1, Vec::new());
+        }
+        self.adjacency[a].push((b, weight));
+        self.adjacency[b].push((a, weight));
+        self.index = None;
+    }
+
+    pub fn set_root(&mut self, root: u32) {
+        self.root = root as usize;
+        self.index = None;
+    }
+
+    pub fn root(&self) -> u32 {
+        self.root as u32
+    }
+
+    pub fn node_count(&self) -> u32 {
+        self.adjacency.len() as u32
+    }
+
+    /// Lowest common ancestor of `a` and `b`.
+    pub fn lca(&mut self, a: u32, b: u32) -> Option<u32> {
+        self.query(a as usize, b as usize).map(|lca| lca as u32)
+    }
+
+    /// Number of edges on the path between `a` and `b`.
+    pub fn distance(&mut self, a: u32, b: u32) -> Option<u32> {
+        let (a, b) = (a as usize, b as usize);
+        let lca = self.query(a, b)?;
+        let depth = &self.index().depth;
+        Some(depth[a] + depth[b] - 2 * depth[lca])
+    }
+
+    /// Sum of edge weights on the path between `a` and `b`.
+    pub fn weighted_distance(&mut self, a: u32, b: u32) -> Option<f64> {
+        let (a, b) = (a as usize, b as usize);
+        let lca = self.query(a, b)?;
+        let dist = &self.index().root_distance;
+        Some(dist[a] + dist[b] - 2.0 * dist[lca])
+    }
+
+    /// Edges between `node` and the root.
+    pub fn depth(&mut self, node: u32) -> Option<u32> {
+        let index = self.index();
+        index.first.get(node as usize)?.as_ref()?;
+        Some(index.depth[node as usize])
+    }
+
+    pub fn parent(&mut self, node: u32) -> Option<u32> {
+        let index = self.index();
+        index.parent.get(node as usize)?.map(|p| p as u32)
+    }
+
+    /// The Euler tour the index is built from.
+    pub fn euler_tour(&mut self) -> Vec<u32> {
+        self.index().tour.iter().map(|&v| v as u32).collect()
+    }
+
+    pub fn get_metrics(&self) -> LcaMetrics {
+        self.metrics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> RootedTree {
+        //         0
+        //       /   \
+        //      1     2
+        //     / \     \
+        //    3   4     5
+        //              |
+        //              6
+        let mut tree = RootedTree::new(7, 0);
+        for (a, b, w) in [
+            (0, 1, 1.0),
+            (0, 2, 2.0),
+            (1, 3, 3.0),
+            (1, 4, 4.0),
+            (2, 5, 5.0),
+            (5, 6, 6.0),
+        ] {
+            tree.add_edge(a, b, w);
+        }
+        tree
+    }
+
+    #[test]
+    fn test_lca_and_distance() {
+        let mut tree = sample();
+        assert_eq!(tree.lca(3, 4), Some(1));
+        assert_eq!(tree.lca(3, 6), Some(0));
+        assert_eq!(tree.lca(5, 6), Some(5));
+        assert_eq!(tree.lca(4, 4), Some(4));
+        assert_eq!(tree.distance(3, 6), Some(5));
+        assert_eq!(tree.weighted_distance(4, 6), Some(18.0));
+        assert_eq!(tree.depth(6), Some(3));
+        assert_eq!(tree.euler_tour().len(), 13);
+        assert_eq!(tree.lca(3, 99), None);
+
+        tree.set_root(5);
+        assert_eq!(tree.lca(3, 6), Some(5));
+        assert_eq!(tree.lca(3, 4), Some(1));
+        let metrics = tree.get_metrics();
+        assert_eq!(metrics.builds, 2);
+        assert_eq!(metrics.queries, 9);
+    }
+
+    #[test]
+    fn test_lca_matches_naive_on_deep_tree() {
+        // A long path with branches: naive parent-walking for reference
+        let n = 5_000;
+        let mut tree = RootedTree::new(n, 0);
+        for v in 1..n {
+            let parent = if v % 3 == 0 { v / 2 } else { v - 1 };
+            tree.add_edge(parent, v, 1.0);
+        }
+        let naive = |tree: &mut RootedTree, mut a: u32, mut b: u32| {
+            while tree.depth(a) > tree.depth(b) {
+                a = tree.parent(a).unwrap();
+            }
+            while tree.depth(b) > tree.depth(a) {
+                b = tree.parent(b).unwrap();
+            }
+            while a != b {
+                a = tree.parent(a).unwrap();
+                b = tree.parent(b).unwrap();
+            }
+            a
+        };
+        for (a, b) in [(4_999, 3_001), (1_234, 4_321), (2, 4_998), (2_500, 2_501)] {
+            let expected = naive(&mut tree, a, b);
+            assert_eq!(tree.lca(a, b), Some(expected));
+        }
+    }
+}
@@ -0,0 +1,330 @@
+use crate::rng::DefaultRng;
+use crate::{HashFunction, BUCKET_COUNT};
+use rand::Rng;
+use std::collections::HashSet;
+
+/// How scenario keys are shaped and how operations pick among them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyDistribution {
+    /// Zero-padded ascending keys (`key000000`, `key000001`, ...), accessed uniformly.
+    /// Worst case for an unbalanced BST.
+    Sequential,
+    /// Random lowercase pseudo-words, accessed uniformly.
+    Uniform,
+    /// Random session ids where access follows a Zipf law (a few keys are hot).
+    Zipfian,
+    /// Long URL-like paths sharing most of their prefix, accessed uniformly.
+    SharedPrefix,
+    /// Keys chosen so they all land in the same chained-HashMap bucket.
+    Adversarial,
+}
+
+/// Percentage split of the measured phase between inserts, gets and deletes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OpMix {
+    pub insert_pct: u32,
+    pub get_pct: u32,
+    pub delete_pct: u32,
+}
+
+/// A single replayable operation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Operation {
+    Insert(String, u32),
+    Get(String),
+    Delete(String),
+}
+
+/// A named, versioned benchmark workload.
+///
+/// Every scenario is fully determined by its fields (including the seed), so two
+/// users running the same name and version against the same structure replay the
+/// exact same operation stream. Bump `version` whenever any field changes.
+#[derive(Clone, Debug)]
+pub struct Scenario {
+    pub name: &'static str,
+    pub version: u32,
+    pub description: &'static str,
+    /// Number of distinct keys inserted during the load phase.
+    pub dataset_size: usize,
+    /// Number of operations in the measured phase.
+    pub operation_count: usize,
+    pub distribution: KeyDistribution,
+    pub op_mix: OpMix,
+    pub seed: u64,
+}
+
+/// The bundled scenario library.
+pub const SCENARIOS: &[Scenario] = &[
+    Scenario {
+        name: "dictionary-load",
+        version: 1,
+        description: "Load 10k random words, then perform read-only lookups",
+        dataset_size: 10_000,
+        operation_count: 20_000,
+        distribution: KeyDistribution::Uniform,
+        op_mix: OpMix {
+            insert_pct: 0,
+            get_pct: 100,
+            delete_pct: 0,
+        },
+        seed: 0xD1C7,
+    },
+    Scenario {
+        name: "session-cache-churn",
+        version: 1,
+        description: "2k session ids with Zipf-skewed reads, writes and expirations",
+        dataset_size: 2_000,
+        operation_count: 20_000,
+        distribution: KeyDistribution::Zipfian,
+        op_mix: OpMix {
+            insert_pct: 30,
+            get_pct: 50,
+            delete_pct: 20,
+        },
+        seed: 0x5E55,
+    },
+    Scenario {
+        name: "sorted-ingest",
+        version: 1,
+        description: "Ingest 5k keys in ascending order, then look them up",
+        dataset_size: 5_000,
+        operation_count: 5_000,
+        distribution: KeyDistribution::Sequential,
+        op_mix: OpMix {
+            insert_pct: 0,
+            get_pct: 100,
+            delete_pct: 0,
+        },
+        seed: 0x5027,
+    },
+    Scenario {
+        name: "url-paths",
+        version: 1,
+        description: "5k URL paths sharing long prefixes, mixed reads and updates",
+        dataset_size: 5_000,
+        operation_count: 10_000,
+        distribution: KeyDistribution::SharedPrefix,
+        op_mix: OpMix {
+            insert_pct: 20,
+            get_pct: 80,
+            delete_pct: 0,
+        },
+        seed: 0x0421,
+    },
+    Scenario {
+        name: "adversarial-collisions",
+        version: 1,
+        description: "1k keys that all hash to one HashMap bucket, then lookups",
+        dataset_size: 1_000,
+        operation_count: 2_000,
+        distribution: KeyDistribution::Adversarial,
+        op_mix: OpMix {
+            insert_pct: 0,
+            get_pct: 100,
+            delete_pct: 0,
+        },
+        seed: 0xBAD,
+    },
+];
+
+/// Zipf exponent used for skewed access (classic web-cache value).
+const ZIPF_EXPONENT: f64 = 1.0;
+
+impl Scenario {
+    /// Look up a bundled scenario by name.
+    pub fn by_name(name: &str) -> Option<&'static Scenario> {
+        SCENARIOS.iter().find(|s| s.name == name)
+    }
+
+    /// Names of all bundled scenarios.
+    pub fn names() -> Vec<&'static str> {
+        SCENARIOS.iter().map(|s| s.name).collect()
+    }
+
+    /// Generate the distinct keys for the load phase, in insertion order.
+    pub fn generate_keys(&self) -> Vec<String> {
+        let mut rng = DefaultRng::seed_from(self.seed);
+        match self.distribution {
+            KeyDistribution::Sequential => (0..self.dataset_size)
+                .map(|i| format!("key{:06}", i))
+                .collect(),
+            KeyDistribution::Uniform => unique_keys(self.dataset_size, || {
+                let len = rng.gen_range(4..=10);
+                (0..len)
+                    .map(|_| rng.gen_range(b'a'..=b'z') as char)
+                    .collect()
+            }),
+            KeyDistribution::Zipfian => unique_keys(self.dataset_size, || {
+                format!("session:{:08x}", rng.gen::<u32>())
+            }),
+            KeyDistribution::SharedPrefix => unique_keys(self.dataset_size, || {
+                format!(
+                    "https://example.com/api/v1/projects/{}/files/{}",
+                    rng.gen_range(0..50),
+                    rng.gen_range(0..100_000)
+                )
+            }),
+            KeyDistribution::Adversarial => {
+                // Keep only candidates that share bucket 0 in a default-sized chained HashMap.
+                (0u64..)
+                    .map(|i| format!("collide{}", i))
+                    .filter(|k| {
+                        (HashFunction::SipHash.hash(k) as usize).is_multiple_of(BUCKET_COUNT)
+                    })
+                    .take(self.dataset_size)
+                    .collect()
+            }
+        }
+    }
+
+    /// Generate the full operation stream: the load phase followed by the
+    /// measured phase. The first `dataset_size` operations are the load inserts.
+    pub fn generate_operations(&self) -> Vec<Operation> {
+        let keys = self.generate_keys();
+        let mut ops: Vec<Operation> = keys
+            .iter()
+            .enumerate()
+            .map(|(i, k)| Operation::Insert(k.clone(), i as u32))
+            .collect();
+
+        // Separate stream so the measured phase doesn't depend on key generation.
+        let mut rng = DefaultRng::seed_from(self.seed.wrapping_add(1));
+        let zipf = match self.distribution {
+            KeyDistribution::Zipfian => Some(ZipfSampler::new(keys.len(), ZIPF_EXPONENT)),
+            _ => None,
+        };
+
+        for i in 0..self.operation_count {
+            let key_index = match &zipf {
+                Some(sampler) => sampler.sample(&mut rng),
+                None => rng.gen_range(0..keys.len()),
+            };
+            let key = keys[key_index].clone();
+            let roll = rng.gen_range(0..100);
+            let op = if roll < self.op_mix.insert_pct {
+                Operation::Insert(key, (self.dataset_size + i) as u32)
+            } else if roll < self.op_mix.insert_pct + self.op_mix.get_pct {
+                Operation::Get(key)
+            } else {
+                Operation::Delete(key)
+            };
+            ops.push(op);
+        }
+        ops
+    }
+}
+
+/// Draw `count` distinct keys from a generator.
+fn unique_keys(count: usize, mut next: impl FnMut() -> String) -> Vec<String> {
+    let mut seen = HashSet::with_capacity(count);
+    let mut keys = Vec::with_capacity(count);
+    while keys.len() < count {
+        let key = next();
+        if seen.insert(key.clone()) {
+            keys.push(key);
+        }
+    }
+    keys
+}
+
+/// Samples ranks `0..n` with probability proportional to `1 / (rank + 1)^s`.
+pub(crate) struct ZipfSampler {
+    cumulative: Vec<f64>,
+}
+
+impl ZipfSampler {
+    pub(crate) fn new(n: usize, exponent: f64) -> Self {
+        let mut total = 0.0;
+        let cumulative = (0..n)
+            .map(|rank| {
+                total += 1.0 / ((rank + 1) as f64).powf(exponent);
+                total
+            })
+            .collect();
+        ZipfSampler { cumulative }
+    }
+
+    pub(crate) fn sample(&self, rng: &mut impl Rng) -> usize {
+        let total = *self.cumulative.last().unwrap_or(&0.0);
+        let target = rng.gen::<f64>() * total;
+        self.cumulative
+            .partition_point(|&c| c < target)
+            .min(self.cumulative.len() - 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::HashMap;
+
+    #[test]
+    fn test_names_are_unique_and_resolvable() {
+        let names = Scenario::names();
+        let unique: HashSet<_> = names.iter().collect();
+        assert_eq!(unique.len(), names.len());
+        for name in names {
+            assert_eq!(Scenario::by_name(name).unwrap().name, name);
+        }
+        assert!(Scenario::by_name("missing").is_none());
+    }
+
+    #[test]
+    fn test_operations_are_deterministic() {
+        let scenario = Scenario::by_name("session-cache-churn").unwrap();
+        assert_eq!(
+            scenario.generate_operations(),
+            scenario.generate_operations()
+        );
+    }
+
+    #[test]
+    fn test_operation_counts_and_mix() {
+        let scenario = Scenario::by_name("session-cache-churn").unwrap();
+        let ops = scenario.generate_operations();
+        assert_eq!(ops.len(), scenario.dataset_size + scenario.operation_count);
+
+        let measured = &ops[scenario.dataset_size..];
+        let deletes = measured
+            .iter()
+            .filter(|op| matches!(op, Operation::Delete(_)))
+            .count();
+        // 20% target with generous tolerance
+        assert!(deletes > 3_000 && deletes < 5_000, "deletes = {}", deletes);
+    }
+
+    #[test]
+    fn test_keys_are_distinct() {
+        for scenario in SCENARIOS {
+            let keys = scenario.generate_keys();
+            let unique: HashSet<_> = keys.iter().collect();
+            assert_eq!(keys.len(), scenario.dataset_size, "{}", scenario.name);
+            assert_eq!(unique.len(), keys.len(), "{}", scenario.name);
+        }
+    }
+
+    #[test]
+    fn test_adversarial_keys_share_a_bucket() {
+        let scenario = Scenario::by_name("adversarial-collisions").unwrap();
+        let mut map = HashMap::new();
+        for key in scenario.generate_keys() {
+            map.insert(key, 0);
+        }
+        assert_eq!(
+            map.get_metrics().max_chain_length as usize,
+            scenario.dataset_size
+        );
+    }
+
+    #[test]
+    fn test_zipf_favours_low_ranks() {
+        let sampler = ZipfSampler::new(1_000, 1.0);
+        let mut rng = DefaultRng::seed_from(7);
+        let hot = (0..10_000)
+            .filter(|_| sampler.sample(&mut rng) < 10)
+            .count();
+        // The top 1% of keys should receive roughly 39% of accesses
+        assert!(hot > 3_000, "hot = {}", hot);
+    }
+}
@@ -0,0 +1,155 @@
+use wasm_bindgen::prelude::*;
+
+/// Array of numbers with O(log n) point updates and range sum / maximum
+/// queries.
+///
+/// Stored bottom-up in a flat array of 2n nodes: leaves at `n..2n`, and
+/// node `i` summarizing its children `2i` and `2i + 1`. Ranges are
+/// half-open, `start..end`.
+///
+/// # Example
+/// ```javascript
+/// const st = new SegmentTree([5, 1, 4, 2]);
+/// st.range_sum(1, 4); // 7
+/// st.update(2, 10);
+/// st.range_max(0, 3); // 10
+/// ```
+#[wasm_bindgen]
+#[derive(Clone, Debug, Default)]
+pub struct SegmentTree {
+    /// (sum, max) per node
+    nodes: Vec<(f64, f64)>,
+    len: usize,
+    metrics: SegmentTreeMetrics,
+}
+
+/// Work done by a [`SegmentTree`]
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SegmentTreeMetrics {
+    pub updates: u32,
+    pub queries: u32,
+    /// Tree nodes read or rewritten by those operations
+    pub nodes_touched: u32,
+}
+
+fn combine(a: (f64, f64), b: (f64, f64)) -> (f64, f64) {
+    (a.0 + b.0, a.1.max(b.1))
+}
+
+/// Summary of an empty range.
+const EMPTY: (f64, f64) = (0.0, f64::NEG_INFINITY);
+
+impl SegmentTree {
+    /// (sum, max) of `start..end`, clamped to the array.
+    pub fn range_summary(&mut self, start: usize, end: usize) -> (f64, f64) {
+        self.metrics.queries += 1;
+        let (mut lo, mut hi) = (start.min(self.len) + self.len, end.min(self.len) + self.len);
+        let mut acc = EMPTY;
+        while lo < hi {
+            if lo & 1 == 1 {
+                acc = combine(acc, self.nodes[lo]);
+                self.metrics.nodes_touched += 1;
+                lo += 1;
+            }
+            if hi & 1 == 1 {
+                hi -= 1;
+                acc = combine(acc, self.nodes[hi]);
+                self.metrics.nodes_touched += 1;
+            }
+            lo /= 2;
+            hi /= 2;
+        }
+        acc
+    }
+}
+
+#[wasm_bindgen]
+impl SegmentTree {
+    #[wasm_bindgen(constructor)]
+    pub fn new(values: Vec<f64>) -> SegmentTree {
+        let len = values.len();
+        let mut nodes = vec![EMPTY; 2 * len];
+        for (i, &v) in values.iter().enumerate() {
+            nodes[len + i] = (v, v);
+        }
+        for i in (1..len).rev() {
+            nodes[i] = combine(nodes[2 * i], nodes[2 * i + 1]);
+        }
+        SegmentTree {
+            nodes,
+            len,
+            metrics: SegmentTreeMetrics::default(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn get(&self, index: usize) -> Option<f64> {
+        (index < self.len).then(|| self.nodes[self.len + index].0)
+    }
+
+    /// Set element `index`. Returns false if it is out of range.
+    pub fn update(&mut self, index: usize, value: f64) -> bool {
+        if index >= self.len {
+            return false;
+        }
+        self.metrics.updates += 1;
+        let mut i = self.len + index;
+        self.nodes[i] = (value, value);
+        while i > 1 {
+            i /= 2;
+            self.nodes[i] = combine(self.nodes[2 * i], self.nodes[2 * i + 1]);
+            self.metrics.nodes_touched += 1;
+        }
+        true
+    }
+
+    /// Sum of `start..end`; 0 for an empty range.
+    pub fn range_sum(&mut self, start: usize, end: usize) -> f64 {
+        self.range_summary(start, end).0
+    }
+
+    /// Maximum of `start..end`, or `undefined` for an empty range.
+    pub fn range_max(&mut self, start: usize, end: usize) -> Option<f64> {
+        let (_, max) = self.range_summary(start, end);
+        (start.min(self.len) < end.min(self.len)).then_some(max)
+    }
+
+    pub fn get_metrics(&self) -> SegmentTreeMetrics {
+        self.metrics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_range_queries_match_naive() {
+        let mut values: Vec<f64> = (0..37).map(|i| ((i * 17) % 23) as f64 - 5.0).collect();
+        let mut st = SegmentTree::new(values.clone());
+        for step in 0..50 {
+            let i = (step * 7) % values.len();
+            values[i] = step as f64 - 20.0;
+            assert!(st.update(i, values[i]));
+            for (start, end) in [(0, 37), (3, 4), (5, 30), (step % 37, 37)] {
+                let slice = &values[start..end];
+                assert_eq!(st.range_sum(start, end), slice.iter().sum::<f64>());
+                assert_eq!(
+                    st.range_max(start, end),
+                    slice.iter().copied().reduce(f64::max)
+                );
+            }
+        }
+        assert_eq!(st.range_max(10, 10), None);
+        assert!(!st.update(37, 1.0));
+        assert_eq!(st.get_metrics().updates, 50);
+    }
+}
@@ -0,0 +1,190 @@
+use crate::minhash::token_hash;
+use wasm_bindgen::prelude::*;
+
+/// Number of bits that differ between two fingerprints.
+#[wasm_bindgen]
+pub fn hamming_distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+/// Charikar's SimHash: a 64-bit fingerprint where similar token streams
+/// get fingerprints a few bits apart.
+///
+/// Every token's hash votes +weight on each bit it has set and -weight on
+/// each bit it doesn't; the fingerprint keeps the sign of each total. The
+/// expected fraction of differing bits is θ / π for the angle θ between the
+/// two weighted token vectors, so Hamming distance tracks cosine
+/// similarity. Where [`MinHash`](crate::MinHash) needs a signature of many
+/// values, SimHash fits in one `u64`.
+///
+/// # Example
+/// ```javascript
+/// const a = new SimHash(); a.add_tokens(docA.split(" "));
+/// const b = new SimHash(); b.add_tokens(docB.split(" "));
+/// if (a.hamming_distance(b) <= 3) console.log("near duplicate");
+/// ```
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct SimHash {
+    votes: [i64; 64],
+    tokens_added: u32,
+}
+
+impl Default for SimHash {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[wasm_bindgen]
+impl SimHash {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> SimHash {
+        SimHash {
+            votes: [0; 64],
+            tokens_added: 0,
+        }
+    }
+
+    pub fn add_token(&mut self, token: &str) {
+        self.add_weighted(token, 1);
+    }
+
+    /// Add a token counting `weight` times, e.g. its term frequency.
+    pub fn add_weighted(&mut self, token: &str, weight: i32) {
+        let hash = token_hash(token);
+        for (bit, vote) in self.votes.iter_mut().enumerate() {
+            if hash >> bit & 1 == 1 {
+                *vote += i64::from(weight);
+            } else {
+                *vote -= i64::from(weight);
+            }
+        }
+        self.tokens_added += 1;
+    }
+
+    pub fn add_tokens(&mut self, tokens: Vec<String>) {
+        for token in &tokens {
+            self.add_token(token);
+        }
+    }
+
+    /// Bit `i` is set when tokens with hash bit `i` set outweighed the rest.
+    pub fn fingerprint(&self) -> u64 {
+        self.votes
+            .iter()
+            .enumerate()
+            .filter(|(_, &vote)| vote > 0)
+            .fold(0, |fp, (bit, _)| fp | 1 << bit)
+    }
+
+    pub fn hamming_distance(&self, other: &SimHash) -> u32 {
+        hamming_distance(self.fingerprint(), other.fingerprint())
+    }
+
+    /// 1 minus the fraction of differing bits.
+    pub fn similarity(&self, other: &SimHash) -> f64 {
+        1.0 - self.hamming_distance(other) as f64 / 64.0
+    }
+
+    pub fn tokens_added(&self) -> u32 {
+        self.tokens_added
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tokens_added == 0
+    }
+}
+
+/// Group fingerprints whose Hamming distance is at most `max_distance`,
+/// transitively: if a~b and b~c then a, b and c share a group even when a
+/// and c are further apart.
+///
+/// Returns the input indices of each group with two or more members, each
+/// group in ascending order. Compares all pairs, so it suits collections of
+/// a few thousand fingerprints.
+pub fn near_duplicate_groups(fingerprints: &[u64], max_distance: u32) -> Vec<Vec<usize>> {
+    let mut parent: Vec<usize> = (0..fingerprints.len()).collect();
+    fn root(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+    for i in 0..fingerprints.len() {
+        for j in i + 1..fingerprints.len() {
+            if hamming_distance(fingerprints[i], fingerprints[j]) <= max_distance {
+                let (a, b) = (root(&mut parent, i), root(&mut parent, j));
+                parent[a.max(b)] = a.min(b);
+            }
+        }
+    }
+    let mut groups: Vec<Vec<usize>> = vec![Vec::new(); fingerprints.len()];
+    for i in 0..fingerprints.len() {
+        let r = root(&mut parent, i);
+        groups[r].push(i);
+    }
+    groups.retain(|g| g.len() > 1);
+    groups
+}
+
+/// Label each fingerprint with its near-duplicate group: fingerprints in
+/// the same group get the same label (the smallest index in the group).
+///
+/// # Example
+/// ```javascript
+/// const labels = group_near_duplicates(BigUint64Array.from(fps), 3);
+/// ```
+#[wasm_bindgen]
+pub fn group_near_duplicates(fingerprints: Vec<u64>, max_distance: u32) -> Vec<u32> {
+    let mut labels: Vec<u32> = (0..fingerprints.len() as u32).collect();
+    for group in near_duplicate_groups(&fingerprints, max_distance) {
+        for &i in &group {
+            labels[i] = group[0] as u32;
+        }
+    }
+    labels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn simhash_of(text: &str) -> SimHash {
+        let mut s = SimHash::new();
+        for word in text.split_whitespace() {
+            s.add_token(word);
+        }
+        s
+    }
+
+    #[test]
+    fn test_similar_texts_have_close_fingerprints() {
+        let base = "the quick brown fox jumps over the lazy dog while the cat \
+                    sleeps on the warm mat beside the old wooden door";
+        let edited = base.replace("lazy", "sleepy");
+        let other = "completely different words about databases and query \
+                     planners choosing indexes for range scans";
+        let a = simhash_of(base);
+        let b = simhash_of(&edited);
+        let c = simhash_of(other);
+        assert!(a.hamming_distance(&b) < a.hamming_distance(&c));
+        assert!(a.hamming_distance(&b) <= 16, "{}", a.hamming_distance(&b));
+        assert_eq!(a.hamming_distance(&simhash_of(base)), 0);
+        assert_eq!(hamming_distance(0b1011, 0b0110), 3);
+    }
+
+    #[test]
+    fn test_groups_near_duplicates() {
+        let fingerprints = [0b0000, 0b0001, 0b0011, u64::MAX, 0xF0F0, u64::MAX - 1];
+        assert_eq!(
+            near_duplicate_groups(&fingerprints, 1),
+            [vec![0, 1, 2], vec![3, 5]]
+        );
+        assert_eq!(
+            group_near_duplicates(fingerprints.to_vec(), 1),
+            [0, 0, 0, 3, 4, 3]
+        );
+    }
+}
@@ -1,10 +1,22 @@
+use crate::builders::MetricsMode;
+use crate::bulk::BulkInsertJob;
+use crate::csv;
+use crate::health::{HealthReport, Severity};
+use crate::interop;
+use crate::memory::MemoryReport;
+#[cfg(feature = "msgpack")]
+use crate::msgpack;
+use crate::rng::DefaultRng;
+use crate::BucketEntry;
 use rand::Rng;
 use std::cell::RefCell;
 use std::rc::Rc;
 use wasm_bindgen::prelude::*;
 
-const MAX_LEVEL: usize = 16;
-const LEVEL_PROBABILITY: f32 = 0.5;
+pub(crate) const MAX_LEVEL: usize = 16;
+pub(crate) const LEVEL_PROBABILITY: f32 = 0.5;
+/// Nodes drawn by `to_ascii` before the diagram is cut off.
+const ASCII_MAX_NODES: usize = 32;
 
 #[wasm_bindgen]
 #[derive(Clone, Debug)]
@@ -24,6 +36,9 @@ struct Node {
     value: u32,
     level: usize,
     forward: Vec<Option<NodePtr>>,
+    /// Bottom-level steps covered by each forward pointer; for a missing
+    /// pointer, the number of nodes after this one
+    span: Vec<u32>,
 }
 
 impl Node {
@@ -33,6 +48,7 @@ impl Node {
             value,
             level,
             forward: vec![None; level + 1],
+            span: vec![0; level + 1],
         }
     }
 }
@@ -43,13 +59,57 @@ pub struct SkipList {
     level: usize,
     size: u32,
     metrics: SkipListMetrics,
+    max_level: usize,
+    probability: f32,
+    rng: DefaultRng,
+    metrics_mode: MetricsMode,
+}
+
+impl Default for SkipList {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
-#[wasm_bindgen]
 impl SkipList {
-    #[wasm_bindgen(constructor)]
-    pub fn new() -> SkipList {
-        let head = Rc::new(RefCell::new(Node::new("".to_string(), 0, MAX_LEVEL)));
+    /// Node at 1-based `rank`.
+    fn node_at(&self, rank: u32) -> Option<NodePtr> {
+        if rank == 0 || rank > self.size {
+            return None;
+        }
+        let mut current = self.head.clone();
+        let mut position = 0;
+        for lv in (0..=self.level).rev() {
+            loop {
+                let (next, span) = {
+                    let node = current.borrow();
+                    (node.forward[lv].clone(), node.span[lv])
+                };
+                match next {
+                    Some(next_node) if position + span <= rank => {
+                        position += span;
+                        current = next_node;
+                    }
+                    _ => break,
+                }
+            }
+            if position == rank {
+                return Some(current);
+            }
+        }
+        None
+    }
+
+    /// Empty list with explicit level parameters (see [`SkipListBuilder`]).
+    ///
+    /// [`SkipListBuilder`]: crate::SkipListBuilder
+    pub(crate) fn with_options(
+        max_level: usize,
+        probability: f32,
+        seed: Option<u64>,
+        metrics_mode: MetricsMode,
+    ) -> SkipList {
+        let head = Rc::new(RefCell::new(Node::new("".to_string(), 0, max_level)));
 
         SkipList {
             head,
@@ -63,15 +123,41 @@ impl SkipList {
                 max_level: 0,
                 insertion_cost: 0,
             },
+            max_level,
+            probability,
+            rng: match seed {
+                Some(seed) => DefaultRng::seed_from(seed),
+                None => DefaultRng::from_entropy(),
+            },
+            metrics_mode,
+        }
+    }
+
+    /// Every entry, in key order.
+    pub(crate) fn pairs(&self) -> Vec<(String, u32)> {
+        let mut entries = Vec::with_capacity(self.size as usize);
+        let mut current = self.head.borrow().forward[0].clone();
+        while let Some(node) = current {
+            let n = node.borrow();
+            entries.push((n.key.clone(), n.value));
+            current = n.forward[0].clone();
         }
+        entries
+    }
+}
+
+#[wasm_bindgen]
+impl SkipList {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> SkipList {
+        SkipList::with_options(MAX_LEVEL, LEVEL_PROBABILITY, None, MetricsMode::Full)
     }
 
     /// Generate random level for new node
-    /// Returns level 0 with P=0.5, level 1 with P=0.25, etc.
-    fn random_level() -> usize {
-        let mut rng = rand::thread_rng();
+    /// Returns level 0 with P=0.5, level 1 with P=0.25, etc. (for the default probability)
+    fn random_level(&mut self) -> usize {
         let mut level = 0;
-        while level < MAX_LEVEL && rng.gen::<f32>() < LEVEL_PROBABILITY {
+        while level < self.max_level && self.rng.gen::<f32>() < self.probability {
             level += 1;
         }
         level
@@ -94,7 +180,7 @@ impl SkipList {
                     Some(next_node) => {
                         comparisons += 1;
                         let next_key = next_node.borrow().key.clone();
-                        if next_key.as_str() < &key {
+                        if next_key.as_str() < key {
                             current = next_node.clone();
                         } else {
                             break;
@@ -121,16 +207,23 @@ impl SkipList {
     /// If key exists, update the value
     pub fn insert(&mut self, key: String, value: u32) {
         let is_new = self.search(&key).is_none();
-        let new_level = Self::random_level();
+        let new_level = self.random_level();
 
-        // Expand list level if necessary
+        // Expand list level if necessary; the head's new levels skip
+        // every node
         if new_level > self.level {
+            let mut head = self.head.borrow_mut();
+            for lv in self.level + 1..=new_level {
+                head.span[lv] = self.size;
+            }
             self.level = new_level;
         }
 
-        // Find insertion points at each level
+        // Find insertion points at each level, and their positions
         let mut update: Vec<NodePtr> = Vec::with_capacity(self.level + 1);
+        let mut rank: Vec<u32> = Vec::with_capacity(self.level + 1);
         let mut current = self.head.clone();
+        let mut position = 0;
 
         for lv in (0..=self.level).rev() {
             loop {
@@ -139,7 +232,8 @@ impl SkipList {
                     None => break,
                     Some(next_node) => {
                         let next_key = next_node.borrow().key.clone();
-                        if next_key.as_str() < &key {
+                        if next_key < key {
+                            position += current.borrow().span[lv];
                             current = next_node.clone();
                         } else {
                             break;
@@ -148,10 +242,12 @@ impl SkipList {
                 }
             }
             update.push(current.clone());
+            rank.push(position);
         }
 
         // Reverse update array so indices match levels
         update.reverse();
+        rank.reverse();
 
         // Check if we need to update existing node
         if !is_new {
@@ -159,7 +255,7 @@ impl SkipList {
             let next_at_zero = update[0].borrow().forward[0].clone();
             if let Some(existing_node) = next_at_zero {
                 let existing_key = existing_node.borrow().key.clone();
-                if existing_key.as_str() == &key {
+                if existing_key == key {
                     existing_node.borrow_mut().value = value;
                     self.metrics.total_insertions += 1;
                     return;
@@ -170,11 +266,23 @@ impl SkipList {
         // Create new node
         let new_node = Rc::new(RefCell::new(Node::new(key.clone(), value, new_level)));
 
-        // Link node at each level
-        for lv in 0..=new_level.min(self.level) {
-            let next_at_lv = update[lv].borrow_mut().forward[lv].take();
-            new_node.borrow_mut().forward[lv] = next_at_lv;
-            update[lv].borrow_mut().forward[lv] = Some(new_node.clone());
+        // Link node at each level, splitting the span it lands in
+        for (lv, prev) in update
+            .iter()
+            .enumerate()
+            .take(new_level.min(self.level) + 1)
+        {
+            let mut prev = prev.borrow_mut();
+            let mut node = new_node.borrow_mut();
+            let steps_before = rank[0] - rank[lv];
+            node.forward[lv] = prev.forward[lv].take();
+            node.span[lv] = prev.span[lv] - steps_before;
+            prev.forward[lv] = Some(new_node.clone());
+            prev.span[lv] = steps_before + 1;
+        }
+        // Higher pointers now jump over one more node
+        for (lv, prev) in update.iter().enumerate().skip(new_level + 1) {
+            prev.borrow_mut().span[lv] += 1;
         }
 
         if is_new {
@@ -222,17 +330,20 @@ impl SkipList {
             if node_key.as_str() == key {
                 let deleted_value = node_to_delete.borrow().value;
 
-                // Remove node from all levels it appears in
-                for lv in 0..=self.level {
-                    let update_node = &update[lv];
+                // Remove node from all levels it appears in; pointers over
+                // it cover one node less
+                for (lv, update_node) in update.iter().enumerate() {
                     let next_at_lv = update_node.borrow().forward[lv].clone();
 
-                    if let Some(ref next_node) = next_at_lv {
-                        if next_node.borrow().key.as_str() == key {
+                    match next_at_lv {
+                        Some(ref next_node) if next_node.borrow().key.as_str() == key => {
                             // Link around the deleted node
-                            let deleted_forward = next_node.borrow_mut().forward[lv].take();
-                            update_node.borrow_mut().forward[lv] = deleted_forward;
+                            let mut next = next_node.borrow_mut();
+                            let mut prev = update_node.borrow_mut();
+                            prev.forward[lv] = next.forward[lv].take();
+                            prev.span[lv] = prev.span[lv] + next.span[lv] - 1;
                         }
+                        _ => update_node.borrow_mut().span[lv] -= 1,
                     }
                 }
 
@@ -250,6 +361,11 @@ impl SkipList {
     }
 
     fn update_metrics(&mut self) {
+        self.metrics.max_level = self.level as u32;
+        if self.metrics_mode == MetricsMode::Counters {
+            return;
+        }
+
         // Calculate average level by traversing bottom level
         let mut total_level = 0u32;
         let mut count = 0u32;
@@ -276,10 +392,115 @@ impl SkipList {
         self.metrics.max_level = self.level as u32;
     }
 
+    /// Entry at `index` in key order, found in O(log n) by following
+    /// the pointer spans.
+    pub fn get_by_index(&self, index: u32) -> Option<BucketEntry> {
+        let node = self.node_at(index.checked_add(1)?)?;
+        let node = node.borrow();
+        Some(BucketEntry {
+            key: node.key.clone(),
+            value: node.value,
+        })
+    }
+
+    /// Position of `key` in key order, if present.
+    pub fn index_of(&self, key: &str) -> Option<u32> {
+        let mut current = self.head.clone();
+        let mut position = 0;
+        for lv in (0..=self.level).rev() {
+            loop {
+                let next = current.borrow().forward[lv].clone();
+                match next {
+                    Some(next_node) if next_node.borrow().key.as_str() <= key => {
+                        position += current.borrow().span[lv];
+                        current = next_node;
+                    }
+                    _ => break,
+                }
+            }
+            if position > 0 && current.borrow().key == key {
+                return Some(position - 1);
+            }
+        }
+        None
+    }
+
+    /// Entries at positions `start..end` in key order (`end` clamped to
+    /// the length): O(log n) to find the first, then one step per entry.
+    pub fn range_by_index(&self, start: u32, end: u32) -> Vec<BucketEntry> {
+        let end = end.min(self.size);
+        if start >= end {
+            return Vec::new();
+        }
+        let mut entries = Vec::with_capacity((end - start) as usize);
+        let mut current = self.node_at(start + 1);
+        while let Some(node) = current {
+            if entries.len() == (end - start) as usize {
+                break;
+            }
+            let n = node.borrow();
+            entries.push(BucketEntry {
+                key: n.key.clone(),
+                value: n.value,
+            });
+            current = n.forward[0].clone();
+        }
+        entries
+    }
+
     pub fn get_metrics(&self) -> SkipListMetrics {
         self.metrics.clone()
     }
 
+    /// Check the node level distribution against the configured
+    /// probability and level cap (see [`HealthReport`]).
+    pub fn analyze(&self) -> HealthReport {
+        let mut report = HealthReport::default();
+        let mut promoted = 0u32;
+        let mut at_cap = 0u32;
+        let mut current = self.head.borrow().forward[0].clone();
+        while let Some(node) = current {
+            let n = node.borrow();
+            promoted += u32::from(n.level > 0);
+            at_cap += u32::from(n.level == self.max_level);
+            current = n.forward[0].clone();
+        }
+        if self.size >= 64 {
+            let fraction = promoted as f32 / self.size as f32;
+            if (fraction - self.probability).abs() > 0.15 {
+                report.add(
+                    Severity::Warning,
+                    "skewed_levels",
+                    format!(
+                        "{:.0}% of nodes are above level 0; {:.0}% expected",
+                        fraction * 100.0,
+                        self.probability * 100.0
+                    ),
+                    "level choices look biased; rebuild with a different seed",
+                );
+            }
+        }
+        if at_cap > 16 {
+            report.add(
+                Severity::Warning,
+                "max_level_too_low",
+                format!(
+                    "{} nodes share the top level {}, which searches scan linearly",
+                    at_cap, self.max_level
+                ),
+                "raise max_level with SkipListBuilder to about log2 of the key count",
+            );
+        }
+        report
+    }
+
+    /// Seed behind this list's level choices. Building another list with
+    /// the same seed and insert order reproduces its shape exactly, even
+    /// when this one was seeded from entropy.
+    pub fn seed(&self) -> u64 {
+        self.rng.seed()
+    }
+
     pub fn len(&self) -> u32 {
         self.size
     }
@@ -287,6 +508,137 @@ impl SkipList {
     pub fn is_empty(&self) -> bool {
         self.size == 0
     }
+
+    /// Build a list from a JS `Map` of string keys to integer values
+    /// (0 to 2^32 - 1). Throws on the first entry that doesn't fit.
+    pub fn from_js_map(map: &js_sys::Map) -> Result<SkipList, JsValue> {
+        interop::from_map(map, |_| SkipList::new())
+    }
+
+    /// Build a list from a plain object's own enumerable properties, as
+    /// `Object.entries` lists them. Values must be integers as for `from_js_map`.
+    pub fn from_object(object: &js_sys::Object) -> Result<SkipList, JsValue> {
+        interop::from_object(object, |_| SkipList::new())
+    }
+
+    /// The entries as a new JS `Map`, in key order.
+    pub fn to_js_map(&self) -> js_sys::Map {
+        interop::to_map(self)
+    }
+
+    /// The entries as a new plain object, one property per key.
+    pub fn to_object(&self) -> js_sys::Object {
+        interop::to_object(self)
+    }
+
+    /// Build a list from CSV text whose first row names the columns,
+    /// taking keys from `key_col` and integer values from `value_col`.
+    /// Quoted fields may contain commas, quotes (as `""`) and line breaks.
+    /// Throws with the line number of the first bad row.
+    pub fn from_csv(text: &str, key_col: &str, value_col: &str) -> Result<SkipList, JsValue> {
+        csv::from_csv(text, key_col, value_col, |_| SkipList::new())
+    }
+
+    /// The entries as CSV with a `key,value` header row, quoting keys
+    /// where needed.
+    pub fn to_csv(&self) -> String {
+        csv::to_csv(self)
+    }
+
+    /// Build a list from a MessagePack map of string keys to integers,
+    /// as JS encoders produce from a plain object. Requires the `msgpack`
+    /// feature.
+    #[cfg(feature = "msgpack")]
+    pub fn from_msgpack(bytes: &[u8]) -> Result<SkipList, JsValue> {
+        msgpack::from_msgpack(bytes, |_| SkipList::new())
+    }
+
+    /// The entries as a MessagePack map. Requires the `msgpack` feature.
+    #[cfg(feature = "msgpack")]
+    pub fn to_msgpack(&self) -> Vec<u8> {
+        msgpack::to_msgpack(self)
+    }
+
+    /// The express lanes as a diagram, top lane first, so the level
+    /// distribution and the shortcuts a search takes are visible. Only the
+    /// first 32 nodes are drawn.
+    ///
+    /// # Example
+    /// ```javascript
+    /// console.log(list.to_ascii());
+    /// // L1 head ------> b ------> NIL
+    /// // L0 head -> a -> b -> c -> NIL
+    /// ```
+    pub fn to_ascii(&self) -> String {
+        if self.size == 0 {
+            return "(empty)".to_string();
+        }
+        let mut nodes = Vec::new();
+        let mut current = self.head.borrow().forward[0].clone();
+        while let Some(node) = current {
+            if nodes.len() == ASCII_MAX_NODES {
+                break;
+            }
+            let n = node.borrow();
+            nodes.push((format!("-> {} ", n.key), n.level));
+            current = n.forward[0].clone();
+        }
+        let hidden = self.size as usize - nodes.len();
+        let mut out = String::new();
+        for lv in (0..=self.level).rev() {
+            out.push_str(&format!("L{} head ", lv));
+            for (column, level) in &nodes {
+                if *level >= lv {
+                    out.push_str(column);
+                } else {
+                    out.push_str(&"-".repeat(column.len()));
+                }
+            }
+            out.push_str(if hidden > 0 { "-> ...\n" } else { "-> NIL\n" });
+        }
+        if hidden > 0 {
+            out.push_str(&format!("({} more nodes)\n", hidden));
+        }
+        out.pop();
+        out
+    }
+
+    /// Insert queued entries from `job` until `millis` milliseconds have elapsed.
+    ///
+    /// Returns how many entries were inserted; call again (e.g. once per
+    /// animation frame) until `job.is_done()`.
+    pub fn insert_budgeted(&mut self, job: &mut BulkInsertJob, millis: f64) -> u32 {
+        job.run_for(self, millis)
+    }
+
+    /// Estimate heap usage: one reference-counted node per key (plus the
+    /// head), each with its forward-pointer and span towers and key string.
+    pub fn memory_report(&self) -> MemoryReport {
+        let mut report = MemoryReport::default();
+        let node_bytes = std::mem::size_of::<RefCell<Node>>() + 2 * std::mem::size_of::<usize>();
+        let mut current = Some(self.head.clone());
+        while let Some(node) = current {
+            let n = node.borrow();
+            report.add_exact(node_bytes);
+            report.add_vec(&n.forward, n.forward.len());
+            report.add_vec(&n.span, n.span.len());
+            report.add_string(&n.key, n.key.capacity());
+            current = n.forward[0].clone();
+        }
+        report
+    }
+
+    /// Trim spare capacity from key strings and forward-pointer towers.
+    pub fn shrink_to_fit(&mut self) {
+        let mut current = Some(self.head.clone());
+        while let Some(node) = current {
+            let mut n = node.borrow_mut();
+            n.key.shrink_to_fit();
+            n.forward.shrink_to_fit();
+            n.span.shrink_to_fit();
+            current = n.forward[0].clone();
+        }
+    }
 }
 
 #[cfg(test)]
@@ -412,7 +764,7 @@ mod tests {
         let mut list = SkipList::new();
 
         // Insert in non-sequential order
-        let keys = vec!["zebra", "alpha", "middle", "beta", "zulu", "alpha-2"];
+        let keys = ["zebra", "alpha", "middle", "beta", "zulu", "alpha-2"];
         for (i, key) in keys.iter().enumerate() {
             list.insert(key.to_string(), i as u32);
         }
@@ -703,4 +1055,63 @@ mod tests {
         assert_eq!(list.search("c"), Some(3));
         assert_eq!(list.search("d"), None);
     }
+
+    #[test]
+    fn test_indexed_access_matches_sorted_keys() {
+        let mut list =
+            SkipList::with_options(MAX_LEVEL, LEVEL_PROBABILITY, Some(36), MetricsMode::Full);
+        let mut model = std::collections::BTreeMap::new();
+        for i in 0..600u32 {
+            let key = format!("k{:03}", (i * 7919) % 400);
+            if i % 5 == 4 {
+                assert_eq!(list.delete(&key), model.remove(&key));
+            } else {
+                list.insert(key.clone(), i);
+                model.insert(key, i);
+            }
+        }
+        let sorted: Vec<(String, u32)> = model.into_iter().collect();
+        assert_eq!(list.len() as usize, sorted.len());
+        for (i, (key, value)) in sorted.iter().enumerate() {
+            let entry = list.get_by_index(i as u32).unwrap();
+            assert_eq!((&entry.key, entry.value), (key, *value));
+            assert_eq!(list.index_of(key), Some(i as u32));
+        }
+        assert!(list.get_by_index(sorted.len() as u32).is_none());
+        assert_eq!(list.index_of("k"), None);
+        assert_eq!(list.index_of("zzz"), None);
+
+        let range = list.range_by_index(10, 15);
+        let keys: Vec<&str> = range.iter().map(|e| e.key.as_str()).collect();
+        let expected: Vec<&str> = sorted[10..15].iter().map(|(k, _)| k.as_str()).collect();
+        assert_eq!(keys, expected);
+        assert_eq!(list.range_by_index(sorted.len() as u32 - 2, 1_000).len(), 2);
+        assert!(list.range_by_index(5, 5).is_empty());
+    }
+
+    #[test]
+    fn test_to_ascii() {
+        assert_eq!(SkipList::new().to_ascii(), "(empty)");
+        // Probability 0 keeps every node on the bottom lane
+        let mut list = SkipList::with_options(MAX_LEVEL, 0.0, Some(1), MetricsMode::Full);
+        for key in ["b", "a", "c"] {
+            list.insert(key.to_string(), 1);
+        }
+        assert_eq!(list.to_ascii(), "L0 head -> a -> b -> c -> NIL");
+
+        let mut list = SkipList::with_options(4, 0.5, Some(7), MetricsMode::Full);
+        for i in 0..40 {
+            list.insert(format!("k{:02}", i), i);
+        }
+        let drawn = list.to_ascii();
+        let lines: Vec<&str> = drawn.lines().collect();
+        assert_eq!(lines.len(), list.level + 2);
+        assert!(lines[list.level].starts_with("L0 head -> k00 -> k01"));
+        assert!(lines[list.level].ends_with("-> k31 -> ..."));
+        assert_eq!(*lines.last().unwrap(), "(8 more nodes)");
+        // Every lane lines up with the bottom one
+        assert!(lines[..=list.level]
+            .iter()
+            .all(|l| l.len() == lines[0].len()));
+    }
 }
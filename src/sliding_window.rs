@@ -0,0 +1,187 @@
+use crate::clock::now_ms;
+use std::collections::VecDeque;
+use wasm_bindgen::prelude::*;
+
+/// Buckets kept per size when the caller passes 0.
+pub const DEFAULT_BUCKETS_PER_SIZE: u32 = 4;
+
+/// Approximate count of events in a sliding window (the exponential
+/// histogram of Datar, Gionis, Indyk and Motwani).
+///
+/// Events are grouped into buckets whose sizes are powers of two, with at
+/// most `k + 1` buckets of each size: when a size overflows, its two oldest
+/// buckets merge into one of the next size. Only the oldest bucket can
+/// straddle the window edge, and half of it is counted, so the estimate is
+/// within 1/k of the true count while memory grows with k·log(count / k)
+/// rather than with the count.
+///
+/// Timestamps can be in any non-decreasing unit: milliseconds from
+/// `record_now()`, seconds, or operation numbers for "in the last N
+/// operations" windows.
+///
+/// # Example
+/// ```javascript
+/// const perMinute = new SlidingWindowCounter(60_000, 10);
+/// button.onclick = () => {
+///     perMinute.record_now();
+///     if (perMinute.count_now() > 30) showSlowDown();
+/// };
+/// ```
+#[wasm_bindgen]
+pub struct SlidingWindowCounter {
+    window: f64,
+    per_size: usize,
+    /// (timestamp of the newest event, event count), newest first
+    buckets: VecDeque<(f64, u64)>,
+    latest: f64,
+    total_events: u64,
+    merges: u64,
+}
+
+impl SlidingWindowCounter {
+    /// Drop buckets whose newest event has left the window ending at `now`.
+    fn expire(&mut self, now: f64) {
+        while self
+            .buckets
+            .back()
+            .is_some_and(|&(t, _)| t <= now - self.window)
+        {
+            self.buckets.pop_back();
+        }
+    }
+
+    /// Restore the invariant of at most `per_size + 1` buckets per size.
+    fn cascade(&mut self) {
+        let mut start = 0;
+        while start < self.buckets.len() {
+            let size = self.buckets[start].1;
+            let end = (start..self.buckets.len())
+                .find(|&i| self.buckets[i].1 != size)
+                .unwrap_or(self.buckets.len());
+            if end - start <= self.per_size + 1 {
+                start = end;
+                continue;
+            }
+            // Merge the two oldest of this size, keeping the newer timestamp
+            let newer = self.buckets[end - 2].0;
+            self.buckets.remove(end - 1);
+            self.buckets[end - 2] = (newer, size * 2);
+            self.merges += 1;
+            start = end - 2;
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl SlidingWindowCounter {
+    /// Count events in the last `window` time units, to within 1/k
+    /// (k = 0 uses 4).
+    #[wasm_bindgen(constructor)]
+    pub fn new(window: f64, k: u32) -> SlidingWindowCounter {
+        let k = if k == 0 { DEFAULT_BUCKETS_PER_SIZE } else { k };
+        SlidingWindowCounter {
+            window,
+            per_size: k as usize,
+            buckets: VecDeque::new(),
+            latest: f64::NEG_INFINITY,
+            total_events: 0,
+            merges: 0,
+        }
+    }
+
+    /// Record one event at `timestamp`. Timestamps earlier than the latest
+    /// one seen are treated as the latest.
+    pub fn record(&mut self, timestamp: f64) {
+        let timestamp = timestamp.max(self.latest);
+        self.latest = timestamp;
+        self.expire(timestamp);
+        self.buckets.push_front((timestamp, 1));
+        self.total_events += 1;
+        self.cascade();
+    }
+
+    /// Record one event at the current time in milliseconds.
+    pub fn record_now(&mut self) {
+        self.record(now_ms());
+    }
+
+    /// Estimated events in the window ending at `now`.
+    pub fn count(&mut self, now: f64) -> u64 {
+        self.expire(now.max(self.latest));
+        let total: u64 = self.buckets.iter().map(|&(_, size)| size).sum();
+        // The oldest bucket may have started before the window
+        total - self.buckets.back().map_or(0, |&(_, size)| size / 2)
+    }
+
+    /// Estimated events in the window ending now, in milliseconds.
+    pub fn count_now(&mut self) -> u64 {
+        self.count(now_ms())
+    }
+
+    /// Largest relative error of `count`, 1/k.
+    pub fn error_bound(&self) -> f64 {
+        1.0 / self.per_size as f64
+    }
+
+    pub fn window(&self) -> f64 {
+        self.window
+    }
+
+    /// Buckets held, the structure's entire memory.
+    pub fn bucket_count(&self) -> usize {
+        self.buckets.len()
+    }
+
+    /// Events ever recorded, including those that have left the window.
+    pub fn total_events(&self) -> u64 {
+        self.total_events
+    }
+
+    /// Bucket merges performed so far.
+    pub fn merges(&self) -> u64 {
+        self.merges
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exact_while_small() {
+        let mut counter = SlidingWindowCounter::new(10.0, 4);
+        for t in 0..5 {
+            counter.record(t as f64);
+        }
+        assert_eq!(counter.count(4.0), 5);
+        assert_eq!(counter.count(12.0), 2); // events at 3 and 4
+        assert_eq!(counter.count(100.0), 0);
+    }
+
+    #[test]
+    fn test_estimate_within_bound() {
+        for k in [1, 2, 8] {
+            let mut counter = SlidingWindowCounter::new(1_000.0, k);
+            let mut times = Vec::new();
+            for i in 0..20_000u64 {
+                // Bursty: several events share some timestamps
+                let t = (i * 7 / 10) as f64;
+                counter.record(t);
+                times.push(t);
+                if i % 997 == 0 {
+                    let exact = times.iter().filter(|&&x| x > t - 1_000.0).count() as f64;
+                    let estimate = counter.count(t) as f64;
+                    assert!(
+                        (estimate - exact).abs() <= exact * counter.error_bound(),
+                        "k={} exact={} estimate={}",
+                        k,
+                        exact,
+                        estimate
+                    );
+                }
+            }
+            // ~1430 events in the window, far fewer buckets
+            assert!(counter.bucket_count() < 20 * (k as usize + 1));
+        }
+    }
+}
@@ -0,0 +1,338 @@
+use crate::kv_store::{new_store, KvStore};
+use std::collections::HashMap;
+use std::fmt;
+use wasm_bindgen::prelude::*;
+
+/// Schema version written into every encoded snapshot. Bump it when the
+/// encoding changes, and add a step to [`MIGRATIONS`] that upgrades the
+/// previous version.
+pub const SNAPSHOT_VERSION: u32 = 3;
+
+/// Oldest schema version that still loads. Snapshots from before versions
+/// were recorded count as version 1.
+pub const OLDEST_SNAPSHOT_VERSION: u32 = 1;
+
+type Migration = fn(Snapshot) -> Result<Snapshot, SnapshotError>;
+
+/// `MIGRATIONS[i]` upgrades a snapshot decoded at version
+/// `OLDEST_SNAPSHOT_VERSION + i` to the next version.
+const MIGRATIONS: [Migration; (SNAPSHOT_VERSION - OLDEST_SNAPSHOT_VERSION) as usize] = [
+    // 1 -> 2: only the version field was added
+    Ok, // 2 -> 3: only the checksum was added
+    Ok,
+];
+
+/// Why a snapshot could not be loaded.
+///
+/// In JS it is thrown as an `Error` whose `name` is the variant's
+/// [`name`](SnapshotError::name), e.g. `"SnapshotCorrupt"`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SnapshotError {
+    /// The bytes are not a well-formed snapshot.
+    Malformed(String),
+    /// The schema version is outside the range this build reads.
+    UnsupportedVersion(u32),
+    /// The stored checksum doesn't match the contents: the bytes were
+    /// damaged in storage or transfer.
+    Corrupt { stored: u32, computed: u32 },
+    /// The snapshot names a structure this build doesn't have.
+    UnknownKind(String),
+    /// An encrypted snapshot didn't authenticate: the key is wrong or the
+    /// bytes were altered.
+    DecryptionFailed,
+}
+
+impl SnapshotError {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SnapshotError::Malformed(_) => "SnapshotMalformed",
+            SnapshotError::UnsupportedVersion(_) => "SnapshotUnsupportedVersion",
+            SnapshotError::Corrupt { .. } => "SnapshotCorrupt",
+            SnapshotError::UnknownKind(_) => "SnapshotUnknownKind",
+            SnapshotError::DecryptionFailed => "SnapshotDecryptionFailed",
+        }
+    }
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotError::Malformed(message) => f.write_str(message),
+            SnapshotError::UnsupportedVersion(version) => {
+                write!(
+                    f,
+                    "unsupported snapshot version {} (supported: {}-{})",
+                    version, OLDEST_SNAPSHOT_VERSION, SNAPSHOT_VERSION
+                )?;
+                if *version > SNAPSHOT_VERSION {
+                    f.write_str("; it was written by a newer release")?;
+                }
+                Ok(())
+            }
+            SnapshotError::Corrupt { stored, computed } => write!(
+                f,
+                "snapshot is corrupt: stored checksum {:08x}, computed {:08x}",
+                stored, computed
+            ),
+            SnapshotError::UnknownKind(kind) => write!(f, "unknown structure '{}'", kind),
+            SnapshotError::DecryptionFailed => {
+                f.write_str("could not decrypt snapshot: wrong key or tampered data")
+            }
+        }
+    }
+}
+
+impl From<String> for SnapshotError {
+    fn from(message: String) -> Self {
+        SnapshotError::Malformed(message)
+    }
+}
+
+impl From<SnapshotError> for JsValue {
+    fn from(error: SnapshotError) -> JsValue {
+        let js_error = js_sys::Error::new(&error.to_string());
+        js_error.set_name(error.name());
+        js_error.into()
+    }
+}
+
+/// A structure's kind and entries, detached from the structure so they can
+/// be encoded, stored and later rebuilt into an equivalent structure.
+///
+/// Only contents survive: configuration such as a custom hash function,
+/// and metrics, start fresh on restore.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Snapshot {
+    /// One of [`STORE_KINDS`](crate::kv_store::STORE_KINDS) or
+    /// [`HASHMAP_VARIANTS`](crate::kv_store::HASHMAP_VARIANTS).
+    pub kind: String,
+    /// In [`KvStore::kv_entries`] order. Restoring re-inserts them in this
+    /// order, so a HashMap keeps its insertion order.
+    pub entries: Vec<(String, u32)>,
+}
+
+impl Snapshot {
+    pub fn capture(store: &dyn KvStore) -> Snapshot {
+        Snapshot {
+            kind: store.kind().to_string(),
+            entries: store.kv_entries(),
+        }
+    }
+
+    /// Upgrade a snapshot decoded at schema `version` to the current one by
+    /// running each migration step in turn. Fails, naming the supported
+    /// range, for versions older than [`OLDEST_SNAPSHOT_VERSION`] or newer
+    /// than this build understands.
+    pub fn migrate(version: u32, snapshot: Snapshot) -> Result<Snapshot, SnapshotError> {
+        if !(OLDEST_SNAPSHOT_VERSION..=SNAPSHOT_VERSION).contains(&version) {
+            return Err(SnapshotError::UnsupportedVersion(version));
+        }
+        MIGRATIONS[(version - OLDEST_SNAPSHOT_VERSION) as usize..]
+            .iter()
+            .try_fold(snapshot, |snapshot, step| step(snapshot))
+    }
+
+    /// A new structure of the snapshot's kind holding its entries.
+    pub fn restore(&self) -> Result<Box<dyn KvStore>, SnapshotError> {
+        let mut store = new_store(&self.kind, self.entries.len())
+            .ok_or_else(|| SnapshotError::UnknownKind(self.kind.clone()))?;
+        for (key, value) in &self.entries {
+            store.kv_insert(key.clone(), *value);
+        }
+        Ok(store)
+    }
+}
+
+/// What changed between two snapshots of a structure, so a replica holding
+/// the older one can catch up without being sent every entry again.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SnapshotDelta {
+    /// Entries whose key is only in the newer snapshot, in its order
+    pub inserted: Vec<(String, u32)>,
+    /// Keys in both whose value changed, with the newer value
+    pub updated: Vec<(String, u32)>,
+    /// Keys only in the older snapshot, in its order
+    pub deleted: Vec<String>,
+}
+
+impl SnapshotDelta {
+    /// The changes that turn `older`'s entries into `newer`'s. Kinds are
+    /// not compared: a delta carries entries only.
+    pub fn between(older: &Snapshot, newer: &Snapshot) -> SnapshotDelta {
+        let old: HashMap<&str, u32> = older
+            .entries
+            .iter()
+            .map(|(key, value)| (key.as_str(), *value))
+            .collect();
+        let new: HashMap<&str, u32> = newer
+            .entries
+            .iter()
+            .map(|(key, value)| (key.as_str(), *value))
+            .collect();
+        let mut delta = SnapshotDelta::default();
+        for (key, value) in &newer.entries {
+            match old.get(key.as_str()) {
+                None => delta.inserted.push((key.clone(), *value)),
+                Some(old_value) if old_value != value => delta.updated.push((key.clone(), *value)),
+                Some(_) => {}
+            }
+        }
+        delta.deleted = older
+            .entries
+            .iter()
+            .filter(|(key, _)| !new.contains_key(key.as_str()))
+            .map(|(key, _)| key.clone())
+            .collect();
+        delta
+    }
+
+    /// Number of changed keys.
+    pub fn len(&self) -> usize {
+        self.inserted.len() + self.updated.len() + self.deleted.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Make the changes to `store`: deletes first, then updates and
+    /// inserts in order.
+    pub fn apply(&self, store: &mut dyn KvStore) {
+        for key in &self.deleted {
+            store.kv_delete(key);
+        }
+        for (key, value) in self.updated.iter().chain(&self.inserted) {
+            store.kv_insert(key.clone(), *value);
+        }
+    }
+}
+
+impl Snapshot {
+    /// Bring this snapshot's entries up to date with `delta`. Updated keys
+    /// keep their position and inserted ones are appended, the order a
+    /// HashMap replaying the same writes would list them in.
+    pub fn apply_delta(&mut self, delta: &SnapshotDelta) {
+        let mut changed: HashMap<&str, Option<u32>> = delta
+            .deleted
+            .iter()
+            .map(|key| (key.as_str(), None))
+            .collect();
+        changed.extend(
+            delta
+                .updated
+                .iter()
+                .map(|(key, value)| (key.as_str(), Some(*value))),
+        );
+        self.entries
+            .retain_mut(|(key, value)| match changed.get(key.as_str()) {
+                Some(Some(new_value)) => {
+                    *value = *new_value;
+                    true
+                }
+                Some(None) => false,
+                None => true,
+            });
+        self.entries.extend(delta.inserted.iter().cloned());
+    }
+}
+
+/// The delta from `older` to `newer`; see [`SnapshotDelta::between`].
+pub fn diff_snapshot(older: &Snapshot, newer: &Snapshot) -> SnapshotDelta {
+    SnapshotDelta::between(older, newer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kv_store::{HASHMAP_VARIANTS, STORE_KINDS};
+
+    #[test]
+    fn test_capture_and_restore() {
+        for kind in STORE_KINDS.iter().chain(&HASHMAP_VARIANTS) {
+            let mut store = new_store(kind, 0).unwrap();
+            for i in 0..100 {
+                store.kv_insert(format!("key{}", (i * 37) % 100), i);
+            }
+            store.kv_delete("key5");
+            let snapshot = Snapshot::capture(store.as_ref());
+            let restored = snapshot.restore().unwrap();
+            assert_eq!(restored.kind(), *kind);
+            let (mut before, mut after) = (store.kv_entries(), restored.kv_entries());
+            if !kind.starts_with("hashmap") {
+                // Open addressing is restored at a different capacity
+                before.sort_unstable();
+                after.sort_unstable();
+            }
+            assert_eq!(after, before, "{}", kind);
+        }
+        let bogus = Snapshot {
+            kind: "btree".to_string(),
+            entries: Vec::new(),
+        };
+        assert_eq!(
+            bogus.restore().err(),
+            Some(SnapshotError::UnknownKind("btree".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_delta_syncs_replicas() {
+        let mut primary = new_store("hashmap", 0).unwrap();
+        for i in 0..50 {
+            primary.kv_insert(format!("key{}", i), i);
+        }
+        let older = Snapshot::capture(primary.as_ref());
+        let mut replica = older.restore().unwrap();
+        let mut replica_snapshot = older.clone();
+
+        primary.kv_insert("key3".to_string(), 300);
+        primary.kv_insert("key4".to_string(), 4);
+        primary.kv_delete("key7");
+        primary.kv_delete("key8");
+        primary.kv_insert("key8".to_string(), 8);
+        primary.kv_insert("new".to_string(), 1);
+        let newer = Snapshot::capture(primary.as_ref());
+
+        let delta = diff_snapshot(&older, &newer);
+        assert_eq!(delta.updated, [("key3".to_string(), 300)]);
+        assert_eq!(delta.deleted, ["key7"]);
+        // key8 was deleted and re-inserted with the same value: no change
+        assert_eq!(delta.inserted, [("new".to_string(), 1)]);
+        assert_eq!(delta.len(), 3);
+
+        delta.apply(replica.as_mut());
+        let mut entries = replica.kv_entries();
+        let mut expected = newer.entries.clone();
+        entries.sort_unstable();
+        expected.sort_unstable();
+        assert_eq!(entries, expected);
+
+        replica_snapshot.apply_delta(&delta);
+        replica_snapshot.entries.sort_unstable();
+        assert_eq!(replica_snapshot.entries, expected);
+        assert!(diff_snapshot(&newer, &newer).is_empty());
+    }
+
+    #[test]
+    fn test_migrate() {
+        let snapshot = Snapshot {
+            kind: "trie".to_string(),
+            entries: vec![("a".to_string(), 1)],
+        };
+        for version in OLDEST_SNAPSHOT_VERSION..=SNAPSHOT_VERSION {
+            assert_eq!(
+                Snapshot::migrate(version, snapshot.clone()).unwrap(),
+                snapshot
+            );
+        }
+        let err = Snapshot::migrate(SNAPSHOT_VERSION + 1, snapshot.clone()).unwrap_err();
+        assert_eq!(err.name(), "SnapshotUnsupportedVersion");
+        let message = err.to_string();
+        assert!(message.contains("supported: 1-3") && message.contains("newer release"));
+        let err = Snapshot::migrate(0, snapshot).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "unsupported snapshot version 0 (supported: 1-3)"
+        );
+    }
+}
@@ -0,0 +1,373 @@
+use crate::json::json_string;
+use std::collections::BTreeMap;
+use wasm_bindgen::prelude::*;
+
+/// Symbols past the last Unicode scalar value, used as unique terminators.
+const TERMINATOR: u32 = 0x11_0000;
+/// `end` of a leaf edge: it grows with the text during the build.
+const LEAF_END: usize = usize::MAX;
+
+#[derive(Clone, Debug)]
+struct Node {
+    /// Edge into this node is `text[start..end]`
+    start: usize,
+    end: usize,
+    suffix_link: usize,
+    children: BTreeMap<u32, usize>,
+}
+
+/// Compressed trie of every suffix of a text, built online in O(n) by
+/// Ukkonen's algorithm.
+///
+/// Edges are labelled by ranges of the text rather than copies, so the tree
+/// has at most 2n nodes. A substring query walks down from the root in
+/// O(pattern length); the longest repeated substring is the deepest
+/// internal node; the longest common substring of two texts is the deepest
+/// node of their joint tree with suffixes of both below it.
+///
+/// # Example
+/// ```javascript
+/// const tree = new SuffixTree("banana");
+/// tree.contains("nan");                     // true
+/// tree.longest_repeated_substring();        // "ana"
+/// tree.longest_common_substring(new SuffixTree("cabana")); // "bana"
+/// const json = JSON.parse(tree.to_tree_json());
+/// ```
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct SuffixTree {
+    /// Characters as u32, followed by terminator(s)
+    text: Vec<u32>,
+    nodes: Vec<Node>,
+    metrics: SuffixTreeMetrics,
+}
+
+/// Work done by a [`SuffixTree`]
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SuffixTreeMetrics {
+    pub node_count: u32,
+    pub leaf_count: u32,
+    /// Edges split while building
+    pub splits: u32,
+    /// Suffix links followed while building
+    pub suffix_links_followed: u32,
+    pub searches: u32,
+    pub chars_compared: u32,
+}
+
+/// Per-node facts gathered by one walk over the finished tree.
+struct Annotation {
+    /// Characters from the root to the node
+    depth: Vec<usize>,
+    /// Start of some suffix ending at a leaf below the node
+    suffix: Vec<usize>,
+    /// Bit 0: a suffix starting before `boundary` is below; bit 1: one
+    /// starting after it
+    sources: Vec<u8>,
+}
+
+impl SuffixTree {
+    /// Build over `text`, which must end with a unique terminator.
+    fn from_symbols(text: Vec<u32>) -> SuffixTree {
+        let mut tree = SuffixTree {
+            text,
+            nodes: vec![Node {
+                start: 0,
+                end: 0,
+                suffix_link: 0,
+                children: BTreeMap::new(),
+            }],
+            metrics: SuffixTreeMetrics::default(),
+        };
+        let (mut active_node, mut active_edge, mut active_len) = (0, 0, 0);
+        let mut remainder = 0;
+        for i in 0..tree.text.len() {
+            let c = tree.text[i];
+            remainder += 1;
+            // Node still waiting for its suffix link (0 = none)
+            let mut needs_link = 0;
+            let mut link = |nodes: &mut Vec<Node>, node: usize| {
+                if needs_link > 0 {
+                    nodes[needs_link].suffix_link = node;
+                }
+                needs_link = node;
+            };
+            while remainder > 0 {
+                if active_len == 0 {
+                    active_edge = i;
+                }
+                let edge_symbol = tree.text[active_edge];
+                match tree.nodes[active_node].children.get(&edge_symbol) {
+                    None => {
+                        let leaf = tree.new_node(i, LEAF_END);
+                        tree.nodes[active_node].children.insert(edge_symbol, leaf);
+                        link(&mut tree.nodes, active_node);
+                    }
+                    Some(&next) => {
+                        let edge_len = tree.edge_end(next, i + 1) - tree.nodes[next].start;
+                        if active_len >= edge_len {
+                            // Walk down past a whole edge
+                            active_edge += edge_len;
+                            active_len -= edge_len;
+                            active_node = next;
+                            continue;
+                        }
+                        if tree.text[tree.nodes[next].start + active_len] == c {
+                            // Already present: extend implicitly
+                            active_len += 1;
+                            link(&mut tree.nodes, active_node);
+                            break;
+                        }
+                        let split_at = tree.nodes[next].start + active_len;
+                        let split = tree.new_node(tree.nodes[next].start, split_at);
+                        tree.nodes[active_node].children.insert(edge_symbol, split);
+                        let leaf = tree.new_node(i, LEAF_END);
+                        tree.nodes[split].children.insert(c, leaf);
+                        tree.nodes[next].start = split_at;
+                        tree.nodes[split].children.insert(tree.text[split_at], next);
+                        tree.metrics.splits += 1;
+                        link(&mut tree.nodes, split);
+                    }
+                }
+                remainder -= 1;
+                if active_node == 0 && active_len > 0 {
+                    active_len -= 1;
+                    active_edge = i + 1 - remainder;
+                } else {
+                    if tree.nodes[active_node].suffix_link != 0 {
+                        tree.metrics.suffix_links_followed += 1;
+                    }
+                    active_node = tree.nodes[active_node].suffix_link;
+                }
+            }
+        }
+        tree.metrics.node_count = tree.nodes.len() as u32;
+        tree.metrics.leaf_count = tree.nodes.iter().filter(|n| n.end == LEAF_END).count() as u32;
+        tree
+    }
+
+    fn new_node(&mut self, start: usize, end: usize) -> usize {
+        self.nodes.push(Node {
+            start,
+            end,
+            suffix_link: 0,
+            children: BTreeMap::new(),
+        });
+        self.nodes.len() - 1
+    }
+
+    /// End of `node`'s edge when the text has length `len`.
+    fn edge_end(&self, node: usize, len: usize) -> usize {
+        self.nodes[node].end.min(len)
+    }
+
+    /// The text without its terminator(s), as characters.
+    fn chars(&self) -> impl Iterator<Item = char> + '_ {
+        self.text.iter().filter_map(|&c| char::from_u32(c))
+    }
+
+    fn substring(&self, start: usize, len: usize) -> String {
+        self.text[start..start + len]
+            .iter()
+            .filter_map(|&c| char::from_u32(c))
+            .collect()
+    }
+
+    /// Depth, a suffix and source mask for every node, iteratively.
+    fn annotate(&self, boundary: usize) -> Annotation {
+        let n = self.nodes.len();
+        let mut annotation = Annotation {
+            depth: vec![0; n],
+            suffix: vec![0; n],
+            sources: vec![0; n],
+        };
+        let mut preorder = Vec::with_capacity(n);
+        let mut stack = vec![0];
+        while let Some(node) = stack.pop() {
+            preorder.push(node);
+            for &child in self.nodes[node].children.values() {
+                let edge = self.edge_end(child, self.text.len()) - self.nodes[child].start;
+                annotation.depth[child] = annotation.depth[node] + edge;
+                stack.push(child);
+            }
+        }
+        for &node in preorder.iter().rev() {
+            if self.nodes[node].children.is_empty() {
+                let start = self.text.len() - annotation.depth[node];
+                annotation.suffix[node] = start;
+                annotation.sources[node] = if start < boundary { 1 } else { 2 };
+            } else {
+                for &child in self.nodes[node].children.values() {
+                    annotation.suffix[node] = annotation.suffix[child];
+                    annotation.sources[node] |= annotation.sources[child];
+                }
+            }
+        }
+        annotation
+    }
+
+    /// Deepest internal node whose source mask contains `mask`, as text.
+    fn deepest_internal(&self, boundary: usize, mask: u8) -> String {
+        let annotation = self.annotate(boundary);
+        (1..self.nodes.len())
+            .filter(|&n| !self.nodes[n].children.is_empty())
+            .filter(|&n| annotation.sources[n] & mask == mask)
+            .max_by_key(|&n| (annotation.depth[n], std::cmp::Reverse(n)))
+            .map_or_else(String::new, |n| {
+                self.substring(annotation.suffix[n], annotation.depth[n])
+            })
+    }
+}
+
+#[wasm_bindgen]
+impl SuffixTree {
+    #[wasm_bindgen(constructor)]
+    pub fn new(text: &str) -> SuffixTree {
+        let mut symbols: Vec<u32> = text.chars().map(u32::from).collect();
+        symbols.push(TERMINATOR);
+        SuffixTree::from_symbols(symbols)
+    }
+
+    /// Whether `pattern` occurs in the text.
+    pub fn contains(&mut self, pattern: &str) -> bool {
+        self.metrics.searches += 1;
+        let pattern: Vec<u32> = pattern.chars().map(u32::from).collect();
+        let (mut node, mut matched) = (0, 0);
+        while matched < pattern.len() {
+            let Some(&child) = self.nodes[node].children.get(&pattern[matched]) else {
+                return false;
+            };
+            let (start, end) = (
+                self.nodes[child].start,
+                self.edge_end(child, self.text.len()),
+            );
+            for &symbol in &self.text[start..end] {
+                if matched == pattern.len() {
+                    return true;
+                }
+                self.metrics.chars_compared += 1;
+                if symbol != pattern[matched] {
+                    return false;
+                }
+                matched += 1;
+            }
+            node = child;
+        }
+        true
+    }
+
+    /// Longest substring occurring at least twice (overlaps allowed); the
+    /// empty string if no character repeats.
+    pub fn longest_repeated_substring(&self) -> String {
+        self.deepest_internal(self.text.len(), 1)
+    }
+
+    /// Longest substring of both this text and `other`'s.
+    pub fn longest_common_substring(&self, other: &SuffixTree) -> String {
+        let mut joint: Vec<u32> = self.chars().map(u32::from).collect();
+        let boundary = joint.len();
+        joint.push(TERMINATOR);
+        joint.extend(other.chars().map(u32::from));
+        joint.push(TERMINATOR + 1);
+        SuffixTree::from_symbols(joint).deepest_internal(boundary, 3)
+    }
+
+    /// The tree as nested JSON for visualization. Every node has `label`
+    /// (its edge's text, `$` for the terminator) and `children`; leaves
+    /// also have `suffix`, the start of the suffix they spell.
+    pub fn to_tree_json(&self) -> String {
+        let annotation = self.annotate(self.text.len());
+        let mut out = String::new();
+        // (node, next child index)
+        let mut stack = vec![(0, 0)];
+        while let Some(&mut (node, ref mut next)) = stack.last_mut() {
+            let children: Vec<usize> = self.nodes[node].children.values().copied().collect();
+            if *next == 0 {
+                let (start, end) = (self.nodes[node].start, self.edge_end(node, self.text.len()));
+                let label: String = self.text[start..end]
+                    .iter()
+                    .map(|&c| char::from_u32(c).unwrap_or('$'))
+                    .collect();
+                out.push_str(&format!("{{\"label\":{}", json_string(&label)));
+                if children.is_empty() {
+                    out.push_str(&format!(",\"suffix\":{}", annotation.suffix[node]));
+                }
+                out.push_str(",\"children\":[");
+            }
+            match children.get(*next) {
+                Some(&child) => {
+                    if *next > 0 {
+                        out.push(',');
+                    }
+                    *next += 1;
+                    stack.push((child, 0));
+                }
+                None => {
+                    out.push_str("]}");
+                    stack.pop();
+                }
+            }
+        }
+        out
+    }
+
+    /// Characters in the text.
+    pub fn len(&self) -> usize {
+        self.text.len() - 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get_metrics(&self) -> SuffixTreeMetrics {
+        self.metrics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_contains_every_substring() {
+        let text = "mississippi";
+        let mut tree = SuffixTree::new(text);
+        assert_eq!(tree.get_metrics().leaf_count, 12);
+        for i in 0..text.len() {
+            for j in i + 1..=text.len() {
+                assert!(tree.contains(&text[i..j]), "{}", &text[i..j]);
+            }
+        }
+        for absent in ["ssm", "pip", "mississippis", "x"] {
+            assert!(!tree.contains(absent));
+        }
+        assert!(tree.contains(""));
+        assert_eq!(tree.longest_repeated_substring(), "issi");
+        assert_eq!(SuffixTree::new("abc").longest_repeated_substring(), "");
+    }
+
+    #[test]
+    fn test_longest_common_substring_and_json() {
+        let a = SuffixTree::new("xabxac");
+        let b = SuffixTree::new("abcabxabcd");
+        assert_eq!(a.longest_common_substring(&b), "abxa");
+        assert_eq!(a.longest_common_substring(&SuffixTree::new("zzz")), "");
+        let banana = SuffixTree::new("banana");
+        assert_eq!(
+            banana.longest_common_substring(&SuffixTree::new("cabana")),
+            "bana"
+        );
+
+        let json = SuffixTree::new("aa").to_tree_json();
+        assert_eq!(
+            json,
+            "{\"label\":\"\",\"children\":[\
+             {\"label\":\"a\",\"children\":[\
+             {\"label\":\"a$\",\"suffix\":0,\"children\":[]},\
+             {\"label\":\"$\",\"suffix\":1,\"children\":[]}]},\
+             {\"label\":\"$\",\"suffix\":2,\"children\":[]}]}"
+        );
+    }
+}
@@ -0,0 +1,274 @@
+use crate::clock::now_ms;
+use crate::rng::DefaultRng;
+use rand::Rng;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use wasm_bindgen::prelude::*;
+
+const SLOT_BITS: u32 = 6;
+const SLOTS: usize = 1 << SLOT_BITS;
+const LEVELS: usize = 4;
+/// Delays at or beyond this wait in the overflow list.
+const WHEEL_SPAN: u64 = 1 << (SLOT_BITS * LEVELS as u32);
+
+/// Hierarchical timing wheel (Varghese & Lauck).
+///
+/// Four wheels of 64 slots each cover delays of up to 64, 64², 64³ and
+/// 64⁴ ticks. A timer lands in the coarsest wheel its delay needs; each time
+/// a finer wheel wraps, the next coarser slot is *cascaded*, its timers
+/// redistributed into finer wheels, until they reach the innermost wheel
+/// and fire exactly on their tick. Scheduling is O(1), and each timer is
+/// moved at most once per level, whereas a binary heap pays O(log n) for
+/// every schedule and expiry.
+///
+/// # Example
+/// ```javascript
+/// const wheel = new TimerWheel();
+/// wheel.schedule(150, 7);
+/// wheel.advance(100); // []
+/// wheel.advance(50);  // [7]
+/// ```
+#[wasm_bindgen]
+pub struct TimerWheel {
+    wheels: Vec<Vec<Vec<(u64, u32)>>>,
+    /// Timers too far out for the wheels
+    overflow: Vec<(u64, u32)>,
+    now: u64,
+    len: usize,
+    metrics: TimerWheelMetrics,
+}
+
+/// Metrics collected by a [`TimerWheel`]
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TimerWheelMetrics {
+    pub scheduled: u32,
+    pub expired: u32,
+    pub ticks: u64,
+    /// Slots of an outer wheel emptied into inner wheels
+    pub cascades: u32,
+    /// Timers moved by those cascades
+    pub cascaded_timers: u32,
+}
+
+impl Default for TimerWheel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TimerWheel {
+    fn place(&mut self, expires: u64, id: u32) {
+        let delta = expires - self.now;
+        if delta >= WHEEL_SPAN {
+            self.overflow.push((expires, id));
+            return;
+        }
+        let level = (0..LEVELS)
+            .find(|&l| delta < 1 << (SLOT_BITS * (l as u32 + 1)))
+            .unwrap_or(LEVELS - 1);
+        let slot = (expires >> (SLOT_BITS * level as u32)) as usize & (SLOTS - 1);
+        self.wheels[level][slot].push((expires, id));
+    }
+
+    /// Redistribute the timers of `level`'s current slot into finer wheels.
+    fn cascade(&mut self, level: usize) {
+        let slot = (self.now >> (SLOT_BITS * level as u32)) as usize & (SLOTS - 1);
+        let timers = std::mem::take(&mut self.wheels[level][slot]);
+        self.metrics.cascades += 1;
+        self.metrics.cascaded_timers += timers.len() as u32;
+        for (expires, id) in timers {
+            self.place(expires, id);
+        }
+    }
+
+    fn tick(&mut self, expired: &mut Vec<u32>) {
+        self.now += 1;
+        self.metrics.ticks += 1;
+        for level in 1..LEVELS {
+            if self.now & ((1 << (SLOT_BITS * level as u32)) - 1) != 0 {
+                break;
+            }
+            self.cascade(level);
+        }
+        if self.now.is_multiple_of(WHEEL_SPAN) && !self.overflow.is_empty() {
+            for (expires, id) in std::mem::take(&mut self.overflow) {
+                self.place(expires, id);
+            }
+        }
+        let slot = self.now as usize & (SLOTS - 1);
+        let fired = std::mem::take(&mut self.wheels[0][slot]);
+        self.len -= fired.len();
+        self.metrics.expired += fired.len() as u32;
+        expired.extend(fired.into_iter().map(|(_, id)| id));
+    }
+}
+
+#[wasm_bindgen]
+impl TimerWheel {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> TimerWheel {
+        TimerWheel {
+            wheels: vec![vec![Vec::new(); SLOTS]; LEVELS],
+            overflow: Vec::new(),
+            now: 0,
+            len: 0,
+            metrics: TimerWheelMetrics::default(),
+        }
+    }
+
+    /// Fire `id` after `delay` ticks (a delay of 0 fires on the next tick).
+    pub fn schedule(&mut self, delay: u64, id: u32) {
+        let expires = self.now + delay.max(1);
+        self.place(expires, id);
+        self.len += 1;
+        self.metrics.scheduled += 1;
+    }
+
+    /// Move time forward `ticks` ticks, returning the ids that fired in
+    /// firing order.
+    pub fn advance(&mut self, ticks: u64) -> Vec<u32> {
+        let mut expired = Vec::new();
+        for _ in 0..ticks {
+            self.tick(&mut expired);
+        }
+        expired
+    }
+
+    /// Current tick.
+    pub fn now(&self) -> u64 {
+        self.now
+    }
+
+    /// Timers scheduled but not yet fired.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn get_metrics(&self) -> TimerWheelMetrics {
+        self.metrics
+    }
+}
+
+/// The same timer workload run on a [`TimerWheel`] and on a binary heap
+/// ordered by expiry.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TimerQueueComparison {
+    pub timers: u32,
+    pub ticks: u64,
+    pub wheel_ms: f64,
+    pub heap_ms: f64,
+    pub wheel_cascaded_timers: u32,
+    /// Pushes plus pops, each O(log n)
+    pub heap_operations: u32,
+}
+
+/// Schedule `timers` timers with random delays in `1..=max_delay`, one
+/// tick passing every `per_tick` schedules, then run until all fire; once
+/// through a timer wheel and once through a binary heap.
+///
+/// # Example
+/// ```javascript
+/// const r = compare_timer_queues(100000, 5000, 10, 1);
+/// console.log(r.wheel_ms, r.heap_ms);
+/// ```
+#[wasm_bindgen]
+pub fn compare_timer_queues(
+    timers: u32,
+    max_delay: u64,
+    per_tick: u32,
+    seed: u32,
+) -> TimerQueueComparison {
+    let mut rng = DefaultRng::seed_from(u64::from(seed));
+    let delays: Vec<u64> = (0..timers)
+        .map(|_| rng.gen_range(1..=max_delay.max(1)))
+        .collect();
+    let per_tick = per_tick.max(1) as usize;
+
+    let start = now_ms();
+    let mut wheel = TimerWheel::new();
+    let mut fired = 0;
+    for (i, chunk) in delays.chunks(per_tick).enumerate() {
+        for (j, &delay) in chunk.iter().enumerate() {
+            wheel.schedule(delay, (i * per_tick + j) as u32);
+        }
+        fired += wheel.advance(1).len();
+    }
+    while !wheel.is_empty() {
+        fired += wheel.advance(1).len();
+    }
+    let wheel_ms = now_ms() - start;
+    debug_assert_eq!(fired, timers as usize);
+
+    let start = now_ms();
+    let mut heap = BinaryHeap::new();
+    let mut now = 0u64;
+    let mut heap_operations = 0;
+    let mut tick = |heap: &mut BinaryHeap<Reverse<(u64, u32)>>, now: &mut u64| {
+        *now += 1;
+        while heap.peek().is_some_and(|Reverse((t, _))| t <= now) {
+            heap.pop();
+            heap_operations += 1;
+        }
+    };
+    for (i, chunk) in delays.chunks(per_tick).enumerate() {
+        for (j, &delay) in chunk.iter().enumerate() {
+            heap.push(Reverse((now + delay, (i * per_tick + j) as u32)));
+        }
+        tick(&mut heap, &mut now);
+    }
+    while !heap.is_empty() {
+        tick(&mut heap, &mut now);
+    }
+    let heap_ms = now_ms() - start;
+
+    TimerQueueComparison {
+        timers,
+        ticks: wheel.now(),
+        wheel_ms,
+        heap_ms,
+        wheel_cascaded_timers: wheel.get_metrics().cascaded_timers,
+        heap_operations: heap_operations + timers,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timers_fire_on_their_tick() {
+        let mut wheel = TimerWheel::new();
+        let delays = [1u64, 5, 63, 64, 65, 200, 4_095, 4_096, 300_000, 20_000_000];
+        for (id, &delay) in delays.iter().enumerate() {
+            wheel.schedule(delay, id as u32);
+        }
+        let mut elapsed = 0;
+        for (id, &delay) in delays.iter().enumerate() {
+            assert!(
+                wheel.advance(delay - 1 - elapsed).is_empty(),
+                "early {}",
+                id
+            );
+            assert_eq!(wheel.advance(1), [id as u32]);
+            elapsed = delay;
+        }
+        assert!(wheel.is_empty());
+        let metrics = wheel.get_metrics();
+        assert_eq!(metrics.expired, delays.len() as u32);
+        assert!(metrics.cascades > 0);
+    }
+
+    #[test]
+    fn test_wheel_matches_heap() {
+        let result = compare_timer_queues(2_000, 5_000, 4, 9);
+        assert_eq!(result.timers, 2_000);
+        assert_eq!(result.heap_operations, 4_000);
+        assert!(result.ticks >= 500);
+    }
+}
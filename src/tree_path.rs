@@ -0,0 +1,263 @@
+use crate::rooted_tree::RootedTree;
+use crate::segment_tree::SegmentTree;
+use wasm_bindgen::prelude::*;
+
+/// Path sum / maximum queries over node values of a [`RootedTree`], with
+/// point updates (heavy-light decomposition).
+///
+/// Each node's child with the largest subtree is its *heavy* child; heavy
+/// edges chain together into paths, and the nodes are laid out so every
+/// chain is a contiguous run of a [`SegmentTree`]. Any root-ward walk
+/// crosses O(log n) light edges, so a path between two nodes splits into
+/// O(log n) chain segments, each one segment-tree range query: O(log² n)
+/// per path query and O(log n) per update.
+///
+/// # Example
+/// ```javascript
+/// const tree = new RootedTree(4, 0);
+/// tree.add_edge(0, 1, 1); tree.add_edge(1, 2, 1); tree.add_edge(0, 3, 1);
+/// const paths = new TreePathQuery(tree, [1, 2, 3, 4]);
+/// paths.path_sum(2, 3); // 3 + 2 + 1 + 4 = 10
+/// paths.update(1, 20);
+/// paths.path_max(2, 3); // 20
+/// ```
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct TreePathQuery {
+    parent: Vec<Option<usize>>,
+    depth: Vec<u32>,
+    /// Topmost node of each node's chain
+    head: Vec<usize>,
+    /// Position in the segment tree; `None` if unreachable from the root
+    position: Vec<Option<usize>>,
+    chain_count: usize,
+    segments: SegmentTree,
+    metrics: TreePathMetrics,
+}
+
+/// Work done by a [`TreePathQuery`]
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TreePathMetrics {
+    pub queries: u32,
+    pub updates: u32,
+    /// Chain segments the queries were split into
+    pub chain_segments: u32,
+}
+
+impl TreePathQuery {
+    /// Decompose `tree` and load `values[node]` into the segment tree.
+    pub fn try_new(tree: &RootedTree, values: &[f64]) -> Result<TreePathQuery, String> {
+        let adjacency = tree.adjacency_ref();
+        let n = adjacency.len();
+        if values.len() != n {
+            return Err(format!("expected {} values, got {}", n, values.len()));
+        }
+        let mut parent = vec![None; n];
+        let mut depth = vec![0; n];
+        let mut head = vec![0; n];
+        let mut position = vec![None; n];
+        let root = tree.root() as usize;
+        if root >= n {
+            return Ok(TreePathQuery {
+                parent,
+                depth,
+                head,
+                position,
+                chain_count: 0,
+                segments: SegmentTree::new(Vec::new()),
+                metrics: TreePathMetrics::default(),
+            });
+        }
+
+        // BFS order gives parents before children
+        let mut order = vec![root];
+        let mut seen = vec![false; n];
+        seen[root] = true;
+        let mut i = 0;
+        while let Some(&v) = order.get(i) {
+            i += 1;
+            for &(w, _) in &adjacency[v] {
+                if !seen[w] {
+                    seen[w] = true;
+                    parent[w] = Some(v);
+                    depth[w] = depth[v] + 1;
+                    order.push(w);
+                }
+            }
+        }
+        let mut size = vec![1usize; n];
+        let mut heavy: Vec<Option<usize>> = vec![None; n];
+        for &v in order.iter().rev() {
+            if let Some(p) = parent[v] {
+                size[p] += size[v];
+                if heavy[p].is_none_or(|h| size[v] > size[h]) {
+                    heavy[p] = Some(v);
+                }
+            }
+        }
+
+        // Lay out chains: walk each chain down its heavy children, queueing
+        // the light children as heads of new chains
+        let mut laid_out = Vec::with_capacity(order.len());
+        let mut chain_heads = vec![root];
+        let mut chain_count = 0;
+        while let Some(top) = chain_heads.pop() {
+            chain_count += 1;
+            let mut node = Some(top);
+            while let Some(v) = node {
+                head[v] = top;
+                position[v] = Some(laid_out.len());
+                laid_out.push(values[v]);
+                for &(w, _) in &adjacency[v] {
+                    if parent[w] == Some(v) && heavy[v] != Some(w) {
+                        chain_heads.push(w);
+                    }
+                }
+                node = heavy[v];
+            }
+        }
+
+        Ok(TreePathQuery {
+            parent,
+            depth,
+            head,
+            position,
+            chain_count,
+            segments: SegmentTree::new(laid_out),
+            metrics: TreePathMetrics::default(),
+        })
+    }
+
+    /// (sum, max) over the path between `a` and `b`, if both are reachable
+    /// from the root.
+    fn path_summary(&mut self, a: usize, b: usize) -> Option<(f64, f64)> {
+        self.position.get(a)?.as_ref()?;
+        self.position.get(b)?.as_ref()?;
+        self.metrics.queries += 1;
+        let (mut a, mut b) = (a, b);
+        let (mut sum, mut max) = (0.0, f64::NEG_INFINITY);
+        let mut add = |segments: &mut SegmentTree, from: usize, to: usize| {
+            let (s, m) = segments.range_summary(from, to + 1);
+            sum += s;
+            max = max.max(m);
+        };
+        while self.head[a] != self.head[b] {
+            if self.depth[self.head[a]] < self.depth[self.head[b]] {
+                std::mem::swap(&mut a, &mut b);
+            }
+            let top = self.head[a];
+            add(&mut self.segments, self.position[top]?, self.position[a]?);
+            self.metrics.chain_segments += 1;
+            a = self.parent[top]?;
+        }
+        let (pa, pb) = (self.position[a]?, self.position[b]?);
+        add(&mut self.segments, pa.min(pb), pa.max(pb));
+        self.metrics.chain_segments += 1;
+        Some((sum, max))
+    }
+}
+
+#[wasm_bindgen]
+impl TreePathQuery {
+    /// Decompose `tree`, with `values[node]` as each node's value. Throws
+    /// unless there is one value per node.
+    #[wasm_bindgen(constructor)]
+    pub fn new(tree: &RootedTree, values: Vec<f64>) -> Result<TreePathQuery, JsValue> {
+        TreePathQuery::try_new(tree, &values).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// Sum of node values on the path between `a` and `b`, both included.
+    pub fn path_sum(&mut self, a: u32, b: u32) -> Option<f64> {
+        self.path_summary(a as usize, b as usize)
+            .map(|(sum, _)| sum)
+    }
+
+    /// Largest node value on the path between `a` and `b`, both included.
+    pub fn path_max(&mut self, a: u32, b: u32) -> Option<f64> {
+        self.path_summary(a as usize, b as usize)
+            .map(|(_, max)| max)
+    }
+
+    /// Set `node`'s value. Returns false if the node is unknown or not
+    /// reachable from the root.
+    pub fn update(&mut self, node: u32, value: f64) -> bool {
+        let Some(&Some(position)) = self.position.get(node as usize) else {
+            return false;
+        };
+        self.metrics.updates += 1;
+        self.segments.update(position, value)
+    }
+
+    pub fn value(&self, node: u32) -> Option<f64> {
+        let position = (*self.position.get(node as usize)?)?;
+        self.segments.get(position)
+    }
+
+    /// Heavy chains the tree was split into.
+    pub fn chain_count(&self) -> usize {
+        self.chain_count
+    }
+
+    pub fn get_metrics(&self) -> TreePathMetrics {
+        self.metrics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::DefaultRng;
+    use rand::Rng;
+
+    #[test]
+    fn test_path_queries_match_naive() {
+        let n = 300;
+        let mut rng = DefaultRng::seed_from(11);
+        let mut tree = RootedTree::new(n, 0);
+        let parent: Vec<usize> = (0..n as usize)
+            .map(|v| if v == 0 { 0 } else { rng.gen_range(0..v) })
+            .collect();
+        for (v, &p) in parent.iter().enumerate().skip(1) {
+            tree.add_edge(p as u32, v as u32, 1.0);
+        }
+        let mut values: Vec<f64> = (0..n).map(|_| rng.gen_range(-50.0..50.0)).collect();
+        let mut paths = TreePathQuery::try_new(&tree, &values).unwrap();
+
+        for round in 0..200 {
+            let (a, b) = (rng.gen_range(0..n), rng.gen_range(0..n));
+            let lca = tree.lca(a, b).unwrap() as usize;
+            let mut on_path = vec![lca];
+            for mut v in [a as usize, b as usize] {
+                while v != lca {
+                    on_path.push(v);
+                    v = parent[v];
+                }
+            }
+            let sum: f64 = on_path.iter().map(|&v| values[v]).sum();
+            let max = on_path.iter().map(|&v| values[v]).fold(f64::MIN, f64::max);
+            assert!((paths.path_sum(a, b).unwrap() - sum).abs() < 1e-9);
+            assert_eq!(paths.path_max(a, b), Some(max));
+
+            let node = rng.gen_range(0..n);
+            values[node as usize] = round as f64;
+            assert!(paths.update(node, round as f64));
+        }
+        let metrics = paths.get_metrics();
+        assert_eq!(metrics.queries, 400);
+        // O(log n) segments per query, far below the path lengths
+        assert!(metrics.chain_segments < 400 * 12);
+    }
+
+    #[test]
+    fn test_unreachable_nodes() {
+        let mut tree = RootedTree::new(4, 0);
+        tree.add_edge(0, 1, 1.0);
+        tree.add_edge(2, 3, 1.0);
+        let mut paths = TreePathQuery::try_new(&tree, &[1.0, 2.0, 3.0, 4.0]).unwrap();
+        assert_eq!(paths.path_sum(0, 1), Some(3.0));
+        assert_eq!(paths.path_sum(1, 3), None);
+        assert!(!paths.update(2, 5.0));
+        assert!(TreePathQuery::try_new(&tree, &[1.0]).is_err());
+    }
+}
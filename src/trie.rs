@@ -1,3 +1,9 @@
+use crate::bulk::BulkInsertJob;
+use crate::csv;
+use crate::interop;
+use crate::memory::MemoryReport;
+#[cfg(feature = "msgpack")]
+use crate::msgpack;
 use std::collections::HashMap;
 use wasm_bindgen::prelude::*;
 
@@ -10,6 +16,21 @@ pub struct TrieMetrics {
     pub node_count: u32,
     pub max_depth: u32,
     pub average_word_length: f32,
+    pub fuzzy_searches: u32,
+    /// Nodes whose edit-distance row was computed during fuzzy searches
+    pub fuzzy_nodes_visited: u32,
+    /// Subtrees skipped because no word below could be within range
+    pub fuzzy_nodes_pruned: u32,
+}
+
+/// A key found by `search_fuzzy`, with its edit distance from the query.
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct FuzzyMatch {
+    pub key: String,
+    pub value: u32,
+    /// Levenshtein distance (insertions, deletions, substitutions)
+    pub distance: u32,
 }
 
 struct TrieNode {
@@ -26,6 +47,39 @@ impl TrieNode {
             value: None,
         }
     }
+
+    fn measure(&self, report: &mut MemoryReport) {
+        // std's hash table stores one control byte per slot next to each entry
+        let slot = std::mem::size_of::<(char, Box<TrieNode>)>() + 1;
+        report.add_exact(std::mem::size_of::<TrieNode>());
+        report.used_bytes += self.children.len() * slot;
+        report.reserved_bytes += self.children.capacity() * slot;
+        for child in self.children.values() {
+            child.measure(report);
+        }
+    }
+
+    /// Queue children for [`Trie::to_ascii`] so they pop in character
+    /// order, flagging the last one.
+    fn push_ascii_children<'a>(
+        &'a self,
+        stack: &mut Vec<(&'a TrieNode, char, String, bool)>,
+        indent: String,
+    ) {
+        let mut children: Vec<(char, &TrieNode)> =
+            self.children.iter().map(|(c, n)| (*c, &**n)).collect();
+        children.sort_unstable_by_key(|&(c, _)| std::cmp::Reverse(c));
+        for (i, (c, child)) in children.into_iter().enumerate() {
+            stack.push((child, c, indent.clone(), i == 0));
+        }
+    }
+
+    fn shrink(&mut self) {
+        self.children.shrink_to_fit();
+        for child in self.children.values_mut() {
+            child.shrink();
+        }
+    }
 }
 
 #[wasm_bindgen]
@@ -97,6 +151,44 @@ impl Trie {
         }
     }
 
+    /// Extend the Levenshtein table one trie level down. `previous` is the
+    /// row for the path so far; each child gets its own row, and a child
+    /// whose row has no entry within `max_edits` can't lead to a match.
+    fn fuzzy_recursive(
+        node: &TrieNode,
+        path: &mut String,
+        word: &[char],
+        previous: &[u32],
+        max_edits: u32,
+        metrics: &mut TrieMetrics,
+        results: &mut Vec<FuzzyMatch>,
+    ) {
+        for (&ch, child) in &node.children {
+            metrics.fuzzy_nodes_visited += 1;
+            let mut row = Vec::with_capacity(previous.len());
+            row.push(previous[0] + 1);
+            for (i, &wc) in word.iter().enumerate() {
+                let substitution = previous[i] + u32::from(wc != ch);
+                row.push(substitution.min(previous[i + 1] + 1).min(row[i] + 1));
+            }
+            if row.iter().min().is_some_and(|&m| m > max_edits) {
+                metrics.fuzzy_nodes_pruned += 1;
+                continue;
+            }
+            path.push(ch);
+            let distance = row[word.len()];
+            if child.is_end_of_word && distance <= max_edits {
+                results.push(FuzzyMatch {
+                    key: path.clone(),
+                    value: child.value.unwrap_or(0),
+                    distance,
+                });
+            }
+            Self::fuzzy_recursive(child, path, word, &row, max_edits, metrics, results);
+            path.pop();
+        }
+    }
+
     // Internal helper for autocomplete that returns Vec<String>
     fn autocomplete_internal(&self, prefix: &str) -> Vec<String> {
         let mut current = &self.root;
@@ -113,6 +205,30 @@ impl Trie {
         words.sort();
         words
     }
+
+    /// Every stored word with its value, in key order.
+    pub(crate) fn pairs(&self) -> Vec<(String, u32)> {
+        let mut entries = Vec::new();
+        let mut stack = vec![(&*self.root, String::new())];
+        while let Some((node, word)) = stack.pop() {
+            if let (true, Some(value)) = (node.is_end_of_word, node.value) {
+                entries.push((word.clone(), value));
+            }
+            for (ch, child) in &node.children {
+                let mut next = word.clone();
+                next.push(*ch);
+                stack.push((child, next));
+            }
+        }
+        entries.sort_unstable();
+        entries
+    }
+}
+
+impl Default for Trie {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 #[wasm_bindgen]
@@ -129,6 +245,9 @@ impl Trie {
                 node_count: 1, // root
                 max_depth: 0,
                 average_word_length: 0.0,
+                fuzzy_searches: 0,
+                fuzzy_nodes_visited: 0,
+                fuzzy_nodes_pruned: 0,
             },
         }
     }
@@ -141,11 +260,10 @@ impl Trie {
 
         for ch in word.chars() {
             depth += 1;
-            if !current.children.contains_key(&ch) {
-                current.children.insert(ch, Box::new(TrieNode::new()));
+            current = current.children.entry(ch).or_insert_with(|| {
                 self.metrics.node_count += 1;
-            }
-            current = current.children.get_mut(&ch).unwrap();
+                Box::new(TrieNode::new())
+            });
         }
 
         current.is_end_of_word = true;
@@ -216,6 +334,38 @@ impl Trie {
             .collect()
     }
 
+    /// Every stored key within `max_edits` edits of `word`, closest first
+    /// (ties in key order).
+    ///
+    /// Walks the trie computing one edit-distance row per node, so shared
+    /// prefixes share work, and abandons a branch as soon as every entry in
+    /// its row exceeds `max_edits`. The pruned count in the metrics shows how
+    /// much of the trie that skipped.
+    pub fn search_fuzzy(&mut self, word: &str, max_edits: u32) -> Vec<FuzzyMatch> {
+        self.metrics.fuzzy_searches += 1;
+        let word: Vec<char> = word.chars().collect();
+        let first_row: Vec<u32> = (0..=word.len() as u32).collect();
+        let mut results = Vec::new();
+        if self.root.is_end_of_word && word.len() as u32 <= max_edits {
+            results.push(FuzzyMatch {
+                key: String::new(),
+                value: self.root.value.unwrap_or(0),
+                distance: word.len() as u32,
+            });
+        }
+        Self::fuzzy_recursive(
+            &self.root,
+            &mut String::new(),
+            &word,
+            &first_row,
+            max_edits,
+            &mut self.metrics,
+            &mut results,
+        );
+        results.sort_by(|a, b| a.distance.cmp(&b.distance).then_with(|| a.key.cmp(&b.key)));
+        results
+    }
+
     pub fn get_metrics(&self) -> TrieMetrics {
         self.metrics.clone()
     }
@@ -227,6 +377,116 @@ impl Trie {
     pub fn is_empty(&self) -> bool {
         self.size == 0
     }
+
+    /// Build a trie from a JS `Map` of string keys to integer values
+    /// (0 to 2^32 - 1). Throws on the first entry that doesn't fit.
+    pub fn from_js_map(map: &js_sys::Map) -> Result<Trie, JsValue> {
+        interop::from_map(map, |_| Trie::new())
+    }
+
+    /// Build a trie from a plain object's own enumerable properties, as
+    /// `Object.entries` lists them. Values must be integers as for `from_js_map`.
+    pub fn from_object(object: &js_sys::Object) -> Result<Trie, JsValue> {
+        interop::from_object(object, |_| Trie::new())
+    }
+
+    /// The entries as a new JS `Map`, in key order.
+    pub fn to_js_map(&self) -> js_sys::Map {
+        interop::to_map(self)
+    }
+
+    /// The entries as a new plain object, one property per key.
+    pub fn to_object(&self) -> js_sys::Object {
+        interop::to_object(self)
+    }
+
+    /// Build a trie from CSV text whose first row names the columns,
+    /// taking keys from `key_col` and integer values from `value_col`.
+    /// Quoted fields may contain commas, quotes (as `""`) and line breaks.
+    /// Throws with the line number of the first bad row.
+    pub fn from_csv(text: &str, key_col: &str, value_col: &str) -> Result<Trie, JsValue> {
+        csv::from_csv(text, key_col, value_col, |_| Trie::new())
+    }
+
+    /// The entries as CSV with a `key,value` header row, quoting keys
+    /// where needed.
+    pub fn to_csv(&self) -> String {
+        csv::to_csv(self)
+    }
+
+    /// Build a trie from a MessagePack map of string keys to integers,
+    /// as JS encoders produce from a plain object. Requires the `msgpack`
+    /// feature.
+    #[cfg(feature = "msgpack")]
+    pub fn from_msgpack(bytes: &[u8]) -> Result<Trie, JsValue> {
+        msgpack::from_msgpack(bytes, |_| Trie::new())
+    }
+
+    /// The entries as a MessagePack map. Requires the `msgpack` feature.
+    #[cfg(feature = "msgpack")]
+    pub fn to_msgpack(&self) -> Vec<u8> {
+        msgpack::to_msgpack(self)
+    }
+
+    /// The trie as an indented tree, one line per branch. Runs of nodes
+    /// with a single child and no word are merged into one label, and words
+    /// end in `= value`.
+    ///
+    /// # Example
+    /// ```javascript
+    /// console.log(trie.to_ascii());
+    /// // (root)
+    /// // +-- ap
+    /// // |   +-- e = 2
+    /// // |   `-- ple = 1
+    /// // `-- fig = 3
+    /// ```
+    pub fn to_ascii(&self) -> String {
+        let mut out = String::from("(root)");
+        // (node, its first char, indentation, whether it's the last sibling)
+        let mut stack = Vec::new();
+        self.root.push_ascii_children(&mut stack, String::new());
+        while let Some((mut node, first, indent, is_last)) = stack.pop() {
+            let mut label = first.to_string();
+            while !node.is_end_of_word && node.children.len() == 1 {
+                let (c, child) = node.children.iter().next().expect("one child");
+                label.push(*c);
+                node = child;
+            }
+            if let (true, Some(value)) = (node.is_end_of_word, node.value) {
+                label.push_str(&format!(" = {}", value));
+            }
+            let (branch, below) = if is_last {
+                ("`-- ", "    ")
+            } else {
+                ("+-- ", "|   ")
+            };
+            out.push_str(&format!("\n{}{}{}", indent, branch, label));
+            node.push_ascii_children(&mut stack, format!("{}{}", indent, below));
+        }
+        out
+    }
+
+    /// Insert queued entries from `job` until `millis` milliseconds have elapsed.
+    ///
+    /// Returns how many entries were inserted; call again (e.g. once per
+    /// animation frame) until `job.is_done()`.
+    pub fn insert_budgeted(&mut self, job: &mut BulkInsertJob, millis: f64) -> u32 {
+        job.run_for(self, millis)
+    }
+
+    /// Estimate heap usage: one boxed node per character position plus each
+    /// node's child table, which std over-allocates as it grows.
+    pub fn memory_report(&self) -> MemoryReport {
+        let mut report = MemoryReport::default();
+        self.root.measure(&mut report);
+        report
+    }
+
+    /// Shrink every node's child table to its current size.
+    pub fn shrink_to_fit(&mut self) {
+        self.root.shrink();
+    }
 }
 
 #[cfg(test)]
@@ -423,4 +683,57 @@ mod tests {
             assert_eq!(trie.search(&format!("word_{}", i)), Some(i));
         }
     }
+
+    #[test]
+    fn test_search_fuzzy() {
+        let mut trie = Trie::new();
+        for (i, word) in ["hello", "help", "hell", "shell", "world", "yellow"]
+            .iter()
+            .enumerate()
+        {
+            trie.insert(word.to_string(), i as u32);
+        }
+
+        let found: Vec<(String, u32)> = trie
+            .search_fuzzy("helo", 1)
+            .into_iter()
+            .map(|m| (m.key, m.distance))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("hell".to_string(), 1),
+                ("hello".to_string(), 1),
+                ("help".to_string(), 1)
+            ]
+        );
+
+        let exact = trie.search_fuzzy("world", 0);
+        assert_eq!(exact.len(), 1);
+        assert_eq!(exact[0].value, 4);
+        assert!(trie.search_fuzzy("xyz", 1).is_empty());
+
+        let metrics = trie.get_metrics();
+        assert_eq!(metrics.fuzzy_searches, 3);
+        assert!(metrics.fuzzy_nodes_pruned > 0);
+        assert!(metrics.fuzzy_nodes_visited < 3 * metrics.node_count);
+    }
+
+    #[test]
+    fn test_to_ascii() {
+        let mut trie = Trie::new();
+        assert_eq!(trie.to_ascii(), "(root)");
+        for (i, word) in ["apple", "ape", "fig", "app"].iter().enumerate() {
+            trie.insert(word.to_string(), i as u32);
+        }
+        let expected = [
+            "(root)",
+            "+-- ap",
+            "|   +-- e = 1",
+            "|   `-- p = 3",
+            "|       `-- le = 0",
+            "`-- fig = 2",
+        ];
+        assert_eq!(trie.to_ascii(), expected.join("\n"));
+    }
 }
@@ -0,0 +1,311 @@
+use crate::ascii;
+use crate::bulk::BulkInsertJob;
+use crate::csv;
+use crate::interop;
+use crate::memory::MemoryReport;
+#[cfg(feature = "msgpack")]
+use crate::msgpack;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use wasm_bindgen::prelude::*;
+
+/// Chained hash table using the "power of two choices".
+///
+/// Each key hashes to two candidate buckets and new keys join whichever
+/// currently holds fewer entries. With n keys in n buckets, single-choice
+/// chaining ends up with a longest chain of about ln n / ln ln n, while two
+/// choices cut it to about ln ln n / ln 2 — an exponential improvement from
+/// one extra hash. `single_choice_max_bucket()` shows what the longest chain
+/// would have been had every key gone to its first choice.
+///
+/// # Example
+/// ```javascript
+/// const table = new TwoChoiceHashMap(1024);
+/// for (let i = 0; i < 1024; i++) table.insert(`key${i}`, i);
+/// console.log(table.get_metrics().max_bucket_size, table.single_choice_max_bucket());
+/// ```
+#[wasm_bindgen]
+pub struct TwoChoiceHashMap {
+    buckets: Vec<Vec<(String, u32)>>,
+    size: usize,
+    metrics: TwoChoiceMetrics,
+}
+
+/// Metrics collected during two-choice operations
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug)]
+pub struct TwoChoiceMetrics {
+    pub total_insertions: u32,
+    /// New keys placed in their second bucket because it was less loaded
+    pub second_choice_count: u32,
+    pub max_bucket_size: u32,
+    pub average_load_factor: f32,
+}
+
+impl TwoChoiceHashMap {
+    /// Hash `key` with one of two independent seeds.
+    fn hash_with(seed: u64, key: &str) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        seed.hash(&mut hasher);
+        key.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// The key's two candidate buckets. They may coincide.
+    fn choices(&self, key: &str) -> (usize, usize) {
+        let n = self.buckets.len() as u64;
+        (
+            (Self::hash_with(0, key) % n) as usize,
+            (Self::hash_with(1, key) % n) as usize,
+        )
+    }
+
+    fn position(&self, key: &str) -> Option<(usize, usize)> {
+        let (a, b) = self.choices(key);
+        [a, b].into_iter().find_map(|bucket| {
+            self.buckets[bucket]
+                .iter()
+                .position(|(k, _)| k == key)
+                .map(|i| (bucket, i))
+        })
+    }
+
+    /// Every entry, in bucket order.
+    pub(crate) fn pairs(&self) -> Vec<(String, u32)> {
+        self.buckets.iter().flatten().cloned().collect()
+    }
+
+    fn update_metrics(&mut self) {
+        self.metrics.max_bucket_size = self
+            .buckets
+            .iter()
+            .map(|b| b.len() as u32)
+            .max()
+            .unwrap_or(0);
+        self.metrics.average_load_factor = self.size as f32 / self.buckets.len() as f32;
+    }
+}
+
+impl Default for TwoChoiceHashMap {
+    fn default() -> Self {
+        Self::new(crate::BUCKET_COUNT as u32)
+    }
+}
+
+#[wasm_bindgen]
+impl TwoChoiceHashMap {
+    /// Create an empty table with `bucket_count` buckets (at least 1).
+    #[wasm_bindgen(constructor)]
+    pub fn new(bucket_count: u32) -> TwoChoiceHashMap {
+        TwoChoiceHashMap {
+            buckets: (0..bucket_count.max(1)).map(|_| Vec::new()).collect(),
+            size: 0,
+            metrics: TwoChoiceMetrics {
+                total_insertions: 0,
+                second_choice_count: 0,
+                max_bucket_size: 0,
+                average_load_factor: 0.0,
+            },
+        }
+    }
+
+    /// Insert or update. New keys go to the less loaded of their two
+    /// buckets, preferring the first on a tie.
+    pub fn insert(&mut self, key: String, value: u32) {
+        if let Some((bucket, i)) = self.position(&key) {
+            self.buckets[bucket][i].1 = value;
+            return;
+        }
+        let (a, b) = self.choices(&key);
+        let target = if self.buckets[b].len() < self.buckets[a].len() {
+            self.metrics.second_choice_count += 1;
+            b
+        } else {
+            a
+        };
+        self.buckets[target].push((key, value));
+        self.size += 1;
+        self.metrics.total_insertions += 1;
+        self.update_metrics();
+    }
+
+    /// Look in both candidate buckets.
+    pub fn get(&self, key: &str) -> Option<u32> {
+        self.position(key)
+            .map(|(bucket, i)| self.buckets[bucket][i].1)
+    }
+
+    pub fn delete(&mut self, key: &str) -> bool {
+        match self.position(key) {
+            Some((bucket, i)) => {
+                self.buckets[bucket].remove(i);
+                self.size -= 1;
+                self.update_metrics();
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn get_metrics(&self) -> TwoChoiceMetrics {
+        self.metrics
+    }
+
+    /// Longest chain the current keys would form if each went to its first
+    /// bucket, i.e. under ordinary single-choice chaining.
+    pub fn single_choice_max_bucket(&self) -> u32 {
+        let mut counts = vec![0u32; self.buckets.len()];
+        for (key, _) in self.buckets.iter().flatten() {
+            counts[self.choices(key).0] += 1;
+        }
+        counts.into_iter().max().unwrap_or(0)
+    }
+
+    /// Number of entries in each bucket, for plotting the distribution.
+    pub fn bucket_sizes(&self) -> Vec<u32> {
+        self.buckets.iter().map(|b| b.len() as u32).collect()
+    }
+
+    pub fn bucket_count(&self) -> usize {
+        self.buckets.len()
+    }
+
+    pub fn len(&self) -> usize {
+        self.size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    /// Build a table from a JS `Map` of string keys to integer values
+    /// (0 to 2^32 - 1). Throws on the first entry that doesn't fit.
+    pub fn from_js_map(map: &js_sys::Map) -> Result<TwoChoiceHashMap, JsValue> {
+        interop::from_map(map, |_| TwoChoiceHashMap::default())
+    }
+
+    /// Build a table from a plain object's own enumerable properties, as
+    /// `Object.entries` lists them. Values must be integers as for `from_js_map`.
+    pub fn from_object(object: &js_sys::Object) -> Result<TwoChoiceHashMap, JsValue> {
+        interop::from_object(object, |_| TwoChoiceHashMap::default())
+    }
+
+    /// The entries as a new JS `Map`, in bucket order.
+    pub fn to_js_map(&self) -> js_sys::Map {
+        interop::to_map(self)
+    }
+
+    /// The entries as a new plain object, one property per key.
+    pub fn to_object(&self) -> js_sys::Object {
+        interop::to_object(self)
+    }
+
+    /// Build a table from CSV text whose first row names the columns,
+    /// taking keys from `key_col` and integer values from `value_col`.
+    /// Quoted fields may contain commas, quotes (as `""`) and line breaks.
+    /// Throws with the line number of the first bad row.
+    pub fn from_csv(
+        text: &str,
+        key_col: &str,
+        value_col: &str,
+    ) -> Result<TwoChoiceHashMap, JsValue> {
+        csv::from_csv(text, key_col, value_col, |_| TwoChoiceHashMap::default())
+    }
+
+    /// The entries as CSV with a `key,value` header row, quoting keys
+    /// where needed.
+    pub fn to_csv(&self) -> String {
+        csv::to_csv(self)
+    }
+
+    /// Build a table from a MessagePack map of string keys to integers,
+    /// as JS encoders produce from a plain object. Requires the `msgpack`
+    /// feature.
+    #[cfg(feature = "msgpack")]
+    pub fn from_msgpack(bytes: &[u8]) -> Result<TwoChoiceHashMap, JsValue> {
+        msgpack::from_msgpack(bytes, |_| TwoChoiceHashMap::default())
+    }
+
+    /// The entries as a MessagePack map. Requires the `msgpack` feature.
+    #[cfg(feature = "msgpack")]
+    pub fn to_msgpack(&self) -> Vec<u8> {
+        msgpack::to_msgpack(self)
+    }
+
+    /// Bucket occupancy as a bar chart; see [`HashMap::to_ascii`](crate::HashMap::to_ascii).
+    pub fn to_ascii(&self) -> String {
+        let sizes: Vec<usize> = self.buckets.iter().map(Vec::len).collect();
+        let summary = format!(
+            "{} entries in {} buckets (fullest {})",
+            self.size,
+            sizes.len(),
+            sizes.iter().max().unwrap_or(&0)
+        );
+        ascii::occupancy_bars(&summary, &sizes)
+    }
+
+    /// Insert queued entries from `job` until `millis` milliseconds have elapsed.
+    ///
+    /// Returns how many entries were inserted; call again (e.g. once per
+    /// animation frame) until `job.is_done()`.
+    pub fn insert_budgeted(&mut self, job: &mut BulkInsertJob, millis: f64) -> u32 {
+        job.run_for(self, millis)
+    }
+
+    /// Estimate heap usage: the bucket headers plus every chain's
+    /// buffer and key strings.
+    pub fn memory_report(&self) -> MemoryReport {
+        let mut report = MemoryReport::default();
+        report.add_vec(&self.buckets, self.buckets.len());
+        for bucket in &self.buckets {
+            report.add_vec(bucket, bucket.len());
+            for (key, _) in bucket {
+                report.add_string(key, key.capacity());
+            }
+        }
+        report
+    }
+
+    /// Release spare chain capacity.
+    pub fn shrink_to_fit(&mut self) {
+        for bucket in &mut self.buckets {
+            bucket.shrink_to_fit();
+            for (key, _) in bucket.iter_mut() {
+                key.shrink_to_fit();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_get_delete() {
+        let mut table = TwoChoiceHashMap::new(16);
+        for i in 0..100 {
+            table.insert(format!("key{}", i), i);
+        }
+        table.insert("key7".to_string(), 700);
+        assert_eq!(table.len(), 100);
+        assert_eq!(table.get("key7"), Some(700));
+        assert!(table.delete("key7"));
+        assert!(!table.delete("key7"));
+        assert_eq!(table.get("key7"), None);
+        assert_eq!(table.bucket_sizes().iter().sum::<u32>(), 99);
+    }
+
+    #[test]
+    fn test_two_choices_beat_one() {
+        let mut table = TwoChoiceHashMap::new(4096);
+        for i in 0..4096 {
+            table.insert(format!("user:{}", i), i);
+        }
+        let metrics = table.get_metrics();
+        assert!(metrics.second_choice_count > 0);
+        assert!(metrics.max_bucket_size < table.single_choice_max_bucket());
+        // ln ln n / ln 2 + O(1) is about 3 here
+        assert!(metrics.max_bucket_size <= 5);
+    }
+}
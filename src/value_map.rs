@@ -0,0 +1,280 @@
+use crate::{HashMap, HashMapMetrics};
+use wasm_bindgen::prelude::*;
+
+/// A [`HashMap`] whose values are any `V`: the map itself stores a slot
+/// index per key, and the values live in a slab beside it. Lookups,
+/// collisions and rehashing are the map's own, so its metrics read exactly
+/// as for `u32` values: finding a written key's slot goes uncounted.
+pub struct SlabMap<V> {
+    index: HashMap,
+    values: Vec<Option<V>>,
+    // Vacated slots, reused before the slab grows
+    free: Vec<u32>,
+}
+
+impl<V> Default for SlabMap<V> {
+    fn default() -> Self {
+        SlabMap {
+            index: HashMap::new(),
+            values: Vec::new(),
+            free: Vec::new(),
+        }
+    }
+}
+
+impl<V> SlabMap<V> {
+    /// Insert or replace, returning the previous value.
+    pub fn insert(&mut self, key: String, value: V) -> Option<V> {
+        if let Some(slot) = self.index.peek(&key) {
+            return self.values[slot as usize].replace(value);
+        }
+        let slot = match self.free.pop() {
+            Some(slot) => {
+                self.values[slot as usize] = Some(value);
+                slot
+            }
+            None => {
+                self.values.push(Some(value));
+                (self.values.len() - 1) as u32
+            }
+        };
+        self.index.insert(key, slot);
+        None
+    }
+
+    pub fn get(&self, key: &str) -> Option<&V> {
+        let slot = self.index.get(key.to_string())?;
+        self.values[slot as usize].as_ref()
+    }
+
+    /// Remove `key`, returning its value.
+    pub fn remove(&mut self, key: &str) -> Option<V> {
+        let slot = self.index.peek(key)?;
+        self.index.delete(key.to_string());
+        self.free.push(slot);
+        self.values[slot as usize].take()
+    }
+
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    /// The underlying key index, for its metrics and analysis.
+    pub fn index(&self) -> &HashMap {
+        &self.index
+    }
+
+    /// Keys in insertion order.
+    pub fn keys(&self) -> Vec<String> {
+        self.index.pairs().into_iter().map(|(key, _)| key).collect()
+    }
+}
+
+/// String keys to arbitrary JS values (objects, arrays, functions, ...),
+/// where [`HashMap`] only holds integers. Built on a `HashMap` from key to
+/// slot, so `get_metrics` reports the same collisions and chain lengths.
+///
+/// # Example
+/// ```javascript
+/// const users = new JsValueMap();
+/// users.insert("alice", { id: 1, roles: ["admin"] });
+/// users.get("alice").roles; // ["admin"]
+/// users.get_metrics().total_collisions;
+/// ```
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct JsValueMap {
+    map: SlabMap<JsValue>,
+}
+
+#[wasm_bindgen]
+impl JsValueMap {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> JsValueMap {
+        JsValueMap::default()
+    }
+
+    /// Insert or replace, returning the previous value (`undefined` if
+    /// there was none).
+    pub fn insert(&mut self, key: String, value: JsValue) -> JsValue {
+        self.map.insert(key, value).unwrap_or(JsValue::UNDEFINED)
+    }
+
+    /// The value for `key`, or `undefined`.
+    pub fn get(&self, key: &str) -> JsValue {
+        self.map.get(key).cloned().unwrap_or(JsValue::UNDEFINED)
+    }
+
+    pub fn has(&self, key: &str) -> bool {
+        self.map.get(key).is_some()
+    }
+
+    pub fn delete(&mut self, key: &str) -> bool {
+        self.map.remove(key).is_some()
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Keys in insertion order.
+    pub fn keys(&self) -> Vec<String> {
+        self.map.keys()
+    }
+
+    pub fn get_metrics(&self) -> HashMapMetrics {
+        self.map.index().get_metrics()
+    }
+}
+
+/// String keys to string values, for text processing without encoding
+/// values as integers. The same chaining [`HashMap`] indexes the keys, so
+/// `get_metrics` reads as it does there.
+///
+/// # Example
+/// ```javascript
+/// const abbreviations = new HashMapStr();
+/// abbreviations.insert("e.g.", "for example");
+/// abbreviations.get("e.g."); // "for example"
+/// ```
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct HashMapStr {
+    map: SlabMap<String>,
+}
+
+#[wasm_bindgen]
+impl HashMapStr {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> HashMapStr {
+        HashMapStr::default()
+    }
+
+    /// Insert or replace, returning the previous value.
+    pub fn insert(&mut self, key: String, value: String) -> Option<String> {
+        self.map.insert(key, value)
+    }
+
+    pub fn get(&self, key: &str) -> Option<String> {
+        self.map.get(key).cloned()
+    }
+
+    pub fn has(&self, key: &str) -> bool {
+        self.map.get(key).is_some()
+    }
+
+    pub fn delete(&mut self, key: &str) -> bool {
+        self.map.remove(key).is_some()
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Keys in insertion order.
+    pub fn keys(&self) -> Vec<String> {
+        self.map.keys()
+    }
+
+    pub fn get_metrics(&self) -> HashMapMetrics {
+        self.map.index().get_metrics()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The lookup counters, which only the caller's reads should move.
+    fn lookup_counters(metrics: HashMapMetrics) -> [u32; 5] {
+        [
+            metrics.get_calls,
+            metrics.total_lookups,
+            metrics.unsuccessful_lookups,
+            metrics.lookup_chain_traversals,
+            metrics.chain_comparisons,
+        ]
+    }
+
+    /// What a plain `HashMap` reports for two inserts and one delete.
+    fn plain_counters() -> [u32; 5] {
+        let mut plain = HashMap::new();
+        plain.insert("a".to_string(), 0);
+        plain.insert("b".to_string(), 1);
+        plain.delete("a".to_string());
+        lookup_counters(plain.get_metrics())
+    }
+
+    #[test]
+    fn test_slab_map() {
+        let mut map: SlabMap<Vec<&str>> = SlabMap::default();
+        assert_eq!(map.insert("alice".to_string(), vec!["admin"]), None);
+        map.insert("bob".to_string(), vec![]);
+        assert_eq!(
+            map.insert("alice".to_string(), vec!["admin", "ops"]),
+            Some(vec!["admin"])
+        );
+        assert_eq!(map.get("alice").map(Vec::len), Some(2));
+        assert_eq!(map.remove("bob"), Some(vec![]));
+        assert_eq!(map.remove("bob"), None);
+        // The vacated slot is reused
+        map.insert("carol".to_string(), vec!["dev"]);
+        assert_eq!(map.values.len(), 2);
+        assert_eq!(map.keys(), ["alice", "carol"]);
+        assert_eq!(map.index().get_metrics().total_insertions, 3);
+    }
+
+    #[test]
+    fn test_hashmap_str() {
+        let mut map = HashMapStr::new();
+        assert_eq!(map.insert("e.g.".into(), "for example".into()), None);
+        assert_eq!(
+            map.insert("e.g.".into(), "exempli gratia".into()),
+            Some("for example".to_string())
+        );
+        assert_eq!(map.get("e.g.").as_deref(), Some("exempli gratia"));
+        assert!(map.has("e.g.") && !map.has("i.e."));
+        assert!(map.delete("e.g.") && map.is_empty());
+    }
+
+    #[test]
+    fn test_writes_are_not_counted_as_lookups() {
+        let mut values = JsValueMap::new();
+        values.insert("a".to_string(), JsValue::NULL);
+        values.insert("b".to_string(), JsValue::NULL);
+        values.delete("a");
+        assert_eq!(lookup_counters(values.get_metrics()), plain_counters());
+        assert_eq!(values.get_metrics().get_calls, 0);
+        assert_eq!(values.get_metrics().total_deletions, 1);
+    }
+
+    #[test]
+    fn test_hashmap_str_writes_are_not_counted_as_lookups() {
+        let mut map = HashMapStr::new();
+        map.insert("a".to_string(), "x".to_string());
+        map.insert("b".to_string(), "y".to_string());
+        map.delete("a");
+        assert_eq!(lookup_counters(map.get_metrics()), plain_counters());
+        assert_eq!(map.get_metrics().total_lookups, 0);
+
+        map.get("b");
+        map.get("missing");
+        let metrics = map.get_metrics();
+        assert_eq!(
+            (metrics.total_lookups, metrics.unsuccessful_lookups),
+            (2, 1)
+        );
+    }
+}
@@ -0,0 +1,338 @@
+use crate::memory::MemoryReport;
+use std::collections::{BTreeMap, VecDeque};
+use std::mem::size_of;
+use wasm_bindgen::prelude::*;
+
+/// One write to a key: the value it held from `version` on, or `None` from
+/// the delete at `version`.
+type Record = (u32, Option<u32>);
+
+/// How much history a [`VersionedMap`] is holding on to.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct VersionedMapMetrics {
+    /// Current version: the number of mutations so far
+    pub version: u32,
+    /// Oldest version `get_at` and `snapshot_at` can still answer
+    pub oldest_version: u32,
+    pub live_keys: u32,
+    /// Records kept across all keys' version chains, tombstones included
+    pub history_records: u32,
+    /// Records dropped because no retained version can see them
+    pub pruned_records: u32,
+}
+
+/// A map where every insert and delete bumps a version, and any retained
+/// version can be read back: multiversion concurrency control in miniature.
+///
+/// Each key keeps a chain of `(version, value)` records, a delete writing a
+/// tombstone. `get_at(key, v)` binary-searches the chain for the last record
+/// at or before `v`, and `snapshot_at(v)` does that for every key. Version
+/// 0 is the empty map before the first mutation.
+///
+/// Without a retention limit history grows with every write. With one, only
+/// the latest `retention` versions stay readable; records no retained
+/// version can see are pruned as the version moves past them, oldest write
+/// first, so pruning is amortized O(log n) per write.
+///
+/// # Example
+/// ```javascript
+/// const doc = VersionedMap.with_retention(100);
+/// const before = doc.version();
+/// doc.insert("title", 1);
+/// doc.delete("title");
+/// doc.get_at("title", before + 1); // 1
+/// doc.snapshot_at(before);         // Map as it was
+/// ```
+#[wasm_bindgen]
+#[derive(Clone, Debug, Default)]
+pub struct VersionedMap {
+    chains: BTreeMap<String, Vec<Record>>,
+    version: u32,
+    // Versions kept readable, including the current one; `None` keeps all
+    retention: Option<u32>,
+    // Every write as (version, key), oldest first, so pruning can visit
+    // just the keys whose records fall out of the retained window
+    writes: VecDeque<(u32, String)>,
+    live_keys: u32,
+    history_records: u32,
+    pruned_records: u32,
+}
+
+impl VersionedMap {
+    pub fn try_with_retention(versions: u32) -> Result<VersionedMap, String> {
+        if versions == 0 {
+            return Err("retention must keep at least 1 version".to_string());
+        }
+        Ok(VersionedMap {
+            retention: Some(versions),
+            ..VersionedMap::default()
+        })
+    }
+
+    /// The value of `key` as of `version`. Fails for a version that is
+    /// pruned or not yet written.
+    pub fn try_get_at(&self, key: &str, version: u32) -> Result<Option<u32>, String> {
+        self.check_version(version)?;
+        Ok(self
+            .chains
+            .get(key)
+            .and_then(|chain| value_at(chain, version)))
+    }
+
+    /// Every entry live at `version`, sorted by key.
+    pub fn try_snapshot_at(&self, version: u32) -> Result<Vec<(String, u32)>, String> {
+        self.check_version(version)?;
+        Ok(self
+            .chains
+            .iter()
+            .filter_map(|(key, chain)| value_at(chain, version).map(|value| (key.clone(), value)))
+            .collect())
+    }
+
+    fn check_version(&self, version: u32) -> Result<(), String> {
+        if version > self.version {
+            return Err(format!(
+                "version {} is newer than the current version {}",
+                version, self.version
+            ));
+        }
+        let oldest = self.oldest_version();
+        if version < oldest {
+            return Err(format!(
+                "version {} has been pruned (oldest retained: {})",
+                version, oldest
+            ));
+        }
+        Ok(())
+    }
+
+    /// Record `value` for `key` at a new version.
+    fn write(&mut self, key: String, value: Option<u32>) {
+        self.version += 1;
+        self.chains
+            .entry(key.clone())
+            .or_default()
+            .push((self.version, value));
+        self.history_records += 1;
+        if self.retention.is_some() {
+            self.writes.push_back((self.version, key));
+            self.prune();
+        }
+    }
+
+    /// Internal: Drop the records of keys written before the oldest
+    /// retained version that no retained version can read.
+    fn prune(&mut self) {
+        let oldest = self.oldest_version();
+        while let Some(&(version, _)) = self.writes.front() {
+            if version >= oldest {
+                break;
+            }
+            let (_, key) = self.writes.pop_front().unwrap();
+            let Some(chain) = self.chains.get_mut(&key) else {
+                continue;
+            };
+            // The last record at or before `oldest` is what every retained
+            // version starts from; only a tombstone there can go too
+            let base = chain.partition_point(|&(v, _)| v <= oldest);
+            let mut drop = base.saturating_sub(1);
+            if base > 0 && chain[base - 1].1.is_none() {
+                drop = base;
+            }
+            chain.drain(..drop);
+            self.history_records -= drop as u32;
+            self.pruned_records += drop as u32;
+            if chain.is_empty() {
+                self.chains.remove(&key);
+            }
+        }
+    }
+}
+
+/// Value of the last record at or before `version`.
+fn value_at(chain: &[Record], version: u32) -> Option<u32> {
+    let end = chain.partition_point(|&(v, _)| v <= version);
+    chain[..end].last().and_then(|&(_, value)| value)
+}
+
+#[wasm_bindgen]
+impl VersionedMap {
+    /// An empty map that keeps every version.
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> VersionedMap {
+        VersionedMap::default()
+    }
+
+    /// An empty map where only the latest `versions` versions stay
+    /// readable. Throws if `versions` is 0.
+    pub fn with_retention(versions: u32) -> Result<VersionedMap, JsValue> {
+        Self::try_with_retention(versions).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// Set `key` to `value`, returning the new version.
+    pub fn insert(&mut self, key: String, value: u32) -> u32 {
+        if self.get(&key).is_none() {
+            self.live_keys += 1;
+        }
+        self.write(key, Some(value));
+        self.version
+    }
+
+    pub fn get(&self, key: &str) -> Option<u32> {
+        self.chains
+            .get(key)
+            .and_then(|chain| chain.last())
+            .and_then(|&(_, value)| value)
+    }
+
+    /// Delete `key`, bumping the version if it was present.
+    pub fn delete(&mut self, key: &str) -> bool {
+        if self.get(key).is_none() {
+            return false;
+        }
+        self.live_keys -= 1;
+        self.write(key.to_string(), None);
+        true
+    }
+
+    /// The value of `key` as of `version`. Throws for a version that is
+    /// pruned or not yet written.
+    pub fn get_at(&self, key: &str, version: u32) -> Result<Option<u32>, JsValue> {
+        self.try_get_at(key, version)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// The entries live at `version` as a new JS `Map`, sorted by key.
+    pub fn snapshot_at(&self, version: u32) -> Result<js_sys::Map, JsValue> {
+        let entries = self
+            .try_snapshot_at(version)
+            .map_err(|e| JsValue::from_str(&e))?;
+        let map = js_sys::Map::new();
+        for (key, value) in entries {
+            map.set(&JsValue::from_str(&key), &JsValue::from(value));
+        }
+        Ok(map)
+    }
+
+    /// Number of mutations so far; reading at this version reads the
+    /// current state.
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    pub fn oldest_version(&self) -> u32 {
+        match self.retention {
+            Some(versions) => (self.version + 1).saturating_sub(versions),
+            None => 0,
+        }
+    }
+
+    pub fn retention(&self) -> Option<u32> {
+        self.retention
+    }
+
+    /// Number of live entries.
+    pub fn len(&self) -> usize {
+        self.live_keys as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.live_keys == 0
+    }
+
+    pub fn metrics(&self) -> VersionedMapMetrics {
+        VersionedMapMetrics {
+            version: self.version,
+            oldest_version: self.oldest_version(),
+            live_keys: self.live_keys,
+            history_records: self.history_records,
+            pruned_records: self.pruned_records,
+        }
+    }
+
+    /// Estimate heap usage: each key's string and version chain, the
+    /// write log pruning works from, and an entry's worth of tree node per
+    /// key.
+    pub fn memory_report(&self) -> MemoryReport {
+        let mut report = MemoryReport::default();
+        for (key, chain) in &self.chains {
+            report.add_exact(size_of::<(String, Vec<Record>)>());
+            report.add_string(key, key.capacity());
+            report.add_vec(chain, chain.len());
+        }
+        report.used_bytes += self.writes.len() * size_of::<(u32, String)>();
+        report.reserved_bytes += self.writes.capacity() * size_of::<(u32, String)>();
+        for (_, key) in &self.writes {
+            report.add_string(key, key.capacity());
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::DefaultRng;
+    use rand::Rng;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_reads_match_recorded_states() {
+        let mut map = VersionedMap::new();
+        let mut rng = DefaultRng::seed_from(11);
+        let mut states = vec![HashMap::new()];
+        let mut state = HashMap::new();
+        for _ in 0..1_000 {
+            let key = format!("k{}", rng.gen_range(0..50));
+            if rng.gen_bool(0.3) {
+                if map.delete(&key) {
+                    state.remove(&key);
+                    states.push(state.clone());
+                }
+            } else {
+                let value = rng.gen_range(0..100);
+                map.insert(key.clone(), value);
+                state.insert(key, value);
+                states.push(state.clone());
+            }
+        }
+        assert_eq!(map.version() as usize, states.len() - 1);
+        assert_eq!(map.len(), state.len());
+        for (version, expected) in states.iter().enumerate() {
+            let snapshot = map.try_snapshot_at(version as u32).unwrap();
+            assert_eq!(snapshot.len(), expected.len());
+            for (key, value) in snapshot {
+                assert_eq!(expected.get(&key), Some(&value));
+                assert_eq!(map.try_get_at(&key, version as u32), Ok(Some(value)));
+            }
+        }
+        assert!(map.try_get_at("k0", map.version() + 1).is_err());
+    }
+
+    #[test]
+    fn test_retention_prunes_history() {
+        let mut map = VersionedMap::try_with_retention(3).unwrap();
+        assert!(VersionedMap::try_with_retention(0).is_err());
+        for value in 0..10 {
+            map.insert("a".to_string(), value);
+        }
+        map.insert("b".to_string(), 1);
+        map.delete("b");
+        // Versions 10-12 are retained; "a" needs only its record from 10
+        assert_eq!(map.oldest_version(), 10);
+        assert_eq!(map.try_get_at("a", 10), Ok(Some(9)));
+        assert_eq!(map.try_get_at("b", 11), Ok(Some(1)));
+        assert!(map.try_get_at("a", 9).unwrap_err().contains("pruned"));
+        let metrics = map.metrics();
+        assert_eq!((metrics.history_records, metrics.pruned_records), (3, 9));
+
+        // Once no retained version sees "b", its tombstone goes too
+        map.insert("a".to_string(), 0);
+        map.insert("a".to_string(), 0);
+        assert_eq!(map.try_snapshot_at(12), Ok(vec![("a".to_string(), 9)]));
+        let metrics = map.metrics();
+        assert_eq!((metrics.live_keys, metrics.history_records), (1, 3));
+        assert!(map.memory_report().used_bytes > 0);
+    }
+}
@@ -0,0 +1,390 @@
+use crate::benchmark::{measure, BenchmarkResult};
+use crate::kv_store::{new_store, DynamicStore, KvStore};
+use crate::scenarios::Operation;
+use std::collections::HashMap;
+use wasm_bindgen::prelude::*;
+
+/// Leading bytes of a serialized [`Workload`]
+const MAGIC: &[u8; 4] = b"WDSW";
+/// Bump when the encoding changes; older readers reject newer data
+const FORMAT_VERSION: u8 = 1;
+
+const TAG_INSERT: u8 = 0;
+const TAG_GET: u8 = 1;
+const TAG_DELETE: u8 = 2;
+
+/// A recorded operation stream that can be saved and replayed later.
+///
+/// The serialized form is compact: each distinct key is stored once in a
+/// dictionary, and operations refer to it by index, with indices and values
+/// as LEB128 varints. A cache-style workload that touches the same few
+/// thousand keys over and over shrinks to two or three bytes per operation.
+///
+/// # Example
+/// ```javascript
+/// const bytes = recorder.to_bytes();               // Uint8Array, e.g. for localStorage
+/// const workload = Workload.from_bytes(bytes);
+/// const result = workload.replay("rbtree");
+/// console.log(result.ops_per_ms(), result.hits);
+/// ```
+#[wasm_bindgen]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Workload {
+    operations: Vec<Operation>,
+}
+
+impl Workload {
+    pub fn from_operations(operations: Vec<Operation>) -> Workload {
+        Workload { operations }
+    }
+
+    pub fn operations(&self) -> &[Operation] {
+        &self.operations
+    }
+
+    pub fn try_from_bytes(bytes: &[u8]) -> Result<Workload, String> {
+        let mut reader = Reader { bytes, pos: 0 };
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err("not a recorded workload".to_string());
+        }
+        let version = reader.take(1)?[0];
+        if version != FORMAT_VERSION {
+            return Err(format!("unsupported workload format version {}", version));
+        }
+        let key_count = reader.varint()? as usize;
+        let mut keys = Vec::with_capacity(key_count.min(bytes.len()));
+        for _ in 0..key_count {
+            let len = reader.varint()? as usize;
+            let key = std::str::from_utf8(reader.take(len)?)
+                .map_err(|_| "workload key is not valid UTF-8".to_string())?;
+            keys.push(key.to_string());
+        }
+        let op_count = reader.varint()? as usize;
+        let mut operations = Vec::with_capacity(op_count.min(bytes.len()));
+        for _ in 0..op_count {
+            let tag = reader.take(1)?[0];
+            let index = reader.varint()? as usize;
+            let key = keys
+                .get(index)
+                .ok_or_else(|| format!("key index {} out of range", index))?
+                .clone();
+            operations.push(match tag {
+                TAG_INSERT => {
+                    let value = u32::try_from(reader.varint()?)
+                        .map_err(|_| "insert value out of range".to_string())?;
+                    Operation::Insert(key, value)
+                }
+                TAG_GET => Operation::Get(key),
+                TAG_DELETE => Operation::Delete(key),
+                tag => return Err(format!("unknown operation tag {}", tag)),
+            });
+        }
+        if reader.pos != bytes.len() {
+            return Err("trailing bytes after workload".to_string());
+        }
+        Ok(Workload { operations })
+    }
+
+    /// Replay against a fresh structure of `structure` kind.
+    pub fn try_replay(&self, structure: &str) -> Result<BenchmarkResult, String> {
+        let mut store = new_store(structure, self.distinct_keys())
+            .ok_or_else(|| format!("unknown structure '{}'", structure))?;
+        Ok(self.replay_on(store.as_mut()))
+    }
+
+    /// Replay against an existing structure. Every operation is measured;
+    /// the result's scenario is `"recorded"`.
+    pub fn replay_on(&self, store: &mut dyn KvStore) -> BenchmarkResult {
+        measure(
+            "recorded",
+            FORMAT_VERSION as u32,
+            store,
+            &[],
+            &self.operations,
+        )
+    }
+}
+
+#[wasm_bindgen]
+impl Workload {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Workload, JsValue> {
+        Self::try_from_bytes(bytes).map_err(|e| JsValue::from_str(&e))
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut index: HashMap<&str, u64> = HashMap::new();
+        let mut keys: Vec<&str> = Vec::new();
+        let mut ops = Vec::new();
+        for op in &self.operations {
+            let (tag, key, value) = match op {
+                Operation::Insert(key, value) => (TAG_INSERT, key, Some(*value)),
+                Operation::Get(key) => (TAG_GET, key, None),
+                Operation::Delete(key) => (TAG_DELETE, key, None),
+            };
+            let id = *index.entry(key.as_str()).or_insert_with(|| {
+                keys.push(key);
+                keys.len() as u64 - 1
+            });
+            ops.push(tag);
+            write_varint(&mut ops, id);
+            if let Some(value) = value {
+                write_varint(&mut ops, value as u64);
+            }
+        }
+
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(FORMAT_VERSION);
+        write_varint(&mut bytes, keys.len() as u64);
+        for key in keys {
+            write_varint(&mut bytes, key.len() as u64);
+            bytes.extend_from_slice(key.as_bytes());
+        }
+        write_varint(&mut bytes, self.operations.len() as u64);
+        bytes.extend(ops);
+        bytes
+    }
+
+    /// Replay every operation against a fresh structure of `structure` kind
+    /// (see [`STORE_KINDS`](crate::kv_store::STORE_KINDS)).
+    pub fn replay(&self, structure: &str) -> Result<BenchmarkResult, JsValue> {
+        self.try_replay(structure)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// Replay every operation against an existing structure.
+    pub fn replay_into(&self, store: &mut DynamicStore) -> BenchmarkResult {
+        self.replay_on(&mut *store.store_mut())
+    }
+
+    pub fn len(&self) -> usize {
+        self.operations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.operations.is_empty()
+    }
+
+    /// Number of different keys the workload touches.
+    pub fn distinct_keys(&self) -> usize {
+        let mut keys: Vec<&str> = self
+            .operations
+            .iter()
+            .map(|op| match op {
+                Operation::Insert(key, _) | Operation::Get(key) | Operation::Delete(key) => {
+                    key.as_str()
+                }
+            })
+            .collect();
+        keys.sort_unstable();
+        keys.dedup();
+        keys.len()
+    }
+}
+
+/// Passes operations through to a structure while recording them into a
+/// [`Workload`], e.g. to capture what a live demo does and replay it later
+/// in a benchmark against other structures.
+///
+/// # Example
+/// ```javascript
+/// const recorder = new WorkloadRecorder("hashmap");
+/// recorder.insert("alice", 1);
+/// recorder.get("alice");
+/// localStorage.setItem("workload", JSON.stringify([...recorder.to_bytes()]));
+/// ```
+#[wasm_bindgen]
+pub struct WorkloadRecorder {
+    store: DynamicStore,
+    workload: Workload,
+    recording: bool,
+}
+
+impl WorkloadRecorder {
+    pub fn try_new(kind: &str) -> Result<WorkloadRecorder, String> {
+        Ok(Self::from_store(DynamicStore::try_new(kind, 0)?))
+    }
+
+    /// Record operations applied to an existing structure.
+    pub fn from_store(store: DynamicStore) -> WorkloadRecorder {
+        WorkloadRecorder {
+            store,
+            workload: Workload::default(),
+            recording: true,
+        }
+    }
+
+    fn record(&mut self, op: Operation) {
+        if self.recording {
+            self.workload.operations.push(op);
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl WorkloadRecorder {
+    /// Record operations applied to a new, empty structure.
+    #[wasm_bindgen(constructor)]
+    pub fn new(kind: &str) -> Result<WorkloadRecorder, JsValue> {
+        Self::try_new(kind).map_err(|e| JsValue::from_str(&e))
+    }
+
+    pub fn insert(&mut self, key: String, value: u32) {
+        self.record(Operation::Insert(key.clone(), value));
+        self.store.insert(key, value);
+    }
+
+    pub fn get(&mut self, key: &str) -> Option<u32> {
+        self.record(Operation::Get(key.to_string()));
+        self.store.get(key)
+    }
+
+    pub fn delete(&mut self, key: &str) -> bool {
+        self.record(Operation::Delete(key.to_string()));
+        self.store.delete(key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.store.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.store.is_empty()
+    }
+
+    /// Stop or resume recording; operations still reach the structure.
+    pub fn set_recording(&mut self, recording: bool) {
+        self.recording = recording;
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording
+    }
+
+    /// Operations recorded so far.
+    pub fn operation_count(&self) -> usize {
+        self.workload.len()
+    }
+
+    /// A copy of everything recorded so far.
+    pub fn workload(&self) -> Workload {
+        self.workload.clone()
+    }
+
+    /// The recording in serialized form (see [`Workload::to_bytes`]).
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.workload.to_bytes()
+    }
+
+    /// Forget what has been recorded; the structure keeps its contents.
+    pub fn clear(&mut self) {
+        self.workload.operations.clear();
+    }
+
+    /// A handle to the structure being recorded.
+    pub fn store(&self) -> DynamicStore {
+        self.store.handle()
+    }
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|&end| end <= self.bytes.len())
+            .ok_or_else(|| "workload is truncated".to_string())?;
+        let slice = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn varint(&mut self) -> Result<u64, String> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.take(1)?[0];
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err("varint is too long".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kv_store::STORE_KINDS;
+
+    #[test]
+    fn test_record_and_replay() {
+        let mut recorder = WorkloadRecorder::try_new("hashmap").unwrap();
+        for i in 0..300u32 {
+            recorder.insert(format!("session{}", i % 50), i * 1_000);
+            recorder.get(&format!("session{}", (i * 7) % 60));
+            if i % 5 == 0 {
+                recorder.delete(&format!("session{}", i % 40));
+            }
+        }
+        recorder.set_recording(false);
+        recorder.insert("unrecorded".to_string(), 1);
+        assert_eq!(recorder.operation_count(), 660);
+
+        let bytes = recorder.to_bytes();
+        // A few bytes per operation once the key dictionary is paid for
+        assert!(bytes.len() < 4 * 660 + 60 * 12);
+        let workload = Workload::try_from_bytes(&bytes).unwrap();
+        assert_eq!(workload, recorder.workload());
+        assert_eq!(workload.distinct_keys(), 60);
+
+        let expected = workload.try_replay("hashmap").unwrap();
+        assert_eq!(expected.scenario, "recorded");
+        assert_eq!(expected.measured_operations, 660);
+        assert_eq!(recorder.len(), expected.final_size as usize + 1);
+        for kind in STORE_KINDS {
+            let result = workload.try_replay(kind).unwrap();
+            assert_eq!(result.structure, kind);
+            assert_eq!(
+                (result.hits, result.misses),
+                (expected.hits, expected.misses)
+            );
+            assert_eq!(result.final_size, expected.final_size);
+        }
+        assert!(workload.try_replay("btree").is_err());
+    }
+
+    #[test]
+    fn test_rejects_malformed_bytes() {
+        let workload = Workload::from_operations(vec![
+            Operation::Insert("a".to_string(), 300),
+            Operation::Delete("a".to_string()),
+        ]);
+        let bytes = workload.to_bytes();
+        assert_eq!(Workload::try_from_bytes(&bytes).unwrap(), workload);
+        for len in 0..bytes.len() {
+            assert!(Workload::try_from_bytes(&bytes[..len]).is_err());
+        }
+        let mut newer = bytes.clone();
+        newer[4] = FORMAT_VERSION + 1;
+        assert!(Workload::try_from_bytes(&newer)
+            .unwrap_err()
+            .contains("version"));
+        let mut trailing = bytes;
+        trailing.push(0);
+        assert!(Workload::try_from_bytes(&trailing).is_err());
+        assert!(Workload::try_from_bytes(b"JSON{}").is_err());
+    }
+}
@@ -0,0 +1,158 @@
+use crate::benchmark::{run_scenario_on, BenchmarkResult};
+use crate::kv_store::DynamicStore;
+use crate::scenarios::Scenario;
+use std::collections::BTreeMap;
+use wasm_bindgen::prelude::*;
+
+/// Structures held under string names.
+///
+/// `get` returns a handle sharing the named structure, so writes through
+/// the handle are visible to everything else referencing the same name.
+///
+/// # Example
+/// ```javascript
+/// const ws = new Workspace();
+/// ws.create("users", "rbtree");
+/// ws.get("users").insert("alice", 1);
+/// console.log(ws.get("users").len()); // 1
+/// const result = ws.benchmark("users", "session-cache-churn");
+/// ```
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct Workspace {
+    stores: BTreeMap<String, DynamicStore>,
+}
+
+impl Workspace {
+    /// Create an empty structure of `kind` named `name`.
+    pub fn try_create(&mut self, name: &str, kind: &str) -> Result<(), String> {
+        if self.stores.contains_key(name) {
+            return Err(format!("'{}' already exists", name));
+        }
+        let store = DynamicStore::try_new(kind, 0)?;
+        self.stores.insert(name.to_string(), store);
+        Ok(())
+    }
+
+    /// Add an existing structure under `name`.
+    pub fn try_adopt(&mut self, name: &str, store: DynamicStore) -> Result<(), String> {
+        if self.stores.contains_key(name) {
+            return Err(format!("'{}' already exists", name));
+        }
+        self.stores.insert(name.to_string(), store);
+        Ok(())
+    }
+
+    pub fn store(&self, name: &str) -> Option<&DynamicStore> {
+        self.stores.get(name)
+    }
+
+    /// Replay a bundled scenario against the named structure, in place.
+    pub fn try_benchmark(&mut self, name: &str, scenario: &str) -> Result<BenchmarkResult, String> {
+        let scenario = Scenario::by_name(scenario)
+            .ok_or_else(|| format!("unknown scenario '{}'", scenario))?;
+        let store = self
+            .stores
+            .get_mut(name)
+            .ok_or_else(|| format!("no structure named '{}'", name))?;
+        Ok(run_scenario_on(scenario, &mut *store.store_mut()))
+    }
+}
+
+#[wasm_bindgen]
+impl Workspace {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Workspace {
+        Workspace::default()
+    }
+
+    /// Create an empty structure of `kind` (see [`STORE_KINDS`](crate::kv_store::STORE_KINDS))
+    /// under `name`. Fails if the name is taken or the kind is unknown.
+    pub fn create(&mut self, name: &str, kind: &str) -> Result<(), JsValue> {
+        self.try_create(name, kind)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// Take ownership of `store` under `name`, e.g. one returned by an async load.
+    pub fn adopt(&mut self, name: &str, store: DynamicStore) -> Result<(), JsValue> {
+        self.try_adopt(name, store)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// A handle to the named structure, or `undefined`.
+    pub fn get(&self, name: &str) -> Option<DynamicStore> {
+        self.stores.get(name).map(DynamicStore::handle)
+    }
+
+    /// Forget the named structure. Outstanding handles keep it alive.
+    pub fn remove(&mut self, name: &str) -> bool {
+        self.stores.remove(name).is_some()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.stores.contains_key(name)
+    }
+
+    /// Kind of the named structure, e.g. `"rbtree"`.
+    pub fn kind_of(&self, name: &str) -> Option<String> {
+        self.stores.get(name).map(|s| s.kind())
+    }
+
+    /// All names, in sorted order.
+    pub fn names(&self) -> Vec<JsValue> {
+        self.stores.keys().map(|n| JsValue::from_str(n)).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.stores.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stores.is_empty()
+    }
+
+    /// Run a bundled scenario against the named structure (which keeps the
+    /// scenario's data afterwards).
+    pub fn benchmark(&mut self, name: &str, scenario: &str) -> Result<BenchmarkResult, JsValue> {
+        self.try_benchmark(name, scenario)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_create_and_share_by_name() {
+        let mut ws = Workspace::new();
+        ws.try_create("users", "rbtree").unwrap();
+        ws.try_create("tags", "trie").unwrap();
+        assert!(ws.try_create("users", "bst").is_err());
+        assert!(ws.try_create("other", "btree").is_err());
+
+        let mut users = ws.get("users").unwrap();
+        users.insert("alice".to_string(), 1);
+        assert_eq!(ws.store("users").unwrap().len(), 1);
+        assert_eq!(ws.kind_of("tags").as_deref(), Some("trie"));
+        assert_eq!(ws.len(), 2);
+        assert!(ws.get("missing").is_none());
+
+        assert!(ws.remove("users"));
+        assert!(!ws.contains("users"));
+        // The handle outlives the workspace entry
+        assert_eq!(users.get("alice"), Some(1));
+    }
+
+    #[test]
+    fn test_adopt_and_benchmark() {
+        let mut ws = Workspace::new();
+        let store = DynamicStore::try_new("hashmap", 0).unwrap();
+        ws.try_adopt("cache", store).unwrap();
+        let result = ws.try_benchmark("cache", "dictionary-load").unwrap();
+        assert_eq!(result.structure, "hashmap");
+        assert_eq!(ws.store("cache").unwrap().len(), result.final_size as usize);
+        assert!(ws.try_benchmark("nope", "dictionary-load").is_err());
+        assert!(ws.try_benchmark("cache", "nope").is_err());
+    }
+}
@@ -0,0 +1,234 @@
+use crate::hashing::{mix_seed, HashFunction};
+use crate::rng::DefaultRng;
+use rand::RngCore;
+use std::collections::HashSet;
+use wasm_bindgen::prelude::*;
+
+/// Seeds a build tries before giving up. Each succeeds with probability
+/// close to 1 at the sizing below, so running out means something else is
+/// wrong.
+const MAX_ATTEMPTS: u32 = 100;
+/// Slots per key (plus `EXTRA_SLOTS`) that make peeling succeed reliably.
+const SLOTS_PER_KEY: f64 = 1.23;
+const EXTRA_SLOTS: usize = 32;
+
+/// Read-only approximate set membership, built once from a fixed key set:
+/// Graf and Lemire's xor filter with 8-bit fingerprints.
+///
+/// Every key maps to three slots, one in each third of the table, and the
+/// table is solved so the three slots XOR to the key's fingerprint. A
+/// lookup is three reads and a compare. That makes it smaller than a
+/// [`BloomFilter`](crate::BloomFilter) at the same accuracy (about 9.8
+/// bits per key for a 0.39% false positive rate, against 9.6 bits for a
+/// Bloom filter's 1%), but keys can't be added or removed afterwards.
+///
+/// Solving works by peeling: repeatedly take a slot only one remaining key
+/// maps to, and assign that key last. With an unlucky seed some keys
+/// never peel, and the build retries with a new seed.
+///
+/// # Example
+/// ```javascript
+/// const banned = new XorFilter(["mallory", "trudy"]);
+/// banned.might_contain("mallory"); // true
+/// banned.might_contain("alice"); // false, barring a 1-in-256 false positive
+/// banned.bits_per_entry(); // about 9.8 for large sets
+/// ```
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct XorFilter {
+    seed: u64,
+    /// Slots in each third of the table
+    block_length: usize,
+    fingerprints: Vec<u8>,
+    items: usize,
+    build_attempts: u32,
+}
+
+/// `hash`'s top 32 bits scaled into `0..n` without a division.
+fn reduce(hash: u64, n: usize) -> usize {
+    (((hash >> 32) * n as u64) >> 32) as usize
+}
+
+impl XorFilter {
+    pub fn try_build<S: AsRef<str>>(keys: &[S]) -> Result<XorFilter, String> {
+        // Keys that hash alike would share all three slots and never peel
+        let hashes: Vec<u64> = keys
+            .iter()
+            .map(|key| HashFunction::SipHash.hash(key.as_ref()))
+            .collect::<HashSet<u64>>()
+            .into_iter()
+            .collect();
+        let capacity = (SLOTS_PER_KEY * hashes.len() as f64).ceil() as usize + EXTRA_SLOTS;
+        let block_length = capacity / 3;
+        let mut filter = XorFilter {
+            seed: 0,
+            block_length,
+            fingerprints: vec![0; block_length * 3],
+            items: hashes.len(),
+            build_attempts: 0,
+        };
+        let mut rng = DefaultRng::seed_from(hashes.len() as u64);
+        while filter.build_attempts < MAX_ATTEMPTS {
+            filter.build_attempts += 1;
+            filter.seed = rng.next_u64();
+            if let Some(order) = filter.peel(&hashes) {
+                filter.assign(order);
+                return Ok(filter);
+            }
+        }
+        Err(format!(
+            "xor filter build failed after {} seeds for {} keys",
+            MAX_ATTEMPTS,
+            hashes.len()
+        ))
+    }
+
+    /// Mixed hash and three slots of a key hash under the current seed.
+    fn slots(&self, hash: u64) -> (u64, [usize; 3]) {
+        let mixed = mix_seed(hash, self.seed);
+        (mixed, self.slots_of_mixed(mixed))
+    }
+
+    fn fingerprint(mixed: u64) -> u8 {
+        (mixed ^ (mixed >> 32)) as u8
+    }
+
+    /// Peel every key off the table, returning (mixed hash, slot it owns)
+    /// in peeling order, or `None` if some keys are stuck in a cycle.
+    fn peel(&self, hashes: &[u64]) -> Option<Vec<(u64, usize)>> {
+        let len = self.fingerprints.len();
+        // Per slot: how many keys map there, and the XOR of their hashes,
+        // which is the lone key's hash once the count drops to one
+        let mut counts = vec![0u32; len];
+        let mut xors = vec![0u64; len];
+        for &hash in hashes {
+            let (mixed, slots) = self.slots(hash);
+            for slot in slots {
+                counts[slot] += 1;
+                xors[slot] ^= mixed;
+            }
+        }
+        let mut queue: Vec<usize> = (0..len).filter(|&slot| counts[slot] == 1).collect();
+        let mut order = Vec::with_capacity(hashes.len());
+        while let Some(slot) = queue.pop() {
+            if counts[slot] != 1 {
+                continue;
+            }
+            let mixed = xors[slot];
+            order.push((mixed, slot));
+            for other in self.slots_of_mixed(mixed) {
+                counts[other] -= 1;
+                xors[other] ^= mixed;
+                if counts[other] == 1 {
+                    queue.push(other);
+                }
+            }
+        }
+        (order.len() == hashes.len()).then_some(order)
+    }
+
+    /// One slot in each third of the table, from different bits of `h`.
+    fn slots_of_mixed(&self, h: u64) -> [usize; 3] {
+        let block = self.block_length;
+        [
+            reduce(h, block),
+            block + reduce(h.rotate_left(21), block),
+            2 * block + reduce(h.rotate_left(42), block),
+        ]
+    }
+
+    /// Fill slots in reverse peeling order, so each key's own slot is set
+    /// after the other two it reads are final.
+    fn assign(&mut self, order: Vec<(u64, usize)>) {
+        self.fingerprints.fill(0);
+        for (mixed, slot) in order.into_iter().rev() {
+            let value = self
+                .slots_of_mixed(mixed)
+                .iter()
+                .filter(|&&other| other != slot)
+                .fold(Self::fingerprint(mixed), |acc, &other| {
+                    acc ^ self.fingerprints[other]
+                });
+            self.fingerprints[slot] = value;
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl XorFilter {
+    /// Build a filter over `keys`; duplicates are ignored.
+    #[wasm_bindgen(constructor)]
+    pub fn new(keys: Vec<String>) -> Result<XorFilter, JsValue> {
+        Self::try_build(&keys).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// False means `key` was certainly not in the build set.
+    pub fn might_contain(&self, key: &str) -> bool {
+        let (mixed, [a, b, c]) = self.slots(HashFunction::SipHash.hash(key));
+        Self::fingerprint(mixed)
+            == self.fingerprints[a] ^ self.fingerprints[b] ^ self.fingerprints[c]
+    }
+
+    /// Distinct keys the filter was built from.
+    pub fn len(&self) -> usize {
+        self.items
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items == 0
+    }
+
+    /// Fingerprint slots, about 1.23 per key.
+    pub fn slot_count(&self) -> usize {
+        self.fingerprints.len()
+    }
+
+    /// Storage bits per distinct key (8 per slot); infinite for an empty
+    /// filter.
+    pub fn bits_per_entry(&self) -> f64 {
+        (self.fingerprints.len() * 8) as f64 / self.items as f64
+    }
+
+    pub fn memory_bytes(&self) -> usize {
+        self.fingerprints.len()
+    }
+
+    /// Seeds tried before peeling succeeded, usually 1.
+    pub fn build_attempts(&self) -> u32 {
+        self.build_attempts
+    }
+
+    /// Chance an absent key matches: 2^-8.
+    pub fn false_positive_rate(&self) -> f64 {
+        1.0 / 256.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_and_query() {
+        let keys: Vec<String> = (0..10_000).map(|i| format!("item{}", i)).collect();
+        let filter = XorFilter::try_build(&keys).unwrap();
+        assert!(keys.iter().all(|key| filter.might_contain(key)));
+        let false_positives = (0..100_000)
+            .filter(|i| filter.might_contain(&format!("absent{}", i)))
+            .count();
+        assert!(false_positives < 600, "{}", false_positives);
+        assert!(
+            filter.bits_per_entry() < 10.0,
+            "{}",
+            filter.bits_per_entry()
+        );
+        assert!(filter.build_attempts() <= 3);
+
+        // Duplicates collapse; tiny and empty sets still build
+        let small = XorFilter::try_build(&["a", "b", "a"]).unwrap();
+        assert_eq!(small.len(), 2);
+        assert!(small.might_contain("a") && small.might_contain("b"));
+        let empty = XorFilter::try_build::<&str>(&[]).unwrap();
+        assert!(empty.is_empty());
+    }
+}